@@ -0,0 +1,121 @@
+// Criterion benches for the kernel loop, event routing and the conditional order store.
+//
+//   cargo bench -p sim-engine
+//
+// Compare against a saved baseline to catch throughput regressions:
+//   cargo bench -p sim-engine -- --save-baseline main
+//   cargo bench -p sim-engine -- --baseline main
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+use sim_engine::bench::{workload, BenchOptions};
+use sim_engine::messages::{ExecutionType, OrderPayload, OrderType, Price, Side};
+use sim_engine::pending_orders::{PendingOrder, PendingOrderStore};
+
+const ORDERS: u64 = 10_000;
+
+fn kernel_loop(c: &mut Criterion) {
+    let mut group = c.benchmark_group("kernel");
+    for (agents, messages_per_tick) in [(10, 1_000), (100, 1_000), (1_000, 10_000)] {
+        let opts = BenchOptions {
+            agents,
+            messages_per_tick,
+            ticks: 10,
+            events_per_tick: 0,
+            listeners: 0,
+            broadcast: false,
+        };
+        group.throughput(Throughput::Elements(messages_per_tick as u64 * 10));
+        group.bench_function(format!("{}x{}", agents, messages_per_tick), |b| {
+            b.iter_batched(|| workload(&opts), |sim| sim.run(), BatchSize::SmallInput)
+        });
+    }
+    group.finish();
+}
+
+/// OracleTick events past execution-only listeners, routed by kind vs delivered to all.
+fn event_bus(c: &mut Criterion) {
+    let mut group = c.benchmark_group("event_bus");
+    group.throughput(Throughput::Elements(10_000 * 10));
+    for broadcast in [false, true] {
+        let opts = BenchOptions {
+            agents: 10,
+            messages_per_tick: 0,
+            ticks: 10,
+            events_per_tick: 10_000,
+            listeners: 6,
+            broadcast,
+        };
+        let name = if broadcast { "broadcast" } else { "routed" };
+        group.bench_function(name, |b| {
+            b.iter_batched(|| workload(&opts), |sim| sim.run(), BatchSize::SmallInput)
+        });
+    }
+    group.finish();
+}
+
+/// Stop losses below and take profits above 3000, spread over 1000 price levels.
+fn order(id: u64) -> PendingOrder {
+    let (execution_type, trigger) = if id % 2 == 0 {
+        (ExecutionType::StopLoss, 2_000 + id % 1_000)
+    } else {
+        (ExecutionType::TakeProfit, 3_001 + id % 1_000)
+    };
+    PendingOrder {
+        id,
+        owner: 1 + (id % 100) as u32,
+        payload: OrderPayload {
+            symbol: "ETH-USD".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Decrease,
+            execution_type,
+            qty: None,
+            leverage: None,
+            size_delta_usd: Some(1_000_000_000),
+            trigger_price: Some(trigger),
+            acceptable_price: None,
+            trailing_distance: None,
+            valid_for_sec: None,
+            oco_group: None,
+        },
+        valid_until_ns: u64::MAX,
+    }
+}
+
+fn filled_store() -> PendingOrderStore {
+    let mut store = PendingOrderStore::new();
+    for id in 0..ORDERS {
+        store.insert(order(id));
+    }
+    store
+}
+
+fn pending_orders(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pending_orders");
+    group.throughput(Throughput::Elements(ORDERS));
+    group.bench_function("insert", |b| b.iter(filled_store));
+    group.bench_function("remove", |b| {
+        b.iter_batched(
+            filled_store,
+            |mut store| {
+                for id in 0..ORDERS {
+                    store.remove(id);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    let store = filled_store();
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("triggered/none", |b| {
+        b.iter(|| store.triggered("ETH-USD", &Price { min: 3_000, max: 3_000 }))
+    });
+    group.bench_function("triggered/some", |b| {
+        b.iter(|| store.triggered("ETH-USD", &Price { min: 2_990, max: 2_990 }))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, kernel_loop, event_bus, pending_orders);
+criterion_main!(benches);
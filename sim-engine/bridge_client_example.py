@@ -0,0 +1,60 @@
+"""Strategy bridge example for Perp DEX Simulator.
+
+Listens for the "bridge" agent and answers every event with order intents.
+Start this first, then run a scenario with:
+
+    { "type": "bridge", "id": 47, "name": "PyBridge", "address": "127.0.0.1:9900" }
+
+Momentum toy: buy after three rising ticks, close after three falling ones.
+"""
+
+import json
+import socket
+
+HOST, PORT = "127.0.0.1", 9900
+QTY, LEVERAGE = 0.5, 3
+
+
+def decide(msg, history):
+    """Return the intents for one event."""
+    if msg["event"] == "fill" and msg["fill"]["kind"] == "liquidation":
+        print("liquidated:", msg["fill"])
+    if msg["event"] != "price":
+        return []
+
+    symbol, price = msg["symbol"], msg["price"]
+    ticks = history.setdefault(symbol, [])
+    ticks.append(price)
+    del ticks[:-4]
+    if len(ticks) < 4:
+        return []
+
+    rising = all(a < b for a, b in zip(ticks, ticks[1:]))
+    falling = all(a > b for a, b in zip(ticks, ticks[1:]))
+    is_long = any(p["symbol"] == symbol and p["side"] == "long" for p in msg["positions"])
+    if rising and not is_long:
+        return [{"action": "open", "symbol": symbol, "side": "long", "qty": QTY, "leverage": LEVERAGE}]
+    if falling and is_long:
+        return [{"action": "close", "symbol": symbol, "side": "long"}]
+    return []
+
+
+def main():
+    with socket.create_server((HOST, PORT)) as server:
+        print(f"waiting for the simulator on {HOST}:{PORT}")
+        conn, addr = server.accept()
+        print("connected:", addr)
+        history = {}
+        with conn, conn.makefile("rw") as stream:
+            for line in stream:
+                msg = json.loads(line)
+                if msg["event"] == "param":
+                    print("param", msg["key"], "=", msg["value"])
+                    continue  # no reply expected
+                reply = {"seq": msg["seq"], "intents": decide(msg, history)}
+                stream.write(json.dumps(reply) + "\n")
+                stream.flush()
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,190 @@
+// Time-of-day and weekend activity cycle for multi-day runs.
+//
+// Sim time is unix time, read in UTC. For any instant the schedule gives a
+// volatility and a volume multiplier: the product of the multipliers of every
+// session the time of day falls into (e.g. US hours, 13:30-20:00 UTC) and, on
+// Saturdays and Sundays, of `weekend`. Outside all sessions on a weekday both
+// are 1, so an empty schedule changes nothing. The synthetic price provider
+// scales its volatility with it, noise traders their order rate.
+
+use serde::{Deserialize, Serialize};
+
+const NS_PER_HOUR: u64 = 3_600_000_000_000;
+const NS_PER_DAY: u64 = 24 * NS_PER_HOUR;
+
+/// Volatility and volume relative to the configured baseline.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActivityLevel {
+    #[serde(default = "default_multiplier")]
+    pub volatility: f64,
+    #[serde(default = "default_multiplier")]
+    pub volume: f64,
+}
+
+fn default_multiplier() -> f64 {
+    1.0
+}
+
+impl Default for ActivityLevel {
+    fn default() -> Self {
+        Self {
+            volatility: 1.0,
+            volume: 1.0,
+        }
+    }
+}
+
+/// Daily window in UTC hours; `end_hour` below `start_hour` wraps past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActivitySession {
+    pub start_hour: f64,
+    pub end_hour: f64,
+    #[serde(default = "default_multiplier")]
+    pub volatility: f64,
+    #[serde(default = "default_multiplier")]
+    pub volume: f64,
+}
+
+impl ActivitySession {
+    fn contains(&self, hour: f64) -> bool {
+        if self.start_hour <= self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActivitySchedule {
+    #[serde(default)]
+    pub sessions: Vec<ActivitySession>,
+    /// Applied all Saturday and Sunday (UTC), on top of the sessions
+    #[serde(default)]
+    pub weekend: Option<ActivityLevel>,
+}
+
+impl ActivitySchedule {
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, session) in self.sessions.iter().enumerate() {
+            let hours = [session.start_hour, session.end_hour];
+            if hours.iter().any(|h| !(0.0..=24.0).contains(h)) {
+                return Err(format!("sessions[{}]: hours must be in [0, 24]", i));
+            }
+            if !positive(session.volatility) || !positive(session.volume) {
+                return Err(format!("sessions[{}]: multipliers must be positive", i));
+            }
+        }
+        if let Some(weekend) = &self.weekend {
+            if !positive(weekend.volatility) || !positive(weekend.volume) {
+                return Err("weekend: multipliers must be positive".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty() && self.weekend.is_none()
+    }
+
+    /// Activity at `unix_ns`.
+    pub fn at(&self, unix_ns: u64) -> ActivityLevel {
+        let hour = (unix_ns % NS_PER_DAY) as f64 / NS_PER_HOUR as f64;
+        let mut level = ActivityLevel::default();
+        for session in self.sessions.iter().filter(|s| s.contains(hour)) {
+            level.volatility *= session.volatility;
+            level.volume *= session.volume;
+        }
+        if let Some(weekend) = self.weekend.filter(|_| is_weekend(unix_ns)) {
+            level.volatility *= weekend.volatility;
+            level.volume *= weekend.volume;
+        }
+        level
+    }
+}
+
+fn positive(value: f64) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+/// Saturday or Sunday in UTC (1970-01-01 was a Thursday)
+fn is_weekend(unix_ns: u64) -> bool {
+    let weekday = (unix_ns / NS_PER_DAY + 3) % 7; // 0 = Monday
+    weekday >= 5
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Monday 2024-01-01 00:00 UTC
+    const MONDAY: u64 = 1_704_067_200 * 1_000_000_000;
+
+    fn at_hour(day: u64, hour: f64) -> u64 {
+        MONDAY + day * NS_PER_DAY + (hour * NS_PER_HOUR as f64) as u64
+    }
+
+    #[test]
+    fn test_sessions_and_weekend_multiply() {
+        let schedule: ActivitySchedule = serde_json::from_str(
+            r#"{
+                "sessions": [
+                    { "start_hour": 13.5, "end_hour": 20, "volatility": 1.5, "volume": 2 },
+                    { "start_hour": 22, "end_hour": 2, "volatility": 0.5 }
+                ],
+                "weekend": { "volatility": 0.5, "volume": 0.25 }
+            }"#,
+        )
+        .unwrap();
+        assert!(schedule.validate().is_ok());
+
+        assert_eq!(schedule.at(at_hour(0, 10.0)), ActivityLevel::default());
+        assert_eq!(
+            schedule.at(at_hour(0, 13.5)),
+            ActivityLevel {
+                volatility: 1.5,
+                volume: 2.0
+            }
+        );
+        assert_eq!(schedule.at(at_hour(0, 20.0)), ActivityLevel::default());
+        // Wraps past midnight
+        assert_eq!(schedule.at(at_hour(1, 1.0)).volatility, 0.5);
+        assert_eq!(schedule.at(at_hour(0, 23.0)).volatility, 0.5);
+        // Saturday US hours: session and weekend
+        assert_eq!(
+            schedule.at(at_hour(5, 15.0)),
+            ActivityLevel {
+                volatility: 0.75,
+                volume: 0.5
+            }
+        );
+        assert_eq!(schedule.at(at_hour(6, 10.0)).volume, 0.25);
+        assert_eq!(schedule.at(at_hour(7, 10.0)).volume, 1.0);
+    }
+
+    #[test]
+    fn test_rejects_bad_sessions() {
+        let session = ActivitySession {
+            start_hour: 9.0,
+            end_hour: 17.0,
+            volatility: 1.0,
+            volume: 1.0,
+        };
+        let schedule = |session| ActivitySchedule {
+            sessions: vec![session],
+            weekend: None,
+        };
+        assert!(schedule(session).validate().is_ok());
+        assert!(schedule(ActivitySession {
+            end_hour: 25.0,
+            ..session
+        })
+        .validate()
+        .is_err());
+        assert!(schedule(ActivitySession { volume: 0.0, ..session }).validate().is_err());
+        assert!(ActivitySchedule::default().is_empty());
+    }
+}
@@ -0,0 +1,454 @@
+//! ArbitrageAgent - trades the basis between the exchange and a reference venue.
+//!
+//! The exchange's mark price is estimated from the oracle index price skewed by
+//! the OI imbalance (the same imbalance that drives price impact and funding):
+//!   mark = index * (1 + impact_factor * (oi_long - oi_short) / liquidity)
+//! The reference price comes from a second provider (e.g. Binance). When the
+//! basis (mark vs reference) exceeds `entry_basis_bps` the agent takes the side
+//! that profits from convergence and closes once it is back within
+//! `exit_basis_bps` (or after `max_hold_sec`).
+
+use crate::agent_println;
+use crate::agents::Agent;
+use crate::api::PriceProvider;
+use crate::messages::{
+    AgentId, CloseOrderPayload, MarketOrderPayload, Message, MessagePayload, MessageType, Side,
+    SimulatorApi,
+};
+use crate::qty::Qty;
+
+#[derive(Debug, Clone)]
+pub struct ArbitrageConfig {
+    pub name: String,
+    pub exchange_id: AgentId,
+    pub symbol: String,
+    pub qty: f64,
+    pub leverage: u32,
+    /// Open when |basis| exceeds this (basis points)
+    pub entry_basis_bps: f64,
+    /// Close when |basis| falls back below this (basis points)
+    pub exit_basis_bps: f64,
+    /// Weight of the OI skew in the mark price estimate
+    pub impact_factor: f64,
+    pub max_hold_sec: u64,
+    pub wake_interval_ms: u64,
+}
+
+pub struct ArbitrageAgent {
+    id: AgentId,
+    name: String,
+    exchange_id: AgentId,
+    symbol: String,
+    address: Option<String>,
+    reference: Box<dyn PriceProvider>,
+
+    qty: f64,
+    leverage: u32,
+    entry_basis_bps: f64,
+    exit_basis_bps: f64,
+    impact_factor: f64,
+    max_hold_ns: u64,
+    wake_interval_ns: u64,
+
+    index_price: Option<u64>,
+    reference_price: Option<u64>,
+    oi_long_usd: i128,
+    oi_short_usd: i128,
+    liquidity_usd: i128,
+
+    /// Open position: (side, opened_at_ns)
+    position: Option<(Side, u64)>,
+
+    trades_opened: u32,
+    trades_closed: u32,
+    liquidations: u32,
+    reference_errors: u32,
+    max_abs_basis_bps: f64,
+}
+
+impl ArbitrageAgent {
+    pub fn new(id: AgentId, config: ArbitrageConfig, reference: Box<dyn PriceProvider>) -> Self {
+        Self {
+            id,
+            name: config.name,
+            exchange_id: config.exchange_id,
+            symbol: config.symbol,
+            address: None,
+            reference,
+            qty: config.qty,
+            leverage: config.leverage.max(1),
+            entry_basis_bps: config.entry_basis_bps,
+            exit_basis_bps: config.exit_basis_bps,
+            impact_factor: config.impact_factor,
+            max_hold_ns: config.max_hold_sec * 1_000_000_000,
+            wake_interval_ns: config.wake_interval_ms * 1_000_000,
+            index_price: None,
+            reference_price: None,
+            oi_long_usd: 0,
+            oi_short_usd: 0,
+            liquidity_usd: 0,
+            position: None,
+            trades_opened: 0,
+            trades_closed: 0,
+            liquidations: 0,
+            reference_errors: 0,
+            max_abs_basis_bps: 0.0,
+        }
+    }
+
+    pub fn set_address(&mut self, address: String) {
+        self.address = Some(address);
+    }
+
+    /// Estimated mark price in micro-USD
+    fn mark_price(&self) -> Option<f64> {
+        let index = self.index_price? as f64;
+        let skew = if self.liquidity_usd > 0 {
+            (self.oi_long_usd - self.oi_short_usd) as f64 / self.liquidity_usd as f64
+        } else {
+            0.0
+        };
+        Some(index * (1.0 + self.impact_factor * skew))
+    }
+
+    /// (mark - reference) / reference in basis points
+    fn basis_bps(&self) -> Option<f64> {
+        let reference = self.reference_price.filter(|p| *p > 0)? as f64;
+        Some((self.mark_price()? - reference) / reference * 10_000.0)
+    }
+
+    fn refresh_reference(&mut self) {
+        match self.reference.fetch_signed_price(&self.symbol) {
+            Ok(data) => self.reference_price = Some(data.price_usd_micro),
+            Err(e) => {
+                self.reference_errors += 1;
+                if self.reference_errors <= 3 || self.reference_errors.is_multiple_of(20) {
+                    eprintln!(
+                        "[Arb {}] {} reference error for {}: {}",
+                        self.name,
+                        self.reference.provider_name(),
+                        self.symbol,
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    fn open(&mut self, sim: &mut dyn SimulatorApi, side: Side, basis: f64, now_ns: u64) {
+        agent_println!(
+            "[Arb {}] OPEN {} basis={:+.1}bps qty={:.3} {}x",
+            self.name,
+            if side == Side::Buy { "LONG" } else { "SHORT" },
+            basis,
+            self.qty,
+            self.leverage
+        );
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::MarketOrder,
+            MessagePayload::MarketOrder(MarketOrderPayload {
+                symbol: self.symbol.clone(),
+                side,
+                qty: Qty::from_tokens(self.qty),
+                leverage: self.leverage,
+                decision_price: sim.market_data().mid(&self.symbol),
+            }),
+        );
+        self.position = Some((side, now_ns));
+        self.trades_opened += 1;
+    }
+
+    fn close(&mut self, sim: &mut dyn SimulatorApi, reason: &str, basis: f64) {
+        let Some((side, _)) = self.position.take() else {
+            return;
+        };
+        agent_println!(
+            "[Arb {}] CLOSE {} ({}) basis={:+.1}bps",
+            self.name,
+            if side == Side::Buy { "LONG" } else { "SHORT" },
+            reason,
+            basis
+        );
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::CloseOrder,
+            MessagePayload::CloseOrder(CloseOrderPayload {
+                symbol: self.symbol.clone(),
+                side,
+                decision_price: sim.market_data().mid(&self.symbol),
+            }),
+        );
+        self.trades_closed += 1;
+    }
+}
+
+impl Agent for ArbitrageAgent {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        match (key, value.as_f64()) {
+            ("qty", Some(v)) if v > 0.0 => self.qty = v,
+            ("leverage", Some(v)) if v >= 1.0 => self.leverage = v as u32,
+            ("entry_basis_bps", Some(v)) if v > 0.0 => self.entry_basis_bps = v,
+            ("exit_basis_bps", Some(v)) if v >= 0.0 => self.exit_basis_bps = v,
+            ("impact_factor", Some(v)) if v >= 0.0 => self.impact_factor = v,
+            ("wake_interval_ms", Some(v)) if v >= 1.0 => self.wake_interval_ns = v as u64 * 1_000_000,
+            _ => return false,
+        }
+        agent_println!("[Arb {}] param {} = {}", self.name, key, value);
+        true
+    }
+
+    fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[Arb {}] START {} vs {} entry={}bps exit={}bps{}",
+            self.name,
+            self.symbol,
+            self.reference.provider_name(),
+            self.entry_basis_bps,
+            self.exit_basis_bps,
+            self.address
+                .as_deref()
+                .map(|addr| format!(" addr={}", addr))
+                .unwrap_or_default()
+        );
+        sim.wakeup(self.id, sim.now_ns() + self.wake_interval_ns);
+    }
+
+    fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
+        self.refresh_reference();
+        if let Some(basis) = self.basis_bps() {
+            self.max_abs_basis_bps = self.max_abs_basis_bps.max(basis.abs());
+            match self.position {
+                None if basis.abs() >= self.entry_basis_bps => {
+                    // Perp rich vs reference -> short it, cheap -> long it
+                    let side = if basis > 0.0 { Side::Sell } else { Side::Buy };
+                    self.open(sim, side, basis, now_ns);
+                }
+                Some((side, opened_at)) => {
+                    // Converged, or the basis flipped past the exit band
+                    let converged = match side {
+                        Side::Sell => basis <= self.exit_basis_bps,
+                        Side::Buy => basis >= -self.exit_basis_bps,
+                    };
+                    if converged {
+                        self.close(sim, "converged", basis);
+                    } else if now_ns.saturating_sub(opened_at) >= self.max_hold_ns {
+                        self.close(sim, "max hold", basis);
+                    }
+                }
+                None => {}
+            }
+        }
+        sim.wakeup(self.id, now_ns + self.wake_interval_ns);
+    }
+
+    fn on_message(&mut self, _sim: &mut dyn SimulatorApi, msg: &Message) {
+        match &msg.payload {
+            MessagePayload::OracleTick(_) | MessagePayload::OracleBatchTick(_) => {
+                if let Some(p) = msg.payload.oracle_tick(&self.symbol) {
+                    self.index_price = Some((p.price.min + p.price.max) / 2);
+                }
+            }
+            MessagePayload::MarketState(p) if p.symbol == self.symbol => {
+                self.oi_long_usd = p.oi_long_usd;
+                self.oi_short_usd = p.oi_short_usd;
+                self.liquidity_usd = p.liquidity_usd;
+            }
+            MessagePayload::PositionLiquidated(p) if p.symbol == self.symbol => {
+                agent_println!(
+                    "[Arb {}] LIQUIDATED {:?} pnl=${:.2}",
+                    self.name,
+                    p.side,
+                    p.pnl as f64 / 1_000_000.0
+                );
+                self.position = None;
+                self.liquidations += 1;
+            }
+            _ if msg.msg_type == MessageType::OrderRejected => {
+                // Opening order failed — allow a new entry
+                if self.position.take().is_some() {
+                    self.trades_opened -= 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[Arb {}] STOP: open={} close={} liq={} max|basis|={:.1}bps ref_errors={}",
+            self.name,
+            self.trades_opened,
+            self.trades_closed,
+            self.liquidations,
+            self.max_abs_basis_bps,
+            self.reference_errors
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::test_support::{delivered, kernel_with_recorder, oracle_tick};
+    use crate::api::SignedPriceData;
+    use crate::messages::MarketStatePayload;
+    use std::error::Error;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    const EXCHANGE: AgentId = 1;
+    const ARB: AgentId = 30;
+    const ETH: &str = "ETH-USD";
+
+    /// Reference venue quoting whatever the test last stored
+    struct Reference(Arc<AtomicU64>);
+
+    impl PriceProvider for Reference {
+        fn fetch_signed_price(&self, symbol: &str) -> Result<SignedPriceData, Box<dyn Error>> {
+            Ok(SignedPriceData {
+                symbol: symbol.to_string(),
+                price_usd_micro: self.0.load(Ordering::Relaxed),
+                confidence: None,
+                ema_price: None,
+                publish_time: 0,
+                signature: Vec::new(),
+                provider_name: "Reference".to_string(),
+            })
+        }
+
+        fn provider_name(&self) -> &str {
+            "Reference"
+        }
+
+        fn supported_symbols(&self) -> Vec<String> {
+            vec![ETH.to_string()]
+        }
+    }
+
+    fn arb(impact_factor: f64) -> (ArbitrageAgent, Arc<AtomicU64>) {
+        let reference = Arc::new(AtomicU64::new(2_000_000_000));
+        let config = ArbitrageConfig {
+            name: "arb".to_string(),
+            exchange_id: EXCHANGE,
+            symbol: ETH.to_string(),
+            qty: 1.0,
+            leverage: 2,
+            entry_basis_bps: 50.0,
+            exit_basis_bps: 10.0,
+            impact_factor,
+            max_hold_sec: 60,
+            wake_interval_ms: 1_000,
+        };
+        let agent = ArbitrageAgent::new(ARB, config, Box::new(Reference(reference.clone())));
+        (agent, reference)
+    }
+
+    /// Orders the exchange received: (type, side)
+    fn orders(msgs: Vec<Message>) -> Vec<(MessageType, Side)> {
+        msgs.into_iter()
+            .filter_map(|msg| match msg.payload {
+                MessagePayload::MarketOrder(o) => Some((msg.msg_type, o.side)),
+                MessagePayload::CloseOrder(c) => Some((msg.msg_type, c.side)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mark_price_is_skewed_by_oi_imbalance() {
+        let (mut arb, _) = arb(0.5);
+        let (mut kernel, _) = kernel_with_recorder(EXCHANGE);
+        arb.on_message(&mut kernel, &oracle_tick(ARB, ETH, 2_000_000_000));
+        let state = MarketStatePayload {
+            symbol: ETH.to_string(),
+            oi_long_usd: 150_000_000_000,
+            oi_short_usd: 50_000_000_000,
+            liquidity_usd: 1_000_000_000_000,
+            pool_pnl_usd: 0,
+            funding_rate_bps_hour_fp: 0,
+            borrowing_rate_bps_hour_fp: 0,
+        };
+        let msg = Message::new(
+            ARB,
+            EXCHANGE,
+            MessageType::MarketState,
+            0,
+            MessagePayload::MarketState(state),
+        );
+        arb.on_message(&mut kernel, &msg);
+        // 10% net long skew at half weight: mark 5% above the index
+        assert!((arb.mark_price().unwrap() - 2_100_000_000.0).abs() < 1e-3);
+        arb.reference_price = Some(2_000_000_000);
+        assert!((arb.basis_bps().unwrap() - 500.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_opens_past_entry_basis_and_closes_on_convergence() {
+        let (mut arb, reference) = arb(0.0);
+        let (mut kernel, received) = kernel_with_recorder(EXCHANGE);
+        arb.on_message(&mut kernel, &oracle_tick(ARB, ETH, 2_000_000_000));
+
+        // 40 bps rich: inside the entry band
+        reference.store(1_992_000_000, Ordering::Relaxed);
+        arb.on_wakeup(&mut kernel, 1_000_000_000);
+        assert!(orders(delivered(&mut kernel, &received)).is_empty());
+
+        // ~75 bps rich: short the perp
+        reference.store(1_985_000_000, Ordering::Relaxed);
+        arb.on_wakeup(&mut kernel, 2_000_000_000);
+        assert_eq!(
+            orders(delivered(&mut kernel, &received)),
+            vec![(MessageType::MarketOrder, Side::Sell)]
+        );
+
+        // ~25 bps: still outside the exit band
+        reference.store(1_995_000_000, Ordering::Relaxed);
+        arb.on_wakeup(&mut kernel, 3_000_000_000);
+        assert!(orders(delivered(&mut kernel, &received)).is_empty());
+
+        reference.store(1_999_000_000, Ordering::Relaxed);
+        arb.on_wakeup(&mut kernel, 4_000_000_000);
+        assert_eq!(
+            orders(delivered(&mut kernel, &received)),
+            vec![(MessageType::CloseOrder, Side::Sell)]
+        );
+        assert_eq!((arb.trades_opened, arb.trades_closed), (1, 1));
+    }
+
+    #[test]
+    fn test_closes_after_max_hold_and_reopens_after_rejection() {
+        let (mut arb, reference) = arb(0.0);
+        let (mut kernel, received) = kernel_with_recorder(EXCHANGE);
+        arb.on_message(&mut kernel, &oracle_tick(ARB, ETH, 2_000_000_000));
+
+        // Perp cheap: long it; the open is rejected, so the next wakeup tries again
+        reference.store(2_020_000_000, Ordering::Relaxed);
+        arb.on_wakeup(&mut kernel, 1_000_000_000);
+        let rejected = Message::new(ARB, EXCHANGE, MessageType::OrderRejected, 0, MessagePayload::Empty);
+        arb.on_message(&mut kernel, &rejected);
+        assert_eq!(arb.trades_opened, 0);
+        arb.on_wakeup(&mut kernel, 2_000_000_000);
+        let sent = orders(delivered(&mut kernel, &received));
+        assert_eq!(sent, vec![(MessageType::MarketOrder, Side::Buy); 2]);
+
+        // The basis never converges: closed once held for `max_hold_sec`
+        arb.on_wakeup(&mut kernel, 61_000_000_000);
+        assert!(orders(delivered(&mut kernel, &received)).is_empty());
+        arb.on_wakeup(&mut kernel, 62_000_000_000);
+        assert_eq!(
+            orders(delivered(&mut kernel, &received)),
+            vec![(MessageType::CloseOrder, Side::Buy)]
+        );
+    }
+}
@@ -0,0 +1,360 @@
+//! BalanceMonitorAgent - native (VARA) gas balances of the bot accounts.
+//!
+//! Every `interval_ms` the agent queries the free balance of each watched
+//! account through the VaraClient (on its blocking pool, results are picked
+//! up on the next wakeup). An account below `min_balance` is warned about once
+//! per crossing; with a top-up configured it is refilled by a transfer from a
+//! funder account or a request to a testnet faucet, at most once per
+//! `cooldown_ms` per account. Long runs then don't die mid-way when bots run
+//! out of gas.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::agent_println;
+use crate::agents::Agent;
+use crate::messages::{AgentId, SimulatorApi};
+use crate::vara::VaraClient;
+
+/// Smallest units per VARA
+const VARA_UNITS: f64 = 1e12;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopUpSource {
+    /// Transfer from the account of this agent
+    Funder(AgentId),
+    /// POST `{"address": ...}` to this faucet URL
+    Faucet(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct TopUpConfig {
+    pub source: TopUpSource,
+    /// Amount per top-up (smallest units; faucets may ignore it)
+    pub amount: u128,
+    /// Minimum time between two top-ups of one account
+    pub cooldown_ms: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BalanceMonitorConfig {
+    pub name: String,
+    /// Agent ids of the watched accounts
+    pub accounts: Vec<AgentId>,
+    pub interval_ms: u64,
+    /// Free balance below which an account runs low (smallest units)
+    pub min_balance: u128,
+    pub top_up: Option<TopUpConfig>,
+}
+
+pub struct BalanceMonitorAgent {
+    id: AgentId,
+    name: String,
+    client: Arc<VaraClient>,
+    accounts: Vec<AgentId>,
+    interval_ns: u64,
+    min_balance: u128,
+    top_up: Option<TopUpConfig>,
+
+    balance_tx: Sender<(u32, Result<u128, String>)>,
+    balance_rx: Receiver<(u32, Result<u128, String>)>,
+    top_up_tx: Sender<(u32, Result<(), String>)>,
+    top_up_rx: Receiver<(u32, Result<(), String>)>,
+
+    /// Last known free balance per account
+    balances: BTreeMap<AgentId, u128>,
+    /// Accounts currently below `min_balance` (warned once per crossing)
+    low: HashSet<AgentId>,
+    /// Top-ups waiting for their result
+    pending: HashSet<AgentId>,
+    last_top_up_ns: HashMap<AgentId, u64>,
+
+    checks: u32,
+    query_errors: u32,
+    warnings: u32,
+    top_ups: u32,
+    top_up_failures: u32,
+}
+
+impl BalanceMonitorAgent {
+    pub fn new(id: AgentId, config: BalanceMonitorConfig, client: Arc<VaraClient>) -> Self {
+        let (balance_tx, balance_rx) = crossbeam_channel::unbounded();
+        let (top_up_tx, top_up_rx) = crossbeam_channel::unbounded();
+        Self {
+            id,
+            name: config.name,
+            client,
+            accounts: config.accounts,
+            interval_ns: config.interval_ms * 1_000_000,
+            min_balance: config.min_balance,
+            top_up: config.top_up,
+            balance_tx,
+            balance_rx,
+            top_up_tx,
+            top_up_rx,
+            balances: BTreeMap::new(),
+            low: HashSet::new(),
+            pending: HashSet::new(),
+            last_top_up_ns: HashMap::new(),
+            checks: 0,
+            query_errors: 0,
+            warnings: 0,
+            top_ups: 0,
+            top_up_failures: 0,
+        }
+    }
+
+    fn drain_results(&mut self, now_ns: u64) {
+        let top_ups: Vec<_> = self.top_up_rx.try_iter().collect();
+        for (account, result) in top_ups {
+            self.pending.remove(&account);
+            match result {
+                Ok(()) => {
+                    self.top_ups += 1;
+                    agent_println!("[Balance {}] topped up agent {}", self.name, account);
+                }
+                Err(e) => {
+                    self.top_up_failures += 1;
+                    eprintln!("[Balance {}] top-up of agent {} failed: {}", self.name, account, e);
+                }
+            }
+        }
+
+        let balances: Vec<_> = self.balance_rx.try_iter().collect();
+        for (account, result) in balances {
+            match result {
+                Ok(balance) => self.on_balance(now_ns, account, balance),
+                Err(e) => {
+                    self.query_errors += 1;
+                    eprintln!(
+                        "[Balance {}] balance of agent {} unavailable: {}",
+                        self.name, account, e
+                    );
+                }
+            }
+        }
+    }
+
+    fn on_balance(&mut self, now_ns: u64, account: AgentId, balance: u128) {
+        self.balances.insert(account, balance);
+        if balance >= self.min_balance {
+            if self.low.remove(&account) {
+                agent_println!(
+                    "[Balance {}] agent {} back at {:.4} VARA",
+                    self.name,
+                    account,
+                    balance as f64 / VARA_UNITS
+                );
+            }
+            return;
+        }
+        if self.low.insert(account) {
+            self.warnings += 1;
+            eprintln!(
+                "[Balance {}] WARNING: agent {} has {:.4} VARA, below {:.4} VARA for gas",
+                self.name,
+                account,
+                balance as f64 / VARA_UNITS,
+                self.min_balance as f64 / VARA_UNITS
+            );
+        }
+        self.request_top_up(now_ns, account);
+    }
+
+    fn request_top_up(&mut self, now_ns: u64, account: AgentId) {
+        let Some(top_up) = &self.top_up else {
+            return;
+        };
+        if self.pending.contains(&account) || top_up.source == TopUpSource::Funder(account) {
+            return;
+        }
+        let cooldown_ns = top_up.cooldown_ms * 1_000_000;
+        if let Some(last) = self.last_top_up_ns.get(&account) {
+            if now_ns < last + cooldown_ns {
+                return;
+            }
+        }
+        self.pending.insert(account);
+        self.last_top_up_ns.insert(account, now_ns);
+        match &top_up.source {
+            TopUpSource::Funder(funder) => {
+                self.client
+                    .transfer_native_async(*funder, account, top_up.amount, self.top_up_tx.clone());
+            }
+            TopUpSource::Faucet(url) => {
+                let address = match self.client.get_address(account) {
+                    Ok(address) => address,
+                    Err(e) => {
+                        let _ = self.top_up_tx.send((account, Err(e.to_string())));
+                        return;
+                    }
+                };
+                let url = url.clone();
+                let sender = self.top_up_tx.clone();
+                std::thread::spawn(move || {
+                    let _ = sender.send((account, request_faucet(&url, &address)));
+                });
+            }
+        }
+    }
+}
+
+/// Ask a faucet to fund `address`; any 2xx answer counts as success.
+fn request_faucet(url: &str, address: &str) -> Result<(), String> {
+    let response = reqwest::blocking::Client::new()
+        .post(url)
+        .json(&serde_json::json!({ "address": address }))
+        .send()
+        .map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("faucet answered {}", response.status()))
+    }
+}
+
+impl Agent for BalanceMonitorAgent {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+        let top_up = match &self.top_up {
+            Some(TopUpConfig {
+                source: TopUpSource::Funder(funder),
+                ..
+            }) => format!(" top-up from agent {}", funder),
+            Some(TopUpConfig {
+                source: TopUpSource::Faucet(url),
+                ..
+            }) => format!(" top-up from faucet {}", url),
+            None => String::new(),
+        };
+        agent_println!(
+            "[Balance {}] START watching {} accounts every {}s, min {:.4} VARA{}",
+            self.name,
+            self.accounts.len(),
+            self.interval_ns / 1_000_000_000,
+            self.min_balance as f64 / VARA_UNITS,
+            top_up
+        );
+        sim.wakeup(self.id, sim.now_ns());
+    }
+
+    fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
+        self.drain_results(now_ns);
+        self.checks += 1;
+        self.client
+            .fetch_native_balances_async(self.accounts.clone(), self.balance_tx.clone());
+        sim.wakeup(self.id, now_ns + self.interval_ns);
+    }
+
+    fn on_stop(&mut self, sim: &mut dyn SimulatorApi) {
+        self.drain_results(sim.now_ns());
+        let lowest = self.balances.iter().min_by_key(|(_, balance)| **balance);
+        agent_println!(
+            "[Balance {}] STOP: checks={} warnings={} low_now={} top_ups={} failed_top_ups={} query_errors={}{}",
+            self.name,
+            self.checks,
+            self.warnings,
+            self.low.len(),
+            self.top_ups,
+            self.top_up_failures,
+            self.query_errors,
+            lowest
+                .map(|(account, balance)| format!(
+                    " lowest=agent {} at {:.4} VARA",
+                    account,
+                    *balance as f64 / VARA_UNITS
+                ))
+                .unwrap_or_default()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vara::VaraConfig;
+
+    const VARA: u128 = 1_000_000_000_000;
+    const SEC: u64 = 1_000_000_000;
+
+    /// Watches agents 7, 8 and the funder 9 against a never-connected client
+    fn monitor(source: TopUpSource) -> BalanceMonitorAgent {
+        let config = VaraConfig::new(
+            "ws://localhost:9944",
+            format!("0x{}", "00".repeat(32)),
+            "keys",
+            "keys/.passphrase",
+        );
+        let client = Arc::new(VaraClient::new(config).unwrap());
+        BalanceMonitorAgent::new(
+            40,
+            BalanceMonitorConfig {
+                name: "balances".to_string(),
+                accounts: vec![7, 8, 9],
+                interval_ms: 10_000,
+                min_balance: 10 * VARA,
+                top_up: Some(TopUpConfig {
+                    source,
+                    amount: 5 * VARA,
+                    cooldown_ms: 60_000,
+                }),
+            },
+            client,
+        )
+    }
+
+    /// Deliver balance query results and process them at `now_ns`
+    fn check(monitor: &mut BalanceMonitorAgent, now_ns: u64, results: &[(AgentId, Result<u128, String>)]) {
+        for result in results {
+            monitor.balance_tx.send(result.clone()).unwrap();
+        }
+        monitor.drain_results(now_ns);
+    }
+
+    #[test]
+    fn test_warns_once_per_crossing_and_tops_up_after_the_cooldown() {
+        let mut monitor = monitor(TopUpSource::Funder(9));
+        check(&mut monitor, 0, &[(7, Ok(VARA)), (8, Ok(20 * VARA)), (9, Ok(VARA))]);
+        assert_eq!(monitor.warnings, 2);
+        // The funder is never asked to top itself up
+        assert_eq!(monitor.pending, HashSet::from([7]));
+
+        // The transfer fails (not connected); still low within the cooldown: no new warning or top-up
+        check(&mut monitor, 30 * SEC, &[(7, Ok(VARA)), (8, Err("rpc".to_string()))]);
+        assert_eq!(
+            (monitor.warnings, monitor.top_up_failures, monitor.query_errors),
+            (2, 1, 1)
+        );
+        assert!(monitor.pending.is_empty());
+
+        check(&mut monitor, 61 * SEC, &[(7, Ok(VARA))]);
+        assert_eq!(monitor.pending, HashSet::from([7]));
+        check(&mut monitor, 62 * SEC, &[]);
+        assert_eq!(monitor.top_up_failures, 2);
+
+        // Recovering re-arms the warning
+        check(&mut monitor, 70 * SEC, &[(7, Ok(12 * VARA))]);
+        assert!(!monitor.low.contains(&7));
+        check(&mut monitor, 80 * SEC, &[(7, Ok(VARA))]);
+        assert_eq!(monitor.warnings, 3);
+        assert_eq!(monitor.balances[&7], VARA);
+    }
+
+    #[test]
+    fn test_faucet_needs_the_account_address() {
+        let mut monitor = monitor(TopUpSource::Faucet("http://127.0.0.1:1/faucet".to_string()));
+        check(&mut monitor, 0, &[(7, Ok(VARA))]);
+        // No keystore behind the client: the top-up fails before any request is made
+        check(&mut monitor, SEC, &[]);
+        assert_eq!((monitor.top_ups, monitor.top_up_failures), (0, 1));
+    }
+}
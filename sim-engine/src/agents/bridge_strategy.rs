@@ -0,0 +1,697 @@
+//! BridgeStrategy - decision logic in an external process over localhost TCP.
+//!
+//! The agent connects to `address` on start and exchanges newline-delimited
+//! JSON with the process (e.g. a Python research script). Every hook sends
+//! one request and waits up to `timeout_ms` for the reply with the same `seq`:
+//!   -> {"seq": 7, "event": "price", "ts": 12.5, "symbol": "ETH-USD", "price": 3012.4,
+//!       "prices": {...}, "positions": [...], "open_orders": [...]}
+//!   <- {"seq": 7, "intents": [{"action": "open", "symbol": "ETH-USD", "side": "long", "qty": 0.5, "leverage": 3}]}
+//! Events: start, price, fill, timer. Timeline SetParam is forwarded as a
+//! `param` event ({"seq", "event", "ts", "key", "value"}) that gets no reply.
+//! Actions: open, close, limit, stop_loss, take_profit, cancel. Prices and
+//! amounts are in USD, sides are "long"/"short".
+//!
+//! A reply that misses the deadline sends nothing; late replies are dropped by
+//! `seq`. With `on_timeout = "disconnect"` the first timeout ends the session.
+//! After `max_timeouts` in a row the bridge disconnects either way.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent_println;
+use crate::agents::strategy_agent::{ActionIntent, Strategy, StrategyAgent, StrategyContext};
+use crate::messages::{ExecutionType, OrderExecutedPayload, OrderExecutionType, OrderId, OrderType, Side};
+
+pub type BridgeAgent = StrategyAgent<BridgeStrategy>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeoutPolicy {
+    /// Treat the call as "no action" and keep the session
+    Skip,
+    /// Close the connection; the agent stays idle
+    Disconnect,
+}
+
+#[derive(Debug, Clone)]
+pub struct BridgeConfig {
+    pub name: String,
+    /// host:port of the external process
+    pub address: String,
+    pub connect_timeout_ms: u64,
+    /// Deadline of one request/reply round trip
+    pub timeout_ms: u64,
+    pub on_timeout: TimeoutPolicy,
+    pub max_timeouts: u32,
+    pub timer_interval_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum BridgeSide {
+    Long,
+    Short,
+}
+
+impl From<Side> for BridgeSide {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Buy => BridgeSide::Long,
+            Side::Sell => BridgeSide::Short,
+        }
+    }
+}
+
+impl From<BridgeSide> for Side {
+    fn from(side: BridgeSide) -> Self {
+        match side {
+            BridgeSide::Long => Side::Buy,
+            BridgeSide::Short => Side::Sell,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BridgePosition<'a> {
+    symbol: &'a str,
+    side: BridgeSide,
+    qty: f64,
+    size_usd: f64,
+    leverage: u32,
+    opened_at: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct BridgeFill<'a> {
+    symbol: &'a str,
+    side: BridgeSide,
+    kind: &'static str,
+    size_usd: f64,
+    pnl_usd: f64,
+    collateral_usd: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct BridgeRequest<'a> {
+    seq: u64,
+    event: &'static str,
+    ts: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    symbol: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fill: Option<BridgeFill<'a>>,
+    prices: HashMap<&'a str, f64>,
+    positions: Vec<BridgePosition<'a>>,
+    open_orders: &'a [OrderId],
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum BridgeIntent {
+    Open {
+        symbol: String,
+        side: BridgeSide,
+        qty: f64,
+        leverage: u32,
+    },
+    Close {
+        symbol: String,
+        side: BridgeSide,
+    },
+    Limit {
+        symbol: String,
+        side: BridgeSide,
+        qty: f64,
+        leverage: u32,
+        price: f64,
+    },
+    StopLoss {
+        symbol: String,
+        side: BridgeSide,
+        price: f64,
+    },
+    TakeProfit {
+        symbol: String,
+        side: BridgeSide,
+        price: f64,
+    },
+    Cancel {
+        order_id: OrderId,
+    },
+}
+
+impl BridgeIntent {
+    fn into_action(self) -> ActionIntent {
+        let to_micro = |usd: f64| (usd * 1_000_000.0).round().max(0.0) as u64;
+        let decrease = |symbol, side: BridgeSide, execution_type, price| ActionIntent::Conditional {
+            symbol,
+            side: side.into(),
+            order_type: OrderType::Decrease,
+            execution_type,
+            qty: 0.0,
+            leverage: 1,
+            trigger_price: to_micro(price),
+        };
+        match self {
+            BridgeIntent::Open {
+                symbol,
+                side,
+                qty,
+                leverage,
+            } => ActionIntent::Open {
+                symbol,
+                side: side.into(),
+                qty,
+                leverage,
+            },
+            BridgeIntent::Close { symbol, side } => ActionIntent::Close {
+                symbol,
+                side: side.into(),
+            },
+            BridgeIntent::Limit {
+                symbol,
+                side,
+                qty,
+                leverage,
+                price,
+            } => ActionIntent::Conditional {
+                symbol,
+                side: side.into(),
+                order_type: OrderType::Increase,
+                execution_type: ExecutionType::Limit,
+                qty,
+                leverage,
+                trigger_price: to_micro(price),
+            },
+            BridgeIntent::StopLoss { symbol, side, price } => decrease(symbol, side, ExecutionType::StopLoss, price),
+            BridgeIntent::TakeProfit { symbol, side, price } => {
+                decrease(symbol, side, ExecutionType::TakeProfit, price)
+            }
+            BridgeIntent::Cancel { order_id } => ActionIntent::Cancel { order_id },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct BridgeReply {
+    pub(crate) seq: u64,
+    #[serde(default)]
+    intents: Vec<BridgeIntent>,
+}
+
+impl BridgeReply {
+    pub(crate) fn into_actions(self) -> Vec<ActionIntent> {
+        self.intents.into_iter().map(BridgeIntent::into_action).collect()
+    }
+}
+
+/// Hook-specific part of a request
+#[derive(Default)]
+pub(crate) struct RequestBody<'a> {
+    symbol: Option<&'a str>,
+    price: Option<f64>,
+    fill: Option<BridgeFill<'a>>,
+}
+
+impl<'a> RequestBody<'a> {
+    pub(crate) fn price(symbol: &'a str, price: u64) -> Self {
+        Self {
+            symbol: Some(symbol),
+            price: Some(price as f64 / 1_000_000.0),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn fill(fill: &'a OrderExecutedPayload) -> Self {
+        Self {
+            symbol: Some(&fill.symbol),
+            fill: Some(BridgeFill {
+                symbol: &fill.symbol,
+                side: fill.side.into(),
+                kind: match fill.order_type {
+                    OrderExecutionType::Increase => "increase",
+                    OrderExecutionType::Decrease => "decrease",
+                    OrderExecutionType::Liquidation => "liquidation",
+                },
+                size_usd: fill.size_usd as f64 / 1_000_000.0,
+                pnl_usd: fill.pnl as f64 / 1_000_000.0,
+                collateral_usd: fill.collateral_delta as f64 / 1_000_000.0,
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+/// One request line of the bridge protocol; `name` is sent with the `start` event only.
+pub(crate) fn encode_request(
+    ctx: &StrategyContext,
+    seq: u64,
+    event: &'static str,
+    name: &str,
+    body: RequestBody,
+) -> serde_json::Result<String> {
+    let request = BridgeRequest {
+        seq,
+        event,
+        ts: ctx.now_ns as f64 / 1e9,
+        name: (event == "start").then_some(name),
+        symbol: body.symbol,
+        price: body.price,
+        fill: body.fill,
+        prices: ctx
+            .prices()
+            .map(|(symbol, price)| (symbol, price as f64 / 1_000_000.0))
+            .collect(),
+        positions: ctx
+            .positions()
+            .map(|(symbol, side, position)| BridgePosition {
+                symbol,
+                side: side.into(),
+                qty: position.qty,
+                size_usd: position.size_usd / 1_000_000.0,
+                leverage: position.leverage,
+                opened_at: position.opened_at_ns as f64 / 1e9,
+            })
+            .collect(),
+        open_orders: ctx.open_orders(),
+    };
+    serde_json::to_string(&request)
+}
+
+/// Fire-and-forget `param` event carrying a timeline SetParam.
+pub(crate) fn encode_param(seq: u64, ts: f64, key: &str, value: &serde_json::Value) -> String {
+    serde_json::json!({
+        "seq": seq,
+        "event": "param",
+        "ts": ts,
+        "key": key,
+        "value": value,
+    })
+    .to_string()
+}
+
+struct Connection {
+    writer: TcpStream,
+    reader: BufReader<TcpStream>,
+    /// Partial line left by a timed-out read
+    pending: String,
+}
+
+pub struct BridgeStrategy {
+    name: String,
+    address: String,
+    connect_timeout: Duration,
+    timeout: Duration,
+    on_timeout: TimeoutPolicy,
+    max_timeouts: u32,
+    timer_interval_ms: Option<u64>,
+
+    conn: Option<Connection>,
+    seq: u64,
+    timeouts_in_row: u32,
+    /// Sim time of the last request, stamped on `param` events
+    last_ts: f64,
+
+    requests: u32,
+    timeouts: u32,
+    bad_replies: u32,
+}
+
+impl BridgeStrategy {
+    pub fn new(config: BridgeConfig) -> Self {
+        Self {
+            name: config.name,
+            address: config.address,
+            connect_timeout: Duration::from_millis(config.connect_timeout_ms),
+            timeout: Duration::from_millis(config.timeout_ms),
+            on_timeout: config.on_timeout,
+            max_timeouts: config.max_timeouts.max(1),
+            timer_interval_ms: config.timer_interval_ms,
+            conn: None,
+            seq: 0,
+            timeouts_in_row: 0,
+            last_ts: 0.0,
+            requests: 0,
+            timeouts: 0,
+            bad_replies: 0,
+        }
+    }
+
+    /// Retry until the external process accepts or `connect_timeout` runs out.
+    fn connect(&mut self) {
+        let deadline = Instant::now() + self.connect_timeout;
+        let addrs: Vec<SocketAddr> = match self.address.to_socket_addrs() {
+            Ok(addrs) => addrs.collect(),
+            Err(e) => {
+                eprintln!("[Bridge {}] bad address {}: {}", self.name, self.address, e);
+                return;
+            }
+        };
+        loop {
+            for addr in &addrs {
+                let remaining = deadline
+                    .saturating_duration_since(Instant::now())
+                    .max(Duration::from_millis(1));
+                let Ok(stream) = TcpStream::connect_timeout(addr, remaining) else {
+                    continue;
+                };
+                let _ = stream.set_nodelay(true);
+                match stream.try_clone() {
+                    Ok(reader) => {
+                        agent_println!("[Bridge {}] connected to {}", self.name, addr);
+                        self.conn = Some(Connection {
+                            writer: stream,
+                            reader: BufReader::new(reader),
+                            pending: String::new(),
+                        });
+                        return;
+                    }
+                    Err(e) => eprintln!("[Bridge {}] {}", self.name, e),
+                }
+            }
+            if Instant::now() >= deadline {
+                eprintln!(
+                    "[Bridge {}] no process at {} after {:?}, agent stays idle",
+                    self.name, self.address, self.connect_timeout
+                );
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(200));
+        }
+    }
+
+    fn disconnect(&mut self, reason: &str) {
+        if self.conn.take().is_some() {
+            eprintln!("[Bridge {}] disconnected: {}", self.name, reason);
+        }
+    }
+
+    /// Send one request and wait for the reply with the same `seq`.
+    fn request(&mut self, ctx: &StrategyContext, event: &'static str, body: RequestBody) -> Vec<ActionIntent> {
+        if self.conn.is_none() {
+            return Vec::new();
+        }
+        self.seq += 1;
+        self.requests += 1;
+        self.last_ts = ctx.now_ns as f64 / 1e9;
+        let line = match encode_request(ctx, self.seq, event, &self.name, body) {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("[Bridge {}] encode failed: {}", self.name, e);
+                return Vec::new();
+            }
+        };
+        let conn = self.conn.as_mut().expect("checked above");
+        if let Err(e) = writeln!(conn.writer, "{}", line) {
+            self.disconnect(&e.to_string());
+            return Vec::new();
+        }
+
+        match self.read_reply() {
+            Ok(Some(reply)) => {
+                self.timeouts_in_row = 0;
+                reply.into_actions()
+            }
+            Ok(None) => {
+                self.timeouts += 1;
+                self.timeouts_in_row += 1;
+                eprintln!(
+                    "[Bridge {}] {} #{} timed out after {:?}",
+                    self.name, event, self.seq, self.timeout
+                );
+                if self.on_timeout == TimeoutPolicy::Disconnect || self.timeouts_in_row >= self.max_timeouts {
+                    self.disconnect("reply timeout");
+                }
+                Vec::new()
+            }
+            Err(reason) => {
+                self.disconnect(&reason);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Ok(None) on timeout; Err when the connection is unusable.
+    fn read_reply(&mut self) -> Result<Option<BridgeReply>, String> {
+        let deadline = Instant::now() + self.timeout;
+        let seq = self.seq;
+        let conn = self.conn.as_mut().ok_or("not connected")?;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            conn.reader
+                .get_ref()
+                .set_read_timeout(Some(remaining))
+                .map_err(|e| e.to_string())?;
+            match conn.reader.read_line(&mut conn.pending) {
+                Ok(0) => return Err("closed by peer".to_string()),
+                Ok(_) if !conn.pending.ends_with('\n') => continue,
+                Ok(_) => {
+                    let line = std::mem::take(&mut conn.pending);
+                    match serde_json::from_str::<BridgeReply>(line.trim()) {
+                        Ok(reply) if reply.seq == seq => return Ok(Some(reply)),
+                        // Late reply to a request that already timed out
+                        Ok(_) => continue,
+                        Err(e) => {
+                            eprintln!("[Bridge {}] bad reply: {}", self.name, e);
+                            self.bad_replies += 1;
+                        }
+                    }
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => return Ok(None),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    }
+}
+
+impl Strategy for BridgeStrategy {
+    fn label(&self) -> &str {
+        "Bridge"
+    }
+
+    fn timer_interval_ms(&self) -> Option<u64> {
+        self.timer_interval_ms
+    }
+
+    fn on_start(&mut self, ctx: &StrategyContext) -> Vec<ActionIntent> {
+        self.connect();
+        self.request(ctx, "start", RequestBody::default())
+    }
+
+    fn on_price(&mut self, ctx: &StrategyContext, symbol: &str, price: u64) -> Vec<ActionIntent> {
+        self.request(ctx, "price", RequestBody::price(symbol, price))
+    }
+
+    fn on_fill(&mut self, ctx: &StrategyContext, fill: &OrderExecutedPayload) -> Vec<ActionIntent> {
+        self.request(ctx, "fill", RequestBody::fill(fill))
+    }
+
+    fn on_timer(&mut self, ctx: &StrategyContext) -> Vec<ActionIntent> {
+        self.request(ctx, "timer", RequestBody::default())
+    }
+
+    /// Forwarded as a fire-and-forget `param` event; the process decides what it means.
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        let Some(conn) = self.conn.as_mut() else {
+            return false;
+        };
+        self.seq += 1;
+        let request = encode_param(self.seq, self.last_ts, key, value);
+        if let Err(e) = writeln!(conn.writer, "{}", request) {
+            self.disconnect(&e.to_string());
+            return false;
+        }
+        true
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "requests={} timeouts={} bad_replies={} connected={}",
+            self.requests,
+            self.timeouts,
+            self.bad_replies,
+            self.conn.is_some()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    use super::*;
+    use crate::agents::test_support::{delivered, kernel_with_recorder, oracle_tick};
+    use crate::agents::Agent;
+    use crate::messages::{AgentId, Message, MessagePayload};
+    use crate::qty::Qty;
+
+    const EXCHANGE: AgentId = 1;
+    const BRIDGE: AgentId = 80;
+
+    /// Stand-in for the external process: answers each request (not `param` events) with the next
+    /// batch of reply lines, an empty batch staying silent. Returns the requests seen until the bridge hangs up.
+    fn serve(replies: Vec<Vec<&'static str>>) -> (String, JoinHandle<Vec<serde_json::Value>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let mut replies = VecDeque::from(replies);
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut seen = Vec::new();
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+                if request["event"] != "param" {
+                    for reply in replies.pop_front().unwrap_or_default() {
+                        writeln!(writer, "{}", reply).unwrap();
+                    }
+                }
+                seen.push(request);
+            }
+            seen
+        });
+        (address, handle)
+    }
+
+    fn bridge(address: String, on_timeout: TimeoutPolicy) -> BridgeAgent {
+        let strategy = BridgeStrategy::new(BridgeConfig {
+            name: "bridge".to_string(),
+            address,
+            connect_timeout_ms: 100,
+            timeout_ms: 50,
+            on_timeout,
+            max_timeouts: 2,
+            timer_interval_ms: None,
+        });
+        BridgeAgent::new(BRIDGE, "bridge".to_string(), EXCHANGE, strategy)
+    }
+
+    fn tick() -> Message {
+        oracle_tick(BRIDGE, "ETH-USD", 2_000_000_000)
+    }
+
+    #[test]
+    fn test_reply_intents_become_orders() {
+        let (address, process) = serve(vec![
+            vec![r#"{"seq": 1}"#],
+            vec![concat!(
+                r#"{"seq": 2, "intents": ["#,
+                r#"{"action": "open", "symbol": "ETH-USD", "side": "long", "qty": 0.5, "leverage": 3}, "#,
+                r#"{"action": "stop_loss", "symbol": "ETH-USD", "side": "long", "price": 1800.0}]}"#
+            )],
+        ]);
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut agent = bridge(address, TimeoutPolicy::Skip);
+        agent.on_start(&mut kernel);
+        agent.on_message(&mut kernel, &tick());
+
+        let sent = delivered(&mut kernel, &exchange);
+        let [Message {
+            payload: MessagePayload::MarketOrder(open),
+            ..
+        }, Message {
+            payload: MessagePayload::Order(stop),
+            ..
+        }] = &sent[..]
+        else {
+            panic!("expected an open and a stop loss, got {:?}", sent);
+        };
+        assert_eq!(
+            (open.side, open.qty, open.leverage),
+            (Side::Buy, Qty::from_tokens(0.5), 3)
+        );
+        assert_eq!(
+            (stop.execution_type, stop.trigger_price),
+            (ExecutionType::StopLoss, Some(1_800_000_000))
+        );
+
+        // SetParam is forwarded without waiting for a reply
+        assert!(agent.set_param("risk", &serde_json::json!(0.5)));
+        drop(agent);
+        let seen = process.join().unwrap();
+        let events: Vec<&str> = seen.iter().map(|r| r["event"].as_str().unwrap()).collect();
+        assert_eq!(events, ["start", "price", "param"]);
+        assert_eq!(seen[0]["name"], "bridge");
+        assert_eq!(
+            (seen[1]["symbol"].as_str(), seen[1]["price"].as_f64()),
+            (Some("ETH-USD"), Some(2000.0))
+        );
+        assert_eq!(seen[2]["key"], "risk");
+    }
+
+    #[test]
+    fn test_timeouts_skip_until_max_timeouts_in_a_row() {
+        let (address, process) = serve(vec![
+            vec![r#"{"seq": 1}"#],
+            vec![],
+            // The late reply to #2 is dropped by its seq
+            vec![
+                concat!(
+                    r#"{"seq": 2, "intents": ["#,
+                    r#"{"action": "open", "symbol": "ETH-USD", "side": "long", "qty": 5.0, "leverage": 2}]}"#
+                ),
+                concat!(
+                    r#"{"seq": 3, "intents": ["#,
+                    r#"{"action": "open", "symbol": "ETH-USD", "side": "short", "qty": 1.0, "leverage": 2}]}"#
+                ),
+            ],
+        ]);
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut agent = bridge(address, TimeoutPolicy::Skip);
+        agent.on_start(&mut kernel);
+
+        agent.on_message(&mut kernel, &tick());
+        assert!(delivered(&mut kernel, &exchange).is_empty());
+        agent.on_message(&mut kernel, &tick());
+        let sent = delivered(&mut kernel, &exchange);
+        assert!(matches!(
+            &sent[..],
+            [Message { payload: MessagePayload::MarketOrder(o), .. }] if o.side == Side::Sell
+        ));
+
+        // A success resets the count: two more silent replies in a row end the session
+        agent.on_message(&mut kernel, &tick());
+        agent.on_message(&mut kernel, &tick());
+        assert_eq!(process.join().unwrap().len(), 5);
+        agent.on_message(&mut kernel, &tick());
+        assert!(delivered(&mut kernel, &exchange).is_empty());
+        assert!(!agent.set_param("risk", &serde_json::json!(0.5)));
+    }
+
+    #[test]
+    fn test_disconnect_policy_and_missing_process_leave_the_agent_idle() {
+        let (address, process) = serve(vec![vec![r#"{"seq": 1}"#]]);
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut agent = bridge(address, TimeoutPolicy::Disconnect);
+        agent.on_start(&mut kernel);
+        agent.on_message(&mut kernel, &tick());
+        assert_eq!(process.join().unwrap().len(), 2);
+
+        // Nobody listening: the connect gives up after `connect_timeout_ms`
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let mut agent = bridge(address, TimeoutPolicy::Skip);
+        agent.on_start(&mut kernel);
+        agent.on_message(&mut kernel, &tick());
+        assert!(delivered(&mut kernel, &exchange).is_empty());
+    }
+}
@@ -0,0 +1,325 @@
+//! CopyTraderAgent - mirrors the fills of a leader agent.
+//!
+//! On start the agent subscribes to the leader's executed orders at the
+//! exchange (SubscribeFills). Every LeaderFill is replayed after `delay_ms`:
+//! opens are copied at `ratio` of the leader's notional with the leader's
+//! leverage (capped at `max_leverage`), closes and liquidations of the leader
+//! close the mirrored side. Several copy traders on one leader show how
+//! herding amplifies OI imbalance and liquidation cascades.
+
+use std::collections::{HashSet, VecDeque};
+
+use crate::agent_println;
+use crate::agents::Agent;
+use crate::messages::{
+    AgentId, CloseOrderPayload, LeaderFillPayload, MarketOrderPayload, Message, MessagePayload,
+    MessageType, OrderExecutionType, Side, SimulatorApi, SubscribeFillsPayload,
+};
+use crate::qty::Qty;
+
+#[derive(Debug, Clone)]
+pub struct CopyTraderConfig {
+    pub name: String,
+    pub exchange_id: AgentId,
+    pub leader_id: AgentId,
+    /// Mirrored notional as a fraction of the leader's
+    pub ratio: f64,
+    /// Reaction delay after the leader's fill
+    pub delay_ms: u64,
+    pub max_leverage: u32,
+}
+
+pub struct CopyTraderAgent {
+    id: AgentId,
+    name: String,
+    exchange_id: AgentId,
+    leader_id: AgentId,
+    address: Option<String>,
+    ratio: f64,
+    delay_ns: u64,
+    max_leverage: u32,
+
+    /// Leader fills waiting for the reaction delay: (at_ns, fill)
+    queue: VecDeque<(u64, LeaderFillPayload)>,
+    /// Mirrored positions
+    open: HashSet<(String, Side)>,
+
+    copied_opens: u32,
+    copied_closes: u32,
+    liquidations: u32,
+    volume_usd: u64,
+}
+
+impl CopyTraderAgent {
+    pub fn new(id: AgentId, config: CopyTraderConfig) -> Self {
+        Self {
+            id,
+            name: config.name,
+            exchange_id: config.exchange_id,
+            leader_id: config.leader_id,
+            address: None,
+            ratio: config.ratio,
+            delay_ns: config.delay_ms * 1_000_000,
+            max_leverage: config.max_leverage.max(1),
+            queue: VecDeque::new(),
+            open: HashSet::new(),
+            copied_opens: 0,
+            copied_closes: 0,
+            liquidations: 0,
+            volume_usd: 0,
+        }
+    }
+
+    pub fn set_address(&mut self, address: String) {
+        self.address = Some(address);
+    }
+
+    fn mirror(&mut self, sim: &mut dyn SimulatorApi, fill: LeaderFillPayload) {
+        match fill.order_type {
+            OrderExecutionType::Increase => {
+                if fill.execution_price == 0 {
+                    return;
+                }
+                let size_usd = (fill.size_usd as f64 * self.ratio) as u64;
+                let qty = size_usd as f64 / fill.execution_price as f64;
+                let leverage = fill.leverage.clamp(1, self.max_leverage);
+                agent_println!(
+                    "[Copy {}] OPEN {:?} {} ${:.2} {}x (leader {})",
+                    self.name,
+                    fill.side,
+                    fill.symbol,
+                    size_usd as f64 / 1_000_000.0,
+                    leverage,
+                    self.leader_id
+                );
+                sim.send(
+                    self.id,
+                    self.exchange_id,
+                    MessageType::MarketOrder,
+                    MessagePayload::MarketOrder(MarketOrderPayload {
+                        symbol: fill.symbol.clone(),
+                        side: fill.side,
+                        qty: Qty::from_tokens(qty),
+                        leverage,
+                        decision_price: sim.market_data().mid(&fill.symbol),
+                    }),
+                );
+                self.open.insert((fill.symbol, fill.side));
+                self.copied_opens += 1;
+                self.volume_usd += size_usd;
+            }
+            OrderExecutionType::Decrease | OrderExecutionType::Liquidation => {
+                if !self.open.remove(&(fill.symbol.clone(), fill.side)) {
+                    return;
+                }
+                agent_println!(
+                    "[Copy {}] CLOSE {:?} {} (leader {} {:?})",
+                    self.name,
+                    fill.side,
+                    fill.symbol,
+                    self.leader_id,
+                    fill.order_type
+                );
+                let decision_price = sim.market_data().mid(&fill.symbol);
+                sim.send(
+                    self.id,
+                    self.exchange_id,
+                    MessageType::CloseOrder,
+                    MessagePayload::CloseOrder(CloseOrderPayload {
+                        symbol: fill.symbol,
+                        side: fill.side,
+                        decision_price,
+                    }),
+                );
+                self.copied_closes += 1;
+            }
+        }
+    }
+}
+
+impl Agent for CopyTraderAgent {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        match (key, value.as_f64()) {
+            ("ratio", Some(v)) if v > 0.0 => self.ratio = v,
+            ("delay_ms", Some(v)) if v >= 0.0 => self.delay_ns = v as u64 * 1_000_000,
+            ("max_leverage", Some(v)) if v >= 1.0 => self.max_leverage = v as u32,
+            _ => return false,
+        }
+        agent_println!("[Copy {}] param {} = {}", self.name, key, value);
+        true
+    }
+
+    fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[Copy {}] START following {} ratio={:.2} delay={}ms{}",
+            self.name,
+            self.leader_id,
+            self.ratio,
+            self.delay_ns / 1_000_000,
+            self.address
+                .as_deref()
+                .map(|addr| format!(" addr={}", addr))
+                .unwrap_or_default()
+        );
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::SubscribeFills,
+            MessagePayload::SubscribeFills(SubscribeFillsPayload { leader: self.leader_id }),
+        );
+    }
+
+    fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
+        while self.queue.front().is_some_and(|(at, _)| *at <= now_ns) {
+            if let Some((_, fill)) = self.queue.pop_front() {
+                self.mirror(sim, fill);
+            }
+        }
+    }
+
+    fn on_message(&mut self, sim: &mut dyn SimulatorApi, msg: &Message) {
+        match &msg.payload {
+            MessagePayload::LeaderFill(fill) if fill.leader == self.leader_id => {
+                let at = sim.now_ns() + self.delay_ns;
+                self.queue.push_back((at, fill.clone()));
+                sim.wakeup(self.id, at);
+            }
+            MessagePayload::PositionLiquidated(p) => {
+                agent_println!(
+                    "[Copy {}] LIQUIDATED {:?} {} pnl=${:.2}",
+                    self.name,
+                    p.side,
+                    p.symbol,
+                    p.pnl as f64 / 1_000_000.0
+                );
+                self.open.remove(&(p.symbol.clone(), p.side));
+                self.liquidations += 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[Copy {}] STOP: opens={} closes={} liq={} volume=${:.2}",
+            self.name,
+            self.copied_opens,
+            self.copied_closes,
+            self.liquidations,
+            self.volume_usd as f64 / 1_000_000.0
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::test_support::{delivered, kernel_with_recorder, liquidated};
+
+    const EXCHANGE: AgentId = 1;
+    const LEADER: AgentId = 7;
+    const COPY: AgentId = 30;
+    const MS: u64 = 1_000_000;
+
+    fn copy_trader() -> CopyTraderAgent {
+        CopyTraderAgent::new(
+            COPY,
+            CopyTraderConfig {
+                name: "copy".to_string(),
+                exchange_id: EXCHANGE,
+                leader_id: LEADER,
+                ratio: 0.5,
+                delay_ms: 500,
+                max_leverage: 10,
+            },
+        )
+    }
+
+    fn fill(leader: AgentId, side: Side, order_type: OrderExecutionType) -> Message {
+        let fill = LeaderFillPayload {
+            leader,
+            symbol: "ETH-USD".to_string(),
+            side,
+            order_type,
+            size_usd: 10_000_000_000,
+            execution_price: 2_000_000_000,
+            leverage: 20,
+        };
+        Message::new(
+            COPY,
+            EXCHANGE,
+            MessageType::LeaderFill,
+            0,
+            MessagePayload::LeaderFill(fill),
+        )
+    }
+
+    #[test]
+    fn test_opens_at_the_copy_ratio_after_the_delay() {
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut copy = copy_trader();
+        copy.on_start(&mut kernel);
+        let subscribed = delivered(&mut kernel, &exchange);
+        assert!(matches!(
+            &subscribed[..],
+            [Message { payload: MessagePayload::SubscribeFills(s), .. }] if s.leader == LEADER
+        ));
+
+        copy.on_message(&mut kernel, &fill(LEADER, Side::Buy, OrderExecutionType::Increase));
+        copy.on_message(&mut kernel, &fill(LEADER + 1, Side::Sell, OrderExecutionType::Increase));
+        copy.on_wakeup(&mut kernel, 499 * MS);
+        assert!(delivered(&mut kernel, &exchange).is_empty());
+
+        copy.on_wakeup(&mut kernel, 600 * MS);
+        let orders = delivered(&mut kernel, &exchange);
+        // Half of $10k at $2000 is 2.5 ETH, and the leader's 20x is capped at 10x
+        let [Message {
+            payload: MessagePayload::MarketOrder(order),
+            ..
+        }] = &orders[..]
+        else {
+            panic!("expected one market order, got {:?}", orders);
+        };
+        assert_eq!(order.side, Side::Buy);
+        assert_eq!(order.qty, Qty::from_tokens(2.5));
+        assert_eq!(order.leverage, 10);
+        assert_eq!(copy.volume_usd, 5_000_000_000);
+    }
+
+    #[test]
+    fn test_closes_only_mirrored_positions() {
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut copy = copy_trader();
+
+        // Nothing mirrored yet: the leader's close is ignored
+        copy.on_message(&mut kernel, &fill(LEADER, Side::Buy, OrderExecutionType::Decrease));
+        copy.on_wakeup(&mut kernel, 500 * MS);
+        assert!(delivered(&mut kernel, &exchange).is_empty());
+
+        copy.on_message(&mut kernel, &fill(LEADER, Side::Buy, OrderExecutionType::Increase));
+        copy.on_message(&mut kernel, &fill(LEADER, Side::Sell, OrderExecutionType::Increase));
+        copy.on_message(&mut kernel, &fill(LEADER, Side::Buy, OrderExecutionType::Liquidation));
+        copy.on_wakeup(&mut kernel, 1_000 * MS);
+        let orders = delivered(&mut kernel, &exchange);
+        assert_eq!(orders.len(), 3);
+        assert!(matches!(
+            &orders[2].payload,
+            MessagePayload::CloseOrder(c) if c.side == Side::Buy && c.symbol == "ETH-USD"
+        ));
+
+        // Our own liquidation drops the short, so the leader's later close has nothing to mirror
+        copy.on_message(&mut kernel, &liquidated(COPY, "ETH-USD", Side::Sell));
+        copy.on_message(&mut kernel, &fill(LEADER, Side::Sell, OrderExecutionType::Decrease));
+        copy.on_wakeup(&mut kernel, 2_000 * MS);
+        assert!(delivered(&mut kernel, &exchange).is_empty());
+        assert_eq!((copy.copied_opens, copy.copied_closes, copy.liquidations), (2, 1, 1));
+    }
+}
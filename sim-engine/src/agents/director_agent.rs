@@ -0,0 +1,201 @@
+//! DirectorAgent - executes the scenario `timeline`.
+//!
+//! Timed, declarative actions for multi-phase stress narratives:
+//! - price shocks (oracle prices scaled for a while or until the end of the run)
+//! - agents joining (pre-built at scenario load) and leaving
+//! - runtime parameter changes of individual agents
+
+use std::collections::VecDeque;
+
+use crate::agent_println;
+use crate::agents::Agent;
+use crate::messages::{AgentId, DirectorCommand, MessagePayload, MessageType, SimulatorApi};
+
+pub enum TimelineAction {
+    /// Move `symbol` by `pct` percent; reverted after `duration_sec` if set
+    PriceShock {
+        symbol: String,
+        pct: f64,
+        duration_sec: Option<u64>,
+    },
+    SpawnAgent(Box<dyn Agent>),
+    RemoveAgent(AgentId),
+    SetParam {
+        agent_id: AgentId,
+        key: String,
+        value: serde_json::Value,
+    },
+}
+
+pub struct TimelineEvent {
+    /// Seconds after simulation start
+    pub at_sec: u64,
+    pub action: TimelineAction,
+}
+
+pub struct DirectorAgent {
+    id: AgentId,
+    name: String,
+    /// Oracles that receive price shocks
+    oracle_ids: Vec<AgentId>,
+    events: VecDeque<TimelineEvent>,
+    /// Scheduled shock reverts: (at_ns, symbol, factor)
+    reverts: Vec<(u64, String, f64)>,
+    start_ns: u64,
+    executed: u32,
+}
+
+impl DirectorAgent {
+    pub fn new(id: AgentId, name: String, oracle_ids: Vec<AgentId>, mut events: Vec<TimelineEvent>) -> Self {
+        events.sort_by_key(|e| e.at_sec);
+        Self {
+            id,
+            name,
+            oracle_ids,
+            events: events.into(),
+            reverts: Vec::new(),
+            start_ns: 0,
+            executed: 0,
+        }
+    }
+
+    fn send_price_factor(&self, sim: &mut dyn SimulatorApi, symbol: &str, factor: f64) {
+        for oracle_id in &self.oracle_ids {
+            sim.send(
+                self.id,
+                *oracle_id,
+                MessageType::Director,
+                MessagePayload::Director(DirectorCommand::PriceShock {
+                    symbol: symbol.to_string(),
+                    factor,
+                }),
+            );
+        }
+    }
+
+    fn execute(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64, action: TimelineAction) {
+        let t = (now_ns - self.start_ns) / 1_000_000_000;
+        match action {
+            TimelineAction::PriceShock {
+                symbol,
+                pct,
+                duration_sec,
+            } => {
+                let factor = 1.0 + pct / 100.0;
+                if factor <= 0.0 {
+                    eprintln!(
+                        "[Director {}] t={}s price_shock {} {:+.1}% ignored",
+                        self.name, t, symbol, pct
+                    );
+                    return;
+                }
+                agent_println!(
+                    "[Director {}] t={}s PRICE SHOCK {} {:+.1}%{}",
+                    self.name,
+                    t,
+                    symbol,
+                    pct,
+                    duration_sec.map(|d| format!(" for {}s", d)).unwrap_or_default()
+                );
+                self.send_price_factor(sim, &symbol, factor);
+                if let Some(d) = duration_sec {
+                    self.reverts.push((now_ns + d * 1_000_000_000, symbol, 1.0 / factor));
+                }
+            }
+            TimelineAction::SpawnAgent(agent) => {
+                agent_println!(
+                    "[Director {}] t={}s JOIN {} (id={})",
+                    self.name,
+                    t,
+                    agent.name(),
+                    agent.id()
+                );
+                sim.spawn_agent(agent);
+            }
+            TimelineAction::RemoveAgent(agent_id) => {
+                agent_println!("[Director {}] t={}s LEAVE id={}", self.name, t, agent_id);
+                sim.remove_agent(agent_id);
+            }
+            TimelineAction::SetParam { agent_id, key, value } => {
+                agent_println!(
+                    "[Director {}] t={}s SET id={} {}={}",
+                    self.name,
+                    t,
+                    agent_id,
+                    key,
+                    value
+                );
+                sim.send(
+                    self.id,
+                    agent_id,
+                    MessageType::Director,
+                    MessagePayload::Director(DirectorCommand::SetParam { key, value }),
+                );
+            }
+        }
+        self.executed += 1;
+    }
+
+    fn schedule_next(&self, sim: &mut dyn SimulatorApi) {
+        let next_event = self.events.front().map(|e| self.start_ns + e.at_sec * 1_000_000_000);
+        let next_revert = self.reverts.iter().map(|(at, _, _)| *at).min();
+        let next = match (next_event, next_revert) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        if let Some(at) = next {
+            sim.wakeup(self.id, at.max(sim.now_ns()));
+        }
+    }
+}
+
+impl Agent for DirectorAgent {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+        self.start_ns = sim.now_ns();
+        agent_println!(
+            "[Director {}] START timeline with {} events",
+            self.name,
+            self.events.len()
+        );
+        self.schedule_next(sim);
+    }
+
+    fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
+        while let Some(event) = self.events.front() {
+            if self.start_ns + event.at_sec * 1_000_000_000 > now_ns {
+                break;
+            }
+            if let Some(event) = self.events.pop_front() {
+                self.execute(sim, now_ns, event.action);
+            }
+        }
+
+        let (due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.reverts)
+            .into_iter()
+            .partition(|(at, _, _)| *at <= now_ns);
+        self.reverts = pending;
+        for (_, symbol, factor) in due {
+            agent_println!("[Director {}] price shock on {} ended", self.name, symbol);
+            self.send_price_factor(sim, &symbol, factor);
+        }
+
+        self.schedule_next(sim);
+    }
+
+    fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[Director {}] STOP: executed={} remaining={}",
+            self.name,
+            self.executed,
+            self.events.len()
+        );
+    }
+}
@@ -1,49 +1,97 @@
+use crate::agent_println;
 use crate::agents::Agent;
+use crate::api::SignedPriceData;
+use crate::blotter::TradeBlotter;
+use crate::capital::{Bankruptcy, CapitalLedger};
+use crate::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig};
+use crate::events::SimEvent;
+use crate::exchange_state::{BalanceState, ExchangeState, LocalPositionState, LpShareState, MarketState};
+use crate::flow_stats::FlowStatsBook;
+use crate::mark_price::{MarkPrice, MarkPriceConfig};
 use crate::messages::{
-    AgentId, CloseOrderPayload, ExecutionType, KeeperRewardPayload, MarketOrderPayload, MarketStatePayload, Message,
-    MessagePayload, MessageType, OracleTickPayload, OrderId, OrderPayload,
-    OrderType as SimOrderType, PendingOrderInfo, PendingOrdersListPayload,
-    PreviewRequestPayload, PreviewResponsePayload, Price, Side as SimSide, SimulatorApi,
+    AgentId, CloseOrderPayload, ExecuteOrderBatchPayload, ExecuteOrderPayload, ExecutionType, GetTradesPayload,
+    KeeperRewardPayload, LeaderFillPayload, LiquidityChangedPayload, LiquidityPayload, MarketOrderPayload,
+    MarketStatePayload, MarketStatsPayload, Message, MessagePayload, MessageType, ModifyOrderPayload,
+    OracleTickPayload, OrderExecutedPayload, OrderExecutionType, OrderId, OrderPayload, OrderType as SimOrderType,
+    PendingOrderInfo, PendingOrdersListPayload, PositionInfo, PositionLiquidatedPayload, PositionsSnapshotPayload,
+    PreviewRequestPayload, PreviewResponsePayload, Price, PriceUpdatePayload, Side as SimSide, SimulatorApi,
+    TradeRecord, TradesListPayload,
 };
+use crate::oi_caps::{utilization_bps, OiCapBreach, OiCaps};
+use crate::order_ids::OrderIdMap;
 use crate::pending_orders::{PendingOrder, PendingOrderStore};
+use crate::price_rules::{PriceRules, PriceViolation};
+use crate::risk_limits::RiskGuard;
 use crate::trigger_checker;
+use crate::scale::{MarketScale, MicroUsd, ScaleRegistry, TokenScale, Usd1e30};
+use crate::vara::clock::{chain_now_sec, ChainClock};
+use crate::vara::oracle::{OracleInputBuilder, OracleInputError, PEGGED_COLLATERAL_MICRO};
 use crate::vara::{
-    ActorId, ExecutionType as VaraExecutionType, OracleInput, OraclePrices, Order as VaraOrder,
-    OrderId as VaraOrderId, OrderType as VaraOrderType, PositionKey as VaraPositionKey,
-    Side as VaraSide, TxResult, VaraClient, u256_from_sails, u256_to_sails,
+    u256_from_sails, u256_to_sails, ActorId, ExecutionType as VaraExecutionType, OracleInput, OraclePrices,
+    Order as VaraOrder, OrderId as VaraOrderId, OrderType as VaraOrderType, Position as VaraPosition,
+    PositionKey as VaraPositionKey, Side as VaraSide, TxResult, TxType, VaraClient,
 };
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use primitive_types::U256;
-use std::io::{BufWriter, Write};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Ids the exchange hands out: orders it holds itself (trailing stops, orders of local agents)
+/// and the local ids of on-chain orders. Far above contract order ids
+const LOCAL_ORDER_ID_BASE: OrderId = 1 << 48;
+
+/// Keeper reward per executed order, in bps of its notional (qty * price)
+pub const KEEPER_REWARD_BPS: u64 = 10;
+
+/// A keeper's claim on an order lapses if it does not execute within this time
+const KEEPER_CLAIM_TTL_NS: u64 = 10_000_000_000;
+
+/// Local positions are liquidated once equity (collateral + PnL) drops below this fraction of their size
+const LOCAL_MAINTENANCE_MARGIN: f64 = 0.01;
 
 // ==== Price Normalization ====
-// perp-futures uses USD(1e30) per 1 atom of token
-// Our oracle provides prices in micro-USD (1e6 = $1)
-//
-// Conversion formula:
-//   price_per_atom = price_micro_usd * 10^(24 - index_decimals)
-//
-// Examples:
-//   ETH ($3000, 18 decimals): 3000_000_000 * 10^6 = 3000 * 10^12
-//   BTC ($100000, 8 decimals): 100000_000_000 * 10^16 = 100000 * 10^22
-
-/// Convert micro-USD price to USD(1e30) per atom
-/// micro_usd: price in micro-USD (1e6 = $1)
-/// index_decimals: token decimals (ETH=18, BTC=8)
-fn normalize_price_to_atom(micro_usd: u64, index_decimals: u32) -> U256 {
-    // price_per_atom = micro_usd * 10^(24 - index_decimals)
-    // = micro_usd * 10^24 / 10^index_decimals
-    let exp = 24u32.saturating_sub(index_decimals);
-    U256::from(micro_usd) * U256::exp10(exp as usize)
+// micro-USD <-> USD(1e30) and token atom conversions live in `crate::scale`
+
+/// Average entry price in micro-USD per whole token: size_usd / size_tokens
+fn entry_price_micro(size_usd: U256, size_tokens: U256, index: TokenScale) -> u64 {
+    Usd1e30(size_usd).per_token(index.amount(size_tokens)).0
+}
+
+/// Liquidation price in micro-USD per token: the contract's `CalculateLiquidationPrice` result
+/// if there is one, else the price at which collateral + PnL falls to the local maintenance margin.
+fn liquidation_price_micro(
+    side: SimSide,
+    size_usd: u64,
+    collateral: u64,
+    entry_price: u64,
+    index: TokenScale,
+    onchain: Option<U256>,
+) -> u64 {
+    if let Some(price) = onchain.map(|p| index.micro_price(p).0).filter(|p| *p > 0) {
+        return price;
+    }
+    if size_usd == 0 {
+        return 0;
+    }
+    let margin = collateral as f64 / size_usd as f64;
+    let factor = match side {
+        SimSide::Buy => 1.0 + LOCAL_MAINTENANCE_MARGIN - margin,
+        SimSide::Sell => 1.0 - LOCAL_MAINTENANCE_MARGIN + margin,
+    };
+    (entry_price as f64 * factor).max(0.0) as u64
 }
 
-/// Convert USD(1e30) to micro-USD for display  
-fn usd_to_micro(usd: U256) -> u64 {
-    // USD(1e30) -> micro-USD (1e6): divide by 10^24
-    // BUT: size_usd in positions is absolute USD(1e30), so divide by 10^24 for display
-    let divisor = U256::exp10(24);
-    (usd / divisor).low_u64()
+/// Whether `price` has crossed the liquidation price of a position on `side`.
+fn crossed_liquidation(side: SimSide, price: u64, liquidation_price: u64) -> bool {
+    if price == 0 || liquidation_price == 0 {
+        return false;
+    }
+    match side {
+        SimSide::Buy => price <= liquidation_price,
+        SimSide::Sell => price >= liquidation_price,
+    }
 }
 
 // ==== Market Configuration (from scenario JSON) ====
@@ -59,33 +107,219 @@ pub struct MarketConfig {
     pub liquidity_usd: i128,
     pub index_decimals: u32,      // Token decimals (ETH=18, BTC=8)
     pub collateral_decimals: u32, // Collateral decimals (USDT=6)
+    pub position_fee_bps: u32,    // Open/close fee on notional (bps)
+    pub fee_rates: FeeRates,
+    /// Oracle feed pricing the collateral token; None = stablecoin pegged at $1
+    pub collateral_price: Option<CollateralPrice>,
+    /// Tick size and price band enforced on order prices
+    pub price_rules: PriceRules,
+    /// Mark price for liquidations and uPnL, and the fill spread; None = both at the index
+    pub mark_price: Option<MarkPriceConfig>,
+    /// Halt of new increases after an extreme move; None = never halted
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// Caps on total and per-side OI, in USD and relative to pool liquidity
+    pub oi_caps: OiCaps,
+}
+
+/// Collateral token priced by the oracle instead of pegged at $1 (e.g. ETH collateral).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CollateralPrice {
+    /// Oracle symbol quoting the collateral token in USD, e.g. "ETH-USD" or "USDT-USD"
+    pub feed: String,
+    /// Price until the feed's first tick, also used to size the initial deposits
+    pub initial_usd: f64,
+}
+
+/// Funding and borrowing parameters of the contract's InitConfig (bps per day).
+///
+/// The contract has no rate query, so the exchange estimates the current rates from
+/// these and the market state, the same way funding_arb does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeeRates {
+    /// FundingConfig.daily_rate_bps: funding at full OI imbalance
+    pub funding_daily_rate_bps: u64,
+    /// BorrowingConfig.base_rate_per_day_bps
+    pub borrowing_base_per_day_bps: u64,
+    /// BorrowingConfig.slope_per_day_bps: extra borrowing at utilization 1
+    pub borrowing_slope_per_day_bps: u64,
+}
+
+impl FeeRates {
+    /// Funding rate in bps*1e6 per hour, positive when longs pay:
+    /// daily_rate / 24 * (long - short) / (long + short)
+    pub fn funding_bps_hour_fp(&self, oi_long_usd: u64, oi_short_usd: u64) -> i64 {
+        let total = oi_long_usd as i128 + oi_short_usd as i128;
+        if total == 0 {
+            return 0;
+        }
+        let imbalance = oi_long_usd as i128 - oi_short_usd as i128;
+        (self.funding_daily_rate_bps as i128 * 1_000_000 * imbalance / (24 * total)) as i64
+    }
+
+    /// Borrowing rate in bps*1e6 per hour: (base + slope * utilization) / 24,
+    /// utilization = OI / liquidity capped at 1
+    pub fn borrowing_bps_hour_fp(&self, oi_long_usd: u64, oi_short_usd: u64, liquidity_usd: u64) -> u64 {
+        let oi = oi_long_usd as u128 + oi_short_usd as u128;
+        let util_fp = if liquidity_usd == 0 {
+            if oi > 0 {
+                1_000_000
+            } else {
+                0
+            }
+        } else {
+            (oi * 1_000_000 / liquidity_usd as u128).min(1_000_000)
+        };
+        let daily_fp =
+            self.borrowing_base_per_day_bps as u128 * 1_000_000 + self.borrowing_slope_per_day_bps as u128 * util_fp;
+        (daily_fp / 24) as u64
+    }
+}
+
+/// Local fallback of on-chain market/close orders (`vara.local_fallback`): an order whose
+/// SubmitAndExecute fails with a transient error before reaching the contract is filled locally
+/// at its expected price, marked unsynced and sent to the chain again until it lands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LocalFallbackConfig {
+    /// Sim time between chain attempts of an unsynced order
+    pub retry_after_ms: u64,
+    /// Chain attempts before an unsynced order is given up (local and chain positions then differ)
+    pub max_retries: u32,
+}
+
+impl Default for LocalFallbackConfig {
+    fn default() -> Self {
+        Self {
+            retry_after_ms: 5_000,
+            max_retries: 10,
+        }
+    }
+}
+
+/// Error fragments (lowercase) of failures worth another attempt: the node, not the contract, refused
+const TRANSIENT_TX_ERRORS: &[&str] = &[
+    "timeout",
+    "timed out",
+    "connection",
+    "transport",
+    "rpc",
+    "network",
+    "websocket",
+    "runtime error",
+    "unavailable",
+];
+
+fn is_transient_tx_error(error: &str) -> bool {
+    let error = error.to_lowercase();
+    TRANSIENT_TX_ERRORS.iter().any(|fragment| error.contains(fragment))
+}
+
+/// Market/close order sent on-chain, waiting for its SubmitAndExecute result.
+/// Results for one agent arrive in submission order (per-agent tx lock), so a FIFO per agent is enough.
+#[derive(Debug, Clone)]
+struct InflightExecution {
+    symbol: String,
+    side: SimSide,
+    order_type: &'static str,
+    size_usd: u64,
+    collateral: u64,
+    execution_price: u64,
+    leverage: u32,
+    pnl: i64,
+    fee_usd: u64,
+    /// Oracle mid the sender decided at (`MarketOrderPayload::decision_price`)
+    decision_price: Option<u64>,
+    /// Keeper and the triggered order it had filled by this execution; the order goes back to
+    /// the book if the tx fails
+    triggered_order: Option<(AgentId, PendingOrder)>,
+    /// Contract order sent, kept for another attempt when the local fallback is on
+    chain_order: Option<VaraOrder>,
+    /// Booked locally by the fallback, with the chain attempts sent since
+    unsynced: Option<u32>,
+}
+
+/// Successful transaction that a simulated reorg may still revert (`vara.reorg`), with what its
+/// result booked locally.
+#[derive(Debug)]
+enum UnfinalizedTx {
+    /// Confirmed market/close order
+    Execution { agent: AgentId, exec: InflightExecution },
+    /// Conditional order linked to its contract id
+    Submission { order_id: OrderId },
+    /// Keeper execution of a triggered order
+    KeeperExecution { order: PendingOrder },
+}
+
+/// Keeper holding the exclusive right to execute an order until `expires_ns`.
+#[derive(Debug, Clone, Copy)]
+struct OrderClaim {
+    keeper: AgentId,
+    expires_ns: u64,
+}
+
+/// Conditional order resting on the contract. The contract keeps expired orders and knows
+/// no OCO groups, so the exchange tracks both and cancels on the owner's behalf.
+#[derive(Debug, Clone)]
+struct OnchainOrder {
+    /// Id the owner knows the order by (see `order_ids`)
+    local_id: OrderId,
+    owner: AgentId,
+    valid_until_ns: u64,
+    oco_group: Option<u64>,
+    /// Cancelled by the owner before the contract assigned an id
+    cancel_requested: bool,
+}
+
+/// Position of an agent that trades locally (scenario `vara.local_agents`), filled at the oracle price.
+#[derive(Debug, Clone, Default)]
+struct LocalPosition {
+    size_usd: u64,
+    size_tokens: f64,
+    /// Micro-USD value of the collateral when it was posted
+    collateral: u64,
+    /// Collateral in collateral tokens (re-priced when the collateral has a feed)
+    collateral_tokens: f64,
+}
+
+impl LocalPosition {
+    fn pnl(&self, side: SimSide, price: u64) -> i64 {
+        let value = (self.size_tokens * price as f64) as i64;
+        match side {
+            SimSide::Buy => value - self.size_usd as i64,
+            SimSide::Sell => self.size_usd as i64 - value,
+        }
+    }
+
+    /// Change in value of the collateral since it was posted, at `collateral_price`
+    /// (micro-USD per collateral token)
+    fn collateral_revaluation(&self, collateral_price: u64) -> i64 {
+        (self.collateral_tokens * collateral_price as f64) as i64 - self.collateral as i64
+    }
 }
 
 #[derive(Clone)]
 struct PriceCache {
     /// Maps symbol -> (index_price_min, index_price_max) in USD(1e30) per atom
     prices: HashMap<String, (U256, U256)>,
-    /// Maps symbol -> index_decimals (needed for price normalization)
-    decimals: HashMap<String, u32>,
+    /// Token decimals of each market (needed for price normalization)
+    scales: ScaleRegistry,
 }
 
 impl PriceCache {
-    fn new() -> Self {
+    fn new(scales: ScaleRegistry) -> Self {
         Self {
             prices: HashMap::new(),
-            decimals: HashMap::new(),
+            scales,
         }
     }
 
-    fn set_decimals(&mut self, symbol: &str, index_decimals: u32) {
-        self.decimals.insert(symbol.to_string(), index_decimals);
-    }
-
     /// Update price from micro-USD (1e6 = $1) to USD(1e30) per atom
     fn update(&mut self, symbol: &str, min_micro: u64, max_micro: u64) {
-        let decimals = self.decimals.get(symbol).copied().unwrap_or(18);
-        let min_atom = normalize_price_to_atom(min_micro, decimals);
-        let max_atom = normalize_price_to_atom(max_micro, decimals);
+        let index = self.scales.get(symbol).index;
+        let min_atom = index.atom_price(MicroUsd(min_micro));
+        let max_atom = index.atom_price(MicroUsd(max_micro));
         self.prices.insert(symbol.to_string(), (min_atom, max_atom));
     }
 
@@ -101,26 +335,127 @@ pub struct ExchangeAgent {
     name: String,
     markets: Vec<MarketConfig>,
     last_prices: HashMap<String, u64>,
+    /// Mark price state of the markets that configure one
+    mark_prices: HashMap<String, MarkPrice>,
+    /// Circuit breakers of the markets that configure one
+    circuit_breakers: HashMap<String, CircuitBreaker>,
 
     price_cache: PriceCache,
     actor_ids: HashMap<AgentId, ActorId>,
     symbols: HashSet<String>,
-    symbol_decimals: HashMap<String, (u32, u32)>,
+    scales: ScaleRegistry,
+    /// Market -> oracle feed of its collateral (markets with priced collateral only)
+    collateral_feeds: HashMap<String, String>,
+    /// Last (min, max) price per collateral feed, micro-USD per token
+    collateral_prices: HashMap<String, (u64, u64)>,
 
     pending_orders: PendingOrderStore,
+    next_local_order_id: OrderId,
     vara_client: Arc<VaraClient>,
     poll_interval_ns: u64,
 
     /// Receiver for on-chain transaction results from VaraClient
     tx_result_rx: Option<crossbeam_channel::Receiver<TxResult>>,
-    /// CSV writer for transaction results log
-    tx_csv_writer: Option<BufWriter<std::fs::File>>,
 
     /// Channel for async OI sync results from background RPC
     oi_sync_tx: crossbeam_channel::Sender<(i128, i128)>,
     oi_sync_rx: crossbeam_channel::Receiver<(i128, i128)>,
     /// Whether an OI fetch is currently in-flight
     oi_sync_pending: bool,
+
+    /// Interval between position/market snapshots (0 = disabled)
+    snapshot_interval_ns: u64,
+    last_snapshot_ns: u64,
+    /// Channel for async position fetches used by snapshots
+    /// Positions with the contract's liquidation price (None if it could not be queried)
+    snapshot_tx: crossbeam_channel::Sender<Result<Vec<(VaraPosition, Option<U256>)>, String>>,
+    snapshot_rx: crossbeam_channel::Receiver<Result<Vec<(VaraPosition, Option<U256>)>, String>>,
+    /// Whether a snapshot fetch is currently in-flight
+    snapshot_pending: bool,
+    /// The last snapshot fetch failed (only the first failure in a row is logged)
+    snapshot_failing: bool,
+    /// Channel for async checks of which OCO-linked on-chain orders still rest on the contract
+    oco_check_tx: crossbeam_channel::Sender<Vec<(OrderId, Result<bool, String>)>>,
+    oco_check_rx: crossbeam_channel::Receiver<Vec<(OrderId, Result<bool, String>)>>,
+    /// Whether an OCO check is currently in-flight
+    oco_check_pending: bool,
+    /// Agents that receive a PositionsSnapshot per market with every snapshot (risk monitoring)
+    snapshot_subscribers: Vec<AgentId>,
+
+    /// Market/close orders awaiting on-chain confirmation, per agent
+    inflight_executions: HashMap<AgentId, VecDeque<InflightExecution>>,
+    /// Conditional orders awaiting their on-chain id, per agent
+    inflight_submissions: HashMap<AgentId, VecDeque<OnchainOrder>>,
+    /// Recapitalization deposits (micro-USD) awaiting on-chain confirmation, per agent
+    inflight_deposits: HashMap<AgentId, VecDeque<u64>>,
+    /// Resting on-chain orders by id
+    onchain_orders: HashMap<OrderId, OnchainOrder>,
+    /// Local <-> contract ids of on-chain orders, filled from SubmitOrder/UpdateOrder results
+    order_ids: OrderIdMap,
+    /// Sim time -> chain timestamps of contract orders
+    chain_clock: ChainClock,
+    /// On-chain orders seen without a local counterpart
+    order_id_drift: u64,
+
+    /// Agents without an on-chain account: market orders are filled locally
+    local_agents: HashSet<AgentId>,
+    local_positions: HashMap<(AgentId, String, SimSide), LocalPosition>,
+    /// Local agents whose positions share collateral (portfolio margin); the rest are isolated
+    cross_margin: HashSet<AgentId>,
+
+    /// Keeper claims on pending orders (at most one keeper attempts each order)
+    order_claims: HashMap<OrderId, OrderClaim>,
+    /// Keeper executions sent to the contract as ExecuteOrder, until their tx result
+    inflight_keeper_orders: HashMap<OrderId, PendingOrder>,
+    /// Claimed orders whose execution failed on-chain and went back to the book
+    failed_claims: u64,
+    /// Keeper executions sent with the keeper's just-in-time price
+    keeper_price_updates: u64,
+    /// On-chain executions of the ExecuteOrderBatch being handled, sent together at its end
+    keeper_batch: Option<Vec<(OracleInput, PendingOrder)>>,
+    /// Batches sent to the contract and the executions in them
+    keeper_batches: u64,
+    keeper_batched_orders: u64,
+
+    /// Successful txs by tx id while a simulated reorg can still revert them, with the wall-clock
+    /// time their result was drained (empty without reorg simulation)
+    unfinalized_txs: HashMap<u64, (Instant, UnfinalizedTx)>,
+    /// Successful txs later reverted by a simulated reorg
+    reverted_txs: u64,
+
+    /// Local fill of market/close orders whose tx failed transiently (None = report the failure)
+    local_fallback: Option<LocalFallbackConfig>,
+    /// Orders filled by the fallback, waiting for their next chain attempt at the sim time given
+    unsynced_orders: Vec<(u64, AgentId, InflightExecution)>,
+    /// Orders filled by the fallback, those that later landed on chain and those given up
+    local_fallbacks: u64,
+    resynced_orders: u64,
+    abandoned_resyncs: u64,
+
+    /// Leader -> agents that receive its fills (copy trading)
+    fill_followers: HashMap<AgentId, Vec<AgentId>>,
+
+    /// Pool shares per market (the scenario's initial liquidity holds the first ones)
+    pool_shares: HashMap<String, f64>,
+    lp_shares: HashMap<(AgentId, String), f64>,
+    /// Realized pool PnL per market, booked into its liquidity
+    pool_pnl: HashMap<String, i128>,
+    /// Last OI fetched from the contract (long, short)
+    last_oi: (i128, i128),
+    /// Pre-trade limits applied to every new Increase order
+    risk_guard: RiskGuard,
+    /// Trader balances; bankrupt traders cannot open positions
+    capital: CapitalLedger,
+    /// Unrealized PnL of on-chain positions per account, from the last snapshot
+    chain_unrealized: HashMap<AgentId, i64>,
+    /// Recent fills and liquidations, answered to GetTrades
+    trades: TradeBlotter,
+    /// Where the executor state is written at the end of the run
+    state_file: Option<PathBuf>,
+    /// Taker flow per market, broadcast as MarketStats every `stats_interval_ns` (None = not broadcast)
+    flow_stats: Option<FlowStatsBook>,
+    stats_interval_ns: u64,
+    last_stats_ns: u64,
 }
 
 impl ExchangeAgent {
@@ -130,22 +465,34 @@ impl ExchangeAgent {
         markets: Vec<MarketConfig>,
         vara_client: Arc<VaraClient>,
         tx_result_rx: Option<crossbeam_channel::Receiver<TxResult>>,
-        logs_dir: Option<&str>,
+        snapshot_interval_ms: u64,
     ) -> Self {
-        let mut price_cache = PriceCache::new();
         let mut symbols = HashSet::new();
-        let mut symbol_decimals = HashMap::new();
+        let mut scales = ScaleRegistry::new();
+        let mut collateral_feeds = HashMap::new();
+        let mut collateral_prices = HashMap::new();
+        let mut mark_prices = HashMap::new();
+        let mut circuit_breakers = HashMap::new();
 
         for market_cfg in markets.iter() {
-            price_cache.set_decimals(&market_cfg.symbol, market_cfg.index_decimals);
-
             symbols.insert(market_cfg.symbol.clone());
-            symbol_decimals.insert(
-                market_cfg.symbol.clone(),
-                (market_cfg.index_decimals, market_cfg.collateral_decimals),
+            scales.register(
+                &market_cfg.symbol,
+                MarketScale::new(market_cfg.index_decimals, market_cfg.collateral_decimals),
             );
+            if let Some(cp) = &market_cfg.collateral_price {
+                let initial = (cp.initial_usd * 1_000_000.0) as u64;
+                collateral_feeds.insert(market_cfg.symbol.clone(), cp.feed.clone());
+                collateral_prices.insert(cp.feed.clone(), (initial, initial));
+            }
+            if let Some(config) = market_cfg.mark_price {
+                mark_prices.insert(market_cfg.symbol.clone(), MarkPrice::new(config));
+            }
+            if let Some(config) = market_cfg.circuit_breaker {
+                circuit_breakers.insert(market_cfg.symbol.clone(), CircuitBreaker::new(config));
+            }
 
-            println!(
+            agent_println!(
                 "[Exchange {}] Market {} ({}) initialized: liquidity=${:.0}M",
                 name,
                 market_cfg.symbol,
@@ -154,45 +501,540 @@ impl ExchangeAgent {
             );
         }
 
-        // Create CSV writer for transaction results
-        let tx_csv_writer = logs_dir.and_then(|dir| {
-            let path = format!("{}/transactions.csv", dir);
-            match std::fs::File::create(&path) {
-                Ok(file) => {
-                    let mut writer = BufWriter::new(file);
-                    let _ = writeln!(writer, "agent_id,tx_type,success,order_id,error,detail");
-                    println!("[Exchange {}] Transaction log: {}", name, path);
-                    Some(writer)
-                }
-                Err(e) => {
-                    eprintln!("[Exchange {}] Failed to create {}: {}", name, path, e);
-                    None
-                }
-            }
-        });
-
         let (oi_sync_tx, oi_sync_rx) = crossbeam_channel::unbounded();
+        let (snapshot_tx, snapshot_rx) = crossbeam_channel::unbounded();
+        let (oco_check_tx, oco_check_rx) = crossbeam_channel::unbounded();
+        let pool_shares = markets
+            .iter()
+            .map(|m| (m.symbol.clone(), m.liquidity_usd.max(0) as f64))
+            .collect();
 
         Self {
             id,
             name,
             markets,
             last_prices: HashMap::new(),
-            price_cache,
+            mark_prices,
+            circuit_breakers,
+            price_cache: PriceCache::new(scales.clone()),
             actor_ids: HashMap::new(),
             symbols,
-            symbol_decimals,
+            scales,
+            collateral_feeds,
+            collateral_prices,
             pending_orders: PendingOrderStore::new(),
+            next_local_order_id: LOCAL_ORDER_ID_BASE,
             vara_client,
             poll_interval_ns: 3_000_000_000,
             tx_result_rx,
-            tx_csv_writer,
             oi_sync_tx,
             oi_sync_rx,
             oi_sync_pending: false,
+            snapshot_interval_ns: snapshot_interval_ms * 1_000_000,
+            last_snapshot_ns: 0,
+            snapshot_tx,
+            snapshot_rx,
+            snapshot_pending: false,
+            snapshot_failing: false,
+            oco_check_tx,
+            oco_check_rx,
+            oco_check_pending: false,
+            snapshot_subscribers: Vec::new(),
+            inflight_executions: HashMap::new(),
+            inflight_submissions: HashMap::new(),
+            inflight_deposits: HashMap::new(),
+            onchain_orders: HashMap::new(),
+            order_ids: OrderIdMap::new(),
+            chain_clock: ChainClock::new(),
+            order_id_drift: 0,
+            local_agents: HashSet::new(),
+            local_positions: HashMap::new(),
+            cross_margin: HashSet::new(),
+            order_claims: HashMap::new(),
+            inflight_keeper_orders: HashMap::new(),
+            failed_claims: 0,
+            keeper_price_updates: 0,
+            keeper_batch: None,
+            keeper_batches: 0,
+            keeper_batched_orders: 0,
+            unfinalized_txs: HashMap::new(),
+            reverted_txs: 0,
+            local_fallback: None,
+            unsynced_orders: Vec::new(),
+            local_fallbacks: 0,
+            resynced_orders: 0,
+            abandoned_resyncs: 0,
+            fill_followers: HashMap::new(),
+            pool_shares,
+            lp_shares: HashMap::new(),
+            pool_pnl: HashMap::new(),
+            last_oi: (0, 0),
+            risk_guard: RiskGuard::default(),
+            capital: CapitalLedger::default(),
+            chain_unrealized: HashMap::new(),
+            trades: TradeBlotter::default(),
+            state_file: None,
+            flow_stats: None,
+            stats_interval_ns: 0,
+            last_stats_ns: 0,
+        }
+    }
+
+    /// Agents that trade locally instead of on-chain (no deposit, no signed transactions).
+    pub fn set_local_agents(&mut self, agent_ids: HashSet<AgentId>) {
+        if !agent_ids.is_empty() {
+            agent_println!("[Exchange {}] {} agent(s) trade locally", self.name, agent_ids.len());
+        }
+        self.local_agents = agent_ids;
+    }
+
+    pub fn set_cross_margin(&mut self, agent_ids: HashSet<AgentId>) {
+        if !agent_ids.is_empty() {
+            agent_println!(
+                "[Exchange {}] {} agent(s) trade cross-margin",
+                self.name,
+                agent_ids.len()
+            );
+        }
+        self.cross_margin = agent_ids;
+    }
+
+    pub fn set_risk_guard(&mut self, guard: RiskGuard) {
+        if guard.is_enabled() {
+            agent_println!("[Exchange {}] pre-trade risk limits enabled", self.name);
+        }
+        self.risk_guard = guard;
+    }
+
+    pub fn set_capital(&mut self, ledger: CapitalLedger) {
+        if ledger.is_enabled() {
+            agent_println!(
+                "[Exchange {}] tracking balances of {} trader(s)",
+                self.name,
+                ledger.accounts().len()
+            );
+        }
+        self.capital = ledger;
+    }
+
+    pub fn set_local_fallback(&mut self, config: LocalFallbackConfig) {
+        agent_println!(
+            "[Exchange {}] transiently failed orders fill locally, retried every {}ms",
+            self.name,
+            config.retry_after_ms
+        );
+        self.local_fallback = Some(config);
+    }
+
+    /// Write the executor state to `path` when the run ends.
+    pub fn set_state_file(&mut self, path: PathBuf) {
+        self.state_file = Some(path);
+    }
+
+    /// Broadcast the taker flow of every market from `book` every `broadcast_ms` (0 = never).
+    pub fn set_market_stats(&mut self, book: FlowStatsBook, broadcast_ms: u64) {
+        if broadcast_ms > 0 {
+            self.flow_stats = Some(book);
+            self.stats_interval_ns = broadcast_ms * 1_000_000;
+        }
+    }
+
+    /// Prices, pool balances, LP shares and local positions, sorted, as of `ts`.
+    pub fn state(&self, ts: u64) -> ExchangeState {
+        let mut markets: Vec<MarketState> = self
+            .symbols
+            .iter()
+            .map(|symbol| MarketState {
+                symbol: symbol.clone(),
+                last_price: self.last_prices.get(symbol).copied().unwrap_or(0),
+                pool_shares: self.pool_shares.get(symbol).copied().unwrap_or(0.0),
+                pool_pnl: self.pool_pnl.get(symbol).copied().unwrap_or(0),
+                liquidity_usd: Some(self.pool_liquidity(symbol)),
+            })
+            .collect();
+        markets.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        let mut lp_shares: Vec<LpShareState> = self
+            .lp_shares
+            .iter()
+            .map(|((agent, symbol), shares)| LpShareState {
+                agent: *agent,
+                symbol: symbol.clone(),
+                shares: *shares,
+            })
+            .collect();
+        lp_shares.sort_by(|a, b| (a.agent, &a.symbol).cmp(&(b.agent, &b.symbol)));
+        let mut local_positions: Vec<LocalPositionState> = self
+            .local_positions
+            .iter()
+            .map(|((agent, symbol, side), p)| LocalPositionState {
+                agent: *agent,
+                symbol: symbol.clone(),
+                side: *side,
+                size_usd: p.size_usd,
+                size_tokens: p.size_tokens,
+                collateral: p.collateral,
+                collateral_tokens: p.collateral_tokens,
+            })
+            .collect();
+        local_positions.sort_by(|a, b| {
+            (a.agent, &a.symbol, a.side == SimSide::Sell).cmp(&(b.agent, &b.symbol, b.side == SimSide::Sell))
+        });
+        let balances = self
+            .capital
+            .accounts()
+            .into_iter()
+            .filter(|agent| self.local_agents.contains(agent))
+            .filter_map(|agent| {
+                Some(BalanceState {
+                    agent,
+                    balance: self.capital.balance(agent)?,
+                })
+            })
+            .collect();
+        ExchangeState {
+            ts,
+            markets,
+            lp_shares,
+            local_positions,
+            balances,
         }
     }
 
+    /// Continue from the state a previous run ended with (`warm_start_from`). Call it after
+    /// `set_local_agents`, `set_risk_guard` and `set_capital`; entries of unknown markets or of
+    /// agents that no longer trade locally are skipped.
+    pub fn restore_state(&mut self, state: ExchangeState) {
+        let mut skipped = 0;
+        for market in state.markets {
+            if !self.symbols.contains(&market.symbol) {
+                skipped += 1;
+                continue;
+            }
+            if market.last_price > 0 {
+                self.last_prices.insert(market.symbol.clone(), market.last_price);
+            }
+            if let Some(liquidity_usd) = market.liquidity_usd {
+                if let Some(m) = self.markets.iter_mut().find(|m| m.symbol == market.symbol) {
+                    m.liquidity_usd = liquidity_usd;
+                }
+            }
+            self.pool_shares.insert(market.symbol.clone(), market.pool_shares);
+            self.pool_pnl.insert(market.symbol, market.pool_pnl);
+        }
+        for lp in state.lp_shares {
+            if !self.symbols.contains(&lp.symbol) {
+                skipped += 1;
+                continue;
+            }
+            self.lp_shares.insert((lp.agent, lp.symbol), lp.shares);
+        }
+        let mut positions = 0;
+        for p in state.local_positions {
+            if !self.symbols.contains(&p.symbol) || !self.local_agents.contains(&p.agent) {
+                skipped += 1;
+                continue;
+            }
+            self.risk_guard.record_increase(p.agent, &p.symbol, p.side, p.size_usd);
+            self.local_positions.insert(
+                (p.agent, p.symbol, p.side),
+                LocalPosition {
+                    size_usd: p.size_usd,
+                    size_tokens: p.size_tokens,
+                    collateral: p.collateral,
+                    collateral_tokens: p.collateral_tokens,
+                },
+            );
+            positions += 1;
+        }
+        for b in state.balances {
+            if !self.local_agents.contains(&b.agent) || !self.capital.set_balance(b.agent, b.balance) {
+                skipped += 1;
+            }
+        }
+        agent_println!(
+            "[Exchange {}] warm start from t={:.0}s: {} local positions restored, {} entries skipped",
+            self.name,
+            state.ts as f64 / 1e9,
+            positions,
+            skipped
+        );
+    }
+
+    /// Number of recent fills kept for GetTrades (default `TradeBlotter::DEFAULT_CAPACITY`).
+    pub fn set_trade_history(&mut self, capacity: usize) {
+        self.trades = TradeBlotter::new(capacity);
+    }
+
+    /// Mark tracked traders to market, then handle new bankruptcies and due top-ups.
+    fn settle_capital(&mut self, sim: &mut dyn SimulatorApi) {
+        if !self.capital.is_enabled() {
+            return;
+        }
+        let mut unrealized = self.chain_unrealized.clone();
+        for ((account, symbol, side), p) in &self.local_positions {
+            if let Some(price) = self.mark_price(symbol) {
+                *unrealized.entry(*account).or_default() +=
+                    p.pnl(*side, price) + self.local_collateral_revaluation(symbol, p);
+            }
+        }
+        for account in self.capital.accounts() {
+            let pnl = unrealized.get(&account).copied().unwrap_or(0);
+            self.capital.set_unrealized(account, pnl);
+        }
+        let now_ns = sim.now_ns();
+        for bankruptcy in self.capital.check(now_ns) {
+            self.declare_bankrupt(sim, bankruptcy);
+        }
+        for (account, amount_usd) in self.capital.due_recapitalizations(now_ns) {
+            self.recapitalize(sim, account, amount_usd);
+        }
+    }
+
+    /// Stop a bankrupt trader: close its local positions, drop the orders held for it and
+    /// remove the agent unless a top-up is scheduled.
+    fn declare_bankrupt(&mut self, sim: &mut dyn SimulatorApi, bankruptcy: Bankruptcy) {
+        let account = bankruptcy.account;
+        agent_println!(
+            "[Exchange {}] BANKRUPT account={} balance=${:.2} equity=${:.2}{}",
+            self.name,
+            account,
+            bankruptcy.balance as f64 / 1_000_000.0,
+            bankruptcy.equity as f64 / 1_000_000.0,
+            if bankruptcy.recapitalize_at_ns.is_some() {
+                " (recapitalization scheduled)"
+            } else {
+                ""
+            }
+        );
+        let mut positions: Vec<(String, SimSide)> = self
+            .local_positions
+            .keys()
+            .filter(|(owner, _, _)| *owner == account)
+            .map(|(_, symbol, side)| (symbol.clone(), *side))
+            .collect();
+        positions.sort_by_key(|(symbol, side)| (symbol.clone(), *side == SimSide::Sell));
+        for (symbol, side) in positions {
+            let close = CloseOrderPayload {
+                symbol,
+                side,
+                decision_price: None,
+            };
+            self.fill_local_close(sim, account, &close);
+        }
+        for order in self.pending_orders.remove_by_owner(account) {
+            sim.send(
+                self.id,
+                account,
+                MessageType::OrderCancelled,
+                MessagePayload::Text(format!("order_id:{},reason:bankrupt", order.id)),
+            );
+        }
+
+        let ts = sim.now_ns();
+        sim.emit_event(SimEvent::AgentBankrupt {
+            ts,
+            account,
+            balance: bankruptcy.balance,
+            equity: bankruptcy.equity,
+            recapitalize_at: bankruptcy.recapitalize_at_ns,
+        });
+        if bankruptcy.recapitalize_at_ns.is_none() {
+            sim.remove_agent(account);
+        }
+    }
+
+    /// Fund a bankrupt trader again; on-chain accounts get a real deposit, booked once its
+    /// tx result comes back.
+    fn recapitalize(&mut self, sim: &mut dyn SimulatorApi, account: AgentId, amount_usd: u64) {
+        if self.local_agents.contains(&account) {
+            self.book_recapitalization(sim, account, amount_usd);
+            return;
+        }
+        let symbol = self.markets.first().map(|m| m.symbol.clone()).unwrap_or_default();
+        let amount = self.collateral_amount(&symbol, amount_usd);
+        if let Err(e) = self.vara_client.deposit_async(account, amount) {
+            self.fail_recapitalization(sim, account, &e.to_string());
+            return;
+        }
+        self.inflight_deposits.entry(account).or_default().push_back(amount_usd);
+    }
+
+    /// A recapitalization deposit failed: the trader stays bankrupt and leaves the simulation.
+    fn fail_recapitalization(&mut self, sim: &mut dyn SimulatorApi, account: AgentId, reason: &str) {
+        eprintln!(
+            "[Exchange {}] recapitalization of {} failed: {}; removing the agent",
+            self.name, account, reason
+        );
+        sim.remove_agent(account);
+    }
+
+    /// Top up the balance of a bankrupt trader and lift its bankruptcy.
+    fn book_recapitalization(&mut self, sim: &mut dyn SimulatorApi, account: AgentId, amount_usd: u64) {
+        self.capital.recapitalize(account, amount_usd);
+        agent_println!(
+            "[Exchange {}] RECAPITALIZED account={} ${:.2}",
+            self.name,
+            account,
+            amount_usd as f64 / 1_000_000.0
+        );
+        let ts = sim.now_ns();
+        sim.emit_event(SimEvent::AgentRecapitalized {
+            ts,
+            account,
+            amount_usd,
+        });
+    }
+
+    /// Run a new Increase through the bankruptcy, circuit breaker, OI cap and risk limits checks;
+    /// a breach is rejected and reported.
+    /// `price` is the trigger of a conditional order (None = current oracle price).
+    fn pre_trade_check(
+        &mut self,
+        sim: &mut dyn SimulatorApi,
+        from: AgentId,
+        order: &MarketOrderPayload,
+        price: Option<u64>,
+    ) -> bool {
+        if self.capital.is_bankrupt(from) {
+            agent_println!("[Exchange {}] ORDER REJECTED from {}: bankrupt", self.name, from);
+            sim.send(
+                self.id,
+                from,
+                MessageType::OrderRejected,
+                MessagePayload::Text("bankrupt".to_string()),
+            );
+            return false;
+        }
+        let symbol = order.symbol.as_str();
+        if let Some(until) = self.halted_until(symbol, sim.now_ns()) {
+            agent_println!(
+                "[Exchange {}] ORDER REJECTED from {}: {} halted by circuit breaker",
+                self.name,
+                from,
+                symbol
+            );
+            sim.send(
+                self.id,
+                from,
+                MessageType::OrderRejected,
+                MessagePayload::Text(format!("circuit_breaker:halted_until:{}", until)),
+            );
+            return false;
+        }
+        let price = price.or_else(|| self.last_prices.get(symbol).copied()).unwrap_or(0);
+        let size_usd = order.qty.notional(price);
+        let now_ns = sim.now_ns();
+        if let Err(breach) = self.check_oi_caps(symbol, order.side, size_usd) {
+            let limit = breach.kind.as_str();
+            agent_println!(
+                "[Exchange {}] OI CAP from {}: {} {} {} > {}",
+                self.name,
+                from,
+                symbol,
+                limit,
+                breach.value,
+                breach.limit
+            );
+            sim.emit_event(SimEvent::RiskLimitBreached {
+                ts: now_ns,
+                account: from,
+                symbol: symbol.to_string(),
+                limit: limit.to_string(),
+                value: breach.value as f64,
+                threshold: breach.limit as f64,
+            });
+            sim.send(
+                self.id,
+                from,
+                MessageType::OrderRejected,
+                MessagePayload::Text(breach.reason()),
+            );
+            return false;
+        }
+        let leverage = order.leverage.max(1);
+        let Err(breach) = self
+            .risk_guard
+            .check_order(from, now_ns, symbol, order.side, size_usd, leverage)
+        else {
+            return true;
+        };
+        let limit = breach.kind.as_str();
+        agent_println!(
+            "[Exchange {}] RISK LIMIT from {}: {} {} {} > {}",
+            self.name,
+            from,
+            symbol,
+            limit,
+            breach.value,
+            breach.limit
+        );
+        sim.emit_event(SimEvent::RiskLimitBreached {
+            ts: now_ns,
+            account: from,
+            symbol: symbol.to_string(),
+            limit: limit.to_string(),
+            value: breach.value,
+            threshold: breach.limit,
+        });
+        sim.send(
+            self.id,
+            from,
+            MessageType::OrderRejected,
+            MessagePayload::Text(format!(
+                "risk_limit:{},value:{},limit:{}",
+                limit, breach.value, breach.limit
+            )),
+        );
+        false
+    }
+
+    /// Check an increase of `size_usd` against the OI caps of `symbol`.
+    fn check_oi_caps(&self, symbol: &str, side: SimSide, size_usd: u64) -> Result<(), OiCapBreach> {
+        let Some(market) = self.markets.iter().find(|m| m.symbol == symbol) else {
+            return Ok(());
+        };
+        if !market.oi_caps.is_enabled() {
+            return Ok(());
+        }
+        let (long, short) = self.open_interest(symbol);
+        let liquidity = self.pool_liquidity(symbol).max(0) as u64;
+        market
+            .oi_caps
+            .check(long.max(0) as u64, short.max(0) as u64, liquidity, side, size_usd)
+    }
+
+    /// Check an order price against the tick size and price band of `symbol`.
+    fn check_price_rules(&self, symbol: &str, price: u64) -> Result<(), PriceViolation> {
+        let Some(market) = self.markets.iter().find(|m| m.symbol == symbol) else {
+            return Ok(());
+        };
+        market.price_rules.check(price, self.last_prices.get(symbol).copied())
+    }
+
+    /// Run the trigger and acceptable price of a new order through the price rules; a violation is rejected.
+    fn price_rules_check(&self, sim: &mut dyn SimulatorApi, from: AgentId, order: &OrderPayload) -> bool {
+        for price in [order.trigger_price, order.acceptable_price].into_iter().flatten() {
+            if let Err(violation) = self.check_price_rules(&order.symbol, price) {
+                agent_println!(
+                    "[Exchange {}] ORDER REJECTED from {}: {} {} ({})",
+                    self.name,
+                    from,
+                    order.symbol,
+                    violation.rule(),
+                    price
+                );
+                sim.send(
+                    self.id,
+                    from,
+                    MessageType::OrderRejected,
+                    MessagePayload::Text(violation.reason()),
+                );
+                return false;
+            }
+        }
+        true
+    }
+
     fn get_or_create_actor(&mut self, agent_id: AgentId) -> Option<ActorId> {
         if let Some(actor) = self.actor_ids.get(&agent_id) {
             return Some(*actor);
@@ -216,18 +1058,52 @@ impl ExchangeAgent {
         }
     }
 
+    fn convert_side_from_vara(side: &VaraSide) -> SimSide {
+        match side {
+            VaraSide::Long => SimSide::Buy,
+            VaraSide::Short => SimSide::Sell,
+        }
+    }
+
     fn build_oracle_input(&self, symbol: &str) -> Option<OracleInput> {
         let (min, max) = self.price_cache.get(symbol)?;
-        let collateral_price = U256::exp10(24); // USDC $1 with 6 decimals
+        let collateral = self.scales.get(symbol).collateral;
+        let (collateral_min, collateral_max) = self
+            .collateral_price(symbol)
+            .unwrap_or((PEGGED_COLLATERAL_MICRO, PEGGED_COLLATERAL_MICRO));
         let prices = OraclePrices {
             index_price_min: u256_to_sails(min),
             index_price_max: u256_to_sails(max),
-            collateral_price_min: u256_to_sails(collateral_price),
-            collateral_price_max: u256_to_sails(collateral_price),
+            collateral_price_min: u256_to_sails(collateral.atom_price(MicroUsd(collateral_min))),
+            collateral_price_max: u256_to_sails(collateral.atom_price(MicroUsd(collateral_max))),
         };
         Some(OracleInput::DevPrices(prices))
     }
 
+    /// OracleInput from a keeper's just-in-time price: staleness is checked against the wall
+    /// clock now, right before the transaction is sent.
+    fn build_keeper_oracle_input(&self, update: &PriceUpdatePayload) -> Result<OracleInput, OracleInputError> {
+        let scale = self.scales.get(&update.symbol);
+        let builder = OracleInputBuilder::new(scale.index.decimals, scale.collateral.decimals)
+            .max_age_secs(update.max_age_secs)
+            .with_confidence(true);
+        let data = SignedPriceData {
+            symbol: update.symbol.clone(),
+            price_usd_micro: update.price_usd_micro,
+            confidence: update.confidence,
+            ema_price: None,
+            publish_time: update.publish_time,
+            signature: update.vaa.clone(),
+            provider_name: "keeper".to_string(),
+        };
+        let now_secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let (min, max) = builder.bounds(&data, now_secs)?;
+        let (collateral_min, collateral_max) = self
+            .collateral_price(&update.symbol)
+            .unwrap_or((PEGGED_COLLATERAL_MICRO, PEGGED_COLLATERAL_MICRO));
+        Ok(OracleInput::DevPrices(builder.prices(min, max, collateral_min, collateral_max)))
+    }
+
     /// Drain all pending transaction results from the channel.
     /// Logs each to CSV and sends failure notifications back to agents.
     fn drain_tx_results(&mut self, sim: &mut dyn SimulatorApi) {
@@ -236,39 +1112,1163 @@ impl ExchangeAgent {
             None => return,
         };
 
-        while let Ok(result) = rx.try_recv() {
-            // Log to CSV
-            if let Some(writer) = &mut self.tx_csv_writer {
-                let oid = result.order_id.map(|id| id.to_string()).unwrap_or_default();
-                let err = result.error.as_deref().unwrap_or("");
-                let _ = writeln!(
-                    writer,
-                    "{},{},{},{},{},\"{}\"",
-                    result.agent_id, result.tx_type, result.success, oid, err, result.detail
-                );
-                let _ = writer.flush();
-            }
+        let mut results = Vec::new();
+        while let Ok(result) = rx.try_recv() {
+            results.push(result);
+        }
+
+        for result in results {
+            // Agents know on-chain orders by their local id
+            let mut order_id = result.order_id;
+            sim.emit_event(SimEvent::ChainTx {
+                ts: sim.now_ns(),
+                agent: result.agent_id,
+                tx_type: result.tx_type.to_string(),
+                success: result.success,
+                order_id: result.order_id,
+                latency_ms: result.latency_ms,
+                error: result.error.clone(),
+                tip: result.tip,
+                fee: result.fee,
+                reverted: result.reverted,
+            });
+
+            if result.reverted {
+                self.revert_tx(sim, &result);
+                continue;
+            }
+
+            match result.tx_type {
+                TxType::SubmitAndExecute => {
+                    if !result.success && self.fall_back_locally(sim, &result) {
+                        continue;
+                    }
+                    if let Some(exec) = self.confirm_execution(sim, result.agent_id, result.success) {
+                        let agent = result.agent_id;
+                        self.track_unfinalized(result.tx_id, UnfinalizedTx::Execution { agent, exec });
+                    }
+                }
+                TxType::SubmitOrder | TxType::UpdateOrder => {
+                    order_id = self.track_onchain_order(&result);
+                    if let (true, Some(chain_id)) = (result.success, result.order_id) {
+                        if self.onchain_orders.contains_key(&chain_id) {
+                            self.track_unfinalized(result.tx_id, UnfinalizedTx::Submission { order_id: chain_id });
+                        }
+                    }
+                }
+                TxType::Deposit => {
+                    let amount_usd = self
+                        .inflight_deposits
+                        .get_mut(&result.agent_id)
+                        .and_then(|q| q.pop_front());
+                    match (result.success, amount_usd) {
+                        (true, Some(amount_usd)) => self.book_recapitalization(sim, result.agent_id, amount_usd),
+                        (false, Some(_)) => {
+                            let reason = result.error.as_deref().unwrap_or("unknown");
+                            self.fail_recapitalization(sim, result.agent_id, reason);
+                        }
+                        _ => {}
+                    }
+                    // Not an order: nothing to reject to the agent
+                    continue;
+                }
+                TxType::ExecuteOrder => {
+                    let order = result.order_id.and_then(|id| self.inflight_keeper_orders.remove(&id));
+                    match (result.success, order) {
+                        (false, Some(order)) => self.release_failed_claim(order),
+                        (true, Some(order)) => {
+                            self.complete_keeper_execution(sim, result.agent_id, &order);
+                            self.track_unfinalized(result.tx_id, UnfinalizedTx::KeeperExecution { order })
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+
+            // Notify agent on failure
+            if !result.success {
+                let reason = result.error.as_deref().unwrap_or("unknown");
+                agent_println!(
+                    "[Exchange {}] TX FAILED: agent={} {} — {}",
+                    self.name,
+                    result.agent_id,
+                    result.tx_type,
+                    reason
+                );
+                sim.send(
+                    self.id,
+                    result.agent_id,
+                    MessageType::OrderRejected,
+                    MessagePayload::Text(format!(
+                        "tx_type:{},order_id:{},error:{}",
+                        result.tx_type,
+                        order_id.map_or(0, |id| self.order_ids.local_id(id).unwrap_or(id)),
+                        reason
+                    )),
+                );
+            }
+        }
+
+        // A revert is queued at most one window after the result was drained
+        if let Some(window_ms) = self.vara_client.reorg_window_ms() {
+            self.unfinalized_txs
+                .retain(|_, (drained, _)| drained.elapsed().as_millis() as u64 <= 2 * window_ms);
+        }
+    }
+
+    /// Keep what a successful tx booked until a simulated reorg can no longer revert it.
+    fn track_unfinalized(&mut self, tx_id: u64, tx: UnfinalizedTx) {
+        if self.vara_client.reorg_window_ms().is_some() {
+            self.unfinalized_txs.insert(tx_id, (Instant::now(), tx));
+        }
+    }
+
+    /// A simulated reorg dropped a transaction that had succeeded: undo what its result booked
+    /// and reject it to the agent like a failed transaction.
+    fn revert_tx(&mut self, sim: &mut dyn SimulatorApi, result: &TxResult) {
+        self.reverted_txs += 1;
+        let order_id = match self.unfinalized_txs.remove(&result.tx_id).map(|(_, tx)| tx) {
+            Some(UnfinalizedTx::Execution { agent, exec }) => {
+                self.unbook_execution(sim, agent, exec, "reorg_revert");
+                None
+            }
+            Some(UnfinalizedTx::Submission { order_id }) => {
+                self.onchain_orders.remove(&order_id);
+                self.order_ids.remove_chain(order_id).or(Some(order_id))
+            }
+            Some(UnfinalizedTx::KeeperExecution { order }) => {
+                let order_id = order.id;
+                self.release_failed_claim(order);
+                Some(order_id)
+            }
+            // CancelOrder: the order rests on the contract again, nothing was booked for it
+            None => result.order_id.map(|id| self.order_ids.local_id(id).unwrap_or(id)),
+        };
+        let reason = result.error.as_deref().unwrap_or("reverted");
+        eprintln!(
+            "[Exchange {}] TX REVERTED: agent={} {} tx #{} — {}",
+            self.name, result.agent_id, result.tx_type, result.tx_id, reason
+        );
+        sim.send(
+            self.id,
+            result.agent_id,
+            MessageType::OrderRejected,
+            MessagePayload::Text(format!(
+                "tx_type:{},order_id:{},error:{}",
+                result.tx_type,
+                order_id.unwrap_or(0),
+                reason
+            )),
+        );
+    }
+
+    /// Reverse the fee, pool PnL, capital and risk exposure booked by `confirm_execution` and
+    /// return a triggered order to the book. A reverted close keeps the exposure it released.
+    fn unbook_execution(
+        &mut self,
+        sim: &mut dyn SimulatorApi,
+        agent: AgentId,
+        exec: InflightExecution,
+        reason: &'static str,
+    ) {
+        let (realized, is_increase) = match exec.order_type {
+            "Increase" => (0, true),
+            _ => (exec.pnl, false),
+        };
+        self.book_pool_pnl(sim, &exec.symbol, exec.pnl as i128 - exec.fee_usd as i128, reason);
+        self.capital.book(agent, exec.fee_usd as i64 - realized);
+        if is_increase {
+            self.risk_guard
+                .revert_increase(agent, &exec.symbol, exec.side, exec.size_usd);
+        }
+        if let Some((_, order)) = exec.triggered_order {
+            self.release_failed_claim(order);
+        }
+        agent_println!(
+            "[Exchange {}] REVERTED {} {} of agent {} size=${:.2}",
+            self.name,
+            exec.order_type,
+            exec.symbol,
+            agent,
+            exec.size_usd as f64 / 1_000_000.0
+        );
+    }
+
+    /// Record the id of a conditional order accepted by the contract (expiry and OCO sweeps)
+    /// and link it to the local id its owner knows. Returns that local id.
+    fn track_onchain_order(&mut self, result: &TxResult) -> Option<OrderId> {
+        let Some(order) = self
+            .inflight_submissions
+            .get_mut(&result.agent_id)
+            .and_then(|q| q.pop_front())
+        else {
+            if let (true, Some(order_id)) = (result.success, result.order_id) {
+                self.warn_order_drift(order_id, result.agent_id);
+            }
+            return result.order_id;
+        };
+        let local_id = order.local_id;
+        let (true, Some(order_id)) = (result.success, result.order_id) else {
+            return Some(local_id);
+        };
+        if order.cancel_requested {
+            agent_println!(
+                "[Exchange {}] CANCEL #{} (on-chain #{}) from={} after confirmation",
+                self.name,
+                local_id,
+                order_id,
+                order.owner
+            );
+            if let Err(e) = self.vara_client.cancel_order(order.owner, VaraOrderId(order_id)) {
+                eprintln!("[Exchange {}] CancelOrder #{} failed: {}", self.name, order_id, e);
+            }
+        } else {
+            self.order_ids.link(local_id, order_id);
+            self.onchain_orders.insert(order_id, order);
+        }
+        Some(local_id)
+    }
+
+    /// An on-chain order without a local counterpart: its owner can neither cancel nor match it.
+    fn warn_order_drift(&mut self, order_id: OrderId, owner: AgentId) {
+        self.order_id_drift += 1;
+        eprintln!(
+            "[Exchange {}] WARNING: on-chain order #{} of {} has no local counterpart ({} so far)",
+            self.name, order_id, owner, self.order_id_drift
+        );
+    }
+
+    /// Pop the oldest in-flight market/close order of `agent_id` and, if the
+    /// transaction succeeded, emit the authoritative OrderExecuted event. Returns the
+    /// confirmed execution while simulated reorgs may still revert it.
+    fn confirm_execution(
+        &mut self,
+        sim: &mut dyn SimulatorApi,
+        agent_id: AgentId,
+        success: bool,
+    ) -> Option<InflightExecution> {
+        let mut exec = self
+            .inflight_executions
+            .get_mut(&agent_id)
+            .and_then(|q| q.pop_front())?;
+        if !success {
+            if let Some((_, order)) = exec.triggered_order.take() {
+                self.release_failed_claim(order);
+            }
+            return None;
+        }
+        if exec.unsynced.is_some() {
+            self.resynced_orders += 1;
+            agent_println!(
+                "[Exchange {}] RESYNCED {} {} of agent {}",
+                self.name,
+                exec.order_type,
+                exec.symbol,
+                agent_id
+            );
+            return None;
+        }
+        let triggered = exec.triggered_order.clone();
+        let confirmed = self.book_execution(sim, agent_id, exec);
+        if let Some((keeper_id, order)) = triggered {
+            self.complete_keeper_execution(sim, keeper_id, &order);
+        }
+        confirmed
+    }
+
+    /// Book a filled market/close order: followers, fees, risk, capital, blotter and event.
+    /// Returns a copy to keep while a simulated reorg may still revert it.
+    fn book_execution(
+        &mut self,
+        sim: &mut dyn SimulatorApi,
+        agent_id: AgentId,
+        exec: InflightExecution,
+    ) -> Option<InflightExecution> {
+        let confirmed = self.vara_client.reorg_window_ms().map(|_| exec.clone());
+        let order_type = if exec.order_type == "Increase" {
+            OrderExecutionType::Increase
+        } else {
+            OrderExecutionType::Decrease
+        };
+        self.notify_followers(
+            sim,
+            agent_id,
+            LeaderFillPayload {
+                leader: agent_id,
+                symbol: exec.symbol.clone(),
+                side: exec.side,
+                order_type,
+                size_usd: exec.size_usd,
+                execution_price: exec.execution_price,
+                leverage: exec.leverage,
+            },
+        );
+        self.book_fee(sim, agent_id, &exec.symbol, "position_fee", exec.fee_usd);
+        self.book_pool_pnl(sim, &exec.symbol, -(exec.pnl as i128), "trader_pnl");
+        let ts = sim.now_ns();
+        match order_type {
+            OrderExecutionType::Increase => {
+                self.risk_guard
+                    .record_increase(agent_id, &exec.symbol, exec.side, exec.size_usd)
+            }
+            _ => self
+                .risk_guard
+                .record_close(agent_id, ts, &exec.symbol, exec.side, exec.pnl - exec.fee_usd as i64),
+        }
+        let realized = match order_type {
+            OrderExecutionType::Increase => 0,
+            _ => exec.pnl,
+        };
+        self.capital.book(agent_id, realized - exec.fee_usd as i64);
+        self.trades.record(TradeRecord {
+            ts,
+            account: agent_id,
+            symbol: exec.symbol.clone(),
+            side: exec.side,
+            order_type,
+            price: exec.execution_price,
+            size_usd: exec.size_usd,
+            fee_usd: exec.fee_usd,
+            pnl: realized,
+        });
+        sim.emit_event(SimEvent::OrderExecuted {
+            ts,
+            account: agent_id,
+            symbol: exec.symbol,
+            side: exec.side,
+            size_usd: exec.size_usd,
+            collateral: exec.collateral,
+            execution_price: exec.execution_price,
+            leverage: exec.leverage,
+            order_type: exec.order_type.to_string(),
+            pnl: exec.pnl,
+            fee_usd: exec.fee_usd,
+            funding_fee_usd: 0, // not reported by the contract
+            decision_price: exec.decision_price,
+        });
+        confirmed
+    }
+
+    /// Forward an executed order of `leader` to its subscribers.
+    fn notify_followers(&self, sim: &mut dyn SimulatorApi, leader: AgentId, fill: LeaderFillPayload) {
+        for follower in self.fill_followers.get(&leader).into_iter().flatten() {
+            sim.send(
+                self.id,
+                *follower,
+                MessageType::LeaderFill,
+                MessagePayload::LeaderFill(fill.clone()),
+            );
+        }
+    }
+
+    fn track_execution(&mut self, agent_id: AgentId, exec: InflightExecution) {
+        self.inflight_executions.entry(agent_id).or_default().push_back(exec);
+    }
+
+    /// Take over a failed SubmitAndExecute the local fallback covers: a transient failure of a
+    /// new order books its fill locally, and an unsynced order goes back to the retry queue.
+    /// False leaves the failure to `confirm_execution` and the agent's OrderRejected.
+    fn fall_back_locally(&mut self, sim: &mut dyn SimulatorApi, result: &TxResult) -> bool {
+        let Some(config) = self.local_fallback else {
+            return false;
+        };
+        let agent_id = result.agent_id;
+        // With an order id the order reached the contract; sending it again would duplicate it
+        let transient = result.order_id.is_none() && result.error.as_deref().is_some_and(is_transient_tx_error);
+        let covered = self
+            .inflight_executions
+            .get(&agent_id)
+            .and_then(|q| q.front())
+            .is_some_and(|exec| {
+                exec.unsynced.is_some() || (transient && exec.chain_order.is_some() && exec.triggered_order.is_none())
+            });
+        let Some(mut exec) = covered
+            .then(|| self.inflight_executions.get_mut(&agent_id).and_then(|q| q.pop_front()))
+            .flatten()
+        else {
+            return false;
+        };
+        let reason = result.error.as_deref().unwrap_or("unknown");
+        match exec.unsynced {
+            None => {
+                self.book_execution(sim, agent_id, exec.clone());
+                exec.unsynced = Some(0);
+                self.local_fallbacks += 1;
+                agent_println!(
+                    "[Exchange {}] LOCAL FILL {} {} of agent {} after tx failure ({}), unsynced",
+                    self.name,
+                    exec.order_type,
+                    exec.symbol,
+                    agent_id,
+                    reason
+                );
+            }
+            Some(attempts) if !transient || attempts >= config.max_retries => {
+                self.abandoned_resyncs += 1;
+                eprintln!(
+                    "[Exchange {}] gave up syncing {} {} of agent {} after {} attempts ({}): local fill reversed",
+                    self.name, exec.order_type, exec.symbol, agent_id, attempts, reason
+                );
+                self.abandon_local_fill(sim, agent_id, exec, attempts, reason);
+                return true;
+            }
+            Some(_) => {}
+        }
+        let retry_at = sim.now_ns() + config.retry_after_ms * 1_000_000;
+        self.unsynced_orders.push((retry_at, agent_id, exec));
+        true
+    }
+
+    /// Undo the local fill of an order the chain never took, like a reorg revert: the agent gets
+    /// OrderRejected, the fill leaves the blotter and a LocalFillAbandoned event records it.
+    fn abandon_local_fill(
+        &mut self,
+        sim: &mut dyn SimulatorApi,
+        agent_id: AgentId,
+        exec: InflightExecution,
+        attempts: u32,
+        reason: &str,
+    ) {
+        self.trades.withdraw(|t| {
+            t.account == agent_id
+                && t.symbol == exec.symbol
+                && t.side == exec.side
+                && t.size_usd == exec.size_usd
+                && t.price == exec.execution_price
+        });
+        let event = SimEvent::LocalFillAbandoned {
+            ts: sim.now_ns(),
+            account: agent_id,
+            symbol: exec.symbol.clone(),
+            side: exec.side,
+            order_type: exec.order_type.to_string(),
+            size_usd: exec.size_usd,
+            attempts,
+            reason: reason.to_string(),
+        };
+        self.unbook_execution(sim, agent_id, exec, "unsynced_revert");
+        sim.emit_event(event);
+        sim.send(
+            self.id,
+            agent_id,
+            MessageType::OrderRejected,
+            MessagePayload::Text(format!(
+                "tx_type:{},order_id:0,error:unsynced: {}",
+                TxType::SubmitAndExecute,
+                reason
+            )),
+        );
+    }
+
+    /// Send the orders filled by the local fallback to the chain again once their retry is due.
+    fn retry_unsynced(&mut self, now_ns: u64) {
+        let Some(config) = self.local_fallback else {
+            return;
+        };
+        let (due, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.unsynced_orders)
+            .into_iter()
+            .partition(|(retry_at, ..)| *retry_at <= now_ns);
+        self.unsynced_orders = waiting;
+        for (_, agent_id, mut exec) in due {
+            let retry_at = now_ns + config.retry_after_ms * 1_000_000;
+            let oracle_input = self.build_oracle_input(&exec.symbol);
+            let (Some(mut order), Some(oracle_input)) = (exec.chain_order.clone(), oracle_input) else {
+                self.unsynced_orders.push((retry_at, agent_id, exec));
+                continue;
+            };
+            let (now_sec, valid_until) = self.chain_validity(now_ns, 3600);
+            order.created_at = now_sec;
+            order.valid_from = now_sec;
+            order.valid_until = valid_until;
+            exec.unsynced = exec.unsynced.map(|attempts| attempts + 1);
+            match self.vara_client.submit_and_execute_order_async(agent_id, order, oracle_input) {
+                Ok(()) => self.track_execution(agent_id, exec),
+                Err(e) => {
+                    eprintln!(
+                        "[Exchange {}] resync of {} {} from={} not sent: {}",
+                        self.name, exec.order_type, exec.symbol, agent_id, e
+                    );
+                    self.unsynced_orders.push((retry_at, agent_id, exec));
+                }
+            }
+        }
+    }
+
+    /// Fill a market order of a local agent immediately at the oracle price plus the fill spread.
+    fn fill_local_increase(&mut self, sim: &mut dyn SimulatorApi, from: AgentId, order: &MarketOrderPayload) {
+        let price = self.fill_price(&order.symbol, order.side == SimSide::Buy);
+        let size_usd = order.qty.notional(price);
+        let leverage = order.leverage.max(1);
+        let collateral = size_usd / leverage as u64;
+        let fee_usd = self.position_fee(&order.symbol, size_usd);
+        self.book_fee(sim, from, &order.symbol, "position_fee", fee_usd);
+        let collateral_price = self.collateral_price_mid(&order.symbol);
+
+        let position = self
+            .local_positions
+            .entry((from, order.symbol.clone(), order.side))
+            .or_default();
+        position.size_usd += size_usd;
+        position.size_tokens += order.qty.tokens();
+        position.collateral += collateral;
+        position.collateral_tokens += collateral as f64 / collateral_price as f64;
+        self.risk_guard
+            .record_increase(from, &order.symbol, order.side, size_usd);
+        self.capital.book(from, -(fee_usd as i64));
+
+        agent_println!(
+            "[Exchange {}] LOCAL MARKET {} from={} side={:?} size=${:.2} leverage={}x",
+            self.name,
+            order.symbol,
+            from,
+            order.side,
+            size_usd as f64 / 1_000_000.0,
+            leverage
+        );
+        let ts = sim.now_ns();
+        self.trades.record(TradeRecord {
+            ts,
+            account: from,
+            symbol: order.symbol.clone(),
+            side: order.side,
+            order_type: OrderExecutionType::Increase,
+            price,
+            size_usd,
+            fee_usd,
+            pnl: 0,
+        });
+        sim.emit_event(SimEvent::OrderExecuted {
+            ts,
+            account: from,
+            symbol: order.symbol.clone(),
+            side: order.side,
+            size_usd,
+            collateral,
+            execution_price: price,
+            leverage,
+            order_type: "Increase".to_string(),
+            pnl: 0,
+            fee_usd,
+            funding_fee_usd: 0,
+            decision_price: order.decision_price,
+        });
+        sim.send(
+            self.id,
+            from,
+            MessageType::OrderExecuted,
+            MessagePayload::OrderExecuted(OrderExecutedPayload {
+                symbol: order.symbol.clone(),
+                side: order.side,
+                order_type: OrderExecutionType::Increase,
+                collateral_delta: collateral as i128,
+                pnl: 0,
+                size_usd: size_usd as i128,
+            }),
+        );
+        self.notify_followers(
+            sim,
+            from,
+            LeaderFillPayload {
+                leader: from,
+                symbol: order.symbol.clone(),
+                side: order.side,
+                order_type: OrderExecutionType::Increase,
+                size_usd,
+                execution_price: price,
+                leverage,
+            },
+        );
+    }
+
+    /// Close the whole local position of an agent at the oracle price minus the fill spread.
+    fn fill_local_close(&mut self, sim: &mut dyn SimulatorApi, from: AgentId, order: &CloseOrderPayload) {
+        let Some(position) = self.local_positions.remove(&(from, order.symbol.clone(), order.side)) else {
+            agent_println!(
+                "[Exchange {}] CLOSE REJECTED from {}: no {:?} position for {}",
+                self.name,
+                from,
+                order.side,
+                order.symbol
+            );
+            return;
+        };
+        // Closing a long sells, closing a short buys
+        let price = self.fill_price(&order.symbol, order.side == SimSide::Sell);
+        let pnl = position.pnl(order.side, price);
+        let fee_usd = self.position_fee(&order.symbol, position.size_usd);
+        self.book_fee(sim, from, &order.symbol, "position_fee", fee_usd);
+        self.book_pool_pnl(sim, &order.symbol, -(pnl as i128), "trader_pnl");
+        // Collateral comes back at its current price
+        let revaluation = self.local_collateral_revaluation(&order.symbol, &position);
+
+        agent_println!(
+            "[Exchange {}] LOCAL CLOSE {} from={} side={:?} pnl=${:.2}",
+            self.name,
+            order.symbol,
+            from,
+            order.side,
+            pnl as f64 / 1_000_000.0
+        );
+        let ts = sim.now_ns();
+        self.risk_guard
+            .record_close(from, ts, &order.symbol, order.side, pnl - fee_usd as i64);
+        self.capital.book(from, pnl + revaluation - fee_usd as i64);
+        self.trades.record(TradeRecord {
+            ts,
+            account: from,
+            symbol: order.symbol.clone(),
+            side: order.side,
+            order_type: OrderExecutionType::Decrease,
+            price,
+            size_usd: position.size_usd,
+            fee_usd,
+            pnl,
+        });
+        sim.emit_event(SimEvent::OrderExecuted {
+            ts,
+            account: from,
+            symbol: order.symbol.clone(),
+            side: order.side,
+            size_usd: position.size_usd,
+            collateral: position.collateral,
+            execution_price: price,
+            leverage: if position.collateral > 0 {
+                (position.size_usd / position.collateral) as u32
+            } else {
+                0
+            },
+            order_type: "Decrease".to_string(),
+            pnl,
+            fee_usd,
+            funding_fee_usd: 0,
+            decision_price: order.decision_price,
+        });
+        sim.send(
+            self.id,
+            from,
+            MessageType::OrderExecuted,
+            MessagePayload::OrderExecuted(OrderExecutedPayload {
+                symbol: order.symbol.clone(),
+                side: order.side,
+                order_type: OrderExecutionType::Decrease,
+                collateral_delta: -(position.collateral as i128 + revaluation as i128),
+                pnl: pnl as i128,
+                size_usd: position.size_usd as i128,
+            }),
+        );
+        self.notify_followers(
+            sim,
+            from,
+            LeaderFillPayload {
+                leader: from,
+                symbol: order.symbol.clone(),
+                side: order.side,
+                order_type: OrderExecutionType::Decrease,
+                size_usd: position.size_usd,
+                execution_price: price,
+                leverage: 0,
+            },
+        );
+    }
+
+    /// Liquidate local positions on `symbol` below the maintenance margin at the mark price;
+    /// their collateral is lost. Positions of cross-margin accounts are checked as a portfolio instead.
+    fn liquidate_local_positions(&mut self, sim: &mut dyn SimulatorApi, symbol: &str) {
+        let Some(price) = self.mark_price(symbol) else {
+            return;
+        };
+        let now_ns = sim.now_ns();
+        if let Some(breaker) = self.circuit_breakers.get(symbol) {
+            if !breaker.allows_liquidations(now_ns) {
+                return;
+            }
+        }
+        let mut underwater: Vec<(AgentId, String, SimSide)> = self
+            .local_positions
+            .iter()
+            .filter(|((account, s, side), p)| {
+                s == symbol
+                    && !self.cross_margin.contains(account)
+                    && self.local_equity(s, *side, p) as f64 <= p.size_usd as f64 * LOCAL_MAINTENANCE_MARGIN
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        underwater.sort_by_key(|(account, _, side)| (*account, *side == SimSide::Sell));
+
+        for key in underwater {
+            self.liquidate_local_position(sim, key, price);
+        }
+        self.liquidate_cross_margin(sim, symbol);
+    }
+
+    /// Equity of a local position: collateral at its current price plus unrealized PnL at the mark.
+    fn local_equity(&self, symbol: &str, side: SimSide, position: &LocalPosition) -> i64 {
+        let pnl = self.mark_price(symbol).map_or(0, |price| position.pnl(side, price));
+        position.collateral as i64 + self.local_collateral_revaluation(symbol, position) + pnl
+    }
+
+    /// Cross-margin accounts holding `symbol` share collateral across their local positions:
+    /// while the portfolio equity is at or below the summed maintenance margin, the position
+    /// with the lowest equity is liquidated first. A loss beyond that position's collateral is
+    /// taken from the collateral of the remaining ones.
+    fn liquidate_cross_margin(&mut self, sim: &mut dyn SimulatorApi, symbol: &str) {
+        let mut accounts: Vec<AgentId> = self
+            .local_positions
+            .keys()
+            .filter(|(account, s, _)| s == symbol && self.cross_margin.contains(account))
+            .map(|(account, _, _)| *account)
+            .collect();
+        accounts.sort();
+        accounts.dedup();
+
+        for account in accounts {
+            loop {
+                let positions: Vec<((AgentId, String, SimSide), i64, u64)> = self
+                    .local_positions
+                    .iter()
+                    .filter(|((a, _, _), _)| *a == account)
+                    .map(|(key, p)| (key.clone(), self.local_equity(&key.1, key.2, p), p.size_usd))
+                    .collect();
+                let equity: i64 = positions.iter().map(|(_, e, _)| e).sum();
+                let maintenance: f64 = positions
+                    .iter()
+                    .map(|(_, _, size)| *size as f64 * LOCAL_MAINTENANCE_MARGIN)
+                    .sum();
+                if equity as f64 > maintenance {
+                    break;
+                }
+                // Lowest equity first; ties by symbol, longs before shorts
+                let Some((key, _, _)) = positions
+                    .into_iter()
+                    .min_by_key(|((_, s, side), e, _)| (*e, s.clone(), *side == SimSide::Sell))
+                else {
+                    break;
+                };
+                let price = self.mark_price(&key.1).unwrap_or(0);
+                let shortfall = self.liquidate_local_position(sim, key, price);
+                if shortfall > 0 {
+                    self.charge_cross_collateral(sim, account, shortfall);
+                }
+            }
+        }
+    }
+
+    /// Take `amount` (micro-USD) from the collateral of the account's local positions, pro rata.
+    fn charge_cross_collateral(&mut self, sim: &mut dyn SimulatorApi, account: AgentId, amount: u64) {
+        let total: u64 = self
+            .local_positions
+            .iter()
+            .filter(|((a, _, _), _)| *a == account)
+            .map(|(_, p)| p.collateral)
+            .sum();
+        if total == 0 {
+            return;
+        }
+        let amount = amount.min(total);
+        let mut charged: Vec<(String, u64)> = Vec::new();
+        for ((a, symbol, _), p) in self.local_positions.iter_mut() {
+            if *a != account || p.collateral == 0 {
+                continue;
+            }
+            let share = (amount as u128 * p.collateral as u128 / total as u128) as u64;
+            p.collateral_tokens *= 1.0 - share as f64 / p.collateral as f64;
+            p.collateral -= share;
+            charged.push((symbol.clone(), share));
+        }
+        let mut charged_total = 0;
+        for (symbol, share) in charged {
+            charged_total += share as i64;
+            self.book_pool_pnl(sim, &symbol, share as i128, "cross_margin");
+        }
+        agent_println!(
+            "[Exchange {}] CROSS MARGIN account={} charged ${:.2} of shared collateral",
+            self.name,
+            account,
+            charged_total as f64 / 1_000_000.0
+        );
+        self.capital.book(account, -charged_total);
+    }
+
+    /// Liquidate one local position at `price`; its collateral goes to the pool. Returns the
+    /// loss beyond the collateral (micro-USD), which a cross-margin account still owes.
+    fn liquidate_local_position(
+        &mut self,
+        sim: &mut dyn SimulatorApi,
+        key: (AgentId, String, SimSide),
+        price: u64,
+    ) -> u64 {
+        let Some(position) = self.local_positions.remove(&key) else {
+            return 0;
+        };
+        let (account, symbol, side) = key;
+        let pnl = position.pnl(side, price);
+        // The trader's collateral goes to the pool, at its current value
+        let revaluation = self.local_collateral_revaluation(&symbol, &position);
+        let seized = (position.collateral as i128 + revaluation as i128).max(0) as u64;
+        self.book_fee(sim, account, &symbol, "liquidation", seized);
+        agent_println!(
+            "[Exchange {}] LOCAL LIQUIDATION {} account={} side={:?} size=${:.2} pnl=${:.2}",
+            self.name,
+            symbol,
+            account,
+            side,
+            position.size_usd as f64 / 1_000_000.0,
+            pnl as f64 / 1_000_000.0
+        );
+        let ts = sim.now_ns();
+        // The account loses what the pool seized, like a close books the revalued collateral
+        self.risk_guard
+            .record_close(account, ts, &symbol, side, -(seized as i64));
+        self.capital.book(account, -(seized as i64));
+        self.trades.record(TradeRecord {
+            ts,
+            account,
+            symbol: symbol.clone(),
+            side,
+            order_type: OrderExecutionType::Liquidation,
+            price,
+            size_usd: position.size_usd,
+            fee_usd: 0,
+            pnl,
+        });
+        sim.emit_event(SimEvent::PositionLiquidated {
+            ts,
+            account,
+            symbol: symbol.clone(),
+            side,
+            size_usd: position.size_usd,
+            collateral_lost: position.collateral,
+            pnl,
+            liquidation_price: price,
+        });
+        self.notify_followers(
+            sim,
+            account,
+            LeaderFillPayload {
+                leader: account,
+                symbol: symbol.clone(),
+                side,
+                order_type: OrderExecutionType::Liquidation,
+                size_usd: position.size_usd,
+                execution_price: price,
+                leverage: 0,
+            },
+        );
+        sim.send(
+            self.id,
+            account,
+            MessageType::PositionLiquidated,
+            MessagePayload::PositionLiquidated(PositionLiquidatedPayload {
+                symbol,
+                side,
+                size_usd: position.size_usd as i128,
+                pnl: pnl as i128,
+                collateral_lost: position.collateral as i128,
+            }),
+        );
+        (-(position.collateral as i64 + revaluation + pnl)).max(0) as u64
+    }
+
+    /// Book a flow into or out of the pool (the market's insurance fund) and report it.
+    fn book_pool_pnl(&mut self, sim: &mut dyn SimulatorApi, symbol: &str, delta: i128, reason: &str) {
+        if delta == 0 {
+            return;
+        }
+        let balance = self.pool_pnl.entry(symbol.to_string()).or_default();
+        *balance += delta;
+        let balance = *balance;
+        if let Some(market) = self.markets.iter_mut().find(|m| m.symbol == symbol) {
+            market.liquidity_usd += delta;
+        }
+        let ts = sim.now_ns();
+        sim.emit_event(SimEvent::InsuranceFundChanged {
+            ts,
+            symbol: symbol.to_string(),
+            delta: delta as i64,
+            balance: balance as i64,
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Charge `account` a fee of `kind` ("position_fee", "liquidation"), paid into the pool.
+    fn book_fee(&mut self, sim: &mut dyn SimulatorApi, account: AgentId, symbol: &str, kind: &str, amount_usd: u64) {
+        if amount_usd == 0 {
+            return;
+        }
+        let ts = sim.now_ns();
+        sim.emit_event(SimEvent::FeeAccrued {
+            ts,
+            account,
+            symbol: symbol.to_string(),
+            kind: kind.to_string(),
+            amount_usd,
+        });
+        self.book_pool_pnl(sim, symbol, amount_usd as i128, kind);
+    }
+
+    fn pool_liquidity(&self, symbol: &str) -> i128 {
+        self.markets
+            .iter()
+            .find(|m| m.symbol == symbol)
+            .map_or(0, |m| m.liquidity_usd)
+    }
+
+    /// Value of an LP's pool share (micro-USD)
+    fn lp_value(&self, account: AgentId, symbol: &str) -> i128 {
+        let shares = self
+            .lp_shares
+            .get(&(account, symbol.to_string()))
+            .copied()
+            .unwrap_or(0.0);
+        let total = self.pool_shares.get(symbol).copied().unwrap_or(0.0);
+        if total <= 0.0 {
+            return 0;
+        }
+        (shares / total * self.pool_liquidity(symbol).max(0) as f64) as i128
+    }
+
+    /// Liquidity backing open interest (larger side), which LPs can't withdraw.
+    fn reserved_liquidity(&self, symbol: &str) -> i128 {
+        let (long, short) = self.open_interest(symbol);
+        long.max(short)
+    }
+
+    /// Long and short OI of `symbol` in micro-USD.
+    /// The contract OI covers the first market only; local positions count everywhere.
+    fn open_interest(&self, symbol: &str) -> (i128, i128) {
+        let (mut long, mut short) = if self.markets.first().is_some_and(|m| m.symbol == symbol) {
+            self.last_oi
+        } else {
+            (0, 0)
+        };
+        for ((_, s, side), p) in &self.local_positions {
+            if s == symbol {
+                match side {
+                    SimSide::Buy => long += p.size_usd as i128,
+                    SimSide::Sell => short += p.size_usd as i128,
+                }
+            }
+        }
+        (long, short)
+    }
+
+    /// Mark price of `symbol`; the index (oracle mid) where no mark is configured.
+    fn mark_price(&self, symbol: &str) -> Option<u64> {
+        self.mark_prices
+            .get(symbol)
+            .and_then(MarkPrice::value)
+            .or_else(|| self.last_prices.get(symbol).copied())
+    }
+
+    /// Price a local taker fills at: the index, plus the fill spread when buying and minus it when selling.
+    fn fill_price(&self, symbol: &str, buying: bool) -> u64 {
+        let index = self.last_prices.get(symbol).copied().unwrap_or(0);
+        match self.mark_prices.get(symbol) {
+            Some(mark) => mark.config().fill_price(index, buying),
+            None => index,
+        }
+    }
+
+    /// Reprice the mark of `symbol` from its new index, the OI and the estimated funding rate.
+    fn update_mark_price(&mut self, symbol: &str, index: u64) {
+        if !self.mark_prices.contains_key(symbol) {
+            return;
+        }
+        let (long, short) = self.open_interest(symbol);
+        let (long, short) = (long.max(0) as u64, short.max(0) as u64);
+        let liquidity = self.pool_liquidity(symbol).max(0) as u64;
+        let funding = self
+            .markets
+            .iter()
+            .find(|m| m.symbol == symbol)
+            .map_or(0, |m| m.fee_rates.funding_bps_hour_fp(long, short));
+        if let Some(mark) = self.mark_prices.get_mut(symbol) {
+            mark.update(index, funding, long, short, liquidity);
+        }
+    }
+
+    /// Feed a new index of `symbol` to its circuit breaker and report a trip.
+    fn update_circuit_breaker(&mut self, sim: &mut dyn SimulatorApi, symbol: &str, index: u64) {
+        let now_ns = sim.now_ns();
+        let Some(trip) = self
+            .circuit_breakers
+            .get_mut(symbol)
+            .and_then(|breaker| breaker.on_price(now_ns, index))
+        else {
+            return;
+        };
+        eprintln!(
+            "[Exchange {}] CIRCUIT BREAKER {}: {} -> {} ({:.2}%), increases halted for {}s",
+            self.name,
+            symbol,
+            trip.from_price,
+            trip.to_price,
+            trip.move_bps as f64 / 100.0,
+            (trip.halted_until_ns - now_ns) / 1_000_000_000
+        );
+        sim.emit_event(SimEvent::CircuitBreakerTripped {
+            ts: now_ns,
+            symbol: symbol.to_string(),
+            from_price: trip.from_price,
+            to_price: trip.to_price,
+            move_bps: trip.move_bps,
+            halted_until: trip.halted_until_ns,
+        });
+    }
+
+    /// End of the circuit breaker halt of `symbol`; None while increases are allowed.
+    fn halted_until(&self, symbol: &str, now_ns: u64) -> Option<u64> {
+        self.circuit_breakers.get(symbol)?.halted_until(now_ns)
+    }
+
+    fn reject_liquidity(&self, sim: &mut dyn SimulatorApi, from: AgentId, reason: String) {
+        agent_println!("[Exchange {}] LIQUIDITY REJECTED from {}: {}", self.name, from, reason);
+        sim.send(self.id, from, MessageType::OrderRejected, MessagePayload::Text(reason));
+    }
+
+    fn send_liquidity_changed(&self, sim: &mut dyn SimulatorApi, to: AgentId, symbol: &str, delta_usd: i128) {
+        sim.send(
+            self.id,
+            to,
+            MessageType::LiquidityChanged,
+            MessagePayload::LiquidityChanged(LiquidityChangedPayload {
+                symbol: symbol.to_string(),
+                delta_usd,
+                lp_value_usd: self.lp_value(to, symbol),
+                liquidity_usd: self.pool_liquidity(symbol),
+            }),
+        );
+    }
+
+    /// Micro-USD to collateral token atoms of the market, at the current collateral price.
+    fn collateral_amount(&self, symbol: &str, amount_usd: u64) -> U256 {
+        let price = MicroUsd(self.collateral_price_mid(symbol));
+        self.scales
+            .get(symbol)
+            .collateral
+            .amount_for(MicroUsd(amount_usd), price)
+            .atoms
+    }
+
+    /// Collateral token atoms of the market to micro-USD, at the current collateral price.
+    fn collateral_value(&self, symbol: &str, atoms: U256) -> u64 {
+        let price = MicroUsd(self.collateral_price_mid(symbol));
+        self.scales.get(symbol).collateral.amount(atoms).value_at(price).0
+    }
+
+    /// (min, max) price of the market's collateral token in micro-USD; None when it is a
+    /// stablecoin pegged at $1.
+    fn collateral_price(&self, symbol: &str) -> Option<(u64, u64)> {
+        let feed = self.collateral_feeds.get(symbol)?;
+        self.collateral_prices.get(feed).copied()
+    }
+
+    fn collateral_price_mid(&self, symbol: &str) -> u64 {
+        self.collateral_price(symbol)
+            .map_or(PEGGED_COLLATERAL_MICRO, |(min, max)| (min + max) / 2)
+    }
+
+    /// Revaluation of a local position's collateral at the current collateral price.
+    fn local_collateral_revaluation(&self, symbol: &str, position: &LocalPosition) -> i64 {
+        self.collateral_price(symbol)
+            .map_or(0, |(min, max)| position.collateral_revaluation((min + max) / 2))
+    }
+
+    /// Mint pool shares at the current pool value; on-chain agents also fund the contract pool.
+    fn process_add_liquidity(&mut self, sim: &mut dyn SimulatorApi, from: AgentId, req: &LiquidityPayload) {
+        if !self.symbols.contains(&req.symbol) || req.amount_usd <= 0 {
+            self.reject_liquidity(sim, from, format!("add_liquidity: invalid request for {}", req.symbol));
+            return;
+        }
+        if !self.local_agents.contains(&from) {
+            let amount = self.collateral_amount(&req.symbol, req.amount_usd as u64);
+            if let Err(e) = self.vara_client.add_liquidity(from, amount) {
+                self.reject_liquidity(sim, from, format!("add_liquidity: {}", e));
+                return;
+            }
+        }
+
+        let liquidity = self.pool_liquidity(&req.symbol);
+        let total = self.pool_shares.get(&req.symbol).copied().unwrap_or(0.0);
+        let minted = if liquidity > 0 && total > 0.0 {
+            req.amount_usd as f64 * total / liquidity as f64
+        } else {
+            req.amount_usd as f64
+        };
+        *self.pool_shares.entry(req.symbol.clone()).or_default() += minted;
+        *self.lp_shares.entry((from, req.symbol.clone())).or_default() += minted;
+        if let Some(market) = self.markets.iter_mut().find(|m| m.symbol == req.symbol) {
+            market.liquidity_usd += req.amount_usd;
+        }
+
+        agent_println!(
+            "[Exchange {}] ADD LIQUIDITY {} from={} ${:.2} (pool ${:.2})",
+            self.name,
+            req.symbol,
+            from,
+            req.amount_usd as f64 / 1_000_000.0,
+            self.pool_liquidity(&req.symbol) as f64 / 1_000_000.0
+        );
+        self.send_liquidity_changed(sim, from, &req.symbol, req.amount_usd);
+    }
 
-            // Notify agent on failure
-            if !result.success {
-                let reason = result.error.as_deref().unwrap_or("unknown");
-                println!(
-                    "[Exchange {}] TX FAILED: agent={} {} — {}",
-                    self.name, result.agent_id, result.tx_type, reason
-                );
-                sim.send(
-                    self.id,
-                    result.agent_id,
-                    MessageType::OrderRejected,
-                    MessagePayload::Text(format!(
-                        "tx_type:{},order_id:{},error:{}",
-                        result.tx_type,
-                        result.order_id.unwrap_or(0),
-                        reason
-                    )),
-                );
-            }
+    /// Burn pool shares, capped at the LP's share value and at the liquidity not backing OI.
+    /// The contract has no RemoveLiquidity, so only local agents can withdraw.
+    fn process_remove_liquidity(&mut self, sim: &mut dyn SimulatorApi, from: AgentId, req: &LiquidityPayload) {
+        if !self.local_agents.contains(&from) {
+            self.reject_liquidity(sim, from, "remove_liquidity: not supported by the contract".to_string());
+            return;
         }
+        let value = self.lp_value(from, &req.symbol);
+        let available = (self.pool_liquidity(&req.symbol) - self.reserved_liquidity(&req.symbol)).max(0);
+        let amount = req.amount_usd.min(value).min(available);
+        if amount <= 0 {
+            self.reject_liquidity(
+                sim,
+                from,
+                format!(
+                    "remove_liquidity: nothing to withdraw (share ${:.2}, available ${:.2})",
+                    value as f64 / 1_000_000.0,
+                    available as f64 / 1_000_000.0
+                ),
+            );
+            return;
+        }
+
+        let key = (from, req.symbol.clone());
+        let shares = self.lp_shares.get(&key).copied().unwrap_or(0.0);
+        let burned = shares * amount as f64 / value as f64;
+        if let Some(s) = self.lp_shares.get_mut(&key) {
+            *s -= burned;
+        }
+        if let Some(total) = self.pool_shares.get_mut(&req.symbol) {
+            *total -= burned;
+        }
+        if let Some(market) = self.markets.iter_mut().find(|m| m.symbol == req.symbol) {
+            market.liquidity_usd -= amount;
+        }
+
+        agent_println!(
+            "[Exchange {}] REMOVE LIQUIDITY {} from={} ${:.2} of ${:.2} requested (pool ${:.2})",
+            self.name,
+            req.symbol,
+            from,
+            amount as f64 / 1_000_000.0,
+            req.amount_usd as f64 / 1_000_000.0,
+            self.pool_liquidity(&req.symbol) as f64 / 1_000_000.0
+        );
+        self.send_liquidity_changed(sim, from, &req.symbol, -amount);
+    }
+
+    fn position_fee(&self, symbol: &str, size_micro: u64) -> u64 {
+        let bps = self
+            .markets
+            .iter()
+            .find(|m| m.symbol == symbol)
+            .map(|m| m.position_fee_bps)
+            .unwrap_or(0);
+        size_micro * bps as u64 / 10_000
     }
 
     /// Start an asynchronous OI fetch if none is already in-flight.
@@ -277,6 +2277,12 @@ impl ExchangeAgent {
         if self.oi_sync_pending {
             return; // previous fetch still running
         }
+        if !self.vara_client.is_connected() {
+            // Offline run (`golden`): the contract holds no positions
+            let _ = self.oi_sync_tx.send((0, 0));
+            self.oi_sync_pending = true;
+            return;
+        }
         self.vara_client.fetch_oi_async(self.oi_sync_tx.clone());
         self.oi_sync_pending = true;
     }
@@ -302,17 +2308,271 @@ impl ExchangeAgent {
         };
 
         let liquidity_usd = self.markets.first().map(|m| m.liquidity_usd).unwrap_or_default();
+        let pool_pnl_usd = self.pool_pnl.get(&symbol).copied().unwrap_or(0);
+        self.last_oi = (oi_long_usd, oi_short_usd);
 
+        let (long, short) = (oi_long_usd.max(0) as u64, oi_short_usd.max(0) as u64);
+        let rates = self.markets.first().map(|m| m.fee_rates).unwrap_or_default();
         let payload = MarketStatePayload {
             symbol,
             oi_long_usd,
             oi_short_usd,
             liquidity_usd,
+            pool_pnl_usd,
+            funding_rate_bps_hour_fp: rates.funding_bps_hour_fp(long, short),
+            borrowing_rate_bps_hour_fp: rates.borrowing_bps_hour_fp(long, short, liquidity_usd.max(0) as u64),
         };
 
         sim.broadcast(self.id, MessageType::MarketState, MessagePayload::MarketState(payload));
     }
 
+    /// Broadcast MarketStats of every market, in symbol order, once the stats interval elapsed.
+    fn broadcast_market_stats(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
+        let Some(book) = self.flow_stats.clone() else {
+            return;
+        };
+        if now_ns < self.last_stats_ns + self.stats_interval_ns {
+            return;
+        }
+        self.last_stats_ns = now_ns;
+        let mut symbols: Vec<&String> = self.markets.iter().map(|m| &m.symbol).collect();
+        symbols.sort();
+        for symbol in symbols {
+            let stats = book.stats(symbol, now_ns);
+            let payload = MarketStatsPayload {
+                symbol: symbol.clone(),
+                window_sec: book.window_sec(),
+                buy_volume_usd: stats.buy_volume_usd,
+                sell_volume_usd: stats.sell_volume_usd,
+                imbalance_bps: stats.imbalance_bps(),
+                trades: stats.trades,
+                avg_trade_usd: stats.avg_trade_usd(),
+            };
+            sim.broadcast(self.id, MessageType::MarketStats, MessagePayload::MarketStats(payload));
+        }
+    }
+
+    /// Start an asynchronous positions fetch for snapshots once the snapshot interval elapsed.
+    fn start_snapshot_fetch(&mut self, now_ns: u64) {
+        if self.snapshot_interval_ns == 0 || self.snapshot_pending {
+            return;
+        }
+        if now_ns < self.last_snapshot_ns + self.snapshot_interval_ns {
+            return;
+        }
+        self.snapshot_pending = true;
+        self.last_snapshot_ns = now_ns;
+        if !self.vara_client.is_connected() {
+            // Offline run: snapshots carry the local positions only
+            let _ = self.snapshot_tx.send(Ok(Vec::new()));
+            return;
+        }
+        let oracle_input = self.markets.first().and_then(|m| self.build_oracle_input(&m.symbol));
+        self.vara_client
+            .fetch_positions_async(oracle_input, self.snapshot_tx.clone());
+    }
+
+    /// Drain a completed positions fetch and emit PositionSnapshot / MarketSnapshot events.
+    /// The contract holds a single market, so all positions belong to the first configured market.
+    /// Positions of accounts not seen by this exchange (no known AgentId) only count towards OI.
+    fn drain_snapshots(&mut self, sim: &mut dyn SimulatorApi) {
+        let result = match self.snapshot_rx.try_recv() {
+            Ok(result) => {
+                self.snapshot_pending = false;
+                result
+            }
+            Err(crossbeam_channel::TryRecvError::Empty) => return,
+            Err(crossbeam_channel::TryRecvError::Disconnected) => {
+                eprintln!("[Exchange {}] snapshot channel disconnected", self.name);
+                return;
+            }
+        };
+        let positions = match result {
+            Ok(positions) => {
+                self.snapshot_failing = false;
+                positions
+            }
+            Err(e) => {
+                if !self.snapshot_failing {
+                    eprintln!("[Exchange {}] positions fetch for snapshots failed: {}", self.name, e);
+                }
+                self.snapshot_failing = true;
+                return;
+            }
+        };
+
+        let market = match self.markets.first() {
+            Some(m) => m.clone(),
+            None => return,
+        };
+        let ts = sim.now_ns();
+        let index_price = self.last_prices.get(&market.symbol).copied().unwrap_or(0);
+        // Unrealized PnL and liquidatability at the mark
+        let current_price = self.mark_price(&market.symbol).unwrap_or(0);
+        let agent_by_actor: HashMap<ActorId, AgentId> =
+            self.actor_ids.iter().map(|(agent, actor)| (*actor, *agent)).collect();
+
+        let mut oi_long_usd: u64 = 0;
+        let mut oi_short_usd: u64 = 0;
+        let mut chain_positions: Vec<PositionInfo> = Vec::new();
+        self.chain_unrealized.clear();
+
+        for (p, onchain_liquidation_price) in positions.iter() {
+            let size_usd_raw = u256_from_sails(p.size_usd);
+            if size_usd_raw.is_zero() {
+                continue;
+            }
+            let size_usd = Usd1e30(size_usd_raw).to_micro().0;
+            let side = match p.key.side {
+                VaraSide::Long => {
+                    oi_long_usd += size_usd;
+                    SimSide::Buy
+                }
+                VaraSide::Short => {
+                    oi_short_usd += size_usd;
+                    SimSide::Sell
+                }
+            };
+
+            let account = match agent_by_actor.get(&p.key.account) {
+                Some(id) => *id,
+                None => continue,
+            };
+
+            let size_tokens = u256_from_sails(p.size_tokens);
+            let collateral = self.collateral_value(&market.symbol, u256_from_sails(p.collateral_amount));
+            let index = self.scales.get(&market.symbol).index;
+            let entry_price = entry_price_micro(size_usd_raw, size_tokens, index);
+
+            // Mark-to-market value of the position in micro-USD
+            let value_micro = index.amount(size_tokens).value_at(MicroUsd(current_price)).0;
+            let unrealized_pnl = if current_price == 0 {
+                0
+            } else {
+                match side {
+                    SimSide::Buy => value_micro as i64 - size_usd as i64,
+                    SimSide::Sell => size_usd as i64 - value_micro as i64,
+                }
+            };
+            let leverage_actual = if collateral > 0 { (size_usd / collateral) as u32 } else { 0 };
+            let liquidation_price = liquidation_price_micro(
+                side,
+                size_usd,
+                collateral,
+                entry_price,
+                index,
+                onchain_liquidation_price.map(u256_from_sails),
+            );
+            *self.chain_unrealized.entry(account).or_default() += unrealized_pnl;
+            chain_positions.push(PositionInfo {
+                account,
+                side,
+                size_usd,
+                collateral,
+                entry_price,
+                liquidation_price,
+            });
+
+            sim.emit_event(SimEvent::PositionSnapshot {
+                ts,
+                account,
+                symbol: market.symbol.clone(),
+                side,
+                size_usd,
+                size_tokens: size_tokens.low_u128() as i128,
+                collateral,
+                entry_price,
+                current_price,
+                unrealized_pnl,
+                liquidation_price,
+                leverage_actual,
+                is_liquidatable: crossed_liquidation(side, current_price, liquidation_price),
+                opened_at_sec: p.opened_at,
+            });
+        }
+
+        let liquidity_usd = market.liquidity_usd.max(0) as u64;
+        sim.emit_event(SimEvent::MarketSnapshot {
+            ts,
+            symbol: market.symbol.clone(),
+            index_price,
+            mark_price: current_price,
+            oi_long_usd,
+            oi_short_usd,
+            liquidity_usd,
+            utilization_bps: utilization_bps(oi_long_usd + oi_short_usd, liquidity_usd),
+            funding_rate_bps_hour_fp: market.fee_rates.funding_bps_hour_fp(oi_long_usd, oi_short_usd),
+            borrowing_rate_bps_hour_fp: market.fee_rates.borrowing_bps_hour_fp(
+                oi_long_usd,
+                oi_short_usd,
+                liquidity_usd,
+            ),
+        });
+
+        self.send_positions_snapshots(sim, chain_positions, (oi_long_usd, oi_short_usd));
+    }
+
+    /// Send every snapshot subscriber one PositionsSnapshot per market: the contract positions
+    /// (first market) plus the local ones. `chain_oi` also counts accounts unknown to this exchange.
+    fn send_positions_snapshots(&self, sim: &mut dyn SimulatorApi, chain: Vec<PositionInfo>, chain_oi: (u64, u64)) {
+        if self.snapshot_subscribers.is_empty() {
+            return;
+        }
+        let mut chain = Some(chain);
+        for (i, market) in self.markets.iter().enumerate() {
+            let (mut positions, (mut oi_long_usd, mut oi_short_usd)) = if i == 0 {
+                (chain.take().unwrap_or_default(), chain_oi)
+            } else {
+                (Vec::new(), (0, 0))
+            };
+            for ((account, symbol, side), p) in &self.local_positions {
+                if *symbol != market.symbol || p.size_tokens <= 0.0 {
+                    continue;
+                }
+                match side {
+                    SimSide::Buy => oi_long_usd += p.size_usd,
+                    SimSide::Sell => oi_short_usd += p.size_usd,
+                }
+                let entry_price = (p.size_usd as f64 / p.size_tokens) as u64;
+                let collateral = (p.collateral as i64 + self.local_collateral_revaluation(symbol, p)).max(0) as u64;
+                positions.push(PositionInfo {
+                    account: *account,
+                    side: *side,
+                    size_usd: p.size_usd,
+                    collateral,
+                    entry_price,
+                    liquidation_price: liquidation_price_micro(
+                        *side,
+                        p.size_usd,
+                        collateral,
+                        entry_price,
+                        self.scales.get(&market.symbol).index,
+                        None,
+                    ),
+                });
+            }
+            positions.sort_by_key(|p| (p.account, p.side == SimSide::Sell));
+
+            let payload = PositionsSnapshotPayload {
+                symbol: market.symbol.clone(),
+                price: self.last_prices.get(&market.symbol).copied().unwrap_or(0),
+                mark_price: self.mark_price(&market.symbol).unwrap_or(0),
+                oi_long_usd,
+                oi_short_usd,
+                liquidity_usd: self.pool_liquidity(&market.symbol),
+                positions: positions.into(),
+            };
+            for subscriber in &self.snapshot_subscribers {
+                sim.send(
+                    self.id,
+                    *subscriber,
+                    MessageType::PositionsSnapshot,
+                    MessagePayload::PositionsSnapshot(payload.clone()),
+                );
+            }
+        }
+    }
+
     /// On-chain liquidations are handled by keepers/contract — no-op here.
     fn process_liquidation_scan(&mut self, _sim: &mut dyn SimulatorApi, _now_ns: u64) {}
 
@@ -328,11 +2588,16 @@ impl ExchangeAgent {
                     return Err("trigger_price required for conditional orders".into());
                 }
             }
+            ExecutionType::TrailingStop => {
+                if order.trailing_distance.unwrap_or(0) == 0 {
+                    return Err("trailing_distance required for trailing stops".into());
+                }
+            }
         }
 
         match order.order_type {
             SimOrderType::Increase => {
-                if order.qty.is_none() || order.qty.unwrap() <= 0.0 {
+                if order.qty.unwrap_or_default().is_zero() {
                     return Err("qty required for Increase".into());
                 }
             }
@@ -342,10 +2607,10 @@ impl ExchangeAgent {
         // SL/TP only for Decrease
         if matches!(
             order.execution_type,
-            ExecutionType::StopLoss | ExecutionType::TakeProfit
+            ExecutionType::StopLoss | ExecutionType::TakeProfit | ExecutionType::TrailingStop
         ) && order.order_type != SimOrderType::Decrease
         {
-            return Err("StopLoss/TakeProfit only valid for Decrease orders".into());
+            return Err("StopLoss/TakeProfit/TrailingStop only valid for Decrease orders".into());
         }
 
         Ok(())
@@ -353,7 +2618,7 @@ impl ExchangeAgent {
 
     fn process_submit_order(&mut self, sim: &mut dyn SimulatorApi, from: AgentId, order: &OrderPayload, now_ns: u64) {
         if let Err(e) = self.validate_order(order) {
-            println!("[Exchange {}] REJECTED from {}: {}", self.name, from, e);
+            agent_println!("[Exchange {}] REJECTED from {}: {}", self.name, from, e);
             return;
         }
 
@@ -364,8 +2629,9 @@ impl ExchangeAgent {
                     let market_order = MarketOrderPayload {
                         symbol: order.symbol.clone(),
                         side: order.side,
-                        qty: order.qty.unwrap_or(0.0),
+                        qty: order.qty.unwrap_or_default(),
                         leverage: order.leverage.unwrap_or(5),
+                        decision_price: order.decision_price,
                     };
                     self.process_market_order(sim, from, &market_order, now_ns);
                 }
@@ -373,6 +2639,7 @@ impl ExchangeAgent {
                     let close_order = CloseOrderPayload {
                         symbol: order.symbol.clone(),
                         side: order.side,
+                        decision_price: order.decision_price,
                     };
                     self.process_close_order(sim, from, &close_order, now_ns);
                 }
@@ -380,31 +2647,28 @@ impl ExchangeAgent {
             return;
         }
 
+        if order.execution_type == ExecutionType::TrailingStop || self.local_agents.contains(&from) {
+            self.add_local_order(sim, from, order, now_ns);
+            return;
+        }
+
         // Add to pending orders
         let actor = match self.get_or_create_actor(from) {
             Some(a) => a,
             None => return,
         };
-        let now_sec: u64 = now_ns / 1_000_000_000;
-
-        let (index_decimals, collateral_decimals) =
-            self.symbol_decimals.get(&order.symbol).copied().unwrap_or((18, 6));
+        let index = self.scales.get(&order.symbol).index;
 
         let current_price_micro = self.last_prices.get(&order.symbol).copied().unwrap_or(0);
 
         let (collateral_atoms, size_usd_1e30, target_leverage_x) = match order.order_type {
             SimOrderType::Increase => {
-                let qty = order.qty.unwrap_or(0.0);
                 let leverage = order.leverage.unwrap_or(5).max(1);
-                let size_micro = (qty * current_price_micro as f64) as u64;
+                let size_micro = order.qty.unwrap_or_default().notional(current_price_micro);
                 let collateral_micro = size_micro / leverage as u64;
 
-                let collateral_atoms = if collateral_decimals >= 6 {
-                    U256::from(collateral_micro) * U256::exp10((collateral_decimals - 6) as usize)
-                } else {
-                    U256::from(collateral_micro) / U256::exp10((6 - collateral_decimals) as usize)
-                };
-                let size_usd_1e30 = U256::from(size_micro) * U256::exp10(24);
+                let collateral_atoms = self.collateral_amount(&order.symbol, collateral_micro);
+                let size_usd_1e30 = MicroUsd(size_micro).to_1e30().0;
                 (u256_to_sails(collateral_atoms), u256_to_sails(size_usd_1e30), leverage)
             }
             SimOrderType::Decrease => {
@@ -414,26 +2678,27 @@ impl ExchangeAgent {
                     let side = Self::convert_side_to_vara(order.side);
                     let position_key = VaraPositionKey { account: actor, side };
                     match self.vara_client.get_position(&position_key) {
-                        Ok(Some(p)) => usd_to_micro(u256_from_sails(p.size_usd)),
+                        Ok(Some(p)) => Usd1e30(u256_from_sails(p.size_usd)).to_micro().0,
                         _ => 0,
                     }
                 };
-                let size_usd_1e30 = U256::from(size_micro) * U256::exp10(24);
+                let size_usd_1e30 = MicroUsd(size_micro).to_1e30().0;
                 (u256_to_sails(U256::zero()), u256_to_sails(size_usd_1e30), 0)
             }
         };
 
         let trigger_price = order
             .trigger_price
-            .map(|p| u256_to_sails(normalize_price_to_atom(p, index_decimals)));
+            .map(|p| u256_to_sails(index.atom_price(MicroUsd(p))));
         let acceptable_price = order
             .acceptable_price
-            .map(|p| u256_to_sails(normalize_price_to_atom(p, index_decimals)));
+            .map(|p| u256_to_sails(index.atom_price(MicroUsd(p))));
 
         let execution_type = match order.execution_type {
             ExecutionType::Market => VaraExecutionType::Market,
             ExecutionType::Limit => VaraExecutionType::Limit,
-            ExecutionType::StopLoss => VaraExecutionType::StopLoss,
+            // Trailing stops never reach the chain (handled above)
+            ExecutionType::StopLoss | ExecutionType::TrailingStop => VaraExecutionType::StopLoss,
             ExecutionType::TakeProfit => VaraExecutionType::TakeProfit,
         };
 
@@ -443,6 +2708,7 @@ impl ExchangeAgent {
         };
 
         let valid_for = order.valid_for_sec.unwrap_or(3600);
+        let (now_sec, valid_until) = self.chain_validity(now_ns, valid_for);
 
         let onchain_order = VaraOrder {
             account: actor,
@@ -457,7 +2723,7 @@ impl ExchangeAgent {
             target_leverage_x,
             created_at: now_sec,
             valid_from: now_sec,
-            valid_until: now_sec + valid_for,
+            valid_until,
         };
 
         // Fire-and-forget: submit limit/stop/TP order to chain
@@ -468,10 +2734,21 @@ impl ExchangeAgent {
             );
             return;
         }
+        let local_id = self.next_local_order_id;
+        self.next_local_order_id += 1;
+        let tracked = OnchainOrder {
+            local_id,
+            owner: from,
+            valid_until_ns: now_ns + valid_for * 1_000_000_000,
+            oco_group: order.oco_group,
+            cancel_requested: false,
+        };
+        self.inflight_submissions.entry(from).or_default().push_back(tracked);
 
-        println!(
-            "[Exchange {}] SUBMITTED LIMIT from={} {:?} {:?} trigger=${:.2}",
+        agent_println!(
+            "[Exchange {}] SUBMITTED LIMIT #{} from={} {:?} {:?} trigger=${:.2}",
             self.name,
+            local_id,
             from,
             order.execution_type,
             order.side,
@@ -482,62 +2759,581 @@ impl ExchangeAgent {
             self.id,
             from,
             MessageType::OrderPending,
-            MessagePayload::Text("order submitted on-chain".to_string()),
+            MessagePayload::Text(format!("order_id:{}", local_id)),
+        );
+    }
+
+    /// Orders held by the exchange instead of the contract: trailing stops (the contract has
+    /// no trailing type; the trigger follows every oracle tick) and all conditional orders of
+    /// local agents. A keeper execution fills them at market.
+    fn add_local_order(&mut self, sim: &mut dyn SimulatorApi, from: AgentId, order: &OrderPayload, now_ns: u64) {
+        let Some(&mid) = self.last_prices.get(&order.symbol) else {
+            agent_println!(
+                "[Exchange {}] REJECTED {:?} from {}: no price for {}",
+                self.name,
+                order.execution_type,
+                from,
+                order.symbol
+            );
+            return;
+        };
+        let mut payload = order.clone();
+        if order.execution_type == ExecutionType::TrailingStop {
+            let distance = order.trailing_distance.unwrap_or(0);
+            let price = Price { min: mid, max: mid };
+            payload.trigger_price = Some(trigger_checker::trail_trigger(
+                order.side,
+                distance,
+                order.trigger_price,
+                &price,
+            ));
+        }
+        let trigger = payload.trigger_price.unwrap_or(0);
+
+        let order_id = self.next_local_order_id;
+        self.next_local_order_id += 1;
+        let valid_for = order.valid_for_sec.unwrap_or(3600);
+        self.pending_orders.insert(PendingOrder {
+            id: order_id,
+            owner: from,
+            payload,
+            valid_until_ns: now_ns + valid_for * 1_000_000_000,
+        });
+
+        agent_println!(
+            "[Exchange {}] HELD {:?} #{} from={} {:?} {} trigger=${:.2}",
+            self.name,
+            order.execution_type,
+            order_id,
+            from,
+            order.side,
+            order.symbol,
+            trigger as f64 / 1_000_000.0
+        );
+        sim.send(
+            self.id,
+            from,
+            MessageType::OrderPending,
+            MessagePayload::Text(format!("order_id:{}", order_id)),
         );
     }
 
     fn process_cancel_order(&mut self, _sim: &mut dyn SimulatorApi, from: AgentId, order_id: OrderId) {
+        // On-chain orders are cancelled by their local id; contract ids are accepted as well
+        let chain_id = self.order_ids.chain_id(order_id).unwrap_or(order_id);
+        if self.onchain_orders.get(&chain_id).is_some_and(|o| o.owner == from) {
+            self.onchain_orders.remove(&chain_id);
+            self.order_ids.remove_chain(chain_id);
+            if let Err(e) = self.vara_client.cancel_order(from, VaraOrderId(chain_id)) {
+                eprintln!("[Exchange {}] CancelOrder #{} failed: {}", self.name, chain_id, e);
+            }
+            return;
+        }
+        // No contract id yet: cancel once the submission is confirmed
+        if let Some(order) = self
+            .inflight_submissions
+            .get_mut(&from)
+            .and_then(|q| q.iter_mut().find(|o| o.local_id == order_id))
+        {
+            order.cancel_requested = true;
+            agent_println!(
+                "[Exchange {}] CANCEL #{} from={} deferred until on-chain confirmation",
+                self.name,
+                order_id,
+                from
+            );
+            return;
+        }
+
         if let Some(order) = self.pending_orders.get(order_id) {
             if order.owner != from {
-                println!("[Exchange {}] CANCEL REJECTED: not owner", self.name);
+                agent_println!("[Exchange {}] CANCEL REJECTED: not owner", self.name);
                 return;
             }
         }
 
         if let Some(_removed) = self.pending_orders.remove(order_id) {
-            println!("[Exchange {}] CANCELLED #{} from={}", self.name, order_id, from);
+            agent_println!("[Exchange {}] CANCELLED #{} from={}", self.name, order_id, from);
         }
     }
 
-    fn execute_triggered_order(&mut self, keeper_id: AgentId, order: &PendingOrder) {
-        let oracle_input = match self.build_oracle_input(&order.payload.symbol) {
-            Some(input) => input,
-            None => {
-                eprintln!(
-                    "[Exchange {}] ExecuteOrder: no oracle price for {}",
-                    self.name, order.payload.symbol
+    /// Amend the trigger and/or size of a resting order. Orders held by the exchange change in
+    /// place; the contract has no amendment, so on-chain orders are cancelled and resubmitted.
+    /// The owner gets OrderAccepted or OrderRejected.
+    fn process_modify_order(&mut self, sim: &mut dyn SimulatorApi, from: AgentId, change: &ModifyOrderPayload) {
+        let order_id = change.order_id;
+        // Orders held here know their market; on-chain orders are on the contract's (first) market
+        let symbol = self
+            .pending_orders
+            .get(order_id)
+            .map(|o| o.payload.symbol.clone())
+            .or_else(|| self.markets.first().map(|m| m.symbol.clone()));
+        let violation = change
+            .trigger_price
+            .zip(symbol)
+            .and_then(|(price, symbol)| self.check_price_rules(&symbol, price).err());
+        let result = if change.trigger_price == Some(0)
+            || change.qty.is_some_and(|qty| qty.is_zero())
+            || change.size_delta_usd == Some(0)
+        {
+            Err("invalid amendment".to_string())
+        } else if let Some(violation) = violation {
+            Err(violation.reason())
+        } else if self.pending_orders.get(order_id).is_some() {
+            self.modify_local_order(sim, from, change)
+        } else {
+            self.modify_onchain_order(sim, from, change)
+        };
+        match result {
+            Ok(true) => {
+                agent_println!(
+                    "[Exchange {}] MODIFIED #{} from={} trigger={:?} qty={:?} size={:?}",
+                    self.name,
+                    order_id,
+                    from,
+                    change.trigger_price,
+                    change.qty.map(|qty| qty.to_string()),
+                    change.size_delta_usd
                 );
-                return;
+                sim.send(
+                    self.id,
+                    from,
+                    MessageType::OrderAccepted,
+                    MessagePayload::Text(format!("order_id:{},modified", order_id)),
+                );
+            }
+            // Refused by the pre-trade check, which already notified the owner
+            Ok(false) => {}
+            Err(e) => {
+                agent_println!(
+                    "[Exchange {}] MODIFY REJECTED #{} from {}: {}",
+                    self.name,
+                    order_id,
+                    from,
+                    e
+                );
+                sim.send(
+                    self.id,
+                    from,
+                    MessageType::OrderRejected,
+                    MessagePayload::Text(format!("order_id:{},error:{}", order_id, e)),
+                );
+            }
+        }
+    }
+
+    fn modify_local_order(
+        &mut self,
+        sim: &mut dyn SimulatorApi,
+        from: AgentId,
+        change: &ModifyOrderPayload,
+    ) -> Result<bool, String> {
+        let Some(order) = self.pending_orders.get(change.order_id) else {
+            return Err("unknown order".to_string());
+        };
+        if order.owner != from {
+            return Err("not owner".to_string());
+        }
+        if let (SimOrderType::Increase, Some(qty)) = (order.payload.order_type, change.qty) {
+            let increase = MarketOrderPayload {
+                symbol: order.payload.symbol.clone(),
+                side: order.payload.side,
+                qty,
+                leverage: order.payload.leverage.unwrap_or(5),
+                decision_price: None,
+            };
+            let trigger_price = change.trigger_price.or(order.payload.trigger_price);
+            if !self.pre_trade_check(sim, from, &increase, trigger_price) {
+                return Ok(false);
+            }
+        }
+        Ok(self.pending_orders.modify(change).is_some())
+    }
+
+    fn modify_onchain_order(
+        &mut self,
+        sim: &mut dyn SimulatorApi,
+        from: AgentId,
+        change: &ModifyOrderPayload,
+    ) -> Result<bool, String> {
+        let actor = self
+            .get_or_create_actor(from)
+            .ok_or_else(|| "no on-chain account".to_string())?;
+        if self
+            .inflight_submissions
+            .get(&from)
+            .is_some_and(|q| q.iter().any(|o| o.local_id == change.order_id))
+        {
+            return Err("order not yet confirmed on-chain".to_string());
+        }
+        let chain_id = self.order_ids.chain_id(change.order_id).unwrap_or(change.order_id);
+        let mut order = match self.vara_client.get_order(VaraOrderId(chain_id)) {
+            Ok(Some(order)) => order,
+            Ok(None) => return Err("unknown order".to_string()),
+            Err(e) => return Err(e.to_string()),
+        };
+        if order.account != actor {
+            return Err("not owner".to_string());
+        }
+        let Some(symbol) = self.markets.first().map(|m| m.symbol.clone()) else {
+            return Err("no market".to_string());
+        };
+        let index = self.scales.get(&symbol).index;
+
+        if let Some(trigger_price) = change.trigger_price {
+            order.trigger_price = Some(u256_to_sails(index.atom_price(MicroUsd(trigger_price))));
+        }
+        match order.order_type {
+            VaraOrderType::Increase => {
+                if let Some(qty) = change.qty {
+                    let side = Self::convert_side_from_vara(&order.side);
+                    let leverage = order.target_leverage_x.max(1);
+                    let increase = MarketOrderPayload {
+                        symbol: symbol.clone(),
+                        side,
+                        qty,
+                        leverage,
+                        decision_price: None,
+                    };
+                    if !self.pre_trade_check(sim, from, &increase, change.trigger_price) {
+                        return Ok(false);
+                    }
+                    // Sized at the current price, as on submission
+                    let price = self.last_prices.get(&symbol).copied().unwrap_or(0);
+                    let size_micro = qty.notional(price);
+                    let collateral_micro = size_micro / leverage as u64;
+                    order.size_delta_usd = u256_to_sails(MicroUsd(size_micro).to_1e30().0);
+                    order.collateral_delta_tokens = u256_to_sails(self.collateral_amount(&symbol, collateral_micro));
+                }
+            }
+            _ => {
+                if let Some(size) = change.size_delta_usd {
+                    order.size_delta_usd = u256_to_sails(MicroUsd(size).to_1e30().0);
+                }
             }
+        }
+
+        self.vara_client
+            .update_order(from, VaraOrderId(chain_id), &order)
+            .map_err(|e| e.to_string())?;
+        // The old id disappears with the cancel: stop tracking it so the OCO sweep ignores it.
+        // The owner keeps its local id, linked to the new contract id by the tx result.
+        // Keep the sim-time expiry; the contract's is in chain time
+        let previous = self.onchain_orders.remove(&chain_id);
+        let valid_until_ns = match &previous {
+            Some(tracked) => tracked.valid_until_ns,
+            None => self
+                .chain_clock
+                .sim_time(sim.now_ns(), chain_now_sec(), order.valid_until),
+        };
+        let oco_group = previous.and_then(|o| o.oco_group);
+        let local_id = self.order_ids.remove_chain(chain_id).unwrap_or(change.order_id);
+        let tracked = OnchainOrder {
+            local_id,
+            owner: from,
+            valid_until_ns,
+            oco_group,
+            cancel_requested: false,
+        };
+        self.inflight_submissions.entry(from).or_default().push_back(tracked);
+        Ok(true)
+    }
+
+    /// Execute on-chain for the keeper, with the keeper's bundled price if it sent one for
+    /// this market, otherwise with the last oracle tick.
+    fn execute_triggered_order(
+        &mut self,
+        keeper_id: AgentId,
+        order: &PendingOrder,
+        price_update: Option<&PriceUpdatePayload>,
+    ) {
+        let oracle_input = match price_update.filter(|u| u.symbol == order.payload.symbol) {
+            Some(update) => match self.build_keeper_oracle_input(update) {
+                Ok(input) => {
+                    self.keeper_price_updates += 1;
+                    input
+                }
+                Err(e) => {
+                    eprintln!(
+                        "[Exchange {}] ExecuteOrder #{} by keeper {}: {}",
+                        self.name, order.id, keeper_id, e
+                    );
+                    self.release_failed_claim(order.clone());
+                    return;
+                }
+            },
+            None => match self.build_oracle_input(&order.payload.symbol) {
+                Some(input) => input,
+                None => {
+                    eprintln!(
+                        "[Exchange {}] ExecuteOrder: no oracle price for {}",
+                        self.name, order.payload.symbol
+                    );
+                    self.release_failed_claim(order.clone());
+                    return;
+                }
+            },
         };
 
         let order_id = VaraOrderId(order.id);
+        if let Some(batch) = self.keeper_batch.as_mut() {
+            batch.push((oracle_input, order.clone()));
+            return;
+        }
         if let Err(e) = self.vara_client.execute_order(keeper_id, order_id, &oracle_input) {
             eprintln!(
                 "[Exchange {}] ExecuteOrder failed id={} by keeper {}: {}",
                 self.name, order.id, keeper_id, e
             );
+            self.release_failed_claim(order.clone());
         } else {
-            println!(
+            self.inflight_keeper_orders.insert(order.id, order.clone());
+            agent_println!(
                 "[Exchange {}] ON-CHAIN EXECUTE #{} by keeper {}",
-                self.name, order.id, keeper_id
+                self.name,
+                order.id,
+                keeper_id
+            );
+        }
+    }
+
+    /// Execute a keeper's claimed orders like single ExecuteOrders, but send the on-chain
+    /// executions among them to the contract as one batch.
+    fn handle_execute_order_batch(
+        &mut self,
+        sim: &mut dyn SimulatorApi,
+        keeper_id: AgentId,
+        batch: &ExecuteOrderBatchPayload,
+        now_ns: u64,
+    ) {
+        self.keeper_batch = Some(Vec::new());
+        for payload in &batch.orders {
+            self.handle_execute_order_from_keeper(sim, keeper_id, payload, now_ns);
+        }
+        let executions = self.keeper_batch.take().unwrap_or_default();
+        if executions.is_empty() {
+            return;
+        }
+        let ids: Vec<OrderId> = executions.iter().map(|(_, order)| order.id).collect();
+        let calls = executions
+            .iter()
+            .map(|(oracle_input, order)| (VaraOrderId(order.id), oracle_input.clone()))
+            .collect();
+        if let Err(e) = self.vara_client.execute_orders_batch(keeper_id, calls) {
+            eprintln!(
+                "[Exchange {}] ExecuteOrder batch of {} by keeper {} failed: {}",
+                self.name,
+                ids.len(),
+                keeper_id,
+                e
+            );
+            for (_, order) in executions {
+                self.release_failed_claim(order);
+            }
+            return;
+        }
+        self.keeper_batches += 1;
+        self.keeper_batched_orders += ids.len() as u64;
+        for (_, order) in executions {
+            self.inflight_keeper_orders.insert(order.id, order);
+        }
+        agent_println!(
+            "[Exchange {}] ON-CHAIN EXECUTE batch {:?} by keeper {}",
+            self.name,
+            ids,
+            keeper_id
+        );
+    }
+
+    /// Cancel the remaining orders of an OCO group after one of them executed.
+    fn cancel_oco_siblings(&mut self, sim: &mut dyn SimulatorApi, owner: AgentId, group: u64) {
+        let mut cancelled: Vec<OrderId> = self
+            .pending_orders
+            .remove_oco_group(owner, group)
+            .iter()
+            .map(|o| o.id)
+            .collect();
+
+        let onchain: Vec<(OrderId, OrderId)> = self
+            .onchain_orders
+            .iter()
+            .filter(|(_, o)| o.owner == owner && o.oco_group == Some(group))
+            .map(|(&order_id, o)| (order_id, o.local_id))
+            .collect();
+        for (order_id, local_id) in onchain {
+            self.onchain_orders.remove(&order_id);
+            self.order_ids.remove_chain(order_id);
+            if let Err(e) = self.vara_client.cancel_order(owner, VaraOrderId(order_id)) {
+                eprintln!("[Exchange {}] CancelOrder #{} failed: {}", self.name, order_id, e);
+                continue;
+            }
+            cancelled.push(local_id);
+        }
+
+        for order_id in cancelled {
+            agent_println!("[Exchange {}] OCO CANCEL #{} of {}", self.name, order_id, owner);
+            sim.send(
+                self.id,
+                owner,
+                MessageType::OrderCancelled,
+                MessagePayload::Text(format!("order_id:{},reason:oco", order_id)),
             );
         }
     }
 
+    /// Start an asynchronous check of the OCO-linked on-chain orders if none is in-flight.
+    fn start_oco_check(&mut self) {
+        if self.oco_check_pending || !self.vara_client.is_connected() {
+            return;
+        }
+        let linked: Vec<OrderId> = self
+            .onchain_orders
+            .iter()
+            .filter(|(_, o)| o.oco_group.is_some())
+            .map(|(&order_id, _)| order_id)
+            .collect();
+        if linked.is_empty() {
+            return;
+        }
+        self.oco_check_pending = true;
+        self.vara_client.fetch_orders_async(linked, self.oco_check_tx.clone());
+    }
+
+    /// The contract drops executed orders: once an OCO member is gone, cancel the rest of its group.
+    /// Non-blocking: if no check result is ready yet, this is a no-op.
+    fn drain_oco_check(&mut self, sim: &mut dyn SimulatorApi) {
+        let checked = match self.oco_check_rx.try_recv() {
+            Ok(checked) => {
+                self.oco_check_pending = false;
+                checked
+            }
+            Err(crossbeam_channel::TryRecvError::Empty) => return,
+            Err(crossbeam_channel::TryRecvError::Disconnected) => {
+                eprintln!("[Exchange {}] OCO check channel disconnected", self.name);
+                return;
+            }
+        };
+
+        for (order_id, present) in checked {
+            // Already handled as a sibling of an earlier order of the group, or cancelled since
+            let Some((owner, group)) = self
+                .onchain_orders
+                .get(&order_id)
+                .and_then(|o| o.oco_group.map(|group| (o.owner, group)))
+            else {
+                continue;
+            };
+            match present {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.onchain_orders.remove(&order_id);
+                    self.notify_onchain_triggered(sim, owner, order_id);
+                    self.cancel_oco_siblings(sim, owner, group);
+                }
+                Err(e) => eprintln!("[Exchange {}] OCO check #{} failed: {}", self.name, order_id, e),
+            }
+        }
+    }
+
+    /// An on-chain order left the contract without a cancel from the exchange: it executed.
+    /// Tell the owner under the local id it knows the order by.
+    fn notify_onchain_triggered(&mut self, sim: &mut dyn SimulatorApi, owner: AgentId, order_id: OrderId) {
+        let Some(local_id) = self.order_ids.remove_chain(order_id) else {
+            self.warn_order_drift(order_id, owner);
+            return;
+        };
+        agent_println!(
+            "[Exchange {}] ON-CHAIN EXECUTED #{} (on-chain #{}) of {}",
+            self.name,
+            local_id,
+            order_id,
+            owner
+        );
+        sim.send(
+            self.id,
+            owner,
+            MessageType::OrderTriggered,
+            MessagePayload::Text(format!("order_id:{}", local_id)),
+        );
+    }
+
+    /// (chain now, chain `valid_until`) of a contract order valid for `valid_for_sec` of sim time.
+    fn chain_validity(&mut self, now_ns: u64, valid_for_sec: u64) -> (u64, u64) {
+        let chain_now = chain_now_sec();
+        let until_ns = now_ns.saturating_add(valid_for_sec * 1_000_000_000);
+        (chain_now, self.chain_clock.chain_time(now_ns, chain_now, until_ns))
+    }
+
     fn cleanup_expired_orders(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
         let expired = self.pending_orders.remove_expired(now_ns);
         for order in expired {
-            println!("[Exchange {}] EXPIRED #{}", self.name, order.id);
-            sim.send(
-                self.id,
-                order.owner,
-                MessageType::OrderCancelled,
-                MessagePayload::Text(format!("order_id:{},reason:expired", order.id)),
-            );
+            agent_println!("[Exchange {}] EXPIRED #{}", self.name, order.id);
+            self.notify_expired(sim, order.owner, order.id, &order.payload.symbol, order.payload.side);
+        }
+        self.cancel_expired_onchain_orders(sim, now_ns);
+    }
+
+    /// Cancel expired on-chain orders on behalf of their owners.
+    fn cancel_expired_onchain_orders(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
+        let expired: Vec<(OrderId, AgentId)> = self
+            .onchain_orders
+            .iter()
+            .filter(|(_, order)| order.valid_until_ns <= now_ns)
+            .map(|(&order_id, order)| (order_id, order.owner))
+            .collect();
+        if expired.is_empty() {
+            return;
+        }
+        let symbol = self.markets.first().map(|m| m.symbol.clone()).unwrap_or_default();
+
+        for (order_id, owner) in expired {
+            self.onchain_orders.remove(&order_id);
+            // Orders executed or cancelled in the meantime are gone from the contract
+            let order = match self.vara_client.get_order(VaraOrderId(order_id)) {
+                Ok(Some(order)) => order,
+                Ok(None) => {
+                    self.notify_onchain_triggered(sim, owner, order_id);
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("[Exchange {}] expiry check #{} failed: {}", self.name, order_id, e);
+                    continue;
+                }
+            };
+            if let Err(e) = self.vara_client.cancel_order(owner, VaraOrderId(order_id)) {
+                eprintln!("[Exchange {}] CancelOrder #{} failed: {}", self.name, order_id, e);
+                continue;
+            }
+            agent_println!("[Exchange {}] EXPIRED on-chain #{} of {}", self.name, order_id, owner);
+            let local_id = self.order_ids.remove_chain(order_id).unwrap_or(order_id);
+            let side = Self::convert_side_from_vara(&order.side);
+            self.notify_expired(sim, owner, local_id, &symbol, side);
         }
     }
 
+    fn notify_expired(
+        &self,
+        sim: &mut dyn SimulatorApi,
+        owner: AgentId,
+        order_id: OrderId,
+        symbol: &str,
+        side: SimSide,
+    ) {
+        sim.send(
+            self.id,
+            owner,
+            MessageType::OrderExpired,
+            MessagePayload::Text(format!("order_id:{}", order_id)),
+        );
+        sim.emit_event(SimEvent::OrderExpired {
+            ts: sim.now_ns(),
+            account: owner,
+            order_id,
+            symbol: symbol.to_string(),
+            side,
+        });
+    }
+
     fn handle_get_pending_orders(&self, sim: &mut dyn SimulatorApi, keeper_id: AgentId) {
         let mut orders_info = Vec::new();
 
@@ -553,6 +3349,7 @@ impl ExchangeAgent {
                         execution_type: order.payload.execution_type,
                         trigger_price,
                         owner: order.owner,
+                        qty: order.payload.qty.unwrap_or_default(),
                     });
                 }
             }
@@ -566,13 +3363,82 @@ impl ExchangeAgent {
         );
     }
 
+    fn handle_get_trades(&self, sim: &mut dyn SimulatorApi, from: AgentId, query: &GetTradesPayload) {
+        let trades = self.trades.query(query.account, query.symbol.as_deref(), query.limit);
+        sim.send(
+            self.id,
+            from,
+            MessageType::TradesList,
+            MessagePayload::TradesList(TradesListPayload { trades }),
+        );
+    }
+
+    /// Put an order whose keeper execution failed on-chain back into the book, unclaimed,
+    /// so that a keeper can claim it again.
+    fn release_failed_claim(&mut self, order: PendingOrder) {
+        self.order_claims.remove(&order.id);
+        self.failed_claims += 1;
+        agent_println!(
+            "[Exchange {}] execution of #{} failed, order released for re-claiming ({} so far)",
+            self.name,
+            order.id,
+            self.failed_claims
+        );
+        self.pending_orders.insert(order);
+    }
+
+    /// Grant `keeper_id` the exclusive right to execute a triggered order, unless another keeper
+    /// holds an unexpired claim. Claims are off-chain; only the claimant sends a transaction.
+    fn handle_claim_order(&mut self, sim: &mut dyn SimulatorApi, keeper_id: AgentId, order_id: OrderId, now_ns: u64) {
+        let Some(order) = self.pending_orders.get(order_id) else {
+            sim.send(
+                self.id,
+                keeper_id,
+                MessageType::OrderAlreadyExecuted,
+                MessagePayload::Text(format!("order_id:{}", order_id)),
+            );
+            return;
+        };
+        let triggered = self
+            .last_prices
+            .get(&order.payload.symbol)
+            .is_some_and(|&mid| trigger_checker::is_triggered(order, &Price { min: mid, max: mid }));
+        let held_by_other = self
+            .order_claims
+            .get(&order_id)
+            .is_some_and(|c| c.keeper != keeper_id && c.expires_ns > now_ns);
+
+        let msg_type = if triggered && !held_by_other {
+            self.order_claims.insert(
+                order_id,
+                OrderClaim {
+                    keeper: keeper_id,
+                    expires_ns: now_ns + KEEPER_CLAIM_TTL_NS,
+                },
+            );
+            MessageType::OrderClaimed
+        } else {
+            MessageType::OrderClaimRejected
+        };
+        sim.send(
+            self.id,
+            keeper_id,
+            msg_type,
+            MessagePayload::ExecuteOrder(ExecuteOrderPayload {
+                order_id,
+                price_update: None,
+            }),
+        );
+    }
+
     fn handle_execute_order_from_keeper(
         &mut self,
         sim: &mut dyn SimulatorApi,
         keeper_id: AgentId,
-        order_id: OrderId,
-        _now_ns: u64,
+        payload: &ExecuteOrderPayload,
+        now_ns: u64,
     ) {
+        let order_id = payload.order_id;
         // 1. Check order exists
         let order = match self.pending_orders.get(order_id) {
             Some(o) => o.clone(),
@@ -587,82 +3453,209 @@ impl ExchangeAgent {
             }
         };
 
+        // Another keeper's claim wins; unclaimed orders can still be executed directly
+        if let Some(claim) = self.order_claims.get(&order_id) {
+            if claim.keeper != keeper_id && claim.expires_ns > now_ns {
+                sim.send(
+                    self.id,
+                    keeper_id,
+                    MessageType::OrderClaimRejected,
+                    MessagePayload::ExecuteOrder(ExecuteOrderPayload {
+                        order_id,
+                        price_update: None,
+                    }),
+                );
+                return;
+            }
+        }
+
         // 2. Verify trigger still valid
         let symbol = &order.payload.symbol;
         let price = match self.last_prices.get(symbol) {
             Some(&mid) => Price { min: mid, max: mid },
             None => {
-                println!(
+                agent_println!(
                     "[Exchange {}] ExecuteOrder rejected: no price for {}",
-                    self.name, symbol
+                    self.name,
+                    symbol
                 );
                 return;
             }
         };
 
         if !trigger_checker::is_triggered(&order, &price) {
-            println!(
+            agent_println!(
                 "[Exchange {}] ExecuteOrder rejected: trigger not satisfied for #{}",
-                self.name, order_id
+                self.name,
+                order_id
+            );
+            self.order_claims.remove(&order_id);
+            return;
+        }
+
+        // Triggered increases wait for the end of a circuit breaker halt
+        if order.payload.order_type == SimOrderType::Increase && self.halted_until(symbol, now_ns).is_some() {
+            agent_println!(
+                "[Exchange {}] ExecuteOrder deferred: {} halted by circuit breaker, #{}",
+                self.name,
+                symbol,
+                order_id
             );
+            self.order_claims.remove(&order_id);
             return;
         }
 
         // 3. Remove and execute
         if let Some(removed_order) = self.pending_orders.remove(order_id) {
-            println!(
+            self.order_claims.remove(&order_id);
+            agent_println!(
                 "[Exchange {}] KEEPER {} EXECUTES #{} {:?} {:?}",
-                self.name, keeper_id, order_id, removed_order.payload.execution_type, removed_order.payload.side
+                self.name,
+                keeper_id,
+                order_id,
+                removed_order.payload.execution_type,
+                removed_order.payload.side
             );
 
-            self.execute_triggered_order(keeper_id, &removed_order);
+            // On-chain fills complete when their tx result comes back
+            if order_id >= LOCAL_ORDER_ID_BASE {
+                if self.execute_local_order(sim, keeper_id, &removed_order, now_ns) {
+                    self.complete_keeper_execution(sim, keeper_id, &removed_order);
+                }
+            } else {
+                self.execute_triggered_order(keeper_id, &removed_order, payload.price_update.as_ref());
+            }
+        }
+    }
+
+    /// A keeper's execution of a triggered order went through: tell the owner, cancel the rest
+    /// of its OCO group and pay the keeper.
+    fn complete_keeper_execution(&mut self, sim: &mut dyn SimulatorApi, keeper_id: AgentId, order: &PendingOrder) {
+        sim.send(
+            self.id,
+            order.owner,
+            MessageType::OrderTriggered,
+            MessagePayload::Text(format!("order_id:{}", order.id)),
+        );
+        if let Some(group) = order.payload.oco_group {
+            self.cancel_oco_siblings(sim, order.owner, group);
+        }
 
-            // 4. Send reward (0.1% of size)
-            let size_micro =
-                removed_order.payload.qty.unwrap_or(0.0) * self.last_prices.get(symbol).copied().unwrap_or(0) as f64;
-            let reward = (size_micro as u64 * 10) / 10000; // 0.1% = 10 bps
+        // Reward: 0.1% of size
+        let size_micro = order
+            .payload
+            .qty
+            .unwrap_or_default()
+            .notional(self.last_prices.get(&order.payload.symbol).copied().unwrap_or(0));
+        let reward = (size_micro * KEEPER_REWARD_BPS) / 10000;
+        sim.send(
+            self.id,
+            keeper_id,
+            MessageType::KeeperReward,
+            MessagePayload::KeeperReward(KeeperRewardPayload {
+                order_id: order.id,
+                reward_micro_usd: reward,
+            }),
+        );
+    }
 
+    /// Fill a triggered order held by the exchange through the market / close path. True if it
+    /// filled locally; an on-chain fill for the owner keeps the order until its tx result comes
+    /// back, and a rejected fill drops it.
+    fn execute_local_order(
+        &mut self,
+        sim: &mut dyn SimulatorApi,
+        keeper_id: AgentId,
+        order: &PendingOrder,
+        now_ns: u64,
+    ) -> bool {
+        let fills = self.trades.total();
+        let inflight = self.inflight_executions.get(&order.owner).map_or(0, |q| q.len());
+        match order.payload.order_type {
+            SimOrderType::Increase => {
+                let market_order = MarketOrderPayload {
+                    symbol: order.payload.symbol.clone(),
+                    side: order.payload.side,
+                    qty: order.payload.qty.unwrap_or_default(),
+                    leverage: order.payload.leverage.unwrap_or(5),
+                    decision_price: order.payload.decision_price,
+                };
+                self.process_market_order(sim, order.owner, &market_order, now_ns);
+            }
+            SimOrderType::Decrease => {
+                let close = CloseOrderPayload {
+                    symbol: order.payload.symbol.clone(),
+                    side: order.payload.side,
+                    decision_price: order.payload.decision_price,
+                };
+                self.process_close_order(sim, order.owner, &close, now_ns);
+            }
+        }
+        if self.trades.total() > fills {
+            return true;
+        }
+        if let Some(exec) = self
+            .inflight_executions
+            .get_mut(&order.owner)
+            .filter(|q| q.len() > inflight)
+            .and_then(|q| q.back_mut())
+        {
+            exec.triggered_order = Some((keeper_id, order.clone()));
+        } else {
+            agent_println!(
+                "[Exchange {}] triggered #{} of {} not filled",
+                self.name,
+                order.id,
+                order.owner
+            );
             sim.send(
                 self.id,
-                keeper_id,
-                MessageType::KeeperReward,
-                MessagePayload::KeeperReward(KeeperRewardPayload {
-                    order_id,
-                    reward_micro_usd: reward,
-                }),
+                order.owner,
+                MessageType::OrderCancelled,
+                MessagePayload::Text(format!("order_id:{},reason:rejected", order.id)),
             );
         }
+        false
     }
 
     fn process_close_order(
         &mut self,
-        _sim: &mut dyn SimulatorApi,
+        sim: &mut dyn SimulatorApi,
         from: AgentId,
         order: &CloseOrderPayload,
         now_ns: u64,
     ) {
         if !self.symbols.contains(&order.symbol) {
-            println!(
+            agent_println!(
                 "[Exchange {}] CLOSE REJECTED from {}: unknown symbol {}",
-                self.name, from, order.symbol
+                self.name,
+                from,
+                order.symbol
             );
             return;
         }
+        if self.local_agents.contains(&from) {
+            self.fill_local_close(sim, from, order);
+            return;
+        }
 
         let actor = match self.get_or_create_actor(from) {
             Some(a) => a,
             None => return,
         };
         let side = Self::convert_side_to_vara(order.side);
-        let now_sec: u64 = now_ns / 1_000_000_000;
+        let (now_sec, valid_until) = self.chain_validity(now_ns, 3600);
 
         let position_key = VaraPositionKey { account: actor, side: side.clone() };
         let position = match self.vara_client.get_position(&position_key) {
             Ok(Some(p)) if !p.size_usd.is_zero() => p,
             Ok(_) => {
-                println!(
+                agent_println!(
                     "[Exchange {}] CLOSE REJECTED from {}: no {:?} position for {}",
-                    self.name, from, order.side, order.symbol
+                    self.name,
+                    from,
+                    order.side,
+                    order.symbol
                 );
                 return;
             }
@@ -685,7 +3678,7 @@ impl ExchangeAgent {
             target_leverage_x: 0,
             created_at: now_sec,
             valid_from: now_sec,
-            valid_until: now_sec + 3600,
+            valid_until,
         };
 
         // Build oracle input before spawning
@@ -700,78 +3693,114 @@ impl ExchangeAgent {
             }
         };
 
-        println!(
+        agent_println!(
             "[Exchange {}] ON-CHAIN CLOSE {} from={} side={:?}",
-            self.name, order.symbol, from, order.side
+            self.name,
+            order.symbol,
+            from,
+            order.side
         );
 
         // Fire-and-forget: submit + execute runs in background
+        let chain_order = self.local_fallback.map(|_| onchain_order.clone());
         if let Err(e) = self.vara_client.submit_and_execute_order_async(from, onchain_order, oracle_input) {
             eprintln!(
                 "[Exchange {}] submit_and_execute_order_async(close) failed {} from={}: {}",
                 self.name, order.symbol, from, e
             );
+            return;
         }
+
+        // Expected result of the close at the current oracle price
+        let index = self.scales.get(&order.symbol).index;
+        let current_price = self.last_prices.get(&order.symbol).copied().unwrap_or(0);
+        let size_usd = Usd1e30(u256_from_sails(position.size_usd)).to_micro().0;
+        let size_tokens = index.amount(u256_from_sails(position.size_tokens));
+        let value_micro = size_tokens.value_at(MicroUsd(current_price)).0;
+        let pnl = match order.side {
+            SimSide::Buy => value_micro as i64 - size_usd as i64,
+            SimSide::Sell => size_usd as i64 - value_micro as i64,
+        };
+        let collateral = self.collateral_value(&order.symbol, u256_from_sails(position.collateral_amount));
+        let fee_usd = self.position_fee(&order.symbol, size_usd);
+        self.track_execution(
+            from,
+            InflightExecution {
+                symbol: order.symbol.clone(),
+                side: order.side,
+                order_type: "Decrease",
+                size_usd,
+                collateral,
+                execution_price: current_price,
+                leverage: if collateral > 0 { (size_usd / collateral) as u32 } else { 0 },
+                pnl,
+                fee_usd,
+                decision_price: order.decision_price,
+                triggered_order: None,
+                chain_order,
+                unsynced: None,
+            },
+        );
     }
 
     fn process_market_order(
         &mut self,
-        _sim: &mut dyn SimulatorApi,
+        sim: &mut dyn SimulatorApi,
         from: AgentId,
         order: &MarketOrderPayload,
         now_ns: u64,
     ) {
         if !self.symbols.contains(&order.symbol) {
-            println!(
+            agent_println!(
                 "[Exchange {}] REJECTED from {}: unknown symbol {}",
-                self.name, from, order.symbol
+                self.name,
+                from,
+                order.symbol
             );
             return;
         }
 
-        // Get decimals for proper conversion
-        let (_index_decimals, collateral_decimals) =
-            self.symbol_decimals.get(&order.symbol).copied().unwrap_or((18, 6));
-
         // Verify we have oracle prices
         if self.price_cache.get(&order.symbol).is_none() {
-            println!(
+            agent_println!(
                 "[Exchange {}] REJECTED from {}: no price for {}",
-                self.name, from, order.symbol
+                self.name,
+                from,
+                order.symbol
             );
             return;
         }
+        if self.local_agents.contains(&from) {
+            self.fill_local_increase(sim, from, order);
+            return;
+        }
 
-        let now_sec: u64 = now_ns / 1_000_000_000;
+        let (now_sec, valid_until) = self.chain_validity(now_ns, 3600);
         let actor = match self.get_or_create_actor(from) {
             Some(a) => a,
             None => return,
         };
         let side = Self::convert_side_to_vara(order.side);
 
-        // order.qty = number of tokens as f64 (e.g., 0.5 = 0.5 ETH, 2.0 = 2 ETH)
+        // order.qty = tokens in fixed point (e.g., 0.5 = 0.5 ETH, 2 = 2 ETH)
         // Get current price in micro-USD
         let current_price_micro = self.last_prices.get(&order.symbol).copied().unwrap_or(0);
 
-        // size = qty * price (in micro-USD)
+        // size = qty * price (in micro-USD), exact in integers
         // e.g., 0.5 ETH * $3115 = $1557.50 = 1557_500_000 micro-USD
-        let size_micro = (order.qty * current_price_micro as f64) as u64;
+        let size_micro = order.qty.notional(current_price_micro);
 
         // collateral = size / leverage (in micro-USD)
         // e.g., $1557.50 / 5 = $311.50 = 311_500_000 micro-USD
         let leverage = order.leverage.max(1) as u64;
         let collateral_micro = size_micro / leverage;
 
-        // Convert micro-USD to collateral atoms
-        // For USDC (6 decimals): atoms = micro-USD (same scale)
-        let collateral_atoms = if collateral_decimals >= 6 {
-            U256::from(collateral_micro) * U256::exp10((collateral_decimals - 6) as usize)
-        } else {
-            U256::from(collateral_micro) / U256::exp10((6 - collateral_decimals) as usize)
-        };
+        // Convert micro-USD to collateral atoms at the collateral price
+        // For USDC (6 decimals, $1): atoms = micro-USD (same scale)
+        let collateral_atoms = self.collateral_amount(&order.symbol, collateral_micro);
 
         // Convert size to USD(1e30) for engine
-        let size_usd_1e30 = U256::from(size_micro) * U256::exp10(24);
+        let size_usd_1e30 = MicroUsd(size_micro).to_1e30().0;
 
         let onchain_order = VaraOrder {
             account: actor,
@@ -786,7 +3815,7 @@ impl ExchangeAgent {
             target_leverage_x: order.leverage.max(1),
             created_at: now_sec,
             valid_from: now_sec,
-            valid_until: now_sec + 3600,
+            valid_until,
         };
 
         // Build oracle input before spawning
@@ -801,7 +3830,7 @@ impl ExchangeAgent {
             }
         };
 
-        println!(
+        agent_println!(
             "[Exchange {}] ON-CHAIN MARKET {} from={} side={:?} size=${:.2} leverage={}x",
             self.name,
             order.symbol,
@@ -812,12 +3841,34 @@ impl ExchangeAgent {
         );
 
         // Fire-and-forget: submit + execute runs in background, does NOT block the kernel
+        let chain_order = self.local_fallback.map(|_| onchain_order.clone());
         if let Err(e) = self.vara_client.submit_and_execute_order_async(from, onchain_order, oracle_input) {
             eprintln!(
                 "[Exchange {}] submit_and_execute_order_async failed {} from={}: {}",
                 self.name, order.symbol, from, e
             );
+            return;
         }
+
+        let fee_usd = self.position_fee(&order.symbol, size_micro);
+        self.track_execution(
+            from,
+            InflightExecution {
+                symbol: order.symbol.clone(),
+                side: order.side,
+                order_type: "Increase",
+                size_usd: size_micro,
+                collateral: collateral_micro,
+                execution_price: current_price_micro,
+                leverage: order.leverage.max(1),
+                pnl: 0,
+                fee_usd,
+                decision_price: order.decision_price,
+                triggered_order: None,
+                chain_order,
+                unsynced: None,
+            },
+        );
     }
 
     fn handle_preview_request(
@@ -865,47 +3916,123 @@ impl Agent for ExchangeAgent {
     }
 
     fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
-        println!("[Exchange {}] started with {} market(s)", self.name, self.markets.len());
+        agent_println!("[Exchange {}] started with {} market(s)", self.name, self.markets.len());
         sim.wakeup(self.id, sim.now_ns() + self.poll_interval_ns);
     }
 
     fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
         self.drain_tx_results(sim);
+        self.retry_unsynced(now_ns);
+        self.drain_oco_check(sim);
+        self.start_oco_check();
         self.drain_oi_sync(sim);    // non-blocking: process result if ready
         self.start_oi_fetch();       // kick off next async RPC fetch
+        self.drain_snapshots(sim);
+        self.start_snapshot_fetch(now_ns);
+        self.broadcast_market_stats(sim, now_ns);
+        self.settle_capital(sim);
         sim.wakeup(self.id, now_ns + self.poll_interval_ns);
     }
 
-    fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
-        println!("[Exchange {}] stopped", self.name);
+    fn on_stop(&mut self, sim: &mut dyn SimulatorApi) {
+        if let Some(path) = &self.state_file {
+            match self.state(sim.now_ns()).save(path) {
+                Ok(()) => agent_println!("[Exchange {}] state written to {}", self.name, path.display()),
+                Err(e) => eprintln!("[Exchange {}] state not written to {}: {}", self.name, path.display(), e),
+            }
+        }
+        if self.order_id_drift > 0 {
+            eprintln!(
+                "[Exchange {}] {} on-chain orders had no local counterpart",
+                self.name, self.order_id_drift
+            );
+        }
+        if self.keeper_price_updates > 0 {
+            agent_println!(
+                "[Exchange {}] {} keeper executions carried a just-in-time price",
+                self.name,
+                self.keeper_price_updates
+            );
+        }
+        if self.keeper_batches > 0 {
+            agent_println!(
+                "[Exchange {}] {} keeper executions went out in {} batches",
+                self.name,
+                self.keeper_batched_orders,
+                self.keeper_batches
+            );
+        }
+        let trips: u32 = self.circuit_breakers.values().map(CircuitBreaker::trips).sum();
+        if trips > 0 {
+            agent_println!("[Exchange {}] circuit breakers tripped {} times", self.name, trips);
+        }
+        if self.reverted_txs > 0 {
+            eprintln!(
+                "[Exchange {}] {} successful txs were reverted by simulated reorgs",
+                self.name, self.reverted_txs
+            );
+        }
+        if self.local_fallbacks > 0 {
+            agent_println!(
+                "[Exchange {}] {} orders filled locally after tx failures: {} synced, {} given up, {} still unsynced",
+                self.name,
+                self.local_fallbacks,
+                self.resynced_orders,
+                self.abandoned_resyncs,
+                self.local_fallbacks - self.resynced_orders - self.abandoned_resyncs
+            );
+        }
+        agent_println!("[Exchange {}] stopped", self.name);
     }
 
     fn on_message(&mut self, sim: &mut dyn SimulatorApi, msg: &Message) {
         match msg.msg_type {
-            MessageType::OracleTick => {
-                if let MessagePayload::OracleTick(OracleTickPayload {
-                    symbol,
-                    price,
-                    publish_time: _,
-                    signature: _,
-                }) = &msg.payload
-                {
+            MessageType::OracleTick | MessageType::OracleBatchTick => {
+                let mut updated = false;
+                for OracleTickPayload { symbol, price, .. } in msg.payload.oracle_ticks() {
                     if self.symbols.contains(symbol) {
                         self.price_cache.update(symbol, price.min, price.max);
                         let mid_price = (price.min + price.max) / 2;
                         self.last_prices.insert(symbol.clone(), mid_price);
-
-                        let now_ns = sim.now_ns();
-                        // sync_from_chain runs on wakeup every poll_interval — no need to duplicate here
-                        self.cleanup_expired_orders(sim, now_ns);
+                        self.update_mark_price(symbol, mid_price);
+                        self.update_circuit_breaker(sim, symbol, mid_price);
+                        self.pending_orders.update_trailing(symbol, price);
+                        self.liquidate_local_positions(sim, symbol);
+                        updated = true;
                     }
+                    if let Some(collateral_price) = self.collateral_prices.get_mut(symbol) {
+                        *collateral_price = (price.min, price.max);
+                        // A collateral crash moves the margin of every market it backs
+                        let mut backed: Vec<String> = self
+                            .collateral_feeds
+                            .iter()
+                            .filter(|(_, feed)| *feed == symbol)
+                            .map(|(market, _)| market.clone())
+                            .collect();
+                        backed.sort();
+                        for market in backed {
+                            self.liquidate_local_positions(sim, &market);
+                        }
+                        updated = true;
+                    }
+                }
+                // Once per block, after every symbol of a batch is priced
+                if updated {
+                    let now_ns = sim.now_ns();
+                    // sync_from_chain runs on wakeup every poll_interval — no need to duplicate here
+                    self.cleanup_expired_orders(sim, now_ns);
+                    self.settle_capital(sim);
                 }
             }
 
             MessageType::MarketOrder => {
                 if let MessagePayload::MarketOrder(order) = &msg.payload {
+                    if !self.pre_trade_check(sim, msg.from, order, None) {
+                        return;
+                    }
                     let now_ns = sim.now_ns();
                     self.process_market_order(sim, msg.from, order, now_ns);
+                    self.settle_capital(sim);
                 }
             }
 
@@ -913,11 +4040,62 @@ impl Agent for ExchangeAgent {
                 if let MessagePayload::CloseOrder(order) = &msg.payload {
                     let now_ns = sim.now_ns();
                     self.process_close_order(sim, msg.from, order, now_ns);
+                    self.settle_capital(sim);
+                }
+            }
+
+            MessageType::AddLiquidity => {
+                if let MessagePayload::Liquidity(req) = &msg.payload {
+                    self.process_add_liquidity(sim, msg.from, req);
+                }
+            }
+
+            MessageType::RemoveLiquidity => {
+                if let MessagePayload::Liquidity(req) = &msg.payload {
+                    self.process_remove_liquidity(sim, msg.from, req);
+                }
+            }
+
+            MessageType::SubscribeFills => {
+                if let MessagePayload::SubscribeFills(p) = &msg.payload {
+                    agent_println!("[Exchange {}] {} follows fills of {}", self.name, msg.from, p.leader);
+                    let followers = self.fill_followers.entry(p.leader).or_default();
+                    if !followers.contains(&msg.from) {
+                        followers.push(msg.from);
+                    }
+                }
+            }
+
+            MessageType::SubscribeSnapshots => {
+                if self.snapshot_interval_ns == 0 {
+                    eprintln!(
+                        "[Exchange {}] {} subscribed to snapshots, but snapshots are disabled",
+                        self.name, msg.from
+                    );
+                }
+                if !self.snapshot_subscribers.contains(&msg.from) {
+                    self.snapshot_subscribers.push(msg.from);
                 }
             }
 
             MessageType::SubmitOrder => {
                 if let MessagePayload::Order(order) = &msg.payload {
+                    if !self.price_rules_check(sim, msg.from, order) {
+                        return;
+                    }
+                    // Conditional orders are checked at submission, not again when they trigger
+                    if order.order_type == SimOrderType::Increase && self.validate_order(order).is_ok() {
+                        let increase = MarketOrderPayload {
+                            symbol: order.symbol.clone(),
+                            side: order.side,
+                            qty: order.qty.unwrap_or_default(),
+                            leverage: order.leverage.unwrap_or(5),
+                            decision_price: None,
+                        };
+                        if !self.pre_trade_check(sim, msg.from, &increase, order.trigger_price) {
+                            return;
+                        }
+                    }
                     let now_ns = sim.now_ns();
                     self.process_submit_order(sim, msg.from, order, now_ns);
                 }
@@ -929,10 +4107,17 @@ impl Agent for ExchangeAgent {
                 }
             }
 
+            MessageType::ModifyOrder => {
+                if let MessagePayload::ModifyOrder(change) = &msg.payload {
+                    self.process_modify_order(sim, msg.from, change);
+                }
+            }
+
             MessageType::LimitOrder => {
-                println!(
+                agent_println!(
                     "[Exchange {}] LIMIT_ORDER from {} (use SubmitOrder instead)",
-                    self.name, msg.from
+                    self.name,
+                    msg.from
                 );
             }
 
@@ -951,10 +4136,30 @@ impl Agent for ExchangeAgent {
                 self.handle_get_pending_orders(sim, msg.from);
             }
 
+            MessageType::GetTrades => {
+                if let MessagePayload::GetTrades(query) = &msg.payload {
+                    self.handle_get_trades(sim, msg.from, query);
+                }
+            }
+
+            MessageType::ClaimOrder => {
+                if let MessagePayload::ExecuteOrder(payload) = &msg.payload {
+                    let now_ns = sim.now_ns();
+                    self.handle_claim_order(sim, msg.from, payload.order_id, now_ns);
+                }
+            }
+
             MessageType::ExecuteOrder => {
                 if let MessagePayload::ExecuteOrder(payload) = &msg.payload {
                     let now_ns = sim.now_ns();
-                    self.handle_execute_order_from_keeper(sim, msg.from, payload.order_id, now_ns);
+                    self.handle_execute_order_from_keeper(sim, msg.from, payload, now_ns);
+                }
+            }
+
+            MessageType::ExecuteOrderBatch => {
+                if let MessagePayload::ExecuteOrderBatch(batch) = &msg.payload {
+                    let now_ns = sim.now_ns();
+                    self.handle_execute_order_batch(sim, msg.from, batch, now_ns);
                 }
             }
 
@@ -962,3 +4167,427 @@ impl Agent for ExchangeAgent {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capital::CapitalConfig;
+    use crate::kernel::Kernel;
+    use crate::latency::FixedLatency;
+    use crate::logging::Verbosity;
+    use crate::qty::Qty;
+    use crate::vara::VaraConfig;
+
+    const ETH: &str = "ETH-USD";
+
+    fn exchange() -> ExchangeAgent {
+        let market = MarketConfig {
+            id: 0,
+            symbol: ETH.to_string(),
+            index_token: "ETH".to_string(),
+            collateral_token: "USDT".to_string(),
+            collateral_amount: 0,
+            index_amount: 0,
+            liquidity_usd: 1_000_000_000_000,
+            index_decimals: 18,
+            collateral_decimals: 6,
+            position_fee_bps: 10,
+            fee_rates: FeeRates::default(),
+            collateral_price: None,
+            price_rules: PriceRules::default(),
+            mark_price: None,
+            circuit_breaker: None,
+            oi_caps: OiCaps::default(),
+        };
+        // Never connected: local agents only
+        let config = VaraConfig::new(
+            "ws://localhost:9944",
+            format!("0x{}", "00".repeat(32)),
+            "keys",
+            "keys/.passphrase",
+        );
+        let client = Arc::new(VaraClient::new(config).unwrap());
+        let mut exchange = ExchangeAgent::new(1, "Exchange".to_string(), vec![market], client, None, 0);
+        exchange.set_local_agents(HashSet::from([7, 8]));
+        exchange.set_capital(CapitalLedger::new(
+            CapitalConfig::default(),
+            HashMap::from([(8, 10_000_000_000)]),
+        ));
+        exchange
+    }
+
+    #[test]
+    fn test_warm_start_keeps_lp_value_and_balances() {
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(0, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        let mut before = exchange();
+        let deposit = LiquidityPayload {
+            symbol: ETH.to_string(),
+            amount_usd: 250_000_000_000,
+        };
+        before.process_add_liquidity(&mut kernel, 7, &deposit);
+        before.book_pool_pnl(&mut kernel, ETH, 40_000_000, "position_fee");
+        before.book_pool_pnl(&mut kernel, ETH, -90_000_000, "trader_pnl");
+        before.capital.book(8, -1_500_000);
+        let state = before.state(0);
+
+        let mut after = exchange();
+        after.restore_state(state.clone());
+        assert_eq!(after.pool_liquidity(ETH), 1_249_950_000_000);
+        assert_eq!(after.lp_value(7, ETH), before.lp_value(7, ETH));
+        assert_eq!(after.capital.balance(8), Some(9_998_500_000));
+        assert_eq!(after.state(0), state);
+    }
+
+    fn quiet_kernel() -> Kernel {
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(0, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        kernel
+    }
+
+    /// ETH position of `size_usd` opened at $2000 (micro-USD)
+    fn position(size_usd: u64, collateral: u64, collateral_tokens: f64) -> LocalPosition {
+        LocalPosition {
+            size_usd,
+            size_tokens: size_usd as f64 / 2_000_000_000.0,
+            collateral,
+            collateral_tokens,
+        }
+    }
+
+    #[test]
+    fn test_cross_margin_charge_is_pro_rata() {
+        let mut kernel = quiet_kernel();
+        let mut ex = exchange();
+        ex.set_cross_margin(HashSet::from([8]));
+        let long = (8, ETH.to_string(), SimSide::Buy);
+        let short = (8, ETH.to_string(), SimSide::Sell);
+        ex.local_positions
+            .insert(long.clone(), position(10_000_000_000, 1_000_000_000, 500.0));
+        ex.local_positions
+            .insert(short.clone(), position(2_000_000_000, 3_000_000_000, 3_000.0));
+
+        ex.charge_cross_collateral(&mut kernel, 8, 400_000_000);
+
+        let (long, short) = (&ex.local_positions[&long], &ex.local_positions[&short]);
+        assert_eq!((long.collateral, short.collateral), (900_000_000, 2_700_000_000));
+        // Collateral tokens shrink with the collateral they back
+        assert!((long.collateral_tokens - 450.0).abs() < 1e-9);
+        assert!((short.collateral_tokens - 2_700.0).abs() < 1e-9);
+        assert_eq!(ex.pool_pnl[ETH], 400_000_000);
+        assert_eq!(ex.capital.balance(8), Some(9_600_000_000));
+    }
+
+    #[test]
+    fn test_cross_margin_liquidates_lowest_equity_first() {
+        let mut kernel = quiet_kernel();
+        let mut ex = exchange();
+        ex.set_cross_margin(HashSet::from([8]));
+        let long = (8, ETH.to_string(), SimSide::Buy);
+        let short = (8, ETH.to_string(), SimSide::Sell);
+        ex.local_positions
+            .insert(long.clone(), position(10_000_000_000, 500_000_000, 500.0));
+        ex.local_positions
+            .insert(short.clone(), position(2_000_000_000, 500_000_000, 500.0));
+
+        // $1800: long -$500 and short +$700 of equity cover the $120 maintenance margin
+        ex.last_prices.insert(ETH.to_string(), 1_800_000_000);
+        ex.liquidate_local_positions(&mut kernel, ETH);
+        assert_eq!(ex.local_positions.len(), 2);
+
+        // $1700: -$1000 + $800 is below it; the long goes first, its $1000 loss beyond its
+        // collateral takes the short's $500
+        ex.last_prices.insert(ETH.to_string(), 1_700_000_000);
+        ex.liquidate_local_positions(&mut kernel, ETH);
+        assert!(!ex.local_positions.contains_key(&long));
+        assert_eq!(ex.local_positions[&short].collateral, 0);
+        assert_eq!(ex.pool_pnl[ETH], 1_000_000_000);
+        assert_eq!(ex.capital.balance(8), Some(9_000_000_000));
+    }
+
+    /// Exchange whose ETH breaker halts increases for 30s after a move of more than 5% within a minute
+    fn exchange_with_breaker() -> ExchangeAgent {
+        let mut ex = exchange();
+        let config = CircuitBreakerConfig {
+            move_pct: 5.0,
+            window_sec: 60,
+            halt_sec: 30,
+            allow_liquidations: true,
+        };
+        ex.circuit_breakers.insert(ETH.to_string(), CircuitBreaker::new(config));
+        ex
+    }
+
+    fn tick(ex: &mut ExchangeAgent, kernel: &mut Kernel, at_sec: u64, price: u64) {
+        kernel.set_start_time(at_sec * 1_000_000_000);
+        let payload = OracleTickPayload {
+            symbol: ETH.to_string(),
+            price: Price { min: price, max: price },
+            publish_time: at_sec,
+            signature: Arc::from(Vec::new()),
+        };
+        let msg = Message::new(
+            1,
+            2,
+            MessageType::OracleTick,
+            kernel.now_ns(),
+            MessagePayload::OracleTick(payload),
+        );
+        ex.on_message(kernel, &msg);
+    }
+
+    fn buy(qty: f64) -> MarketOrderPayload {
+        MarketOrderPayload {
+            symbol: ETH.to_string(),
+            side: SimSide::Buy,
+            qty: Qty::from_tokens(qty),
+            leverage: 5,
+            decision_price: None,
+        }
+    }
+
+    #[test]
+    fn test_circuit_breaker_halts_increases_but_not_closes() {
+        let mut kernel = quiet_kernel();
+        let mut ex = exchange_with_breaker();
+        tick(&mut ex, &mut kernel, 0, 2_000_000_000);
+        ex.process_market_order(&mut kernel, 8, &buy(1.0), 0);
+        assert!(ex.pre_trade_check(&mut kernel, 8, &buy(1.0), None));
+
+        // +10% trips the breaker until 31s
+        tick(&mut ex, &mut kernel, 1, 2_200_000_000);
+        assert_eq!(ex.halted_until(ETH, kernel.now_ns()), Some(31_000_000_000));
+        assert!(!ex.pre_trade_check(&mut kernel, 8, &buy(1.0), None));
+
+        let close = CloseOrderPayload {
+            symbol: ETH.to_string(),
+            side: SimSide::Buy,
+            decision_price: None,
+        };
+        ex.process_close_order(&mut kernel, 8, &close, kernel.now_ns());
+        assert!(ex.local_positions.is_empty());
+
+        tick(&mut ex, &mut kernel, 31, 2_200_000_000);
+        assert!(ex.pre_trade_check(&mut kernel, 8, &buy(1.0), None));
+    }
+
+    #[test]
+    fn test_circuit_breaker_defers_triggered_increases() {
+        let mut kernel = quiet_kernel();
+        let mut ex = exchange_with_breaker();
+        let order_id = LOCAL_ORDER_ID_BASE + 1;
+        ex.pending_orders.insert(PendingOrder {
+            id: order_id,
+            owner: 8,
+            payload: OrderPayload {
+                symbol: ETH.to_string(),
+                side: SimSide::Buy,
+                order_type: SimOrderType::Increase,
+                execution_type: ExecutionType::Limit,
+                qty: Some(Qty::from_tokens(1.0)),
+                leverage: Some(5),
+                size_delta_usd: None,
+                trigger_price: Some(2_300_000_000),
+                acceptable_price: None,
+                trailing_distance: None,
+                valid_for_sec: None,
+                oco_group: None,
+                decision_price: None,
+            },
+            valid_until_ns: u64::MAX,
+        });
+        let execute = ExecuteOrderPayload {
+            order_id,
+            price_update: None,
+        };
+        tick(&mut ex, &mut kernel, 0, 2_000_000_000);
+        tick(&mut ex, &mut kernel, 1, 2_200_000_000);
+
+        // Triggered, but halted: the order stays in the book for a later keeper
+        ex.handle_execute_order_from_keeper(&mut kernel, 5, &execute, kernel.now_ns());
+        assert!(ex.pending_orders.get(order_id).is_some());
+        assert!(ex.local_positions.is_empty());
+
+        tick(&mut ex, &mut kernel, 31, 2_200_000_000);
+        ex.handle_execute_order_from_keeper(&mut kernel, 5, &execute, kernel.now_ns());
+        assert!(ex.pending_orders.get(order_id).is_none());
+        assert!(ex.local_positions.contains_key(&(8, ETH.to_string(), SimSide::Buy)));
+    }
+
+    #[test]
+    fn test_transient_tx_errors() {
+        for error in [
+            "RPC call timed out",
+            "Connection reset by peer",
+            "Transport error: WebSocket closed",
+            "Node unavailable",
+            "Runtime error: Extrinsic dropped",
+        ] {
+            assert!(is_transient_tx_error(error), "{error}");
+        }
+        for error in ["Panic: insufficient collateral", "InvalidSignature", "order expired"] {
+            assert!(!is_transient_tx_error(error), "{error}");
+        }
+    }
+
+    /// Collects the kinds of the events it sees
+    struct EventLog(Arc<std::sync::Mutex<Vec<crate::events::EventKind>>>);
+
+    impl crate::events::EventListener for EventLog {
+        fn on_event(&mut self, event: &SimEvent) {
+            self.0.lock().unwrap().push(event.kind());
+        }
+    }
+
+    /// $10k long increase at $2000 of agent 8 sent on-chain, waiting for its result
+    fn sent_increase() -> InflightExecution {
+        InflightExecution {
+            symbol: ETH.to_string(),
+            side: SimSide::Buy,
+            order_type: "Increase",
+            size_usd: 10_000_000_000,
+            collateral: 2_000_000_000,
+            execution_price: 2_000_000_000,
+            leverage: 5,
+            pnl: 0,
+            fee_usd: 10_000_000,
+            decision_price: None,
+            triggered_order: None,
+            chain_order: Some(VaraOrder {
+                account: ActorId::zero(),
+                side: VaraSide::Long,
+                order_type: VaraOrderType::Increase,
+                execution_type: VaraExecutionType::Market,
+                collateral_delta_tokens: u256_to_sails(U256::zero()),
+                size_delta_usd: u256_to_sails(U256::zero()),
+                trigger_price: None,
+                acceptable_price: None,
+                withdraw_collateral_amount: u256_to_sails(U256::zero()),
+                target_leverage_x: 5,
+                created_at: 0,
+                valid_from: 0,
+                valid_until: 3600,
+            }),
+            unsynced: None,
+        }
+    }
+
+    fn failed_execution(error: &str, order_id: Option<u64>) -> TxResult {
+        TxResult {
+            agent_id: 8,
+            tx_type: TxType::SubmitAndExecute,
+            success: false,
+            order_id,
+            error: Some(error.to_string()),
+            detail: String::new(),
+            latency_ms: 0,
+            tip: 0,
+            fee: None,
+            tx_id: 0,
+            reverted: false,
+        }
+    }
+
+    #[test]
+    fn test_local_fallback_covers_only_transient_failures() {
+        let mut kernel = quiet_kernel();
+        let mut ex = exchange();
+        ex.set_local_fallback(LocalFallbackConfig::default());
+
+        // The contract refused it, or it already has an order id: the agent gets OrderRejected
+        for (error, order_id) in [("Panic: insufficient collateral", None), ("RPC timeout", Some(3))] {
+            ex.track_execution(8, sent_increase());
+            assert!(!ex.fall_back_locally(&mut kernel, &failed_execution(error, order_id)));
+            ex.inflight_executions.clear();
+        }
+        assert!(ex.trades.is_empty());
+
+        ex.track_execution(8, sent_increase());
+        assert!(ex.fall_back_locally(&mut kernel, &failed_execution("RPC timeout", None)));
+        assert_eq!(ex.trades.len(), 1);
+        assert_eq!(ex.capital.balance(8), Some(9_990_000_000));
+        let (retry_at, agent, exec) = &ex.unsynced_orders[0];
+        assert_eq!((*retry_at, *agent, exec.unsynced), (5_000_000_000, 8, Some(0)));
+    }
+
+    #[test]
+    fn test_abandoned_resync_reverses_the_local_fill() {
+        let mut kernel = quiet_kernel();
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        kernel.event_bus_mut().subscribe(Box::new(EventLog(events.clone())));
+        let mut ex = exchange();
+        ex.set_local_fallback(LocalFallbackConfig {
+            retry_after_ms: 1_000,
+            max_retries: 2,
+        });
+        ex.track_execution(8, sent_increase());
+        assert!(ex.fall_back_locally(&mut kernel, &failed_execution("RPC timeout", None)));
+
+        // Resync attempts that fail transiently go back to the queue until `max_retries`
+        let resync = |ex: &mut ExchangeAgent, kernel: &mut Kernel, error: &str| {
+            let (_, agent, mut exec) = ex.unsynced_orders.pop().unwrap();
+            exec.unsynced = exec.unsynced.map(|attempts| attempts + 1);
+            ex.track_execution(agent, exec);
+            assert!(ex.fall_back_locally(kernel, &failed_execution(error, None)));
+        };
+        resync(&mut ex, &mut kernel, "connection reset");
+        assert_eq!((ex.unsynced_orders.len(), ex.abandoned_resyncs), (1, 0));
+        resync(&mut ex, &mut kernel, "connection reset");
+        assert_eq!((ex.unsynced_orders.len(), ex.abandoned_resyncs), (0, 1));
+        assert!(ex.trades.is_empty());
+        assert_eq!(ex.trades.total(), 1);
+        assert_eq!(ex.capital.balance(8), Some(10_000_000_000));
+        assert!(events
+            .lock()
+            .unwrap()
+            .contains(&crate::events::EventKind::LocalFillAbandoned));
+
+        // A non-transient error gives up right away
+        ex.track_execution(8, sent_increase());
+        assert!(ex.fall_back_locally(&mut kernel, &failed_execution("RPC timeout", None)));
+        resync(&mut ex, &mut kernel, "Panic: order expired");
+        assert_eq!((ex.unsynced_orders.len(), ex.abandoned_resyncs), (0, 2));
+        assert_eq!(ex.capital.balance(8), Some(10_000_000_000));
+    }
+
+    #[test]
+    fn test_lp_shares_mint_and_burn_at_pool_value() {
+        const K: i128 = 1_000_000_000; // $1000
+        let mut kernel = quiet_kernel();
+        let mut ex = exchange();
+        let liquidity = |amount_usd| LiquidityPayload {
+            symbol: ETH.to_string(),
+            amount_usd,
+        };
+        let near = |value: i128, expected: i128| (value - expected).abs() <= 1;
+
+        // $1M seeded pool: the first LP gets shares at par
+        ex.process_add_liquidity(&mut kernel, 7, &liquidity(250 * K));
+        assert!(near(ex.lp_value(7, ETH), 250 * K));
+        ex.book_pool_pnl(&mut kernel, ETH, 125 * K, "position_fee");
+        assert!(near(ex.lp_value(7, ETH), 275 * K));
+
+        // A later deposit buys in at the grown pool value and doesn't dilute the first LP
+        ex.process_add_liquidity(&mut kernel, 8, &liquidity(275 * K));
+        assert!(near(ex.lp_value(8, ETH), 275 * K));
+        assert!(near(ex.lp_value(7, ETH), 275 * K));
+        assert_eq!(ex.pool_liquidity(ETH), 1_650 * K);
+
+        // Withdrawals are capped at the share value...
+        ex.process_remove_liquidity(&mut kernel, 8, &liquidity(1_000 * K));
+        assert!(near(ex.lp_value(8, ETH), 0));
+        assert!(near(ex.pool_liquidity(ETH), 1_375 * K));
+        // ...and at the liquidity not backing open interest
+        ex.last_oi = (1_300 * K, 0);
+        ex.process_remove_liquidity(&mut kernel, 7, &liquidity(100 * K));
+        assert!(near(ex.pool_liquidity(ETH), 1_300 * K));
+        assert!(near(ex.lp_value(7, ETH), 200 * K));
+
+        // Rejected: an on-chain agent (no RemoveLiquidity on the contract), an empty deposit
+        ex.last_oi = (0, 0);
+        ex.process_remove_liquidity(&mut kernel, 9, &liquidity(100 * K));
+        ex.process_add_liquidity(&mut kernel, 7, &liquidity(0));
+        assert!(near(ex.pool_liquidity(ETH), 1_300 * K));
+        assert!(near(ex.lp_value(7, ETH), 200 * K));
+    }
+}
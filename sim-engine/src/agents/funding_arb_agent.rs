@@ -0,0 +1,567 @@
+//! FundingArbAgent - harvests funding when the rate is extreme.
+//!
+//! Funding flows from the larger OI side to the smaller one. The rate is
+//! estimated from the market state like the contract's funding model:
+//!   rate (bps/hour) = daily_rate_bps / 24 * (oi_long - oi_short) / (oi_long + oi_short)
+//! (positive: longs pay). Once |rate| reaches `entry_rate_bps_hour` the agent
+//! opens `qty` on the receiving side and, with `hedge_symbol`, the opposite
+//! side of a correlated market (`hedge_ratio` of the notional) to stay close to
+//! delta-neutral. With `hedge_venue` the hedge is spot of the same symbol
+//! swapped on a SwapExchangeAgent instead (a basis trade). Both legs are closed
+//! once the rate normalizes below `exit_rate_bps_hour`, flips against the
+//! position, or after `max_hold_sec`.
+//!
+//! A perp hedge leg trades a second market from the same account, so hedged
+//! agents must be listed in `vara.local_agents`.
+
+use std::collections::HashMap;
+
+use crate::agent_println;
+use crate::agents::Agent;
+use crate::messages::{
+    AgentId, CloseOrderPayload, MarketOrderPayload, MarketStatePayload, Message, MessagePayload,
+    MessageType, Side, SimulatorApi, SwapPayload,
+};
+use crate::qty::Qty;
+
+#[derive(Debug, Clone)]
+pub struct FundingArbConfig {
+    pub name: String,
+    pub exchange_id: AgentId,
+    pub symbol: String,
+    /// Correlated market for the hedge leg (None = unhedged)
+    pub hedge_symbol: Option<String>,
+    /// Spot venue (SwapExchangeAgent) hedging with spot of `symbol` instead
+    pub hedge_venue: Option<AgentId>,
+    /// Hedge notional as a fraction of the main leg's
+    pub hedge_ratio: f64,
+    pub qty: f64,
+    pub leverage: u32,
+    /// Funding rate of the market at full imbalance (bps per day)
+    pub daily_rate_bps: f64,
+    pub entry_rate_bps_hour: f64,
+    pub exit_rate_bps_hour: f64,
+    pub max_hold_sec: u64,
+}
+
+#[derive(Debug, Clone)]
+struct ArbPosition {
+    side: Side,
+    opened_at_ns: u64,
+    size_usd: f64,
+    hedge: Option<Hedge>,
+}
+
+#[derive(Debug, Clone)]
+enum Hedge {
+    /// Correlated perp market: (symbol, side)
+    Perp(String, Side),
+    /// Spot of the main symbol on the hedge venue: (side, tokens)
+    Spot(Side, f64),
+}
+
+fn opposite(side: Side) -> Side {
+    match side {
+        Side::Buy => Side::Sell,
+        Side::Sell => Side::Buy,
+    }
+}
+
+pub struct FundingArbAgent {
+    id: AgentId,
+    name: String,
+    exchange_id: AgentId,
+    symbol: String,
+    address: Option<String>,
+
+    hedge_symbol: Option<String>,
+    hedge_venue: Option<AgentId>,
+    hedge_ratio: f64,
+    qty: f64,
+    leverage: u32,
+    daily_rate_bps: f64,
+    entry_rate_bps_hour: f64,
+    exit_rate_bps_hour: f64,
+    max_hold_ns: u64,
+
+    /// Oracle mid prices per symbol (micro-USD)
+    prices: HashMap<String, u64>,
+    position: Option<ArbPosition>,
+    /// Time and rate of the previous market state, for the funding estimate
+    last_rate: Option<(u64, f64)>,
+
+    trades_opened: u32,
+    trades_closed: u32,
+    liquidations: u32,
+    /// Estimated funding received minus paid (micro-USD)
+    funding_earned_usd: f64,
+    max_abs_rate_bps_hour: f64,
+}
+
+impl FundingArbAgent {
+    pub fn new(id: AgentId, config: FundingArbConfig) -> Self {
+        Self {
+            id,
+            name: config.name,
+            exchange_id: config.exchange_id,
+            symbol: config.symbol,
+            address: None,
+            hedge_symbol: config.hedge_symbol,
+            hedge_venue: config.hedge_venue,
+            hedge_ratio: config.hedge_ratio,
+            qty: config.qty,
+            leverage: config.leverage.max(1),
+            daily_rate_bps: config.daily_rate_bps,
+            entry_rate_bps_hour: config.entry_rate_bps_hour,
+            exit_rate_bps_hour: config.exit_rate_bps_hour,
+            max_hold_ns: config.max_hold_sec * 1_000_000_000,
+            prices: HashMap::new(),
+            position: None,
+            last_rate: None,
+            trades_opened: 0,
+            trades_closed: 0,
+            liquidations: 0,
+            funding_earned_usd: 0.0,
+            max_abs_rate_bps_hour: 0.0,
+        }
+    }
+
+    pub fn set_address(&mut self, address: String) {
+        self.address = Some(address);
+    }
+
+    /// Estimated funding rate in bps per hour (positive: longs pay shorts)
+    fn funding_rate_bps_hour(&self, state: &MarketStatePayload) -> f64 {
+        let total = state.oi_long_usd + state.oi_short_usd;
+        if total <= 0 {
+            return 0.0;
+        }
+        let imbalance = (state.oi_long_usd - state.oi_short_usd) as f64 / total as f64;
+        self.daily_rate_bps / 24.0 * imbalance
+    }
+
+    /// Rate received by a position on `side` (negative: the position pays)
+    fn received_rate(side: Side, rate: f64) -> f64 {
+        match side {
+            Side::Buy => -rate,
+            Side::Sell => rate,
+        }
+    }
+
+    fn send_market(&self, sim: &mut dyn SimulatorApi, symbol: &str, side: Side, qty: f64) {
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::MarketOrder,
+            MessagePayload::MarketOrder(MarketOrderPayload {
+                symbol: symbol.to_string(),
+                side,
+                qty: Qty::from_tokens(qty),
+                leverage: self.leverage,
+                decision_price: sim.market_data().mid(symbol),
+            }),
+        );
+    }
+
+    fn send_close(&self, sim: &mut dyn SimulatorApi, symbol: &str, side: Side) {
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::CloseOrder,
+            MessagePayload::CloseOrder(CloseOrderPayload {
+                symbol: symbol.to_string(),
+                side,
+                decision_price: sim.market_data().mid(symbol),
+            }),
+        );
+    }
+
+    fn send_swap(&self, sim: &mut dyn SimulatorApi, venue: AgentId, side: Side, qty: f64) {
+        sim.send(
+            self.id,
+            venue,
+            MessageType::Swap,
+            MessagePayload::Swap(SwapPayload {
+                symbol: self.symbol.clone(),
+                side,
+                qty: Qty::from_tokens(qty),
+                limit_price: None,
+            }),
+        );
+    }
+
+    /// Send the orders that take `hedge` off
+    fn unwind_hedge(&self, sim: &mut dyn SimulatorApi, hedge: &Hedge) {
+        match hedge {
+            Hedge::Perp(symbol, side) => self.send_close(sim, symbol, *side),
+            Hedge::Spot(side, qty) => {
+                if let Some(venue) = self.hedge_venue {
+                    self.send_swap(sim, venue, opposite(*side), *qty);
+                }
+            }
+        }
+    }
+
+    fn open(&mut self, sim: &mut dyn SimulatorApi, rate: f64, now_ns: u64) {
+        let Some(&price) = self.prices.get(&self.symbol) else {
+            return;
+        };
+        // Longs pay -> collect on the short side and vice versa
+        let side = if rate > 0.0 { Side::Sell } else { Side::Buy };
+        let size_usd = self.qty * price as f64;
+        self.send_market(sim, &self.symbol, side, self.qty);
+
+        let mut hedge = None;
+        if let Some(hedge_symbol) = self.hedge_symbol.clone() {
+            match self.prices.get(&hedge_symbol).filter(|p| **p > 0) {
+                Some(&hedge_price) if self.hedge_ratio > 0.0 => {
+                    let hedge_qty = size_usd * self.hedge_ratio / hedge_price as f64;
+                    self.send_market(sim, &hedge_symbol, opposite(side), hedge_qty);
+                    hedge = Some(Hedge::Perp(hedge_symbol, opposite(side)));
+                }
+                Some(_) => {}
+                None => eprintln!("[FArb {}] no price for {}, opening unhedged", self.name, hedge_symbol),
+            }
+        } else if let Some(venue) = self.hedge_venue.filter(|_| self.hedge_ratio > 0.0) {
+            let hedge_qty = self.qty * self.hedge_ratio;
+            self.send_swap(sim, venue, opposite(side), hedge_qty);
+            hedge = Some(Hedge::Spot(opposite(side), hedge_qty));
+        }
+
+        agent_println!(
+            "[FArb {}] OPEN {:?} {} ${:.2} rate={:+.3}bps/h{}",
+            self.name,
+            side,
+            self.symbol,
+            size_usd / 1_000_000.0,
+            rate,
+            match &hedge {
+                Some(Hedge::Perp(symbol, side)) => format!(" hedge {:?} {}", side, symbol),
+                Some(Hedge::Spot(side, qty)) => format!(" hedge spot {:?} {:.4}", side, qty),
+                None => String::new(),
+            }
+        );
+        self.position = Some(ArbPosition {
+            side,
+            opened_at_ns: now_ns,
+            size_usd,
+            hedge,
+        });
+        self.trades_opened += 1;
+    }
+
+    fn close(&mut self, sim: &mut dyn SimulatorApi, reason: &str, rate: f64) {
+        let Some(position) = self.position.take() else {
+            return;
+        };
+        agent_println!(
+            "[FArb {}] CLOSE {:?} {} ({}) rate={:+.3}bps/h",
+            self.name,
+            position.side,
+            self.symbol,
+            reason,
+            rate
+        );
+        self.send_close(sim, &self.symbol, position.side);
+        if let Some(hedge) = &position.hedge {
+            self.unwind_hedge(sim, hedge);
+        }
+        self.trades_closed += 1;
+    }
+
+    fn on_market_state(&mut self, sim: &mut dyn SimulatorApi, state: &MarketStatePayload) {
+        let now_ns = sim.now_ns();
+        let rate = self.funding_rate_bps_hour(state);
+        self.max_abs_rate_bps_hour = self.max_abs_rate_bps_hour.max(rate.abs());
+
+        // Accrue the funding estimate at the previous rate
+        if let (Some(position), Some((last_ns, last_rate))) = (&self.position, self.last_rate) {
+            let hours = now_ns.saturating_sub(last_ns.max(position.opened_at_ns)) as f64 / 3.6e12;
+            self.funding_earned_usd +=
+                Self::received_rate(position.side, last_rate) / 10_000.0 * position.size_usd * hours;
+        }
+        self.last_rate = Some((now_ns, rate));
+
+        match &self.position {
+            None if rate.abs() >= self.entry_rate_bps_hour => self.open(sim, rate, now_ns),
+            Some(position) => {
+                let received = Self::received_rate(position.side, rate);
+                if received < 0.0 {
+                    self.close(sim, "rate flipped", rate);
+                } else if received <= self.exit_rate_bps_hour {
+                    self.close(sim, "normalized", rate);
+                } else if now_ns.saturating_sub(position.opened_at_ns) >= self.max_hold_ns {
+                    self.close(sim, "max hold", rate);
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+impl Agent for FundingArbAgent {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        match (key, value.as_f64()) {
+            ("qty", Some(v)) if v > 0.0 => self.qty = v,
+            ("leverage", Some(v)) if v >= 1.0 => self.leverage = v as u32,
+            ("hedge_ratio", Some(v)) if v >= 0.0 => self.hedge_ratio = v,
+            ("daily_rate_bps", Some(v)) if v >= 0.0 => self.daily_rate_bps = v,
+            ("entry_rate_bps_hour", Some(v)) if v > 0.0 => self.entry_rate_bps_hour = v,
+            ("exit_rate_bps_hour", Some(v)) if v >= 0.0 => self.exit_rate_bps_hour = v,
+            _ => return false,
+        }
+        agent_println!("[FArb {}] param {} = {}", self.name, key, value);
+        true
+    }
+
+    fn on_start(&mut self, _sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[FArb {}] START {}{}{} entry={}bps/h exit={}bps/h {}x{}",
+            self.name,
+            self.symbol,
+            self.hedge_symbol
+                .as_deref()
+                .map(|hedge| format!(" hedge={}", hedge))
+                .unwrap_or_default(),
+            self.hedge_venue
+                .map(|venue| format!(" spot_hedge@{}", venue))
+                .unwrap_or_default(),
+            self.entry_rate_bps_hour,
+            self.exit_rate_bps_hour,
+            self.leverage,
+            self.address
+                .as_deref()
+                .map(|addr| format!(" addr={}", addr))
+                .unwrap_or_default()
+        );
+    }
+
+    fn on_wakeup(&mut self, _sim: &mut dyn SimulatorApi, _now_ns: u64) {}
+
+    fn on_message(&mut self, sim: &mut dyn SimulatorApi, msg: &Message) {
+        match &msg.payload {
+            MessagePayload::OracleTick(_) | MessagePayload::OracleBatchTick(_) => {
+                for p in msg.payload.oracle_ticks() {
+                    self.prices.insert(p.symbol.clone(), (p.price.min + p.price.max) / 2);
+                }
+            }
+            MessagePayload::MarketState(p) if p.symbol == self.symbol => self.on_market_state(sim, p),
+            MessagePayload::PositionLiquidated(p) => {
+                let Some(position) = &self.position else {
+                    return;
+                };
+                let main_leg = p.symbol == self.symbol && p.side == position.side;
+                let hedge_leg = matches!(
+                    &position.hedge,
+                    Some(Hedge::Perp(symbol, side)) if *symbol == p.symbol && *side == p.side
+                );
+                if !main_leg && !hedge_leg {
+                    return;
+                }
+                agent_println!(
+                    "[FArb {}] LIQUIDATED {:?} {} pnl=${:.2}",
+                    self.name,
+                    p.side,
+                    p.symbol,
+                    p.pnl as f64 / 1_000_000.0
+                );
+                self.liquidations += 1;
+                // The remaining leg is no longer hedged: close it
+                if let Some(position) = self.position.take() {
+                    if !main_leg {
+                        self.send_close(sim, &self.symbol, position.side);
+                    } else if let Some(hedge) = &position.hedge {
+                        self.unwind_hedge(sim, hedge);
+                    }
+                }
+            }
+            MessagePayload::Text(reason)
+                if msg.msg_type == MessageType::OrderRejected && reason.starts_with("swap:") =>
+            {
+                // The spot hedge didn't fill: the position runs unhedged
+                if let Some(position) = self.position.as_mut() {
+                    if matches!(position.hedge, Some(Hedge::Spot(..))) {
+                        eprintln!("[FArb {}] spot hedge rejected ({}), unhedged", self.name, reason);
+                        position.hedge = None;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[FArb {}] STOP: open={} close={} liq={} funding_est=${:.2} max|rate|={:.3}bps/h",
+            self.name,
+            self.trades_opened,
+            self.trades_closed,
+            self.liquidations,
+            self.funding_earned_usd / 1_000_000.0,
+            self.max_abs_rate_bps_hour
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::test_support::{delivered, kernel_with_recorder, liquidated, oracle_tick};
+    use crate::kernel::Kernel;
+
+    const EXCHANGE: AgentId = 1;
+    const FARB: AgentId = 35;
+    const USD: u64 = 1_000_000;
+
+    fn funding_arb(max_hold_sec: u64) -> FundingArbAgent {
+        FundingArbAgent::new(
+            FARB,
+            FundingArbConfig {
+                name: "farb".to_string(),
+                exchange_id: EXCHANGE,
+                symbol: "ETH-USD".to_string(),
+                hedge_symbol: Some("BTC-USD".to_string()),
+                hedge_venue: None,
+                hedge_ratio: 0.5,
+                qty: 1.0,
+                leverage: 2,
+                // 10 bps/hour at full imbalance
+                daily_rate_bps: 240.0,
+                entry_rate_bps_hour: 5.0,
+                exit_rate_bps_hour: 1.0,
+                max_hold_sec,
+            },
+        )
+    }
+
+    /// Agent that has seen ETH at $2000 and BTC at $40k
+    fn priced(kernel: &mut Kernel, max_hold_sec: u64) -> FundingArbAgent {
+        let mut farb = funding_arb(max_hold_sec);
+        farb.on_message(kernel, &oracle_tick(FARB, "ETH-USD", 2_000 * USD));
+        farb.on_message(kernel, &oracle_tick(FARB, "BTC-USD", 40_000 * USD));
+        farb
+    }
+
+    /// Market state with `long_k` / `short_k` thousand USD of OI
+    fn state(long_k: i128, short_k: i128) -> Message {
+        let state = MarketStatePayload {
+            symbol: "ETH-USD".to_string(),
+            oi_long_usd: long_k * 1_000 * USD as i128,
+            oi_short_usd: short_k * 1_000 * USD as i128,
+            liquidity_usd: 1_000_000 * USD as i128,
+            pool_pnl_usd: 0,
+            funding_rate_bps_hour_fp: 0,
+            borrowing_rate_bps_hour_fp: 0,
+        };
+        Message::new(
+            FARB,
+            EXCHANGE,
+            MessageType::MarketState,
+            0,
+            MessagePayload::MarketState(state),
+        )
+    }
+
+    fn orders(msgs: &[Message]) -> Vec<(MessageType, String, Side, Option<Qty>)> {
+        msgs.iter()
+            .map(|msg| match &msg.payload {
+                MessagePayload::MarketOrder(o) => (msg.msg_type, o.symbol.clone(), o.side, Some(o.qty)),
+                MessagePayload::CloseOrder(c) => (msg.msg_type, c.symbol.clone(), c.side, None),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_enters_past_the_rate_threshold_with_a_hedge_and_exits_on_normalization() {
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut farb = priced(&mut kernel, 3_600);
+
+        // 60/40 is 2 bps/hour: below the entry
+        farb.on_message(&mut kernel, &state(6, 4));
+        assert!(delivered(&mut kernel, &exchange).is_empty());
+
+        // 90/10 is 8 bps/hour paid by longs: short ETH, hedged with half the notional of long BTC
+        farb.on_message(&mut kernel, &state(9, 1));
+        assert_eq!(
+            orders(&delivered(&mut kernel, &exchange)),
+            [
+                (
+                    MessageType::MarketOrder,
+                    "ETH-USD".to_string(),
+                    Side::Sell,
+                    Some(Qty::from_tokens(1.0))
+                ),
+                (
+                    MessageType::MarketOrder,
+                    "BTC-USD".to_string(),
+                    Side::Buy,
+                    Some(Qty::from_tokens(0.025))
+                ),
+            ]
+        );
+
+        // 4 bps/hour still pays the short; 0.8 bps/hour is below the exit
+        farb.on_message(&mut kernel, &state(7, 3));
+        assert!(delivered(&mut kernel, &exchange).is_empty());
+        assert!(farb.funding_earned_usd > 0.0);
+        farb.on_message(&mut kernel, &state(54, 46));
+        assert_eq!(
+            orders(&delivered(&mut kernel, &exchange)),
+            [
+                (MessageType::CloseOrder, "ETH-USD".to_string(), Side::Sell, None),
+                (MessageType::CloseOrder, "BTC-USD".to_string(), Side::Buy, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_closes_when_the_rate_flips_or_the_hold_expires() {
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut farb = priced(&mut kernel, 3_600);
+        farb.on_message(&mut kernel, &state(9, 1));
+        delivered(&mut kernel, &exchange);
+
+        // Shorts pay now: close, and only reopen (long) on the next state
+        farb.on_message(&mut kernel, &state(1, 9));
+        assert_eq!(delivered(&mut kernel, &exchange).len(), 2);
+        assert!(farb.position.is_none());
+        farb.on_message(&mut kernel, &state(1, 9));
+        let reopened = orders(&delivered(&mut kernel, &exchange));
+        assert_eq!(reopened[0].2, Side::Buy);
+
+        let mut farb = priced(&mut kernel, 0);
+        farb.on_message(&mut kernel, &state(9, 1));
+        delivered(&mut kernel, &exchange);
+        farb.on_message(&mut kernel, &state(9, 1));
+        assert_eq!(delivered(&mut kernel, &exchange).len(), 2);
+        assert_eq!((farb.trades_opened, farb.trades_closed), (1, 1));
+    }
+
+    #[test]
+    fn test_hedge_liquidation_closes_the_main_leg() {
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut farb = priced(&mut kernel, 3_600);
+        farb.on_message(&mut kernel, &state(9, 1));
+        delivered(&mut kernel, &exchange);
+
+        // A liquidation of some other position is not ours to act on
+        farb.on_message(&mut kernel, &liquidated(FARB, "BTC-USD", Side::Sell));
+        assert!(delivered(&mut kernel, &exchange).is_empty());
+
+        farb.on_message(&mut kernel, &liquidated(FARB, "BTC-USD", Side::Buy));
+        assert_eq!(
+            orders(&delivered(&mut kernel, &exchange)),
+            [(MessageType::CloseOrder, "ETH-USD".to_string(), Side::Sell, None)]
+        );
+        assert!(farb.position.is_none());
+    }
+}
@@ -0,0 +1,420 @@
+//! HedgerAgent - keeps the delta of a spot holding near a target with perps.
+//!
+//! The agent holds a virtual spot exposure of `spot_qty` tokens of
+//! `spot_symbol` and hedges it with perp positions on one or more markets:
+//!   delta = spot_qty * spot_price + sum(leg_tokens * leg_price * leg_beta)
+//! When |delta - target_delta_usd| exceeds `band_pct` of the spot notional,
+//! every leg is moved to its share (`weight`) of the required hedge:
+//!   leg_tokens = -(spot_usd - target_delta_usd) * weight / beta / leg_price
+//! A leg that has to grow gets a market order for the difference; one that has
+//! to shrink or flip is closed and re-opened at the new size (a Decrease always
+//! closes the whole position). `spot_qty` can be changed from the timeline to
+//! drive correlated flow on all hedge markets.
+//!
+//! Hedges on several markets trade from one account, so such agents must be
+//! listed in `vara.local_agents`.
+
+use std::collections::HashMap;
+
+use crate::agent_println;
+use crate::agents::Agent;
+use crate::messages::{
+    AgentId, CloseOrderPayload, MarketOrderPayload, Message, MessagePayload, MessageType, Side,
+    SimulatorApi,
+};
+use crate::qty::Qty;
+
+#[derive(Debug, Clone)]
+pub struct HedgeLeg {
+    pub symbol: String,
+    /// Share of the required hedge carried by this leg
+    pub weight: f64,
+    /// Price sensitivity of the leg relative to the spot asset
+    pub beta: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct HedgerConfig {
+    pub name: String,
+    pub exchange_id: AgentId,
+    pub spot_symbol: String,
+    /// Spot holding in tokens (negative = short spot)
+    pub spot_qty: f64,
+    pub hedges: Vec<HedgeLeg>,
+    /// Net delta to keep (micro-USD, 0 = fully hedged)
+    pub target_delta_usd: f64,
+    /// Rebalance when the delta drifts beyond this share of the spot notional (percent)
+    pub band_pct: f64,
+    pub leverage: u32,
+    /// Minimum time between two rebalances
+    pub min_interval_sec: u64,
+}
+
+pub struct HedgerAgent {
+    id: AgentId,
+    name: String,
+    exchange_id: AgentId,
+    address: Option<String>,
+    spot_symbol: String,
+    spot_qty: f64,
+    hedges: Vec<HedgeLeg>,
+    target_delta_usd: f64,
+    band_pct: f64,
+    leverage: u32,
+    min_interval_ns: u64,
+
+    /// Oracle mid prices per symbol (micro-USD)
+    prices: HashMap<String, u64>,
+    /// Perp position per hedge symbol in tokens (positive = long)
+    positions: HashMap<String, f64>,
+    last_rebalance_ns: Option<u64>,
+
+    rebalances: u32,
+    orders_sent: u32,
+    liquidations: u32,
+    max_abs_drift_usd: f64,
+}
+
+impl HedgerAgent {
+    pub fn new(id: AgentId, config: HedgerConfig) -> Self {
+        Self {
+            id,
+            name: config.name,
+            exchange_id: config.exchange_id,
+            address: None,
+            spot_symbol: config.spot_symbol,
+            spot_qty: config.spot_qty,
+            hedges: config.hedges,
+            target_delta_usd: config.target_delta_usd,
+            band_pct: config.band_pct,
+            leverage: config.leverage.max(1),
+            min_interval_ns: config.min_interval_sec * 1_000_000_000,
+            prices: HashMap::new(),
+            positions: HashMap::new(),
+            last_rebalance_ns: None,
+            rebalances: 0,
+            orders_sent: 0,
+            liquidations: 0,
+            max_abs_drift_usd: 0.0,
+        }
+    }
+
+    pub fn set_address(&mut self, address: String) {
+        self.address = Some(address);
+    }
+
+    fn price(&self, symbol: &str) -> Option<f64> {
+        self.prices.get(symbol).filter(|p| **p > 0).map(|p| *p as f64)
+    }
+
+    /// Spot notional and current net delta (micro-USD), once all prices are known
+    fn delta(&self) -> Option<(f64, f64)> {
+        let spot_usd = self.spot_qty * self.price(&self.spot_symbol)?;
+        let mut delta = spot_usd;
+        for leg in &self.hedges {
+            let tokens = self.positions.get(&leg.symbol).copied().unwrap_or(0.0);
+            delta += tokens * self.price(&leg.symbol)? * leg.beta;
+        }
+        Some((spot_usd, delta))
+    }
+
+    fn send_market(&mut self, sim: &mut dyn SimulatorApi, symbol: &str, side: Side, qty: f64) {
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::MarketOrder,
+            MessagePayload::MarketOrder(MarketOrderPayload {
+                symbol: symbol.to_string(),
+                side,
+                qty: Qty::from_tokens(qty),
+                leverage: self.leverage,
+                decision_price: sim.market_data().mid(symbol),
+            }),
+        );
+        self.orders_sent += 1;
+    }
+
+    fn send_close(&mut self, sim: &mut dyn SimulatorApi, symbol: &str, side: Side) {
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::CloseOrder,
+            MessagePayload::CloseOrder(CloseOrderPayload {
+                symbol: symbol.to_string(),
+                side,
+                decision_price: sim.market_data().mid(symbol),
+            }),
+        );
+        self.orders_sent += 1;
+    }
+
+    /// Move one leg from its current position to `target` tokens.
+    fn adjust_leg(&mut self, sim: &mut dyn SimulatorApi, symbol: &str, target: f64) {
+        let current = self.positions.get(symbol).copied().unwrap_or(0.0);
+        if target == current {
+            return;
+        }
+        let side_of = |tokens: f64| if tokens > 0.0 { Side::Buy } else { Side::Sell };
+        let grows = current == 0.0 || (current.signum() == target.signum() && target.abs() > current.abs());
+        if grows {
+            self.send_market(sim, symbol, side_of(target), (target - current).abs());
+        } else {
+            self.send_close(sim, symbol, side_of(current));
+            if target != 0.0 {
+                self.send_market(sim, symbol, side_of(target), target.abs());
+            }
+        }
+        self.positions.insert(symbol.to_string(), target);
+    }
+
+    fn check_delta(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
+        let Some((spot_usd, delta)) = self.delta() else {
+            return;
+        };
+        let drift = delta - self.target_delta_usd;
+        self.max_abs_drift_usd = self.max_abs_drift_usd.max(drift.abs());
+        if drift.abs() <= spot_usd.abs() * self.band_pct / 100.0 {
+            return;
+        }
+        if self
+            .last_rebalance_ns
+            .is_some_and(|last| now_ns.saturating_sub(last) < self.min_interval_ns)
+        {
+            return;
+        }
+
+        agent_println!(
+            "[Hedger {}] REBALANCE delta=${:.2} target=${:.2} spot=${:.2}",
+            self.name,
+            delta / 1_000_000.0,
+            self.target_delta_usd / 1_000_000.0,
+            spot_usd / 1_000_000.0
+        );
+        let required_usd = -(spot_usd - self.target_delta_usd);
+        for leg in self.hedges.clone() {
+            let Some(price) = self.price(&leg.symbol) else {
+                continue;
+            };
+            let target = required_usd * leg.weight / leg.beta / price;
+            let current = self.positions.get(&leg.symbol).copied().unwrap_or(0.0);
+            // Skip legs that are already within a tenth of the band
+            if (target - current).abs() * price * leg.beta <= spot_usd.abs() * self.band_pct / 1_000.0 {
+                continue;
+            }
+            agent_println!(
+                "[Hedger {}]   {} {:.4} -> {:.4} tokens",
+                self.name,
+                leg.symbol,
+                current,
+                target
+            );
+            self.adjust_leg(sim, &leg.symbol, target);
+        }
+        self.last_rebalance_ns = Some(now_ns);
+        self.rebalances += 1;
+    }
+}
+
+impl Agent for HedgerAgent {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        match (key, value.as_f64()) {
+            ("spot_qty", Some(v)) => self.spot_qty = v,
+            ("target_delta_usd", Some(v)) => self.target_delta_usd = v,
+            ("band_pct", Some(v)) if v > 0.0 => self.band_pct = v,
+            ("leverage", Some(v)) if v >= 1.0 => self.leverage = v as u32,
+            _ => return false,
+        }
+        agent_println!("[Hedger {}] param {} = {}", self.name, key, value);
+        true
+    }
+
+    fn on_start(&mut self, _sim: &mut dyn SimulatorApi) {
+        let legs: Vec<String> = self
+            .hedges
+            .iter()
+            .map(|leg| format!("{}({:.2})", leg.symbol, leg.weight))
+            .collect();
+        agent_println!(
+            "[Hedger {}] START spot {:.3} {} hedged with {} band={}%{}",
+            self.name,
+            self.spot_qty,
+            self.spot_symbol,
+            legs.join(", "),
+            self.band_pct,
+            self.address
+                .as_deref()
+                .map(|addr| format!(" addr={}", addr))
+                .unwrap_or_default()
+        );
+    }
+
+    fn on_wakeup(&mut self, _sim: &mut dyn SimulatorApi, _now_ns: u64) {}
+
+    fn on_message(&mut self, sim: &mut dyn SimulatorApi, msg: &Message) {
+        match &msg.payload {
+            MessagePayload::OracleTick(_) | MessagePayload::OracleBatchTick(_) => {
+                for p in msg.payload.oracle_ticks() {
+                    self.prices.insert(p.symbol.clone(), (p.price.min + p.price.max) / 2);
+                }
+                let now_ns = sim.now_ns();
+                self.check_delta(sim, now_ns);
+            }
+            MessagePayload::PositionLiquidated(p) => {
+                let side = match self.positions.get(&p.symbol) {
+                    Some(tokens) if *tokens > 0.0 => Side::Buy,
+                    Some(tokens) if *tokens < 0.0 => Side::Sell,
+                    _ => return,
+                };
+                if side != p.side {
+                    return;
+                }
+                agent_println!(
+                    "[Hedger {}] LIQUIDATED {:?} {} pnl=${:.2}",
+                    self.name,
+                    p.side,
+                    p.symbol,
+                    p.pnl as f64 / 1_000_000.0
+                );
+                // The delta check re-opens the leg on the next tick
+                self.positions.remove(&p.symbol);
+                self.last_rebalance_ns = None;
+                self.liquidations += 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
+        let delta = self.delta().map_or(0.0, |(_, delta)| delta);
+        agent_println!(
+            "[Hedger {}] STOP: rebalances={} orders={} liq={} delta=${:.2} max_drift=${:.2}",
+            self.name,
+            self.rebalances,
+            self.orders_sent,
+            self.liquidations,
+            delta / 1_000_000.0,
+            self.max_abs_drift_usd / 1_000_000.0
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::test_support::{delivered, kernel_with_recorder, liquidated, oracle_tick, Received};
+    use crate::kernel::Kernel;
+
+    const EXCHANGE: AgentId = 1;
+    const HEDGER: AgentId = 45;
+    const USD: u64 = 1_000_000;
+
+    /// 10 ETH spot hedged half on ETH perps and half on BTC perps (beta 2), 5% band
+    fn hedger() -> HedgerAgent {
+        let leg = |symbol: &str, beta| HedgeLeg {
+            symbol: symbol.to_string(),
+            weight: 0.5,
+            beta,
+        };
+        HedgerAgent::new(
+            HEDGER,
+            HedgerConfig {
+                name: "hedger".to_string(),
+                exchange_id: EXCHANGE,
+                spot_symbol: "ETH-USD".to_string(),
+                spot_qty: 10.0,
+                hedges: vec![leg("ETH-USD", 1.0), leg("BTC-USD", 2.0)],
+                target_delta_usd: 0.0,
+                band_pct: 5.0,
+                leverage: 3,
+                min_interval_sec: 0,
+            },
+        )
+    }
+
+    /// Orders reaching the exchange after a tick: (symbol, side, tokens), tokens `None` for a close
+    fn tick(
+        hedger: &mut HedgerAgent,
+        kernel: &mut Kernel,
+        exchange: &Received,
+        symbol: &str,
+        price: u64,
+    ) -> Vec<(String, Side, Option<Qty>)> {
+        hedger.on_message(kernel, &oracle_tick(HEDGER, symbol, price * USD));
+        delivered(kernel, exchange)
+            .iter()
+            .map(|msg| match &msg.payload {
+                MessagePayload::MarketOrder(o) => (o.symbol.clone(), o.side, Some(o.qty)),
+                MessagePayload::CloseOrder(c) => (c.symbol.clone(), c.side, None),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect()
+    }
+
+    fn order(symbol: &str, side: Side, tokens: Option<f64>) -> (String, Side, Option<Qty>) {
+        (symbol.to_string(), side, tokens.map(Qty::from_tokens))
+    }
+
+    #[test]
+    fn test_splits_the_hedge_by_weight_and_beta_and_tops_up_outside_the_band() {
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut hedger = hedger();
+
+        // Nothing until every leg has a price
+        assert!(tick(&mut hedger, &mut kernel, &exchange, "ETH-USD", 2_000).is_empty());
+        // $20k spot: $10k of ETH shorts and $5k of BTC shorts at beta 2
+        assert_eq!(
+            tick(&mut hedger, &mut kernel, &exchange, "BTC-USD", 40_000),
+            [
+                order("ETH-USD", Side::Sell, Some(5.0)),
+                order("BTC-USD", Side::Sell, Some(0.125))
+            ]
+        );
+
+        // +5% on ETH leaves a $500 delta, inside the $1050 band
+        assert!(tick(&mut hedger, &mut kernel, &exchange, "ETH-USD", 2_100).is_empty());
+        // +20% is $2k out: the ETH leg already tracks the price, only BTC grows
+        assert_eq!(
+            tick(&mut hedger, &mut kernel, &exchange, "ETH-USD", 2_400),
+            [order("BTC-USD", Side::Sell, Some(0.025))]
+        );
+        assert_eq!(hedger.rebalances, 2);
+    }
+
+    #[test]
+    fn test_flips_legs_with_a_close_and_reopens_liquidated_ones() {
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut hedger = hedger();
+        tick(&mut hedger, &mut kernel, &exchange, "ETH-USD", 2_000);
+        tick(&mut hedger, &mut kernel, &exchange, "BTC-USD", 40_000);
+
+        // Short spot now: every short leg is closed and re-opened long
+        assert!(hedger.set_param("spot_qty", &serde_json::json!(-10.0)));
+        assert_eq!(
+            tick(&mut hedger, &mut kernel, &exchange, "ETH-USD", 2_000),
+            [
+                order("ETH-USD", Side::Sell, None),
+                order("ETH-USD", Side::Buy, Some(5.0)),
+                order("BTC-USD", Side::Sell, None),
+                order("BTC-USD", Side::Buy, Some(0.125)),
+            ]
+        );
+
+        // A liquidation of the other side is not ours
+        hedger.on_message(&mut kernel, &liquidated(HEDGER, "ETH-USD", Side::Sell));
+        assert!(tick(&mut hedger, &mut kernel, &exchange, "ETH-USD", 2_000).is_empty());
+        hedger.on_message(&mut kernel, &liquidated(HEDGER, "ETH-USD", Side::Buy));
+        assert_eq!(
+            tick(&mut hedger, &mut kernel, &exchange, "ETH-USD", 2_000),
+            [order("ETH-USD", Side::Buy, Some(5.0))]
+        );
+        assert_eq!(hedger.liquidations, 1);
+    }
+}
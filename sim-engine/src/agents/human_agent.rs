@@ -1,13 +1,70 @@
+use std::collections::{BTreeMap, VecDeque};
+
 use crossbeam_channel::{Receiver, Sender};
 
+use crate::agent_println;
 use crate::agents::Agent;
-use crate::api::{ApiCommand, ApiResponse};
+use crate::api::{ApiCommand, ApiResponse, SessionHub};
+use crate::pnl::PnlBook;
 use crate::messages::{
-    AgentId, CloseOrderPayload, MarketOrderPayload, Message, MessagePayload, MessageType, OrderExecutedPayload,
-    OrderExecutionType, PositionLiquidatedPayload, PreviewRequestPayload, PreviewResponsePayload, Side, SimulatorApi,
+    AdminCommand, AdminResultPayload, AgentId, CancelOrderPayload, CloseOrderPayload, DirectorCommand, ExecutionType,
+    GetTradesPayload, MarketOrderPayload, Message, MessagePayload, MessageType, OrderExecutedPayload,
+    OrderExecutionType, OrderId, OrderPayload, OrderType, PositionInfo, PositionLiquidatedPayload,
+    PositionsSnapshotPayload, PreviewRequestPayload, PreviewResponsePayload, Side, SimulatorApi, TradesListPayload,
 };
+use crate::qty::Qty;
 
 const INITIAL_BALANCE: i128 = 10_000_000_000;
+/// Order size when the API command has no `qty` (1 token)
+const DEFAULT_QTY: Qty = Qty::from_atoms(Qty::SCALE);
+/// Fills returned by `trades` when the command has no `limit`
+const DEFAULT_TRADES_LIMIT: usize = 100;
+/// Lifetime of an `/admin/price` override when the command has no `duration_sec`
+const DEFAULT_OVERRIDE_SEC: u64 = 60;
+
+/// Conditional order submitted from the API (micro-USD trigger)
+#[derive(Debug, Clone)]
+struct ApiOrder {
+    symbol: String,
+    side: Side,
+    order_type: OrderType,
+    execution_type: ExecutionType,
+    qty: Option<Qty>,
+    leverage: Option<u32>,
+    trigger_price: u64,
+    valid_for_sec: Option<u64>,
+    oco_group: Option<u64>,
+}
+
+impl ApiOrder {
+    fn to_json(&self, order_id: Option<OrderId>) -> serde_json::Value {
+        serde_json::json!({
+            "order_id": order_id,
+            "symbol": self.symbol,
+            "side": format!("{:?}", self.side),
+            "order_type": format!("{:?}", self.order_type),
+            "execution_type": format!("{:?}", self.execution_type),
+            "qty": self.qty,
+            "leverage": self.leverage,
+            "trigger_price": self.trigger_price as f64 / 1_000_000.0,
+            "valid_for_sec": self.valid_for_sec,
+            "oco_group": self.oco_group,
+        })
+    }
+}
+
+fn parse_side(side: Option<&str>) -> Option<Side> {
+    match side {
+        Some("long") | Some("buy") | Some("Long") | Some("Buy") => Some(Side::Buy),
+        Some("short") | Some("sell") | Some("Short") | Some("Sell") => Some(Side::Sell),
+        _ => None,
+    }
+}
+
+/// Id in an exchange notification ("order_id:N" or "order_id:N,reason:...")
+fn parse_order_id(text: &str) -> Option<OrderId> {
+    text.strip_prefix("order_id:")?.split(',').next()?.parse().ok()
+}
 
 pub struct HumanAgent {
     id: AgentId,
@@ -26,6 +83,24 @@ pub struct HumanAgent {
     total_pnl: i128,
     preview_tx: Sender<PreviewResponsePayload>,
     preview_rx: Receiver<PreviewResponsePayload>,
+    /// Engine-side PnL ledger (authoritative, from execution events)
+    pnl_book: Option<PnlBook>,
+    /// Conditional orders waiting for OrderPending, in submission order
+    unacked_orders: VecDeque<ApiOrder>,
+    /// Resting conditional orders by exchange id (until filled, cancelled or expired)
+    pending_orders: BTreeMap<OrderId, ApiOrder>,
+    /// Own positions from the latest exchange snapshot of each market: symbol -> (price, positions)
+    snapshot_positions: BTreeMap<String, (u64, Vec<PositionInfo>)>,
+    /// Accounts and request ids of GetTrades queries awaiting the exchange's TradesList, oldest first
+    trade_queries: VecDeque<(AgentId, Option<String>)>,
+    /// Request ids of admin commands awaiting the kernel's AdminResult, oldest first
+    admin_requests: VecDeque<Option<String>>,
+    /// Oracles that take manual prices from `/admin/price`
+    oracle_ids: Vec<AgentId>,
+    /// WebSocket trading sessions; this agent spawns a HumanAgent for each new one
+    sessions: Option<SessionHub>,
+    /// Session agents trade only their own account: no admin actions or other accounts' fills
+    session: bool,
 }
 
 impl HumanAgent {
@@ -53,6 +128,49 @@ impl HumanAgent {
             total_pnl: 0,
             preview_tx,
             preview_rx,
+            pnl_book: None,
+            unacked_orders: VecDeque::new(),
+            pending_orders: BTreeMap::new(),
+            snapshot_positions: BTreeMap::new(),
+            trade_queries: VecDeque::new(),
+            admin_requests: VecDeque::new(),
+            oracle_ids: Vec::new(),
+            sessions: None,
+            session: false,
+        }
+    }
+
+    pub fn set_pnl_book(&mut self, book: PnlBook) {
+        self.pnl_book = Some(book);
+    }
+
+    pub fn set_oracle_ids(&mut self, oracle_ids: Vec<AgentId>) {
+        self.oracle_ids = oracle_ids;
+    }
+
+    pub fn set_sessions(&mut self, sessions: SessionHub) {
+        self.sessions = Some(sessions);
+    }
+
+    /// Spawn a HumanAgent for every session opened since the last wakeup.
+    fn spawn_sessions(&mut self, sim: &mut dyn SimulatorApi) {
+        let Some(hub) = &self.sessions else {
+            return;
+        };
+        for opened in hub.take_opened() {
+            let mut agent = HumanAgent::new(
+                opened.agent_id,
+                format!("{}-{}", self.name, opened.agent_id),
+                self.exchange_id,
+                None,
+                opened.command_rx,
+                opened.response_tx,
+                self.wake_interval_ns / 1_000_000,
+            );
+            agent.pnl_book = self.pnl_book.clone();
+            agent.session = true;
+            agent_println!("[{}] new session: {} (id={})", self.name, agent.name, agent.id);
+            sim.spawn_agent(Box::new(agent));
         }
     }
 
@@ -63,39 +181,67 @@ impl HumanAgent {
 
     fn process_commands(&mut self, sim: &mut dyn SimulatorApi) {
         while let Ok(cmd) = self.command_rx.try_recv() {
-            println!("[{}] cmd: {} {}", self.name, cmd.action, cmd.symbol);
+            agent_println!("[{}] cmd: {} {}", self.name, cmd.action, cmd.symbol);
 
-            let response = match cmd.action.as_str() {
+            let mut response = match cmd.action.as_str() {
                 "open" | "order" => self.handle_open(sim, &cmd),
                 "close" => self.handle_close(sim, &cmd),
                 "status" => self.handle_status(),
                 "balance" => self.handle_balance(),
                 "preview" => self.handle_preview(sim, &cmd),
+                "limit" => self.handle_conditional(sim, &cmd, ExecutionType::Limit),
+                "stop_loss" => self.handle_conditional(sim, &cmd, ExecutionType::StopLoss),
+                "take_profit" => self.handle_conditional(sim, &cmd, ExecutionType::TakeProfit),
+                "cancel" => self.handle_cancel(sim, &cmd),
+                "orders" => self.handle_orders(),
+                "positions" => self.handle_positions(),
+                // Answered when the exchange's TradesList arrives
+                "trades" => {
+                    self.request_trades(sim, &cmd);
+                    continue;
+                }
+                "price" | "pause" | "resume" | "config" if self.session => ApiResponse {
+                    success: false,
+                    message: format!("{} is not available in a trading session", cmd.action),
+                    data: None,
+                    request_id: None,
+                },
+                "price" => self.handle_price_override(sim, &cmd),
+                "pause" | "resume" | "config" => match self.send_admin(sim, &cmd) {
+                    Some(response) => response,
+                    // Answered when the kernel's AdminResult arrives
+                    None => {
+                        self.admin_requests.push_back(cmd.request_id);
+                        continue;
+                    }
+                },
                 _ => ApiResponse {
                     success: false,
                     message: format!("Unknown action: {}", cmd.action),
                     data: None,
+                    request_id: None,
                 },
             };
 
+            response.request_id = cmd.request_id;
             let _ = self.response_tx.send(response);
         }
     }
 
     fn handle_open(&mut self, sim: &mut dyn SimulatorApi, cmd: &ApiCommand) -> ApiResponse {
-        let side = match cmd.side.as_deref() {
-            Some("long") | Some("buy") | Some("Long") | Some("Buy") => Side::Buy,
-            Some("short") | Some("sell") | Some("Short") | Some("Sell") => Side::Sell,
-            _ => {
+        let side = match parse_side(cmd.side.as_deref()) {
+            Some(side) => side,
+            None => {
                 return ApiResponse {
                     success: false,
                     message: "side must be 'long' or 'short'".to_string(),
                     data: None,
+                    request_id: None,
                 }
             }
         };
 
-        let qty = cmd.qty.unwrap_or(1.0);
+        let qty = cmd.qty.unwrap_or(DEFAULT_QTY);
         let leverage = cmd.leverage.unwrap_or(5);
 
         sim.send(
@@ -107,6 +253,7 @@ impl HumanAgent {
                 side,
                 qty,
                 leverage,
+                decision_price: sim.market_data().mid(&cmd.symbol),
             }),
         );
 
@@ -114,13 +261,14 @@ impl HumanAgent {
 
         ApiResponse {
             success: true,
-            message: format!("Order: {} {:?} qty={:.2} lev={}x", cmd.symbol, side, qty, leverage),
+            message: format!("Order: {} {:?} qty={} lev={}x", cmd.symbol, side, qty, leverage),
             data: Some(serde_json::json!({
                 "symbol": cmd.symbol,
                 "side": format!("{:?}", side),
                 "qty": qty,
                 "leverage": leverage,
             })),
+            request_id: None,
         }
     }
 
@@ -132,6 +280,7 @@ impl HumanAgent {
                     success: false,
                     message: format!("No open position for {}", cmd.symbol),
                     data: None,
+                    request_id: None,
                 }
             }
         };
@@ -143,6 +292,7 @@ impl HumanAgent {
             MessagePayload::CloseOrder(CloseOrderPayload {
                 symbol: cmd.symbol.clone(),
                 side,
+                decision_price: sim.market_data().mid(&cmd.symbol),
             }),
         );
 
@@ -152,6 +302,321 @@ impl HumanAgent {
             success: true,
             message: format!("Close: {} ({:?})", cmd.symbol, side),
             data: None,
+            request_id: None,
+        }
+    }
+
+    /// Limit opens a position at `trigger_price`; stop-loss / take-profit close the position
+    /// on `side` (default: the open position of the symbol).
+    fn handle_conditional(
+        &mut self,
+        sim: &mut dyn SimulatorApi,
+        cmd: &ApiCommand,
+        execution_type: ExecutionType,
+    ) -> ApiResponse {
+        let trigger_price = match cmd.trigger_price {
+            Some(p) if p > 0.0 => (p * 1_000_000.0).round() as u64,
+            _ => {
+                return ApiResponse {
+                    success: false,
+                    message: "trigger_price must be > 0".to_string(),
+                    data: None,
+                    request_id: None,
+                }
+            }
+        };
+
+        let order = if execution_type == ExecutionType::Limit {
+            let Some(side) = parse_side(cmd.side.as_deref()) else {
+                return ApiResponse {
+                    success: false,
+                    message: "side must be 'long' or 'short'".to_string(),
+                    data: None,
+                    request_id: None,
+                };
+            };
+            ApiOrder {
+                symbol: cmd.symbol.clone(),
+                side,
+                order_type: OrderType::Increase,
+                execution_type,
+                qty: Some(cmd.qty.unwrap_or(DEFAULT_QTY)),
+                leverage: Some(cmd.leverage.unwrap_or(5)),
+                trigger_price,
+                valid_for_sec: cmd.valid_for_sec,
+                oco_group: cmd.oco_group,
+            }
+        } else {
+            let side = match parse_side(cmd.side.as_deref()).or_else(|| self.open_positions.get(&cmd.symbol).copied()) {
+                Some(side) => side,
+                None => {
+                    return ApiResponse {
+                        success: false,
+                        message: format!("No open position for {}", cmd.symbol),
+                        data: None,
+                        request_id: None,
+                    }
+                }
+            };
+            ApiOrder {
+                symbol: cmd.symbol.clone(),
+                side,
+                order_type: OrderType::Decrease,
+                execution_type,
+                qty: None,
+                leverage: None,
+                trigger_price,
+                valid_for_sec: cmd.valid_for_sec,
+                oco_group: cmd.oco_group,
+            }
+        };
+
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::SubmitOrder,
+            MessagePayload::Order(OrderPayload {
+                symbol: order.symbol.clone(),
+                side: order.side,
+                order_type: order.order_type,
+                execution_type: order.execution_type,
+                qty: order.qty,
+                leverage: order.leverage,
+                size_delta_usd: None,
+                trigger_price: Some(order.trigger_price),
+                acceptable_price: None,
+                trailing_distance: None,
+                valid_for_sec: order.valid_for_sec,
+                oco_group: order.oco_group,
+                decision_price: None,
+            }),
+        );
+
+        let response = ApiResponse {
+            success: true,
+            message: format!(
+                "{:?}: {} {:?} trigger=${:.2}",
+                execution_type,
+                order.symbol,
+                order.side,
+                order.trigger_price as f64 / 1_000_000.0
+            ),
+            data: Some(order.to_json(None)),
+            request_id: None,
+        };
+        self.unacked_orders.push_back(order);
+        response
+    }
+
+    fn handle_cancel(&mut self, sim: &mut dyn SimulatorApi, cmd: &ApiCommand) -> ApiResponse {
+        let Some(order_id) = cmd.order_id else {
+            return ApiResponse {
+                success: false,
+                message: "order_id is required".to_string(),
+                data: None,
+                request_id: None,
+            };
+        };
+        if self.pending_orders.remove(&order_id).is_none() {
+            return ApiResponse {
+                success: false,
+                message: format!("No pending order #{}", order_id),
+                data: None,
+                request_id: None,
+            };
+        }
+
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::CancelOrder,
+            MessagePayload::CancelOrder(CancelOrderPayload { order_id }),
+        );
+
+        ApiResponse {
+            success: true,
+            message: format!("Cancel: #{}", order_id),
+            data: None,
+            request_id: None,
+        }
+    }
+
+    fn handle_orders(&self) -> ApiResponse {
+        let orders: Vec<_> = self
+            .pending_orders
+            .iter()
+            .map(|(id, order)| order.to_json(Some(*id)))
+            .collect();
+
+        ApiResponse {
+            success: true,
+            message: format!("{} pending orders", orders.len()),
+            data: Some(serde_json::json!({
+                "agent": self.name,
+                "orders": orders,
+                "awaiting_id": self.unacked_orders.len(),
+            })),
+            request_id: None,
+        }
+    }
+
+    /// Ask the exchange for recent fills of `cmd.account` (default, and always in a session: own account).
+    fn request_trades(&mut self, sim: &mut dyn SimulatorApi, cmd: &ApiCommand) {
+        let account = cmd.account.filter(|_| !self.session).unwrap_or(self.id);
+        self.trade_queries.push_back((account, cmd.request_id.clone()));
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::GetTrades,
+            MessagePayload::GetTrades(GetTradesPayload {
+                account: Some(account),
+                symbol: (!cmd.symbol.is_empty()).then(|| cmd.symbol.clone()),
+                limit: cmd.limit.unwrap_or(DEFAULT_TRADES_LIMIT),
+            }),
+        );
+    }
+
+    /// Send an admin command to `cmd.agent`; an error response if the command is incomplete.
+    fn send_admin(&mut self, sim: &mut dyn SimulatorApi, cmd: &ApiCommand) -> Option<ApiResponse> {
+        let error = |message: &str| {
+            Some(ApiResponse {
+                success: false,
+                message: message.to_string(),
+                data: None,
+                request_id: None,
+            })
+        };
+        let Some(agent) = cmd.agent else {
+            return error("agent is required");
+        };
+        let command = match (cmd.action.as_str(), &cmd.config) {
+            // Paused, this agent would never see the resume command
+            ("pause", _) if agent == self.id => return error("the API agent can't be paused"),
+            ("pause", _) => AdminCommand::Pause,
+            ("resume", _) => AdminCommand::Resume,
+            (_, Some(config)) => AdminCommand::ConfigUpdate(config.clone()),
+            (_, None) => return error("config is required"),
+        };
+        sim.send(self.id, agent, MessageType::Admin, MessagePayload::Admin(command));
+        None
+    }
+
+    /// Have every oracle publish `cmd.price` for `cmd.symbol` for `duration_sec` (0 ends an override).
+    fn handle_price_override(&mut self, sim: &mut dyn SimulatorApi, cmd: &ApiCommand) -> ApiResponse {
+        let error = |message: &str| ApiResponse {
+            success: false,
+            message: message.to_string(),
+            data: None,
+            request_id: None,
+        };
+        if cmd.symbol.is_empty() {
+            return error("symbol is required");
+        }
+        let duration_sec = cmd.duration_sec.unwrap_or(DEFAULT_OVERRIDE_SEC);
+        let price = match cmd.price {
+            Some(price) if price > 0.0 => (price * 1_000_000.0) as u64,
+            _ if duration_sec == 0 => 0,
+            _ => return error("price must be > 0"),
+        };
+        if self.oracle_ids.is_empty() {
+            return error("no oracle to override");
+        }
+        for oracle_id in &self.oracle_ids {
+            sim.send(
+                self.id,
+                *oracle_id,
+                MessageType::Director,
+                MessagePayload::Director(DirectorCommand::PriceOverride {
+                    symbol: cmd.symbol.clone(),
+                    price,
+                    duration_ns: duration_sec * 1_000_000_000,
+                }),
+            );
+        }
+        ApiResponse {
+            success: true,
+            message: if duration_sec == 0 {
+                format!("{} price override cleared", cmd.symbol)
+            } else {
+                format!(
+                    "{} at ${:.2} for {}s",
+                    cmd.symbol,
+                    price as f64 / 1_000_000.0,
+                    duration_sec
+                )
+            },
+            data: Some(serde_json::json!({
+                "symbol": cmd.symbol,
+                "price": price as f64 / 1_000_000.0,
+                "duration_sec": duration_sec,
+            })),
+            request_id: None,
+        }
+    }
+
+    fn on_admin_result(&mut self, result: &AdminResultPayload) {
+        let _ = self.response_tx.send(ApiResponse {
+            success: result.error.is_none(),
+            message: match &result.error {
+                Some(e) => format!("{} agent {} failed: {}", result.command, result.agent, e),
+                None => format!("{} agent {}: ok", result.command, result.agent),
+            },
+            data: Some(serde_json::json!({"agent": result.agent, "command": result.command})),
+            request_id: self.admin_requests.pop_front().flatten(),
+        });
+    }
+
+    fn on_trades(&mut self, list: &TradesListPayload) {
+        let Some((account, request_id)) = self.trade_queries.pop_front() else {
+            return;
+        };
+        let _ = self.response_tx.send(ApiResponse {
+            success: true,
+            message: format!("{} trades", list.trades.len()),
+            data: Some(serde_json::json!({"account": account, "trades": list.trades})),
+            request_id,
+        });
+    }
+
+    /// Open positions as of the last snapshot, with liquidation prices from the exchange.
+    fn handle_positions(&self) -> ApiResponse {
+        let positions: Vec<_> = self
+            .snapshot_positions
+            .iter()
+            .flat_map(|(symbol, (price, positions))| {
+                positions.iter().map(move |p| {
+                    serde_json::json!({
+                        "symbol": symbol,
+                        "side": format!("{:?}", p.side),
+                        "size_usd": p.size_usd,
+                        "collateral": p.collateral,
+                        "entry_price": p.entry_price,
+                        "current_price": price,
+                        "liquidation_price": p.liquidation_price,
+                    })
+                })
+            })
+            .collect();
+
+        ApiResponse {
+            success: true,
+            message: format!("{} positions", positions.len()),
+            data: Some(serde_json::json!({"agent": self.name, "positions": positions})),
+            request_id: None,
+        }
+    }
+
+    fn on_snapshot(&mut self, snap: &PositionsSnapshotPayload) {
+        let own: Vec<PositionInfo> = snap
+            .positions
+            .iter()
+            .filter(|p| p.account == self.id)
+            .cloned()
+            .collect();
+        if own.is_empty() {
+            self.snapshot_positions.remove(&snap.symbol);
+        } else {
+            self.snapshot_positions.insert(snap.symbol.clone(), (snap.price, own));
         }
     }
 
@@ -166,11 +631,17 @@ impl HumanAgent {
             success: true,
             message: format!("{} positions", positions.len()),
             data: Some(serde_json::json!({"agent": self.name, "positions": positions})),
+            request_id: None,
         }
     }
 
     fn handle_balance(&self) -> ApiResponse {
         let available = self.available_balance();
+        let pnl = self
+            .pnl_book
+            .as_ref()
+            .and_then(|book| book.get(self.id))
+            .unwrap_or_default();
         ApiResponse {
             success: true,
             message: format!("Balance: ${:.2}", available as f64 / 1_000_000.0),
@@ -179,24 +650,30 @@ impl HumanAgent {
                 "collateral_used": self.collateral_used,
                 "available_balance": available,
                 "total_pnl": self.total_pnl,
+                "realized_pnl": pnl.realized_pnl,
+                "unrealized_pnl": pnl.unrealized_pnl,
+                "fees_paid": pnl.fees_paid,
+                "funding_paid": pnl.funding_paid,
+                "net_pnl": pnl.net_pnl(),
             })),
+            request_id: None,
         }
     }
 
     fn handle_preview(&mut self, sim: &mut dyn SimulatorApi, cmd: &ApiCommand) -> ApiResponse {
-        let side = match cmd.side.as_deref() {
-            Some("long") | Some("buy") | Some("Long") | Some("Buy") => Side::Buy,
-            Some("short") | Some("sell") | Some("Short") | Some("Sell") => Side::Sell,
-            _ => {
+        let side = match parse_side(cmd.side.as_deref()) {
+            Some(side) => side,
+            None => {
                 return ApiResponse {
                     success: false,
                     message: "side must be 'long' or 'short'".to_string(),
                     data: None,
+                    request_id: None,
                 }
             }
         };
 
-        let qty = cmd.qty.unwrap_or(1.0);
+        let qty = cmd.qty.unwrap_or(DEFAULT_QTY);
         let leverage = cmd.leverage.unwrap_or(5);
 
         sim.send(
@@ -230,11 +707,13 @@ impl HumanAgent {
                     "price_impact_usd": resp.price_impact_usd,
                     "close_fees_usd": resp.close_fees_usd,
                 })),
+                request_id: None,
             },
             Err(_) => ApiResponse {
                 success: false,
                 message: "preview timeout".to_string(),
                 data: None,
+                request_id: None,
             },
         }
     }
@@ -244,7 +723,8 @@ impl HumanAgent {
             OrderExecutionType::Increase => {
                 // Opening position - lock collateral
                 self.collateral_used += payload.collateral_delta;
-                println!(
+                self.open_positions.insert(payload.symbol.clone(), payload.side);
+                agent_println!(
                     "[{}] Position opened: {} {:?} size=${:.2} collateral=${:.2}",
                     self.name,
                     payload.symbol,
@@ -257,9 +737,10 @@ impl HumanAgent {
                 // Closing position - return collateral and apply PnL
                 // collateral_delta is negative (returned)
                 self.collateral_used += payload.collateral_delta; // Adds negative = decreases
+                self.open_positions.remove(&payload.symbol);
                 self.balance += payload.pnl;
                 self.total_pnl += payload.pnl;
-                println!(
+                agent_println!(
                     "[{}] Position closed: {} {:?} pnl=${:.2} balance=${:.2}",
                     self.name,
                     payload.symbol,
@@ -273,7 +754,7 @@ impl HumanAgent {
                 self.collateral_used += payload.collateral_delta;
                 self.balance += payload.pnl;
                 self.total_pnl += payload.pnl;
-                println!(
+                agent_println!(
                     "[{}] ⚠️ Liquidated: {} {:?} pnl=${:.2}",
                     self.name,
                     payload.symbol,
@@ -294,7 +775,7 @@ impl Agent for HumanAgent {
     }
 
     fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
-        println!(
+        agent_println!(
             "[{}] ====== STARTED id={} balance=${:.2}{} ======",
             self.name,
             self.id,
@@ -304,6 +785,13 @@ impl Agent for HumanAgent {
                 .map(|addr| format!(" addr={}", addr))
                 .unwrap_or_default()
         );
+        // Positions with liquidation prices for GET /positions
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::SubscribeSnapshots,
+            MessagePayload::Empty,
+        );
         sim.wakeup(self.id, sim.now_ns() + self.wake_interval_ns);
     }
 
@@ -311,16 +799,51 @@ impl Agent for HumanAgent {
         // Check for pending commands
         let pending = self.command_rx.len();
         if pending > 0 {
-            println!("[{}] Processing {} pending commands", self.name, pending);
+            agent_println!("[{}] Processing {} pending commands", self.name, pending);
         }
         self.process_commands(sim);
+        self.spawn_sessions(sim);
         sim.wakeup(self.id, sim.now_ns() + self.wake_interval_ns);
     }
 
     fn on_message(&mut self, _sim: &mut dyn SimulatorApi, msg: &Message) {
         match msg.msg_type {
-            MessageType::OrderAccepted | MessageType::OrderRejected => {
-                println!("[{}] received {:?}", self.name, msg.msg_type);
+            MessageType::OrderAccepted => {
+                agent_println!("[{}] received {:?}", self.name, msg.msg_type);
+            }
+            MessageType::OrderRejected => {
+                agent_println!("[{}] received {:?}", self.name, msg.msg_type);
+                // A rejected conditional order never gets an id
+                self.unacked_orders.pop_front();
+            }
+            MessageType::OrderPending => {
+                if let MessagePayload::Text(text) = &msg.payload {
+                    let Some(order) = self.unacked_orders.pop_front() else {
+                        return;
+                    };
+                    match parse_order_id(text) {
+                        Some(order_id) => {
+                            agent_println!("[{}] order #{} pending", self.name, order_id);
+                            self.pending_orders.insert(order_id, order);
+                        }
+                        // Every order gets an id from the exchange; other replies are informational
+                        None => agent_println!("[{}] {:?} order {}", self.name, order.execution_type, text),
+                    }
+                }
+            }
+            MessageType::OrderTriggered | MessageType::OrderCancelled | MessageType::OrderExpired => {
+                if let MessagePayload::Text(text) = &msg.payload {
+                    if let Some(order) = parse_order_id(text).and_then(|id| self.pending_orders.remove(&id)) {
+                        agent_println!(
+                            "[{}] {:?} {} {:?} {}",
+                            self.name,
+                            order.execution_type,
+                            order.symbol,
+                            msg.msg_type,
+                            text
+                        );
+                    }
+                }
             }
             MessageType::OrderExecuted => {
                 if let MessagePayload::OrderExecuted(payload) = &msg.payload {
@@ -340,7 +863,7 @@ impl Agent for HumanAgent {
                         Side::Buy => "LONG",
                         Side::Sell => "SHORT",
                     };
-                    println!(
+                    agent_println!(
                         "[{}] ⚠️ LIQUIDATED {} {} pnl=${:.2} lost=${:.2}",
                         self.name,
                         symbol,
@@ -361,6 +884,21 @@ impl Agent for HumanAgent {
                     let _ = self.preview_tx.send(payload.clone());
                 }
             }
+            MessageType::PositionsSnapshot => {
+                if let MessagePayload::PositionsSnapshot(snap) = &msg.payload {
+                    self.on_snapshot(snap);
+                }
+            }
+            MessageType::TradesList => {
+                if let MessagePayload::TradesList(list) = &msg.payload {
+                    self.on_trades(list);
+                }
+            }
+            MessageType::AdminResult => {
+                if let MessagePayload::AdminResult(result) = &msg.payload {
+                    self.on_admin_result(result);
+                }
+            }
             _ => {}
         }
     }
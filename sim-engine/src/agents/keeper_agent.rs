@@ -1,10 +1,14 @@
+use crate::agent_println;
+use crate::agents::exchange_agent::KEEPER_REWARD_BPS;
 use crate::agents::Agent;
+use crate::api::PriceProvider;
 use crate::messages::{
-    AgentId, ExecuteOrderPayload, KeeperRewardPayload, Message, MessagePayload, MessageType, OracleTickPayload,
-    PendingOrderInfo, PendingOrdersListPayload, Price, SimulatorApi,
+    AgentId, ExecuteOrderBatchPayload, ExecuteOrderPayload, KeeperRewardPayload, Message, MessagePayload, MessageType,
+    OracleTickPayload, OrderId, PendingOrderInfo, PendingOrdersListPayload, Price, PriceUpdatePayload, SimulatorApi,
 };
+use crate::pending_orders::TriggerIndex;
 use crate::trigger_checker;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 pub struct KeeperAgent {
     id: AgentId,
@@ -13,11 +17,46 @@ pub struct KeeperAgent {
     wake_interval_ns: u64,
 
     prices: HashMap<String, Price>,
-    pending_orders: Vec<PendingOrderInfo>,
+    pending_orders: HashMap<OrderId, PendingOrderInfo>,
+    /// Per-symbol trigger index of `pending_orders`
+    triggers: HashMap<String, TriggerIndex>,
+
+    /// Simulated cost of one ExecuteOrder attempt (micro-USD)
+    gas_cost_usd: u64,
+    /// Executions whose expected reward is below gas_cost_usd * min_profit_ratio are skipped
+    min_profit_ratio: f64,
+    /// Triggered orders skipped as unprofitable, still pending (each is counted once)
+    skipped: HashSet<OrderId>,
+    /// Orders claimed at the exchange since the last pending-orders refresh
+    claimed: HashSet<OrderId>,
+    /// Claims sent and not answered yet
+    claims_in_flight: u32,
+
+    /// Most executions sent in one transaction (1 = one transaction per order)
+    max_batch: usize,
+    /// Simulated gas of each execution after the first in a batch (micro-USD)
+    batched_order_gas_usd: u64,
+    /// Granted claims waiting for the other answers of the same round
+    batch: Vec<ExecuteOrderPayload>,
+    batches_sent: u32,
 
     orders_executed: u32,
     orders_missed: u32,
+    orders_skipped: u32,
+    /// Claims granted / held by another keeper
+    claims_won: u32,
+    claims_lost: u32,
+    /// Expected rewards of the skipped orders
+    skipped_rewards: u64,
     total_rewards: u64,
+    /// Gas paid for every attempt, including the ones another keeper won
+    total_gas: u64,
+
+    /// Source of the just-in-time price bundled with each execution (Vara mode)
+    price_source: Option<Box<dyn PriceProvider>>,
+    max_price_age_secs: u64,
+    /// Executions sent without a bundled price because the fetch failed
+    price_fetch_failures: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -26,6 +65,16 @@ pub struct KeeperConfig {
     pub exchange_id: AgentId,
     pub address: Option<String>,
     pub wake_interval_ms: u64,
+    /// Simulated gas per execution attempt (micro-USD); 0 executes every triggered order
+    pub gas_cost_usd: u64,
+    /// Required reward / gas ratio for an execution (1.0 = break even)
+    pub min_profit_ratio: f64,
+    /// Oldest bundled price the exchange accepts, in seconds (0 = no limit)
+    pub max_price_age_secs: u64,
+    /// Executions sent in one transaction once the claims of a round are answered (1 = unbatched)
+    pub max_batch: usize,
+    /// Simulated gas of each execution after the first in a batch (micro-USD)
+    pub batched_order_gas_usd: u64,
 }
 
 impl KeeperAgent {
@@ -36,34 +85,170 @@ impl KeeperAgent {
             exchange_id: config.exchange_id,
             wake_interval_ns: config.wake_interval_ms * 1_000_000,
             prices: HashMap::new(),
-            pending_orders: Vec::new(),
+            pending_orders: HashMap::new(),
+            triggers: HashMap::new(),
+            gas_cost_usd: config.gas_cost_usd,
+            min_profit_ratio: config.min_profit_ratio,
+            skipped: HashSet::new(),
+            claimed: HashSet::new(),
+            claims_in_flight: 0,
+            max_batch: config.max_batch.max(1),
+            batched_order_gas_usd: config.batched_order_gas_usd,
+            batch: Vec::new(),
+            batches_sent: 0,
             orders_executed: 0,
             orders_missed: 0,
+            orders_skipped: 0,
+            claims_won: 0,
+            claims_lost: 0,
+            skipped_rewards: 0,
             total_rewards: 0,
+            total_gas: 0,
+            price_source: None,
+            max_price_age_secs: config.max_price_age_secs,
+            price_fetch_failures: 0,
         }
     }
 
-    fn check_and_execute_triggers(&mut self, sim: &mut dyn SimulatorApi) {
-        for order in &self.pending_orders {
-            if let Some(price) = self.prices.get(&order.symbol) {
-                if trigger_checker::is_triggered_info(order, price) {
-                    println!(
-                        "[Keeper {}] Triggering order #{} {}",
-                        self.name, order.order_id, order.symbol
-                    );
+    /// Fetch a fresh price from `provider` for every execution and bundle it with the order.
+    pub fn set_price_source(&mut self, provider: Box<dyn PriceProvider>) {
+        self.price_source = Some(provider);
+    }
+
+    /// Latest provider price of `symbol`; None without a price source or when the fetch fails.
+    fn fetch_price_update(&mut self, symbol: &str) -> Option<PriceUpdatePayload> {
+        let provider = self.price_source.as_ref()?;
+        match provider.fetch_signed_price(symbol) {
+            Ok(data) => Some(PriceUpdatePayload {
+                symbol: data.symbol,
+                price_usd_micro: data.price_usd_micro,
+                confidence: data.confidence,
+                publish_time: data.publish_time,
+                vaa: data.signature,
+                max_age_secs: self.max_price_age_secs,
+            }),
+            Err(e) => {
+                eprintln!("[Keeper {}] price fetch for {} failed: {}", self.name, symbol, e);
+                self.price_fetch_failures += 1;
+                None
+            }
+        }
+    }
 
+    fn set_pending_orders(&mut self, orders: &[PendingOrderInfo]) {
+        self.pending_orders.clear();
+        self.triggers.clear();
+        for order in orders {
+            let direction = trigger_checker::trigger_direction(order.execution_type, order.order_type, order.side);
+            if let Some(direction) = direction {
+                let index = self.triggers.entry(order.symbol.clone()).or_default();
+                index.insert(direction, order.trigger_price, order.order_id);
+                self.pending_orders.insert(order.order_id, order.clone());
+            }
+        }
+        let pending = &self.pending_orders;
+        self.skipped.retain(|id| pending.contains_key(id));
+        // Orders still pending can be claimed again: a claim lapses if its holder fails
+        self.claimed.clear();
+    }
+
+    /// Reward the exchange pays for executing `order` at `price` (a share of its notional).
+    fn expected_reward(order: &PendingOrderInfo, price: &Price) -> u64 {
+        let mid = (price.min + price.max) / 2;
+        order.qty.notional(mid) * KEEPER_REWARD_BPS / 10_000
+    }
+
+    fn check_and_execute_triggers(&mut self, sim: &mut dyn SimulatorApi, symbol: &str) {
+        let (Some(index), Some(price)) = (self.triggers.get(symbol), self.prices.get(symbol)) else {
+            return;
+        };
+        for order_id in index.candidates(price) {
+            let Some(order) = self.pending_orders.get(&order_id) else {
+                continue;
+            };
+            if trigger_checker::is_triggered_info(order, price) {
+                let reward = Self::expected_reward(order, price);
+                if self.gas_cost_usd > 0 && (reward as f64) < self.gas_cost_usd as f64 * self.min_profit_ratio {
+                    if self.skipped.insert(order_id) {
+                        self.orders_skipped += 1;
+                        self.skipped_rewards += reward;
+                        agent_println!(
+                            "[Keeper {}] Skipping order #{} {}: reward ${:.4} < gas ${:.4}",
+                            self.name,
+                            order_id,
+                            order.symbol,
+                            reward as f64 / 1_000_000.0,
+                            self.gas_cost_usd as f64 / 1_000_000.0
+                        );
+                    }
+                    continue;
+                }
+                // Claim first (off-chain): only the keeper granted the claim pays for the execution
+                if self.claimed.insert(order_id) {
+                    self.claims_in_flight += 1;
                     sim.send(
                         self.id,
                         self.exchange_id,
-                        MessageType::ExecuteOrder,
+                        MessageType::ClaimOrder,
                         MessagePayload::ExecuteOrder(ExecuteOrderPayload {
-                            order_id: order.order_id,
+                            order_id,
+                            price_update: None,
                         }),
                     );
                 }
             }
         }
     }
+
+    fn execute_claimed(&mut self, sim: &mut dyn SimulatorApi, order_id: OrderId) {
+        self.claims_won += 1;
+        let symbol = self.pending_orders.get(&order_id).map(|o| o.symbol.clone()).unwrap_or_default();
+        agent_println!("[Keeper {}] Triggering order #{} {}", self.name, order_id, symbol);
+        let price_update = if symbol.is_empty() {
+            None
+        } else {
+            self.fetch_price_update(&symbol)
+        };
+        let execution = ExecuteOrderPayload { order_id, price_update };
+        if self.max_batch > 1 {
+            self.batch.push(execution);
+            return;
+        }
+        self.total_gas += self.gas_cost_usd;
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::ExecuteOrder,
+            MessagePayload::ExecuteOrder(execution),
+        );
+    }
+
+    /// An answer to a claim arrived: send the batch once the round is answered or the batch is full.
+    fn claim_answered(&mut self, sim: &mut dyn SimulatorApi) {
+        self.claims_in_flight = self.claims_in_flight.saturating_sub(1);
+        if self.claims_in_flight == 0 || self.batch.len() >= self.max_batch {
+            self.flush_batch(sim);
+        }
+    }
+
+    /// Send the queued executions, at most `max_batch` per transaction. A batch pays the full
+    /// gas for its first execution and `batched_order_gas_usd` for each further one.
+    fn flush_batch(&mut self, sim: &mut dyn SimulatorApi) {
+        let batch = std::mem::take(&mut self.batch);
+        for orders in batch.chunks(self.max_batch) {
+            self.batches_sent += 1;
+            self.total_gas += self.gas_cost_usd + (orders.len() as u64 - 1) * self.batched_order_gas_usd;
+            agent_println!("[Keeper {}] Executing {} orders in one transaction", self.name, orders.len());
+            sim.send(
+                self.id,
+                self.exchange_id,
+                MessageType::ExecuteOrderBatch,
+                MessagePayload::ExecuteOrderBatch(ExecuteOrderBatchPayload {
+                    orders: orders.to_vec(),
+                }),
+            );
+        }
+    }
 }
 
 impl Agent for KeeperAgent {
@@ -76,15 +261,19 @@ impl Agent for KeeperAgent {
     }
 
     fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
-        println!(
-            "[Keeper {}] Started (interval={}ms)",
+        agent_println!(
+            "[Keeper {}] Started (interval={}ms, gas=${:.4})",
             self.name,
             self.wake_interval_ns / 1_000_000,
+            self.gas_cost_usd as f64 / 1_000_000.0,
         );
         sim.wakeup(self.id, sim.now_ns() + self.wake_interval_ns);
     }
 
     fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
+        // Claims left unanswered (e.g. by a departed exchange) must not hold the batch back
+        self.claims_in_flight = 0;
+        self.flush_batch(sim);
         sim.send(
             self.id,
             self.exchange_id,
@@ -96,17 +285,20 @@ impl Agent for KeeperAgent {
 
     fn on_message(&mut self, sim: &mut dyn SimulatorApi, msg: &Message) {
         match msg.msg_type {
-            MessageType::OracleTick => {
-                if let MessagePayload::OracleTick(OracleTickPayload { symbol, price, .. }) = &msg.payload {
+            MessageType::OracleTick | MessageType::OracleBatchTick => {
+                for OracleTickPayload { symbol, price, .. } in msg.payload.oracle_ticks() {
                     self.prices.insert(symbol.clone(), *price);
-                    self.check_and_execute_triggers(sim);
+                    self.check_and_execute_triggers(sim, symbol);
                 }
             }
 
             MessageType::PendingOrdersList => {
                 if let MessagePayload::PendingOrdersList(PendingOrdersListPayload { orders }) = &msg.payload {
-                    self.pending_orders = orders.clone();
-                    self.check_and_execute_triggers(sim);
+                    self.set_pending_orders(orders);
+                    let symbols: Vec<String> = self.triggers.keys().cloned().collect();
+                    for symbol in &symbols {
+                        self.check_and_execute_triggers(sim, symbol);
+                    }
                 }
             }
 
@@ -118,7 +310,7 @@ impl Agent for KeeperAgent {
                 {
                     self.orders_executed += 1;
                     self.total_rewards += reward_micro_usd;
-                    println!(
+                    agent_println!(
                         "[Keeper {}] REWARD #{}: ${:.4}",
                         self.name,
                         order_id,
@@ -127,8 +319,21 @@ impl Agent for KeeperAgent {
                 }
             }
 
+            MessageType::OrderClaimed => {
+                if let MessagePayload::ExecuteOrder(ExecuteOrderPayload { order_id, .. }) = &msg.payload {
+                    self.execute_claimed(sim, *order_id);
+                }
+                self.claim_answered(sim);
+            }
+
+            MessageType::OrderClaimRejected => {
+                self.claims_lost += 1;
+                self.claim_answered(sim);
+            }
+
             MessageType::OrderAlreadyExecuted => {
                 self.orders_missed += 1;
+                self.claim_answered(sim);
             }
 
             _ => {}
@@ -136,12 +341,33 @@ impl Agent for KeeperAgent {
     }
 
     fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
-        println!(
-            "[Keeper {}] STOP: executed={} missed={} rewards=${:.2}",
+        agent_println!(
+            "[Keeper {}] STOP: executed={} missed={} skipped={} claims={}/{} rewards=${:.2} gas=${:.2} net=${:.2} forgone=${:.2}",
             self.name,
             self.orders_executed,
             self.orders_missed,
-            self.total_rewards as f64 / 1_000_000.0
+            self.orders_skipped,
+            self.claims_won,
+            self.claims_won + self.claims_lost,
+            self.total_rewards as f64 / 1_000_000.0,
+            self.total_gas as f64 / 1_000_000.0,
+            (self.total_rewards as i64 - self.total_gas as i64) as f64 / 1_000_000.0,
+            self.skipped_rewards as f64 / 1_000_000.0
         );
+        if self.batches_sent > 0 {
+            agent_println!(
+                "[Keeper {}] {} executions sent in {} batches",
+                self.name,
+                self.claims_won,
+                self.batches_sent
+            );
+        }
+        if self.price_fetch_failures > 0 {
+            agent_println!(
+                "[Keeper {}] {} executions sent without a fresh price (fetch failed)",
+                self.name,
+                self.price_fetch_failures
+            );
+        }
     }
 }
@@ -1,13 +1,16 @@
+use crate::agent_println;
 use crate::agents::Agent;
+use crate::indicators::{Atr, Rsi, Sma};
 use crate::messages::{
     AgentId, CancelOrderPayload, ExecutionType, Message, MessagePayload, MessageType,
-    OrderPayload, OrderType, OracleTickPayload, OrderExecutedPayload, OrderExecutionType,
+    ModifyOrderPayload, OrderPayload, OrderType, OracleTickPayload, OrderExecutedPayload, OrderExecutionType,
     Side, SimulatorApi,
 };
-use std::collections::VecDeque;
+use crate::qty::Qty;
 
 const DEFAULT_BALANCE: i128 = 50_000_000_000;
-const MAX_PRICE_HISTORY: usize = 300;
+/// Active mode reprices the resting entry once its target moved by more than this fraction
+const REPRICE_MIN_MOVE: f64 = 0.001;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OrderMode {
@@ -70,15 +73,16 @@ pub struct LimitTraderConfig {
     pub qty: f64,
     pub wake_interval_ms: u64,
     pub balance: Option<i128>,
+    /// Replace the fixed stop loss by a trailing stop this far (in %) behind the price
+    pub trailing_stop_pct: Option<f64>,
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Candle {
-    #[allow(dead_code)]
-    open: u64,
-    high: u64,
-    low: u64,
-    close: u64,
+/// Indicators of the Smart strategy: SMAs and RSI on every tick, ATR on closed candles
+struct SmartIndicators {
+    sma_fast: Sma,
+    sma_slow: Sma,
+    rsi: Rsi,
+    atr: Atr,
 }
 
 pub struct LimitTraderAgent {
@@ -90,6 +94,7 @@ pub struct LimitTraderAgent {
     strategy: LimitStrategy,
     qty: f64,
     wake_interval_ns: u64,
+    trailing_stop_pct: Option<f64>,
 
     balance: i128,
 
@@ -99,20 +104,21 @@ pub struct LimitTraderAgent {
 
     pending_entry_order: Option<u64>,
     pending_entry_side: Option<Side>,
+    pending_entry_price: Option<u64>,
     pending_sl_order: Option<u64>,
     pending_tp_order: Option<u64>,
+    /// OCO group of the last SL/TP pair
+    oco_group: u64,
 
-    price_history: VecDeque<u64>,
-    candles: VecDeque<Candle>,
-    current_candle: Option<Candle>,
-    last_candle_time: u64,
-    candle_duration_ns: u64,
+    indicators: Option<SmartIndicators>,
+    /// Start of the last closed candle fed to the ATR
+    last_candle_ns: Option<u64>,
     current_price: Option<u64>,
 
     last_signal: Signal,
-    last_atr: Option<f64>,
 
     orders_submitted: u32,
+    orders_repriced: u32,
     orders_filled: u32,
     orders_cancelled: u32,
     total_pnl: i128,
@@ -120,6 +126,21 @@ pub struct LimitTraderAgent {
 
 impl LimitTraderAgent {
     pub fn new(id: AgentId, config: LimitTraderConfig) -> Self {
+        let indicators = match &config.strategy {
+            LimitStrategy::Smart {
+                sma_fast,
+                sma_slow,
+                rsi_period,
+                atr_period,
+                ..
+            } => Some(SmartIndicators {
+                sma_fast: Sma::new(*sma_fast as usize),
+                sma_slow: Sma::new(*sma_slow as usize),
+                rsi: Rsi::new(*rsi_period as usize),
+                atr: Atr::new(*atr_period as usize),
+            }),
+            _ => None,
+        };
         Self {
             id,
             name: config.name,
@@ -129,23 +150,23 @@ impl LimitTraderAgent {
             strategy: config.strategy,
             qty: config.qty,
             wake_interval_ns: config.wake_interval_ms * 1_000_000,
+            trailing_stop_pct: config.trailing_stop_pct.filter(|pct| *pct > 0.0),
             balance: config.balance.unwrap_or(DEFAULT_BALANCE),
             has_position: false,
             position_side: None,
             entry_price: None,
             pending_entry_order: None,
             pending_entry_side: None,
+            pending_entry_price: None,
             pending_sl_order: None,
             pending_tp_order: None,
-            price_history: VecDeque::with_capacity(MAX_PRICE_HISTORY),
-            candles: VecDeque::with_capacity(100),
-            current_candle: None,
-            last_candle_time: 0,
-            candle_duration_ns: 5_000_000_000, // 5 sec candles
+            oco_group: 0,
+            indicators,
+            last_candle_ns: None,
             current_price: None,
             last_signal: Signal::None,
-            last_atr: None,
             orders_submitted: 0,
+            orders_repriced: 0,
             orders_filled: 0,
             orders_cancelled: 0,
             total_pnl: 0,
@@ -158,172 +179,63 @@ impl LimitTraderAgent {
 
     // ========== INDICATORS ==========
 
-    fn calc_sma(&self, period: u32) -> Option<f64> {
-        if self.price_history.len() < period as usize {
-            return None;
-        }
-        let sum: u64 = self.price_history.iter().rev().take(period as usize).sum();
-        Some(sum as f64 / period as f64)
-    }
-
-    #[allow(dead_code)]
-    fn calc_ema(&self, period: u32) -> Option<f64> {
-        if self.price_history.len() < period as usize {
-            return None;
-        }
-        let k = 2.0 / (period as f64 + 1.0);
-        let prices: Vec<u64> = self.price_history.iter().rev().take(period as usize * 2).copied().collect();
-        
-        let mut ema = prices.last().copied()? as f64;
-        for &p in prices.iter().rev().skip(1) {
-            ema = (p as f64) * k + ema * (1.0 - k);
-        }
-        Some(ema)
-    }
-
-    fn calc_rsi(&self, period: u32) -> Option<f64> {
-        if self.price_history.len() < (period + 1) as usize {
-            return None;
-        }
-
-        let prices: Vec<u64> = self.price_history.iter().rev().take((period + 1) as usize).copied().collect();
-        
-        let mut gains = 0.0;
-        let mut losses = 0.0;
-
-        for i in 0..period as usize {
-            let diff = prices[i] as f64 - prices[i + 1] as f64;
-            if diff > 0.0 {
-                gains += diff;
-            } else {
-                losses += -diff;
-            }
-        }
-
-        let avg_gain = gains / period as f64;
-        let avg_loss = losses / period as f64;
-
-        if avg_loss < 0.0001 {
-            return Some(100.0);
-        }
-
-        let rs = avg_gain / avg_loss;
-        Some(100.0 - (100.0 / (1.0 + rs)))
-    }
-
-    fn calc_atr(&self, period: u32) -> Option<f64> {
-        if self.candles.len() < period as usize {
-            return None;
-        }
-
-        let candles: Vec<&Candle> = self.candles.iter().rev().take(period as usize).collect();
-        
-        let mut tr_sum = 0.0;
-        for (i, c) in candles.iter().enumerate() {
-            let high_low = (c.high - c.low) as f64;
-            let tr = if i + 1 < candles.len() {
-                let prev_close = candles[i + 1].close as f64;
-                let high_close = (c.high as f64 - prev_close).abs();
-                let low_close = (c.low as f64 - prev_close).abs();
-                high_low.max(high_close).max(low_close)
-            } else {
-                high_low
-            };
-            tr_sum += tr;
-        }
-
-        Some(tr_sum / period as f64)
-    }
-
-    fn update_candle(&mut self, price: u64, now_ns: u64) {
-        if self.last_candle_time == 0 {
-            self.last_candle_time = now_ns;
-            self.current_candle = Some(Candle {
-                open: price,
-                high: price,
-                low: price,
-                close: price,
-            });
+    /// Feed the candles closed since the last call to the ATR (Smart strategy).
+    fn update_atr(&mut self, sim: &dyn SimulatorApi) {
+        let (Some(indicators), Some(history)) = (self.indicators.as_mut(), sim.market_data().history(&self.symbol))
+        else {
             return;
-        }
-
-        if now_ns - self.last_candle_time >= self.candle_duration_ns {
-            if let Some(candle) = self.current_candle.take() {
-                self.candles.push_back(candle);
-                if self.candles.len() > 100 {
-                    self.candles.pop_front();
-                }
-            }
-            self.last_candle_time = now_ns;
-            self.current_candle = Some(Candle {
-                open: price,
-                high: price,
-                low: price,
-                close: price,
-            });
-        } else if let Some(ref mut candle) = self.current_candle {
-            candle.high = candle.high.max(price);
-            candle.low = candle.low.min(price);
-            candle.close = price;
+        };
+        let since = self.last_candle_ns;
+        for candle in history.candles().filter(|c| since.is_none_or(|s| c.start_ns > s)) {
+            indicators
+                .atr
+                .update(candle.high as f64, candle.low as f64, candle.close as f64);
+            self.last_candle_ns = Some(candle.start_ns);
         }
     }
 
     // ========== SIGNAL LOGIC ==========
 
-    fn detect_trend(&self, lookback: u32) -> Option<bool> {
-        if self.price_history.len() < lookback as usize {
+    fn detect_trend(&self, sim: &dyn SimulatorApi, lookback: u32) -> Option<bool> {
+        let history = sim.market_data().history(&self.symbol)?;
+        if history.len() < lookback as usize {
             return None;
         }
 
-        let recent: Vec<u64> = self.price_history
-            .iter()
-            .rev()
-            .take(lookback as usize)
-            .copied()
-            .collect();
-
-        let first = *recent.last()?;
-        let last = *recent.first()?;
+        let mut recent = history.recent_mids(lookback as usize);
+        let first = recent.next()?;
+        let last = recent.last().unwrap_or(first);
 
         Some(last > first)
     }
 
-    fn calc_smart_signal(&mut self) -> Signal {
-        if let LimitStrategy::Smart {
-            sma_fast,
-            sma_slow,
-            rsi_period,
-            rsi_low,
-            rsi_high,
-            atr_period,
-            ..
-        } = &self.strategy
-        {
-            let sma_f = match self.calc_sma(*sma_fast) {
-                Some(v) => v,
-                None => return Signal::None,
-            };
-            let sma_s = match self.calc_sma(*sma_slow) {
-                Some(v) => v,
-                None => return Signal::None,
-            };
-            let rsi = match self.calc_rsi(*rsi_period) {
-                Some(v) => v,
-                None => return Signal::None,
-            };
-
-            self.last_atr = self.calc_atr(*atr_period);
+    fn calc_smart_signal(&self) -> Signal {
+        let (LimitStrategy::Smart { rsi_low, rsi_high, .. }, Some(indicators)) = (&self.strategy, &self.indicators)
+        else {
+            return Signal::None;
+        };
+        let (Some(sma_f), Some(sma_s), Some(rsi)) = (
+            indicators.sma_fast.value(),
+            indicators.sma_slow.value(),
+            indicators.rsi.value(),
+        ) else {
+            return Signal::None;
+        };
 
-            if sma_f > sma_s && rsi < *rsi_low {
-                return Signal::Buy;
-            }
-            if sma_f < sma_s && rsi > *rsi_high {
-                return Signal::Sell;
-            }
+        if sma_f > sma_s && rsi < *rsi_low {
+            return Signal::Buy;
+        }
+        if sma_f < sma_s && rsi > *rsi_high {
+            return Signal::Sell;
         }
         Signal::None
     }
 
+    /// ATR of the closed candles (None while warming up or outside the Smart strategy)
+    fn atr(&self) -> Option<f64> {
+        self.indicators.as_ref().and_then(|i| i.atr.value())
+    }
+
     // ========== ORDER MANAGEMENT ==========
 
     fn submit_entry_order(&mut self, sim: &mut dyn SimulatorApi, side: Side, trigger_price: u64) {
@@ -334,15 +246,18 @@ impl LimitTraderAgent {
             side,
             order_type: OrderType::Increase,
             execution_type: ExecutionType::Limit,
-            qty: Some(self.qty),
+            qty: Some(Qty::from_tokens(self.qty)),
             leverage: Some(leverage),
             size_delta_usd: None,
             trigger_price: Some(trigger_price),
             acceptable_price: None,
+            trailing_distance: None,
             valid_for_sec: Some(3600),
+            oco_group: None,
+            decision_price: None,
         };
 
-        println!(
+        agent_println!(
             "[{}] SUBMIT LIMIT {} @ ${:.2}",
             self.name,
             if side == Side::Buy { "BUY" } else { "SELL" },
@@ -357,12 +272,46 @@ impl LimitTraderAgent {
         );
 
         self.pending_entry_side = Some(side);
+        self.pending_entry_price = Some(trigger_price);
         self.orders_submitted += 1;
     }
 
+    /// Move the resting entry to a new trigger instead of cancelling and resubmitting it.
+    fn reprice_pending_entry(&mut self, sim: &mut dyn SimulatorApi, order_id: u64, trigger_price: u64) {
+        let Some(current) = self.pending_entry_price else {
+            return;
+        };
+        if (trigger_price as f64 - current as f64).abs() <= current as f64 * REPRICE_MIN_MOVE {
+            return;
+        }
+
+        agent_println!(
+            "[{}] REPRICE #{} ${:.2} -> ${:.2}",
+            self.name,
+            order_id,
+            current as f64 / 1_000_000.0,
+            trigger_price as f64 / 1_000_000.0
+        );
+
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::ModifyOrder,
+            MessagePayload::ModifyOrder(ModifyOrderPayload {
+                order_id,
+                trigger_price: Some(trigger_price),
+                qty: None,
+                size_delta_usd: None,
+            }),
+        );
+
+        self.pending_entry_price = Some(trigger_price);
+        self.orders_repriced += 1;
+    }
+
     fn cancel_pending_entry(&mut self, sim: &mut dyn SimulatorApi) {
         if let Some(order_id) = self.pending_entry_order.take() {
-            println!("[{}] CANCEL #{}", self.name, order_id);
+            agent_println!("[{}] CANCEL #{}", self.name, order_id);
             sim.send(
                 self.id,
                 self.exchange_id,
@@ -370,6 +319,22 @@ impl LimitTraderAgent {
                 MessagePayload::CancelOrder(CancelOrderPayload { order_id }),
             );
             self.pending_entry_side = None;
+            self.pending_entry_price = None;
+        }
+    }
+
+    /// Drop the SL/TP orders once the position is gone.
+    fn cancel_exit_orders(&mut self, sim: &mut dyn SimulatorApi) {
+        for order_id in [self.pending_sl_order.take(), self.pending_tp_order.take()]
+            .into_iter()
+            .flatten()
+        {
+            sim.send(
+                self.id,
+                self.exchange_id,
+                MessageType::CancelOrder,
+                MessagePayload::CancelOrder(CancelOrderPayload { order_id }),
+            );
         }
     }
 
@@ -380,22 +345,41 @@ impl LimitTraderAgent {
         };
 
         let (sl_price, tp_price) = self.calc_sl_tp_prices(entry_price, side);
+        // Linked: whichever leg executes, the exchange cancels the other
+        self.oco_group += 1;
 
-        // Stop Loss
+        // Stop Loss (fixed, or trailing the price when configured)
+        let trailing_distance = self
+            .trailing_stop_pct
+            .map(|pct| (entry_price as f64 * pct / 100.0) as u64);
         let sl_order = OrderPayload {
             symbol: self.symbol.clone(),
             side,
             order_type: OrderType::Decrease,
-            execution_type: ExecutionType::StopLoss,
+            execution_type: if trailing_distance.is_some() {
+                ExecutionType::TrailingStop
+            } else {
+                ExecutionType::StopLoss
+            },
             qty: None,
             leverage: None,
             size_delta_usd: None,
-            trigger_price: Some(sl_price),
+            trigger_price: if trailing_distance.is_some() { None } else { Some(sl_price) },
             acceptable_price: None,
+            trailing_distance,
             valid_for_sec: Some(86400),
+            oco_group: Some(self.oco_group),
+            decision_price: None,
         };
 
-        println!("[{}] SUBMIT SL @ ${:.2}", self.name, sl_price as f64 / 1_000_000.0);
+        match trailing_distance {
+            Some(distance) => agent_println!(
+                "[{}] SUBMIT TRAILING SL distance=${:.2}",
+                self.name,
+                distance as f64 / 1_000_000.0
+            ),
+            None => agent_println!("[{}] SUBMIT SL @ ${:.2}", self.name, sl_price as f64 / 1_000_000.0),
+        }
 
         sim.send(
             self.id,
@@ -415,10 +399,13 @@ impl LimitTraderAgent {
             size_delta_usd: None,
             trigger_price: Some(tp_price),
             acceptable_price: None,
+            trailing_distance: None,
             valid_for_sec: Some(86400),
+            oco_group: Some(self.oco_group),
+            decision_price: None,
         };
 
-        println!("[{}] SUBMIT TP @ ${:.2}", self.name, tp_price as f64 / 1_000_000.0);
+        agent_println!("[{}] SUBMIT TP @ ${:.2}", self.name, tp_price as f64 / 1_000_000.0);
 
         sim.send(
             self.id,
@@ -437,7 +424,7 @@ impl LimitTraderAgent {
                 take_atr_mult,
                 ..
             } => {
-                let atr = self.last_atr.unwrap_or(entry_price as f64 * 0.02);
+                let atr = self.atr().unwrap_or(entry_price as f64 * 0.02);
                 let (sl, tp) = match side {
                     Side::Buy => (
                         (entry_price as f64 - atr * stop_atr_mult) as u64,
@@ -505,7 +492,7 @@ impl LimitTraderAgent {
                 None => return,
             };
 
-            let trend_up = match self.detect_trend(*trend_lookback) {
+            let trend_up = match self.detect_trend(sim, *trend_lookback) {
                 Some(t) => t,
                 None => return,
             };
@@ -563,36 +550,34 @@ impl LimitTraderAgent {
             }
         }
 
-        if self.pending_entry_order.is_some() {
+        let entry = self.smart_entry(signal);
+
+        if let Some(order_id) = self.pending_entry_order {
+            // Active mode: the signal still holds, follow it with the resting entry
+            if let (OrderMode::Active, Some((_, trigger_price))) = (order_mode, entry) {
+                self.reprice_pending_entry(sim, order_id, trigger_price);
+            }
             return;
         }
 
-        if signal == Signal::None {
-            return;
+        if let Some((side, trigger_price)) = entry {
+            self.last_signal = signal;
+            self.submit_entry_order(sim, side, trigger_price);
         }
+    }
 
-        let current_price = match self.current_price {
-            Some(p) => p,
-            None => return,
+    /// Entry side and trigger for a Smart signal: `entry_atr_mult` ATRs away from the price.
+    fn smart_entry(&self, signal: Signal) -> Option<(Side, u64)> {
+        let LimitStrategy::Smart { entry_atr_mult, .. } = &self.strategy else {
+            return None;
         };
+        let current_price = self.current_price?;
+        let atr = self.atr().unwrap_or(current_price as f64 * 0.01);
 
-        let atr = self.last_atr.unwrap_or(current_price as f64 * 0.01);
-
-        if let LimitStrategy::Smart { entry_atr_mult, .. } = &self.strategy {
-            let (side, trigger_price) = match signal {
-                Signal::Buy => {
-                    let price = (current_price as f64 - atr * entry_atr_mult) as u64;
-                    (Side::Buy, price)
-                }
-                Signal::Sell => {
-                    let price = (current_price as f64 + atr * entry_atr_mult) as u64;
-                    (Side::Sell, price)
-                }
-                Signal::None => return,
-            };
-
-            self.last_signal = signal;
-            self.submit_entry_order(sim, side, trigger_price);
+        match signal {
+            Signal::Buy => Some((Side::Buy, (current_price as f64 - atr * entry_atr_mult) as u64)),
+            Signal::Sell => Some((Side::Sell, (current_price as f64 + atr * entry_atr_mult) as u64)),
+            Signal::None => None,
         }
     }
 
@@ -603,6 +588,7 @@ impl LimitTraderAgent {
                 self.position_side = Some(payload.side);
                 self.pending_entry_order = None;
                 self.pending_entry_side = None;
+                self.pending_entry_price = None;
                 self.orders_filled += 1;
 
                 if let Some(price) = self.current_price {
@@ -610,25 +596,27 @@ impl LimitTraderAgent {
                     self.submit_sl_tp_orders(sim, price);
                 }
 
-                println!("[{}] ENTRY FILLED {:?}", self.name, payload.side);
+                agent_println!("[{}] ENTRY FILLED {:?}", self.name, payload.side);
             }
             OrderExecutionType::Decrease => {
+                // The other leg of the OCO pair is cancelled by the exchange
+                self.pending_sl_order = None;
                 self.has_position = false;
                 self.position_side = None;
                 self.entry_price = None;
-                self.pending_sl_order = None;
                 self.pending_tp_order = None;
                 self.orders_filled += 1;
                 self.total_pnl += payload.pnl;
                 self.last_signal = Signal::None;
 
-                println!(
+                agent_println!(
                     "[{}] CLOSED pnl=${:.2}",
                     self.name,
                     payload.pnl as f64 / 1_000_000.0
                 );
             }
             OrderExecutionType::Liquidation => {
+                self.cancel_exit_orders(sim);
                 self.has_position = false;
                 self.position_side = None;
                 self.total_pnl += payload.pnl;
@@ -647,6 +635,23 @@ impl Agent for LimitTraderAgent {
         &self.name
     }
 
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        match (key, value.as_f64()) {
+            ("qty", Some(v)) if v > 0.0 => self.qty = v,
+            ("wake_interval_ms", Some(v)) if v >= 1.0 => self.wake_interval_ns = v as u64 * 1_000_000,
+            ("trailing_stop_pct", Some(v)) if v >= 0.0 => self.trailing_stop_pct = (v > 0.0).then_some(v),
+            ("leverage", Some(v)) if v >= 1.0 => match &mut self.strategy {
+                LimitStrategy::MeanReversion { leverage, .. }
+                | LimitStrategy::Breakout { leverage, .. }
+                | LimitStrategy::Grid { leverage, .. }
+                | LimitStrategy::Smart { leverage, .. } => *leverage = v as u32,
+            },
+            _ => return false,
+        }
+        agent_println!("[{}] param {} = {}", self.name, key, value);
+        true
+    }
+
     fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
         let strategy_name = match &self.strategy {
             LimitStrategy::MeanReversion { .. } => "MeanReversion".to_string(),
@@ -659,7 +664,7 @@ impl Agent for LimitTraderAgent {
             }
         };
 
-        println!(
+        agent_println!(
             "[{}] START {} bal=${:.0}{}",
             self.name,
             strategy_name,
@@ -685,20 +690,17 @@ impl Agent for LimitTraderAgent {
     }
 
     fn on_message(&mut self, sim: &mut dyn SimulatorApi, msg: &Message) {
-        let now_ns = sim.now_ns();
-
         match msg.msg_type {
-            MessageType::OracleTick => {
-                if let MessagePayload::OracleTick(OracleTickPayload { symbol, price, .. }) = &msg.payload {
-                    if *symbol == self.symbol {
-                        let mid = (price.min + price.max) / 2;
-                        self.current_price = Some(mid);
-                        self.price_history.push_back(mid);
-                        if self.price_history.len() > MAX_PRICE_HISTORY {
-                            self.price_history.pop_front();
-                        }
-                        self.update_candle(mid, now_ns);
+            MessageType::OracleTick | MessageType::OracleBatchTick => {
+                if let Some(OracleTickPayload { price, .. }) = msg.payload.oracle_tick(&self.symbol) {
+                    let mid = (price.min + price.max) / 2;
+                    self.current_price = Some(mid);
+                    if let Some(indicators) = self.indicators.as_mut() {
+                        indicators.sma_fast.update(mid as f64);
+                        indicators.sma_slow.update(mid as f64);
+                        indicators.rsi.update(mid as f64);
                     }
+                    self.update_atr(sim);
                 }
             }
             MessageType::OrderPending => {
@@ -707,6 +709,11 @@ impl Agent for LimitTraderAgent {
                         if let Ok(id) = id_str.parse::<u64>() {
                             if !self.has_position {
                                 self.pending_entry_order = Some(id);
+                            } else if self.pending_sl_order.is_none() {
+                                // SL and TP are acknowledged in submission order
+                                self.pending_sl_order = Some(id);
+                            } else {
+                                self.pending_tp_order = Some(id);
                             }
                         }
                     }
@@ -722,6 +729,7 @@ impl Agent for LimitTraderAgent {
             MessageType::PositionLiquidated => {
                 if let MessagePayload::PositionLiquidated(p) = &msg.payload {
                     if p.symbol == self.symbol {
+                        self.cancel_exit_orders(sim);
                         self.has_position = false;
                         self.position_side = None;
                         self.total_pnl += p.pnl;
@@ -735,15 +743,19 @@ impl Agent for LimitTraderAgent {
                     eprintln!("[{}] OrderRejected — clearing pending entry", self.name);
                     self.pending_entry_order = None;
                     self.pending_entry_side = None;
+                    self.pending_entry_price = None;
                 }
             }
-            MessageType::OrderCancelled => {
+            MessageType::OrderCancelled | MessageType::OrderExpired => {
                 if let MessagePayload::Text(text) = &msg.payload {
-                    if text.contains("order_id:") {
-                        if self.pending_entry_order.is_some() {
-                            self.pending_entry_order = None;
-                            self.pending_entry_side = None;
-                        }
+                    let order_id = text
+                        .strip_prefix("order_id:")
+                        .and_then(|rest| rest.split(',').next())
+                        .and_then(|id| id.parse::<u64>().ok());
+                    if order_id.is_some() && self.pending_entry_order == order_id {
+                        self.pending_entry_order = None;
+                        self.pending_entry_side = None;
+                        self.pending_entry_price = None;
                     }
                 }
                 self.orders_cancelled += 1;
@@ -759,10 +771,11 @@ impl Agent for LimitTraderAgent {
             format!("-${:.2}", (-self.total_pnl) as f64 / 1_000_000.0)
         };
 
-        println!(
-            "[{}] STOP: submitted={} filled={} cancelled={} pnl={}",
+        agent_println!(
+            "[{}] STOP: submitted={} repriced={} filled={} cancelled={} pnl={}",
             self.name,
             self.orders_submitted,
+            self.orders_repriced,
             self.orders_filled,
             self.orders_cancelled,
             pnl_str
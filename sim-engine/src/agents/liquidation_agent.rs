@@ -1,3 +1,4 @@
+use crate::agent_println;
 use crate::agents::Agent;
 use crate::messages::{AgentId, Message, MessagePayload, MessageType, SimulatorApi};
 
@@ -38,7 +39,7 @@ impl Agent for LiquidationAgent {
     }
 
     fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
-        println!(
+        agent_println!(
             "[Liquidation {}] starting (interval={}ms)",
             self.name,
             self.wake_interval_ns / 1_000_000,
@@ -59,9 +60,11 @@ impl Agent for LiquidationAgent {
     }
 
     fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
-        println!(
+        agent_println!(
             "[Liquidation {}] stopping after {} scans, {} liquidations triggered",
-            self.name, self.scan_count, self.liquidations_triggered
+            self.name,
+            self.scan_count,
+            self.liquidations_triggered
         );
     }
 }
@@ -0,0 +1,389 @@
+//! LpAgent - pool liquidity provider.
+//!
+//! Deposits `initial_usd` on start, then reacts to every MarketState:
+//! - utilization (larger OI side / liquidity) above `add_above_util` -> add
+//!   `step_usd` (up to `max_deposit_usd`): a busy pool earns more fees
+//! - utilization below `remove_below_util` -> withdraw `step_usd`
+//! - realized pool PnL since the LP's first deposit below
+//!   `-panic_loss_pct` of the pool -> withdraw everything (panic)
+//!
+//! Several LPs with different thresholds model a pool run. The contract has
+//! no RemoveLiquidity, so withdrawals only work for local agents.
+
+use crate::agent_println;
+use crate::agents::Agent;
+use crate::messages::{
+    AgentId, LiquidityPayload, MarketStatePayload, Message, MessagePayload, MessageType, SimulatorApi,
+};
+
+#[derive(Debug, Clone)]
+pub struct LpConfig {
+    pub name: String,
+    pub exchange_id: AgentId,
+    pub symbol: String,
+    pub initial_usd: i128,
+    pub step_usd: i128,
+    pub max_deposit_usd: i128,
+    /// Utilization thresholds (0.0 - 1.0)
+    pub add_above_util: f64,
+    pub remove_below_util: f64,
+    /// Pool loss (percent of liquidity) that triggers a full withdrawal
+    pub panic_loss_pct: f64,
+    /// Minimum time between two actions
+    pub cooldown_ms: u64,
+}
+
+pub struct LpAgent {
+    id: AgentId,
+    name: String,
+    exchange_id: AgentId,
+    symbol: String,
+    address: Option<String>,
+
+    initial_usd: i128,
+    step_usd: i128,
+    max_deposit_usd: i128,
+    add_above_util: f64,
+    remove_below_util: f64,
+    panic_loss_pct: f64,
+    cooldown_ns: u64,
+
+    /// Value of the pool share as last reported by the exchange
+    lp_value_usd: i128,
+    /// Net amount put into the pool
+    deposited_usd: i128,
+    /// Pool PnL when the LP entered (first MarketState after the deposit)
+    entry_pool_pnl: Option<i128>,
+    /// A request is waiting for LiquidityChanged / OrderRejected
+    in_flight: bool,
+    last_action_ns: u64,
+    panicked: bool,
+
+    adds: u32,
+    removes: u32,
+    rejected: u32,
+}
+
+impl LpAgent {
+    pub fn new(id: AgentId, config: LpConfig) -> Self {
+        Self {
+            id,
+            name: config.name,
+            exchange_id: config.exchange_id,
+            symbol: config.symbol,
+            address: None,
+            initial_usd: config.initial_usd,
+            step_usd: config.step_usd,
+            max_deposit_usd: config.max_deposit_usd,
+            add_above_util: config.add_above_util,
+            remove_below_util: config.remove_below_util,
+            panic_loss_pct: config.panic_loss_pct,
+            cooldown_ns: config.cooldown_ms * 1_000_000,
+            lp_value_usd: 0,
+            deposited_usd: 0,
+            entry_pool_pnl: None,
+            in_flight: false,
+            last_action_ns: 0,
+            panicked: false,
+            adds: 0,
+            removes: 0,
+            rejected: 0,
+        }
+    }
+
+    pub fn set_address(&mut self, address: String) {
+        self.address = Some(address);
+    }
+
+    fn request(&mut self, sim: &mut dyn SimulatorApi, msg_type: MessageType, amount_usd: i128) {
+        sim.send(
+            self.id,
+            self.exchange_id,
+            msg_type,
+            MessagePayload::Liquidity(LiquidityPayload {
+                symbol: self.symbol.clone(),
+                amount_usd,
+            }),
+        );
+        self.in_flight = true;
+        self.last_action_ns = sim.now_ns();
+    }
+
+    fn on_market_state(&mut self, sim: &mut dyn SimulatorApi, state: &MarketStatePayload) {
+        if self.in_flight || state.liquidity_usd <= 0 {
+            return;
+        }
+        if self.deposited_usd > 0 && self.entry_pool_pnl.is_none() {
+            self.entry_pool_pnl = Some(state.pool_pnl_usd);
+        }
+        if sim.now_ns().saturating_sub(self.last_action_ns) < self.cooldown_ns {
+            return;
+        }
+
+        let utilization = state.oi_long_usd.max(state.oi_short_usd) as f64 / state.liquidity_usd as f64;
+        let loss_pct = self.entry_pool_pnl.map_or(0.0, |entry| {
+            (entry - state.pool_pnl_usd) as f64 / state.liquidity_usd as f64 * 100.0
+        });
+
+        // After a panic the LP keeps withdrawing what the pool lets it take
+        if self.lp_value_usd > 0 && (self.panicked || loss_pct >= self.panic_loss_pct) {
+            agent_println!(
+                "[LP {}] PANIC pool loss {:.2}% util={:.1}% -> withdraw ${:.2}",
+                self.name,
+                loss_pct,
+                utilization * 100.0,
+                self.lp_value_usd as f64 / 1_000_000.0
+            );
+            self.panicked = true;
+            self.request(sim, MessageType::RemoveLiquidity, self.lp_value_usd);
+        } else if self.panicked {
+            // Stays out of the pool
+        } else if utilization > self.add_above_util && self.deposited_usd < self.max_deposit_usd {
+            let amount = self.step_usd.min(self.max_deposit_usd - self.deposited_usd);
+            agent_println!(
+                "[LP {}] ADD ${:.2} util={:.1}%",
+                self.name,
+                amount as f64 / 1_000_000.0,
+                utilization * 100.0
+            );
+            self.request(sim, MessageType::AddLiquidity, amount);
+        } else if utilization < self.remove_below_util && self.lp_value_usd > 0 {
+            let amount = self.step_usd.min(self.lp_value_usd);
+            agent_println!(
+                "[LP {}] REMOVE ${:.2} util={:.1}%",
+                self.name,
+                amount as f64 / 1_000_000.0,
+                utilization * 100.0
+            );
+            self.request(sim, MessageType::RemoveLiquidity, amount);
+        }
+    }
+}
+
+impl Agent for LpAgent {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        match (key, value.as_f64()) {
+            ("step_usd", Some(v)) if v > 0.0 => self.step_usd = v as i128,
+            ("max_deposit_usd", Some(v)) if v >= 0.0 => self.max_deposit_usd = v as i128,
+            ("add_above_util", Some(v)) if v >= 0.0 => self.add_above_util = v,
+            ("remove_below_util", Some(v)) if v >= 0.0 => self.remove_below_util = v,
+            ("panic_loss_pct", Some(v)) if v > 0.0 => self.panic_loss_pct = v,
+            _ => return false,
+        }
+        agent_println!("[LP {}] param {} = {}", self.name, key, value);
+        true
+    }
+
+    fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[LP {}] START {} deposit=${:.2} util add>{:.0}% remove<{:.0}% panic at -{:.1}%{}",
+            self.name,
+            self.symbol,
+            self.initial_usd as f64 / 1_000_000.0,
+            self.add_above_util * 100.0,
+            self.remove_below_util * 100.0,
+            self.panic_loss_pct,
+            self.address
+                .as_deref()
+                .map(|addr| format!(" addr={}", addr))
+                .unwrap_or_default()
+        );
+        if self.initial_usd > 0 {
+            self.request(sim, MessageType::AddLiquidity, self.initial_usd);
+        }
+    }
+
+    fn on_wakeup(&mut self, _sim: &mut dyn SimulatorApi, _now_ns: u64) {}
+
+    fn on_message(&mut self, sim: &mut dyn SimulatorApi, msg: &Message) {
+        match &msg.payload {
+            MessagePayload::MarketState(state) if state.symbol == self.symbol => self.on_market_state(sim, state),
+            MessagePayload::LiquidityChanged(p) if p.symbol == self.symbol => {
+                self.in_flight = false;
+                self.deposited_usd += p.delta_usd;
+                self.lp_value_usd = p.lp_value_usd;
+                if p.delta_usd > 0 {
+                    self.adds += 1;
+                } else {
+                    self.removes += 1;
+                }
+                agent_println!(
+                    "[LP {}] {} ${:.2} share=${:.2} pool=${:.2}",
+                    self.name,
+                    if p.delta_usd > 0 { "ADDED" } else { "REMOVED" },
+                    p.delta_usd.abs() as f64 / 1_000_000.0,
+                    p.lp_value_usd as f64 / 1_000_000.0,
+                    p.liquidity_usd as f64 / 1_000_000.0
+                );
+            }
+            _ if msg.msg_type == MessageType::OrderRejected && self.in_flight => {
+                self.in_flight = false;
+                self.rejected += 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[LP {}] STOP: adds={} removes={} rejected={} net_deposit=${:.2} share=${:.2}{}",
+            self.name,
+            self.adds,
+            self.removes,
+            self.rejected,
+            self.deposited_usd as f64 / 1_000_000.0,
+            self.lp_value_usd as f64 / 1_000_000.0,
+            if self.panicked { " (panicked)" } else { "" }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::test_support::{delivered, kernel_with_recorder, Received};
+    use crate::kernel::Kernel;
+    use crate::messages::LiquidityChangedPayload;
+
+    const EXCHANGE: AgentId = 1;
+    const USD: i128 = 1_000_000;
+
+    fn lp() -> LpAgent {
+        LpAgent::new(
+            20,
+            LpConfig {
+                name: "lp".to_string(),
+                exchange_id: EXCHANGE,
+                symbol: "ETH-USD".to_string(),
+                initial_usd: 100_000 * USD,
+                step_usd: 10_000 * USD,
+                max_deposit_usd: 110_000 * USD,
+                add_above_util: 0.5,
+                remove_below_util: 0.1,
+                panic_loss_pct: 5.0,
+                cooldown_ms: 0,
+            },
+        )
+    }
+
+    /// MarketState of a $1M pool with `oi_usd` on the long side
+    fn state(oi_usd: i128, pool_pnl_usd: i128) -> Message {
+        let state = MarketStatePayload {
+            symbol: "ETH-USD".to_string(),
+            oi_long_usd: oi_usd * USD,
+            oi_short_usd: 0,
+            liquidity_usd: 1_000_000 * USD,
+            pool_pnl_usd: pool_pnl_usd * USD,
+            funding_rate_bps_hour_fp: 0,
+            borrowing_rate_bps_hour_fp: 0,
+        };
+        Message::new(
+            20,
+            EXCHANGE,
+            MessageType::MarketState,
+            0,
+            MessagePayload::MarketState(state),
+        )
+    }
+
+    fn changed(delta_usd: i128, lp_value_usd: i128) -> Message {
+        let changed = LiquidityChangedPayload {
+            symbol: "ETH-USD".to_string(),
+            delta_usd: delta_usd * USD,
+            lp_value_usd: lp_value_usd * USD,
+            liquidity_usd: 1_000_000 * USD,
+        };
+        Message::new(
+            20,
+            EXCHANGE,
+            MessageType::LiquidityChanged,
+            0,
+            MessagePayload::LiquidityChanged(changed),
+        )
+    }
+
+    /// Liquidity requests the exchange received: (type, amount in USD)
+    fn requests(kernel: &mut Kernel, received: &Received) -> Vec<(MessageType, i128)> {
+        delivered(kernel, received)
+            .into_iter()
+            .filter_map(|msg| match msg.payload {
+                MessagePayload::Liquidity(p) => Some((msg.msg_type, p.amount_usd / USD)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_adds_when_busy_and_removes_when_idle() {
+        let (mut kernel, received) = kernel_with_recorder(EXCHANGE);
+        let mut lp = lp();
+        lp.on_start(&mut kernel);
+        assert_eq!(
+            requests(&mut kernel, &received),
+            vec![(MessageType::AddLiquidity, 100_000)]
+        );
+
+        // Nothing while the deposit is in flight
+        lp.on_message(&mut kernel, &state(600_000, 0));
+        assert!(requests(&mut kernel, &received).is_empty());
+
+        lp.on_message(&mut kernel, &changed(100_000, 100_000));
+        lp.on_message(&mut kernel, &state(600_000, 0));
+        assert_eq!(
+            requests(&mut kernel, &received),
+            vec![(MessageType::AddLiquidity, 10_000)]
+        );
+
+        // At `max_deposit_usd` a busy pool gets nothing more; an idle one loses a step
+        lp.on_message(&mut kernel, &changed(10_000, 110_000));
+        lp.on_message(&mut kernel, &state(600_000, 0));
+        assert!(requests(&mut kernel, &received).is_empty());
+        lp.on_message(&mut kernel, &state(50_000, 0));
+        assert_eq!(
+            requests(&mut kernel, &received),
+            vec![(MessageType::RemoveLiquidity, 10_000)]
+        );
+    }
+
+    #[test]
+    fn test_panic_withdraws_everything_and_stays_out() {
+        let (mut kernel, received) = kernel_with_recorder(EXCHANGE);
+        let mut lp = lp();
+        lp.on_start(&mut kernel);
+        lp.on_message(&mut kernel, &changed(100_000, 100_000));
+        // Entry pool PnL is taken from the first state after the deposit
+        lp.on_message(&mut kernel, &state(200_000, 20_000));
+        delivered(&mut kernel, &received);
+
+        // A pool loss just under 5% of the liquidity is tolerated
+        lp.on_message(&mut kernel, &state(200_000, -29_000));
+        assert!(requests(&mut kernel, &received).is_empty());
+        lp.on_message(&mut kernel, &state(200_000, -31_000));
+        assert_eq!(
+            requests(&mut kernel, &received),
+            vec![(MessageType::RemoveLiquidity, 100_000)]
+        );
+
+        // After a rejection it keeps withdrawing even once the pool recovered
+        let rejected = Message::new(20, EXCHANGE, MessageType::OrderRejected, 0, MessagePayload::Empty);
+        lp.on_message(&mut kernel, &rejected);
+        lp.on_message(&mut kernel, &state(200_000, 20_000));
+        assert_eq!(
+            requests(&mut kernel, &received),
+            vec![(MessageType::RemoveLiquidity, 100_000)]
+        );
+
+        // Out of the pool, a busy pool doesn't bring it back
+        lp.on_message(&mut kernel, &changed(-100_000, 0));
+        lp.on_message(&mut kernel, &state(900_000, 20_000));
+        assert!(requests(&mut kernel, &received).is_empty());
+    }
+}
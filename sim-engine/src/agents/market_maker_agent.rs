@@ -8,11 +8,13 @@
 //! This is NOT a traditional MM with bid/ask spread, but a "balancer" agent
 //! that ensures healthy OI distribution for the simulation.
 
+use crate::agent_println;
 use crate::agents::Agent;
 use crate::messages::{
-    AgentId, MarketOrderPayload, MarketStatePayload, Message, MessagePayload, MessageType, OracleTickPayload,
-    OrderExecutedPayload, OrderExecutionType, PositionLiquidatedPayload, Side, SimulatorApi,
+    AgentId, MarketOrderPayload, MarketStatePayload, Message, MessagePayload, MessageType,
+    OracleTickPayload, OrderExecutedPayload, OrderExecutionType, PositionLiquidatedPayload, Side, SimulatorApi,
 };
+use crate::qty::Qty;
 
 /// Configuration for Market Maker
 #[derive(Debug, Clone)]
@@ -174,7 +176,7 @@ impl MarketMakerAgent {
         // Check if we have enough balance
         let available = self.balance - self.collateral_locked;
         if collateral_needed > available {
-            println!(
+            agent_println!(
                 "[MM {}] Insufficient balance for {} order: need ${:.2}, have ${:.2}",
                 self.name,
                 if side == Side::Buy { "LONG" } else { "SHORT" },
@@ -187,11 +189,12 @@ impl MarketMakerAgent {
         let payload = MessagePayload::MarketOrder(MarketOrderPayload {
             symbol: self.symbol.clone(),
             side,
-            qty: self.order_size_tokens,
+            qty: Qty::from_tokens(self.order_size_tokens),
             leverage: self.leverage,
+            decision_price: sim.market_data().mid(&self.symbol),
         });
 
-        println!(
+        agent_println!(
             "[MM {}] {} {} {}x qty={:.3} @ ${:.2} (imbalance={:.1}%)",
             self.name,
             reason,
@@ -233,13 +236,13 @@ impl MarketMakerAgent {
             }
             OrderExecutionType::Liquidation => {
                 // Oops - we got liquidated (shouldn't happen with 2x leverage normally)
-                println!("[MM {}] WARNING: Got liquidated!", self.name);
+                agent_println!("[MM {}] WARNING: Got liquidated!", self.name);
             }
         }
     }
 
     fn handle_liquidation(&mut self, payload: &PositionLiquidatedPayload) {
-        println!(
+        agent_println!(
             "[MM {}] LIQUIDATED {} - lost ${:.2}",
             self.name,
             if payload.side == Side::Buy { "LONG" } else { "SHORT" },
@@ -299,8 +302,21 @@ impl Agent for MarketMakerAgent {
         &self.name
     }
 
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        match (key, value.as_f64()) {
+            ("target_oi_per_side", Some(v)) if v >= 0.0 => self.target_oi_per_side = v as i128,
+            ("max_imbalance_pct", Some(v)) if v >= 0.0 => self.max_imbalance_pct = v,
+            ("order_size_tokens", Some(v)) if v > 0.0 => self.order_size_tokens = v,
+            ("leverage", Some(v)) if v >= 1.0 => self.leverage = v as u32,
+            ("wake_interval_ms", Some(v)) if v >= 1.0 => self.wake_interval_ns = v as u64 * 1_000_000,
+            _ => return false,
+        }
+        agent_println!("[MM {}] param {} = {}", self.name, key, value);
+        true
+    }
+
     fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
-        println!(
+        agent_println!(
             "[MM {}] Starting: target_oi=${:.0}k/side, max_imbalance={:.0}%, order_size={:.1} tokens{}",
             self.name,
             self.target_oi_per_side as f64 / 1_000_000_000.0,
@@ -323,11 +339,9 @@ impl Agent for MarketMakerAgent {
 
     fn on_message(&mut self, _sim: &mut dyn SimulatorApi, msg: &Message) {
         match msg.msg_type {
-            MessageType::OracleTick => {
-                if let MessagePayload::OracleTick(OracleTickPayload { symbol, price, .. }) = &msg.payload {
-                    if *symbol == self.symbol {
-                        self.current_price = Some((price.min + price.max) / 2);
-                    }
+            MessageType::OracleTick | MessageType::OracleBatchTick => {
+                if let Some(OracleTickPayload { price, .. }) = msg.payload.oracle_tick(&self.symbol) {
+                    self.current_price = Some((price.min + price.max) / 2);
                 }
             }
             MessageType::MarketState => {
@@ -366,7 +380,7 @@ impl Agent for MarketMakerAgent {
 
     fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
         let imbalance = self.calculate_imbalance_pct();
-        println!(
+        agent_println!(
             "[MM {}] STOP: orders={} rebalances={} final_imbalance={:.1}% bal=${:.0}k",
             self.name,
             self.orders_placed,
@@ -1,13 +1,33 @@
 use crate::messages::{AgentId, Message, SimulatorApi};
 
+pub mod arbitrage_agent;
+pub mod balance_monitor_agent;
+pub mod bridge_strategy;
+pub mod copy_trader_agent;
+pub mod director_agent;
 pub mod exchange_agent;
+pub mod funding_arb_agent;
+pub mod hedger_agent;
 pub mod human_agent;
 pub mod keeper_agent;
 pub mod limit_trader_agent;
 pub mod liquidation_agent;
+pub mod lp_agent;
 pub mod market_maker_agent;
+pub mod noise_trader_agent;
 pub mod oracle_agent;
+pub mod portfolio_agent;
+pub mod quoting_mm_agent;
+pub mod remote_strategy;
+pub mod risk_monitor_agent;
+pub mod scripted_strategy;
+pub mod sma_cross_strategy;
 pub mod smart_trader_agent;
+pub mod strategy_agent;
+pub mod swap_exchange_agent;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod whale_agent;
 
 pub use keeper_agent::{KeeperAgent, KeeperConfig};
 pub use limit_trader_agent::{LimitStrategy, LimitTraderAgent, LimitTraderConfig, OrderMode};
@@ -20,4 +40,31 @@ pub trait Agent {
     fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {}
     fn on_wakeup(&mut self, _sim: &mut dyn SimulatorApi, _now_ns: u64) {}
     fn on_message(&mut self, _sim: &mut dyn SimulatorApi, _msg: &Message) {}
+    /// Paused from the admin API: the kernel holds back the agent's wakeups until it is
+    /// resumed; messages are still delivered.
+    fn on_pause(&mut self, _sim: &mut dyn SimulatorApi) {}
+    /// Resumed from the admin API; the held wakeups are delivered right after.
+    fn on_resume(&mut self, _sim: &mut dyn SimulatorApi) {}
+    /// Change one runtime parameter (timeline `set_param`, admin config updates).
+    /// False if the agent has no such parameter or the value is invalid.
+    fn set_param(&mut self, _key: &str, _value: &serde_json::Value) -> bool {
+        false
+    }
+    /// New runtime parameters from the admin API (a JSON object), applied one by one with
+    /// `set_param`. Fails listing the keys that were not applied.
+    fn on_config_update(&mut self, _sim: &mut dyn SimulatorApi, config: serde_json::Value) -> Result<(), String> {
+        let serde_json::Value::Object(params) = config else {
+            return Err("config must be a JSON object".to_string());
+        };
+        let rejected: Vec<String> = params
+            .into_iter()
+            .filter(|(key, value)| !self.set_param(key, value))
+            .map(|(key, _)| key)
+            .collect();
+        if rejected.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("unsupported params: {}", rejected.join(", ")))
+        }
+    }
 }
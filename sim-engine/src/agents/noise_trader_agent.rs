@@ -0,0 +1,348 @@
+//! NoiseTraderAgent - background retail order flow.
+//!
+//! Market orders arrive as a Poisson process (exponential gaps at
+//! `orders_per_min`), with random sides, lognormal sizes and leverage drawn
+//! from a weighted set of choices. Each arrival either adds to the book on a
+//! random side or, with `close_prob`, closes one of the open sides. All draws
+//! come from a seeded generator, so the same seed gives the same flow.
+//! An activity schedule scales the arrival rate by time of day and weekday;
+//! each gap is drawn at the rate of the moment it starts.
+
+use crate::activity_schedule::ActivitySchedule;
+use crate::agent_println;
+use crate::agents::Agent;
+use crate::messages::{
+    AgentId, CloseOrderPayload, MarketOrderPayload, Message, MessagePayload, MessageType, Side,
+    SimulatorApi,
+};
+use crate::qty::Qty;
+use crate::scenarios::population::Rng;
+
+#[derive(Debug, Clone)]
+pub struct NoiseTraderConfig {
+    pub name: String,
+    pub exchange_id: AgentId,
+    pub symbol: String,
+    /// Mean arrival rate of orders
+    pub orders_per_min: f64,
+    /// Probability of a buy when opening
+    pub buy_prob: f64,
+    /// Probability that an arrival closes an open side instead of opening
+    pub close_prob: f64,
+    /// Order size in tokens: exp(size_mu + size_sigma * N(0,1)), capped at qty_max
+    pub size_mu: f64,
+    pub size_sigma: f64,
+    pub qty_max: f64,
+    /// Leverage choices and their weights
+    pub leverage_choices: Vec<u32>,
+    pub leverage_weights: Vec<f64>,
+    pub seed: u64,
+    /// Volume multiplier of `orders_per_min` by time of day
+    pub schedule: ActivitySchedule,
+}
+
+pub struct NoiseTraderAgent {
+    id: AgentId,
+    name: String,
+    exchange_id: AgentId,
+    symbol: String,
+    address: Option<String>,
+    orders_per_min: f64,
+    buy_prob: f64,
+    close_prob: f64,
+    size_mu: f64,
+    size_sigma: f64,
+    qty_max: f64,
+    leverage_choices: Vec<u32>,
+    leverage_weights: Vec<f64>,
+    schedule: ActivitySchedule,
+    rng: Rng,
+
+    has_long: bool,
+    has_short: bool,
+    orders_sent: u32,
+    closes_sent: u32,
+    liquidations: u32,
+    volume_tokens: f64,
+}
+
+impl NoiseTraderAgent {
+    pub fn new(id: AgentId, config: NoiseTraderConfig) -> Self {
+        Self {
+            id,
+            name: config.name,
+            exchange_id: config.exchange_id,
+            symbol: config.symbol,
+            address: None,
+            orders_per_min: config.orders_per_min,
+            buy_prob: config.buy_prob,
+            close_prob: config.close_prob,
+            size_mu: config.size_mu,
+            size_sigma: config.size_sigma,
+            qty_max: config.qty_max,
+            leverage_choices: config.leverage_choices,
+            leverage_weights: config.leverage_weights,
+            schedule: config.schedule,
+            rng: Rng::new(config.seed ^ (id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)),
+            has_long: false,
+            has_short: false,
+            orders_sent: 0,
+            closes_sent: 0,
+            liquidations: 0,
+            volume_tokens: 0.0,
+        }
+    }
+
+    pub fn set_address(&mut self, address: String) {
+        self.address = Some(address);
+    }
+
+    /// Exponential gap to the next arrival in ns, at the scheduled rate of `now_ns`
+    fn next_gap_ns(&mut self, now_ns: u64) -> u64 {
+        let rate = self.orders_per_min * self.schedule.at(now_ns).volume;
+        let u = self.rng.next_f64().max(f64::MIN_POSITIVE);
+        let gap_sec = -u.ln() * 60.0 / rate;
+        (gap_sec * 1_000_000_000.0).max(1_000_000.0) as u64
+    }
+
+    fn sample_qty(&mut self) -> f64 {
+        let qty = (self.size_mu + self.size_sigma * self.rng.normal()).exp();
+        qty.min(self.qty_max)
+    }
+
+    fn sample_leverage(&mut self) -> u32 {
+        let total: f64 = self.leverage_weights.iter().sum();
+        let mut pick = self.rng.next_f64() * total;
+        for (leverage, w) in self.leverage_choices.iter().zip(&self.leverage_weights) {
+            if pick < *w {
+                return *leverage;
+            }
+            pick -= w;
+        }
+        self.leverage_choices.last().copied().unwrap_or(1)
+    }
+
+    fn arrive(&mut self, sim: &mut dyn SimulatorApi) {
+        let open_sides: Vec<Side> = [(self.has_long, Side::Buy), (self.has_short, Side::Sell)]
+            .into_iter()
+            .filter_map(|(open, side)| open.then_some(side))
+            .collect();
+
+        if !open_sides.is_empty() && self.rng.next_f64() < self.close_prob {
+            let side = open_sides[(self.rng.next_u64() % open_sides.len() as u64) as usize];
+            sim.send(
+                self.id,
+                self.exchange_id,
+                MessageType::CloseOrder,
+                MessagePayload::CloseOrder(CloseOrderPayload {
+                    symbol: self.symbol.clone(),
+                    side,
+                    decision_price: sim.market_data().mid(&self.symbol),
+                }),
+            );
+            match side {
+                Side::Buy => self.has_long = false,
+                Side::Sell => self.has_short = false,
+            }
+            self.closes_sent += 1;
+            return;
+        }
+
+        let side = if self.rng.next_f64() < self.buy_prob {
+            Side::Buy
+        } else {
+            Side::Sell
+        };
+        let qty = self.sample_qty();
+        let leverage = self.sample_leverage();
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::MarketOrder,
+            MessagePayload::MarketOrder(MarketOrderPayload {
+                symbol: self.symbol.clone(),
+                side,
+                qty: Qty::from_tokens(qty),
+                leverage,
+                decision_price: sim.market_data().mid(&self.symbol),
+            }),
+        );
+        match side {
+            Side::Buy => self.has_long = true,
+            Side::Sell => self.has_short = true,
+        }
+        self.orders_sent += 1;
+        self.volume_tokens += qty;
+    }
+}
+
+impl Agent for NoiseTraderAgent {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        match (key, value.as_f64()) {
+            ("orders_per_min", Some(v)) if v > 0.0 => self.orders_per_min = v,
+            ("buy_prob", Some(v)) if (0.0..=1.0).contains(&v) => self.buy_prob = v,
+            ("close_prob", Some(v)) if (0.0..=1.0).contains(&v) => self.close_prob = v,
+            ("size_mu", Some(v)) => self.size_mu = v,
+            ("size_sigma", Some(v)) if v >= 0.0 => self.size_sigma = v,
+            ("qty_max", Some(v)) if v > 0.0 => self.qty_max = v,
+            _ => return false,
+        }
+        agent_println!("[Noise {}] param {} = {}", self.name, key, value);
+        true
+    }
+
+    fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[Noise {}] START {} {:.1} orders/min{}",
+            self.name,
+            self.symbol,
+            self.orders_per_min,
+            self.address
+                .as_deref()
+                .map(|addr| format!(" addr={}", addr))
+                .unwrap_or_default()
+        );
+        let gap = self.next_gap_ns(sim.now_ns());
+        sim.wakeup(self.id, sim.now_ns() + gap);
+    }
+
+    fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
+        self.arrive(sim);
+        let gap = self.next_gap_ns(now_ns);
+        sim.wakeup(self.id, now_ns + gap);
+    }
+
+    fn on_message(&mut self, _sim: &mut dyn SimulatorApi, msg: &Message) {
+        match &msg.payload {
+            MessagePayload::PositionLiquidated(p) if p.symbol == self.symbol => {
+                match p.side {
+                    Side::Buy => self.has_long = false,
+                    Side::Sell => self.has_short = false,
+                }
+                self.liquidations += 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[Noise {}] STOP: orders={} closes={} liq={} volume={:.3}",
+            self.name,
+            self.orders_sent,
+            self.closes_sent,
+            self.liquidations,
+            self.volume_tokens
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activity_schedule::ActivitySession;
+    use crate::agents::test_support::{delivered, kernel_with_recorder};
+
+    const EXCHANGE: AgentId = 1;
+
+    fn config(seed: u64) -> NoiseTraderConfig {
+        NoiseTraderConfig {
+            name: "noise".to_string(),
+            exchange_id: EXCHANGE,
+            symbol: "ETH-USD".to_string(),
+            orders_per_min: 6.0,
+            buy_prob: 0.5,
+            close_prob: 0.3,
+            size_mu: 0.0,
+            size_sigma: 0.5,
+            qty_max: 3.0,
+            leverage_choices: vec![2, 5, 10],
+            leverage_weights: vec![0.5, 0.3, 0.2],
+            seed,
+            schedule: ActivitySchedule::default(),
+        }
+    }
+
+    /// Orders of `n` arrivals: (type, side, qty in tokens, leverage)
+    fn flow(config: NoiseTraderConfig, n: usize) -> Vec<(MessageType, Side, f64, u32)> {
+        let (mut kernel, received) = kernel_with_recorder(EXCHANGE);
+        let mut noise = NoiseTraderAgent::new(20, config);
+        for _ in 0..n {
+            noise.arrive(&mut kernel);
+        }
+        delivered(&mut kernel, &received)
+            .into_iter()
+            .filter_map(|msg| match msg.payload {
+                MessagePayload::MarketOrder(o) => Some((msg.msg_type, o.side, o.qty.tokens(), o.leverage)),
+                MessagePayload::CloseOrder(c) => Some((msg.msg_type, c.side, 0.0, 0)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_same_seed_gives_the_same_flow() {
+        let first = flow(config(7), 200);
+        assert_eq!(first.len(), 200);
+        assert_eq!(first, flow(config(7), 200));
+        assert_ne!(first, flow(config(8), 200));
+        // With `close_prob` 0.3, some arrivals close an open side instead
+        let closes = first.iter().filter(|o| o.0 == MessageType::CloseOrder).count();
+        assert!(closes > 0 && closes < 100, "{closes} closes");
+    }
+
+    #[test]
+    fn test_gaps_are_exponential_at_the_scheduled_rate() {
+        let mut noise = NoiseTraderAgent::new(20, config(7));
+        let gaps: Vec<u64> = (0..20_000).map(|_| noise.next_gap_ns(0)).collect();
+        let mean_sec = gaps.iter().sum::<u64>() as f64 / gaps.len() as f64 / 1e9;
+        assert!((mean_sec - 10.0).abs() < 0.3, "mean gap {mean_sec}s");
+        // Memoryless: about e^-1 of the gaps exceed the mean
+        let above = gaps.iter().filter(|g| **g > 10_000_000_000).count() as f64 / gaps.len() as f64;
+        assert!((above - (-1.0f64).exp()).abs() < 0.02, "{above} above the mean");
+
+        // Twice the volume all day: the same draws give half the gaps
+        let mut busy = config(7);
+        busy.schedule.sessions.push(ActivitySession {
+            start_hour: 0.0,
+            end_hour: 24.0,
+            volatility: 1.0,
+            volume: 2.0,
+        });
+        let mut busy = NoiseTraderAgent::new(20, busy);
+        for gap in gaps.iter().take(100) {
+            let busy_gap = busy.next_gap_ns(0) as i64;
+            assert!((*gap as i64 - 2 * busy_gap).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn test_sizes_are_lognormal_and_capped() {
+        let mut noise = NoiseTraderAgent::new(20, config(7));
+        let mut sizes: Vec<f64> = (0..20_000).map(|_| noise.sample_qty()).collect();
+        sizes.sort_by(f64::total_cmp);
+        // Median exp(size_mu) = 1 token; ln(3) is 2.2 sigma, so ~1.4% of the draws hit the cap
+        assert!((sizes[sizes.len() / 2] - 1.0).abs() < 0.03);
+        assert_eq!(*sizes.last().unwrap(), 3.0);
+        let capped = sizes.iter().filter(|s| **s == 3.0).count() as f64 / sizes.len() as f64;
+        assert!(capped > 0.0 && capped < 0.03, "{capped} capped");
+    }
+
+    #[test]
+    fn test_leverage_follows_its_weights() {
+        let mut noise = NoiseTraderAgent::new(20, config(7));
+        let draws: Vec<u32> = (0..20_000).map(|_| noise.sample_leverage()).collect();
+        for (leverage, weight) in [(2, 0.5), (5, 0.3), (10, 0.2)] {
+            let share = draws.iter().filter(|l| **l == leverage).count() as f64 / draws.len() as f64;
+            assert!((share - weight).abs() < 0.02, "{leverage}x drawn {share}");
+        }
+    }
+}
@@ -1,6 +1,44 @@
+use crate::agent_println;
 use crate::agents::Agent;
-use crate::api::PriceProvider;
-use crate::messages::{AgentId, Message, MessagePayload, MessageType, OracleTickPayload, Price, SimulatorApi};
+use crate::api::{PriceProvider, SignedPriceData};
+use crate::events::SimEvent;
+use crate::spread_model::{SpreadModel, SpreadTracker};
+use std::collections::HashMap;
+use std::error::Error;
+
+use crate::messages::{
+    AgentId, DirectorCommand, Message, MessagePayload, MessageType, OracleBatchTickPayload, OracleTickPayload, Price,
+    SimulatorApi,
+};
+
+type FetchResult = Result<SignedPriceData, Box<dyn Error>>;
+
+/// When to leave a failing provider and when to try the primary again.
+#[derive(Debug, Clone, Copy)]
+pub struct FailoverConfig {
+    /// Consecutive blocks with a failed symbol before switching to the next provider
+    pub failures_to_switch: u32,
+    /// Blocks on a fallback between two probes of the primary
+    pub recovery_interval_blocks: u64,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            failures_to_switch: 3,
+            recovery_interval_blocks: 10,
+        }
+    }
+}
+
+/// Push-style updates: fetch every block, publish a symbol only when it moved enough or got old.
+#[derive(Debug, Clone, Copy)]
+pub struct DeviationTrigger {
+    /// Move of the mid since the last published tick that forces an update
+    pub deviation_bps: f64,
+    /// Heartbeat: a symbol is republished at least this often
+    pub max_age_ns: u64,
+}
 
 pub struct OracleAgent {
     id: AgentId,
@@ -9,7 +47,31 @@ pub struct OracleAgent {
     exchange_id: AgentId,
     wake_interval_ns: u64,
     block_number: u64,
-    price_provider: Box<dyn PriceProvider>,
+    /// Primary provider first, then the fallbacks in order
+    providers: Vec<Box<dyn PriceProvider>>,
+    active: usize,
+    failover: FailoverConfig,
+    consecutive_failures: u32,
+    blocks_on_fallback: u64,
+    failovers: u32,
+    /// Scripted price shocks: symbol -> multiplier applied to provider prices
+    price_factors: HashMap<String, f64>,
+    /// Fixed starting prices: symbol -> price of the first tick (micro-USD)
+    start_prices: HashMap<String, u64>,
+    /// Multiplier moving the provider's prices onto `start_prices`, set by the first fetch
+    rebase_factors: HashMap<String, f64>,
+    /// Manual prices from the admin API: symbol -> (price, until_ns), replacing the provider's
+    price_overrides: HashMap<String, (u64, u64)>,
+    /// Synthetic min/max band instead of the provider's confidence (None = pass it through)
+    spread_model: Option<SpreadModel>,
+    spreads: HashMap<String, SpreadTracker>,
+    /// Publish on deviation or heartbeat only (None = every block)
+    deviation: Option<DeviationTrigger>,
+    /// Mid and time of the last published tick per symbol
+    published: HashMap<String, (u64, u64)>,
+    suppressed_ticks: u64,
+    /// Recipients of the ticks, the exchange first (None = broadcast to every agent)
+    subscribers: Option<Vec<AgentId>>,
 }
 
 impl OracleAgent {
@@ -28,8 +90,167 @@ impl OracleAgent {
             exchange_id,
             wake_interval_ns,
             block_number: 0,
-            price_provider,
+            providers: vec![price_provider],
+            active: 0,
+            failover: FailoverConfig::default(),
+            consecutive_failures: 0,
+            blocks_on_fallback: 0,
+            failovers: 0,
+            price_factors: HashMap::new(),
+            start_prices: HashMap::new(),
+            rebase_factors: HashMap::new(),
+            price_overrides: HashMap::new(),
+            spread_model: None,
+            spreads: HashMap::new(),
+            deviation: None,
+            published: HashMap::new(),
+            suppressed_ticks: 0,
+            subscribers: None,
+        }
+    }
+
+    /// Derive the min/max band from simulated volatility and utilization.
+    pub fn set_spread_model(&mut self, model: SpreadModel) {
+        self.spread_model = Some(model);
+        self.spreads.clear();
+    }
+
+    /// Publish a symbol only when its mid moved `deviation_bps` or its last tick is `max_age_ns` old.
+    pub fn set_deviation_trigger(&mut self, trigger: DeviationTrigger) {
+        self.deviation = Some(trigger);
+    }
+
+    /// Whether a fetched mid goes out in this block; records it as published if so.
+    fn should_publish(&mut self, symbol: &str, mid: u64, now_ns: u64) -> bool {
+        let Some(trigger) = self.deviation else {
+            return true;
+        };
+        if let Some(&(last_mid, last_ns)) = self.published.get(symbol) {
+            let moved_bps = (mid as f64 - last_mid as f64).abs() * 10_000.0 / last_mid.max(1) as f64;
+            if moved_bps < trigger.deviation_bps && now_ns.saturating_sub(last_ns) < trigger.max_age_ns {
+                self.suppressed_ticks += 1;
+                return false;
+            }
         }
+        self.published.insert(symbol.to_string(), (mid, now_ns));
+        true
+    }
+
+    /// Start `symbol` at a fixed price: the provider's moves carry on from there, scaled by
+    /// the ratio of the start price to its first fetched price.
+    pub fn set_start_prices(&mut self, prices: HashMap<String, u64>) {
+        self.start_prices = prices;
+        self.rebase_factors.clear();
+    }
+
+    /// Multiplier of `symbol`'s provider prices, fixed on its first successful fetch.
+    fn rebase_factor(&mut self, symbol: &str, provider_price: u64) -> f64 {
+        let Some(&start) = self.start_prices.get(symbol) else {
+            return 1.0;
+        };
+        *self
+            .rebase_factors
+            .entry(symbol.to_string())
+            .or_insert_with(|| start as f64 / provider_price.max(1) as f64)
+    }
+
+    /// Manual price of `symbol` at `now_ns`; expired overrides are dropped.
+    fn price_override(&mut self, symbol: &str, now_ns: u64) -> Option<u64> {
+        let &(price, until_ns) = self.price_overrides.get(symbol)?;
+        if now_ns >= until_ns {
+            agent_println!("[Oracle {}] price override of {} expired", self.name, symbol);
+            self.price_overrides.remove(symbol);
+            return None;
+        }
+        Some(price)
+    }
+
+    /// Send ticks to the exchange and `subscribers` only instead of broadcasting them.
+    pub fn set_subscribers(&mut self, subscribers: &[AgentId]) {
+        let mut recipients = vec![self.exchange_id];
+        for id in subscribers {
+            if !recipients.contains(id) && *id != self.id {
+                recipients.push(*id);
+            }
+        }
+        self.subscribers = Some(recipients);
+    }
+
+    fn publish(&self, sim: &mut dyn SimulatorApi, kind: MessageType, payload: MessagePayload) {
+        match &self.subscribers {
+            Some(recipients) => sim.multicast(self.id, recipients, kind, payload),
+            None => sim.broadcast(self.id, kind, payload),
+        }
+    }
+
+    /// Providers to switch to, in order, when the current one keeps failing.
+    pub fn set_fallback_providers(&mut self, fallbacks: Vec<Box<dyn PriceProvider>>, config: FailoverConfig) {
+        self.providers.truncate(1);
+        self.providers.extend(fallbacks);
+        self.failover = config;
+    }
+
+    /// Fetch from the active provider. On a fallback the primary is probed every
+    /// `recovery_interval_blocks` and taken back once it prices every symbol; after
+    /// `failures_to_switch` failed blocks the next provider takes over, already for this block.
+    fn fetch_prices(&mut self, sim: &mut dyn SimulatorApi, symbols: &[&str]) -> Vec<FetchResult> {
+        if self.active != 0 {
+            self.blocks_on_fallback += 1;
+            if self
+                .blocks_on_fallback
+                .is_multiple_of(self.failover.recovery_interval_blocks)
+            {
+                let results = self.providers[0].fetch_batch(symbols);
+                if results.iter().all(Result::is_ok) {
+                    self.switch_provider(sim, 0, "recovered");
+                    return results;
+                }
+            }
+        }
+
+        let results = self.providers[self.active].fetch_batch(symbols);
+        if results.iter().all(Result::is_ok) || self.providers.len() < 2 {
+            self.consecutive_failures = 0;
+            return results;
+        }
+        self.consecutive_failures += 1;
+        if self.consecutive_failures < self.failover.failures_to_switch {
+            return results;
+        }
+
+        let next = (self.active + 1) % self.providers.len();
+        self.switch_provider(sim, next, "failover");
+        // Symbols the new provider can't price keep the old provider's answer (or error)
+        self.providers[next]
+            .fetch_batch(symbols)
+            .into_iter()
+            .zip(results)
+            .map(|(new, old)| if new.is_ok() { new } else { old })
+            .collect()
+    }
+
+    fn switch_provider(&mut self, sim: &mut dyn SimulatorApi, to: usize, reason: &str) {
+        let from = self.providers[self.active].provider_name().to_string();
+        let to_name = self.providers[to].provider_name().to_string();
+        eprintln!(
+            "[Oracle {}] {}: {} -> {} after {} failed blocks",
+            self.name, reason, from, to_name, self.consecutive_failures
+        );
+        let ts = sim.now_ns();
+        sim.emit_event(SimEvent::OracleFailover {
+            ts,
+            oracle: self.id,
+            from,
+            to: to_name,
+            reason: reason.to_string(),
+            failures: self.consecutive_failures,
+        });
+        if to != 0 {
+            self.failovers += 1;
+        }
+        self.active = to;
+        self.consecutive_failures = 0;
+        self.blocks_on_fallback = 0;
     }
 }
 
@@ -42,16 +263,28 @@ impl Agent for OracleAgent {
         &self.name
     }
 
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        match (key, value.as_u64()) {
+            ("wake_interval_ms", Some(ms)) if ms > 0 => self.wake_interval_ns = ms * 1_000_000,
+            _ => return false,
+        }
+        true
+    }
+
     fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
-        println!(
+        agent_println!(
             "[Oracle {}] starting with provider '{}' for {} symbols -> exchange={}",
             self.name,
-            self.price_provider.provider_name(),
+            self.providers[0].provider_name(),
             self.symbols.len(),
             self.exchange_id
         );
-        println!("[Oracle {}] symbols: {}", self.name, self.symbols.join(", "));
-        println!(
+        if self.providers.len() > 1 {
+            let fallbacks: Vec<&str> = self.providers[1..].iter().map(|p| p.provider_name()).collect();
+            agent_println!("[Oracle {}] fallback providers: {}", self.name, fallbacks.join(", "));
+        }
+        agent_println!("[Oracle {}] symbols: {}", self.name, self.symbols.join(", "));
+        agent_println!(
             "[Oracle {}] wake interval: {}s",
             self.name,
             self.wake_interval_ns / 1_000_000_000
@@ -69,32 +302,77 @@ impl Agent for OracleAgent {
         let verbose = self.block_number <= 3 || self.block_number.is_multiple_of(10);
 
         if verbose {
-            println!("[Oracle {}] BLOCK #{} at t={} ns", self.name, self.block_number, now_ns);
+            agent_println!("[Oracle {}] BLOCK #{} at t={} ns", self.name, self.block_number, now_ns);
         }
 
-        let symbol_refs: Vec<&str> = self.symbols.iter().map(|s| s.as_str()).collect();
-        let results = self.price_provider.fetch_batch(&symbol_refs);
+        let symbols = self.symbols.clone();
+        let symbol_refs: Vec<&str> = symbols.iter().map(|s| s.as_str()).collect();
+        let results = self.fetch_prices(sim, &symbol_refs);
 
-        for (symbol, result) in self.symbols.iter().zip(results.into_iter()) {
+        let mut ticks = Vec::with_capacity(self.symbols.len());
+        for (symbol, result) in symbols.iter().zip(results.into_iter()) {
+            // An override replaces the provider's price (and stands in when it fails); shocks don't apply
+            let (result, factor) = match self.price_override(symbol, now_ns) {
+                Some(price) => {
+                    let data = match result {
+                        Ok(data) => SignedPriceData {
+                            price_usd_micro: price,
+                            confidence: None,
+                            ..data
+                        },
+                        Err(_) => SignedPriceData {
+                            symbol: symbol.clone(),
+                            price_usd_micro: price,
+                            confidence: None,
+                            ema_price: None,
+                            publish_time: now_ns / 1_000_000_000,
+                            signature: Vec::new(),
+                            provider_name: "Override".to_string(),
+                        },
+                    };
+                    (Ok(data), 1.0)
+                }
+                None => {
+                    let shock = self.price_factors.get(symbol).copied().unwrap_or(1.0);
+                    let rebase = match &result {
+                        Ok(data) => self.rebase_factor(symbol, data.price_usd_micro),
+                        Err(_) => 1.0,
+                    };
+                    (result, shock * rebase)
+                }
+            };
             match result {
                 Ok(signed_data) => {
-                    let confidence = signed_data.confidence.unwrap_or(0);
-                    let min = signed_data.price_usd_micro.saturating_sub(confidence);
-                    let max = signed_data.price_usd_micro.saturating_add(confidence);
+                    let price_micro = (signed_data.price_usd_micro as f64 * factor) as u64;
+                    let (min, max) = match self.spread_model {
+                        Some(model) => self
+                            .spreads
+                            .entry(symbol.clone())
+                            .or_insert_with(|| SpreadTracker::new(model))
+                            .band(price_micro),
+                        None => {
+                            let confidence = (signed_data.confidence.unwrap_or(0) as f64 * factor) as u64;
+                            (
+                                price_micro.saturating_sub(confidence),
+                                price_micro.saturating_add(confidence),
+                            )
+                        }
+                    };
 
+                    if !self.should_publish(symbol, (min + max) / 2, now_ns) {
+                        continue;
+                    }
                     if verbose {
-                        let price_usd = signed_data.price_usd_micro as f64 / 1_000_000.0;
-                        println!("[Oracle] {} = ${:.2}", symbol, price_usd);
+                        let price_usd = price_micro as f64 / 1_000_000.0;
+                        agent_println!("[Oracle] {} = ${:.2}", symbol, price_usd);
                     }
 
-                    let payload = MessagePayload::OracleTick(OracleTickPayload {
+                    ticks.push(OracleTickPayload {
                         symbol: symbol.clone(),
                         price: Price { min, max },
                         publish_time: signed_data.publish_time,
-                        signature: signed_data.signature,
+                        signature: signed_data.signature.into(),
                     });
-
-                    sim.broadcast(self.id, MessageType::OracleTick, payload);
                 }
                 Err(e) => {
                     eprintln!("[Oracle {}] error fetching {}: {}", self.name, symbol, e);
@@ -102,20 +380,91 @@ impl Agent for OracleAgent {
             }
         }
 
+        // Several symbols go out as one batch per block
+        if ticks.len() > 1 {
+            let payload = MessagePayload::OracleBatchTick(OracleBatchTickPayload { ticks });
+            self.publish(sim, MessageType::OracleBatchTick, payload);
+        } else if let Some(tick) = ticks.pop() {
+            self.publish(sim, MessageType::OracleTick, MessagePayload::OracleTick(tick));
+        }
+
         let next = now_ns + self.wake_interval_ns;
         sim.wakeup(self.id, next);
     }
 
-    fn on_message(&mut self, _sim: &mut dyn SimulatorApi, msg: &Message) {
-        if msg.msg_type != MessageType::MarketState {
-            println!(
-                "[Oracle {}] received msg {:?} from {}",
-                self.name, msg.msg_type, msg.from
-            );
+    fn on_message(&mut self, sim: &mut dyn SimulatorApi, msg: &Message) {
+        if let MessagePayload::Director(cmd) = &msg.payload {
+            match cmd {
+                DirectorCommand::PriceShock { symbol, factor } => {
+                    if self.symbols.contains(symbol) {
+                        let f = self.price_factors.entry(symbol.clone()).or_insert(1.0);
+                        *f *= factor;
+                        if (*f - 1.0).abs() < 1e-9 {
+                            self.price_factors.remove(symbol);
+                        }
+                    }
+                }
+                DirectorCommand::PriceOverride {
+                    symbol,
+                    price,
+                    duration_ns,
+                } => {
+                    if !self.symbols.contains(symbol) {
+                        return;
+                    }
+                    if *duration_ns == 0 {
+                        self.price_overrides.remove(symbol);
+                        agent_println!("[Oracle {}] price override of {} cleared", self.name, symbol);
+                    } else {
+                        let until_ns = sim.now_ns() + duration_ns;
+                        self.price_overrides.insert(symbol.clone(), (*price, until_ns));
+                        agent_println!(
+                            "[Oracle {}] {} overridden at ${:.2} for {}s",
+                            self.name,
+                            symbol,
+                            *price as f64 / 1_000_000.0,
+                            duration_ns / 1_000_000_000
+                        );
+                    }
+                }
+                // Applied by the kernel through `Agent::set_param`
+                DirectorCommand::SetParam { .. } => {}
+            }
+            return;
+        }
+        if let MessagePayload::MarketState(state) = &msg.payload {
+            if let (Some(model), true) = (self.spread_model, self.symbols.contains(&state.symbol)) {
+                self.spreads
+                    .entry(state.symbol.clone())
+                    .or_insert_with(|| SpreadTracker::new(model))
+                    .on_market_state(state);
+            }
+            return;
         }
+        agent_println!(
+            "[Oracle {}] received msg {:?} from {}",
+            self.name,
+            msg.msg_type,
+            msg.from
+        );
     }
 
     fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
-        println!("[Oracle {}] stopping after {} blocks", self.name, self.block_number);
+        agent_println!("[Oracle {}] stopping after {} blocks", self.name, self.block_number);
+        if self.deviation.is_some() {
+            agent_println!(
+                "[Oracle {}] {} ticks held back below the deviation threshold",
+                self.name,
+                self.suppressed_ticks
+            );
+        }
+        if self.failovers > 0 {
+            agent_println!(
+                "[Oracle {}] {} failovers, ending on '{}'",
+                self.name,
+                self.failovers,
+                self.providers[self.active].provider_name()
+            );
+        }
     }
 }
@@ -0,0 +1,663 @@
+//! PortfolioAgent - one account trading several symbols.
+//!
+//! Every leg is a regular single-symbol trader (SmartTrader / LimitTrader) that
+//! shares the portfolio's AgentId. The portfolio:
+//! - multiplexes wakeups, so each leg only wakes on its own schedule
+//! - routes messages by symbol (ticks, fills, liquidations) to the matching legs
+//! - enforces account-level exposure limits on orders before they reach the exchange
+//!
+//! Exposure is notional in micro-USD: filled positions (from OrderExecuted) plus
+//! market orders still in flight. Replies without a symbol are routed by order id:
+//! the exchange acknowledges conditional orders (OrderPending) in submission order,
+//! which links each id to the leg that submitted it; later replies naming that id
+//! (OrderTriggered, OrderCancelled, ...) go to that leg. Replies without a known
+//! order id go to the leg that last sent an order.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::agent_println;
+use crate::agents::Agent;
+use crate::events::SimEvent;
+use crate::market_data::MarketDataService;
+use crate::messages::{
+    AgentId, Message, MessagePayload, MessageType, OrderExecutionType, OrderId, OrderType, Side,
+    SimulatorApi,
+};
+
+/// Account-level limits in micro-USD (None = unlimited)
+#[derive(Debug, Clone, Default)]
+pub struct ExposureLimits {
+    /// Sum of long and short notional over all symbols
+    pub max_gross_usd: Option<i128>,
+    /// |long - short| notional over all symbols
+    pub max_net_usd: Option<i128>,
+}
+
+struct Leg {
+    symbol: String,
+    agent: Box<dyn Agent>,
+    next_wakeup: Option<u64>,
+}
+
+/// Exposure bookkeeping shared by all legs.
+#[derive(Default)]
+struct Book {
+    limits: ExposureLimits,
+    filled: HashMap<(String, Side), i128>,
+    inflight: HashMap<(String, Side), VecDeque<i128>>,
+    last_prices: HashMap<String, u64>,
+    blocked: u32,
+}
+
+impl Book {
+    fn long_short(&self) -> (i128, i128) {
+        let inflight = self
+            .inflight
+            .iter()
+            .map(|((_, side), q)| (*side, q.iter().sum::<i128>()));
+        let filled = self.filled.iter().map(|((_, side), n)| (*side, *n));
+        filled
+            .chain(inflight)
+            .fold((0, 0), |(long, short), (side, n)| match side {
+                Side::Buy => (long + n, short),
+                Side::Sell => (long, short + n),
+            })
+    }
+
+    /// Why adding `notional` on `side` would breach a limit, if it would.
+    fn check(&self, side: Side, notional: i128) -> Option<String> {
+        let (mut long, mut short) = self.long_short();
+        match side {
+            Side::Buy => long += notional,
+            Side::Sell => short += notional,
+        }
+        if let Some(max) = self.limits.max_gross_usd {
+            if long + short > max {
+                return Some(format!(
+                    "gross ${:.0} > ${:.0}",
+                    (long + short) as f64 / 1e6,
+                    max as f64 / 1e6
+                ));
+            }
+        }
+        if let Some(max) = self.limits.max_net_usd {
+            let net = (long - short).abs();
+            // Orders that reduce the net exposure are always allowed
+            let (old_long, old_short) = self.long_short();
+            if net > max && net > (old_long - old_short).abs() {
+                return Some(format!("net ${:.0} > ${:.0}", net as f64 / 1e6, max as f64 / 1e6));
+            }
+        }
+        None
+    }
+
+    fn clear(&mut self, symbol: &str, side: Side) {
+        self.filled.remove(&(symbol.to_string(), side));
+    }
+}
+
+/// SimulatorApi seen by one leg: wakeups are recorded for the portfolio's
+/// multiplexer, orders pass the exposure check.
+struct LegSim<'a> {
+    inner: &'a mut dyn SimulatorApi,
+    portfolio_id: AgentId,
+    exchange_id: AgentId,
+    name: &'a str,
+    book: &'a mut Book,
+    next_wakeup: &'a mut Option<u64>,
+    sent_order: bool,
+    /// Conditional orders sent to the exchange, awaiting their OrderPending
+    submitted: usize,
+    rejected: Vec<String>,
+}
+
+impl LegSim<'_> {
+    /// Notional of an order that opens exposure, if the message is one.
+    fn order_notional(&self, payload: &MessagePayload) -> Option<(String, Side, i128, bool)> {
+        match payload {
+            MessagePayload::MarketOrder(p) => {
+                let price = self.book.last_prices.get(&p.symbol).copied().unwrap_or(0);
+                Some((p.symbol.clone(), p.side, p.qty.notional(price) as i128, true))
+            }
+            MessagePayload::Order(p) if p.order_type == OrderType::Increase => {
+                let price = p
+                    .trigger_price
+                    .or_else(|| self.book.last_prices.get(&p.symbol).copied())
+                    .unwrap_or(0);
+                let notional = p.qty.unwrap_or_default().notional(price) as i128;
+                Some((p.symbol.clone(), p.side, notional, false))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl SimulatorApi for LegSim<'_> {
+    fn now_ns(&self) -> u64 {
+        self.inner.now_ns()
+    }
+
+    fn send(&mut self, from: AgentId, to: AgentId, kind: MessageType, payload: MessagePayload) {
+        if to == self.exchange_id {
+            self.sent_order = true;
+            if let Some((symbol, side, notional, immediate)) = self.order_notional(&payload) {
+                if let Some(reason) = self.book.check(side, notional) {
+                    self.book.blocked += 1;
+                    agent_println!(
+                        "[{}] BLOCKED {:?} {} ${:.0}: {}",
+                        self.name,
+                        side,
+                        symbol,
+                        notional as f64 / 1_000_000.0,
+                        reason
+                    );
+                    self.rejected.push(format!("exposure_limit:{}", reason));
+                    return;
+                }
+                if immediate {
+                    self.book
+                        .inflight
+                        .entry((symbol, side))
+                        .or_default()
+                        .push_back(notional);
+                }
+            }
+            if kind == MessageType::SubmitOrder {
+                self.submitted += 1;
+            }
+        }
+        self.inner.send(from, to, kind, payload);
+    }
+
+    fn wakeup(&mut self, agent_id: AgentId, at_ns: u64) {
+        if agent_id == self.portfolio_id {
+            *self.next_wakeup = Some(self.next_wakeup.map_or(at_ns, |t| t.min(at_ns)));
+        }
+        self.inner.wakeup(agent_id, at_ns);
+    }
+
+    fn broadcast(&mut self, from: AgentId, kind: MessageType, payload: MessagePayload) {
+        self.inner.broadcast(from, kind, payload);
+    }
+
+    fn broadcast_to_group(&mut self, from: AgentId, group: &str, kind: MessageType, payload: MessagePayload) {
+        self.inner.broadcast_to_group(from, group, kind, payload);
+    }
+
+    fn emit_event(&mut self, event: SimEvent) {
+        self.inner.emit_event(event);
+    }
+
+    fn spawn_agent(&mut self, agent: Box<dyn Agent>) {
+        self.inner.spawn_agent(agent);
+    }
+
+    fn remove_agent(&mut self, agent_id: AgentId) {
+        self.inner.remove_agent(agent_id);
+    }
+
+    fn market_data(&self) -> &MarketDataService {
+        self.inner.market_data()
+    }
+}
+
+enum LegCall<'m> {
+    Start,
+    Stop,
+    Wakeup(u64),
+    Message(&'m Message),
+}
+
+pub struct PortfolioAgent {
+    id: AgentId,
+    name: String,
+    exchange_id: AgentId,
+    legs: Vec<Leg>,
+    book: Book,
+    /// Leg that sent the last order to the exchange
+    last_order_leg: usize,
+    /// Legs whose conditional orders await their OrderPending, oldest first
+    submitted: VecDeque<usize>,
+    /// Leg that placed each acknowledged conditional order
+    order_legs: HashMap<OrderId, usize>,
+}
+
+impl PortfolioAgent {
+    /// `legs`: (symbol, trader) pairs; every trader must use `id` as its own id.
+    pub fn new(
+        id: AgentId,
+        name: String,
+        exchange_id: AgentId,
+        legs: Vec<(String, Box<dyn Agent>)>,
+        limits: ExposureLimits,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            exchange_id,
+            legs: legs
+                .into_iter()
+                .map(|(symbol, agent)| Leg {
+                    symbol,
+                    agent,
+                    next_wakeup: None,
+                })
+                .collect(),
+            book: Book {
+                limits,
+                ..Default::default()
+            },
+            last_order_leg: 0,
+            submitted: VecDeque::new(),
+            order_legs: HashMap::new(),
+        }
+    }
+
+    /// Run one callback of leg `i`, then deliver the exposure rejections it caused.
+    fn call_leg(&mut self, sim: &mut dyn SimulatorApi, i: usize, call: LegCall) {
+        let leg = &mut self.legs[i];
+        let mut leg_sim = LegSim {
+            inner: sim,
+            portfolio_id: self.id,
+            exchange_id: self.exchange_id,
+            name: &self.name,
+            book: &mut self.book,
+            next_wakeup: &mut leg.next_wakeup,
+            sent_order: false,
+            submitted: 0,
+            rejected: Vec::new(),
+        };
+        match call {
+            LegCall::Start => leg.agent.on_start(&mut leg_sim),
+            LegCall::Stop => leg.agent.on_stop(&mut leg_sim),
+            LegCall::Wakeup(now_ns) => leg.agent.on_wakeup(&mut leg_sim, now_ns),
+            LegCall::Message(msg) => leg.agent.on_message(&mut leg_sim, msg),
+        }
+        let (sent_order, submitted, rejected) = (leg_sim.sent_order, leg_sim.submitted, leg_sim.rejected);
+        if sent_order {
+            self.last_order_leg = i;
+        }
+        self.submitted.extend(std::iter::repeat_n(i, submitted));
+        for reason in rejected {
+            let msg = Message::new(
+                self.id,
+                self.id,
+                MessageType::OrderRejected,
+                sim.now_ns(),
+                MessagePayload::Text(reason),
+            );
+            self.call_leg(sim, i, LegCall::Message(&msg));
+        }
+    }
+
+    fn call_legs_for(&mut self, sim: &mut dyn SimulatorApi, symbol: &str, msg: &Message) {
+        for i in 0..self.legs.len() {
+            if self.legs[i].symbol == symbol {
+                self.call_leg(sim, i, LegCall::Message(msg));
+            }
+        }
+    }
+
+    /// Leg an exchange reply without a symbol belongs to, by the order id it names.
+    fn reply_leg(&mut self, msg: &Message) -> usize {
+        let order_id = match &msg.payload {
+            MessagePayload::Text(text) => text
+                .split(',')
+                .find_map(|field| field.strip_prefix("order_id:"))
+                .and_then(|id| id.parse::<OrderId>().ok()),
+            _ => None,
+        };
+        let Some(order_id) = order_id else {
+            return self.last_order_leg;
+        };
+        let leg = match self.order_legs.get(&order_id) {
+            Some(&leg) => Some(leg),
+            None if msg.msg_type == MessageType::OrderPending => self.submitted.pop_front(),
+            None => None,
+        };
+        let Some(leg) = leg.filter(|&leg| leg < self.legs.len()) else {
+            return self.last_order_leg;
+        };
+        match msg.msg_type {
+            MessageType::OrderPending => {
+                self.order_legs.insert(order_id, leg);
+            }
+            MessageType::OrderTriggered
+            | MessageType::OrderCancelled
+            | MessageType::OrderExpired
+            | MessageType::OrderAlreadyExecuted => {
+                self.order_legs.remove(&order_id);
+            }
+            _ => {}
+        }
+        leg
+    }
+
+    fn exposure_usd(&self) -> (f64, f64) {
+        let (long, short) = self.book.long_short();
+        ((long + short) as f64 / 1_000_000.0, (long - short) as f64 / 1_000_000.0)
+    }
+}
+
+impl Agent for PortfolioAgent {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Exposure limits of the portfolio; any other key goes to every leg.
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        if !matches!(key, "max_gross_usd" | "max_net_usd") {
+            return self
+                .legs
+                .iter_mut()
+                .fold(false, |applied, leg| leg.agent.set_param(key, value) || applied);
+        }
+        let limit = if value.is_null() {
+            None
+        } else {
+            match value.as_i64() {
+                Some(v) if v >= 0 => Some(v as i128),
+                _ => return false,
+            }
+        };
+        match key {
+            "max_gross_usd" => self.book.limits.max_gross_usd = limit,
+            _ => self.book.limits.max_net_usd = limit,
+        }
+        true
+    }
+
+    fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+        let symbols: Vec<&str> = self.legs.iter().map(|l| l.symbol.as_str()).collect();
+        agent_println!(
+            "[{}] START portfolio {} (gross<={} net<={})",
+            self.name,
+            symbols.join(","),
+            self.book
+                .limits
+                .max_gross_usd
+                .map(|v| format!("${:.0}", v as f64 / 1_000_000.0))
+                .unwrap_or_else(|| "-".to_string()),
+            self.book
+                .limits
+                .max_net_usd
+                .map(|v| format!("${:.0}", v as f64 / 1_000_000.0))
+                .unwrap_or_else(|| "-".to_string())
+        );
+        for i in 0..self.legs.len() {
+            self.call_leg(sim, i, LegCall::Start);
+        }
+    }
+
+    fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
+        for i in 0..self.legs.len() {
+            if self.legs[i].next_wakeup.is_some_and(|t| t <= now_ns) {
+                self.legs[i].next_wakeup = None;
+                self.call_leg(sim, i, LegCall::Wakeup(now_ns));
+            }
+        }
+    }
+
+    fn on_message(&mut self, sim: &mut dyn SimulatorApi, msg: &Message) {
+        match &msg.payload {
+            MessagePayload::OracleTick(_) | MessagePayload::OracleBatchTick(_) => {
+                for p in msg.payload.oracle_ticks() {
+                    let mid = (p.price.min + p.price.max) / 2;
+                    self.book.last_prices.insert(p.symbol.clone(), mid);
+                    self.call_legs_for(sim, &p.symbol, msg);
+                }
+            }
+            MessagePayload::MarketState(p) => self.call_legs_for(sim, &p.symbol, msg),
+            MessagePayload::OrderExecuted(p) => {
+                let key = (p.symbol.clone(), p.side);
+                match p.order_type {
+                    OrderExecutionType::Increase => {
+                        if let Some(q) = self.book.inflight.get_mut(&key) {
+                            q.pop_front();
+                        }
+                        *self.book.filled.entry(key).or_default() += p.size_usd;
+                    }
+                    OrderExecutionType::Decrease | OrderExecutionType::Liquidation => {
+                        self.book.clear(&p.symbol, p.side);
+                    }
+                }
+                self.call_legs_for(sim, &p.symbol, msg);
+            }
+            MessagePayload::PositionLiquidated(p) => {
+                self.book.clear(&p.symbol, p.side);
+                let (gross, net) = self.exposure_usd();
+                agent_println!(
+                    "[{}] LEG LIQUIDATED {} {:?} (portfolio gross=${:.0} net=${:+.0})",
+                    self.name,
+                    p.symbol,
+                    p.side,
+                    gross,
+                    net
+                );
+                self.call_legs_for(sim, &p.symbol, msg);
+            }
+            _ if msg.from == self.exchange_id && !self.legs.is_empty() => {
+                let i = self.reply_leg(msg);
+                self.call_leg(sim, i, LegCall::Message(msg));
+            }
+            _ => {
+                for i in 0..self.legs.len() {
+                    self.call_leg(sim, i, LegCall::Message(msg));
+                }
+            }
+        }
+    }
+
+    fn on_stop(&mut self, sim: &mut dyn SimulatorApi) {
+        for i in 0..self.legs.len() {
+            self.call_leg(sim, i, LegCall::Stop);
+        }
+        let (gross, net) = self.exposure_usd();
+        agent_println!(
+            "[{}] STOP portfolio: legs={} gross=${:.0} net=${:+.0} blocked={}",
+            self.name,
+            self.legs.len(),
+            gross,
+            net,
+            self.book.blocked
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::kernel::Kernel;
+    use crate::latency::FixedLatency;
+    use crate::logging::Verbosity;
+    use crate::messages::{ExecutionType, MarketOrderPayload, OracleTickPayload, OrderPayload, Price};
+    use crate::qty::Qty;
+
+    const PORTFOLIO: AgentId = 10;
+    const EXCHANGE: AgentId = 1;
+
+    type Seen = Rc<RefCell<Vec<(String, MessageType)>>>;
+
+    /// Leg that sends one order on start and records what it receives
+    struct TestLeg {
+        symbol: String,
+        order: Option<(MessageType, MessagePayload)>,
+        seen: Seen,
+    }
+
+    impl Agent for TestLeg {
+        fn id(&self) -> AgentId {
+            PORTFOLIO
+        }
+
+        fn name(&self) -> &str {
+            &self.symbol
+        }
+
+        fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+            if let Some((kind, payload)) = self.order.take() {
+                sim.send(PORTFOLIO, EXCHANGE, kind, payload);
+            }
+        }
+
+        fn on_message(&mut self, _sim: &mut dyn SimulatorApi, msg: &Message) {
+            self.seen.borrow_mut().push((self.symbol.clone(), msg.msg_type));
+        }
+    }
+
+    fn leg(symbol: &str, order: Option<(MessageType, MessagePayload)>, seen: &Seen) -> (String, Box<dyn Agent>) {
+        let agent = TestLeg {
+            symbol: symbol.to_string(),
+            order,
+            seen: seen.clone(),
+        };
+        (symbol.to_string(), Box::new(agent))
+    }
+
+    fn market(symbol: &str, side: Side, tokens: f64) -> (MessageType, MessagePayload) {
+        let order = MarketOrderPayload {
+            symbol: symbol.to_string(),
+            side,
+            qty: Qty::from_tokens(tokens),
+            leverage: 5,
+            decision_price: None,
+        };
+        (MessageType::MarketOrder, MessagePayload::MarketOrder(order))
+    }
+
+    fn stop_loss(symbol: &str) -> (MessageType, MessagePayload) {
+        let order = OrderPayload {
+            symbol: symbol.to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Decrease,
+            execution_type: ExecutionType::StopLoss,
+            qty: None,
+            leverage: None,
+            size_delta_usd: None,
+            trigger_price: Some(1_000_000_000),
+            acceptable_price: None,
+            trailing_distance: None,
+            valid_for_sec: None,
+            oco_group: None,
+            decision_price: None,
+        };
+        (MessageType::SubmitOrder, MessagePayload::Order(order))
+    }
+
+    fn kernel() -> Kernel {
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(0, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        kernel
+    }
+
+    fn tick(symbol: &str, price: u64) -> Message {
+        let payload = OracleTickPayload {
+            symbol: symbol.to_string(),
+            price: Price { min: price, max: price },
+            publish_time: 0,
+            signature: std::sync::Arc::from(Vec::new()),
+        };
+        Message::new(
+            PORTFOLIO,
+            2,
+            MessageType::OracleTick,
+            0,
+            MessagePayload::OracleTick(payload),
+        )
+    }
+
+    fn reply(kind: MessageType, text: &str) -> Message {
+        Message::new(PORTFOLIO, EXCHANGE, kind, 0, MessagePayload::Text(text.to_string()))
+    }
+
+    fn book(max_gross_usd: Option<i128>, max_net_usd: Option<i128>) -> Book {
+        Book {
+            limits: ExposureLimits {
+                max_gross_usd,
+                max_net_usd,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_gross_limit_counts_both_sides() {
+        let mut book = book(Some(10_000_000_000), None);
+        book.filled.insert(("ETH-USD".to_string(), Side::Buy), 6_000_000_000);
+        book.inflight
+            .entry(("BTC-USD".to_string(), Side::Sell))
+            .or_default()
+            .push_back(3_000_000_000);
+        assert_eq!(book.check(Side::Sell, 1_000_000_000), None);
+        assert!(book.check(Side::Sell, 1_000_000_001).is_some());
+        assert!(book.check(Side::Buy, 2_000_000_000).is_some());
+    }
+
+    #[test]
+    fn test_net_limit_allows_reducing_orders() {
+        let mut book = book(None, Some(5_000_000_000));
+        book.filled.insert(("ETH-USD".to_string(), Side::Buy), 8_000_000_000);
+        // Above the limit already: a smaller net exposure passes, a larger one does not
+        assert_eq!(book.check(Side::Sell, 2_000_000_000), None);
+        assert!(book.check(Side::Buy, 1_000_000).is_some());
+    }
+
+    #[test]
+    fn test_blocked_order_is_rejected_to_its_leg() {
+        let seen = Seen::default();
+        let legs = vec![
+            leg("ETH-USD", None, &seen),
+            leg("BTC-USD", Some(market("BTC-USD", Side::Buy, 1.0)), &seen),
+        ];
+        let limits = ExposureLimits {
+            max_gross_usd: Some(50_000_000_000),
+            max_net_usd: None,
+        };
+        let mut portfolio = PortfolioAgent::new(PORTFOLIO, "P".to_string(), EXCHANGE, legs, limits);
+        let mut kernel = kernel();
+        portfolio.on_message(&mut kernel, &tick("BTC-USD", 60_000_000_000));
+        seen.borrow_mut().clear();
+
+        portfolio.on_start(&mut kernel);
+        assert_eq!(portfolio.book.blocked, 1);
+        assert!(portfolio.book.inflight.is_empty());
+        assert_eq!(
+            *seen.borrow(),
+            vec![("BTC-USD".to_string(), MessageType::OrderRejected)]
+        );
+    }
+
+    #[test]
+    fn test_replies_route_by_order_id() {
+        let seen = Seen::default();
+        let legs = vec![
+            leg("ETH-USD", Some(stop_loss("ETH-USD")), &seen),
+            leg("BTC-USD", Some(stop_loss("BTC-USD")), &seen),
+        ];
+        let mut portfolio = PortfolioAgent::new(PORTFOLIO, "P".to_string(), EXCHANGE, legs, ExposureLimits::default());
+        let mut kernel = kernel();
+        portfolio.on_start(&mut kernel);
+
+        // Acknowledged in submission order, then triggered after the BTC leg sent the last order
+        portfolio.on_message(&mut kernel, &reply(MessageType::OrderPending, "order_id:11"));
+        portfolio.on_message(&mut kernel, &reply(MessageType::OrderPending, "order_id:12"));
+        portfolio.on_message(&mut kernel, &reply(MessageType::OrderTriggered, "order_id:11"));
+        portfolio.on_message(&mut kernel, &reply(MessageType::OrderRejected, "bankrupt"));
+
+        let expected = vec![
+            ("ETH-USD".to_string(), MessageType::OrderPending),
+            ("BTC-USD".to_string(), MessageType::OrderPending),
+            ("ETH-USD".to_string(), MessageType::OrderTriggered),
+            ("BTC-USD".to_string(), MessageType::OrderRejected),
+        ];
+        assert_eq!(*seen.borrow(), expected);
+        assert_eq!(portfolio.order_legs, HashMap::from([(12, 1)]));
+    }
+}
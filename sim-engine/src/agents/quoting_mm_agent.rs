@@ -0,0 +1,450 @@
+//! QuotingMarketMakerAgent - a quoting market maker with resting limit orders.
+//!
+//! Unlike the OI-balancing MarketMakerAgent, this agent keeps a bid and an ask
+//! (limit Increase orders) around the oracle mid on every tick:
+//!   reservation = mid * (1 - inventory_skew_bps / 10_000 * inventory / max_inventory)
+//!   bid = reservation * (1 - spread_bps / 20_000), ask = reservation * (1 + spread_bps / 20_000)
+//! A long inventory shifts both quotes down (cheaper to sell to, less eager to
+//! buy) and vice versa. The side that would grow the inventory past
+//! `max_inventory_usd` is not quoted.
+//!
+//! Quotes are cancelled and re-placed when the mid moves by `requote_bps` or
+//! more. `quote_ttl_sec` also bounds the life of quotes whose cancel fails.
+
+use std::collections::VecDeque;
+
+use crate::agent_println;
+use crate::agents::Agent;
+use crate::messages::{
+    AgentId, CancelOrderPayload, ExecutionType, Message, MessagePayload, MessageType,
+    OrderExecutedPayload, OrderExecutionType, OrderId, OrderPayload, OrderType, Side, SimulatorApi,
+};
+use crate::qty::Qty;
+
+#[derive(Debug, Clone)]
+pub struct QuotingMmConfig {
+    pub name: String,
+    pub exchange_id: AgentId,
+    pub symbol: String,
+    /// Quote size per side in tokens
+    pub qty: f64,
+    pub leverage: u32,
+    /// Full bid-ask spread (basis points)
+    pub spread_bps: f64,
+    /// Requote when the mid moved this much since the last quotes (0 = every tick)
+    pub requote_bps: f64,
+    /// Quote shift at full inventory (basis points)
+    pub inventory_skew_bps: f64,
+    /// Net inventory (micro-USD) at which the increasing side stops being quoted
+    pub max_inventory_usd: i128,
+    pub quote_ttl_sec: u64,
+}
+
+pub struct QuotingMarketMakerAgent {
+    id: AgentId,
+    name: String,
+    exchange_id: AgentId,
+    symbol: String,
+    address: Option<String>,
+
+    qty: f64,
+    leverage: u32,
+    spread_bps: f64,
+    requote_bps: f64,
+    inventory_skew_bps: f64,
+    max_inventory_usd: i128,
+    quote_ttl_sec: u64,
+
+    /// Mid price and time the live quotes were placed at
+    quoted_mid: Option<u64>,
+    quoted_at_ns: u64,
+    /// Acknowledged, unfilled quotes
+    bid_live: bool,
+    ask_live: bool,
+    bid_order: Option<OrderId>,
+    ask_order: Option<OrderId>,
+    /// Sides of submitted quotes waiting for OrderPending, in submission order
+    unacked: VecDeque<Side>,
+
+    /// Filled notional per side (micro-USD)
+    long_usd: i128,
+    short_usd: i128,
+
+    quotes_placed: u32,
+    bid_fills: u32,
+    ask_fills: u32,
+    liquidations: u32,
+}
+
+impl QuotingMarketMakerAgent {
+    pub fn new(id: AgentId, config: QuotingMmConfig) -> Self {
+        Self {
+            id,
+            name: config.name,
+            exchange_id: config.exchange_id,
+            symbol: config.symbol,
+            address: None,
+            qty: config.qty,
+            leverage: config.leverage.max(1),
+            spread_bps: config.spread_bps,
+            requote_bps: config.requote_bps,
+            inventory_skew_bps: config.inventory_skew_bps,
+            max_inventory_usd: config.max_inventory_usd,
+            quote_ttl_sec: config.quote_ttl_sec,
+            quoted_mid: None,
+            quoted_at_ns: 0,
+            bid_live: false,
+            ask_live: false,
+            bid_order: None,
+            ask_order: None,
+            unacked: VecDeque::new(),
+            long_usd: 0,
+            short_usd: 0,
+            quotes_placed: 0,
+            bid_fills: 0,
+            ask_fills: 0,
+            liquidations: 0,
+        }
+    }
+
+    pub fn set_address(&mut self, address: String) {
+        self.address = Some(address);
+    }
+
+    /// Long minus short filled notional (micro-USD)
+    fn inventory(&self) -> i128 {
+        self.long_usd - self.short_usd
+    }
+
+    /// (bid, ask) around the inventory-skewed reservation price
+    fn quotes(&self, mid: u64) -> (u64, u64) {
+        let fill = if self.max_inventory_usd > 0 {
+            (self.inventory() as f64 / self.max_inventory_usd as f64).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        };
+        let reservation = mid as f64 * (1.0 - self.inventory_skew_bps / 10_000.0 * fill);
+        let half_spread = self.spread_bps / 20_000.0;
+        (
+            (reservation * (1.0 - half_spread)) as u64,
+            (reservation * (1.0 + half_spread)) as u64,
+        )
+    }
+
+    fn cancel_quotes(&mut self, sim: &mut dyn SimulatorApi) {
+        self.bid_live = false;
+        self.ask_live = false;
+        for order_id in [self.bid_order.take(), self.ask_order.take()].into_iter().flatten() {
+            sim.send(
+                self.id,
+                self.exchange_id,
+                MessageType::CancelOrder,
+                MessagePayload::CancelOrder(CancelOrderPayload { order_id }),
+            );
+        }
+    }
+
+    fn submit_quote(&mut self, sim: &mut dyn SimulatorApi, side: Side, price: u64) {
+        let order = OrderPayload {
+            symbol: self.symbol.clone(),
+            side,
+            order_type: OrderType::Increase,
+            execution_type: ExecutionType::Limit,
+            qty: Some(Qty::from_tokens(self.qty)),
+            leverage: Some(self.leverage),
+            size_delta_usd: None,
+            trigger_price: Some(price),
+            acceptable_price: None,
+            trailing_distance: None,
+            valid_for_sec: Some(self.quote_ttl_sec),
+            oco_group: None,
+            decision_price: None,
+        };
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::SubmitOrder,
+            MessagePayload::Order(order),
+        );
+        self.unacked.push_back(side);
+        self.quotes_placed += 1;
+    }
+
+    fn requote(&mut self, sim: &mut dyn SimulatorApi, mid: u64, now_ns: u64) {
+        // Wait for the previous quotes to be acknowledged before replacing them
+        if !self.unacked.is_empty() {
+            return;
+        }
+        let moved_bps = self
+            .quoted_mid
+            .map(|q| (mid as f64 - q as f64).abs() / q.max(1) as f64 * 10_000.0);
+        let expired = now_ns.saturating_sub(self.quoted_at_ns) >= self.quote_ttl_sec * 1_000_000_000;
+        let live = self.bid_live || self.ask_live;
+        if live && !expired && moved_bps.is_some_and(|m| m < self.requote_bps.max(f64::EPSILON)) {
+            return;
+        }
+
+        self.cancel_quotes(sim);
+        let (bid, ask) = self.quotes(mid);
+        let inventory = self.inventory();
+        let quote_bid = inventory < self.max_inventory_usd;
+        let quote_ask = inventory > -self.max_inventory_usd;
+        agent_println!(
+            "[QMM {}] QUOTE {} bid=${:.2}{} ask=${:.2}{} inv=${:.2}",
+            self.name,
+            self.symbol,
+            bid as f64 / 1_000_000.0,
+            if quote_bid { "" } else { " (off)" },
+            ask as f64 / 1_000_000.0,
+            if quote_ask { "" } else { " (off)" },
+            inventory as f64 / 1_000_000.0
+        );
+        if quote_bid {
+            self.submit_quote(sim, Side::Buy, bid);
+        }
+        if quote_ask {
+            self.submit_quote(sim, Side::Sell, ask);
+        }
+        self.quoted_mid = Some(mid);
+        self.quoted_at_ns = now_ns;
+    }
+
+    fn handle_order_executed(&mut self, payload: &OrderExecutedPayload) {
+        match (payload.order_type, payload.side) {
+            (OrderExecutionType::Increase, Side::Buy) => {
+                self.long_usd += payload.size_usd;
+                self.bid_live = false;
+                self.bid_order = None;
+                self.bid_fills += 1;
+            }
+            (OrderExecutionType::Increase, Side::Sell) => {
+                self.short_usd += payload.size_usd;
+                self.ask_live = false;
+                self.ask_order = None;
+                self.ask_fills += 1;
+            }
+            (_, Side::Buy) => self.long_usd = 0,
+            (_, Side::Sell) => self.short_usd = 0,
+        }
+        // Requote on the next tick
+        self.quoted_mid = None;
+    }
+}
+
+impl Agent for QuotingMarketMakerAgent {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        match (key, value.as_f64()) {
+            ("qty", Some(v)) if v > 0.0 => self.qty = v,
+            ("leverage", Some(v)) if v >= 1.0 => self.leverage = v as u32,
+            ("spread_bps", Some(v)) if v > 0.0 => self.spread_bps = v,
+            ("requote_bps", Some(v)) if v >= 0.0 => self.requote_bps = v,
+            ("inventory_skew_bps", Some(v)) if v >= 0.0 => self.inventory_skew_bps = v,
+            ("max_inventory_usd", Some(v)) if v > 0.0 => self.max_inventory_usd = v as i128,
+            _ => return false,
+        }
+        // New parameters apply from the next tick
+        self.quoted_mid = None;
+        agent_println!("[QMM {}] param {} = {}", self.name, key, value);
+        true
+    }
+
+    fn on_start(&mut self, _sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[QMM {}] START {} spread={}bps qty={:.3} {}x{}",
+            self.name,
+            self.symbol,
+            self.spread_bps,
+            self.qty,
+            self.leverage,
+            self.address
+                .as_deref()
+                .map(|addr| format!(" addr={}", addr))
+                .unwrap_or_default()
+        );
+    }
+
+    fn on_wakeup(&mut self, _sim: &mut dyn SimulatorApi, _now_ns: u64) {}
+
+    fn on_message(&mut self, sim: &mut dyn SimulatorApi, msg: &Message) {
+        match &msg.payload {
+            MessagePayload::OracleTick(_) | MessagePayload::OracleBatchTick(_) => {
+                if let Some(p) = msg.payload.oracle_tick(&self.symbol) {
+                    let mid = (p.price.min + p.price.max) / 2;
+                    let now_ns = sim.now_ns();
+                    self.requote(sim, mid, now_ns);
+                }
+            }
+            MessagePayload::OrderExecuted(p) if p.symbol == self.symbol => self.handle_order_executed(p),
+            MessagePayload::PositionLiquidated(p) if p.symbol == self.symbol => {
+                match p.side {
+                    Side::Buy => self.long_usd = 0,
+                    Side::Sell => self.short_usd = 0,
+                }
+                self.liquidations += 1;
+                self.quoted_mid = None;
+            }
+            MessagePayload::Text(text) if msg.msg_type == MessageType::OrderPending => {
+                let Some(side) = self.unacked.pop_front() else {
+                    return;
+                };
+                let order_id = text.strip_prefix("order_id:").and_then(|id| id.parse::<OrderId>().ok());
+                match side {
+                    Side::Buy => (self.bid_live, self.bid_order) = (true, order_id),
+                    Side::Sell => (self.ask_live, self.ask_order) = (true, order_id),
+                }
+            }
+            _ if msg.msg_type == MessageType::OrderRejected => {
+                self.unacked.pop_front();
+            }
+            _ => {}
+        }
+    }
+
+    fn on_stop(&mut self, sim: &mut dyn SimulatorApi) {
+        self.cancel_quotes(sim);
+        agent_println!(
+            "[QMM {}] STOP: quotes={} bid_fills={} ask_fills={} liq={} inv=${:.2}",
+            self.name,
+            self.quotes_placed,
+            self.bid_fills,
+            self.ask_fills,
+            self.liquidations,
+            self.inventory() as f64 / 1_000_000.0
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::test_support::{delivered, kernel_with_recorder, oracle_tick};
+
+    const EXCHANGE: AgentId = 1;
+    const QMM: AgentId = 25;
+    const USD: u64 = 1_000_000;
+
+    fn quoting_mm() -> QuotingMarketMakerAgent {
+        QuotingMarketMakerAgent::new(
+            QMM,
+            QuotingMmConfig {
+                name: "qmm".to_string(),
+                exchange_id: EXCHANGE,
+                symbol: "ETH-USD".to_string(),
+                qty: 1.0,
+                leverage: 5,
+                spread_bps: 20.0,
+                requote_bps: 10.0,
+                inventory_skew_bps: 50.0,
+                max_inventory_usd: 10_000 * USD as i128,
+                quote_ttl_sec: 60,
+            },
+        )
+    }
+
+    fn ack(order_id: OrderId) -> Message {
+        let text = format!("order_id:{}", order_id);
+        Message::new(QMM, EXCHANGE, MessageType::OrderPending, 0, MessagePayload::Text(text))
+    }
+
+    fn bought(size_usd: i128) -> Message {
+        let fill = OrderExecutedPayload {
+            symbol: "ETH-USD".to_string(),
+            side: Side::Buy,
+            order_type: OrderExecutionType::Increase,
+            collateral_delta: size_usd / 5,
+            pnl: 0,
+            size_usd,
+        };
+        Message::new(
+            QMM,
+            EXCHANGE,
+            MessageType::OrderExecuted,
+            0,
+            MessagePayload::OrderExecuted(fill),
+        )
+    }
+
+    /// (cancelled order ids, submitted (side, price) quotes)
+    fn sorted(msgs: &[Message]) -> (Vec<OrderId>, Vec<(Side, u64)>) {
+        let mut cancels = Vec::new();
+        let mut quotes = Vec::new();
+        for msg in msgs {
+            match &msg.payload {
+                MessagePayload::CancelOrder(c) => cancels.push(c.order_id),
+                MessagePayload::Order(o) => quotes.push((o.side, o.trigger_price.unwrap_or_default())),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        (cancels, quotes)
+    }
+
+    fn assert_near(actual: u64, expected: u64) {
+        assert!(actual.abs_diff(expected) <= 1, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn test_quotes_shift_with_inventory() {
+        let mut qmm = quoting_mm();
+        let (bid, ask) = qmm.quotes(2_000 * USD);
+        assert_near(bid, 1_998 * USD);
+        assert_near(ask, 2_002 * USD);
+
+        // Half the max inventory long moves the reservation 25bps down
+        qmm.long_usd = 5_000 * USD as i128;
+        let (bid, ask) = qmm.quotes(2_000 * USD);
+        assert_near(bid, 1_993_005_000);
+        assert_near(ask, 1_996_995_000);
+
+        // Beyond the max the skew saturates
+        qmm.long_usd = 0;
+        qmm.short_usd = 50_000 * USD as i128;
+        let (bid, _) = qmm.quotes(2_000 * USD);
+        assert_near(bid, 2_007_990_000);
+    }
+
+    #[test]
+    fn test_requotes_on_moves_and_drops_the_full_side() {
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut qmm = quoting_mm();
+
+        qmm.on_message(&mut kernel, &oracle_tick(QMM, "ETH-USD", 2_000 * USD));
+        let (cancels, quotes) = sorted(&delivered(&mut kernel, &exchange));
+        assert!(cancels.is_empty());
+        assert_eq!(
+            quotes.iter().map(|(side, _)| *side).collect::<Vec<_>>(),
+            [Side::Buy, Side::Sell]
+        );
+        qmm.on_message(&mut kernel, &ack(11));
+        qmm.on_message(&mut kernel, &ack(12));
+
+        // 5bps is inside the requote band
+        qmm.on_message(&mut kernel, &oracle_tick(QMM, "ETH-USD", 2_001 * USD));
+        assert!(delivered(&mut kernel, &exchange).is_empty());
+
+        // 50bps replaces both quotes
+        qmm.on_message(&mut kernel, &oracle_tick(QMM, "ETH-USD", 2_010 * USD));
+        let (cancels, quotes) = sorted(&delivered(&mut kernel, &exchange));
+        assert_eq!(cancels, [11, 12]);
+        assert_eq!(quotes.len(), 2);
+        assert_near(quotes[0].1, 2_007_990_000);
+        qmm.on_message(&mut kernel, &ack(13));
+        qmm.on_message(&mut kernel, &ack(14));
+
+        // A bid fill up to the max inventory leaves only the ask, skewed down
+        qmm.on_message(&mut kernel, &bought(10_000 * USD as i128));
+        qmm.on_message(&mut kernel, &oracle_tick(QMM, "ETH-USD", 2_010 * USD));
+        let (cancels, quotes) = sorted(&delivered(&mut kernel, &exchange));
+        assert_eq!(cancels, [14]);
+        assert_eq!(quotes.len(), 1);
+        assert_eq!(quotes[0].0, Side::Sell);
+        assert_near(quotes[0].1, 2_001_949_950);
+    }
+}
@@ -0,0 +1,291 @@
+//! RemoteStrategy - decision logic in an external process attached over the WebSocket API.
+//!
+//! Same JSON protocol as the bridge agent (see `bridge_strategy`), one text
+//! frame per request or reply, but the process connects to the sim instead of
+//! the other way round: `ws://host:<api_port + 1>/remote/<agent name>`. Only
+//! one client per agent; until one is attached every hook is a no-op. A newly
+//! attached client first gets a `start` event. A reply missing `timeout_ms` is
+//! a no-op as well and the session stays open; late replies are dropped by `seq`.
+
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::RecvTimeoutError;
+
+use crate::agent_println;
+use crate::agents::bridge_strategy::{encode_param, encode_request, BridgeReply, RequestBody};
+use crate::agents::strategy_agent::{ActionIntent, Strategy, StrategyAgent, StrategyContext};
+use crate::api::RemoteLink;
+use crate::messages::OrderExecutedPayload;
+
+pub type RemoteAgent = StrategyAgent<RemoteStrategy>;
+
+#[derive(Debug, Clone)]
+pub struct RemoteConfig {
+    pub name: String,
+    /// Deadline of one request/reply round trip
+    pub timeout_ms: u64,
+    pub timer_interval_ms: Option<u64>,
+}
+
+pub struct RemoteStrategy {
+    name: String,
+    link: RemoteLink,
+    timeout: Duration,
+    timer_interval_ms: Option<u64>,
+
+    seq: u64,
+    /// The attached client got its `start` event
+    started: bool,
+    /// Sim time of the last request, stamped on `param` events
+    last_ts: f64,
+
+    requests: u32,
+    timeouts: u32,
+    bad_replies: u32,
+    /// Hooks while no client was attached
+    idle: u32,
+}
+
+impl RemoteStrategy {
+    pub fn new(config: RemoteConfig, link: RemoteLink) -> Self {
+        Self {
+            name: config.name,
+            link,
+            timeout: Duration::from_millis(config.timeout_ms),
+            timer_interval_ms: config.timer_interval_ms,
+            seq: 0,
+            started: false,
+            last_ts: 0.0,
+            requests: 0,
+            timeouts: 0,
+            bad_replies: 0,
+            idle: 0,
+        }
+    }
+
+    /// Greet a newly attached client, then forward the event; no client = no action.
+    fn request(&mut self, ctx: &StrategyContext, event: &'static str, body: RequestBody) -> Vec<ActionIntent> {
+        if !self.link.is_attached() {
+            if self.started {
+                agent_println!("[Remote {}] client detached", self.name);
+                self.started = false;
+            }
+            self.idle += 1;
+            return Vec::new();
+        }
+        let mut actions = Vec::new();
+        if !self.started {
+            self.started = true;
+            agent_println!("[Remote {}] client attached", self.name);
+            if event != "start" {
+                actions = self.round_trip(ctx, "start", RequestBody::default());
+            }
+        }
+        actions.extend(self.round_trip(ctx, event, body));
+        actions
+    }
+
+    fn round_trip(&mut self, ctx: &StrategyContext, event: &'static str, body: RequestBody) -> Vec<ActionIntent> {
+        self.seq += 1;
+        self.requests += 1;
+        self.last_ts = ctx.now_ns as f64 / 1e9;
+        let line = match encode_request(ctx, self.seq, event, &self.name, body) {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("[Remote {}] encode failed: {}", self.name, e);
+                return Vec::new();
+            }
+        };
+        // Replies to requests that already timed out
+        self.link.drain();
+        if !self.link.send(line) {
+            return Vec::new();
+        }
+        match self.read_reply() {
+            Some(reply) => reply.into_actions(),
+            None => {
+                self.timeouts += 1;
+                eprintln!(
+                    "[Remote {}] {} #{} timed out after {:?}",
+                    self.name, event, self.seq, self.timeout
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    /// None on timeout or when the client went away.
+    fn read_reply(&mut self) -> Option<BridgeReply> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let line = match self.link.recv_timeout(remaining) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => return None,
+            };
+            match serde_json::from_str::<BridgeReply>(line.trim()) {
+                Ok(reply) if reply.seq == self.seq => return Some(reply),
+                Ok(_) => continue,
+                Err(e) => {
+                    eprintln!("[Remote {}] bad reply: {}", self.name, e);
+                    self.bad_replies += 1;
+                }
+            }
+        }
+    }
+}
+
+impl Strategy for RemoteStrategy {
+    fn label(&self) -> &str {
+        "Remote"
+    }
+
+    fn timer_interval_ms(&self) -> Option<u64> {
+        self.timer_interval_ms
+    }
+
+    fn on_start(&mut self, ctx: &StrategyContext) -> Vec<ActionIntent> {
+        self.request(ctx, "start", RequestBody::default())
+    }
+
+    fn on_price(&mut self, ctx: &StrategyContext, symbol: &str, price: u64) -> Vec<ActionIntent> {
+        self.request(ctx, "price", RequestBody::price(symbol, price))
+    }
+
+    fn on_fill(&mut self, ctx: &StrategyContext, fill: &OrderExecutedPayload) -> Vec<ActionIntent> {
+        self.request(ctx, "fill", RequestBody::fill(fill))
+    }
+
+    fn on_timer(&mut self, ctx: &StrategyContext) -> Vec<ActionIntent> {
+        self.request(ctx, "timer", RequestBody::default())
+    }
+
+    /// Forwarded as a `param` event without a reply; dropped while no client is attached.
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        self.seq += 1;
+        self.link.send(encode_param(self.seq, self.last_ts, key, value))
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "requests={} timeouts={} bad_replies={} idle={} attached={}",
+            self.requests,
+            self.timeouts,
+            self.bad_replies,
+            self.idle,
+            self.link.is_attached()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::thread::JoinHandle;
+
+    use super::*;
+    use crate::agents::test_support::{delivered, kernel_with_recorder, oracle_tick};
+    use crate::agents::Agent;
+    use crate::api::{RemoteEndpoint, RemoteHub};
+    use crate::messages::{AgentId, Message, MessagePayload, Side};
+
+    const EXCHANGE: AgentId = 1;
+    const REMOTE: AgentId = 90;
+
+    fn remote(hub: &RemoteHub) -> RemoteAgent {
+        let config = RemoteConfig {
+            name: "remote".to_string(),
+            timeout_ms: 50,
+            timer_interval_ms: None,
+        };
+        let strategy = RemoteStrategy::new(config, hub.register("remote"));
+        RemoteAgent::new(REMOTE, "remote".to_string(), EXCHANGE, strategy)
+    }
+
+    /// Attached client answering each of the next requests with its batch of replies (an empty
+    /// batch stays silent, a reply without `seq` gets the request's); returns the requests it saw.
+    fn client(endpoint: RemoteEndpoint, replies: Vec<Vec<serde_json::Value>>) -> JoinHandle<Vec<serde_json::Value>> {
+        std::thread::spawn(move || {
+            let mut seen = Vec::new();
+            for batch in replies {
+                let line = endpoint.outbound.recv_timeout(Duration::from_secs(5)).unwrap();
+                let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+                for mut reply in batch {
+                    if reply.get("seq").is_none() {
+                        reply["seq"] = request["seq"].clone();
+                    }
+                    endpoint.inbound.send(reply.to_string()).unwrap();
+                }
+                seen.push(request);
+            }
+            seen
+        })
+    }
+
+    fn open(side: &str) -> serde_json::Value {
+        serde_json::json!({
+            "intents": [{"action": "open", "symbol": "ETH-USD", "side": side, "qty": 1.0, "leverage": 2}],
+        })
+    }
+
+    fn events(seen: &[serde_json::Value]) -> Vec<&str> {
+        seen.iter().map(|request| request["event"].as_str().unwrap()).collect()
+    }
+
+    fn tick() -> Message {
+        oracle_tick(REMOTE, "ETH-USD", 2_000_000_000)
+    }
+
+    #[test]
+    fn test_idle_until_attached_and_start_comes_first() {
+        let hub = RemoteHub::new();
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut agent = remote(&hub);
+        agent.on_start(&mut kernel);
+        agent.on_message(&mut kernel, &tick());
+        assert!(!agent.set_param("risk", &serde_json::json!(1)));
+        assert!(delivered(&mut kernel, &exchange).is_empty());
+
+        // A client attaching mid-run is greeted with `start` before the event itself
+        let endpoint = hub.attach("remote").unwrap();
+        assert!(hub.attach("remote").is_none());
+        let attached = endpoint.attached.clone();
+        let process = client(endpoint, vec![vec![open("long")], vec![serde_json::json!({})]]);
+        agent.on_message(&mut kernel, &tick());
+        assert_eq!(events(&process.join().unwrap()), ["start", "price"]);
+        assert!(matches!(
+            &delivered(&mut kernel, &exchange)[..],
+            [Message { payload: MessagePayload::MarketOrder(o), .. }] if o.side == Side::Buy
+        ));
+
+        // Detached: idle again; the next client gets its own `start`
+        attached.store(false, Ordering::SeqCst);
+        agent.on_message(&mut kernel, &tick());
+        let endpoint = hub.attach("remote").unwrap();
+        let process = client(endpoint, vec![vec![], vec![]]);
+        agent.on_message(&mut kernel, &tick());
+        assert_eq!(events(&process.join().unwrap()), ["start", "price"]);
+    }
+
+    #[test]
+    fn test_timeouts_keep_the_session_and_late_replies_are_dropped() {
+        let hub = RemoteHub::new();
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut agent = remote(&hub);
+        let endpoint = hub.attach("remote").unwrap();
+        // `start` (#1) times out; its reply only arrives with the next request's
+        let mut late = open("long");
+        late["seq"] = 1.into();
+        let process = client(endpoint, vec![vec![], vec![late, open("short")]]);
+
+        agent.on_start(&mut kernel);
+        assert!(delivered(&mut kernel, &exchange).is_empty());
+        agent.on_message(&mut kernel, &tick());
+        assert_eq!(events(&process.join().unwrap()), ["start", "price"]);
+        assert!(matches!(
+            &delivered(&mut kernel, &exchange)[..],
+            [Message { payload: MessagePayload::MarketOrder(o), .. }] if o.side == Side::Sell
+        ));
+        assert!(agent.set_param("risk", &serde_json::json!(1)));
+    }
+}
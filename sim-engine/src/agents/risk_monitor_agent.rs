@@ -0,0 +1,541 @@
+//! RiskMonitorAgent - real-time risk reports from exchange snapshots.
+//!
+//! The agent subscribes to PositionsSnapshot at the exchange and follows the
+//! oracle ticks. For every snapshot it emits a RiskReport event with:
+//! - VaR: z * sigma(horizon) * |long OI - short OI|, the pool loss on the net
+//!   trader exposure, sigma estimated from the last `vol_window` oracle returns
+//! - the account with the largest share of OI
+//! - a liquidation-price heatmap: notional per `bucket_bps` price bucket within
+//!   `heatmap_range_pct` of the current price
+//! - the notional of positions within `warn_distance_pct` of their liquidation
+//!
+//! Liquidation prices are estimated from entry price, collateral and
+//! `maintenance_margin`. Thresholds raise RiskAlert events once per crossing.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use crate::agent_println;
+use crate::agents::Agent;
+use crate::events::{LiqBucket, SimEvent};
+use crate::messages::{
+    AgentId, Message, MessagePayload, MessageType, PositionInfo, PositionsSnapshotPayload, Side,
+    SimulatorApi,
+};
+
+#[derive(Debug, Clone)]
+pub struct RiskMonitorConfig {
+    pub name: String,
+    pub exchange_id: AgentId,
+    pub var_horizon_sec: u64,
+    /// One-sided z-score of the VaR confidence (2.326 = 99%)
+    pub var_z: f64,
+    /// Oracle returns used for the volatility estimate
+    pub vol_window: usize,
+    /// Maintenance margin (fraction of size) used to estimate liquidation prices
+    pub maintenance_margin: f64,
+    pub bucket_bps: f64,
+    pub heatmap_range_pct: f64,
+    /// Alert thresholds
+    pub warn_distance_pct: f64,
+    /// VaR as percent of pool liquidity
+    pub var_alert_pct: f64,
+    /// Largest account as percent of OI
+    pub concentration_alert_pct: f64,
+    /// Densest heatmap bucket as percent of OI
+    pub cluster_alert_pct: f64,
+}
+
+/// Estimated price at which the position hits the maintenance margin:
+/// collateral + pnl(price) = size * maintenance_margin.
+fn liquidation_price(p: &PositionInfo, maintenance_margin: f64) -> u64 {
+    if p.size_usd == 0 {
+        return 0;
+    }
+    let margin = p.collateral as f64 / p.size_usd as f64;
+    let factor = match p.side {
+        Side::Buy => 1.0 + maintenance_margin - margin,
+        Side::Sell => 1.0 - maintenance_margin + margin,
+    };
+    (p.entry_price as f64 * factor).max(0.0) as u64
+}
+
+pub struct RiskMonitorAgent {
+    id: AgentId,
+    name: String,
+    exchange_id: AgentId,
+    config: RiskMonitorConfig,
+
+    /// Recent oracle mid prices per symbol: (ts, ln price)
+    prices: HashMap<String, VecDeque<(u64, f64)>>,
+    /// Latched market-level alerts (kind, symbol); re-armed once the metric drops below the threshold
+    active_alerts: HashSet<(&'static str, String)>,
+    /// Positions already reported as close to liquidation
+    warned: HashSet<(AgentId, String, Side)>,
+
+    reports: u32,
+    alerts: u32,
+    max_var_usd: u64,
+}
+
+impl RiskMonitorAgent {
+    pub fn new(id: AgentId, config: RiskMonitorConfig) -> Self {
+        Self {
+            id,
+            name: config.name.clone(),
+            exchange_id: config.exchange_id,
+            config,
+            prices: HashMap::new(),
+            active_alerts: HashSet::new(),
+            warned: HashSet::new(),
+            reports: 0,
+            alerts: 0,
+            max_var_usd: 0,
+        }
+    }
+
+    fn record_price(&mut self, symbol: &str, ts: u64, mid: u64) {
+        if mid == 0 {
+            return;
+        }
+        let window = self.prices.entry(symbol.to_string()).or_default();
+        window.push_back((ts, (mid as f64).ln()));
+        while window.len() > self.config.vol_window + 1 {
+            window.pop_front();
+        }
+    }
+
+    /// Volatility over the VaR horizon (fraction, 1 sigma), scaled from the per-second variance of log returns
+    fn horizon_volatility(&self, symbol: &str) -> f64 {
+        let Some(window) = self.prices.get(symbol) else {
+            return 0.0;
+        };
+        let mut sum_sq = 0.0;
+        for ((_, prev), (_, next)) in window.iter().zip(window.iter().skip(1)) {
+            sum_sq += (next - prev).powi(2);
+        }
+        let elapsed_sec = match (window.front(), window.back()) {
+            (Some((first, _)), Some((last, _))) => last.saturating_sub(*first) as f64 / 1e9,
+            _ => 0.0,
+        };
+        if elapsed_sec <= 0.0 {
+            return 0.0;
+        }
+        (sum_sq / elapsed_sec * self.config.var_horizon_sec as f64).sqrt()
+    }
+
+    fn alert(
+        &mut self,
+        sim: &mut dyn SimulatorApi,
+        kind: &str,
+        symbol: &str,
+        account: Option<AgentId>,
+        value: f64,
+        message: String,
+    ) {
+        agent_println!("[Risk {}] ALERT {} {}: {}", self.name, kind, symbol, message);
+        self.alerts += 1;
+        let ts = sim.now_ns();
+        sim.emit_event(SimEvent::RiskAlert {
+            ts,
+            kind: kind.to_string(),
+            symbol: symbol.to_string(),
+            account,
+            value,
+            message,
+        });
+    }
+
+    /// Market-level alert, raised once while `value >= threshold`.
+    fn check_threshold(
+        &mut self,
+        sim: &mut dyn SimulatorApi,
+        kind: &'static str,
+        symbol: &str,
+        value: f64,
+        threshold: f64,
+        message: String,
+    ) {
+        let key = (kind, symbol.to_string());
+        if value < threshold {
+            self.active_alerts.remove(&key);
+        } else if self.active_alerts.insert(key) {
+            self.alert(sim, kind, symbol, None, value, message);
+        }
+    }
+
+    fn on_snapshot(&mut self, sim: &mut dyn SimulatorApi, snap: &PositionsSnapshotPayload) {
+        // Liquidations trigger at the mark; snapshots without one carry only the index
+        let mark = if snap.mark_price > 0 {
+            snap.mark_price
+        } else {
+            snap.price
+        };
+        if mark == 0 {
+            return;
+        }
+        let price = mark as f64;
+        let total_oi = snap.oi_long_usd + snap.oi_short_usd;
+        let net_oi = (snap.oi_long_usd as f64 - snap.oi_short_usd as f64).abs();
+
+        let volatility = self.horizon_volatility(&snap.symbol);
+        let var_usd = (self.config.var_z * volatility * net_oi) as u64;
+        self.max_var_usd = self.max_var_usd.max(var_usd);
+
+        let mut per_account: HashMap<AgentId, u64> = HashMap::new();
+        let bucket_width = (price * self.config.bucket_bps / 10_000.0).max(1.0);
+        let range = price * self.config.heatmap_range_pct / 100.0;
+        let mut buckets: BTreeMap<i64, (u64, u64)> = BTreeMap::new();
+        let mut at_risk_usd = 0;
+        let mut imminent: Vec<(PositionInfo, u64, f64)> = Vec::new();
+        let mut open: HashSet<(AgentId, Side)> = HashSet::new();
+
+        for p in snap.positions.iter() {
+            *per_account.entry(p.account).or_default() += p.size_usd;
+            open.insert((p.account, p.side));
+            let liq = liquidation_price(p, self.config.maintenance_margin);
+            let distance = liq as f64 - price;
+            if distance.abs() <= range {
+                let bucket = buckets.entry((distance / bucket_width).floor() as i64).or_default();
+                match p.side {
+                    Side::Buy => bucket.0 += p.size_usd,
+                    Side::Sell => bucket.1 += p.size_usd,
+                }
+            }
+            // Distance the price still has to travel to reach the liquidation price (<= 0: liquidatable)
+            let distance_pct = match p.side {
+                Side::Buy => -distance / price * 100.0,
+                Side::Sell => distance / price * 100.0,
+            };
+            let key = (p.account, snap.symbol.clone(), p.side);
+            if distance_pct <= self.config.warn_distance_pct {
+                at_risk_usd += p.size_usd;
+                if !self.warned.contains(&key) {
+                    imminent.push((p.clone(), liq, distance_pct));
+                }
+            } else {
+                self.warned.remove(&key);
+            }
+        }
+        // Closed or liquidated positions are re-armed
+        self.warned
+            .retain(|(account, symbol, side)| *symbol != snap.symbol || open.contains(&(*account, *side)));
+
+        for (p, liq, distance_pct) in imminent {
+            self.warned.insert((p.account, snap.symbol.clone(), p.side));
+            let message = format!(
+                "account {} {:?} ${:.2} liq=${:.2} {:.2}% away",
+                p.account,
+                p.side,
+                p.size_usd as f64 / 1_000_000.0,
+                liq as f64 / 1_000_000.0,
+                distance_pct
+            );
+            self.alert(
+                sim,
+                "imminent_liquidation",
+                &snap.symbol,
+                Some(p.account),
+                distance_pct,
+                message,
+            );
+        }
+
+        let (top_account, top_usd) = per_account
+            .iter()
+            .max_by_key(|(account, usd)| (**usd, std::cmp::Reverse(**account)))
+            .map_or((None, 0), |(account, usd)| (Some(*account), *usd));
+        let share_of_oi = |usd: u64| {
+            if total_oi > 0 {
+                usd as f64 / total_oi as f64 * 100.0
+            } else {
+                0.0
+            }
+        };
+        let top_account_pct = share_of_oi(top_usd);
+
+        let liq_heatmap: Vec<LiqBucket> = buckets
+            .iter()
+            .map(|(k, (long_usd, short_usd))| LiqBucket {
+                price: (price + *k as f64 * bucket_width).max(0.0) as u64,
+                long_usd: *long_usd,
+                short_usd: *short_usd,
+            })
+            .collect();
+        let densest = liq_heatmap.iter().max_by_key(|b| b.long_usd + b.short_usd);
+
+        let var_pct = if snap.liquidity_usd > 0 {
+            var_usd as f64 / snap.liquidity_usd as f64 * 100.0
+        } else {
+            0.0
+        };
+        let symbol = snap.symbol.as_str();
+        self.check_threshold(
+            sim,
+            "var",
+            symbol,
+            var_pct,
+            self.config.var_alert_pct,
+            format!(
+                "VaR ${:.2} = {:.1}% of liquidity",
+                var_usd as f64 / 1_000_000.0,
+                var_pct
+            ),
+        );
+        if let Some(account) = top_account {
+            self.check_threshold(
+                sim,
+                "concentration",
+                symbol,
+                top_account_pct,
+                self.config.concentration_alert_pct,
+                format!("account {} holds {:.1}% of OI", account, top_account_pct),
+            );
+        }
+        let (cluster_price, cluster_usd) = densest.map_or((0, 0), |b| (b.price, b.long_usd + b.short_usd));
+        let cluster_pct = share_of_oi(cluster_usd);
+        self.check_threshold(
+            sim,
+            "liq_cluster",
+            symbol,
+            cluster_pct,
+            self.config.cluster_alert_pct,
+            format!(
+                "{:.1}% of OI liquidates near ${:.2}",
+                cluster_pct,
+                cluster_price as f64 / 1_000_000.0
+            ),
+        );
+
+        self.reports += 1;
+        let ts = sim.now_ns();
+        sim.emit_event(SimEvent::RiskReport {
+            ts,
+            symbol: snap.symbol.clone(),
+            price: mark,
+            oi_long_usd: snap.oi_long_usd,
+            oi_short_usd: snap.oi_short_usd,
+            liquidity_usd: snap.liquidity_usd as i64,
+            volatility_pct: volatility * 100.0,
+            var_usd,
+            top_account,
+            top_account_pct,
+            at_risk_usd,
+            liq_heatmap,
+        });
+    }
+}
+
+impl Agent for RiskMonitorAgent {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        let c = &mut self.config;
+        match (key, value.as_f64()) {
+            ("var_z", Some(v)) if v > 0.0 => c.var_z = v,
+            ("maintenance_margin", Some(v)) if (0.0..1.0).contains(&v) => c.maintenance_margin = v,
+            ("warn_distance_pct", Some(v)) if v > 0.0 => c.warn_distance_pct = v,
+            ("var_alert_pct", Some(v)) if v > 0.0 => c.var_alert_pct = v,
+            ("concentration_alert_pct", Some(v)) if v > 0.0 => c.concentration_alert_pct = v,
+            ("cluster_alert_pct", Some(v)) if v > 0.0 => c.cluster_alert_pct = v,
+            _ => return false,
+        }
+        agent_println!("[Risk {}] param {} = {}", self.name, key, value);
+        true
+    }
+
+    fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[Risk {}] START VaR {}s z={:.3} warn<{:.1}% bucket={}bps",
+            self.name,
+            self.config.var_horizon_sec,
+            self.config.var_z,
+            self.config.warn_distance_pct,
+            self.config.bucket_bps
+        );
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::SubscribeSnapshots,
+            MessagePayload::Empty,
+        );
+    }
+
+    fn on_wakeup(&mut self, _sim: &mut dyn SimulatorApi, _now_ns: u64) {}
+
+    fn on_message(&mut self, sim: &mut dyn SimulatorApi, msg: &Message) {
+        match &msg.payload {
+            MessagePayload::OracleTick(_) | MessagePayload::OracleBatchTick(_) => {
+                for p in msg.payload.oracle_ticks() {
+                    let mid = (p.price.min + p.price.max) / 2;
+                    self.record_price(&p.symbol, sim.now_ns(), mid);
+                }
+            }
+            MessagePayload::PositionsSnapshot(snap) => self.on_snapshot(sim, snap),
+            _ => {}
+        }
+    }
+
+    fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[Risk {}] STOP: reports={} alerts={} max_var=${:.2}",
+            self.name,
+            self.reports,
+            self.alerts,
+            self.max_var_usd as f64 / 1_000_000.0
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::test_support::{kernel_with_recorder, record_events, Emitted};
+
+    const EXCHANGE: AgentId = 1;
+    const RISK: AgentId = 50;
+    const USD: u64 = 1_000_000;
+
+    fn risk_monitor() -> RiskMonitorAgent {
+        RiskMonitorAgent::new(
+            RISK,
+            RiskMonitorConfig {
+                name: "risk".to_string(),
+                exchange_id: EXCHANGE,
+                var_horizon_sec: 3_600,
+                var_z: 2.326,
+                vol_window: 10,
+                maintenance_margin: 0.01,
+                bucket_bps: 100.0,
+                heatmap_range_pct: 20.0,
+                warn_distance_pct: 5.0,
+                var_alert_pct: 50.0,
+                concentration_alert_pct: 60.0,
+                cluster_alert_pct: 90.0,
+            },
+        )
+    }
+
+    fn position(account: AgentId, side: Side, size_usd: u64, leverage: u64) -> PositionInfo {
+        PositionInfo {
+            account,
+            side,
+            size_usd,
+            collateral: size_usd / leverage,
+            entry_price: 2_000 * USD,
+            liquidation_price: 0,
+        }
+    }
+
+    /// Snapshot at a $1900 mark with $20k of pool liquidity
+    fn snapshot(positions: Vec<PositionInfo>) -> Message {
+        let oi = |side: Side| -> u64 { positions.iter().filter(|p| p.side == side).map(|p| p.size_usd).sum() };
+        let snap = PositionsSnapshotPayload {
+            symbol: "ETH-USD".to_string(),
+            price: 1_900 * USD,
+            mark_price: 1_900 * USD,
+            oi_long_usd: oi(Side::Buy),
+            oi_short_usd: oi(Side::Sell),
+            liquidity_usd: 20_000 * USD as i128,
+            positions: positions.into(),
+        };
+        Message::new(
+            RISK,
+            EXCHANGE,
+            MessageType::PositionsSnapshot,
+            0,
+            MessagePayload::PositionsSnapshot(snap),
+        )
+    }
+
+    /// Alert kinds emitted since the last call, plus the reports
+    fn drain(emitted: &Emitted) -> (Vec<String>, Vec<SimEvent>) {
+        let mut alerts = Vec::new();
+        let mut reports = Vec::new();
+        for event in emitted.borrow_mut().drain(..) {
+            match event {
+                SimEvent::RiskAlert { kind, .. } => alerts.push(kind),
+                report => reports.push(report),
+            }
+        }
+        (alerts, reports)
+    }
+
+    #[test]
+    fn test_liquidation_price_from_margin() {
+        let long = position(5, Side::Buy, 10_000 * USD, 10);
+        let short = position(6, Side::Sell, 10_000 * USD, 10);
+        // 10% margin over a 1% maintenance margin leaves 9% of room either way
+        assert!(liquidation_price(&long, 0.01).abs_diff(1_820 * USD) <= 1);
+        assert!(liquidation_price(&short, 0.01).abs_diff(2_180 * USD) <= 1);
+        assert_eq!(liquidation_price(&position(5, Side::Buy, 0, 1), 0.01), 0);
+    }
+
+    #[test]
+    fn test_alerts_latch_and_rearm() {
+        let (mut kernel, _) = kernel_with_recorder(EXCHANGE);
+        let emitted = record_events(&mut kernel);
+        let mut risk = risk_monitor();
+        // The 10x long liquidates at $1820, 4.2% below the mark; the 2x short is far away
+        let long = position(5, Side::Buy, 10_000 * USD, 10);
+        let short = position(6, Side::Sell, 2_000 * USD, 2);
+
+        risk.on_message(&mut kernel, &snapshot(vec![long.clone(), short.clone()]));
+        let (alerts, reports) = drain(&emitted);
+        assert_eq!(alerts, ["imminent_liquidation", "concentration"]);
+        let [SimEvent::RiskReport {
+            var_usd,
+            top_account,
+            at_risk_usd,
+            liq_heatmap,
+            ..
+        }] = &reports[..]
+        else {
+            panic!("expected one report, got {:?}", reports);
+        };
+        assert_eq!((*var_usd, *top_account, *at_risk_usd), (0, Some(5), 10_000 * USD));
+        assert_eq!(liq_heatmap.len(), 1);
+        assert_eq!((liq_heatmap[0].long_usd, liq_heatmap[0].short_usd), (10_000 * USD, 0));
+
+        // Still above the thresholds: no repeated alerts
+        risk.on_message(&mut kernel, &snapshot(vec![long.clone(), short.clone()]));
+        assert!(drain(&emitted).0.is_empty());
+
+        // Once the long is gone it is re-armed; the short alone still holds all the OI
+        risk.on_message(&mut kernel, &snapshot(vec![short.clone()]));
+        assert!(drain(&emitted).0.is_empty());
+        risk.on_message(&mut kernel, &snapshot(vec![long, short]));
+        assert_eq!(drain(&emitted).0, ["imminent_liquidation"]);
+        assert_eq!((risk.reports, risk.alerts), (4, 3));
+    }
+
+    #[test]
+    fn test_var_scales_oracle_volatility_to_the_horizon() {
+        let (mut kernel, _) = kernel_with_recorder(EXCHANGE);
+        let emitted = record_events(&mut kernel);
+        let mut risk = risk_monitor();
+        // A 1% log move every second
+        for (sec, price) in [2_000, 2_020, 2_000, 2_020, 2_000].into_iter().enumerate() {
+            risk.record_price("ETH-USD", sec as u64 * 1_000_000_000, price * USD);
+        }
+        let sigma = 1.01f64.ln() * 60.0;
+        assert!((risk.horizon_volatility("ETH-USD") - sigma).abs() < 1e-9);
+
+        // $8k net long exposure: VaR ~$11.1k, over half the pool
+        let long = position(5, Side::Buy, 10_000 * USD, 2);
+        let short = position(6, Side::Sell, 2_000 * USD, 2);
+        risk.on_message(&mut kernel, &snapshot(vec![long, short]));
+        let (alerts, reports) = drain(&emitted);
+        assert!(alerts.iter().any(|kind| kind == "var"));
+        let [SimEvent::RiskReport { var_usd, .. }] = &reports[..] else {
+            panic!("expected one report, got {:?}", reports);
+        };
+        let expected = 2.326 * sigma * 8_000.0 * USD as f64;
+        assert!((*var_usd as f64 - expected).abs() < 1.0);
+    }
+}
@@ -0,0 +1,483 @@
+//! ScriptedStrategy - decision logic from a Rhai script loaded with the scenario.
+//!
+//! The script defines any of the hooks `on_start()`, `on_price(symbol, price)`,
+//! `on_fill(fill)`, `on_timer()`. State kept between calls lives in `this`, an
+//! object map seeded from the scenario `params`; timeline SetParam updates the
+//! keys already present there. The engine is sandboxed: no `eval`, no module
+//! imports, and every hook call is capped at `max_operations`.
+//!
+//! Script API (prices and amounts in USD, sides "long"/"short"):
+//!   now()                                   sim time in seconds
+//!   price(symbol)                           last oracle mid (0.0 if unknown)
+//!   position(symbol, side)                  #{qty, size_usd, leverage, opened_at} or ()
+//!   has_position(symbol)
+//!   regime(symbol)                          #{trending, ranging, high_vol, efficiency, volatility, zscore} or ()
+//!   open_orders()                           ids of resting orders
+//!   open(symbol, side, qty, leverage)       market order
+//!   close(symbol, side)                     close the whole position
+//!   limit(symbol, side, qty, leverage, price)
+//!   stop_loss(symbol, side, price) / take_profit(symbol, side, price)   close at the trigger
+//!   cancel(order_id)
+//!   print(text)                             log line tagged with the agent name
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use rhai::{Array, CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+
+use crate::agent_println;
+use crate::agents::strategy_agent::{ActionIntent, PositionView, Strategy, StrategyAgent, StrategyContext};
+use crate::indicators::Regime;
+use crate::messages::{ExecutionType, OrderExecutedPayload, OrderExecutionType, OrderId, OrderType, Side};
+
+pub type ScriptedAgent = StrategyAgent<ScriptedStrategy>;
+
+const HOOKS: [&str; 4] = ["on_start", "on_price", "on_fill", "on_timer"];
+
+#[derive(Debug, Clone)]
+pub struct ScriptedConfig {
+    pub name: String,
+    pub source: String,
+    /// Initial `this` of the script
+    pub params: serde_json::Value,
+    pub timer_interval_ms: Option<u64>,
+    /// Operation budget of a single hook call
+    pub max_operations: u64,
+}
+
+/// Copy of the strategy context the script API reads from, plus its output.
+#[derive(Default)]
+struct ScriptView {
+    now_ns: u64,
+    prices: HashMap<String, u64>,
+    positions: HashMap<(String, Side), PositionView>,
+    regimes: HashMap<String, Regime>,
+    open_orders: Vec<OrderId>,
+    intents: Vec<ActionIntent>,
+}
+
+type SharedView = Rc<RefCell<ScriptView>>;
+
+fn parse_side(side: &str) -> Result<Side, Box<EvalAltResult>> {
+    match side {
+        "long" | "buy" => Ok(Side::Buy),
+        "short" | "sell" => Ok(Side::Sell),
+        _ => Err(format!("unknown side '{}' (expected \"long\" or \"short\")", side).into()),
+    }
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "long",
+        Side::Sell => "short",
+    }
+}
+
+fn to_micro(usd: f64) -> u64 {
+    (usd * 1_000_000.0).round().max(0.0) as u64
+}
+
+fn to_usd(micro: i128) -> f64 {
+    micro as f64 / 1_000_000.0
+}
+
+fn conditional(
+    view: &SharedView,
+    symbol: &str,
+    side: &str,
+    kind: (OrderType, ExecutionType),
+    qty: f64,
+    leverage: i64,
+    price: f64,
+) -> Result<(), Box<EvalAltResult>> {
+    let side = parse_side(side)?;
+    if price <= 0.0 || (kind.0 == OrderType::Increase && qty <= 0.0) {
+        return Err("qty and price must be positive".into());
+    }
+    view.borrow_mut().intents.push(ActionIntent::Conditional {
+        symbol: symbol.to_string(),
+        side,
+        order_type: kind.0,
+        execution_type: kind.1,
+        qty,
+        leverage: leverage.max(1) as u32,
+        trigger_price: to_micro(price),
+    });
+    Ok(())
+}
+
+fn build_engine(name: &str, view: &SharedView, max_operations: u64) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.set_max_operations(max_operations);
+    engine.set_max_call_levels(32);
+    engine.set_max_string_size(64 * 1024);
+    engine.set_max_array_size(100_000);
+    engine.set_max_map_size(10_000);
+
+    let tag = name.to_string();
+    engine.on_print(move |text| agent_println!("[Script {}] {}", tag, text));
+    let tag = name.to_string();
+    engine.on_debug(move |text, _, _| agent_println!("[Script {}] {}", tag, text));
+
+    let v = view.clone();
+    engine.register_fn("now", move || v.borrow().now_ns as f64 / 1e9);
+    let v = view.clone();
+    engine.register_fn("price", move |symbol: &str| {
+        v.borrow().prices.get(symbol).map_or(0.0, |p| *p as f64 / 1_000_000.0)
+    });
+    let v = view.clone();
+    engine.register_fn(
+        "position",
+        move |symbol: &str, side: &str| -> Result<Dynamic, Box<EvalAltResult>> {
+            let side = parse_side(side)?;
+            let view = v.borrow();
+            let Some(position) = view.positions.get(&(symbol.to_string(), side)) else {
+                return Ok(Dynamic::UNIT);
+            };
+            let mut map = Map::new();
+            map.insert("qty".into(), Dynamic::from(position.qty));
+            map.insert("size_usd".into(), Dynamic::from(position.size_usd / 1_000_000.0));
+            map.insert("leverage".into(), Dynamic::from(position.leverage as i64));
+            map.insert("opened_at".into(), Dynamic::from(position.opened_at_ns as f64 / 1e9));
+            Ok(Dynamic::from_map(map))
+        },
+    );
+    let v = view.clone();
+    engine.register_fn("has_position", move |symbol: &str| {
+        v.borrow().positions.keys().any(|(s, _)| s == symbol)
+    });
+    let v = view.clone();
+    engine.register_fn("regime", move |symbol: &str| -> Dynamic {
+        let Some(regime) = v.borrow().regimes.get(symbol).copied() else {
+            return Dynamic::UNIT;
+        };
+        let mut map = Map::new();
+        map.insert("trending".into(), Dynamic::from(regime.trending));
+        map.insert("ranging".into(), Dynamic::from(regime.ranging));
+        map.insert("high_vol".into(), Dynamic::from(regime.high_vol));
+        map.insert("efficiency".into(), Dynamic::from(regime.efficiency));
+        map.insert("volatility".into(), Dynamic::from(regime.volatility));
+        map.insert("zscore".into(), Dynamic::from(regime.zscore));
+        Dynamic::from_map(map)
+    });
+    let v = view.clone();
+    engine.register_fn("open_orders", move || -> Array {
+        v.borrow()
+            .open_orders
+            .iter()
+            .map(|id| Dynamic::from(*id as i64))
+            .collect()
+    });
+
+    let v = view.clone();
+    engine.register_fn(
+        "open",
+        move |symbol: &str, side: &str, qty: f64, leverage: i64| -> Result<(), Box<EvalAltResult>> {
+            let side = parse_side(side)?;
+            if qty <= 0.0 {
+                return Err("qty must be positive".into());
+            }
+            v.borrow_mut().intents.push(ActionIntent::Open {
+                symbol: symbol.to_string(),
+                side,
+                qty,
+                leverage: leverage.max(1) as u32,
+            });
+            Ok(())
+        },
+    );
+    let v = view.clone();
+    engine.register_fn(
+        "close",
+        move |symbol: &str, side: &str| -> Result<(), Box<EvalAltResult>> {
+            let side = parse_side(side)?;
+            v.borrow_mut().intents.push(ActionIntent::Close {
+                symbol: symbol.to_string(),
+                side,
+            });
+            Ok(())
+        },
+    );
+    let v = view.clone();
+    engine.register_fn(
+        "limit",
+        move |symbol: &str, side: &str, qty: f64, leverage: i64, price: f64| {
+            let kind = (OrderType::Increase, ExecutionType::Limit);
+            conditional(&v, symbol, side, kind, qty, leverage, price)
+        },
+    );
+    let v = view.clone();
+    engine.register_fn("stop_loss", move |symbol: &str, side: &str, price: f64| {
+        let kind = (OrderType::Decrease, ExecutionType::StopLoss);
+        conditional(&v, symbol, side, kind, 0.0, 1, price)
+    });
+    let v = view.clone();
+    engine.register_fn("take_profit", move |symbol: &str, side: &str, price: f64| {
+        let kind = (OrderType::Decrease, ExecutionType::TakeProfit);
+        conditional(&v, symbol, side, kind, 0.0, 1, price)
+    });
+    let v = view.clone();
+    engine.register_fn("cancel", move |order_id: i64| {
+        v.borrow_mut().intents.push(ActionIntent::Cancel {
+            order_id: order_id as OrderId,
+        });
+    });
+    engine
+}
+
+pub struct ScriptedStrategy {
+    name: String,
+    engine: Engine,
+    ast: AST,
+    /// `this` of every hook call
+    state: Dynamic,
+    view: SharedView,
+    /// Hooks defined by the script
+    hooks: Vec<&'static str>,
+    timer_interval_ms: Option<u64>,
+
+    calls: u32,
+    errors: u32,
+}
+
+impl ScriptedStrategy {
+    /// Compile the script; errors carry Rhai's line/position.
+    pub fn new(config: ScriptedConfig) -> Result<Self, String> {
+        let view = SharedView::default();
+        let engine = build_engine(&config.name, &view, config.max_operations);
+        let ast = engine.compile(&config.source).map_err(|e| format!("script: {}", e))?;
+        let hooks: Vec<&'static str> = HOOKS
+            .into_iter()
+            .filter(|hook| ast.iter_functions().any(|f| f.name == *hook))
+            .collect();
+        if hooks.is_empty() {
+            return Err(format!("script defines none of {}", HOOKS.join(", ")));
+        }
+        let state = match &config.params {
+            serde_json::Value::Null => Dynamic::from_map(Map::new()),
+            params @ serde_json::Value::Object(_) => {
+                rhai::serde::to_dynamic(params).map_err(|e| format!("params: {}", e))?
+            }
+            _ => return Err("params must be an object".to_string()),
+        };
+        Ok(Self {
+            name: config.name,
+            engine,
+            ast,
+            state,
+            view,
+            hooks,
+            timer_interval_ms: config.timer_interval_ms,
+            calls: 0,
+            errors: 0,
+        })
+    }
+
+    /// Run one hook against a fresh copy of the context and collect its intents.
+    fn run(&mut self, ctx: &StrategyContext, hook: &'static str, args: impl rhai::FuncArgs) -> Vec<ActionIntent> {
+        if !self.hooks.contains(&hook) {
+            return Vec::new();
+        }
+        {
+            let mut view = self.view.borrow_mut();
+            view.now_ns = ctx.now_ns;
+            view.prices = ctx
+                .prices()
+                .map(|(symbol, price)| (symbol.to_string(), price))
+                .collect();
+            view.positions = ctx
+                .positions()
+                .map(|(symbol, side, position)| ((symbol.to_string(), side), position.clone()))
+                .collect();
+            view.regimes = ctx
+                .regimes()
+                .map(|(symbol, regime)| (symbol.to_string(), regime))
+                .collect();
+            view.open_orders = ctx.open_orders().to_vec();
+            view.intents.clear();
+        }
+        self.calls += 1;
+        let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.state);
+        let result = self
+            .engine
+            .call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &self.ast, hook, args);
+        let intents = std::mem::take(&mut self.view.borrow_mut().intents);
+        match result {
+            Ok(_) => intents,
+            Err(e) => {
+                // A failing call sends nothing; the script keeps running on later events
+                eprintln!("[Script {}] {} failed: {}", self.name, hook, e);
+                self.errors += 1;
+                Vec::new()
+            }
+        }
+    }
+}
+
+impl Strategy for ScriptedStrategy {
+    fn label(&self) -> &str {
+        "Script"
+    }
+
+    fn timer_interval_ms(&self) -> Option<u64> {
+        self.timer_interval_ms
+    }
+
+    fn on_start(&mut self, ctx: &StrategyContext) -> Vec<ActionIntent> {
+        self.run(ctx, "on_start", ())
+    }
+
+    fn on_price(&mut self, ctx: &StrategyContext, symbol: &str, price: u64) -> Vec<ActionIntent> {
+        self.run(ctx, "on_price", (symbol.to_string(), price as f64 / 1_000_000.0))
+    }
+
+    fn on_fill(&mut self, ctx: &StrategyContext, fill: &OrderExecutedPayload) -> Vec<ActionIntent> {
+        let kind = match fill.order_type {
+            OrderExecutionType::Increase => "increase",
+            OrderExecutionType::Decrease => "decrease",
+            OrderExecutionType::Liquidation => "liquidation",
+        };
+        let mut map = Map::new();
+        map.insert("symbol".into(), Dynamic::from(fill.symbol.clone()));
+        map.insert("side".into(), Dynamic::from(side_name(fill.side).to_string()));
+        map.insert("kind".into(), Dynamic::from(kind.to_string()));
+        map.insert("size_usd".into(), Dynamic::from(to_usd(fill.size_usd)));
+        map.insert("pnl_usd".into(), Dynamic::from(to_usd(fill.pnl)));
+        map.insert("collateral_usd".into(), Dynamic::from(to_usd(fill.collateral_delta)));
+        self.run(ctx, "on_fill", (Dynamic::from_map(map),))
+    }
+
+    fn on_timer(&mut self, ctx: &StrategyContext) -> Vec<ActionIntent> {
+        self.run(ctx, "on_timer", ())
+    }
+
+    /// Only keys the script already keeps in `this` can be changed.
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        let Ok(value) = rhai::serde::to_dynamic(value) else {
+            return false;
+        };
+        match self.state.write_lock::<Map>() {
+            Some(mut state) if state.contains_key(key) => {
+                state.insert(key.into(), value);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "hooks={} calls={} errors={}",
+            self.hooks.join(","),
+            self.calls,
+            self.errors
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::test_support::{delivered, kernel_with_recorder, oracle_tick};
+    use crate::agents::Agent;
+    use crate::messages::{AgentId, Message, MessagePayload};
+    use crate::qty::Qty;
+
+    const EXCHANGE: AgentId = 1;
+    const SCRIPTED: AgentId = 70;
+
+    fn config(source: &str, params: serde_json::Value) -> ScriptedConfig {
+        ScriptedConfig {
+            name: "script".to_string(),
+            source: source.to_string(),
+            params,
+            timer_interval_ms: None,
+            max_operations: 10_000,
+        }
+    }
+
+    fn scripted(source: &str, params: serde_json::Value) -> ScriptedAgent {
+        let strategy = ScriptedStrategy::new(config(source, params)).unwrap();
+        ScriptedAgent::new(SCRIPTED, "script".to_string(), EXCHANGE, strategy)
+    }
+
+    #[test]
+    fn test_rejects_bad_scripts_and_params() {
+        let new = |source: &str, params| ScriptedStrategy::new(config(source, params)).err();
+        assert!(new("fn on_price(symbol, price) {", serde_json::Value::Null).is_some_and(|e| e.starts_with("script:")));
+        assert!(new("fn helper() { 1 }", serde_json::Value::Null).is_some_and(|e| e.contains("none of")));
+        assert!(new("fn on_start() {}", serde_json::json!([1, 2])).is_some());
+        // `eval` is disabled at compile time
+        assert!(new(
+            r#"fn on_start() { eval("open(\"ETH-USD\", \"long\", 1.0, 1)") }"#,
+            serde_json::Value::Null
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn test_hooks_keep_state_in_this_and_emit_orders() {
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let source = r#"
+            fn on_price(symbol, price) {
+                this.ticks += 1;
+                if this.ticks == 2 {
+                    open(symbol, "long", this.qty, 3);
+                    stop_loss(symbol, "long", price * 0.9);
+                }
+            }
+        "#;
+        let mut agent = scripted(source, serde_json::json!({ "ticks": 0, "qty": 0.5 }));
+
+        agent.on_message(&mut kernel, &oracle_tick(SCRIPTED, "ETH-USD", 2_000_000_000));
+        assert!(delivered(&mut kernel, &exchange).is_empty());
+        agent.on_message(&mut kernel, &oracle_tick(SCRIPTED, "ETH-USD", 2_000_000_000));
+        let sent = delivered(&mut kernel, &exchange);
+        let [Message {
+            payload: MessagePayload::MarketOrder(open),
+            ..
+        }, Message {
+            payload: MessagePayload::Order(stop),
+            ..
+        }] = &sent[..]
+        else {
+            panic!("expected an open and a stop loss, got {:?}", sent);
+        };
+        assert_eq!(
+            (open.side, open.qty, open.leverage),
+            (Side::Buy, Qty::from_tokens(0.5), 3)
+        );
+        assert_eq!(stop.execution_type, ExecutionType::StopLoss);
+        assert_eq!(stop.trigger_price, Some(1_800_000_000));
+
+        // SetParam only reaches keys the script keeps in `this`
+        assert!(agent.set_param("qty", &serde_json::json!(1.0)));
+        assert!(!agent.set_param("speed", &serde_json::json!(1.0)));
+    }
+
+    #[test]
+    fn test_runaway_hooks_are_cut_off_and_send_nothing() {
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let source = r#"
+            fn on_price(symbol, price) {
+                open(symbol, "long", 1.0, 2);
+                if this.spin {
+                    loop {}
+                }
+            }
+        "#;
+        let mut agent = scripted(source, serde_json::json!({ "spin": true }));
+
+        // The operation budget stops the loop; the open queued before it is dropped
+        agent.on_message(&mut kernel, &oracle_tick(SCRIPTED, "ETH-USD", 2_000_000_000));
+        assert!(delivered(&mut kernel, &exchange).is_empty());
+
+        // The script keeps running on later events
+        assert!(agent.set_param("spin", &serde_json::json!(false)));
+        agent.on_message(&mut kernel, &oracle_tick(SCRIPTED, "ETH-USD", 2_000_000_000));
+        assert_eq!(delivered(&mut kernel, &exchange).len(), 1);
+    }
+}
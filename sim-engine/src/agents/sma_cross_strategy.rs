@@ -0,0 +1,119 @@
+//! SmaCrossStrategy - moving-average crossover on one market.
+//!
+//! Reference `Strategy` for `StrategyAgent`: goes long when the fast SMA of
+//! the oracle mid crosses above the slow one, short when it crosses below,
+//! always closing the opposite position first. With `trend_filter` crosses
+//! only open a position in a trending regime.
+
+use crate::agents::strategy_agent::{ActionIntent, Strategy, StrategyContext};
+use crate::indicators::Sma;
+use crate::messages::Side;
+
+#[derive(Debug, Clone)]
+pub struct SmaCrossConfig {
+    pub symbol: String,
+    /// Window lengths in oracle ticks
+    pub fast: usize,
+    pub slow: usize,
+    pub qty: f64,
+    pub leverage: u32,
+    /// Skip entries while the market is ranging (closes still happen)
+    pub trend_filter: bool,
+}
+
+pub struct SmaCrossStrategy {
+    symbol: String,
+    fast_period: usize,
+    slow_period: usize,
+    qty: f64,
+    leverage: u32,
+    trend_filter: bool,
+
+    fast: Sma,
+    slow: Sma,
+    /// Sign of (fast - slow) at the previous tick
+    last_trend: Option<bool>,
+    crosses: u32,
+}
+
+impl SmaCrossStrategy {
+    pub fn new(config: SmaCrossConfig) -> Self {
+        let fast_period = config.fast.max(1);
+        let slow_period = config.slow.max(fast_period + 1);
+        Self {
+            symbol: config.symbol,
+            fast_period,
+            slow_period,
+            qty: config.qty,
+            leverage: config.leverage.max(1),
+            trend_filter: config.trend_filter,
+            fast: Sma::new(fast_period),
+            slow: Sma::new(slow_period),
+            last_trend: None,
+            crosses: 0,
+        }
+    }
+}
+
+impl Strategy for SmaCrossStrategy {
+    fn label(&self) -> &str {
+        "SMA"
+    }
+
+    fn on_price(&mut self, ctx: &StrategyContext, symbol: &str, price: u64) -> Vec<ActionIntent> {
+        if symbol != self.symbol {
+            return Vec::new();
+        }
+        let fast = self.fast.update(price as f64);
+        let (Some(fast), Some(slow)) = (fast, self.slow.update(price as f64)) else {
+            return Vec::new();
+        };
+
+        let up = fast > slow;
+        let crossed = self.last_trend.is_some_and(|last| last != up);
+        self.last_trend = Some(up);
+        if !crossed {
+            return Vec::new();
+        }
+        self.crosses += 1;
+
+        let (side, opposite) = if up {
+            (Side::Buy, Side::Sell)
+        } else {
+            (Side::Sell, Side::Buy)
+        };
+        let mut intents = Vec::new();
+        if ctx.position(&self.symbol, opposite).is_some() {
+            intents.push(ActionIntent::Close {
+                symbol: self.symbol.clone(),
+                side: opposite,
+            });
+        }
+        let ranging = self.trend_filter && ctx.regime(&self.symbol).is_none_or(|r| r.ranging);
+        if ctx.position(&self.symbol, side).is_none() && !ranging {
+            intents.push(ActionIntent::Open {
+                symbol: self.symbol.clone(),
+                side,
+                qty: self.qty,
+                leverage: self.leverage,
+            });
+        }
+        intents
+    }
+
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        match (key, value.as_f64()) {
+            ("qty", Some(v)) if v > 0.0 => self.qty = v,
+            ("leverage", Some(v)) if v >= 1.0 => self.leverage = v as u32,
+            _ => return false,
+        }
+        true
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{} sma {}/{} crosses={}",
+            self.symbol, self.fast_period, self.slow_period, self.crosses
+        )
+    }
+}
@@ -1,11 +1,13 @@
+use crate::agent_println;
 use crate::agents::Agent;
+use crate::indicators::Sma;
 use crate::messages::{
-    AgentId, CloseOrderPayload, ExecutionType, MarketOrderPayload, MarketStatePayload, Message, MessagePayload,
-    MessageType, OracleTickPayload, OrderExecutedPayload, OrderExecutionType, OrderPayload, OrderType,
+    AgentId, CloseOrderPayload, ExecutionType, MarketOrderPayload, MarketStatePayload, Message,
+    MessagePayload, MessageType, OracleTickPayload, OrderExecutedPayload, OrderExecutionType, OrderPayload, OrderType,
     PositionLiquidatedPayload, Side, SimulatorApi,
 };
+use crate::qty::Qty;
 use std::collections::hash_map::DefaultHasher;
-use std::collections::VecDeque;
 use std::hash::{Hash, Hasher};
 
 const DEFAULT_BALANCE: i128 = 50_000_000_000; // $50,000
@@ -94,8 +96,6 @@ pub struct SmartTraderConfig {
     pub qty_max: f64,
     pub wake_interval_ms: u64,
     pub balance: Option<i128>,
-    /// NEW: Delay before first trade (for staggered start)
-    pub start_delay_ms: Option<u64>,
 }
 
 pub struct SmartTraderAgent {
@@ -108,7 +108,10 @@ pub struct SmartTraderAgent {
     qty_min: f64,
     qty_max: f64,
     wake_interval_ns: u64,
-    start_delay_ns: u64, // NEW: staggered start
+    /// Spread of the first trades of a cohort started together
+    start_delay_ns: u64,
+    /// Scenario seed mixed into pseudo-random choices (Monte Carlo repetitions)
+    seed: u64,
 
     has_position: bool,
     position_side: Option<Side>,
@@ -119,7 +122,8 @@ pub struct SmartTraderAgent {
     balance: i128,
     collateral_in_position: i128,
 
-    price_history: VecDeque<(u64, u64)>,
+    /// Mean of the last `lookback_periods` ticks (MeanReversion only)
+    sma: Option<Sma>,
     current_price: Option<u64>,
 
     // OI tracking for Arbitrageur/FundingHarvester
@@ -137,15 +141,18 @@ pub struct SmartTraderAgent {
     pending_sl_order_id: Option<u64>,
     pending_tp_order_id: Option<u64>,
     use_conditional_sl_tp: bool,
+    /// OCO group of the last SL/TP pair
+    oco_group: u64,
 }
 
 impl SmartTraderAgent {
     pub fn new(id: AgentId, config: SmartTraderConfig) -> Self {
-        // Calculate staggered start delay
-        // Base delay + spread based on agent ID
-        let base_delay = config.start_delay_ms.unwrap_or(0) * 1_000_000;
-        let id_spread = (id as u64 % 30) * 200_000_000; // 0-6 seconds spread
-        let start_delay_ns = base_delay + id_spread;
+        // Staggered first trade based on agent ID (late joins: see `Kernel::set_agent_window`)
+        let start_delay_ns = (id as u64 % 30) * 200_000_000; // 0-6 seconds spread
+        let sma = match &config.strategy {
+            TradingStrategy::MeanReversion { lookback_periods, .. } => Some(Sma::new(*lookback_periods as usize)),
+            _ => None,
+        };
 
         Self {
             id,
@@ -158,6 +165,7 @@ impl SmartTraderAgent {
             qty_max: config.qty_max.max(config.qty_min),
             wake_interval_ns: config.wake_interval_ms * 1_000_000,
             start_delay_ns,
+            seed: 0,
             has_position: false,
             position_side: None,
             position_opened_at: 0,
@@ -165,7 +173,7 @@ impl SmartTraderAgent {
             last_close_at: 0,
             balance: config.balance.unwrap_or(DEFAULT_BALANCE),
             collateral_in_position: 0,
-            price_history: VecDeque::with_capacity(200),
+            sma,
             current_price: None,
             oi_long_usd: 0,
             oi_short_usd: 0,
@@ -178,6 +186,7 @@ impl SmartTraderAgent {
             pending_sl_order_id: None,
             pending_tp_order_id: None,
             use_conditional_sl_tp: true,
+            oco_group: 0,
         }
     }
 
@@ -185,12 +194,27 @@ impl SmartTraderAgent {
         self.address = Some(address);
     }
 
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    fn set_leverage(&mut self, new_leverage: u32) {
+        match &mut self.strategy {
+            TradingStrategy::Hodler { leverage, .. }
+            | TradingStrategy::Institutional { leverage, .. }
+            | TradingStrategy::TrendFollower { leverage, .. }
+            | TradingStrategy::MeanReversion { leverage, .. }
+            | TradingStrategy::Arbitrageur { leverage, .. }
+            | TradingStrategy::FundingHarvester { leverage, .. } => *leverage = new_leverage,
+        }
+    }
+
     fn random_qty(&self, now_ns: u64) -> f64 {
         if (self.qty_max - self.qty_min).abs() < 0.001 {
             return self.qty_min.max(0.01);
         }
         let mut hasher = DefaultHasher::new();
-        (now_ns, self.id, self.trades_opened).hash(&mut hasher);
+        (self.seed, now_ns, self.id, self.trades_opened).hash(&mut hasher);
         let hash = hasher.finish();
         let range = self.qty_max - self.qty_min;
         let fraction = (hash % 1000) as f64 / 1000.0;
@@ -218,20 +242,6 @@ impl SmartTraderAgent {
         })
     }
 
-    fn calculate_sma(&self, periods: u32) -> Option<f64> {
-        if self.price_history.len() < periods as usize {
-            return None;
-        }
-        let sum: u64 = self
-            .price_history
-            .iter()
-            .rev()
-            .take(periods as usize)
-            .map(|(_, price)| *price)
-            .sum();
-        Some(sum as f64 / periods as f64)
-    }
-
     /// Calculate OI imbalance percentage
     /// Positive = long-heavy, Negative = short-heavy
     fn calculate_oi_imbalance_pct(&self) -> f64 {
@@ -326,7 +336,7 @@ impl SmartTraderAgent {
         if !is_safe {
             self.skipped_due_to_oi += 1;
             if self.skipped_due_to_oi <= 3 || self.skipped_due_to_oi % 20 == 0 {
-                println!(
+                agent_println!(
                     "[{}] SKIP {} - {} (OI: L=${:.0}k S=${:.0}k, imb={:.1}%)",
                     self.name,
                     if side == Side::Buy { "LONG" } else { "SHORT" },
@@ -363,7 +373,7 @@ impl SmartTraderAgent {
             return;
         }
 
-        println!(
+        agent_println!(
             "[{}] OPEN {} {}x qty={:.3} @ ${:.2} (OI: L=${:.0}k S=${:.0}k)",
             self.name,
             if side == Side::Buy { "LONG" } else { "SHORT" },
@@ -382,8 +392,9 @@ impl SmartTraderAgent {
             MessagePayload::MarketOrder(MarketOrderPayload {
                 symbol: self.symbol.clone(),
                 side,
-                qty: qty_tokens,
+                qty: Qty::from_tokens(qty_tokens),
                 leverage,
+                decision_price: sim.market_data().mid(&self.symbol),
             }),
         );
 
@@ -401,7 +412,7 @@ impl SmartTraderAgent {
         if let Some(side) = self.position_side {
             let pnl_pct = self.calculate_pnl_pct().unwrap_or(0.0);
 
-            println!(
+            agent_println!(
                 "[{}] CLOSE {} ({}) pnl={:+.2}%",
                 self.name,
                 if side == Side::Buy { "LONG" } else { "SHORT" },
@@ -417,6 +428,7 @@ impl SmartTraderAgent {
                 MessagePayload::CloseOrder(CloseOrderPayload {
                     symbol: self.symbol.clone(),
                     side,
+                    decision_price: sim.market_data().mid(&self.symbol),
                 }),
             );
 
@@ -461,6 +473,8 @@ impl SmartTraderAgent {
             Some(s) => s,
             None => return,
         };
+        // Linked: whichever leg executes, the exchange cancels the other
+        self.oco_group += 1;
 
         if sl_pct > 0.0 {
             let sl_price = match side {
@@ -478,7 +492,10 @@ impl SmartTraderAgent {
                 size_delta_usd: None,
                 trigger_price: Some(sl_price),
                 acceptable_price: None,
+                trailing_distance: None,
                 valid_for_sec: Some(86400),
+                oco_group: Some(self.oco_group),
+                decision_price: None,
             };
 
             sim.send(
@@ -505,7 +522,10 @@ impl SmartTraderAgent {
                 size_delta_usd: None,
                 trigger_price: Some(tp_price),
                 acceptable_price: None,
+                trailing_distance: None,
                 valid_for_sec: Some(86400),
+                oco_group: Some(self.oco_group),
+                decision_price: None,
             };
 
             sim.send(
@@ -544,7 +564,7 @@ impl SmartTraderAgent {
     }
 
     fn handle_liquidation(&mut self, payload: &PositionLiquidatedPayload) {
-        println!(
+        agent_println!(
             "[{}] LIQUIDATED {} pnl=${:.2}",
             self.name,
             if payload.side == Side::Buy { "LONG" } else { "SHORT" },
@@ -674,13 +694,13 @@ impl SmartTraderAgent {
                 }
             }
 
-            if self.price_history.len() < 2 {
+            let Some(history) = sim.market_data().history(&self.symbol).filter(|h| h.len() >= 2) else {
                 return;
-            }
+            };
 
             let lookback_ns = *lookback_sec * 1_000_000_000;
             let cutoff = now_ns.saturating_sub(lookback_ns);
-            let old_price = self.price_history.iter().find(|(ts, _)| *ts >= cutoff).map(|(_, p)| *p);
+            let old_price = history.first_since(cutoff).map(|p| p.mid());
 
             let current = match self.current_price {
                 Some(p) => p,
@@ -713,7 +733,6 @@ impl SmartTraderAgent {
 
     fn execute_mean_reversion(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
         if let TradingStrategy::MeanReversion {
-            lookback_periods,
             entry_deviation_pct,
             exit_deviation_pct,
             max_hold_sec,
@@ -724,7 +743,7 @@ impl SmartTraderAgent {
                 Some(p) => p as f64,
                 None => return,
             };
-            let sma = match self.calculate_sma(*lookback_periods) {
+            let sma = match self.sma.as_ref().and_then(Sma::value) {
                 Some(s) => s,
                 None => return,
             };
@@ -808,10 +827,10 @@ impl SmartTraderAgent {
 
                 // Long-heavy → open SHORT, Short-heavy → open LONG
                 if imbalance > *min_imbalance_pct {
-                    println!("[{}] ARB: Long-heavy {:.1}%, opening SHORT", self.name, imbalance);
+                    agent_println!("[{}] ARB: Long-heavy {:.1}%, opening SHORT", self.name, imbalance);
                     self.open_position(sim, Side::Sell, now_ns);
                 } else if imbalance < -*min_imbalance_pct {
-                    println!("[{}] ARB: Short-heavy {:.1}%, opening LONG", self.name, imbalance);
+                    agent_println!("[{}] ARB: Short-heavy {:.1}%, opening LONG", self.name, imbalance);
                     self.open_position(sim, Side::Buy, now_ns);
                 }
             }
@@ -873,7 +892,7 @@ impl SmartTraderAgent {
                 }
 
                 if let Some(side) = self.get_smaller_oi_side() {
-                    println!(
+                    agent_println!(
                         "[{}] FUNDING: Imbalance {:.1}%, joining {} side",
                         self.name,
                         self.calculate_oi_imbalance_pct(),
@@ -895,6 +914,21 @@ impl Agent for SmartTraderAgent {
         &self.name
     }
 
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        match (key, value.as_f64()) {
+            ("qty_min", Some(v)) if v > 0.0 => {
+                self.qty_min = v;
+                self.qty_max = self.qty_max.max(v);
+            }
+            ("qty_max", Some(v)) if v > 0.0 => self.qty_max = v.max(self.qty_min),
+            ("wake_interval_ms", Some(v)) if v >= 1.0 => self.wake_interval_ns = v as u64 * 1_000_000,
+            ("leverage", Some(v)) if v >= 1.0 => self.set_leverage(v as u32),
+            _ => return false,
+        }
+        agent_println!("[{}] param {} = {}", self.name, key, value);
+        true
+    }
+
     fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
         let strategy = match &self.strategy {
             TradingStrategy::Hodler { side, leverage, .. } => format!("Hodler({:?},{}x)", side, leverage),
@@ -905,10 +939,9 @@ impl Agent for SmartTraderAgent {
             TradingStrategy::FundingHarvester { leverage, .. } => format!("FundHarv({}x)", leverage),
         };
 
-        // NEW: Apply staggered start
         let delay_ms = self.start_delay_ns / 1_000_000;
         if delay_ms > 0 {
-            println!(
+            agent_println!(
                 "[{}] START {} bal=${:.0}{} (delayed {}ms)",
                 self.name,
                 strategy,
@@ -920,7 +953,7 @@ impl Agent for SmartTraderAgent {
                 delay_ms
             );
         } else {
-            println!(
+            agent_println!(
                 "[{}] START {} bal=${:.0}{}",
                 self.name,
                 strategy,
@@ -949,15 +982,12 @@ impl Agent for SmartTraderAgent {
 
     fn on_message(&mut self, sim: &mut dyn SimulatorApi, msg: &Message) {
         match msg.msg_type {
-            MessageType::OracleTick => {
-                if let MessagePayload::OracleTick(OracleTickPayload { symbol, price, .. }) = &msg.payload {
-                    if *symbol == self.symbol {
-                        let mid = (price.min + price.max) / 2;
-                        self.current_price = Some(mid);
-                        self.price_history.push_back((msg.at, mid));
-                        if self.price_history.len() > 200 {
-                            self.price_history.pop_front();
-                        }
+            MessageType::OracleTick | MessageType::OracleBatchTick => {
+                if let Some(OracleTickPayload { price, .. }) = msg.payload.oracle_tick(&self.symbol) {
+                    let mid = (price.min + price.max) / 2;
+                    self.current_price = Some(mid);
+                    if let Some(sma) = self.sma.as_mut() {
+                        sma.update(mid as f64);
                     }
                 }
             }
@@ -1010,8 +1040,8 @@ impl Agent for SmartTraderAgent {
                     self.pending_tp_order_id = None;
                 }
             }
-            MessageType::OrderTriggered | MessageType::OrderCancelled => {
-                // SL/TP was triggered or cancelled - position state already handled
+            MessageType::OrderTriggered | MessageType::OrderCancelled | MessageType::OrderExpired => {
+                // SL/TP was triggered, cancelled or expired - position state already handled
             }
             _ => {}
         }
@@ -1023,7 +1053,7 @@ impl Agent for SmartTraderAgent {
         } else {
             format!("-${:.2}", (-self.total_pnl) as f64 / 1_000_000.0)
         };
-        println!(
+        agent_println!(
             "[{}] STOP: open={} close={} liq={} skip={} pnl={} bal=${:.0}",
             self.name,
             self.trades_opened,
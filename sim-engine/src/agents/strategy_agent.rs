@@ -0,0 +1,700 @@
+//! Strategy plug-in API.
+//!
+//! A `Strategy` only decides: it sees prices, fills and timer ticks through a
+//! read-only `StrategyContext` and answers with `ActionIntent`s. The generic
+//! `StrategyAgent<S>` owns the Agent plumbing shared by all traders: message
+//! dispatch, price book, optimistic position tracking (rolled back on
+//! OrderRejected, cleared on closes and liquidations), resting order ids,
+//! per-market regime flags, the exchange's MarketStats, timer wakeups, SetParam
+//! routing and the START/STOP lines. A new strategy is a small struct implementing the hooks it needs.
+
+use std::collections::HashMap;
+
+use crate::agent_println;
+use crate::agents::Agent;
+use crate::indicators::{Regime, RegimeDetector};
+use crate::messages::{
+    AgentId, CancelOrderPayload, CloseOrderPayload, ExecutionType, MarketOrderPayload,
+    MarketStatsPayload, Message, MessagePayload, MessageType, OrderExecutedPayload, OrderExecutionType, OrderId,
+    OrderPayload, OrderType, Side, SimulatorApi,
+};
+use crate::qty::Qty;
+
+/// Oracle ticks per regime window
+const REGIME_WINDOW: usize = 30;
+
+/// What a strategy wants the agent to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActionIntent {
+    /// Market order opening or adding to the position on `side`
+    Open {
+        symbol: String,
+        side: Side,
+        qty: f64,
+        leverage: u32,
+    },
+    /// Close the whole position on `side`
+    Close { symbol: String, side: Side },
+    /// Limit / stop-loss / take-profit order resting at `trigger_price`
+    /// (`qty` and `leverage` are ignored for Decrease: it closes the whole position)
+    Conditional {
+        symbol: String,
+        side: Side,
+        order_type: OrderType,
+        execution_type: ExecutionType,
+        qty: f64,
+        leverage: u32,
+        trigger_price: u64,
+    },
+    /// Cancel a resting order (ids are listed in `StrategyContext::open_orders`)
+    Cancel { order_id: OrderId },
+}
+
+/// Position as tracked by the agent (notional estimated at the order's price).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PositionView {
+    pub qty: f64,
+    pub size_usd: f64,
+    pub leverage: u32,
+    pub opened_at_ns: u64,
+}
+
+/// Read-only view handed to every strategy hook.
+pub struct StrategyContext<'a> {
+    pub now_ns: u64,
+    prices: &'a HashMap<String, u64>,
+    positions: &'a HashMap<(String, Side), PositionView>,
+    open_orders: &'a [OrderId],
+    regimes: &'a HashMap<String, RegimeDetector>,
+    market_stats: &'a HashMap<String, MarketStatsPayload>,
+}
+
+impl StrategyContext<'_> {
+    /// Last oracle mid price (micro-USD)
+    pub fn price(&self, symbol: &str) -> Option<u64> {
+        self.prices.get(symbol).copied()
+    }
+
+    /// Trending / ranging / high-vol flags over the last oracle ticks (None while warming up)
+    pub fn regime(&self, symbol: &str) -> Option<Regime> {
+        self.regimes.get(symbol).and_then(RegimeDetector::regime)
+    }
+
+    /// Taker flow of the last MarketStats broadcast (None before the first one)
+    pub fn market_stats(&self, symbol: &str) -> Option<&MarketStatsPayload> {
+        self.market_stats.get(symbol)
+    }
+
+    pub fn position(&self, symbol: &str, side: Side) -> Option<&PositionView> {
+        self.positions.get(&(symbol.to_string(), side))
+    }
+
+    #[allow(dead_code)]
+    pub fn has_position(&self, symbol: &str) -> bool {
+        self.positions.keys().any(|(s, _)| s == symbol)
+    }
+
+    /// Acknowledged conditional orders with a known id, oldest first
+    pub fn open_orders(&self) -> &[OrderId] {
+        self.open_orders
+    }
+
+    pub fn prices(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.prices.iter().map(|(symbol, price)| (symbol.as_str(), *price))
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = (&str, Side, &PositionView)> {
+        self.positions
+            .iter()
+            .map(|((symbol, side), position)| (symbol.as_str(), *side, position))
+    }
+
+    pub fn regimes(&self) -> impl Iterator<Item = (&str, Regime)> {
+        self.regimes
+            .iter()
+            .filter_map(|(symbol, detector)| Some((symbol.as_str(), detector.regime()?)))
+    }
+}
+
+/// Decision logic of a trading agent.
+pub trait Strategy {
+    /// Short tag used in log lines
+    fn label(&self) -> &str;
+
+    /// Interval of `on_timer` calls (None = no timer)
+    fn timer_interval_ms(&self) -> Option<u64> {
+        None
+    }
+
+    fn on_start(&mut self, _ctx: &StrategyContext) -> Vec<ActionIntent> {
+        Vec::new()
+    }
+
+    /// Oracle mid price update
+    fn on_price(&mut self, _ctx: &StrategyContext, _symbol: &str, _price: u64) -> Vec<ActionIntent> {
+        Vec::new()
+    }
+
+    /// Executed order; liquidations arrive with `OrderExecutionType::Liquidation`
+    fn on_fill(&mut self, _ctx: &StrategyContext, _fill: &OrderExecutedPayload) -> Vec<ActionIntent> {
+        Vec::new()
+    }
+
+    fn on_timer(&mut self, _ctx: &StrategyContext) -> Vec<ActionIntent> {
+        Vec::new()
+    }
+
+    /// Runtime parameter change from the scenario timeline
+    fn set_param(&mut self, _key: &str, _value: &serde_json::Value) -> bool {
+        false
+    }
+
+    /// Extra text for the STOP line
+    fn summary(&self) -> String {
+        String::new()
+    }
+}
+
+impl<S: Strategy + ?Sized> Strategy for Box<S> {
+    fn label(&self) -> &str {
+        (**self).label()
+    }
+
+    fn timer_interval_ms(&self) -> Option<u64> {
+        (**self).timer_interval_ms()
+    }
+
+    fn on_start(&mut self, ctx: &StrategyContext) -> Vec<ActionIntent> {
+        (**self).on_start(ctx)
+    }
+
+    fn on_price(&mut self, ctx: &StrategyContext, symbol: &str, price: u64) -> Vec<ActionIntent> {
+        (**self).on_price(ctx, symbol, price)
+    }
+
+    fn on_fill(&mut self, ctx: &StrategyContext, fill: &OrderExecutedPayload) -> Vec<ActionIntent> {
+        (**self).on_fill(ctx, fill)
+    }
+
+    fn on_timer(&mut self, ctx: &StrategyContext) -> Vec<ActionIntent> {
+        (**self).on_timer(ctx)
+    }
+
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        (**self).set_param(key, value)
+    }
+
+    fn summary(&self) -> String {
+        (**self).summary()
+    }
+}
+
+/// Hook that produced a batch of intents
+enum Hook<'a> {
+    Start,
+    Price(&'a str, u64),
+    Fill(&'a OrderExecutedPayload),
+    Timer,
+}
+
+pub struct StrategyAgent<S: Strategy> {
+    id: AgentId,
+    name: String,
+    exchange_id: AgentId,
+    address: Option<String>,
+    strategy: S,
+
+    prices: HashMap<String, u64>,
+    regimes: HashMap<String, RegimeDetector>,
+    market_stats: HashMap<String, MarketStatsPayload>,
+    positions: HashMap<(String, Side), PositionView>,
+    /// Latest market open and the position before it, rolled back on OrderRejected
+    last_open: Option<((String, Side), Option<PositionView>)>,
+    open_orders: Vec<OrderId>,
+    /// Conditional orders waiting for OrderPending
+    unacked_orders: u32,
+
+    orders_sent: u32,
+    rejected: u32,
+    liquidations: u32,
+}
+
+impl<S: Strategy> StrategyAgent<S> {
+    pub fn new(id: AgentId, name: String, exchange_id: AgentId, strategy: S) -> Self {
+        Self {
+            id,
+            name,
+            exchange_id,
+            address: None,
+            strategy,
+            prices: HashMap::new(),
+            regimes: HashMap::new(),
+            market_stats: HashMap::new(),
+            positions: HashMap::new(),
+            last_open: None,
+            open_orders: Vec::new(),
+            unacked_orders: 0,
+            orders_sent: 0,
+            rejected: 0,
+            liquidations: 0,
+        }
+    }
+
+    pub fn set_address(&mut self, address: String) {
+        self.address = Some(address);
+    }
+
+    fn call(&mut self, sim: &mut dyn SimulatorApi, hook: Hook<'_>) {
+        let ctx = StrategyContext {
+            now_ns: sim.now_ns(),
+            prices: &self.prices,
+            positions: &self.positions,
+            open_orders: &self.open_orders,
+            regimes: &self.regimes,
+            market_stats: &self.market_stats,
+        };
+        let intents = match hook {
+            Hook::Start => self.strategy.on_start(&ctx),
+            Hook::Price(symbol, price) => self.strategy.on_price(&ctx, symbol, price),
+            Hook::Fill(fill) => self.strategy.on_fill(&ctx, fill),
+            Hook::Timer => self.strategy.on_timer(&ctx),
+        };
+        for intent in intents {
+            self.execute(sim, intent);
+        }
+    }
+
+    fn execute(&mut self, sim: &mut dyn SimulatorApi, intent: ActionIntent) {
+        let label = self.strategy.label().to_string();
+        let (msg_type, payload) = match intent {
+            ActionIntent::Open {
+                symbol,
+                side,
+                qty,
+                leverage,
+            } => {
+                let price = self.prices.get(&symbol).copied().unwrap_or(0);
+                agent_println!(
+                    "[{} {}] OPEN {:?} {} {:.4} {}x",
+                    label,
+                    self.name,
+                    side,
+                    symbol,
+                    qty,
+                    leverage
+                );
+                let key = (symbol.clone(), side);
+                let before = self.positions.get(&key).cloned();
+                let now_ns = sim.now_ns();
+                let position = self.positions.entry(key.clone()).or_insert_with(|| PositionView {
+                    opened_at_ns: now_ns,
+                    ..Default::default()
+                });
+                position.qty += qty;
+                position.size_usd += qty * price as f64;
+                position.leverage = leverage;
+                self.last_open = Some((key, before));
+                (
+                    MessageType::MarketOrder,
+                    MessagePayload::MarketOrder(MarketOrderPayload {
+                        symbol,
+                        side,
+                        qty: Qty::from_tokens(qty),
+                        leverage,
+                        decision_price: (price > 0).then_some(price),
+                    }),
+                )
+            }
+            ActionIntent::Close { symbol, side } => {
+                if self.positions.remove(&(symbol.clone(), side)).is_none() {
+                    return;
+                }
+                agent_println!("[{} {}] CLOSE {:?} {}", label, self.name, side, symbol);
+                let decision_price = self.prices.get(&symbol).copied();
+                (
+                    MessageType::CloseOrder,
+                    MessagePayload::CloseOrder(CloseOrderPayload {
+                        symbol,
+                        side,
+                        decision_price,
+                    }),
+                )
+            }
+            ActionIntent::Conditional {
+                symbol,
+                side,
+                order_type,
+                execution_type,
+                qty,
+                leverage,
+                trigger_price,
+            } => {
+                agent_println!(
+                    "[{} {}] {:?} {:?} {:?} {} {:.4} @ ${:.2}",
+                    label,
+                    self.name,
+                    execution_type,
+                    order_type,
+                    side,
+                    symbol,
+                    qty,
+                    trigger_price as f64 / 1_000_000.0
+                );
+                self.unacked_orders += 1;
+                (
+                    MessageType::SubmitOrder,
+                    MessagePayload::Order(OrderPayload {
+                        symbol,
+                        side,
+                        order_type,
+                        execution_type,
+                        qty: (order_type == OrderType::Increase).then(|| Qty::from_tokens(qty)),
+                        leverage: (order_type == OrderType::Increase).then_some(leverage),
+                        size_delta_usd: None,
+                        trigger_price: Some(trigger_price),
+                        acceptable_price: None,
+                        trailing_distance: None,
+                        valid_for_sec: None,
+                        oco_group: None,
+                        decision_price: None,
+                    }),
+                )
+            }
+            ActionIntent::Cancel { order_id } => {
+                self.open_orders.retain(|id| *id != order_id);
+                (
+                    MessageType::CancelOrder,
+                    MessagePayload::CancelOrder(CancelOrderPayload { order_id }),
+                )
+            }
+        };
+        sim.send(self.id, self.exchange_id, msg_type, payload);
+        self.orders_sent += 1;
+    }
+
+    /// Bring the position book in line with a fill before the strategy sees it.
+    fn apply_fill(&mut self, fill: &OrderExecutedPayload) {
+        let key = (fill.symbol.clone(), fill.side);
+        match fill.order_type {
+            OrderExecutionType::Increase => {
+                if self.last_open.as_ref().is_some_and(|(k, _)| *k == key) {
+                    self.last_open = None;
+                }
+                // Market opens are already booked; this adds positions opened by resting orders
+                self.positions.entry(key).or_insert_with(|| PositionView {
+                    size_usd: fill.size_usd as f64,
+                    ..Default::default()
+                });
+            }
+            OrderExecutionType::Decrease | OrderExecutionType::Liquidation => {
+                self.positions.remove(&key);
+            }
+        }
+    }
+}
+
+impl<S: Strategy> Agent for StrategyAgent<S> {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        if !self.strategy.set_param(key, value) {
+            return false;
+        }
+        agent_println!("[{} {}] param {} = {}", self.strategy.label(), self.name, key, value);
+        true
+    }
+
+    fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[{} {}] START{}",
+            self.strategy.label(),
+            self.name,
+            self.address
+                .as_deref()
+                .map(|addr| format!(" addr={}", addr))
+                .unwrap_or_default()
+        );
+        self.call(sim, Hook::Start);
+        if let Some(interval_ms) = self.strategy.timer_interval_ms() {
+            sim.wakeup(self.id, sim.now_ns() + interval_ms * 1_000_000);
+        }
+    }
+
+    fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
+        self.call(sim, Hook::Timer);
+        if let Some(interval_ms) = self.strategy.timer_interval_ms() {
+            sim.wakeup(self.id, now_ns + interval_ms * 1_000_000);
+        }
+    }
+
+    fn on_message(&mut self, sim: &mut dyn SimulatorApi, msg: &Message) {
+        match &msg.payload {
+            MessagePayload::OracleTick(_) | MessagePayload::OracleBatchTick(_) => {
+                for p in msg.payload.oracle_ticks() {
+                    let mid = (p.price.min + p.price.max) / 2;
+                    self.prices.insert(p.symbol.clone(), mid);
+                    self.regimes
+                        .entry(p.symbol.clone())
+                        .or_insert_with(|| RegimeDetector::new(REGIME_WINDOW))
+                        .update(mid as f64);
+                    self.call(sim, Hook::Price(&p.symbol, mid));
+                }
+            }
+            MessagePayload::MarketStats(p) => {
+                self.market_stats.insert(p.symbol.clone(), p.clone());
+            }
+            MessagePayload::OrderExecuted(p) => {
+                self.apply_fill(p);
+                self.call(sim, Hook::Fill(p));
+            }
+            MessagePayload::PositionLiquidated(p) => {
+                agent_println!(
+                    "[{} {}] LIQUIDATED {:?} {} pnl=${:.2}",
+                    self.strategy.label(),
+                    self.name,
+                    p.side,
+                    p.symbol,
+                    p.pnl as f64 / 1_000_000.0
+                );
+                self.liquidations += 1;
+                let fill = OrderExecutedPayload {
+                    symbol: p.symbol.clone(),
+                    side: p.side,
+                    order_type: OrderExecutionType::Liquidation,
+                    collateral_delta: -p.collateral_lost,
+                    pnl: p.pnl,
+                    size_usd: p.size_usd,
+                };
+                self.apply_fill(&fill);
+                self.call(sim, Hook::Fill(&fill));
+            }
+            MessagePayload::Text(text) if msg.msg_type == MessageType::OrderPending => {
+                if self.unacked_orders == 0 {
+                    return;
+                }
+                self.unacked_orders -= 1;
+                if let Some(order_id) = text.strip_prefix("order_id:").and_then(|id| id.parse::<OrderId>().ok()) {
+                    self.open_orders.push(order_id);
+                }
+            }
+            MessagePayload::Text(text)
+                if matches!(msg.msg_type, MessageType::OrderCancelled | MessageType::OrderExpired) =>
+            {
+                let order_id = text
+                    .strip_prefix("order_id:")
+                    .and_then(|rest| rest.split(',').next())
+                    .and_then(|id| id.parse::<OrderId>().ok());
+                if let Some(order_id) = order_id {
+                    self.open_orders.retain(|id| *id != order_id);
+                }
+            }
+            _ if msg.msg_type == MessageType::OrderRejected => {
+                self.rejected += 1;
+                // Roll back the latest open (conditional orders have nothing to roll back)
+                if let Some((key, before)) = self.last_open.take() {
+                    match before {
+                        Some(position) => self.positions.insert(key, position),
+                        None => self.positions.remove(&key),
+                    };
+                } else if self.unacked_orders > 0 {
+                    self.unacked_orders -= 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[{} {}] STOP: orders={} rejected={} liq={} open_positions={} {}",
+            self.strategy.label(),
+            self.name,
+            self.orders_sent,
+            self.rejected,
+            self.liquidations,
+            self.positions.len(),
+            self.strategy.summary()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::agents::test_support::{delivered, kernel_with_recorder, liquidated, oracle_tick};
+    use crate::kernel::Kernel;
+
+    const EXCHANGE: AgentId = 1;
+    const TRADER: AgentId = 60;
+    const ETH: &str = "ETH-USD";
+
+    /// Answers each price with the next queued intents and records the fills it sees
+    #[derive(Default)]
+    struct Script {
+        intents: VecDeque<Vec<ActionIntent>>,
+        fills: Vec<(Side, OrderExecutionType)>,
+    }
+
+    impl Strategy for Script {
+        fn label(&self) -> &str {
+            "Script"
+        }
+
+        fn on_price(&mut self, _ctx: &StrategyContext, _symbol: &str, _price: u64) -> Vec<ActionIntent> {
+            self.intents.pop_front().unwrap_or_default()
+        }
+
+        fn on_fill(&mut self, _ctx: &StrategyContext, fill: &OrderExecutedPayload) -> Vec<ActionIntent> {
+            self.fills.push((fill.side, fill.order_type));
+            Vec::new()
+        }
+    }
+
+    fn trader() -> StrategyAgent<Script> {
+        StrategyAgent::new(TRADER, "trader".to_string(), EXCHANGE, Script::default())
+    }
+
+    fn open(side: Side, qty: f64) -> ActionIntent {
+        ActionIntent::Open {
+            symbol: ETH.to_string(),
+            side,
+            qty,
+            leverage: 5,
+        }
+    }
+
+    /// Let the strategy answer a $2000 tick with `intents`
+    fn tick_with(trader: &mut StrategyAgent<Script>, kernel: &mut Kernel, intents: Vec<ActionIntent>) {
+        trader.strategy.intents.push_back(intents);
+        trader.on_message(kernel, &oracle_tick(TRADER, ETH, 2_000_000_000));
+    }
+
+    fn reply(msg_type: MessageType, text: &str) -> Message {
+        Message::new(TRADER, EXCHANGE, msg_type, 0, MessagePayload::Text(text.to_string()))
+    }
+
+    fn held(trader: &StrategyAgent<Script>, side: Side) -> Option<(f64, f64)> {
+        trader
+            .positions
+            .get(&(ETH.to_string(), side))
+            .map(|p| (p.qty, p.size_usd))
+    }
+
+    #[test]
+    fn test_rejected_opens_roll_back_the_position() {
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut trader = trader();
+
+        tick_with(&mut trader, &mut kernel, vec![open(Side::Buy, 2.0)]);
+        let sent = delivered(&mut kernel, &exchange);
+        assert!(matches!(
+            &sent[..],
+            [Message { payload: MessagePayload::MarketOrder(o), .. }]
+                if o.qty == Qty::from_tokens(2.0) && o.decision_price == Some(2_000_000_000)
+        ));
+        assert_eq!(held(&trader, Side::Buy), Some((2.0, 4_000_000_000.0)));
+
+        // A rejected add restores the previous position, a rejected first open removes it
+        tick_with(&mut trader, &mut kernel, vec![open(Side::Buy, 1.0)]);
+        assert_eq!(held(&trader, Side::Buy), Some((3.0, 6_000_000_000.0)));
+        trader.on_message(&mut kernel, &reply(MessageType::OrderRejected, "error:slippage"));
+        assert_eq!(held(&trader, Side::Buy), Some((2.0, 4_000_000_000.0)));
+        tick_with(&mut trader, &mut kernel, vec![open(Side::Sell, 1.0)]);
+        trader.on_message(&mut kernel, &reply(MessageType::OrderRejected, "error:slippage"));
+        assert_eq!(held(&trader, Side::Sell), None);
+        assert_eq!((trader.orders_sent, trader.rejected), (3, 2));
+    }
+
+    #[test]
+    fn test_fills_and_liquidations_reach_the_strategy() {
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut trader = trader();
+        tick_with(&mut trader, &mut kernel, vec![open(Side::Buy, 2.0)]);
+
+        // Once filled, a late rejection has no open left to undo
+        let fill = OrderExecutedPayload {
+            symbol: ETH.to_string(),
+            side: Side::Buy,
+            order_type: OrderExecutionType::Increase,
+            collateral_delta: 800_000_000,
+            pnl: 0,
+            size_usd: 4_000_000_000,
+        };
+        let executed = Message::new(
+            TRADER,
+            EXCHANGE,
+            MessageType::OrderExecuted,
+            0,
+            MessagePayload::OrderExecuted(fill),
+        );
+        trader.on_message(&mut kernel, &executed);
+        trader.on_message(&mut kernel, &reply(MessageType::OrderRejected, "error:other"));
+        assert!(held(&trader, Side::Buy).is_some());
+
+        trader.on_message(&mut kernel, &liquidated(TRADER, ETH, Side::Buy));
+        assert_eq!(held(&trader, Side::Buy), None);
+        assert_eq!(
+            trader.strategy.fills,
+            [
+                (Side::Buy, OrderExecutionType::Increase),
+                (Side::Buy, OrderExecutionType::Liquidation)
+            ]
+        );
+
+        // Nothing to close any more
+        delivered(&mut kernel, &exchange);
+        let close = ActionIntent::Close {
+            symbol: ETH.to_string(),
+            side: Side::Buy,
+        };
+        tick_with(&mut trader, &mut kernel, vec![close]);
+        assert!(delivered(&mut kernel, &exchange).is_empty());
+    }
+
+    #[test]
+    fn test_resting_orders_are_tracked_until_cancelled() {
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let mut trader = trader();
+        let limit = ActionIntent::Conditional {
+            symbol: ETH.to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Increase,
+            execution_type: ExecutionType::Limit,
+            qty: 1.0,
+            leverage: 5,
+            trigger_price: 1_900_000_000,
+        };
+        tick_with(&mut trader, &mut kernel, vec![limit.clone(), limit]);
+        let sent = delivered(&mut kernel, &exchange);
+        assert_eq!(sent.len(), 2);
+        assert!(sent.iter().all(|msg| msg.msg_type == MessageType::SubmitOrder));
+
+        // The first is acknowledged, the second rejected
+        trader.on_message(&mut kernel, &reply(MessageType::OrderPending, "order_id:7"));
+        trader.on_message(&mut kernel, &reply(MessageType::OrderRejected, "error:price"));
+        assert_eq!(trader.open_orders, [7]);
+        assert_eq!(trader.unacked_orders, 0);
+
+        trader.on_message(
+            &mut kernel,
+            &reply(MessageType::OrderCancelled, "order_id:7,reason:bankrupt"),
+        );
+        assert!(trader.open_orders.is_empty());
+        tick_with(&mut trader, &mut kernel, vec![ActionIntent::Cancel { order_id: 9 }]);
+        assert!(matches!(
+            &delivered(&mut kernel, &exchange)[..],
+            [Message {
+                payload: MessagePayload::CancelOrder(CancelOrderPayload { order_id: 9 }),
+                ..
+            }]
+        ));
+    }
+}
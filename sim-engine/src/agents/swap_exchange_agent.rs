@@ -0,0 +1,515 @@
+//! SwapExchangeAgent - constant-product spot venue (x * y = k).
+//!
+//! One pool per symbol holds `base` tokens against `quote` USD. A pool is
+//! seeded with `liquidity_usd` on each side at the first oracle price it sees
+//! and then moves only along its curve: buying `qty` takes the tokens out of
+//! the pool and pays in USD until `base * quote` is back at `k`, selling does
+//! the reverse. The fee (`fee_bps` of the USD amount) stays in the pool.
+//!
+//! The spot price is therefore free to diverge from the oracle and the perp.
+//! Outside arbitrage is modelled by `arb_pull`: on every oracle tick the pool
+//! closes that fraction of its (log) gap to the oracle price. With
+//! `arb_pull = 0` only scenario agents move spot, e.g. for manipulation studies.
+//!
+//! Swaps are answered with SwapExecuted or OrderRejected. New pool prices are
+//! broadcast as SpotPrice and every fill is emitted as a SpotSwap event
+//! (logs/swaps.csv). Spot holdings are tracked per account and may go negative
+//! (borrowed tokens), so a trader can also short spot against a long perp.
+
+use std::collections::HashMap;
+
+use crate::agent_println;
+use crate::agents::Agent;
+use crate::events::SimEvent;
+use crate::messages::{
+    AgentId, Message, MessagePayload, MessageType, Side, SimulatorApi, SpotPricePayload,
+    SwapExecutedPayload, SwapPayload,
+};
+
+#[derive(Debug, Clone)]
+pub struct SwapPoolConfig {
+    pub symbol: String,
+    /// USD on each side of the pool at seeding (micro-USD)
+    pub liquidity_usd: i128,
+}
+
+#[derive(Debug, Clone)]
+pub struct SwapExchangeConfig {
+    pub name: String,
+    pub pools: Vec<SwapPoolConfig>,
+    pub fee_bps: f64,
+    /// Fraction of the spot/oracle gap closed by outside arbitrage per oracle tick (0..=1)
+    pub arb_pull: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pool {
+    /// Tokens
+    base: f64,
+    /// micro-USD
+    quote: f64,
+}
+
+impl Pool {
+    fn seeded(liquidity_usd: f64, price: f64) -> Self {
+        Self {
+            base: liquidity_usd / price,
+            quote: liquidity_usd,
+        }
+    }
+
+    /// micro-USD per token
+    fn price(&self) -> f64 {
+        self.quote / self.base
+    }
+
+    /// USD paid (Buy) or received (Sell) for `qty` tokens before the fee; None if the pool can't fill it
+    fn quote_for(&self, side: Side, qty: f64) -> Option<f64> {
+        let k = self.base * self.quote;
+        match side {
+            Side::Buy => (qty < self.base).then(|| k / (self.base - qty) - self.quote),
+            Side::Sell => Some(self.quote - k / (self.base + qty)),
+        }
+    }
+
+    /// Slide along the curve to `price`, keeping `k`
+    fn move_to(&mut self, price: f64) {
+        let k = self.base * self.quote;
+        self.base = (k / price).sqrt();
+        self.quote = (k * price).sqrt();
+    }
+}
+
+#[derive(Debug, Default)]
+struct SpotAccount {
+    /// Tokens per symbol (negative: borrowed)
+    holdings: HashMap<String, f64>,
+    /// USD received minus paid (micro-USD)
+    cash_usd: f64,
+    swaps: u32,
+}
+
+pub struct SwapExchangeAgent {
+    id: AgentId,
+    name: String,
+    fee_bps: f64,
+    arb_pull: f64,
+
+    /// Seed liquidity of pools not yet priced (micro-USD)
+    unseeded: HashMap<String, i128>,
+    pools: HashMap<String, Pool>,
+    /// Oracle mid prices per symbol (micro-USD)
+    oracle_prices: HashMap<String, u64>,
+    accounts: HashMap<AgentId, SpotAccount>,
+
+    swaps: u64,
+    rejected: u64,
+    volume_usd: f64,
+    fees_usd: f64,
+    /// Largest |spot / oracle - 1| seen, in bps
+    max_basis_bps: f64,
+}
+
+impl SwapExchangeAgent {
+    pub fn new(id: AgentId, config: SwapExchangeConfig) -> Self {
+        Self {
+            id,
+            name: config.name,
+            fee_bps: config.fee_bps,
+            arb_pull: config.arb_pull.clamp(0.0, 1.0),
+            unseeded: config
+                .pools
+                .into_iter()
+                .map(|pool| (pool.symbol, pool.liquidity_usd))
+                .collect(),
+            pools: HashMap::new(),
+            oracle_prices: HashMap::new(),
+            accounts: HashMap::new(),
+            swaps: 0,
+            rejected: 0,
+            volume_usd: 0.0,
+            fees_usd: 0.0,
+            max_basis_bps: 0.0,
+        }
+    }
+
+    fn broadcast_price(&mut self, sim: &mut dyn SimulatorApi, symbol: &str) {
+        let Some(pool) = self.pools.get(symbol) else {
+            return;
+        };
+        let spot_price = pool.price() as u64;
+        let oracle_price = self.oracle_prices.get(symbol).copied().unwrap_or(0);
+        if oracle_price > 0 {
+            let basis_bps = (spot_price as f64 / oracle_price as f64 - 1.0).abs() * 10_000.0;
+            self.max_basis_bps = self.max_basis_bps.max(basis_bps);
+        }
+        sim.broadcast(
+            self.id,
+            MessageType::SpotPrice,
+            MessagePayload::SpotPrice(SpotPricePayload {
+                symbol: symbol.to_string(),
+                spot_price,
+                oracle_price,
+            }),
+        );
+    }
+
+    fn on_oracle(&mut self, sim: &mut dyn SimulatorApi, symbol: &str, price: u64) {
+        if price == 0 {
+            return;
+        }
+        self.oracle_prices.insert(symbol.to_string(), price);
+        if let Some(liquidity_usd) = self.unseeded.remove(symbol) {
+            let pool = Pool::seeded(liquidity_usd as f64, price as f64);
+            agent_println!(
+                "[Swap {}] POOL {} {:.4} tokens / ${:.2} @ ${:.4}",
+                self.name,
+                symbol,
+                pool.base,
+                pool.quote / 1_000_000.0,
+                pool.price() / 1_000_000.0
+            );
+            self.pools.insert(symbol.to_string(), pool);
+            self.broadcast_price(sim, symbol);
+            return;
+        }
+        if self.arb_pull <= 0.0 {
+            return;
+        }
+        let Some(pool) = self.pools.get_mut(symbol) else {
+            return;
+        };
+        let spot = pool.price();
+        let target = spot * (price as f64 / spot).powf(self.arb_pull);
+        pool.move_to(target);
+        if pool.price() as u64 != spot as u64 {
+            self.broadcast_price(sim, symbol);
+        }
+    }
+
+    fn reject(&mut self, sim: &mut dyn SimulatorApi, to: AgentId, symbol: &str, reason: &str) {
+        self.rejected += 1;
+        sim.send(
+            self.id,
+            to,
+            MessageType::OrderRejected,
+            MessagePayload::Text(format!("swap:{},error:{}", symbol, reason)),
+        );
+    }
+
+    fn on_swap(&mut self, sim: &mut dyn SimulatorApi, from: AgentId, swap: &SwapPayload) {
+        if swap.qty.is_zero() {
+            return self.reject(sim, from, &swap.symbol, "qty must be positive");
+        }
+        let qty = swap.qty.tokens();
+        let Some(pool) = self.pools.get(&swap.symbol).copied() else {
+            let reason = if self.unseeded.contains_key(&swap.symbol) {
+                "pool not seeded yet"
+            } else {
+                "no pool"
+            };
+            return self.reject(sim, from, &swap.symbol, reason);
+        };
+        let Some(amount) = pool.quote_for(swap.side, qty) else {
+            return self.reject(sim, from, &swap.symbol, "insufficient pool liquidity");
+        };
+        let fee = amount * self.fee_bps / 10_000.0;
+        // What the trader pays (Buy) or receives (Sell)
+        let total = match swap.side {
+            Side::Buy => amount + fee,
+            Side::Sell => amount - fee,
+        };
+        let avg_price = total / qty;
+        let beyond_limit = swap.limit_price.is_some_and(|limit| match swap.side {
+            Side::Buy => avg_price > limit as f64,
+            Side::Sell => avg_price < limit as f64,
+        });
+        if beyond_limit {
+            return self.reject(sim, from, &swap.symbol, "price beyond limit");
+        }
+
+        let pool = self.pools.get_mut(&swap.symbol).expect("pool checked above");
+        let cash_delta = match swap.side {
+            Side::Buy => {
+                pool.base -= qty;
+                pool.quote += total;
+                -total
+            }
+            Side::Sell => {
+                pool.base += qty;
+                pool.quote -= total;
+                total
+            }
+        };
+        let spot_price = pool.price() as u64;
+
+        let account = self.accounts.entry(from).or_default();
+        let holding = account.holdings.entry(swap.symbol.clone()).or_insert(0.0);
+        *holding += match swap.side {
+            Side::Buy => qty,
+            Side::Sell => -qty,
+        };
+        let holdings = *holding;
+        account.cash_usd += cash_delta;
+        account.swaps += 1;
+        self.swaps += 1;
+        self.volume_usd += total;
+        self.fees_usd += fee;
+
+        agent_println!(
+            "[Swap {}] {:?} {} {} from {} @ ${:.4} fee=${:.2} spot=${:.4}",
+            self.name,
+            swap.side,
+            swap.qty,
+            swap.symbol,
+            from,
+            avg_price / 1_000_000.0,
+            fee / 1_000_000.0,
+            spot_price as f64 / 1_000_000.0
+        );
+        sim.send(
+            self.id,
+            from,
+            MessageType::SwapExecuted,
+            MessagePayload::SwapExecuted(SwapExecutedPayload {
+                symbol: swap.symbol.clone(),
+                side: swap.side,
+                qty: swap.qty,
+                price: avg_price as u64,
+                fee_usd: fee as u64,
+                holdings,
+                spot_price,
+            }),
+        );
+        sim.emit_event(SimEvent::SpotSwap {
+            ts: sim.now_ns(),
+            account: from,
+            symbol: swap.symbol.clone(),
+            side: swap.side,
+            qty: swap.qty,
+            price: avg_price as u64,
+            fee_usd: fee as u64,
+            spot_price,
+            oracle_price: self.oracle_prices.get(&swap.symbol).copied().unwrap_or(0),
+        });
+        self.broadcast_price(sim, &swap.symbol);
+    }
+}
+
+impl Agent for SwapExchangeAgent {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+        match (key, value.as_f64()) {
+            ("fee_bps", Some(v)) if v >= 0.0 => self.fee_bps = v,
+            ("arb_pull", Some(v)) if (0.0..=1.0).contains(&v) => self.arb_pull = v,
+            _ => return false,
+        }
+        agent_println!("[Swap {}] param {} = {}", self.name, key, value);
+        true
+    }
+
+    fn on_start(&mut self, _sim: &mut dyn SimulatorApi) {
+        let mut symbols: Vec<&str> = self.unseeded.keys().map(String::as_str).collect();
+        symbols.sort_unstable();
+        agent_println!(
+            "[Swap {}] START pools={} fee={}bps arb_pull={}",
+            self.name,
+            symbols.join(","),
+            self.fee_bps,
+            self.arb_pull
+        );
+    }
+
+    fn on_wakeup(&mut self, _sim: &mut dyn SimulatorApi, _now_ns: u64) {}
+
+    fn on_message(&mut self, sim: &mut dyn SimulatorApi, msg: &Message) {
+        match &msg.payload {
+            MessagePayload::OracleTick(_) | MessagePayload::OracleBatchTick(_) => {
+                for p in msg.payload.oracle_ticks() {
+                    self.on_oracle(sim, &p.symbol, (p.price.min + p.price.max) / 2);
+                }
+            }
+            MessagePayload::Swap(swap) => self.on_swap(sim, msg.from, swap),
+            _ => {}
+        }
+    }
+
+    fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[Swap {}] STOP: swaps={} rejected={} volume=${:.2} fees=${:.2} max|basis|={:.1}bps",
+            self.name,
+            self.swaps,
+            self.rejected,
+            self.volume_usd / 1_000_000.0,
+            self.fees_usd / 1_000_000.0,
+            self.max_basis_bps
+        );
+        let mut symbols: Vec<&String> = self.pools.keys().collect();
+        symbols.sort_unstable();
+        for symbol in symbols {
+            let pool = &self.pools[symbol];
+            agent_println!(
+                "[Swap {}]   {} spot=${:.4} oracle=${:.4} reserves {:.4} / ${:.2}",
+                self.name,
+                symbol,
+                pool.price() / 1_000_000.0,
+                self.oracle_prices.get(symbol).copied().unwrap_or(0) as f64 / 1_000_000.0,
+                pool.base,
+                pool.quote / 1_000_000.0
+            );
+        }
+        let mut accounts: Vec<_> = self.accounts.iter().collect();
+        accounts.sort_unstable_by_key(|(id, _)| **id);
+        for (id, account) in accounts {
+            // Marked at the pool price
+            let value: f64 = account
+                .holdings
+                .iter()
+                .filter_map(|(symbol, qty)| self.pools.get(symbol).map(|pool| qty * pool.price()))
+                .sum();
+            agent_println!(
+                "[Swap {}]   agent {}: swaps={} pnl=${:.2}",
+                self.name,
+                id,
+                account.swaps,
+                (account.cash_usd + value) / 1_000_000.0
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::test_support::{delivered, kernel_with_recorder, oracle_tick};
+    use crate::qty::Qty;
+
+    const SWAP: AgentId = 3;
+    const TRADER: AgentId = 5;
+    const USD: f64 = 1_000_000.0;
+
+    fn venue(arb_pull: f64) -> SwapExchangeAgent {
+        SwapExchangeAgent::new(
+            SWAP,
+            SwapExchangeConfig {
+                name: "spot".to_string(),
+                pools: vec![SwapPoolConfig {
+                    symbol: "ETH-USD".to_string(),
+                    liquidity_usd: 100_000_000_000,
+                }],
+                fee_bps: 30.0,
+                arb_pull,
+            },
+        )
+    }
+
+    fn swap(symbol: &str, side: Side, qty: f64, limit_price: Option<u64>) -> Message {
+        let swap = SwapPayload {
+            symbol: symbol.to_string(),
+            side,
+            qty: Qty::from_tokens(qty),
+            limit_price,
+        };
+        Message::new(SWAP, TRADER, MessageType::Swap, 0, MessagePayload::Swap(swap))
+    }
+
+    /// Replies to the trader, without the SpotPrice broadcasts
+    fn replies(msgs: Vec<Message>) -> Vec<MessagePayload> {
+        msgs.into_iter()
+            .filter(|msg| msg.msg_type != MessageType::SpotPrice)
+            .map(|msg| msg.payload)
+            .collect()
+    }
+
+    fn rejection(payload: &MessagePayload) -> &str {
+        match payload {
+            MessagePayload::Text(text) => text.split("error:").nth(1).unwrap_or_default(),
+            other => panic!("expected a rejection, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pool_keeps_the_constant_product() {
+        // $100k a side at $2000: 50 tokens, k = 50 * 100k
+        let mut pool = Pool::seeded(100_000.0 * USD, 2_000.0 * USD);
+        assert_eq!((pool.base, pool.price()), (50.0, 2_000.0 * USD));
+        // Buying 10 leaves 40 tokens against 125k USD, selling 10 leaves 60 against 83.3k
+        assert_eq!(pool.quote_for(Side::Buy, 10.0), Some(25_000.0 * USD));
+        let sell = pool.quote_for(Side::Sell, 10.0).unwrap();
+        assert!((sell - 100_000.0 * USD / 6.0).abs() < 1e-3);
+        assert_eq!(pool.quote_for(Side::Buy, 50.0), None);
+
+        // 4x the price: half the tokens, twice the USD
+        pool.move_to(8_000.0 * USD);
+        assert!((pool.base - 25.0).abs() < 1e-9);
+        assert!((pool.quote - 200_000.0 * USD).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_swaps_fill_along_the_curve_and_keep_the_fee() {
+        let (mut kernel, trader) = kernel_with_recorder(TRADER);
+        let mut spot = venue(0.0);
+
+        spot.on_message(&mut kernel, &swap("ETH-USD", Side::Buy, 1.0, None));
+        spot.on_message(&mut kernel, &swap("BTC-USD", Side::Buy, 1.0, None));
+        let got = replies(delivered(&mut kernel, &trader));
+        assert_eq!(
+            got.iter().map(rejection).collect::<Vec<_>>(),
+            ["pool not seeded yet", "no pool"]
+        );
+
+        spot.on_message(&mut kernel, &oracle_tick(SWAP, "ETH-USD", 2_000_000_000));
+        spot.on_message(&mut kernel, &swap("ETH-USD", Side::Buy, 10.0, None));
+        let got = replies(delivered(&mut kernel, &trader));
+        let [MessagePayload::SwapExecuted(fill)] = &got[..] else {
+            panic!("expected a fill, got {:?}", got);
+        };
+        // $25k along the curve plus 30 bps
+        assert_eq!(
+            (fill.price, fill.fee_usd, fill.holdings),
+            (2_507_500_000, 75_000_000, 10.0)
+        );
+        assert_eq!(fill.spot_price, (125_075_000_000.0 / 40.0) as u64);
+
+        // The limit is checked against the average price; nothing moves on a rejection
+        spot.on_message(&mut kernel, &swap("ETH-USD", Side::Sell, 10.0, Some(3_000_000_000)));
+        spot.on_message(&mut kernel, &swap("ETH-USD", Side::Sell, 0.0, None));
+        let got = replies(delivered(&mut kernel, &trader));
+        assert_eq!(
+            got.iter().map(rejection).collect::<Vec<_>>(),
+            ["price beyond limit", "qty must be positive"]
+        );
+        let pool = spot.pools["ETH-USD"];
+        assert_eq!(pool.base, 40.0);
+        // The fee stays in the pool
+        assert!(pool.base * pool.quote > 50.0 * 100_000.0 * USD);
+        assert_eq!((spot.swaps, spot.rejected), (1, 4));
+    }
+
+    #[test]
+    fn test_arbitrage_pulls_spot_towards_the_oracle() {
+        let (mut kernel, trader) = kernel_with_recorder(TRADER);
+        let mut spot = venue(0.5);
+        spot.on_message(&mut kernel, &oracle_tick(SWAP, "ETH-USD", 2_000_000_000));
+        spot.on_message(&mut kernel, &swap("ETH-USD", Side::Buy, 10.0, None));
+        delivered(&mut kernel, &trader);
+        let pushed = spot.pools["ETH-USD"].price();
+
+        // Half the log gap: the geometric mean of spot and oracle
+        spot.on_message(&mut kernel, &oracle_tick(SWAP, "ETH-USD", 2_000_000_000));
+        let pulled = spot.pools["ETH-USD"].price();
+        assert!((pulled - (pushed * 2_000.0 * USD).sqrt()).abs() < 1.0);
+        let broadcast = delivered(&mut kernel, &trader);
+        assert!(matches!(
+            &broadcast[..],
+            [Message { payload: MessagePayload::SpotPrice(p), .. }] if p.oracle_price == 2_000_000_000
+        ));
+    }
+}
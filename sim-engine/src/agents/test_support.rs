@@ -0,0 +1,108 @@
+//! Test doubles shared by the agent tests.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::agents::Agent;
+use crate::events::{EventListener, SimEvent};
+use crate::kernel::Kernel;
+use crate::latency::FixedLatency;
+use crate::logging::Verbosity;
+use crate::messages::{
+    AgentId, Message, MessagePayload, MessageType, OracleTickPayload, PositionLiquidatedPayload, Price, Side,
+    SimulatorApi,
+};
+
+pub(crate) type Received = Rc<RefCell<Vec<Message>>>;
+pub(crate) type Emitted = Rc<RefCell<Vec<SimEvent>>>;
+
+/// Stands in for the exchange (or any other peer) and records every message it receives.
+struct Recorder {
+    id: AgentId,
+    received: Received,
+}
+
+impl Agent for Recorder {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        "Recorder"
+    }
+
+    fn on_message(&mut self, _sim: &mut dyn SimulatorApi, msg: &Message) {
+        self.received.borrow_mut().push(msg.clone());
+    }
+}
+
+/// Records every event emitted on the bus.
+struct EventRecorder(Emitted);
+
+impl EventListener for EventRecorder {
+    fn on_event(&mut self, event: &SimEvent) {
+        self.0.borrow_mut().push(event.clone());
+    }
+}
+
+/// Quiet zero-latency kernel with a recorder at `peer`; the agent under test is driven
+/// directly with the kernel as its `SimulatorApi`, and `delivered` returns what reached the peer.
+pub(crate) fn kernel_with_recorder(peer: AgentId) -> (Kernel, Received) {
+    let mut kernel = Kernel::new(Box::new(FixedLatency::new(0, 0)), 100);
+    kernel.set_verbosity(Verbosity::Quiet);
+    let received = add_recorder(&mut kernel, peer);
+    (kernel, received)
+}
+
+/// Another recorder, for agents that talk to more than one peer.
+pub(crate) fn add_recorder(kernel: &mut Kernel, id: AgentId) -> Received {
+    let received = Received::default();
+    kernel.add_agent(Box::new(Recorder {
+        id,
+        received: received.clone(),
+    }));
+    received
+}
+
+/// Events emitted through `kernel` from now on.
+pub(crate) fn record_events(kernel: &mut Kernel) -> Emitted {
+    let emitted = Emitted::default();
+    kernel.event_bus_mut().subscribe(Box::new(EventRecorder(emitted.clone())));
+    emitted
+}
+
+/// Oracle tick of `symbol` for `to` with a zero spread around `price` (micro-USD).
+pub(crate) fn oracle_tick(to: AgentId, symbol: &str, price: u64) -> Message {
+    let tick = OracleTickPayload {
+        symbol: symbol.to_string(),
+        price: Price { min: price, max: price },
+        publish_time: 0,
+        signature: Arc::from(Vec::new()),
+    };
+    Message::new(to, 0, MessageType::OracleTick, 0, MessagePayload::OracleTick(tick))
+}
+
+/// Liquidation notice of the `side` position of `to` on `symbol`, sent by the exchange (id 1).
+pub(crate) fn liquidated(to: AgentId, symbol: &str, side: Side) -> Message {
+    let payload = PositionLiquidatedPayload {
+        symbol: symbol.to_string(),
+        side,
+        size_usd: 0,
+        pnl: -1_000_000,
+        collateral_lost: 1_000_000,
+    };
+    Message::new(
+        to,
+        1,
+        MessageType::PositionLiquidated,
+        0,
+        MessagePayload::PositionLiquidated(payload),
+    )
+}
+
+/// Deliver what was sent so far and return the messages the recorder got since the last call.
+pub(crate) fn delivered(kernel: &mut Kernel, received: &Received) -> Vec<Message> {
+    kernel.run(1);
+    std::mem::take(&mut *received.borrow_mut())
+}
@@ -0,0 +1,388 @@
+//! WhaleAgent - scripted manipulation plan.
+//!
+//! The plan is a fixed schedule relative to the agent's start:
+//! 1. accumulate `total_qty` on `side` in `accumulate_chunks` market orders
+//!    spread evenly over `accumulate_sec`
+//! 2. at `dump_at_sec` close the whole position, then push `dump_qty` on the
+//!    opposite side in `dump_chunks` orders over `dump_sec`
+//! 3. optionally move the oracle price by `shock_pct` at `shock_at_sec`
+//!    (reverted after `shock_duration_sec`), e.g. right before the dump
+//!
+//! Used to observe price impact, ADL and insurance-fund behaviour under
+//! deliberate manipulation.
+
+use crate::agent_println;
+use crate::agents::Agent;
+use crate::messages::{
+    AgentId, CloseOrderPayload, DirectorCommand, MarketOrderPayload, Message, MessagePayload, MessageType, Side,
+    SimulatorApi,
+};
+use crate::qty::Qty;
+
+#[derive(Debug, Clone)]
+pub struct WhaleConfig {
+    pub name: String,
+    pub exchange_id: AgentId,
+    pub symbol: String,
+    pub side: Side,
+    pub leverage: u32,
+    /// Position built in the accumulation phase (tokens)
+    pub total_qty: f64,
+    pub accumulate_start_sec: u64,
+    pub accumulate_sec: u64,
+    pub accumulate_chunks: u32,
+    pub dump_at_sec: u64,
+    /// Opposite-side volume pushed after the close (tokens, 0 = close only)
+    pub dump_qty: f64,
+    pub dump_sec: u64,
+    pub dump_chunks: u32,
+    /// Oracle shock in percent, sent to `oracle_ids`
+    pub shock_pct: Option<f64>,
+    pub shock_at_sec: u64,
+    pub shock_duration_sec: Option<u64>,
+    pub oracle_ids: Vec<AgentId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WhaleStep {
+    Accumulate(f64),
+    Close,
+    Dump(f64),
+    /// Oracle price factor
+    Shock(f64),
+}
+
+pub struct WhaleAgent {
+    id: AgentId,
+    name: String,
+    exchange_id: AgentId,
+    symbol: String,
+    address: Option<String>,
+    side: Side,
+    leverage: u32,
+    oracle_ids: Vec<AgentId>,
+    config: WhaleConfig,
+
+    /// Remaining plan, sorted by time: (at_ns, step)
+    plan: Vec<(u64, WhaleStep)>,
+    next_step: usize,
+    start_ns: u64,
+
+    accumulated_qty: f64,
+    dumped_qty: f64,
+    rejected: u32,
+    liquidated: bool,
+}
+
+impl WhaleAgent {
+    pub fn new(id: AgentId, config: WhaleConfig) -> Self {
+        Self {
+            id,
+            name: config.name.clone(),
+            exchange_id: config.exchange_id,
+            symbol: config.symbol.clone(),
+            address: None,
+            side: config.side,
+            leverage: config.leverage.max(1),
+            oracle_ids: config.oracle_ids.clone(),
+            config,
+            plan: Vec::new(),
+            next_step: 0,
+            start_ns: 0,
+            accumulated_qty: 0.0,
+            dumped_qty: 0.0,
+            rejected: 0,
+            liquidated: false,
+        }
+    }
+
+    pub fn set_address(&mut self, address: String) {
+        self.address = Some(address);
+    }
+
+    /// Evenly spread `chunks` orders of `qty / chunks` over `[start, start + duration]`.
+    fn spread(start_sec: u64, duration_sec: u64, chunks: u32, qty: f64) -> impl Iterator<Item = (u64, f64)> {
+        let chunks = chunks.max(1);
+        let step_ns = duration_sec * 1_000_000_000 / chunks as u64;
+        (0..chunks as u64).map(move |i| (start_sec * 1_000_000_000 + i * step_ns, qty / chunks as f64))
+    }
+
+    /// Build the schedule relative to `start_ns`.
+    fn build_plan(&self, start_ns: u64) -> Vec<(u64, WhaleStep)> {
+        let c = &self.config;
+        let acc = Self::spread(
+            c.accumulate_start_sec,
+            c.accumulate_sec,
+            c.accumulate_chunks,
+            c.total_qty,
+        );
+        let mut plan: Vec<(u64, WhaleStep)> = acc.map(|(at, qty)| (at, WhaleStep::Accumulate(qty))).collect();
+        plan.push((c.dump_at_sec * 1_000_000_000, WhaleStep::Close));
+        if c.dump_qty > 0.0 {
+            plan.extend(
+                Self::spread(c.dump_at_sec, c.dump_sec, c.dump_chunks, c.dump_qty)
+                    .map(|(at, qty)| (at, WhaleStep::Dump(qty))),
+            );
+        }
+        if let Some(pct) = c.shock_pct {
+            let factor = 1.0 + pct / 100.0;
+            plan.push((c.shock_at_sec * 1_000_000_000, WhaleStep::Shock(factor)));
+            if let Some(d) = c.shock_duration_sec {
+                plan.push(((c.shock_at_sec + d) * 1_000_000_000, WhaleStep::Shock(1.0 / factor)));
+            }
+        }
+        // Stable: the close precedes the first dump chunk at the same instant
+        plan.sort_by_key(|(at, _)| *at);
+        plan.into_iter().map(|(at, step)| (start_ns + at, step)).collect()
+    }
+
+    fn t_sec(&self, now_ns: u64) -> u64 {
+        now_ns.saturating_sub(self.start_ns) / 1_000_000_000
+    }
+
+    fn send_market(&mut self, sim: &mut dyn SimulatorApi, side: Side, qty: f64) {
+        sim.send(
+            self.id,
+            self.exchange_id,
+            MessageType::MarketOrder,
+            MessagePayload::MarketOrder(MarketOrderPayload {
+                symbol: self.symbol.clone(),
+                side,
+                qty: Qty::from_tokens(qty),
+                leverage: self.leverage,
+                decision_price: sim.market_data().mid(&self.symbol),
+            }),
+        );
+    }
+
+    fn execute(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64, step: WhaleStep) {
+        let t = self.t_sec(now_ns);
+        match step {
+            WhaleStep::Accumulate(qty) => {
+                if self.liquidated {
+                    return;
+                }
+                self.accumulated_qty += qty;
+                agent_println!(
+                    "[Whale {}] t={}s ACCUMULATE {:?} {:.3} (total {:.3})",
+                    self.name,
+                    t,
+                    self.side,
+                    qty,
+                    self.accumulated_qty
+                );
+                self.send_market(sim, self.side, qty);
+            }
+            WhaleStep::Close => {
+                if self.liquidated {
+                    agent_println!("[Whale {}] t={}s DUMP: position already liquidated", self.name, t);
+                    return;
+                }
+                agent_println!(
+                    "[Whale {}] t={}s DUMP close {:?} {:.3}",
+                    self.name,
+                    t,
+                    self.side,
+                    self.accumulated_qty
+                );
+                sim.send(
+                    self.id,
+                    self.exchange_id,
+                    MessageType::CloseOrder,
+                    MessagePayload::CloseOrder(CloseOrderPayload {
+                        symbol: self.symbol.clone(),
+                        side: self.side,
+                        decision_price: sim.market_data().mid(&self.symbol),
+                    }),
+                );
+            }
+            WhaleStep::Dump(qty) => {
+                let side = match self.side {
+                    Side::Buy => Side::Sell,
+                    Side::Sell => Side::Buy,
+                };
+                self.dumped_qty += qty;
+                agent_println!("[Whale {}] t={}s DUMP {:?} {:.3}", self.name, t, side, qty);
+                self.send_market(sim, side, qty);
+            }
+            WhaleStep::Shock(factor) => {
+                agent_println!(
+                    "[Whale {}] t={}s ORACLE SHOCK {} {:+.1}%",
+                    self.name,
+                    t,
+                    self.symbol,
+                    (factor - 1.0) * 100.0
+                );
+                for oracle_id in &self.oracle_ids {
+                    sim.send(
+                        self.id,
+                        *oracle_id,
+                        MessageType::Director,
+                        MessagePayload::Director(DirectorCommand::PriceShock {
+                            symbol: self.symbol.clone(),
+                            factor,
+                        }),
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl Agent for WhaleAgent {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+        self.start_ns = sim.now_ns();
+        self.plan = self.build_plan(self.start_ns);
+        agent_println!(
+            "[Whale {}] START {} {:?} {:.3} @ {}x, dump at t={}s{}",
+            self.name,
+            self.symbol,
+            self.side,
+            self.config.total_qty,
+            self.leverage,
+            self.config.dump_at_sec,
+            self.address
+                .as_deref()
+                .map(|addr| format!(" addr={}", addr))
+                .unwrap_or_default()
+        );
+        if let Some((at, _)) = self.plan.first() {
+            sim.wakeup(self.id, *at);
+        }
+    }
+
+    fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
+        while let Some(&(at, step)) = self.plan.get(self.next_step) {
+            if at > now_ns {
+                sim.wakeup(self.id, at);
+                break;
+            }
+            self.next_step += 1;
+            self.execute(sim, now_ns, step);
+        }
+    }
+
+    fn on_message(&mut self, _sim: &mut dyn SimulatorApi, msg: &Message) {
+        match &msg.payload {
+            MessagePayload::PositionLiquidated(p) if p.symbol == self.symbol && p.side == self.side => {
+                agent_println!(
+                    "[Whale {}] LIQUIDATED {:?} pnl=${:.2}",
+                    self.name,
+                    p.side,
+                    p.pnl as f64 / 1_000_000.0
+                );
+                self.liquidated = true;
+            }
+            _ if msg.msg_type == MessageType::OrderRejected => self.rejected += 1,
+            _ => {}
+        }
+    }
+
+    fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
+        agent_println!(
+            "[Whale {}] STOP: accumulated={:.3} dumped={:.3} rejected={} liquidated={} steps={}/{}",
+            self.name,
+            self.accumulated_qty,
+            self.dumped_qty,
+            self.rejected,
+            self.liquidated,
+            self.next_step,
+            self.plan.len()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::test_support::{add_recorder, delivered, kernel_with_recorder, liquidated};
+
+    const EXCHANGE: AgentId = 1;
+    const ORACLE: AgentId = 2;
+    const WHALE: AgentId = 40;
+    const SEC: u64 = 1_000_000_000;
+
+    fn whale() -> WhaleAgent {
+        WhaleAgent::new(
+            WHALE,
+            WhaleConfig {
+                name: "whale".to_string(),
+                exchange_id: EXCHANGE,
+                symbol: "ETH-USD".to_string(),
+                side: Side::Buy,
+                leverage: 5,
+                total_qty: 30.0,
+                accumulate_start_sec: 10,
+                accumulate_sec: 60,
+                accumulate_chunks: 3,
+                dump_at_sec: 100,
+                dump_qty: 20.0,
+                dump_sec: 20,
+                dump_chunks: 2,
+                shock_pct: Some(-10.0),
+                shock_at_sec: 95,
+                shock_duration_sec: Some(30),
+                oracle_ids: vec![ORACLE],
+            },
+        )
+    }
+
+    #[test]
+    fn test_plan_spreads_chunks_and_closes_before_the_dump() {
+        let plan = whale().build_plan(1_000 * SEC);
+        let at_sec: Vec<u64> = plan.iter().map(|(at, _)| at / SEC - 1_000).collect();
+        assert_eq!(at_sec, vec![10, 30, 50, 95, 100, 100, 110, 125]);
+        let steps: Vec<WhaleStep> = plan.into_iter().map(|(_, step)| step).collect();
+        assert_eq!(steps[..3], [WhaleStep::Accumulate(10.0); 3]);
+        assert_eq!(steps[3], WhaleStep::Shock(0.9));
+        assert_eq!(
+            steps[4..7],
+            [WhaleStep::Close, WhaleStep::Dump(10.0), WhaleStep::Dump(10.0)]
+        );
+        // The reversal undoes the shock exactly
+        let WhaleStep::Shock(revert) = steps[7] else {
+            panic!("expected the shock reversal, got {:?}", steps[7]);
+        };
+        assert!((0.9 * revert - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_liquidation_stops_accumulating_but_not_the_dump() {
+        let (mut kernel, exchange) = kernel_with_recorder(EXCHANGE);
+        let oracle = add_recorder(&mut kernel, ORACLE);
+        let mut whale = whale();
+        whale.on_start(&mut kernel);
+
+        // Both steps due by t=30s run in one wakeup
+        whale.on_wakeup(&mut kernel, 30 * SEC);
+        let orders = delivered(&mut kernel, &exchange);
+        assert_eq!(orders.len(), 2);
+        assert!(orders.iter().all(|msg| matches!(
+            &msg.payload,
+            MessagePayload::MarketOrder(o) if o.side == Side::Buy && o.qty == Qty::from_tokens(10.0)
+        )));
+
+        whale.on_message(&mut kernel, &liquidated(WHALE, "ETH-USD", Side::Buy));
+        whale.on_wakeup(&mut kernel, 100 * SEC);
+        // No third chunk and no close of the liquidated position; the first dump chunk still goes out
+        let orders = delivered(&mut kernel, &exchange);
+        assert_eq!(orders.len(), 1);
+        assert!(matches!(&orders[0].payload, MessagePayload::MarketOrder(o) if o.side == Side::Sell));
+        assert!(matches!(
+            oracle.borrow()[..],
+            [Message {
+                payload: MessagePayload::Director(DirectorCommand::PriceShock { factor, .. }),
+                ..
+            }] if factor == 0.9
+        ));
+        assert_eq!(whale.accumulated_qty, 20.0);
+    }
+}
@@ -0,0 +1,88 @@
+// src/api/binance.rs
+// Binance spot book ticker as a reference price (unsigned, not usable on-chain).
+
+use super::provider::{PriceProvider, SignedPriceData};
+use serde::Deserialize;
+use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BookTicker {
+    bid_price: String,
+    ask_price: String,
+}
+
+/// Binance spot prices: mid of the best bid/ask, half spread as confidence.
+pub struct BinanceProvider {
+    base_url: String,
+    client: reqwest::blocking::Client,
+}
+
+impl BinanceProvider {
+    pub fn new() -> Self {
+        Self {
+            base_url: "https://api.binance.com".to_string(),
+            client: reqwest::blocking::Client::builder()
+                .timeout(std::time::Duration::from_secs(10))
+                .build()
+                .expect("Failed to create HTTP client"),
+        }
+    }
+
+    /// "ETH-USD" -> "ETHUSDT" (USD markets are quoted in USDT on Binance)
+    pub fn get_pair(symbol: &str) -> String {
+        let base = symbol.to_uppercase();
+        let base = base.strip_suffix("-USD").unwrap_or(&base);
+        format!("{}USDT", base)
+    }
+
+    fn to_micro(price: &str) -> Result<u64, Box<dyn Error>> {
+        let price: f64 = price.parse()?;
+        Ok((price * 1_000_000.0).round() as u64)
+    }
+}
+
+impl Default for BinanceProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PriceProvider for BinanceProvider {
+    fn fetch_signed_price(&self, symbol: &str) -> Result<SignedPriceData, Box<dyn Error>> {
+        let url = format!("{}/api/v3/ticker/bookTicker", self.base_url);
+        let response = self
+            .client
+            .get(&url)
+            .query(&[("symbol", Self::get_pair(symbol))])
+            .send()?;
+        if !response.status().is_success() {
+            return Err(format!("Binance API error: {} - {}", response.status(), response.text()?).into());
+        }
+        let ticker: BookTicker = response.json()?;
+        let bid = Self::to_micro(&ticker.bid_price)?;
+        let ask = Self::to_micro(&ticker.ask_price)?;
+
+        Ok(SignedPriceData {
+            symbol: symbol.to_string(),
+            price_usd_micro: (bid + ask) / 2,
+            confidence: Some(ask.saturating_sub(bid) / 2),
+            ema_price: None,
+            publish_time: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            signature: Vec::new(),
+            provider_name: "Binance".to_string(),
+        })
+    }
+
+    fn provider_name(&self) -> &str {
+        "Binance"
+    }
+
+    fn supported_symbols(&self) -> Vec<String> {
+        ["BTC-USD", "ETH-USD", "SOL-USD", "AVAX-USD"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+}
@@ -0,0 +1,154 @@
+// Cross-origin access to the HTTP API.
+//
+// Without a `cors` section the server sends no CORS headers and browsers only
+// reach it from its own origin. With one, responses to an allowed `Origin`
+// carry `Access-Control-Allow-Origin`, and `OPTIONS` preflights are answered
+// directly with the allowed methods and headers, so a web dashboard served
+// elsewhere can call the API.
+
+use serde::{Deserialize, Serialize};
+use tiny_http::Header;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins allowed to call the API (`"*"` = any)
+    pub allowed_origins: Vec<String>,
+    /// Request headers a browser may send
+    #[serde(default = "default_allowed_headers")]
+    pub allowed_headers: Vec<String>,
+    /// How long a browser may cache a preflight answer
+    #[serde(default = "default_max_age_sec")]
+    pub max_age_sec: u64,
+}
+
+fn default_allowed_headers() -> Vec<String> {
+    vec!["Content-Type".to_string()]
+}
+
+fn default_max_age_sec() -> u64 {
+    600
+}
+
+const ALLOWED_METHODS: &str = "GET, POST, OPTIONS";
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+impl CorsConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.allowed_origins.is_empty() {
+            return Err("allowed_origins must not be empty".to_string());
+        }
+        let printable = |s: &String| !s.is_empty() && s.bytes().all(|b| b.is_ascii_graphic());
+        if !self.allowed_origins.iter().chain(&self.allowed_headers).all(printable) {
+            return Err("origins and headers must be non-empty ASCII without spaces".to_string());
+        }
+        Ok(())
+    }
+
+    /// The `Access-Control-Allow-Origin` value for a request from `origin`; None if not allowed.
+    pub fn allow_origin(&self, origin: Option<&str>) -> Option<String> {
+        if self.allowed_origins.iter().any(|o| o == "*") {
+            return Some("*".to_string());
+        }
+        let origin = origin?;
+        self.allowed_origins
+            .iter()
+            .any(|o| o == origin)
+            .then(|| origin.to_string())
+    }
+
+    /// Headers of a response to a request from `origin`.
+    pub fn headers(&self, origin: Option<&str>) -> Vec<Header> {
+        let Some(allowed) = self.allow_origin(origin) else {
+            return Vec::new();
+        };
+        let mut headers = vec![header("Access-Control-Allow-Origin", &allowed)];
+        if allowed != "*" {
+            // The answer depends on the request's origin
+            headers.push(header("Vary", "Origin"));
+        }
+        headers
+    }
+
+    /// Headers of the answer to a preflight `OPTIONS` request from `origin`.
+    pub fn preflight_headers(&self, origin: Option<&str>) -> Vec<Header> {
+        let mut headers = self.headers(origin);
+        if !headers.is_empty() {
+            headers.push(header("Access-Control-Allow-Methods", ALLOWED_METHODS));
+            headers.push(header("Access-Control-Allow-Headers", &self.allowed_headers.join(", ")));
+            headers.push(header("Access-Control-Max-Age", &self.max_age_sec.to_string()));
+        }
+        headers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(headers: &[Header]) -> Vec<String> {
+        headers.iter().map(|h| h.field.to_string()).collect()
+    }
+
+    #[test]
+    fn test_listed_origins_only() {
+        let cors: CorsConfig =
+            serde_json::from_str(r#"{ "allowed_origins": ["http://localhost:3000", "https://dash.example"] }"#)
+                .unwrap();
+        assert!(cors.validate().is_ok());
+        assert_eq!(
+            cors.allow_origin(Some("https://dash.example")).as_deref(),
+            Some("https://dash.example")
+        );
+        assert_eq!(cors.allow_origin(Some("https://evil.example")), None);
+        assert_eq!(cors.allow_origin(None), None);
+
+        assert_eq!(
+            names(&cors.headers(Some("http://localhost:3000"))),
+            vec!["Access-Control-Allow-Origin", "Vary"]
+        );
+        assert!(cors.headers(Some("https://evil.example")).is_empty());
+        assert!(cors.preflight_headers(Some("https://evil.example")).is_empty());
+
+        let preflight = cors.preflight_headers(Some("http://localhost:3000"));
+        let max_age = preflight
+            .iter()
+            .find(|h| h.field.equiv("Access-Control-Max-Age"))
+            .unwrap();
+        assert_eq!(max_age.value.as_str(), "600");
+        let allowed = preflight
+            .iter()
+            .find(|h| h.field.equiv("Access-Control-Allow-Headers"))
+            .unwrap();
+        assert_eq!(allowed.value.as_str(), "Content-Type");
+    }
+
+    #[test]
+    fn test_wildcard_allows_any_origin() {
+        let cors = CorsConfig {
+            allowed_origins: vec!["*".to_string()],
+            allowed_headers: default_allowed_headers(),
+            max_age_sec: 60,
+        };
+        assert_eq!(cors.allow_origin(None).as_deref(), Some("*"));
+        assert_eq!(
+            names(&cors.headers(Some("http://anywhere"))),
+            vec!["Access-Control-Allow-Origin"]
+        );
+        assert!(CorsConfig {
+            allowed_origins: vec![],
+            ..cors.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(CorsConfig {
+            allowed_origins: vec!["http://a b".to_string()],
+            ..cors
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,306 @@
+// Throttling and validation of API commands before they reach the HumanAgent.
+//
+// Every command from the HTTP and WebSocket servers goes through one shared
+// guard. Each client (remote IP, or its trading session) has a token bucket
+// refilled at `commands_per_sec` and holding at most `burst` commands; an empty
+// bucket rejects with HTTP 429 and the wait until the next token. Commands
+// with a qty or leverage outside the bounds or an unknown symbol are rejected
+// with 400, so a spamming UI neither fills the command channel nor stalls the
+// commands of other clients behind it.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use super::{ApiCommand, ApiResponse};
+use crate::qty::Qty;
+
+/// Per-client rate and order bounds of the API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiLimits {
+    /// Sustained commands per second of one client (0 = unlimited)
+    #[serde(default = "default_commands_per_sec")]
+    pub commands_per_sec: f64,
+    /// Commands a client may send at once
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+    /// Largest order size in tokens
+    #[serde(default)]
+    pub max_qty: Option<Qty>,
+    #[serde(default)]
+    pub max_leverage: Option<u32>,
+    /// Accepted symbols (empty = the exchange's markets)
+    #[serde(default)]
+    pub symbols: Vec<String>,
+}
+
+fn default_commands_per_sec() -> f64 {
+    20.0
+}
+
+fn default_burst() -> u32 {
+    40
+}
+
+impl Default for ApiLimits {
+    fn default() -> Self {
+        Self {
+            commands_per_sec: default_commands_per_sec(),
+            burst: default_burst(),
+            max_qty: None,
+            max_leverage: None,
+            symbols: Vec::new(),
+        }
+    }
+}
+
+impl ApiLimits {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.commands_per_sec.is_finite() || self.commands_per_sec < 0.0 {
+            return Err("commands_per_sec must be >= 0".to_string());
+        }
+        if self.commands_per_sec > 0.0 && self.burst == 0 {
+            return Err("burst must be positive".to_string());
+        }
+        if self.max_qty.is_some_and(Qty::is_zero) || self.max_leverage == Some(0) {
+            return Err("max_qty and max_leverage must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Why a command was not forwarded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    RateLimited { retry_after_ms: u64 },
+    Invalid { field: &'static str, reason: String },
+}
+
+impl Rejection {
+    /// HTTP status of the rejection
+    pub fn status(&self) -> u16 {
+        match self {
+            Rejection::RateLimited { .. } => 429,
+            Rejection::Invalid { .. } => 400,
+        }
+    }
+
+    pub fn to_response(&self) -> ApiResponse {
+        match self {
+            Rejection::RateLimited { retry_after_ms } => ApiResponse {
+                success: false,
+                message: format!("Too many commands, retry in {}ms", retry_after_ms),
+                data: Some(serde_json::json!({"error": "rate_limited", "retry_after_ms": retry_after_ms})),
+                request_id: None,
+            },
+            Rejection::Invalid { field, reason } => ApiResponse {
+                success: false,
+                message: format!("Invalid {}: {}", field, reason),
+                data: Some(serde_json::json!({"error": "invalid", "field": field, "reason": reason})),
+                request_id: None,
+            },
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Rate limiter and validator shared by the API servers.
+pub struct CommandGuard {
+    limits: ApiLimits,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl Default for CommandGuard {
+    fn default() -> Self {
+        Self::new(ApiLimits::default())
+    }
+}
+
+impl CommandGuard {
+    pub fn new(limits: ApiLimits) -> Self {
+        Self {
+            limits,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token of `client` and validate `cmd`; Err if it must not reach the agent.
+    pub fn check(&self, client: &str, cmd: &ApiCommand) -> Result<(), Rejection> {
+        self.check_at(client, cmd, Instant::now())
+    }
+
+    fn check_at(&self, client: &str, cmd: &ApiCommand, now: Instant) -> Result<(), Rejection> {
+        self.take_token(client, now)?;
+        self.validate(cmd)
+    }
+
+    fn take_token(&self, client: &str, now: Instant) -> Result<(), Rejection> {
+        let rate = self.limits.commands_per_sec;
+        if rate <= 0.0 {
+            return Ok(());
+        }
+        let burst = self.limits.burst as f64;
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.updated = now;
+        if bucket.tokens < 1.0 {
+            let retry_after_ms = ((1.0 - bucket.tokens) / rate * 1000.0).ceil() as u64;
+            return Err(Rejection::RateLimited { retry_after_ms });
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+
+    fn validate(&self, cmd: &ApiCommand) -> Result<(), Rejection> {
+        let invalid = |field, reason: String| Err(Rejection::Invalid { field, reason });
+        if !cmd.symbol.is_empty() && !self.limits.symbols.is_empty() && !self.limits.symbols.contains(&cmd.symbol) {
+            return invalid("symbol", format!("unknown symbol '{}'", cmd.symbol));
+        }
+        if let Some(qty) = cmd.qty {
+            if qty.is_zero() {
+                return invalid("qty", "must be positive".to_string());
+            }
+            if let Some(max) = self.limits.max_qty.filter(|max| qty > *max) {
+                return invalid("qty", format!("{} above the limit of {}", qty, max));
+            }
+        }
+        if let Some(leverage) = cmd.leverage {
+            if leverage == 0 {
+                return invalid("leverage", "must be at least 1".to_string());
+            }
+            if let Some(max) = self.limits.max_leverage.filter(|max| leverage > *max) {
+                return invalid("leverage", format!("{}x above the limit of {}x", leverage, max));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn command(json: &str) -> ApiCommand {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn guard(limits: ApiLimits) -> CommandGuard {
+        CommandGuard::new(limits)
+    }
+
+    #[test]
+    fn test_bucket_allows_burst_then_refills_per_client() {
+        let guard = guard(ApiLimits {
+            commands_per_sec: 10.0,
+            burst: 3,
+            ..ApiLimits::default()
+        });
+        let status = command(r#"{ "action": "status" }"#);
+        let t0 = Instant::now();
+        for _ in 0..3 {
+            assert!(guard.check_at("1.2.3.4", &status, t0).is_ok());
+        }
+        let rejection = guard.check_at("1.2.3.4", &status, t0).unwrap_err();
+        assert_eq!(rejection, Rejection::RateLimited { retry_after_ms: 100 });
+        assert_eq!(rejection.status(), 429);
+        // Other clients have their own bucket
+        assert!(guard.check_at("5.6.7.8", &status, t0).is_ok());
+        // One token back after 100ms, never more than the burst
+        assert!(guard
+            .check_at("1.2.3.4", &status, t0 + Duration::from_millis(100))
+            .is_ok());
+        assert!(guard
+            .check_at("1.2.3.4", &status, t0 + Duration::from_millis(100))
+            .is_err());
+        let later = t0 + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(guard.check_at("1.2.3.4", &status, later).is_ok());
+        }
+        assert!(guard.check_at("1.2.3.4", &status, later).is_err());
+    }
+
+    #[test]
+    fn test_zero_rate_disables_throttling() {
+        let guard = guard(ApiLimits {
+            commands_per_sec: 0.0,
+            ..ApiLimits::default()
+        });
+        let status = command(r#"{ "action": "status" }"#);
+        let now = Instant::now();
+        assert!((0..1000).all(|_| guard.check_at("client", &status, now).is_ok()));
+    }
+
+    #[test]
+    fn test_order_bounds_and_symbols() {
+        let guard = guard(ApiLimits {
+            max_qty: Some(Qty::from_tokens(10.0)),
+            max_leverage: Some(20),
+            symbols: vec!["ETH-USD".to_string()],
+            ..ApiLimits::default()
+        });
+        let check = |json: &str| guard.check("client", &command(json));
+        assert!(
+            check(r#"{ "action": "open", "symbol": "ETH-USD", "side": "long", "qty": 10, "leverage": 20 }"#).is_ok()
+        );
+        assert!(check(r#"{ "action": "status" }"#).is_ok());
+
+        let field = |json: &str| match check(json) {
+            Err(Rejection::Invalid { field, .. }) => field,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(field(r#"{ "action": "open", "symbol": "BTC-USD" }"#), "symbol");
+        assert_eq!(
+            field(r#"{ "action": "open", "symbol": "ETH-USD", "qty": 10.5 }"#),
+            "qty"
+        );
+        assert_eq!(field(r#"{ "action": "open", "symbol": "ETH-USD", "qty": 0 }"#), "qty");
+        assert_eq!(
+            field(r#"{ "action": "open", "symbol": "ETH-USD", "leverage": 50 }"#),
+            "leverage"
+        );
+        assert_eq!(
+            field(r#"{ "action": "limit", "symbol": "ETH-USD", "leverage": 0 }"#),
+            "leverage"
+        );
+
+        let response = check(r#"{ "action": "open", "symbol": "ETH-USD", "leverage": 50 }"#)
+            .unwrap_err()
+            .to_response();
+        assert!(!response.success);
+        assert_eq!(response.data.unwrap()["field"], "leverage");
+    }
+
+    #[test]
+    fn test_limits_parsing_and_validation() {
+        let limits: ApiLimits = serde_json::from_str(r#"{ "max_qty": "2.5", "max_leverage": 10 }"#).unwrap();
+        assert_eq!(limits.commands_per_sec, 20.0);
+        assert_eq!(limits.burst, 40);
+        assert_eq!(limits.max_qty, Some(Qty::from_tokens(2.5)));
+        assert!(limits.validate().is_ok());
+        assert!(ApiLimits {
+            burst: 0,
+            ..limits.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(ApiLimits {
+            max_leverage: Some(0),
+            ..limits
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -1,11 +1,22 @@
+pub mod binance;
 pub mod cache;
+pub mod cors;
+pub mod limits;
+pub mod openapi;
 pub mod server;
 pub mod provider;
 pub mod pyth;
+pub mod recording;
+pub mod synthetic;
 pub mod ws;
 
+pub use binance::*;
 pub use cache::*;
+pub use cors::*;
+pub use limits::*;
 pub use server::*;
 pub use provider::*;
 pub use pyth::*;
+pub use recording::*;
+pub use synthetic::*;
 pub use ws::*;
@@ -0,0 +1,327 @@
+// OpenAPI 3 description of the HTTP API, served at GET /openapi.json.
+//
+// The schemas come from the field tables below, one entry per serialized
+// field of `ApiCommand` and `ApiResponse`; the tests serialize both types and
+// fail when a field is added without its table entry, so generated clients
+// never miss a field. `ROUTES` lists the endpoints the server dispatches.
+
+use serde_json::{json, Map, Value};
+
+/// (field, JSON schema, description) of every `ApiCommand` field
+const COMMAND_FIELDS: &[(&str, &str, &str)] = &[
+    (
+        "action",
+        concat!(
+            r#"{"type":"string","enum":["open","order","close","preview","limit","stop_loss","take_profit","cancel","#,
+            r#""orders","positions","trades","status","balance","pause","resume","config","price"]}"#
+        ),
+        "What to do; set by the endpoint except on /order",
+    ),
+    ("symbol", r#"{"type":"string","example":"ETH-USD"}"#, "Market"),
+    (
+        "side",
+        r#"{"type":"string","enum":["long","short","buy","sell"]}"#,
+        "Order side",
+    ),
+    (
+        "qty",
+        r#"{"oneOf":[{"type":"number"},{"type":"string"}],"example":0.5}"#,
+        "Tokens, number or decimal string, up to 9 decimals (default 1)",
+    ),
+    ("leverage", r#"{"type":"integer","minimum":1}"#, "Leverage (default 5)"),
+    (
+        "trigger_price",
+        r#"{"type":"number"}"#,
+        "Trigger price in USD of limit / stop_loss / take_profit",
+    ),
+    (
+        "valid_for_sec",
+        r#"{"type":"integer","minimum":0}"#,
+        "Lifetime of a conditional order (default 1h)",
+    ),
+    ("order_id", r#"{"type":"integer","minimum":0}"#, "Order to cancel"),
+    (
+        "oco_group",
+        r#"{"type":"integer","minimum":0}"#,
+        "Orders of a group cancel each other once one executes",
+    ),
+    (
+        "account",
+        r#"{"type":"integer","minimum":0}"#,
+        "Agent whose fills to list (default: own account)",
+    ),
+    (
+        "limit",
+        r#"{"type":"integer","minimum":0}"#,
+        "Newest fills to return (default 100)",
+    ),
+    (
+        "agent",
+        r#"{"type":"integer","minimum":0}"#,
+        "Target agent of pause / resume / config",
+    ),
+    ("config", r#"{"type":"object"}"#, "Runtime parameters of config"),
+    ("price", r#"{"type":"number"}"#, "Manual oracle price in USD"),
+    (
+        "duration_sec",
+        r#"{"type":"integer","minimum":0}"#,
+        "How long a manual price lasts (default 60, 0 clears it)",
+    ),
+    (
+        "request_id",
+        r#"{"type":"string"}"#,
+        "Echoed in the response (set by the HTTP server)",
+    ),
+];
+
+/// (field, JSON schema, description) of every `ApiResponse` field
+const RESPONSE_FIELDS: &[(&str, &str, &str)] = &[
+    ("success", r#"{"type":"boolean"}"#, "Whether the command was accepted"),
+    ("message", r#"{"type":"string"}"#, "Human-readable outcome"),
+    (
+        "data",
+        r#"{}"#,
+        "Command-specific payload; rejections carry an `error` code",
+    ),
+    ("request_id", r#"{"type":"string"}"#, "`request_id` of the command answered"),
+];
+
+/// How an endpoint takes its input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteInput {
+    None,
+    /// `ApiCommand` JSON body
+    Command,
+    /// `{"symbol": ...}` body
+    Symbol,
+    /// Query string parameters (`ApiCommand` fields)
+    Query(&'static [&'static str]),
+}
+
+pub struct Route {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub summary: &'static str,
+    pub input: RouteInput,
+}
+
+const fn route(method: &'static str, path: &'static str, summary: &'static str, input: RouteInput) -> Route {
+    Route {
+        method,
+        path,
+        summary,
+        input,
+    }
+}
+
+/// Endpoints of the ApiServer
+pub const ROUTES: &[Route] = &[
+    route(
+        "post",
+        "/order",
+        "Any command; `action` selects it",
+        RouteInput::Command,
+    ),
+    route("post", "/close", "Close the position in a market", RouteInput::Symbol),
+    route(
+        "post",
+        "/preview",
+        "Price impact, fees and liquidation price of an order",
+        RouteInput::Command,
+    ),
+    route("post", "/limit", "Limit order at `trigger_price`", RouteInput::Command),
+    route(
+        "post",
+        "/stop_loss",
+        "Stop loss at `trigger_price`",
+        RouteInput::Command,
+    ),
+    route(
+        "post",
+        "/take_profit",
+        "Take profit at `trigger_price`",
+        RouteInput::Command,
+    ),
+    route("post", "/cancel", "Cancel a conditional order", RouteInput::Command),
+    route("post", "/admin/pause", "Pause an agent", RouteInput::Command),
+    route("post", "/admin/resume", "Resume a paused agent", RouteInput::Command),
+    route(
+        "post",
+        "/admin/config",
+        "Update runtime parameters of an agent",
+        RouteInput::Command,
+    ),
+    route(
+        "post",
+        "/admin/price",
+        "Publish a manual oracle price for a while",
+        RouteInput::Command,
+    ),
+    route("get", "/status", "Balance, PnL and open positions", RouteInput::None),
+    route("get", "/orders", "Resting conditional orders", RouteInput::None),
+    route(
+        "get",
+        "/positions",
+        "Positions with liquidation prices",
+        RouteInput::None,
+    ),
+    route(
+        "get",
+        "/trades",
+        "Recent fills, newest first",
+        RouteInput::Query(&["account", "symbol", "limit"]),
+    ),
+    route(
+        "get",
+        "/metrics",
+        "Kernel delivery lag and agent handler time histograms (Prometheus text)",
+        RouteInput::None,
+    ),
+    route("get", "/health", "Liveness check", RouteInput::None),
+    route("get", "/openapi.json", "This document", RouteInput::None),
+];
+
+fn schema_of((_, schema, description): &(&str, &str, &str)) -> Value {
+    let mut schema: Value = serde_json::from_str(schema).expect("valid field schema");
+    schema["description"] = json!(description);
+    schema
+}
+
+fn object_schema(fields: &[(&str, &str, &str)], required: &[&str]) -> Value {
+    let properties: Map<String, Value> = fields.iter().map(|f| (f.0.to_string(), schema_of(f))).collect();
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+/// Schema of the `ApiCommand` field `name`
+fn field_schema(name: &str) -> Value {
+    schema_of(COMMAND_FIELDS.iter().find(|(n, ..)| *n == name).expect("known field"))
+}
+
+fn operation(route: &Route) -> Value {
+    let response = |description: &str| {
+        json!({
+            "description": description,
+            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ApiResponse" } } }
+        })
+    };
+    let mut op = json!({
+        "summary": route.summary,
+        "responses": {
+            "200": response("Outcome of the command"),
+            "400": response("Invalid command"),
+            "429": response("Too many commands from this client; see Retry-After"),
+            "503": response("Command queue full"),
+        }
+    });
+    let body = |schema: Value| json!({ "required": true, "content": { "application/json": { "schema": schema } } });
+    match route.input {
+        RouteInput::None => {}
+        RouteInput::Command => op["requestBody"] = body(json!({ "$ref": "#/components/schemas/ApiCommand" })),
+        RouteInput::Symbol => {
+            let schema = json!({
+                "type": "object",
+                "properties": { "symbol": field_schema("symbol") },
+                "required": ["symbol"],
+            });
+            op["requestBody"] = body(schema);
+        }
+        RouteInput::Query(params) => {
+            op["parameters"] = params
+                .iter()
+                .map(|name| json!({ "name": name, "in": "query", "required": false, "schema": field_schema(name) }))
+                .collect();
+        }
+    }
+    op
+}
+
+/// The OpenAPI document of the HTTP API.
+pub fn spec() -> Value {
+    let mut paths = Map::new();
+    for route in ROUTES {
+        let path = paths.entry(route.path).or_insert_with(|| json!({}));
+        path[route.method] = operation(route);
+    }
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "perp-sim HTTP API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Commands of the realtime simulation's HumanAgent",
+        },
+        "paths": paths,
+        "components": {
+            "schemas": {
+                "ApiCommand": object_schema(COMMAND_FIELDS, &[]),
+                "ApiResponse": object_schema(RESPONSE_FIELDS, &["success", "message"]),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{ApiCommand, ApiResponse};
+    use crate::qty::Qty;
+    use std::collections::BTreeSet;
+
+    fn keys(value: &Value) -> BTreeSet<String> {
+        value.as_object().unwrap().keys().cloned().collect()
+    }
+
+    fn table(fields: &[(&str, &str, &str)]) -> BTreeSet<String> {
+        fields.iter().map(|(name, ..)| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_schemas_cover_every_field() {
+        let cmd = ApiCommand {
+            action: "open".to_string(),
+            symbol: "ETH-USD".to_string(),
+            side: Some("long".to_string()),
+            qty: Some(Qty::from_tokens(1.0)),
+            leverage: Some(5),
+            trigger_price: Some(3000.0),
+            valid_for_sec: Some(60),
+            order_id: Some(1),
+            oco_group: Some(1),
+            account: Some(1),
+            limit: Some(1),
+            agent: Some(1),
+            config: Some(json!({})),
+            price: Some(1.0),
+            duration_sec: Some(1),
+            request_id: None,
+        };
+        assert_eq!(keys(&serde_json::to_value(&cmd).unwrap()), table(COMMAND_FIELDS));
+        let resp = ApiResponse {
+            success: true,
+            message: String::new(),
+            data: Some(json!({})),
+            request_id: Some("http-1".to_string()),
+        };
+        assert_eq!(keys(&serde_json::to_value(&resp).unwrap()), table(RESPONSE_FIELDS));
+    }
+
+    fn body_schema<'a>(spec: &'a Value, path: &str) -> &'a Value {
+        &spec["paths"][path]["post"]["requestBody"]["content"]["application/json"]["schema"]
+    }
+
+    #[test]
+    fn test_spec_lists_every_route() {
+        let spec = spec();
+        assert_eq!(spec["openapi"], "3.0.3");
+        for route in ROUTES {
+            let op = &spec["paths"][route.path][route.method];
+            assert_eq!(op["summary"], route.summary, "{} {}", route.method, route.path);
+        }
+        let command = &spec["components"]["schemas"]["ApiCommand"]["properties"];
+        assert_eq!(command["qty"]["description"], COMMAND_FIELDS[3].2);
+        assert_eq!(body_schema(&spec, "/order")["$ref"], "#/components/schemas/ApiCommand");
+        assert_eq!(body_schema(&spec, "/close")["required"][0], "symbol");
+        let params = spec["paths"]["/trades"]["get"]["parameters"].as_array().unwrap();
+        assert_eq!(params.len(), 3);
+        assert_eq!(params[2]["schema"]["type"], "integer");
+    }
+}
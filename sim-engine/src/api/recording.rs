@@ -0,0 +1,282 @@
+// Price recording and playback.
+//
+// `RecordingProvider` wraps a live provider and appends every answer it gives
+// (price, confidence, publish time and the VAA) to a JSONL file, one line per
+// symbol, tagged with the index of the fetch call. `PlaybackProvider` answers
+// the n-th fetch with the n-th recorded one, so a session with real prices can
+// be replayed deterministically and without network access, also in
+// fast-forward. Fetch errors are recorded and replayed as errors; after the
+// last recorded fetch the last prices are held.
+
+use super::provider::{PriceProvider, SignedPriceData};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::{create_dir_all, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// One line of a recording: the answer for `symbol` in fetch call `fetch`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PriceRecord {
+    fetch: u64,
+    symbol: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    price: Option<RecordedPrice>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedPrice {
+    price_usd_micro: u64,
+    confidence: Option<u64>,
+    ema_price: Option<u64>,
+    publish_time: u64,
+    /// VAA, hex encoded
+    vaa: String,
+    provider_name: String,
+}
+
+impl From<&SignedPriceData> for RecordedPrice {
+    fn from(data: &SignedPriceData) -> Self {
+        Self {
+            price_usd_micro: data.price_usd_micro,
+            confidence: data.confidence,
+            ema_price: data.ema_price,
+            publish_time: data.publish_time,
+            vaa: hex::encode(&data.signature),
+            provider_name: data.provider_name.clone(),
+        }
+    }
+}
+
+impl PriceRecord {
+    fn to_result(&self) -> Result<SignedPriceData, Box<dyn Error>> {
+        let Some(price) = &self.price else {
+            let error = self.error.as_deref().unwrap_or("no price recorded");
+            return Err(error.into());
+        };
+        Ok(SignedPriceData {
+            symbol: self.symbol.clone(),
+            price_usd_micro: price.price_usd_micro,
+            confidence: price.confidence,
+            ema_price: price.ema_price,
+            publish_time: price.publish_time,
+            signature: hex::decode(&price.vaa)?,
+            provider_name: price.provider_name.clone(),
+        })
+    }
+}
+
+/// Archives every answer of the wrapped provider to a JSONL file.
+pub struct RecordingProvider<P: PriceProvider> {
+    inner: P,
+    /// Writer and the index of the next fetch call
+    out: Mutex<(BufWriter<File>, u64)>,
+}
+
+impl<P: PriceProvider> RecordingProvider<P> {
+    /// Record to `path` (created, or truncated if it exists)
+    pub fn new(provider: P, path: &Path) -> std::io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            create_dir_all(dir)?;
+        }
+        let out = BufWriter::new(File::create(path)?);
+        Ok(Self {
+            inner: provider,
+            out: Mutex::new((out, 0)),
+        })
+    }
+
+    fn record(&self, symbols: &[&str], results: &[Result<SignedPriceData, Box<dyn Error>>]) {
+        let mut guard = self.out.lock().unwrap();
+        let (out, fetch) = &mut *guard;
+        for (symbol, result) in symbols.iter().zip(results) {
+            let record = PriceRecord {
+                fetch: *fetch,
+                symbol: symbol.to_string(),
+                price: result.as_ref().ok().map(RecordedPrice::from),
+                error: result.as_ref().err().map(|e| e.to_string()),
+            };
+            if serde_json::to_writer(&mut *out, &record).is_ok() {
+                let _ = out.write_all(b"\n");
+            }
+        }
+        // Keep the file usable if the run is killed
+        let _ = out.flush();
+        *fetch += 1;
+    }
+}
+
+impl<P: PriceProvider> PriceProvider for RecordingProvider<P> {
+    fn fetch_signed_price(&self, symbol: &str) -> Result<SignedPriceData, Box<dyn Error>> {
+        self.fetch_batch(&[symbol])
+            .pop()
+            .unwrap_or_else(|| Err("no result".into()))
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn supported_symbols(&self) -> Vec<String> {
+        self.inner.supported_symbols()
+    }
+
+    fn fetch_batch(&self, symbols: &[&str]) -> Vec<Result<SignedPriceData, Box<dyn Error>>> {
+        let results = self.inner.fetch_batch(symbols);
+        self.record(symbols, &results);
+        results
+    }
+}
+
+/// Answers fetch calls from a recording, in recorded order.
+pub struct PlaybackProvider {
+    /// Recorded answers grouped by fetch call
+    fetches: Vec<Vec<PriceRecord>>,
+    symbols: Vec<String>,
+    next: Mutex<usize>,
+}
+
+impl PlaybackProvider {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::from_reader(BufReader::new(file)).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, String> {
+        let mut fetches: Vec<Vec<PriceRecord>> = Vec::new();
+        let mut symbols: Vec<String> = Vec::new();
+        let mut last_fetch = None;
+        for (i, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: PriceRecord = serde_json::from_str(&line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+            if !symbols.contains(&record.symbol) {
+                symbols.push(record.symbol.clone());
+            }
+            if last_fetch != Some(record.fetch) {
+                last_fetch = Some(record.fetch);
+                fetches.push(Vec::new());
+            }
+            fetches.last_mut().expect("pushed above").push(record);
+        }
+        if fetches.is_empty() {
+            return Err("empty recording".to_string());
+        }
+        Ok(Self {
+            fetches,
+            symbols,
+            next: Mutex::new(0),
+        })
+    }
+
+    /// Fetch calls in the recording
+    pub fn len(&self) -> usize {
+        self.fetches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fetches.is_empty()
+    }
+}
+
+impl PriceProvider for PlaybackProvider {
+    fn fetch_signed_price(&self, symbol: &str) -> Result<SignedPriceData, Box<dyn Error>> {
+        self.fetch_batch(&[symbol])
+            .pop()
+            .unwrap_or_else(|| Err("no result".into()))
+    }
+
+    fn provider_name(&self) -> &str {
+        "Playback"
+    }
+
+    fn supported_symbols(&self) -> Vec<String> {
+        self.symbols.clone()
+    }
+
+    fn fetch_batch(&self, symbols: &[&str]) -> Vec<Result<SignedPriceData, Box<dyn Error>>> {
+        let index = {
+            let mut next = self.next.lock().unwrap();
+            let index = (*next).min(self.fetches.len() - 1);
+            if *next == self.fetches.len() {
+                println!(
+                    "[Playback] recording exhausted after {} fetches, holding last prices",
+                    index + 1
+                );
+            }
+            *next += 1;
+            index
+        };
+        let fetch = &self.fetches[index];
+        symbols
+            .iter()
+            .map(|symbol| match fetch.iter().find(|r| r.symbol == *symbol) {
+                Some(record) => record.to_result(),
+                None => Err(format!("{} not recorded in fetch {}", symbol, index).into()),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECORDING: &str = r#"{"fetch":0,"symbol":"ETH-USD","price":{"price_usd_micro":3000000000,"confidence":1500000,"ema_price":null,"publish_time":100,"vaa":"01ff","provider_name":"Pyth"}}
+{"fetch":0,"symbol":"BTC-USD","error":"timeout"}
+{"fetch":1,"symbol":"ETH-USD","price":{"price_usd_micro":3010000000,"confidence":null,"ema_price":null,"publish_time":103,"vaa":"","provider_name":"Pyth"}}
+"#;
+
+    #[test]
+    fn test_replays_fetches_in_order() {
+        let playback = PlaybackProvider::from_reader(RECORDING.as_bytes()).unwrap();
+        assert_eq!(playback.len(), 2);
+        assert_eq!(playback.supported_symbols(), vec!["ETH-USD", "BTC-USD"]);
+
+        let first = playback.fetch_batch(&["ETH-USD", "BTC-USD"]);
+        let eth = first[0].as_ref().unwrap();
+        assert_eq!(eth.price_usd_micro, 3_000_000_000);
+        assert_eq!(eth.confidence, Some(1_500_000));
+        assert_eq!(eth.signature, vec![0x01, 0xff]);
+        assert_eq!(first[1].as_ref().unwrap_err().to_string(), "timeout");
+
+        let second = playback.fetch_batch(&["ETH-USD", "BTC-USD"]);
+        assert_eq!(second[0].as_ref().unwrap().publish_time, 103);
+        assert!(second[1].is_err());
+
+        // Exhausted: the last fetch is held
+        let held = playback.fetch_signed_price("ETH-USD").unwrap();
+        assert_eq!(held.price_usd_micro, 3_010_000_000);
+    }
+
+    #[test]
+    fn test_record_round_trip() {
+        let data = SignedPriceData {
+            symbol: "ETH-USD".to_string(),
+            price_usd_micro: 2_999_500_000,
+            confidence: Some(900_000),
+            ema_price: Some(2_998_000_000),
+            publish_time: 1_700_000_000,
+            signature: vec![0x50, 0x4e, 0x41, 0x55],
+            provider_name: "Pyth".to_string(),
+        };
+        let record = PriceRecord {
+            fetch: 0,
+            symbol: data.symbol.clone(),
+            price: Some(RecordedPrice::from(&data)),
+            error: None,
+        };
+        let line = serde_json::to_string(&record).unwrap();
+        let playback = PlaybackProvider::from_reader(line.as_bytes()).unwrap();
+        let replayed = playback.fetch_signed_price("ETH-USD").unwrap();
+        assert_eq!(replayed.price_usd_micro, data.price_usd_micro);
+        assert_eq!(replayed.ema_price, data.ema_price);
+        assert_eq!(replayed.signature, data.signature);
+        assert!(PlaybackProvider::from_reader("".as_bytes()).is_err());
+    }
+}
@@ -1,20 +1,43 @@
 //! HTTP API server for external interaction with the simulation.
 
-use crossbeam_channel::{Receiver, Sender};
+use super::cors::CorsConfig;
+use super::limits::{CommandGuard, Rejection};
+use crate::metrics::SharedMetrics;
+use crate::qty::Qty;
+use crossbeam_channel::{Receiver, Sender, TrySendError};
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::io::Read;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
 use std::thread;
-use tiny_http::{Method, Response, Server};
+use tiny_http::{Header, Method, Response, Server};
 
 /// Command sent from HTTP API to HumanAgent
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiCommand {
-    pub action: String,      // "open", "close", "status", "liquidations", "positions"
+    /// "open", "close", "limit", "stop_loss", "take_profit", "cancel", "orders", "positions", "trades", "status",
+    /// "pause", "resume", "config", "price"
+    pub action: String,
+    #[serde(default)]
     pub symbol: String,
     pub side: Option<String>, // "long" or "short"
-    pub qty: Option<f64>,     // Number of tokens as float (e.g., 0.5, 2.0)
+    pub qty: Option<Qty>,     // Tokens, number or decimal string (e.g., 0.5, "0.001"), up to 9 decimals
     pub leverage: Option<u32>,
+    pub trigger_price: Option<f64>, // USD, for limit / stop_loss / take_profit
+    pub valid_for_sec: Option<u64>, // Lifetime of a conditional order (default 1h)
+    pub order_id: Option<u64>,      // For cancel
+    pub oco_group: Option<u64>,     // Link stop_loss / take_profit orders: one executing cancels the rest
+    pub account: Option<u32>,       // For trades: agent whose fills to list (default: own account)
+    pub limit: Option<usize>,       // For trades: newest fills to return (default 100)
+    pub agent: Option<u32>,         // For pause / resume / config: target agent
+    pub config: Option<serde_json::Value>, // For config: runtime parameters (JSON object)
+    pub price: Option<f64>,         // For price: manual oracle price (USD)
+    pub duration_sec: Option<u64>,  // For price: how long it replaces the provider (default 60, 0 = clear)
+    /// Echoed in the response; the HTTP server sets its own, WS clients may send one
+    #[serde(default)]
+    pub request_id: Option<String>,
 }
 
 /// Response from HumanAgent back to HTTP API
@@ -24,6 +47,118 @@ pub struct ApiResponse {
     pub message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<serde_json::Value>,
+    /// `request_id` of the command answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// An HTTP request with the CORS headers its response must carry.
+struct ApiRequest {
+    inner: tiny_http::Request,
+    cors: Vec<Header>,
+}
+
+impl ApiRequest {
+    fn as_reader(&mut self) -> &mut dyn Read {
+        self.inner.as_reader()
+    }
+
+    fn remote_addr(&self) -> Option<&SocketAddr> {
+        self.inner.remote_addr()
+    }
+
+    fn respond<R: Read>(self, mut response: Response<R>) -> std::io::Result<()> {
+        for header in self.cors {
+            response.add_header(header);
+        }
+        self.inner.respond(response)
+    }
+}
+
+/// Prefix of the request ids the HTTP server assigns; WebSocket clients may not use it.
+pub const HTTP_REQUEST_ID_PREFIX: &str = "http-";
+
+/// Routes the HumanAgent's answers to the HTTP requests waiting for them by `request_id`.
+/// Answers meant for WebSocket clients and for requests that already timed out are dropped.
+struct Correlator {
+    next_id: AtomicU64,
+    pending: Mutex<HashMap<String, Sender<ApiResponse>>>,
+}
+
+impl Correlator {
+    fn new(response_rx: Receiver<ApiResponse>) -> Arc<Self> {
+        let correlator = Arc::new(Self {
+            next_id: AtomicU64::new(1),
+            pending: Mutex::new(HashMap::new()),
+        });
+        let dispatcher = correlator.clone();
+        thread::spawn(move || {
+            for resp in response_rx {
+                dispatcher.dispatch(resp);
+            }
+        });
+        correlator
+    }
+
+    /// A fresh request id and the receiver of its answer
+    fn register(&self) -> (String, Receiver<ApiResponse>) {
+        let id = format!("{}{}", HTTP_REQUEST_ID_PREFIX, self.next_id.fetch_add(1, Ordering::Relaxed));
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        self.pending.lock().unwrap().insert(id.clone(), tx);
+        (id, rx)
+    }
+
+    fn forget(&self, id: &str) {
+        self.pending.lock().unwrap().remove(id);
+    }
+
+    fn dispatch(&self, resp: ApiResponse) {
+        let Some(id) = resp.request_id.as_deref() else {
+            return;
+        };
+        if let Some(tx) = self.pending.lock().unwrap().remove(id) {
+            let _ = tx.send(resp);
+        }
+    }
+}
+
+/// Threads answering HTTP requests; a slow answer (preview) holds up only its own worker.
+const API_WORKERS: usize = 16;
+/// Requests waiting for a free worker before new ones are turned away with 503
+const API_QUEUE: usize = 64;
+
+/// A request with its method and URL, waiting for a worker
+type HttpJob = (ApiRequest, Method, String);
+
+/// Fixed set of worker threads fed through a bounded queue, so a burst of requests
+/// can't spawn threads faster than the command guard turns them away.
+struct RequestPool<J> {
+    jobs: Sender<J>,
+}
+
+impl<J: Send + 'static> RequestPool<J> {
+    fn new<F>(workers: usize, queue: usize, handle: F) -> Self
+    where
+        F: Fn(J) + Send + Clone + 'static,
+    {
+        let (jobs, job_rx) = crossbeam_channel::bounded::<J>(queue);
+        for _ in 0..workers {
+            let job_rx = job_rx.clone();
+            let handle = handle.clone();
+            // Workers exit once the pool (the only sender) is dropped
+            thread::spawn(move || {
+                for job in job_rx {
+                    handle(job);
+                }
+            });
+        }
+        Self { jobs }
+    }
+
+    /// Queue `job`; gives it back when the queue is full.
+    fn submit(&self, job: J) -> Result<(), J> {
+        self.jobs.try_send(job).map_err(TrySendError::into_inner)
+    }
 }
 
 /// API Server handle
@@ -37,15 +172,20 @@ impl ApiServer {
     pub fn start(port: u16, response_rx: Receiver<ApiResponse>) -> (Self, Sender<ApiCommand>, Receiver<ApiCommand>) {
         // Bounded channel to prevent memory leak if HumanAgent is slow
         let (cmd_tx, cmd_rx) = crossbeam_channel::bounded::<ApiCommand>(100);
-        let server = Self::start_with_channel(port, response_rx, cmd_tx.clone());
+        let guard = Arc::new(CommandGuard::default());
+        let server = Self::start_with_channel(port, response_rx, cmd_tx.clone(), guard, None, None);
         (server, cmd_tx, cmd_rx)
     }
 
-    /// Start the API server with an existing command channel
+    /// Start the API server with an existing command channel; `guard` throttles and validates
+    /// commands, `cors` lets browsers on other origins call the API, `metrics` are served on /metrics.
     pub fn start_with_channel(
         port: u16,
         response_rx: Receiver<ApiResponse>,
         cmd_tx: Sender<ApiCommand>,
+        guard: Arc<CommandGuard>,
+        cors: Option<CorsConfig>,
+        metrics: Option<SharedMetrics>,
     ) -> Self {
         let cmd_tx_clone = cmd_tx.clone();
         
@@ -63,6 +203,18 @@ impl ApiServer {
             };
 
             println!("[ApiServer] listening on http://{}", addr);
+            let responses = Correlator::new(response_rx);
+            let pool = RequestPool::new(API_WORKERS, API_QUEUE, move |(request, method, url): HttpJob| {
+                route_request(
+                    request,
+                    method,
+                    &url,
+                    &guard,
+                    &cmd_tx_clone,
+                    &responses,
+                    metrics.as_ref(),
+                )
+            });
             
             loop {
                 if shutdown_clone.load(Ordering::Relaxed) {
@@ -81,33 +233,32 @@ impl ApiServer {
                 let method = request.method().clone();
                 let url = request.url().to_string();
 
-                match (method, url.as_str()) {
-                    (Method::Post, "/order") => {
-                        handle_order_request(request, &cmd_tx_clone, &response_rx);
-                    }
-                    (Method::Post, "/close") => {
-                        handle_close_request(request, &cmd_tx_clone, &response_rx);
-                    }
-                    (Method::Post, "/preview") => {
-                        handle_preview_request(request, &cmd_tx_clone, &response_rx);
-                    }
-                    (Method::Get, "/status") => {
-                        handle_status_request(request, &cmd_tx_clone, &response_rx);
-                    }
-                    (Method::Get, "/health") => {
-                        send_json_response(request, &ApiResponse {
-                            success: true,
-                            message: "OK".to_string(),
-                            data: None,
-                        });
-                    }
-                    _ => {
-                        send_json_response(request, &ApiResponse {
-                            success: false,
-                            message: format!("Unknown endpoint: {}", url),
-                            data: None,
-                        });
+                let origin = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Origin"))
+                    .map(|h| h.value.as_str().to_string());
+                if let (Method::Options, Some(cors)) = (&method, &cors) {
+                    let mut response = Response::empty(204);
+                    for header in cors.preflight_headers(origin.as_deref()) {
+                        response.add_header(header);
                     }
+                    let _ = request.respond(response);
+                    continue;
+                }
+                let request = ApiRequest {
+                    inner: request,
+                    cors: cors.as_ref().map(|c| c.headers(origin.as_deref())).unwrap_or_default(),
+                };
+
+                if let Err((request, _, _)) = pool.submit((request, method, url)) {
+                    let busy = ApiResponse {
+                        success: false,
+                        message: "Server is busy, retry later".to_string(),
+                        data: Some(serde_json::json!({"error": "server_busy"})),
+                        request_id: None,
+                    };
+                    let _ = request.respond(json_response(&busy).with_status_code(503));
                 }
             }
         });
@@ -132,17 +283,114 @@ impl Drop for ApiServer {
     }
 }
 
-fn send_json_response(request: tiny_http::Request, resp: &ApiResponse) {
+/// Dispatch one request to its handler.
+fn route_request(
+    request: ApiRequest,
+    method: Method,
+    url: &str,
+    guard: &CommandGuard,
+    cmd_tx: &Sender<ApiCommand>,
+    responses: &Correlator,
+    metrics: Option<&SharedMetrics>,
+) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    match (method, path) {
+        (Method::Post, "/order") => {
+            handle_order_request(request, guard, cmd_tx, responses);
+        }
+        (Method::Post, "/close") => {
+            handle_close_request(request, guard, cmd_tx, responses);
+        }
+        (Method::Post, "/preview") => {
+            handle_preview_request(request, guard, cmd_tx, responses);
+        }
+        (Method::Post, "/limit") => {
+            handle_action_request(request, "limit", guard, cmd_tx, responses);
+        }
+        (Method::Post, "/stop_loss") => {
+            handle_action_request(request, "stop_loss", guard, cmd_tx, responses);
+        }
+        (Method::Post, "/take_profit") => {
+            handle_action_request(request, "take_profit", guard, cmd_tx, responses);
+        }
+        (Method::Post, "/cancel") => {
+            handle_action_request(request, "cancel", guard, cmd_tx, responses);
+        }
+        (Method::Post, "/admin/pause") => {
+            handle_action_request(request, "pause", guard, cmd_tx, responses);
+        }
+        (Method::Post, "/admin/resume") => {
+            handle_action_request(request, "resume", guard, cmd_tx, responses);
+        }
+        (Method::Post, "/admin/config") => {
+            handle_action_request(request, "config", guard, cmd_tx, responses);
+        }
+        (Method::Post, "/admin/price") => {
+            handle_action_request(request, "price", guard, cmd_tx, responses);
+        }
+        (Method::Get, "/status") => {
+            handle_query_request(request, "status", guard, cmd_tx, responses);
+        }
+        (Method::Get, "/orders") => {
+            handle_query_request(request, "orders", guard, cmd_tx, responses);
+        }
+        (Method::Get, "/positions") => {
+            handle_query_request(request, "positions", guard, cmd_tx, responses);
+        }
+        (Method::Get, "/trades") => {
+            handle_trades_request(request, query, guard, cmd_tx, responses);
+        }
+        (Method::Get, "/openapi.json") => {
+            let spec = super::openapi::spec().to_string();
+            let response = Response::from_string(spec).with_header(
+                Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap(),
+            );
+            let _ = request.respond(response);
+        }
+        (Method::Get, "/metrics") => {
+            let Some(text) = metrics.and_then(|m| m.lock().ok()).map(|m| m.prometheus()) else {
+                let _ = request.respond(Response::from_string("metrics disabled\n").with_status_code(404));
+                return;
+            };
+            let response = Response::from_string(text).with_header(
+                Header::from_bytes(&b"Content-Type"[..], &b"text/plain; version=0.0.4"[..]).unwrap(),
+            );
+            let _ = request.respond(response);
+        }
+        (Method::Get, "/health") => {
+            send_json_response(request, &ApiResponse {
+                success: true,
+                message: "OK".to_string(),
+                data: None,
+                request_id: None,
+            });
+        }
+        _ => {
+            send_json_response(request, &ApiResponse {
+                success: false,
+                message: format!("Unknown endpoint: {}", url),
+                data: None,
+                request_id: None,
+            });
+        }
+    }
+}
+
+fn json_response(resp: &ApiResponse) -> Response<std::io::Cursor<Vec<u8>>> {
     let response = serde_json::to_string(resp).unwrap_or_else(|_| "{}".to_string());
-    let http_response = Response::from_string(response)
-        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap());
-    let _ = request.respond(http_response);
+    Response::from_string(response)
+        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
+}
+
+fn send_json_response(request: ApiRequest, resp: &ApiResponse) {
+    let _ = request.respond(json_response(resp));
 }
 
 fn handle_order_request(
-    mut request: tiny_http::Request,
+    mut request: ApiRequest,
+    guard: &CommandGuard,
     cmd_tx: &Sender<ApiCommand>,
-    response_rx: &Receiver<ApiResponse>,
+    responses: &Correlator,
 ) {
     let mut body = String::new();
     if let Err(e) = std::io::Read::read_to_string(&mut request.as_reader(), &mut body) {
@@ -150,6 +398,7 @@ fn handle_order_request(
             success: false,
             message: format!("Failed to read body: {}", e),
             data: None,
+            request_id: None,
         });
         return;
     }
@@ -161,35 +410,20 @@ fn handle_order_request(
                 success: false,
                 message: format!("Invalid JSON: {}", e),
                 data: None,
+                request_id: None,
             });
             return;
         }
     };
 
-    if let Err(e) = cmd_tx.send(cmd) {
-        send_json_response(request, &ApiResponse {
-            success: false,
-            message: format!("Failed to send command: {}", e),
-            data: None,
-        });
-        return;
-    }
-
-    let resp = match response_rx.recv_timeout(std::time::Duration::from_secs(5)) {
-        Ok(resp) => resp,
-        Err(_) => ApiResponse {
-            success: false,
-            message: "Timeout waiting for response".to_string(),
-            data: None,
-        },
-    };
-    send_json_response(request, &resp);
+    forward_query(request, cmd, guard, cmd_tx, responses);
 }
 
 fn handle_close_request(
-    mut request: tiny_http::Request,
+    mut request: ApiRequest,
+    guard: &CommandGuard,
     cmd_tx: &Sender<ApiCommand>,
-    response_rx: &Receiver<ApiResponse>,
+    responses: &Correlator,
 ) {
     let mut body = String::new();
     if let Err(e) = std::io::Read::read_to_string(&mut request.as_reader(), &mut body) {
@@ -197,6 +431,7 @@ fn handle_close_request(
             success: false,
             message: format!("Failed to read body: {}", e),
             data: None,
+            request_id: None,
         });
         return;
     }
@@ -211,6 +446,7 @@ fn handle_close_request(
                 success: false,
                 message: format!("Invalid JSON: {}", e),
                 data: None,
+                request_id: None,
             });
             return;
         }
@@ -222,32 +458,27 @@ fn handle_close_request(
         side: None,
         qty: None,
         leverage: None,
+        trigger_price: None,
+        valid_for_sec: None,
+        order_id: None,
+        oco_group: None,
+        account: None,
+        limit: None,
+        agent: None,
+        config: None,
+        price: None,
+        duration_sec: None,
+        request_id: None,
     };
 
-    if let Err(e) = cmd_tx.send(cmd) {
-        send_json_response(request, &ApiResponse {
-            success: false,
-            message: format!("Failed to send command: {}", e),
-            data: None,
-        });
-        return;
-    }
-
-    let resp = match response_rx.recv_timeout(std::time::Duration::from_secs(5)) {
-        Ok(resp) => resp,
-        Err(_) => ApiResponse {
-            success: false,
-            message: "Timeout".to_string(),
-            data: None,
-        },
-    };
-    send_json_response(request, &resp);
+    forward_query(request, cmd, guard, cmd_tx, responses);
 }
 
 fn handle_preview_request(
-    mut request: tiny_http::Request,
+    mut request: ApiRequest,
+    guard: &CommandGuard,
     cmd_tx: &Sender<ApiCommand>,
-    response_rx: &Receiver<ApiResponse>,
+    responses: &Correlator,
 ) {
     let mut body = String::new();
     if let Err(e) = std::io::Read::read_to_string(&mut request.as_reader(), &mut body) {
@@ -255,6 +486,7 @@ fn handle_preview_request(
             success: false,
             message: format!("Failed to read body: {}", e),
             data: None,
+            request_id: None,
         });
         return;
     }
@@ -266,6 +498,7 @@ fn handle_preview_request(
                 success: false,
                 message: format!("Invalid JSON: {}", e),
                 data: None,
+                request_id: None,
             });
             return;
         }
@@ -273,56 +506,263 @@ fn handle_preview_request(
 
     cmd.action = "preview".to_string();
 
-    if let Err(e) = cmd_tx.send(cmd) {
+    forward_query(request, cmd, guard, cmd_tx, responses);
+}
+
+/// Body is an `ApiCommand` without `action`; the endpoint sets it.
+fn handle_action_request(
+    mut request: ApiRequest,
+    action: &str,
+    guard: &CommandGuard,
+    cmd_tx: &Sender<ApiCommand>,
+    responses: &Correlator,
+) {
+    let mut body = String::new();
+    if let Err(e) = std::io::Read::read_to_string(&mut request.as_reader(), &mut body) {
         send_json_response(request, &ApiResponse {
             success: false,
-            message: format!("Failed to send command: {}", e),
+            message: format!("Failed to read body: {}", e),
             data: None,
+            request_id: None,
         });
         return;
     }
 
-    let resp = match response_rx.recv_timeout(std::time::Duration::from_secs(5)) {
-        Ok(resp) => resp,
-        Err(_) => ApiResponse {
-            success: false,
-            message: "Timeout waiting for response".to_string(),
-            data: None,
-        },
+    let mut value: serde_json::Value = match serde_json::from_str(&body) {
+        Ok(v) => v,
+        Err(e) => {
+            send_json_response(request, &ApiResponse {
+                success: false,
+                message: format!("Invalid JSON: {}", e),
+                data: None,
+                request_id: None,
+            });
+            return;
+        }
     };
-    send_json_response(request, &resp);
+    if let Some(obj) = value.as_object_mut() {
+        obj.insert("action".to_string(), serde_json::Value::from(action));
+    }
+    let cmd: ApiCommand = match serde_json::from_value(value) {
+        Ok(c) => c,
+        Err(e) => {
+            send_json_response(request, &ApiResponse {
+                success: false,
+                message: format!("Invalid command: {}", e),
+                data: None,
+                request_id: None,
+            });
+            return;
+        }
+    };
+
+    forward_query(request, cmd, guard, cmd_tx, responses);
 }
 
-fn handle_status_request(
-    request: tiny_http::Request,
+/// GET endpoint without a body: forwards `action` and returns the agent's answer.
+fn handle_query_request(
+    request: ApiRequest,
+    action: &str,
+    guard: &CommandGuard,
     cmd_tx: &Sender<ApiCommand>,
-    response_rx: &Receiver<ApiResponse>,
+    responses: &Correlator,
 ) {
     let cmd = ApiCommand {
-        action: "status".to_string(),
+        action: action.to_string(),
         symbol: "".to_string(),
         side: None,
         qty: None,
         leverage: None,
+        trigger_price: None,
+        valid_for_sec: None,
+        order_id: None,
+        oco_group: None,
+        account: None,
+        limit: None,
+        agent: None,
+        config: None,
+        price: None,
+        duration_sec: None,
+        request_id: None,
     };
+    forward_query(request, cmd, guard, cmd_tx, responses);
+}
 
-    if let Err(e) = cmd_tx.send(cmd) {
-        send_json_response(request, &ApiResponse {
-            success: false,
-            message: format!("Failed: {}", e),
-            data: None,
-        });
+/// GET /trades?account=&symbol=&limit=: fills from the exchange's trade blotter, newest first.
+fn handle_trades_request(
+    request: ApiRequest,
+    query: &str,
+    guard: &CommandGuard,
+    cmd_tx: &Sender<ApiCommand>,
+    responses: &Correlator,
+) {
+    let mut cmd = ApiCommand {
+        action: "trades".to_string(),
+        symbol: "".to_string(),
+        side: None,
+        qty: None,
+        leverage: None,
+        trigger_price: None,
+        valid_for_sec: None,
+        order_id: None,
+        oco_group: None,
+        account: None,
+        limit: None,
+        agent: None,
+        config: None,
+        price: None,
+        duration_sec: None,
+        request_id: None,
+    };
+    for (key, value) in query.split('&').filter_map(|kv| kv.split_once('=')) {
+        let parsed = match key {
+            "account" => value.parse().map(|v| cmd.account = Some(v)).is_ok(),
+            "limit" => value.parse().map(|v| cmd.limit = Some(v)).is_ok(),
+            "symbol" => {
+                cmd.symbol = value.to_string();
+                true
+            }
+            _ => true,
+        };
+        if !parsed {
+            send_json_response(
+                request,
+                &ApiResponse {
+                    success: false,
+                    message: format!("Invalid {}: '{}'", key, value),
+                    data: None,
+                    request_id: None,
+                },
+            );
+            return;
+        }
+    }
+    forward_query(request, cmd, guard, cmd_tx, responses);
+}
+
+/// Send `cmd` to the agent and return its answer as the response. Commands the guard
+/// rejects and commands that find the agent's queue full never reach it.
+fn forward_query(
+    request: ApiRequest,
+    mut cmd: ApiCommand,
+    guard: &CommandGuard,
+    cmd_tx: &Sender<ApiCommand>,
+    responses: &Correlator,
+) {
+    let client = request.remote_addr().map(|addr| addr.ip().to_string()).unwrap_or_default();
+    if let Err(rejection) = guard.check(&client, &cmd) {
+        let mut response = json_response(&rejection.to_response()).with_status_code(rejection.status());
+        if let Rejection::RateLimited { retry_after_ms } = rejection {
+            let retry_after = retry_after_ms.div_ceil(1000).to_string();
+            response.add_header(tiny_http::Header::from_bytes(&b"Retry-After"[..], retry_after.as_bytes()).unwrap());
+        }
+        let _ = request.respond(response);
         return;
     }
 
+    let (request_id, response_rx) = responses.register();
+    cmd.request_id = Some(request_id.clone());
+    match cmd_tx.try_send(cmd) {
+        Ok(()) => {}
+        Err(TrySendError::Full(_)) => {
+            responses.forget(&request_id);
+            let busy = ApiResponse {
+                success: false,
+                message: "Command queue is full, retry later".to_string(),
+                data: Some(serde_json::json!({"error": "queue_full"})),
+                request_id: None,
+            };
+            let _ = request.respond(json_response(&busy).with_status_code(503));
+            return;
+        }
+        Err(e) => {
+            responses.forget(&request_id);
+            send_json_response(request, &ApiResponse {
+                success: false,
+                message: format!("Failed: {}", e),
+                data: None,
+                request_id: None,
+            });
+            return;
+        }
+    }
+
     let resp = match response_rx.recv_timeout(std::time::Duration::from_secs(5)) {
         Ok(resp) => resp,
-        Err(_) => ApiResponse {
-            success: false,
-            message: "Timeout".to_string(),
-            data: None,
-        },
+        Err(_) => {
+            responses.forget(&request_id);
+            ApiResponse {
+                success: false,
+                message: "Timeout".to_string(),
+                data: None,
+                request_id: Some(request_id),
+            }
+        }
     };
     send_json_response(request, &resp);
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn answer(request_id: Option<&str>) -> ApiResponse {
+        ApiResponse {
+            success: true,
+            message: String::new(),
+            data: None,
+            request_id: request_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_answers_reach_their_own_request() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let responses = Correlator::new(rx);
+        let (first, first_rx) = responses.register();
+        let (second, second_rx) = responses.register();
+        assert_ne!(first, second);
+        assert!(first.starts_with(HTTP_REQUEST_ID_PREFIX));
+
+        // WS answers, and answers to forgotten requests, go nowhere
+        let (late, late_rx) = responses.register();
+        responses.forget(&late);
+        tx.send(answer(None)).unwrap();
+        tx.send(answer(Some("ws-1"))).unwrap();
+        tx.send(answer(Some(&late))).unwrap();
+        tx.send(answer(Some(&second))).unwrap();
+        tx.send(answer(Some(&first))).unwrap();
+
+        let timeout = Duration::from_secs(1);
+        assert_eq!(first_rx.recv_timeout(timeout).unwrap().request_id, Some(first));
+        assert_eq!(second_rx.recv_timeout(timeout).unwrap().request_id, Some(second));
+        assert!(late_rx.recv_timeout(Duration::from_millis(50)).is_err());
+        assert!(responses.pending.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pool_turns_jobs_away_when_queue_is_full() {
+        let (started_tx, started_rx) = crossbeam_channel::unbounded();
+        let (release_tx, release_rx) = crossbeam_channel::unbounded::<()>();
+        let (done_tx, done_rx) = crossbeam_channel::unbounded();
+        let pool = RequestPool::new(1, 1, move |job: u32| {
+            started_tx.send(job).unwrap();
+            release_rx.recv().unwrap();
+            done_tx.send(job).unwrap();
+        });
+
+        let timeout = Duration::from_secs(1);
+        assert_eq!(pool.submit(1), Ok(()));
+        // The only worker is busy with job 1: job 2 waits in the queue, job 3 is given back
+        assert_eq!(started_rx.recv_timeout(timeout), Ok(1));
+        assert_eq!(pool.submit(2), Ok(()));
+        assert_eq!(pool.submit(3), Err(3));
+
+        release_tx.send(()).unwrap();
+        release_tx.send(()).unwrap();
+        assert_eq!(done_rx.recv_timeout(timeout), Ok(1));
+        assert_eq!(done_rx.recv_timeout(timeout), Ok(2));
+        assert_eq!(pool.submit(4), Ok(()));
+    }
+}
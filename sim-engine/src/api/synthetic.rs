@@ -0,0 +1,181 @@
+// Synthetic prices for offline and multi-day fast-forward runs.
+//
+// Every symbol follows a seeded geometric random walk from its start price.
+// Each fetch call is one oracle block: the provider's clock moves by `step_ns`
+// and each requested price by daily_vol * sqrt(step / 1 day) * N(0,1), with
+// the volatility scaled by the activity schedule at that time. The same seed
+// gives the same path. Prices carry no VAA, so an on-chain exchange needs the
+// contract's dev price mode.
+
+use super::provider::{PriceProvider, SignedPriceData};
+use crate::activity_schedule::ActivitySchedule;
+use crate::scenarios::population::Rng;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::sync::Mutex;
+
+const NS_PER_DAY: f64 = 86_400e9;
+
+#[derive(Debug, Clone)]
+pub struct SyntheticConfig {
+    /// Start price per symbol (micro-USD)
+    pub start_prices: BTreeMap<String, u64>,
+    /// Standard deviation of daily log returns, in percent
+    pub daily_vol_pct: f64,
+    pub seed: u64,
+    /// Unix time of the first fetch
+    pub start_ns: u64,
+    /// Clock advance per fetch call (the oracle's block interval)
+    pub step_ns: u64,
+    pub schedule: ActivitySchedule,
+}
+
+struct WalkState {
+    rng: Rng,
+    prices: BTreeMap<String, f64>,
+    /// Time of the last fetch; None before the first
+    now_ns: Option<u64>,
+}
+
+pub struct SyntheticProvider {
+    config: SyntheticConfig,
+    state: Mutex<WalkState>,
+}
+
+impl SyntheticProvider {
+    pub fn new(config: SyntheticConfig) -> Self {
+        let state = WalkState {
+            rng: Rng::new(config.seed),
+            prices: config
+                .start_prices
+                .iter()
+                .map(|(s, p)| (s.clone(), *p as f64))
+                .collect(),
+            now_ns: None,
+        };
+        Self {
+            config,
+            state: Mutex::new(state),
+        }
+    }
+}
+
+impl PriceProvider for SyntheticProvider {
+    fn fetch_signed_price(&self, symbol: &str) -> Result<SignedPriceData, Box<dyn Error>> {
+        self.fetch_batch(&[symbol])
+            .pop()
+            .unwrap_or_else(|| Err("no result".into()))
+    }
+
+    fn provider_name(&self) -> &str {
+        "Synthetic"
+    }
+
+    fn supported_symbols(&self) -> Vec<String> {
+        self.config.start_prices.keys().cloned().collect()
+    }
+
+    fn fetch_batch(&self, symbols: &[&str]) -> Vec<Result<SignedPriceData, Box<dyn Error>>> {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        let (now_ns, sigma) = match state.now_ns {
+            None => (self.config.start_ns, 0.0),
+            Some(last) => {
+                let now_ns = last + self.config.step_ns;
+                let volatility = self.config.schedule.at(now_ns).volatility;
+                let sigma =
+                    self.config.daily_vol_pct / 100.0 * volatility * (self.config.step_ns as f64 / NS_PER_DAY).sqrt();
+                (now_ns, sigma)
+            }
+        };
+        state.now_ns = Some(now_ns);
+        symbols
+            .iter()
+            .map(|symbol| {
+                let Some(price) = state.prices.get_mut(*symbol) else {
+                    return Err(format!("{}: no synthetic start price", symbol).into());
+                };
+                // Drift-free in expectation: E[exp(sigma*Z - sigma^2/2)] = 1
+                *price *= (sigma * state.rng.normal() - sigma * sigma / 2.0).exp();
+                let price_usd_micro = price.round().max(1.0) as u64;
+                Ok(SignedPriceData {
+                    symbol: symbol.to_string(),
+                    price_usd_micro,
+                    confidence: None,
+                    ema_price: None,
+                    publish_time: now_ns / 1_000_000_000,
+                    signature: Vec::new(),
+                    provider_name: "Synthetic".to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activity_schedule::ActivitySession;
+
+    /// Monday 2024-01-01 00:00 UTC
+    const MONDAY: u64 = 1_704_067_200 * 1_000_000_000;
+    const MINUTE: u64 = 60_000_000_000;
+
+    fn provider(schedule: ActivitySchedule, seed: u64) -> SyntheticProvider {
+        SyntheticProvider::new(SyntheticConfig {
+            start_prices: BTreeMap::from([("ETH-USD".to_string(), 3_000_000_000)]),
+            daily_vol_pct: 5.0,
+            seed,
+            start_ns: MONDAY,
+            step_ns: MINUTE,
+            schedule,
+        })
+    }
+
+    /// Realized standard deviation of one-minute log returns over `n` fetches
+    fn realized_vol(provider: &SyntheticProvider, n: usize) -> f64 {
+        let prices: Vec<f64> = (0..n)
+            .map(|_| provider.fetch_signed_price("ETH-USD").unwrap().price_usd_micro as f64)
+            .collect();
+        let returns: Vec<f64> = prices.windows(2).map(|w| (w[1] / w[0]).ln()).collect();
+        let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+        (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / returns.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn test_starts_at_start_price_and_is_reproducible() {
+        let a = provider(ActivitySchedule::default(), 7);
+        let b = provider(ActivitySchedule::default(), 7);
+        let first = a.fetch_signed_price("ETH-USD").unwrap();
+        assert_eq!(first.price_usd_micro, 3_000_000_000);
+        assert_eq!(first.publish_time, MONDAY / 1_000_000_000);
+        b.fetch_signed_price("ETH-USD").unwrap();
+        for _ in 0..100 {
+            assert_eq!(
+                a.fetch_signed_price("ETH-USD").unwrap().price_usd_micro,
+                b.fetch_signed_price("ETH-USD").unwrap().price_usd_micro
+            );
+        }
+        assert!(a.fetch_signed_price("BTC-USD").is_err());
+    }
+
+    #[test]
+    fn test_schedule_scales_volatility() {
+        // 1 day of minutes; per-minute sigma = 5% / sqrt(1440)
+        let expected = 0.05 / 1440f64.sqrt();
+        let calm = realized_vol(&provider(ActivitySchedule::default(), 1), 1441);
+        assert!((calm / expected - 1.0).abs() < 0.1, "calm {}", calm);
+
+        let busy = ActivitySchedule {
+            sessions: vec![ActivitySession {
+                start_hour: 0.0,
+                end_hour: 24.0,
+                volatility: 3.0,
+                volume: 1.0,
+            }],
+            weekend: None,
+        };
+        let stressed = realized_vol(&provider(busy, 1), 1441);
+        assert!((stressed / (3.0 * expected) - 1.0).abs() < 0.1, "stressed {}", stressed);
+    }
+}
@@ -1,14 +1,197 @@
-use crate::api::{ApiCommand, ApiResponse};
+use crate::api::{ApiCommand, ApiResponse, CommandGuard, Rejection, HTTP_REQUEST_ID_PREFIX};
 use crate::events::SimEvent;
-use crossbeam_channel::{Receiver, Sender};
+use crate::messages::AgentId;
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender, TrySendError};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::net::TcpListener;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::{TcpListener, TcpStream};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use tungstenite::accept;
-use tungstenite::Message;
+use std::time::Duration;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::{accept_hdr, Message, WebSocket};
+
+/// Path prefix of remote agent sessions: `ws://host:port/remote/<agent name>`
+pub const REMOTE_PATH_PREFIX: &str = "/remote/";
+
+/// Path prefix of per-client trading sessions: `ws://host:port/session/<token>`
+pub const SESSION_PATH_PREFIX: &str = "/session/";
+
+/// Read timeout of a remote session socket; bounds the added round-trip latency
+const REMOTE_POLL: Duration = Duration::from_millis(5);
+
+/// Server side of one remote agent: what the agent sends out and where client lines go.
+#[derive(Clone)]
+pub(crate) struct RemoteEndpoint {
+    pub(crate) outbound: Receiver<String>,
+    pub(crate) inbound: Sender<String>,
+    pub(crate) attached: Arc<AtomicBool>,
+}
+
+/// Remote agents that external processes can attach to over the WebSocket server.
+#[derive(Clone, Default)]
+pub struct RemoteHub {
+    endpoints: Arc<Mutex<HashMap<String, RemoteEndpoint>>>,
+}
+
+impl RemoteHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an agent under `name`; the returned link is its side of the session.
+    pub fn register(&self, name: &str) -> RemoteLink {
+        let (out_tx, out_rx) = crossbeam_channel::unbounded();
+        let (in_tx, in_rx) = crossbeam_channel::unbounded();
+        let attached = Arc::new(AtomicBool::new(false));
+        let endpoint = RemoteEndpoint {
+            outbound: out_rx,
+            inbound: in_tx,
+            attached: attached.clone(),
+        };
+        self.endpoints.lock().unwrap().insert(name.to_string(), endpoint);
+        RemoteLink {
+            outbound: out_tx,
+            inbound: in_rx,
+            attached,
+        }
+    }
+
+    /// Claim the endpoint of `name` for a new client; None if unknown or already attached.
+    pub(crate) fn attach(&self, name: &str) -> Option<RemoteEndpoint> {
+        let guard = self.endpoints.lock().unwrap();
+        let endpoint = guard.get(name)?;
+        if endpoint.attached.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+        // Lines queued for a previous client are stale
+        while endpoint.outbound.try_recv().is_ok() {}
+        Some(endpoint.clone())
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.endpoints.lock().unwrap().contains_key(name)
+    }
+}
+
+/// Agent side of a remote session: lines to the attached client and lines from it.
+pub struct RemoteLink {
+    outbound: Sender<String>,
+    inbound: Receiver<String>,
+    attached: Arc<AtomicBool>,
+}
+
+impl RemoteLink {
+    /// Whether an external process is connected
+    pub fn is_attached(&self) -> bool {
+        self.attached.load(Ordering::SeqCst)
+    }
+
+    pub fn send(&self, line: String) -> bool {
+        self.is_attached() && self.outbound.send(line).is_ok()
+    }
+
+    /// Next line from the client; Err(Timeout) when none arrives within `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<String, RecvTimeoutError> {
+        self.inbound.recv_timeout(timeout)
+    }
+
+    /// Drop lines that arrived outside a request (late replies)
+    pub fn drain(&self) -> usize {
+        self.inbound.try_iter().count()
+    }
+}
+
+/// Server side of one trading session: commands to its HumanAgent and the agent's responses.
+#[derive(Clone)]
+struct SessionEndpoint {
+    agent_id: AgentId,
+    commands: Sender<ApiCommand>,
+    responses: Receiver<ApiResponse>,
+    attached: Arc<AtomicBool>,
+}
+
+/// A session opened by a new client; the simulation spawns its HumanAgent with these channels.
+pub struct NewSession {
+    pub agent_id: AgentId,
+    pub command_rx: Receiver<ApiCommand>,
+    pub response_tx: Sender<ApiResponse>,
+}
+
+#[derive(Default)]
+struct SessionState {
+    /// Agent ids not yet given to a session, lowest first
+    free_ids: VecDeque<AgentId>,
+    /// Accepted tokens; empty accepts any
+    tokens: HashSet<String>,
+    /// Sessions by token; they outlive their client so a reconnect resumes the same account
+    sessions: HashMap<String, SessionEndpoint>,
+    /// Sessions whose agent is not spawned yet
+    opened: Vec<NewSession>,
+}
+
+/// Per-client trading sessions: each token gets its own HumanAgent, balance and positions.
+#[derive(Clone, Default)]
+pub struct SessionHub {
+    state: Arc<Mutex<SessionState>>,
+}
+
+impl SessionHub {
+    /// Sessions take their agent ids from `ids`; only `tokens` are accepted unless it is empty.
+    pub fn new(ids: impl IntoIterator<Item = AgentId>, tokens: impl IntoIterator<Item = String>) -> Self {
+        let state = SessionState {
+            free_ids: ids.into_iter().collect(),
+            tokens: tokens.into_iter().collect(),
+            ..SessionState::default()
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Sessions opened since the last call
+    pub fn take_opened(&self) -> Vec<NewSession> {
+        std::mem::take(&mut self.state.lock().unwrap().opened)
+    }
+
+    /// Attach a client to the session of `token`, opening it on first use.
+    fn open(&self, token: &str) -> Result<SessionEndpoint, (StatusCode, String)> {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        if let Some(endpoint) = state.sessions.get(token) {
+            if endpoint.attached.swap(true, Ordering::SeqCst) {
+                return Err((StatusCode::CONFLICT, "session already has a client".to_string()));
+            }
+            // Responses to a previous client are stale
+            while endpoint.responses.try_recv().is_ok() {}
+            return Ok(endpoint.clone());
+        }
+        if token.is_empty() || !(state.tokens.is_empty() || state.tokens.contains(token)) {
+            return Err((StatusCode::FORBIDDEN, "unknown session token".to_string()));
+        }
+        let Some(agent_id) = state.free_ids.pop_front() else {
+            return Err((StatusCode::SERVICE_UNAVAILABLE, "no free trading session".to_string()));
+        };
+        let (cmd_tx, cmd_rx) = crossbeam_channel::unbounded();
+        let (resp_tx, resp_rx) = crossbeam_channel::unbounded();
+        let endpoint = SessionEndpoint {
+            agent_id,
+            commands: cmd_tx,
+            responses: resp_rx,
+            attached: Arc::new(AtomicBool::new(true)),
+        };
+        state.sessions.insert(token.to_string(), endpoint.clone());
+        state.opened.push(NewSession {
+            agent_id,
+            command_rx: cmd_rx,
+            response_tx: resp_tx,
+        });
+        Ok(endpoint)
+    }
+}
 
 /// WebSocket Server handle
 pub struct WsServer {
@@ -34,12 +217,19 @@ enum WsMessage {
 }
 
 impl WsServer {
-    /// Start the WebSocket server on the given port
+    /// Start the WebSocket server on the given port. Clients on `/remote/<name>` drive the
+    /// remote agent `name` registered in `remotes`; clients on `/session/<token>` trade through
+    /// their own HumanAgent from `sessions`. All others share the API HumanAgent. Every client
+    /// gets the events; command responses go only to the clients of the agent that answered.
+    /// Commands pass `guard` first, per session or per remote IP.
     pub fn start(
         port: u16,
         cmd_tx: Sender<ApiCommand>,
         event_rx: Receiver<SimEvent>,
         response_rx: Receiver<ApiResponse>,
+        remotes: RemoteHub,
+        sessions: SessionHub,
+        guard: Arc<CommandGuard>,
     ) -> Self {
         let shutdown = Arc::new(AtomicBool::new(false));
         let shutdown_clone = shutdown.clone();
@@ -67,6 +257,8 @@ impl WsServer {
         // Let's spawn 2 threads per client: Reader and Writer.
         
         let clients: Arc<Mutex<Vec<Sender<String>>>> = Arc::new(Mutex::new(Vec::new()));
+        // Clients of the shared API HumanAgent (not in a session)
+        let shared_clients: Arc<Mutex<Vec<Sender<String>>>> = Arc::new(Mutex::new(Vec::new()));
 
         let thread_handle = thread::spawn(move || {
             let addr = format!("0.0.0.0:{}", port);
@@ -104,7 +296,7 @@ impl WsServer {
             });
 
             // Thread for broadcasting command responses (from HumanAgent)
-            let response_clients = clients.clone();
+            let response_clients = shared_clients.clone();
             thread::spawn(move || {
                 while let Ok(resp) = response_rx.recv() {
                      let json = match serde_json::to_string(&WsMessage::Response(resp)) {
@@ -127,29 +319,113 @@ impl WsServer {
                 match stream {
                     Ok(stream) => {
                         let clients_inner = clients.clone();
+                        let shared_clients_inner = shared_clients.clone();
                         let cmd_tx_inner = cmd_tx.clone();
+                        let remotes_inner = remotes.clone();
+                        let sessions_inner = sessions.clone();
+                        let guard_inner = guard.clone();
                         
                         thread::spawn(move || {
                             let peer_addr = stream.peer_addr().unwrap();
                             println!("[WsServer] New connection: {}", peer_addr);
 
-                            let mut websocket = match accept(stream) {
+                            let mut remote_name = None;
+                            let mut session = None;
+                            let check_path = |req: &Request, resp: Response| -> Result<Response, ErrorResponse> {
+                                if let Some(token) = req.uri().path().strip_prefix(SESSION_PATH_PREFIX) {
+                                    return match sessions_inner.open(token) {
+                                        Ok(endpoint) => {
+                                            session = Some(endpoint);
+                                            Ok(resp)
+                                        }
+                                        Err((status, reason)) => {
+                                            let mut err = ErrorResponse::new(Some(reason));
+                                            *err.status_mut() = status;
+                                            Err(err)
+                                        }
+                                    };
+                                }
+                                let Some(name) = req.uri().path().strip_prefix(REMOTE_PATH_PREFIX) else {
+                                    return Ok(resp);
+                                };
+                                if !remotes_inner.contains(name) {
+                                    let mut err = ErrorResponse::new(Some(format!("unknown remote agent '{}'", name)));
+                                    *err.status_mut() = StatusCode::NOT_FOUND;
+                                    return Err(err);
+                                }
+                                remote_name = Some(name.to_string());
+                                Ok(resp)
+                            };
+                            let mut websocket = match accept_hdr(stream, check_path) {
                                 Ok(ws) => ws,
                                 Err(e) => {
                                     eprintln!("[WsServer] Handshake error: {}", e);
+                                    if let Some(endpoint) = &session {
+                                        endpoint.attached.store(false, Ordering::SeqCst);
+                                    }
                                     return;
                                 }
                             };
 
+                            if let Some(name) = remote_name {
+                                match remotes_inner.attach(&name) {
+                                    Some(endpoint) => {
+                                        println!("[WsServer] {} attached to remote agent {}", peer_addr, name);
+                                        run_remote_session(&mut websocket, &endpoint);
+                                        endpoint.attached.store(false, Ordering::SeqCst);
+                                        println!("[WsServer] {} detached from remote agent {}", peer_addr, name);
+                                    }
+                                    None => {
+                                        let err_msg = json!({
+                                            "type": "Error",
+                                            "payload": format!("remote agent '{}' already has a client", name)
+                                        }).to_string();
+                                        let _ = websocket.send(Message::Text(err_msg.into()));
+                                        let _ = websocket.close(None);
+                                    }
+                                }
+                                return;
+                            }
+
                             // Channel to send messages to this client
                             let (tx, rx) = crossbeam_channel::unbounded::<String>();
                             
                             // Add to clients list
                             {
                                 let mut guard = clients_inner.lock().unwrap();
-                                guard.push(tx);
+                                guard.push(tx.clone());
                             }
 
+                            // Session clients command their own agent and get only its responses
+                            let commands = match &session {
+                                Some(endpoint) => {
+                                    println!(
+                                        "[WsServer] {} attached to session agent {}",
+                                        peer_addr, endpoint.agent_id
+                                    );
+                                    let welcome = WsMessage::Response(ApiResponse {
+                                        success: true,
+                                        message: format!("session agent {}", endpoint.agent_id),
+                                        data: Some(json!({ "agent": endpoint.agent_id })),
+                                        request_id: None,
+                                    });
+                                    if let Ok(json) = serde_json::to_string(&welcome) {
+                                        let _ = tx.send(json);
+                                    }
+                                    endpoint.commands.clone()
+                                }
+                                None => {
+                                    shared_clients_inner.lock().unwrap().push(tx);
+                                    cmd_tx_inner
+                                }
+                            };
+                            let client = match &session {
+                                Some(endpoint) => format!("session:{}", endpoint.agent_id),
+                                None => peer_addr.ip().to_string(),
+                            };
+                            let session_inner = session.clone();
+                            let _detach = DetachOnDrop(session.map(|endpoint| endpoint.attached));
+
                             // We need to handle both reading and writing. 
                             // Since `websocket` is a single object, we can't easily split it across threads without `Arc<Mutex>` which kills concurrency 
                             // or using `start_threading` features if available, but standard tungstenite is simple.
@@ -163,7 +439,11 @@ impl WsServer {
                             loop {
                                 // 1. Try to read from channel and write to socket
                                 let mut wrote = false;
-                                while let Ok(msg) = rx.try_recv() {
+                                let session_responses = session_inner
+                                    .iter()
+                                    .flat_map(|endpoint| endpoint.responses.try_iter())
+                                    .filter_map(|resp| serde_json::to_string(&WsMessage::Response(resp)).ok());
+                                for msg in rx.try_iter().chain(session_responses) {
                                     if let Err(e) = websocket.send(Message::Text(msg.into())) {
                                         eprintln!("[WsServer] Write error to {}: {}", peer_addr, e);
                                         return; // Disconnect
@@ -186,7 +466,11 @@ impl WsServer {
                                             match serde_json::from_str::<ApiCommand>(&text) {
                                                 Ok(cmd) => {
                                                     println!("[WsServer] Cmd from {}: {:?}", peer_addr, cmd);
-                                                    let _ = cmd_tx_inner.send(cmd);
+                                                    if let Some(resp) = submit(&guard_inner, &client, &commands, cmd) {
+                                                        let json = serde_json::to_string(&WsMessage::Response(resp))
+                                                            .unwrap_or_default();
+                                                        let _ = websocket.send(Message::Text(json.into()));
+                                                    }
                                                 },
                                                 Err(e) => {
                                                     // Maybe it's wrapped in WsMessage::Command?
@@ -239,6 +523,76 @@ impl WsServer {
     }
 }
 
+/// Pump lines between an attached client and its remote agent until either side goes away.
+fn run_remote_session(websocket: &mut WebSocket<TcpStream>, endpoint: &RemoteEndpoint) {
+    websocket.get_ref().set_read_timeout(Some(REMOTE_POLL)).ok();
+    loop {
+        let mut wrote = false;
+        while let Ok(line) = endpoint.outbound.try_recv() {
+            if websocket.write(Message::Text(line.into())).is_err() {
+                return;
+            }
+            wrote = true;
+        }
+        if wrote && websocket.flush().is_err() {
+            return;
+        }
+        match websocket.read() {
+            Ok(msg) if msg.is_text() || msg.is_binary() => {
+                if endpoint.inbound.send(msg.to_string()).is_err() {
+                    // Agent gone (simulation stopped)
+                    let _ = websocket.close(None);
+                    return;
+                }
+            }
+            Ok(msg) if msg.is_close() => return,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(ref e))
+                if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {}
+            Err(_) => return,
+        }
+    }
+}
+
+/// Pass `cmd` to the agent unless the guard rejects it or the agent's queue is full;
+/// the error response, carrying the command's `request_id`, otherwise.
+fn submit(guard: &CommandGuard, client: &str, commands: &Sender<ApiCommand>, cmd: ApiCommand) -> Option<ApiResponse> {
+    let request_id = cmd.request_id.clone();
+    let rejected = |mut resp: ApiResponse| {
+        resp.request_id = request_id.clone();
+        Some(resp)
+    };
+    if request_id.as_deref().is_some_and(|id| id.starts_with(HTTP_REQUEST_ID_PREFIX)) {
+        // Its answer would go to the HTTP server
+        let reason = format!("must not start with '{}'", HTTP_REQUEST_ID_PREFIX);
+        return rejected(Rejection::Invalid { field: "request_id", reason }.to_response());
+    }
+    if let Err(rejection) = guard.check(client, &cmd) {
+        return rejected(rejection.to_response());
+    }
+    match commands.try_send(cmd) {
+        Err(TrySendError::Full(_)) => rejected(ApiResponse {
+            success: false,
+            message: "Command queue is full, retry later".to_string(),
+            data: Some(json!({"error": "queue_full"})),
+            request_id: None,
+        }),
+        // A disconnected agent means the simulation stopped
+        _ => None,
+    }
+}
+
+/// Frees a session for a new client when its client thread ends.
+struct DetachOnDrop(Option<Arc<AtomicBool>>);
+
+impl Drop for DetachOnDrop {
+    fn drop(&mut self) {
+        if let Some(attached) = &self.0 {
+            attached.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
 fn broadcast_to_all(clients: &Arc<Mutex<Vec<Sender<String>>>>, msg: &str) {
     let mut guard = clients.lock().unwrap();
     // Retain only active clients (those where send succeeds)
@@ -247,3 +601,66 @@ fn broadcast_to_all(clients: &Arc<Mutex<Vec<Sender<String>>>>, msg: &str) {
     });
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(t: &str) -> String {
+        t.to_string()
+    }
+
+    fn status() -> ApiCommand {
+        serde_json::from_str(r#"{ "action": "status" }"#).unwrap()
+    }
+
+    #[test]
+    fn test_sessions_get_own_agents_and_resume_on_reconnect() {
+        let hub = SessionHub::new([101, 102], [token("alice"), token("bob"), token("carol")]);
+        let alice = hub.open("alice").unwrap();
+        let bob = hub.open("bob").unwrap();
+        assert_eq!((alice.agent_id, bob.agent_id), (101, 102));
+        let opened = hub.take_opened();
+        assert_eq!(opened.iter().map(|s| s.agent_id).collect::<Vec<_>>(), vec![101, 102]);
+        assert!(hub.take_opened().is_empty());
+
+        // Commands reach the session's own agent only
+        alice.commands.send(status()).unwrap();
+        assert_eq!(opened[0].command_rx.len(), 1);
+        assert!(opened[1].command_rx.is_empty());
+
+        // One client per session; after a disconnect the same token resumes the same agent
+        assert_eq!(hub.open("alice").unwrap_err().0, StatusCode::CONFLICT);
+        alice.attached.store(false, Ordering::SeqCst);
+        assert_eq!(hub.open("alice").unwrap().agent_id, 101);
+        assert!(hub.take_opened().is_empty());
+
+        // Ids exhausted, unknown token
+        assert_eq!(hub.open("carol").unwrap_err().0, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(hub.open("mallory").unwrap_err().0, StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_empty_token_list_accepts_any_token() {
+        let hub = SessionHub::new([200], Vec::new());
+        assert_eq!(hub.open("").unwrap_err().0, StatusCode::FORBIDDEN);
+        assert_eq!(hub.open("anyone").unwrap().agent_id, 200);
+    }
+
+    #[test]
+    fn test_rejections_echo_the_request_id() {
+        let guard = CommandGuard::default();
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        let command = |json: &str| serde_json::from_str::<ApiCommand>(json).unwrap();
+
+        assert!(submit(&guard, "client", &tx, command(r#"{ "action": "status", "request_id": "a" }"#)).is_none());
+        assert_eq!(rx.try_recv().unwrap().request_id.as_deref(), Some("a"));
+
+        let resp = submit(&guard, "client", &tx, command(r#"{ "action": "open", "qty": 0, "request_id": "b" }"#));
+        assert_eq!(resp.unwrap().request_id.as_deref(), Some("b"));
+
+        // Ids of the HTTP server are reserved
+        let resp = submit(&guard, "client", &tx, command(r#"{ "action": "status", "request_id": "http-1" }"#)).unwrap();
+        assert_eq!(resp.data.unwrap()["field"], "request_id");
+        assert!(rx.is_empty());
+    }
+}
@@ -0,0 +1,247 @@
+// Headless throughput benchmark.
+//
+// `bench` runs a synthetic workload with no chain, price provider or logs:
+// N agents that together send M messages per tick to each other, for a fixed
+// number of ticks. It reports delivered messages per second of wall time and,
+// with the binary's counting allocator installed, heap allocations per
+// message. The criterion benches in benches/ reuse `workload`.
+//
+// Optionally the agents also emit OracleTick events to listeners that only
+// handle executions (the shape of most CSV loggers); `broadcast` subscribes
+// them unfiltered to measure what routing events by kind saves.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use crate::agents::Agent;
+use crate::events::{EventKind, EventListener, SimEvent};
+use crate::messages::{AgentId, MessagePayload, MessageType, SimulatorApi};
+use crate::simulation::SimulationBuilder;
+
+const TICK_MS: u64 = 1_000;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// System allocator that counts allocations; install it with `#[global_allocator]`.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// (allocations, bytes) since start; stays zero without the counting allocator.
+pub fn allocation_stats() -> (u64, u64) {
+    (
+        ALLOCATIONS.load(Ordering::Relaxed),
+        ALLOCATED_BYTES.load(Ordering::Relaxed),
+    )
+}
+
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    pub agents: u32,
+    /// Messages sent per tick by all agents together
+    pub messages_per_tick: u32,
+    pub ticks: u32,
+    /// OracleTick events emitted per tick by all agents together
+    pub events_per_tick: u32,
+    /// Execution-only listeners on the event bus
+    pub listeners: u32,
+    /// Deliver every event to every listener instead of routing by kind
+    pub broadcast: bool,
+}
+
+/// Wakes every tick and sends its share of the messages round-robin to the other agents.
+struct BenchAgent {
+    id: AgentId,
+    agents: u32,
+    sends: u32,
+    events: u32,
+    next_peer: u32,
+}
+
+impl Agent for BenchAgent {
+    fn id(&self) -> AgentId {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        "BenchAgent"
+    }
+
+    fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+        sim.wakeup(self.id, sim.now_ns() + TICK_MS * 1_000_000);
+    }
+
+    fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, _now_ns: u64) {
+        for _ in 0..self.sends {
+            self.next_peer = self.next_peer % self.agents + 1;
+            if self.next_peer == self.id && self.agents > 1 {
+                self.next_peer = self.next_peer % self.agents + 1;
+            }
+            sim.send(self.id, self.next_peer, MessageType::MarketData, MessagePayload::Empty);
+        }
+        for _ in 0..self.events {
+            sim.emit_event(SimEvent::OracleTick {
+                ts: sim.now_ns(),
+                symbol: "ETH-USD".to_string(),
+                price_min: 3_000_000_000,
+                price_max: 3_000_000_000,
+            });
+        }
+        sim.wakeup(self.id, sim.now_ns() + TICK_MS * 1_000_000);
+    }
+}
+
+/// Counts executions and ignores everything else, like CsvExecutionLogger without the file.
+struct ExecutionSink {
+    broadcast: bool,
+    executions: u64,
+}
+
+impl EventListener for ExecutionSink {
+    fn on_event(&mut self, event: &SimEvent) {
+        if let SimEvent::OrderExecuted { .. } = event {
+            self.executions += 1;
+        }
+    }
+
+    fn interests(&self) -> Option<&'static [EventKind]> {
+        if self.broadcast {
+            None
+        } else {
+            Some(&[EventKind::OrderExecuted])
+        }
+    }
+}
+
+/// Split `total` over agents 1..=N; the first `total % N` agents take one more.
+fn share(total: u32, agents: u32, id: u32) -> u32 {
+    total / agents + u32::from(id <= total % agents)
+}
+
+/// Synthetic run: agents 1..=N sharing the messages and events of each tick.
+pub fn workload(opts: &BenchOptions) -> SimulationBuilder {
+    let agents = opts.agents.max(1);
+    let mut builder = SimulationBuilder::new("bench")
+        .tick_ms(TICK_MS)
+        .duration_sec(opts.ticks as u64 * TICK_MS / 1_000)
+        .start_ns(0)
+        .quiet();
+    for id in 1..=agents {
+        builder = builder.agent(Box::new(BenchAgent {
+            id,
+            agents,
+            sends: share(opts.messages_per_tick, agents, id),
+            events: share(opts.events_per_tick, agents, id),
+            next_peer: id,
+        }));
+    }
+    for _ in 0..opts.listeners {
+        builder = builder.listener(Box::new(ExecutionSink {
+            broadcast: opts.broadcast,
+            executions: 0,
+        }));
+    }
+    builder
+}
+
+pub fn run(opts: &BenchOptions) -> Result<(), String> {
+    if opts.agents == 0 || opts.ticks == 0 {
+        return Err("--agents and --ticks must be > 0".to_string());
+    }
+    println!(
+        "[Bench] {} agents, {} messages/tick, {} ticks",
+        opts.agents, opts.messages_per_tick, opts.ticks
+    );
+    if opts.events_per_tick > 0 {
+        println!(
+            "[Bench] {} events/tick, {} execution-only listeners ({})",
+            opts.events_per_tick,
+            opts.listeners,
+            if opts.broadcast { "broadcast" } else { "routed by kind" }
+        );
+    }
+
+    let builder = workload(opts);
+    let (allocs_before, bytes_before) = allocation_stats();
+    let started = Instant::now();
+    let result = builder.run();
+    let elapsed = started.elapsed().as_secs_f64();
+    let (allocs_after, bytes_after) = allocation_stats();
+
+    let messages = result.messages.max(1) as f64;
+    println!(
+        "[Bench] {} messages in {:.3}s: {:.0} msg/s",
+        result.messages,
+        elapsed,
+        result.messages as f64 / elapsed.max(1e-9)
+    );
+    if allocs_after > allocs_before {
+        let allocs = allocs_after - allocs_before;
+        let bytes = bytes_after - bytes_before;
+        println!(
+            "[Bench] {} allocations ({:.1} MB): {:.2} allocs/msg, {:.0} B/msg",
+            allocs,
+            bytes as f64 / 1e6,
+            allocs as f64 / messages,
+            bytes as f64 / messages
+        );
+    } else {
+        println!("[Bench] allocations: n/a (counting allocator not installed)");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workload_delivers_messages_per_tick() {
+        let result = workload(&BenchOptions {
+            agents: 3,
+            messages_per_tick: 10,
+            ticks: 4,
+            events_per_tick: 0,
+            listeners: 0,
+            broadcast: false,
+        })
+        .run();
+        // 3 wakeups per tick; sends arrive 1.5ms later, i.e. on the next tick, so the
+        // last tick's messages stay queued
+        assert_eq!(result.messages, 4 * 3 + 3 * 10);
+    }
+
+    #[test]
+    fn test_workload_emits_events_per_tick() {
+        let opts = BenchOptions {
+            agents: 2,
+            messages_per_tick: 0,
+            ticks: 3,
+            events_per_tick: 5,
+            listeners: 2,
+            broadcast: false,
+        };
+        // The sinks only see executions; the recorder (unfiltered) sees every tick
+        let result = workload(&opts).record_events().run();
+        assert_eq!(result.events.len(), 3 * 5);
+        assert_eq!(result.messages, 3 * 2);
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::VecDeque;
+
+use crate::messages::{AgentId, TradeRecord};
+
+/// Last `capacity` fills of the exchange, oldest dropped first. Lets agents and the API
+/// look at recent trades of an account mid-run without reading trades.csv.
+#[derive(Debug)]
+pub struct TradeBlotter {
+    capacity: usize,
+    trades: VecDeque<TradeRecord>,
+    /// Fills recorded since the start, including dropped ones
+    total: u64,
+}
+
+impl Default for TradeBlotter {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl TradeBlotter {
+    pub const DEFAULT_CAPACITY: usize = 10_000;
+
+    /// Blotter keeping at most `capacity` fills (0 keeps none)
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            trades: VecDeque::with_capacity(capacity.min(Self::DEFAULT_CAPACITY)),
+            total: 0,
+        }
+    }
+
+    pub fn record(&mut self, trade: TradeRecord) {
+        self.total += 1;
+        if self.capacity == 0 {
+            return;
+        }
+        if self.trades.len() == self.capacity {
+            self.trades.pop_front();
+        }
+        self.trades.push_back(trade);
+    }
+
+    /// Newest `limit` fills matching `account` and `symbol` (None matches all), newest first.
+    pub fn query(&self, account: Option<AgentId>, symbol: Option<&str>, limit: usize) -> Vec<TradeRecord> {
+        self.trades
+            .iter()
+            .rev()
+            .filter(|t| account.is_none_or(|a| t.account == a))
+            .filter(|t| symbol.is_none_or(|s| t.symbol == s))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Remove the newest fill `matches` accepts, e.g. a local fill the chain never took.
+    /// `total` keeps counting it.
+    pub fn withdraw(&mut self, matches: impl Fn(&TradeRecord) -> bool) -> Option<TradeRecord> {
+        let idx = self.trades.iter().rposition(matches)?;
+        self.trades.remove(idx)
+    }
+
+    pub fn len(&self) -> usize {
+        self.trades.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trades.is_empty()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{OrderExecutionType, Side};
+
+    fn trade(ts: u64, account: AgentId, symbol: &str) -> TradeRecord {
+        TradeRecord {
+            ts,
+            account,
+            symbol: symbol.to_string(),
+            side: Side::Buy,
+            order_type: OrderExecutionType::Increase,
+            price: 3_000_000_000,
+            size_usd: 1_000_000_000,
+            fee_usd: 1_000_000,
+            pnl: 0,
+        }
+    }
+
+    #[test]
+    fn test_drops_oldest_beyond_capacity() {
+        let mut blotter = TradeBlotter::new(2);
+        for ts in 1..=3 {
+            blotter.record(trade(ts, 10, "ETH-USD"));
+        }
+        assert_eq!(blotter.len(), 2);
+        assert_eq!(blotter.total(), 3);
+        let ts: Vec<u64> = blotter.query(None, None, 10).iter().map(|t| t.ts).collect();
+        assert_eq!(ts, vec![3, 2]);
+    }
+
+    #[test]
+    fn test_filters_by_account_and_symbol() {
+        let mut blotter = TradeBlotter::new(10);
+        blotter.record(trade(1, 10, "ETH-USD"));
+        blotter.record(trade(2, 11, "ETH-USD"));
+        blotter.record(trade(3, 10, "BTC-USD"));
+        blotter.record(trade(4, 10, "ETH-USD"));
+        let ts = |trades: Vec<TradeRecord>| trades.iter().map(|t| t.ts).collect::<Vec<_>>();
+        assert_eq!(ts(blotter.query(Some(10), None, 10)), vec![4, 3, 1]);
+        assert_eq!(ts(blotter.query(Some(10), Some("ETH-USD"), 10)), vec![4, 1]);
+        assert_eq!(ts(blotter.query(None, Some("ETH-USD"), 2)), vec![4, 2]);
+        assert!(blotter.query(Some(12), None, 10).is_empty());
+    }
+
+    #[test]
+    fn test_zero_capacity_keeps_nothing() {
+        let mut blotter = TradeBlotter::new(0);
+        blotter.record(trade(1, 10, "ETH-USD"));
+        assert!(blotter.is_empty());
+        assert_eq!(blotter.total(), 1);
+    }
+
+    #[test]
+    fn test_withdraw_removes_newest_match() {
+        let mut blotter = TradeBlotter::new(10);
+        blotter.record(trade(1, 10, "ETH-USD"));
+        blotter.record(trade(2, 10, "ETH-USD"));
+        blotter.record(trade(3, 11, "ETH-USD"));
+        assert_eq!(blotter.withdraw(|t| t.account == 10).map(|t| t.ts), Some(2));
+        assert!(blotter.withdraw(|t| t.account == 12).is_none());
+        let ts: Vec<u64> = blotter.query(None, None, 10).iter().map(|t| t.ts).collect();
+        assert_eq!(ts, vec![3, 1]);
+        assert_eq!(blotter.total(), 3);
+    }
+}
@@ -0,0 +1,271 @@
+// Trader balances and bankruptcy.
+//
+// The exchange keeps the authoritative balance of every funded trader: the
+// initial deposit plus realized PnL minus fees and funding, plus any
+// recapitalizations. Equity adds the unrealized PnL of open positions. Once
+// equity drops to the bankruptcy threshold the account is bankrupt: its new
+// orders are rejected and it is either removed from the simulation or topped
+// up after a delay (scenario `capital.recapitalize`). Amounts in micro-USD.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::messages::AgentId;
+
+/// Scenario `capital` block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CapitalConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Equity at or below which a trader is bankrupt
+    #[serde(default)]
+    pub bankrupt_below_usd: i64,
+    /// Top up bankrupt traders instead of removing them
+    #[serde(default)]
+    pub recapitalize: Option<Recapitalization>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for CapitalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            bankrupt_below_usd: 0,
+            recapitalize: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Recapitalization {
+    /// Top-up per bankruptcy (default: the initial deposit)
+    #[serde(default)]
+    pub amount_usd: Option<u64>,
+    /// Sim time between the bankruptcy and the top-up
+    #[serde(default)]
+    pub delay_sec: u64,
+    /// Top-ups per account; the next bankruptcy removes the agent (0 = unlimited)
+    #[serde(default = "default_max_times")]
+    pub max_times: u32,
+}
+
+fn default_max_times() -> u32 {
+    1
+}
+
+/// An account that just went bankrupt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bankruptcy {
+    pub account: AgentId,
+    pub balance: i64,
+    pub equity: i64,
+    /// Sim time of the scheduled top-up (None = the agent is removed)
+    pub recapitalize_at_ns: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct Account {
+    deposit: u64,
+    balance: i64,
+    unrealized: i64,
+    bankrupt: bool,
+    recapitalizations: u32,
+    recapitalize_at_ns: Option<u64>,
+}
+
+impl Account {
+    fn equity(&self) -> i64 {
+        self.balance + self.unrealized
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CapitalLedger {
+    config: CapitalConfig,
+    accounts: BTreeMap<AgentId, Account>,
+}
+
+impl CapitalLedger {
+    /// Track the traders in `deposits` (initial balance per account); other accounts are never bankrupt.
+    pub fn new(config: CapitalConfig, deposits: HashMap<AgentId, u64>) -> Self {
+        let accounts = if config.enabled {
+            deposits
+                .into_iter()
+                .map(|(account, deposit)| {
+                    (
+                        account,
+                        Account {
+                            deposit,
+                            balance: deposit as i64,
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+        Self { config, accounts }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.accounts.is_empty()
+    }
+
+    /// Tracked accounts, ordered by id.
+    pub fn accounts(&self) -> Vec<AgentId> {
+        self.accounts.keys().copied().collect()
+    }
+
+    pub fn is_bankrupt(&self, account: AgentId) -> bool {
+        self.accounts.get(&account).is_some_and(|a| a.bankrupt)
+    }
+
+    /// Balance of a tracked account.
+    pub fn balance(&self, account: AgentId) -> Option<i64> {
+        self.accounts.get(&account).map(|a| a.balance)
+    }
+
+    /// Carry over the balance an account ended an earlier run with (warm start).
+    pub fn set_balance(&mut self, account: AgentId, balance: i64) -> bool {
+        match self.accounts.get_mut(&account) {
+            Some(a) => {
+                a.balance = balance;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Book a realized cash flow (PnL net of fees and funding, lost collateral).
+    pub fn book(&mut self, account: AgentId, delta: i64) {
+        if let Some(a) = self.accounts.get_mut(&account) {
+            a.balance += delta;
+        }
+    }
+
+    /// Unrealized PnL of all open positions of the account.
+    pub fn set_unrealized(&mut self, account: AgentId, pnl: i64) {
+        if let Some(a) = self.accounts.get_mut(&account) {
+            a.unrealized = pnl;
+        }
+    }
+
+    /// Mark solvent accounts whose equity dropped to the threshold as bankrupt.
+    pub fn check(&mut self, now_ns: u64) -> Vec<Bankruptcy> {
+        let threshold = self.config.bankrupt_below_usd;
+        let recapitalize = self.config.recapitalize.as_ref();
+        let mut bankrupt = Vec::new();
+        for (account, a) in self.accounts.iter_mut() {
+            if a.bankrupt || a.equity() > threshold {
+                continue;
+            }
+            a.bankrupt = true;
+            a.recapitalize_at_ns = recapitalize
+                .filter(|r| r.max_times == 0 || a.recapitalizations < r.max_times)
+                .map(|r| now_ns + r.delay_sec * 1_000_000_000);
+            bankrupt.push(Bankruptcy {
+                account: *account,
+                balance: a.balance,
+                equity: a.equity(),
+                recapitalize_at_ns: a.recapitalize_at_ns,
+            });
+        }
+        bankrupt
+    }
+
+    /// Bankrupt accounts whose top-up is due, with the amount. The caller funds them
+    /// and confirms with `recapitalize`.
+    pub fn due_recapitalizations(&mut self, now_ns: u64) -> Vec<(AgentId, u64)> {
+        let amount = self.config.recapitalize.as_ref().and_then(|r| r.amount_usd);
+        let mut due = Vec::new();
+        for (account, a) in self.accounts.iter_mut() {
+            if a.recapitalize_at_ns.is_some_and(|at| at <= now_ns) {
+                a.recapitalize_at_ns = None;
+                due.push((*account, amount.unwrap_or(a.deposit)));
+            }
+        }
+        due
+    }
+
+    /// Credit a top-up: remaining losses are written off and the account trades again.
+    pub fn recapitalize(&mut self, account: AgentId, amount: u64) {
+        if let Some(a) = self.accounts.get_mut(&account) {
+            a.balance = a.balance.max(0) + amount as i64;
+            a.unrealized = 0;
+            a.bankrupt = false;
+            a.recapitalizations += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ledger(config: CapitalConfig) -> CapitalLedger {
+        CapitalLedger::new(config, HashMap::from([(1, 1_000), (2, 1_000)]))
+    }
+
+    #[test]
+    fn test_bankrupt_once_equity_reaches_threshold() {
+        let mut l = ledger(CapitalConfig::default());
+        l.book(1, -400);
+        l.set_unrealized(1, -500);
+        assert!(l.check(0).is_empty());
+        l.set_unrealized(1, -600);
+        let bankrupt = l.check(5);
+        assert_eq!(bankrupt.len(), 1);
+        assert_eq!(bankrupt[0].account, 1);
+        assert_eq!(bankrupt[0].equity, 0);
+        assert_eq!(bankrupt[0].recapitalize_at_ns, None);
+        assert!(l.is_bankrupt(1));
+        assert!(!l.is_bankrupt(2));
+        // Reported once
+        assert!(l.check(6).is_empty());
+    }
+
+    #[test]
+    fn test_untracked_and_disabled_accounts_never_bankrupt() {
+        let mut l = ledger(CapitalConfig::default());
+        l.book(9, -10_000);
+        assert!(l.check(0).is_empty());
+
+        let mut l = ledger(CapitalConfig {
+            enabled: false,
+            ..Default::default()
+        });
+        l.book(1, -10_000);
+        assert!(l.check(0).is_empty());
+        assert!(l.accounts().is_empty());
+    }
+
+    #[test]
+    fn test_recapitalization_after_delay_up_to_max_times() {
+        let mut l = ledger(CapitalConfig {
+            recapitalize: Some(Recapitalization {
+                amount_usd: None,
+                delay_sec: 10,
+                max_times: 1,
+            }),
+            ..Default::default()
+        });
+        l.book(1, -1_200);
+        assert_eq!(l.check(0)[0].recapitalize_at_ns, Some(10_000_000_000));
+        assert!(l.due_recapitalizations(9_999_999_999).is_empty());
+        assert_eq!(l.due_recapitalizations(10_000_000_000), vec![(1, 1_000)]);
+        l.recapitalize(1, 1_000);
+        // The loss beyond the old balance is written off
+        assert!(!l.is_bankrupt(1));
+        assert!(l.check(10_000_000_000).is_empty());
+
+        l.book(1, -1_000);
+        assert_eq!(l.check(20_000_000_000)[0].recapitalize_at_ns, None);
+    }
+}
@@ -0,0 +1,273 @@
+// Liquidation cascade analytics on top of EventBus.
+//
+// Liquidations of one market that follow each other within `window_sec` and
+// `max_move_bps` of price are linked into a cascade: each liquidation joins the
+// open cascade of its market when it is close to one of the cascade's recent
+// liquidations, otherwise it starts a new one. The depth of a liquidation is
+// one more than the deepest earlier liquidation it links to (liquidations at
+// the same instant share a depth), so the depth counts the rounds in which
+// liquidations pushed the price into the next ones. Cascades of two or more
+// liquidations go to the summary report.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::{EventKind, EventListener, SimEvent};
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+
+/// When two liquidations belong to the same cascade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CascadeConfig {
+    /// Largest gap between linked liquidations
+    #[serde(default = "default_window_sec")]
+    pub window_sec: u64,
+    /// Largest price difference between linked liquidations
+    #[serde(default = "default_max_move_bps")]
+    pub max_move_bps: u64,
+}
+
+fn default_window_sec() -> u64 {
+    60
+}
+
+fn default_max_move_bps() -> u64 {
+    200
+}
+
+impl Default for CascadeConfig {
+    fn default() -> Self {
+        Self {
+            window_sec: default_window_sec(),
+            max_move_bps: default_max_move_bps(),
+        }
+    }
+}
+
+impl CascadeConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window_sec == 0 {
+            return Err("window_sec must be > 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// One cascade of a market.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CascadeReport {
+    pub symbol: String,
+    pub start_ts: u64,
+    pub end_ts: u64,
+    pub liquidations: u64,
+    /// Rounds of liquidations (1 = all at the same instant)
+    pub depth: u32,
+    /// Liquidated position size (micro-USD)
+    pub notional_usd: u64,
+    /// Liquidation prices spanned (micro-USD)
+    pub first_price: u64,
+    pub last_price: u64,
+}
+
+/// Liquidation of the open cascade: (ts, price, depth).
+type Link = (u64, u64, u32);
+
+/// Open cascade of a market: its index in the book and its liquidations within the window.
+struct OpenCascade {
+    index: usize,
+    recent: Vec<Link>,
+}
+
+/// Shared read handle to the cascades found so far (the last ones may still grow).
+#[derive(Clone, Default)]
+pub struct CascadeBook {
+    inner: Arc<Mutex<Vec<CascadeReport>>>,
+}
+
+impl CascadeBook {
+    /// Cascades of at least two liquidations, in start order.
+    pub fn cascades(&self) -> Vec<CascadeReport> {
+        let Ok(all) = self.inner.lock() else {
+            return Vec::new();
+        };
+        all.iter().filter(|c| c.liquidations >= 2).cloned().collect()
+    }
+}
+
+/// Cascade detector: subscribe it before the ReportBuilder that reads its `book()`.
+pub struct CascadeTracker {
+    config: CascadeConfig,
+    open: HashMap<String, OpenCascade>,
+    book: CascadeBook,
+}
+
+impl CascadeTracker {
+    pub fn new(config: CascadeConfig) -> Self {
+        Self {
+            config,
+            open: HashMap::new(),
+            book: CascadeBook::default(),
+        }
+    }
+
+    /// Handle for the summary report.
+    pub fn book(&self) -> CascadeBook {
+        self.book.clone()
+    }
+
+    fn linked(&self, link: &Link, ts: u64, price: u64) -> bool {
+        let (link_ts, link_price, _) = *link;
+        let move_bps = link_price.abs_diff(price) as u128 * 10_000 / link_price.max(1) as u128;
+        ts.saturating_sub(link_ts) <= self.config.window_sec * NS_PER_SEC
+            && move_bps <= self.config.max_move_bps as u128
+    }
+
+    fn record(&mut self, ts: u64, symbol: &str, size_usd: u64, price: u64) {
+        let window_ns = self.config.window_sec * NS_PER_SEC;
+        let depth = self.open.get(symbol).and_then(|open| {
+            open.recent
+                .iter()
+                .filter(|link| self.linked(link, ts, price))
+                .map(|&(link_ts, _, depth)| if link_ts < ts { depth + 1 } else { depth })
+                .max()
+        });
+        let Ok(mut cascades) = self.book.inner.lock() else {
+            return;
+        };
+
+        match (depth, self.open.get_mut(symbol)) {
+            (Some(depth), Some(open)) => {
+                open.recent
+                    .retain(|(link_ts, ..)| ts.saturating_sub(*link_ts) <= window_ns);
+                open.recent.push((ts, price, depth));
+                let cascade = &mut cascades[open.index];
+                cascade.end_ts = ts;
+                cascade.liquidations += 1;
+                cascade.depth = cascade.depth.max(depth);
+                cascade.notional_usd += size_usd;
+                cascade.last_price = price;
+            }
+            _ => {
+                self.open.insert(
+                    symbol.to_string(),
+                    OpenCascade {
+                        index: cascades.len(),
+                        recent: vec![(ts, price, 1)],
+                    },
+                );
+                cascades.push(CascadeReport {
+                    symbol: symbol.to_string(),
+                    start_ts: ts,
+                    end_ts: ts,
+                    liquidations: 1,
+                    depth: 1,
+                    notional_usd: size_usd,
+                    first_price: price,
+                    last_price: price,
+                });
+            }
+        }
+    }
+}
+
+impl EventListener for CascadeTracker {
+    fn on_event(&mut self, event: &SimEvent) {
+        if let SimEvent::PositionLiquidated {
+            ts,
+            symbol,
+            size_usd,
+            liquidation_price,
+            ..
+        } = event
+        {
+            self.record(*ts, symbol, *size_usd, *liquidation_price);
+        }
+    }
+
+    fn interests(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::PositionLiquidated])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::Side;
+
+    fn liquidated(ts_sec: u64, symbol: &str, size_usd: u64, price: u64) -> SimEvent {
+        SimEvent::PositionLiquidated {
+            ts: ts_sec * NS_PER_SEC,
+            account: 1,
+            symbol: symbol.to_string(),
+            side: Side::Buy,
+            size_usd,
+            collateral_lost: 0,
+            pnl: 0,
+            liquidation_price: price,
+        }
+    }
+
+    #[test]
+    fn test_liquidations_close_in_time_and_price_form_a_cascade() {
+        let mut tracker = CascadeTracker::new(CascadeConfig {
+            window_sec: 30,
+            max_move_bps: 100,
+        });
+        let book = tracker.book();
+        // Two at once, then two rounds each 0.5% lower
+        for event in [
+            liquidated(0, "ETH-USD", 10, 3_000_000_000),
+            liquidated(0, "ETH-USD", 20, 3_000_000_000),
+            liquidated(10, "ETH-USD", 30, 2_985_000_000),
+            liquidated(20, "ETH-USD", 40, 2_970_000_000),
+            // Another market does not join
+            liquidated(20, "BTC-USD", 50, 60_000_000_000),
+        ] {
+            tracker.on_event(&event);
+        }
+
+        let cascades = book.cascades();
+        assert_eq!(cascades.len(), 1);
+        let cascade = &cascades[0];
+        assert_eq!((cascade.liquidations, cascade.depth, cascade.notional_usd), (4, 3, 100));
+        assert_eq!((cascade.start_ts, cascade.end_ts), (0, 20 * NS_PER_SEC));
+        assert_eq!(
+            (cascade.first_price, cascade.last_price),
+            (3_000_000_000, 2_970_000_000)
+        );
+    }
+
+    #[test]
+    fn test_gaps_and_price_jumps_start_new_cascades() {
+        let mut tracker = CascadeTracker::new(CascadeConfig {
+            window_sec: 30,
+            max_move_bps: 100,
+        });
+        let book = tracker.book();
+        for event in [
+            liquidated(0, "ETH-USD", 10, 3_000_000_000),
+            liquidated(5, "ETH-USD", 10, 3_000_000_000),
+            // 2% away from both
+            liquidated(10, "ETH-USD", 10, 2_940_000_000),
+            liquidated(15, "ETH-USD", 10, 2_940_000_000),
+            // Too late for the last one
+            liquidated(60, "ETH-USD", 10, 2_940_000_000),
+        ] {
+            tracker.on_event(&event);
+        }
+
+        let cascades = book.cascades();
+        assert_eq!(cascades.len(), 2);
+        assert!(cascades.iter().all(|c| c.liquidations == 2 && c.depth == 2));
+        assert_eq!(cascades[1].first_price, 2_940_000_000);
+        assert!(CascadeConfig {
+            window_sec: 0,
+            ..CascadeConfig::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,249 @@
+// Per-market circuit breaker on extreme moves.
+//
+// The exchange feeds every oracle mid of a market into its breaker. When the
+// mid has moved more than `move_pct` from any price of the last `window_sec`,
+// the breaker trips: for `halt_sec` the exchange rejects new increases
+// (market orders, conditional increases, their amendments and triggered
+// executions) with a `circuit_breaker:halted_until:<ns>` reason. Closes always
+// go through; liquidations only with `allow_liquidations`. A trip starts a
+// fresh window, so the move that tripped the breaker cannot trip it again
+// once the halt is over.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+
+/// Trip threshold and halt of one market.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CircuitBreakerConfig {
+    /// Largest move of the oracle mid within the window, in percent
+    pub move_pct: f64,
+    pub window_sec: u64,
+    /// How long new increases are rejected after a trip
+    pub halt_sec: u64,
+    /// Keep liquidating during a halt (false = liquidations wait for its end)
+    #[serde(default = "default_allow_liquidations")]
+    pub allow_liquidations: bool,
+}
+
+fn default_allow_liquidations() -> bool {
+    true
+}
+
+impl CircuitBreakerConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.move_pct.is_finite() || self.move_pct <= 0.0 {
+            return Err("circuit_breaker.move_pct must be > 0".to_string());
+        }
+        if self.window_sec == 0 || self.halt_sec == 0 {
+            return Err("circuit_breaker.window_sec and halt_sec must be > 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// A move that tripped the breaker; prices in micro-USD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trip {
+    /// Price within the window farthest from the new one
+    pub from_price: u64,
+    pub to_price: u64,
+    pub move_bps: u64,
+    pub halted_until_ns: u64,
+}
+
+/// Circuit breaker state of one market.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    /// (time, price) of the oracle mids within the window, oldest first
+    prices: VecDeque<(u64, u64)>,
+    halted_until_ns: u64,
+    trips: u32,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            prices: VecDeque::new(),
+            halted_until_ns: 0,
+            trips: 0,
+        }
+    }
+
+    pub fn config(&self) -> &CircuitBreakerConfig {
+        &self.config
+    }
+
+    /// Record a new oracle mid; returns the trip if it moved too far within the window.
+    /// A halted breaker does not trip again until the halt is over.
+    pub fn on_price(&mut self, now_ns: u64, price: u64) -> Option<Trip> {
+        let window_start = now_ns.saturating_sub(self.config.window_sec * NS_PER_SEC);
+        while self.prices.front().is_some_and(|(ts, _)| *ts < window_start) {
+            self.prices.pop_front();
+        }
+        let extreme = self
+            .prices
+            .iter()
+            .map(|(_, p)| *p)
+            .filter(|p| *p > 0)
+            .max_by_key(|p| p.abs_diff(price) as u128 * 10_000 / *p as u128);
+        self.prices.push_back((now_ns, price));
+        if self.is_halted(now_ns) {
+            return None;
+        }
+        let from_price = extreme?;
+        let move_bps = (from_price.abs_diff(price) as u128 * 10_000 / from_price as u128) as u64;
+        if move_bps as f64 <= self.config.move_pct * 100.0 {
+            return None;
+        }
+        self.halted_until_ns = now_ns + self.config.halt_sec * NS_PER_SEC;
+        self.trips += 1;
+        self.prices.clear();
+        self.prices.push_back((now_ns, price));
+        Some(Trip {
+            from_price,
+            to_price: price,
+            move_bps,
+            halted_until_ns: self.halted_until_ns,
+        })
+    }
+
+    pub fn is_halted(&self, now_ns: u64) -> bool {
+        now_ns < self.halted_until_ns
+    }
+
+    /// End of the current halt; None when trading is open
+    pub fn halted_until(&self, now_ns: u64) -> Option<u64> {
+        self.is_halted(now_ns).then_some(self.halted_until_ns)
+    }
+
+    /// Whether liquidations may run at `now_ns`
+    pub fn allows_liquidations(&self, now_ns: u64) -> bool {
+        self.config.allow_liquidations || !self.is_halted(now_ns)
+    }
+
+    pub fn trips(&self) -> u32 {
+        self.trips
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRICE: u64 = 3_000_000_000;
+    const SEC: u64 = NS_PER_SEC;
+
+    fn breaker(allow_liquidations: bool) -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            move_pct: 5.0,
+            window_sec: 60,
+            halt_sec: 300,
+            allow_liquidations,
+        })
+    }
+
+    /// Feed (second, price) ticks and collect the trips
+    fn run(breaker: &mut CircuitBreaker, ticks: &[(u64, u64)]) -> Vec<(u64, Trip)> {
+        ticks
+            .iter()
+            .filter_map(|(sec, price)| breaker.on_price(sec * SEC, *price).map(|trip| (*sec, trip)))
+            .collect()
+    }
+
+    #[test]
+    fn test_crash_within_window_trips_and_halts() {
+        let mut cb = breaker(true);
+        // -2% per 10s: -6% after 30s, measured from the first tick
+        let ticks: Vec<(u64, u64)> = (0..4).map(|i| (i * 10, PRICE - i * 60_000_000)).collect();
+        let trips = run(&mut cb, &ticks);
+        assert_eq!(trips.len(), 1);
+        let (sec, trip) = trips[0];
+        assert_eq!(sec, 30);
+        assert_eq!(trip.from_price, PRICE);
+        assert_eq!(trip.to_price, 2_820_000_000);
+        assert_eq!(trip.move_bps, 600);
+        assert_eq!(trip.halted_until_ns, 330 * SEC);
+
+        assert!(cb.is_halted(30 * SEC));
+        assert!(cb.is_halted(329 * SEC));
+        assert_eq!(cb.halted_until(100 * SEC), Some(330 * SEC));
+        assert!(!cb.is_halted(330 * SEC));
+        assert_eq!(cb.halted_until(330 * SEC), None);
+        assert!(cb.allows_liquidations(100 * SEC));
+    }
+
+    #[test]
+    fn test_pump_trips_like_a_crash() {
+        let mut cb = breaker(true);
+        let trips = run(&mut cb, &[(0, PRICE), (5, PRICE * 106 / 100)]);
+        assert_eq!(trips.len(), 1);
+        assert_eq!(trips[0].1.move_bps, 600);
+    }
+
+    #[test]
+    fn test_moves_under_threshold_or_outside_window_do_not_trip() {
+        let mut cb = breaker(true);
+        // Exactly 5% is not more than 5%
+        assert!(run(&mut cb, &[(0, PRICE), (30, PRICE * 95 / 100)]).is_empty());
+
+        // -2% per 30s: a 20% slide, but never more than ~4% within one window
+        let mut cb = breaker(true);
+        let ticks: Vec<(u64, u64)> = (0..13)
+            .scan(PRICE, |price, i| {
+                let tick = (i * 30, *price);
+                *price = *price * 98 / 100;
+                Some(tick)
+            })
+            .collect();
+        assert!(ticks.last().unwrap().1 < PRICE * 80 / 100);
+        assert!(run(&mut cb, &ticks).is_empty());
+        assert!(!cb.is_halted(360 * SEC));
+    }
+
+    #[test]
+    fn test_no_retrip_during_halt_or_on_the_same_move() {
+        let mut cb = breaker(true);
+        let low = PRICE * 90 / 100;
+        let ticks = [(0, PRICE), (10, low), (20, PRICE), (40, low), (400, low), (410, low)];
+        let trips = run(&mut cb, &ticks);
+        // Only the first drop; the swings during the halt and the flat price after it don't count
+        assert_eq!(trips.len(), 1);
+        assert_eq!(cb.trips(), 1);
+        assert!(!cb.is_halted(400 * SEC));
+
+        // A new move after the halt trips again
+        let trips = run(&mut cb, &[(420, low * 110 / 100)]);
+        assert_eq!(trips.len(), 1);
+        assert_eq!(trips[0].1.halted_until_ns, 720 * SEC);
+    }
+
+    #[test]
+    fn test_liquidations_wait_for_the_halt_when_disallowed() {
+        let mut cb = breaker(false);
+        run(&mut cb, &[(0, PRICE), (1, PRICE / 2)]);
+        assert!(!cb.allows_liquidations(100 * SEC));
+        assert!(cb.allows_liquidations(301 * SEC));
+    }
+
+    #[test]
+    fn test_config_parsing_and_validation() {
+        let config: CircuitBreakerConfig =
+            serde_json::from_str(r#"{ "move_pct": 10, "window_sec": 300, "halt_sec": 600 }"#).unwrap();
+        assert!(config.allow_liquidations);
+        assert!(config.validate().is_ok());
+        assert!(CircuitBreakerConfig {
+            move_pct: 0.0,
+            ..config
+        }
+        .validate()
+        .is_err());
+        assert!(CircuitBreakerConfig { halt_sec: 0, ..config }.validate().is_err());
+        assert!(serde_json::from_str::<CircuitBreakerConfig>(r#"{ "move_pct": 10 }"#).is_err());
+    }
+}
@@ -0,0 +1,236 @@
+// Compare summary.json files across runs (A/B testing of engine/scenario changes).
+//
+// Each input is either a summary.json file or a directory; a directory is
+// searched recursively and every summary.json found counts as one (seeded)
+// repetition of the same variant. The first input is the baseline. With at
+// least two repetitions on both sides, Welch's t-statistic is reported and
+// |t| >= 2 is flagged as significant.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::report::RunSummary;
+
+/// |t| above which a difference is flagged (≈95% for moderate sample sizes)
+const SIGNIFICANCE_T: f64 = 2.0;
+
+struct Variant {
+    label: String,
+    runs: Vec<RunSummary>,
+}
+
+fn collect_summaries(path: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if path.is_file() {
+        out.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries: Vec<_> = std::fs::read_dir(path)?.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            collect_summaries(&entry, out)?;
+        } else if entry.file_name().map(|n| n == "summary.json").unwrap_or(false) {
+            out.push(entry);
+        }
+    }
+    Ok(())
+}
+
+fn load_variant(path: &Path) -> Result<Variant, String> {
+    let mut files = Vec::new();
+    collect_summaries(path, &mut files).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if files.is_empty() {
+        return Err(format!("{}: no summary.json found", path.display()));
+    }
+
+    let mut runs = Vec::with_capacity(files.len());
+    for file in &files {
+        let content = std::fs::read_to_string(file).map_err(|e| format!("{}: {}", file.display(), e))?;
+        let summary: RunSummary =
+            serde_json::from_str(&content).map_err(|e| format!("{}: {}", file.display(), e))?;
+        runs.push(summary);
+    }
+
+    Ok(Variant {
+        label: path.display().to_string(),
+        runs,
+    })
+}
+
+/// Key metrics of one run, by name.
+pub(crate) fn metrics(run: &RunSummary) -> BTreeMap<String, f64> {
+    let mut m = BTreeMap::new();
+    m.insert("fee_revenue_usd".to_string(), run.fee_revenue_usd as f64 / 1e6);
+    m.insert("liquidations".to_string(), run.total_liquidations as f64);
+    m.insert("bankruptcies".to_string(), run.total_bankruptcies as f64);
+    m.insert("liquidation_cascades".to_string(), run.liquidation_cascades.len() as f64);
+    m.insert("max_cascade_depth".to_string(), run.max_cascade_depth as f64);
+    m.insert("trades".to_string(), run.total_trades as f64);
+    m.insert("volume_usd".to_string(), run.total_volume_usd as f64 / 1e6);
+
+    let mut trader_pnl = 0.0;
+    // kind -> (fills, sum of slippage in bps)
+    let mut slippage: BTreeMap<&str, (u64, f64)> = BTreeMap::new();
+    for a in &run.agents {
+        let kind = if a.kind.is_empty() { "unknown" } else { a.kind.as_str() };
+        *m.entry(format!("pnl_usd[{}]", kind)).or_insert(0.0) += a.total_pnl as f64 / 1e6;
+        *m.entry(format!("liquidations[{}]", kind)).or_insert(0.0) += a.liquidations as f64;
+        trader_pnl += a.total_pnl as f64 / 1e6;
+        if let Some(s) = &a.slippage {
+            let entry = slippage.entry(kind).or_default();
+            entry.0 += s.fills;
+            entry.1 += s.mean_bps * s.fills as f64;
+        }
+    }
+    for (kind, (fills, sum)) in slippage {
+        m.insert(format!("slippage_bps[{}]", kind), sum / fills as f64);
+    }
+    m.insert("trader_pnl_usd".to_string(), trader_pnl);
+    // Counterparty side (LP pool / insurance fund): fees in, trader profits out
+    m.insert("pool_pnl_usd".to_string(), run.fee_revenue_usd as f64 / 1e6 - trader_pnl);
+
+    if !run.oi_timeline.is_empty() {
+        let n = run.oi_timeline.len() as f64;
+        let long = run.oi_timeline.iter().map(|p| p.oi_long_usd as f64).sum::<f64>() / n;
+        let short = run.oi_timeline.iter().map(|p| p.oi_short_usd as f64).sum::<f64>() / n;
+        let imbalance = run
+            .oi_timeline
+            .iter()
+            .map(|p| (p.oi_long_usd as f64 - p.oi_short_usd as f64).abs())
+            .sum::<f64>()
+            / n;
+        let max_total = run
+            .oi_timeline
+            .iter()
+            .map(|p| (p.oi_long_usd + p.oi_short_usd) as f64)
+            .fold(0.0, f64::max);
+        m.insert("oi_long_avg_usd".to_string(), long / 1e6);
+        m.insert("oi_short_avg_usd".to_string(), short / 1e6);
+        m.insert("oi_imbalance_avg_usd".to_string(), imbalance / 1e6);
+        m.insert("oi_total_max_usd".to_string(), max_total / 1e6);
+    }
+    m
+}
+
+/// Sample mean and (unbiased) variance.
+pub(crate) fn mean_var(xs: &[f64]) -> (f64, f64) {
+    let n = xs.len() as f64;
+    if n == 0.0 {
+        return (0.0, 0.0);
+    }
+    let mean = xs.iter().sum::<f64>() / n;
+    if n < 2.0 {
+        return (mean, 0.0);
+    }
+    let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, var)
+}
+
+/// Welch's t-statistic for the difference of means (b - a). None if not computable.
+fn welch_t(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() < 2 || b.len() < 2 {
+        return None;
+    }
+    let (ma, va) = mean_var(a);
+    let (mb, vb) = mean_var(b);
+    let se = (va / a.len() as f64 + vb / b.len() as f64).sqrt();
+    if se == 0.0 {
+        return if ma == mb { Some(0.0) } else { None };
+    }
+    Some((mb - ma) / se)
+}
+
+/// Per-variant samples of every metric seen in any run (missing = 0).
+fn samples(variant: &Variant, names: &[String]) -> BTreeMap<String, Vec<f64>> {
+    let per_run: Vec<_> = variant.runs.iter().map(metrics).collect();
+    names
+        .iter()
+        .map(|name| {
+            let xs = per_run.iter().map(|m| m.get(name).copied().unwrap_or(0.0)).collect();
+            (name.clone(), xs)
+        })
+        .collect()
+}
+
+/// Entry point of the `compare` subcommand.
+pub fn run(paths: &[PathBuf]) -> Result<(), String> {
+    if paths.len() < 2 {
+        return Err("compare needs at least two summary.json files or directories".to_string());
+    }
+    let variants = paths.iter().map(|p| load_variant(p)).collect::<Result<Vec<_>, _>>()?;
+
+    let mut names: Vec<String> = variants
+        .iter()
+        .flat_map(|v| v.runs.iter().flat_map(|r| metrics(r).into_keys()))
+        .collect();
+    names.sort();
+    names.dedup();
+
+    let baseline = &variants[0];
+    let base_samples = samples(baseline, &names);
+
+    println!("=== Run comparison ===");
+    for (i, v) in variants.iter().enumerate() {
+        let role = if i == 0 { "baseline" } else { "variant" };
+        println!("[{}] {} ({}, {} run(s))", i, v.label, role, v.runs.len());
+    }
+
+    for (i, variant) in variants.iter().enumerate().skip(1) {
+        let var_samples = samples(variant, &names);
+        println!("\n--- [0] vs [{}] ---", i);
+        println!(
+            "{:<32} {:>14} {:>14} {:>14} {:>9} {:>8}",
+            "metric", "baseline", "variant", "delta", "delta%", "t"
+        );
+        for name in &names {
+            let a = &base_samples[name];
+            let b = &var_samples[name];
+            let (ma, _) = mean_var(a);
+            let (mb, _) = mean_var(b);
+            let delta = mb - ma;
+            let delta_pct = if ma != 0.0 {
+                format!("{:+.1}%", delta * 100.0 / ma.abs())
+            } else {
+                "-".to_string()
+            };
+            let t = match welch_t(a, b) {
+                Some(t) if t.abs() >= SIGNIFICANCE_T => format!("{:+.2}*", t),
+                Some(t) => format!("{:+.2}", t),
+                None => "-".to_string(),
+            };
+            println!(
+                "{:<32} {:>14.2} {:>14.2} {:>+14.2} {:>9} {:>8}",
+                name, ma, mb, delta, delta_pct, t
+            );
+        }
+    }
+    println!("\n(* = |t| >= {:.1}, needs >= 2 runs per variant)", SIGNIFICANCE_T);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mean_var() {
+        let (m, v) = mean_var(&[1.0, 2.0, 3.0, 4.0]);
+        assert!((m - 2.5).abs() < 1e-9);
+        assert!((v - 5.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_welch_t_needs_repetitions() {
+        assert!(welch_t(&[1.0], &[2.0, 3.0]).is_none());
+    }
+
+    #[test]
+    fn test_welch_t_detects_shift() {
+        let a = [10.0, 11.0, 9.0, 10.5, 9.5];
+        let b = [20.0, 21.0, 19.0, 20.5, 19.5];
+        let t = welch_t(&a, &b).unwrap();
+        assert!(t > SIGNIFICANCE_T);
+        let same = welch_t(&a, &a).unwrap();
+        assert!(same.abs() < 1e-9);
+    }
+}
@@ -0,0 +1,247 @@
+// Equity curve sampler on top of EventBus.
+//
+// Every `interval` of sim time it reads the PnL ledgers and appends one row per
+// trader to logs/equity.csv: equity = initial balance + net PnL (realized +
+// unrealized - fees - funding); recapitalizations add to the initial balance. Per-account curve statistics (max drawdown,
+// Sharpe-like ratio, exposure) are shared with the summary report.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::create_dir_all;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::{EventListener, SimEvent};
+use crate::messages::AgentId;
+use crate::pnl::PnlBook;
+
+/// Curve statistics of one account.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct EquityStats {
+    pub samples: u64,
+    pub start_equity: i64,
+    pub final_equity: i64,
+    pub peak_equity: i64,
+    /// Largest peak-to-trough drop (micro-USD)
+    pub max_drawdown: i64,
+    /// Largest peak-to-trough drop relative to the peak (%)
+    pub max_drawdown_pct: f64,
+    /// mean / stddev of per-sample returns (not annualized)
+    pub sharpe_like: f64,
+    pub avg_exposure_usd: u64,
+    pub max_exposure_usd: u64,
+    /// Share of samples with an open position (%)
+    pub time_in_market_pct: f64,
+}
+
+#[derive(Default)]
+struct Curve {
+    initial_balance: i64,
+    last_equity: Option<i64>,
+    peak: i64,
+    max_dd: i64,
+    max_dd_pct: f64,
+    /// Per-sample returns: count, running mean and sum of squared deviations (Welford)
+    returns: u64,
+    return_mean: f64,
+    return_m2: f64,
+    samples: u64,
+    exposure_sum: u128,
+    exposure_max: u64,
+    samples_in_market: u64,
+    start_equity: i64,
+}
+
+impl Curve {
+    fn record(&mut self, equity: i64, exposure: u64) {
+        match self.last_equity {
+            None => {
+                self.start_equity = equity;
+                self.peak = equity;
+            }
+            Some(prev) => {
+                if prev != 0 {
+                    let r = (equity - prev) as f64 / prev.abs() as f64;
+                    self.returns += 1;
+                    let delta = r - self.return_mean;
+                    self.return_mean += delta / self.returns as f64;
+                    self.return_m2 += delta * (r - self.return_mean);
+                }
+            }
+        }
+        self.last_equity = Some(equity);
+        self.samples += 1;
+
+        if equity > self.peak {
+            self.peak = equity;
+        }
+        let dd = self.peak - equity;
+        if dd > self.max_dd {
+            self.max_dd = dd;
+        }
+        if self.peak > 0 {
+            let dd_pct = dd as f64 * 100.0 / self.peak as f64;
+            if dd_pct > self.max_dd_pct {
+                self.max_dd_pct = dd_pct;
+            }
+        }
+
+        self.exposure_sum += exposure as u128;
+        self.exposure_max = self.exposure_max.max(exposure);
+        if exposure > 0 {
+            self.samples_in_market += 1;
+        }
+    }
+
+    fn stats(&self) -> EquityStats {
+        let sharpe_like = if self.returns >= 2 {
+            let std = (self.return_m2 / (self.returns - 1) as f64).sqrt();
+            if std > 0.0 { self.return_mean / std } else { 0.0 }
+        } else {
+            0.0
+        };
+        let samples = self.samples.max(1);
+        EquityStats {
+            samples: self.samples,
+            start_equity: self.start_equity,
+            final_equity: self.last_equity.unwrap_or(self.initial_balance),
+            peak_equity: self.peak,
+            max_drawdown: self.max_dd,
+            max_drawdown_pct: self.max_dd_pct,
+            sharpe_like,
+            avg_exposure_usd: (self.exposure_sum / samples as u128) as u64,
+            max_exposure_usd: self.exposure_max,
+            time_in_market_pct: self.samples_in_market as f64 * 100.0 / samples as f64,
+        }
+    }
+}
+
+/// Shared read handle to per-account equity statistics.
+#[derive(Clone, Default)]
+pub struct EquityBook {
+    inner: Arc<Mutex<BTreeMap<AgentId, EquityStats>>>,
+}
+
+impl EquityBook {
+    pub fn get(&self, account: AgentId) -> Option<EquityStats> {
+        self.inner.lock().ok()?.get(&account).cloned()
+    }
+}
+
+/// Equity sampler: must be subscribed after the PnlTracker that owns `pnl_book`.
+pub struct EquityTracker {
+    interval_ns: u64,
+    next_sample_ns: Option<u64>,
+    pnl_book: PnlBook,
+    curves: BTreeMap<AgentId, Curve>,
+    writer: Option<BufWriter<std::fs::File>>,
+    book: EquityBook,
+}
+
+impl EquityTracker {
+    /// `initial_balances`: starting balance per trader (micro-USD).
+    pub fn new<P: AsRef<Path>>(
+        dir: P,
+        interval_sec: u64,
+        initial_balances: HashMap<AgentId, i64>,
+        pnl_book: PnlBook,
+    ) -> Self {
+        let writer = create_dir_all(dir.as_ref())
+            .and_then(|_| std::fs::File::create(dir.as_ref().join("equity.csv")))
+            .and_then(|file| {
+                let mut w = BufWriter::new(file);
+                writeln!(w, "ts,account,equity,net_pnl,exposure_usd,drawdown")?;
+                Ok(w)
+            })
+            .map_err(|e| eprintln!("[EquityTracker] failed to create equity.csv: {e}"))
+            .ok();
+
+        let curves = initial_balances
+            .into_iter()
+            .map(|(id, balance)| {
+                (
+                    id,
+                    Curve {
+                        initial_balance: balance,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+
+        Self {
+            interval_ns: interval_sec.max(1) * 1_000_000_000,
+            next_sample_ns: None,
+            pnl_book,
+            curves,
+            writer,
+            book: EquityBook::default(),
+        }
+    }
+
+    /// Handle for the summary report.
+    pub fn book(&self) -> EquityBook {
+        self.book.clone()
+    }
+
+    fn sample(&mut self, ts: u64) {
+        // Accounts not declared up front (e.g. HumanAgent) start from zero balance
+        for (account, _) in self.pnl_book.all() {
+            self.curves.entry(account).or_default();
+        }
+
+        for (account, curve) in self.curves.iter_mut() {
+            let pnl = self.pnl_book.get(*account).unwrap_or_default();
+            let net = pnl.net_pnl();
+            let equity = curve.initial_balance + net;
+            curve.record(equity, pnl.open_notional_usd);
+
+            if let Some(w) = &mut self.writer {
+                if let Err(e) = writeln!(
+                    w,
+                    "{},{},{},{},{},{}",
+                    ts,
+                    account,
+                    equity,
+                    net,
+                    pnl.open_notional_usd,
+                    curve.peak - equity
+                ) {
+                    eprintln!("[EquityTracker] write error: {e}");
+                }
+            }
+        }
+
+        if let Ok(mut book) = self.book.inner.lock() {
+            for (account, curve) in self.curves.iter() {
+                book.insert(*account, curve.stats());
+            }
+        }
+    }
+}
+
+impl EventListener for EquityTracker {
+    fn on_event(&mut self, event: &SimEvent) {
+        if let SimEvent::AgentRecapitalized {
+            account, amount_usd, ..
+        } = event
+        {
+            self.curves.entry(*account).or_default().initial_balance += *amount_usd as i64;
+        }
+        let ts = event.ts();
+        let next = *self.next_sample_ns.get_or_insert(ts);
+        if ts >= next {
+            self.sample(ts);
+            self.next_sample_ns = Some(ts + self.interval_ns);
+        }
+    }
+
+    fn on_finish(&mut self, ts: u64) {
+        self.sample(ts);
+        if let Some(w) = &mut self.writer {
+            let _ = w.flush();
+        }
+    }
+}
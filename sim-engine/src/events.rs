@@ -1,4 +1,5 @@
-use crate::messages::{AgentId, MessageType, Side};
+use crate::messages::{AgentId, MessageType, OrderId, Side};
+use crate::qty::Qty;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,7 +14,7 @@ pub enum SimEvent {
         symbol: Option<String>,
         side: Option<Side>,
         price: Option<u64>,
-        qty: Option<f64>,
+        qty: Option<Qty>,
     },
 
     /// Order executed by exchange
@@ -28,6 +29,12 @@ pub enum SimEvent {
         leverage: u32,
         order_type: String,  // "Increase", "Decrease", "Liquidation"
         pnl: i64,            // PnL on close (0 for open)
+        #[serde(default)]
+        fee_usd: u64,        // Position fee paid in micro-USD
+        #[serde(default)]
+        funding_fee_usd: i64, // Funding settled on this execution (+ = paid by trader)
+        #[serde(default)]
+        decision_price: Option<u64>, // Oracle mid the sender decided at (market orders carrying it)
     },
 
     /// Oracle price update
@@ -48,9 +55,9 @@ pub enum SimEvent {
         size_tokens: i128,
         collateral: u64,
         entry_price: u64,       // Calculated: size_usd / size_tokens
-        current_price: u64,     // From oracle
+        current_price: u64,     // Mark price (the oracle index without a mark model)
         unrealized_pnl: i64,    // Calculated on our side (not from engine)
-        liquidation_price: u64, // TODO(perp-futures): get from engine
+        liquidation_price: u64, // contract CalculateLiquidationPrice, or a maintenance-margin estimate
         leverage_actual: u32,   // size_usd / collateral
         is_liquidatable: bool,  // current_price crossed liquidation_price
         opened_at_sec: u64,
@@ -60,9 +67,16 @@ pub enum SimEvent {
     MarketSnapshot {
         ts: u64,
         symbol: String,
+        /// Oracle mid the fills are priced from
+        index_price: u64,
+        /// Price of liquidation checks and unrealized PnL
+        mark_price: u64,
         oi_long_usd: u64,
         oi_short_usd: u64,
         liquidity_usd: u64,
+        /// (long + short OI) / liquidity in bps; u64::MAX for OI without liquidity
+        #[serde(default)]
+        utilization_bps: u64,
         /// Funding rate per hour in bps * 1e6 (signed; + = longs pay, - = shorts pay)
         funding_rate_bps_hour_fp: i64,
         /// Borrowing rate per hour in bps * 1e6 (unsigned)
@@ -80,30 +94,408 @@ pub enum SimEvent {
         pnl: i64,              // Final PnL (usually negative)
         liquidation_price: u64, // Price at which liquidated
     },
+
+    /// Periodic risk metrics of one market (RiskMonitorAgent)
+    RiskReport {
+        ts: u64,
+        symbol: String,
+        price: u64,
+        oi_long_usd: u64,
+        oi_short_usd: u64,
+        liquidity_usd: i64,
+        /// Price volatility over the VaR horizon (percent, 1 sigma)
+        volatility_pct: f64,
+        /// Pool loss on the net trader exposure not exceeded at the VaR confidence
+        var_usd: u64,
+        /// Account holding the largest share of OI
+        top_account: Option<AgentId>,
+        top_account_pct: f64,
+        /// Notional of positions whose liquidation price is within the warning distance
+        at_risk_usd: u64,
+        /// Liquidation notional per price bucket
+        liq_heatmap: Vec<LiqBucket>,
+    },
+
+    /// Risk threshold crossed (RiskMonitorAgent)
+    RiskAlert {
+        ts: u64,
+        kind: String, // "var", "concentration", "liq_cluster", "imminent_liquidation"
+        symbol: String,
+        account: Option<AgentId>,
+        value: f64,
+        message: String,
+    },
+
+    /// Order rejected by the exchange's pre-trade risk check
+    RiskLimitBreached {
+        ts: u64,
+        account: AgentId,
+        symbol: String,
+        limit: String, // "max_position_usd", "max_leverage", "max_daily_loss_usd", "max_orders_per_min"
+        value: f64,
+        threshold: f64,
+    },
+
+    /// Trader equity hit the bankruptcy threshold; it stops trading
+    AgentBankrupt {
+        ts: u64,
+        account: AgentId,
+        balance: i64,                 // Deposit + realized PnL after fees
+        equity: i64,                  // Balance + unrealized PnL
+        recapitalize_at: Option<u64>, // Scheduled top-up (None = agent removed)
+    },
+
+    /// Bankrupt trader funded again
+    AgentRecapitalized { ts: u64, account: AgentId, amount_usd: u64 },
+
+    /// Conditional order removed at the end of its validity
+    OrderExpired {
+        ts: u64,
+        account: AgentId,
+        order_id: OrderId,
+        symbol: String,
+        side: Side,
+    },
+
+    /// On-chain transaction result reported by the exchange's Vara client
+    ChainTx {
+        ts: u64,
+        agent: AgentId,
+        tx_type: String, // "SubmitOrder", "SubmitAndExecute", "ExecuteOrder", "CancelOrder", "UpdateOrder", "Deposit"
+        success: bool,
+        order_id: Option<u64>,
+        latency_ms: u64, // Queued -> result, wall clock
+        error: Option<String>,
+        tip: u128,         // Priority tip offered, chain's smallest unit
+        fee: Option<u128>, // Native fee measured with `vara.tx_spend`
+        reverted: bool,    // Earlier success dropped by a simulated reorg (success = false)
+    },
+
+    /// Fill on a spot venue (SwapExchangeAgent), prices in micro-USD
+    SpotSwap {
+        ts: u64,
+        account: AgentId,
+        symbol: String,
+        side: Side,
+        qty: Qty,
+        price: u64, // Average, including the fee
+        fee_usd: u64,
+        spot_price: u64, // Pool price after the swap
+        oracle_price: u64,
+    },
+
+    /// Fee charged to a trader: "position" (open/close fee) or "liquidation" (collateral seized)
+    FeeAccrued {
+        ts: u64,
+        account: AgentId,
+        symbol: String,
+        kind: String,
+        amount_usd: u64,
+    },
+
+    /// Change of a market's insurance fund, the pool's realized PnL: fees and seized collateral
+    /// in, trader profits out (reason "fee", "liquidation", "trader_pnl", "cross_margin")
+    InsuranceFundChanged {
+        ts: u64,
+        symbol: String,
+        delta: i64,
+        balance: i64,
+        reason: String,
+    },
+
+    /// Messages waiting in the inbox of a rate-limited agent, reported when the count changes
+    InboxBacklog {
+        ts: u64,
+        agent: AgentId,
+        backlog: usize,
+        delayed: u64, // Messages that had to wait so far
+    },
+
+    /// Oracle switched price provider: "failover" after `failures` failed blocks in a row,
+    /// "recovered" when the primary prices every symbol again
+    OracleFailover {
+        ts: u64,
+        oracle: AgentId,
+        from: String,
+        to: String,
+        reason: String,
+        failures: u32,
+    },
+
+    /// Message for an agent id that was never registered; `action` is what the dead-letter
+    /// policy did with it: "retry", "drop" or "fail"
+    DeadLetter {
+        ts: u64,
+        from: AgentId,
+        to: AgentId,
+        msg_type: String,
+        attempt: u32, // 1 for the original delivery
+        action: String,
+    },
+
+    /// Oracle mid of a market moved more than its circuit breaker allows within the window;
+    /// new increases are rejected until `halted_until`
+    CircuitBreakerTripped {
+        ts: u64,
+        symbol: String,
+        from_price: u64, // Price within the window farthest from the new one
+        to_price: u64,
+        move_bps: u64,
+        halted_until: u64,
+    },
+
+    /// Realtime tick that took longer than the watchdog limit (wall clock); `agent` is the one
+    /// whose handler took longest in it
+    TickOverrun {
+        ts: u64,
+        tick: u64,
+        elapsed_ms: u64,
+        limit_ms: u64,
+        agent: Option<AgentId>,
+        msg_type: Option<String>,
+        handler_ms: u64,
+    },
+
+    /// Order filled by the local fallback that never reached the chain; its fill was reversed
+    /// (fee, PnL, capital, blotter) after `attempts` chain attempts
+    LocalFillAbandoned {
+        ts: u64,
+        account: AgentId,
+        symbol: String,
+        side: Side,
+        order_type: String, // "Increase" or "Decrease"
+        size_usd: u64,
+        attempts: u32,
+        reason: String,
+    },
+}
+
+/// Liquidation prices falling into `[price, price + bucket width)`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiqBucket {
+    pub price: u64,
+    pub long_usd: u64,
+    pub short_usd: u64,
+}
+
+impl SimEvent {
+    /// Simulation time of the event (ns).
+    pub fn ts(&self) -> u64 {
+        match self {
+            SimEvent::OrderLog { ts, .. }
+            | SimEvent::OrderExecuted { ts, .. }
+            | SimEvent::OracleTick { ts, .. }
+            | SimEvent::PositionSnapshot { ts, .. }
+            | SimEvent::MarketSnapshot { ts, .. }
+            | SimEvent::PositionLiquidated { ts, .. }
+            | SimEvent::RiskReport { ts, .. }
+            | SimEvent::RiskAlert { ts, .. }
+            | SimEvent::RiskLimitBreached { ts, .. }
+            | SimEvent::AgentBankrupt { ts, .. }
+            | SimEvent::AgentRecapitalized { ts, .. }
+            | SimEvent::OrderExpired { ts, .. }
+            | SimEvent::ChainTx { ts, .. }
+            | SimEvent::SpotSwap { ts, .. }
+            | SimEvent::FeeAccrued { ts, .. }
+            | SimEvent::InsuranceFundChanged { ts, .. }
+            | SimEvent::InboxBacklog { ts, .. }
+            | SimEvent::OracleFailover { ts, .. }
+            | SimEvent::DeadLetter { ts, .. }
+            | SimEvent::CircuitBreakerTripped { ts, .. }
+            | SimEvent::TickOverrun { ts, .. }
+            | SimEvent::LocalFillAbandoned { ts, .. } => *ts,
+        }
+    }
+
+    pub fn kind(&self) -> EventKind {
+        match self {
+            SimEvent::OrderLog { .. } => EventKind::OrderLog,
+            SimEvent::OrderExecuted { .. } => EventKind::OrderExecuted,
+            SimEvent::OracleTick { .. } => EventKind::OracleTick,
+            SimEvent::PositionSnapshot { .. } => EventKind::PositionSnapshot,
+            SimEvent::MarketSnapshot { .. } => EventKind::MarketSnapshot,
+            SimEvent::PositionLiquidated { .. } => EventKind::PositionLiquidated,
+            SimEvent::RiskReport { .. } => EventKind::RiskReport,
+            SimEvent::RiskAlert { .. } => EventKind::RiskAlert,
+            SimEvent::RiskLimitBreached { .. } => EventKind::RiskLimitBreached,
+            SimEvent::AgentBankrupt { .. } => EventKind::AgentBankrupt,
+            SimEvent::AgentRecapitalized { .. } => EventKind::AgentRecapitalized,
+            SimEvent::OrderExpired { .. } => EventKind::OrderExpired,
+            SimEvent::ChainTx { .. } => EventKind::ChainTx,
+            SimEvent::SpotSwap { .. } => EventKind::SpotSwap,
+            SimEvent::FeeAccrued { .. } => EventKind::FeeAccrued,
+            SimEvent::InsuranceFundChanged { .. } => EventKind::InsuranceFundChanged,
+            SimEvent::InboxBacklog { .. } => EventKind::InboxBacklog,
+            SimEvent::OracleFailover { .. } => EventKind::OracleFailover,
+            SimEvent::DeadLetter { .. } => EventKind::DeadLetter,
+            SimEvent::CircuitBreakerTripped { .. } => EventKind::CircuitBreakerTripped,
+            SimEvent::TickOverrun { .. } => EventKind::TickOverrun,
+            SimEvent::LocalFillAbandoned { .. } => EventKind::LocalFillAbandoned,
+        }
+    }
+}
+
+/// Discriminant of a `SimEvent`, used to route events to interested listeners; named like
+/// the `event_type` of the serialized event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EventKind {
+    OrderLog,
+    OrderExecuted,
+    OracleTick,
+    PositionSnapshot,
+    MarketSnapshot,
+    PositionLiquidated,
+    RiskReport,
+    RiskAlert,
+    RiskLimitBreached,
+    AgentBankrupt,
+    AgentRecapitalized,
+    OrderExpired,
+    ChainTx,
+    SpotSwap,
+    FeeAccrued,
+    InsuranceFundChanged,
+    InboxBacklog,
+    OracleFailover,
+    DeadLetter,
+    CircuitBreakerTripped,
+    TickOverrun,
+    LocalFillAbandoned,
+}
+
+impl EventKind {
+    pub const COUNT: usize = 22;
 }
 
 pub trait EventListener {
     fn on_event(&mut self, event: &SimEvent);
+
+    /// Event kinds this listener handles; `None` (default) receives every event.
+    /// Read once at subscribe time.
+    fn interests(&self) -> Option<&'static [EventKind]> {
+        None
+    }
+
+    /// Called once when the simulation finishes (flush files, write reports).
+    fn on_finish(&mut self, _ts: u64) {}
 }
 
 pub struct EventBus {
     listeners: Vec<Box<dyn EventListener>>,
+    /// Listener indices per `EventKind`, in subscription order
+    routes: [Vec<usize>; EventKind::COUNT],
 }
 
 impl EventBus {
     pub fn new() -> Self {
-        Self { listeners: Vec::new() }
+        Self {
+            listeners: Vec::new(),
+            routes: Default::default(),
+        }
     }
 
     /// Subscribe a new listener.
     pub fn subscribe(&mut self, listener: Box<dyn EventListener>) {
+        let idx = self.listeners.len();
+        match listener.interests() {
+            Some(kinds) => {
+                for kind in kinds {
+                    let route = &mut self.routes[*kind as usize];
+                    if route.last() != Some(&idx) {
+                        route.push(idx);
+                    }
+                }
+            }
+            None => {
+                for route in self.routes.iter_mut() {
+                    route.push(idx);
+                }
+            }
+        }
         self.listeners.push(listener);
     }
 
-    /// Emit an event to all listeners.
+    /// Emit an event to the listeners interested in its kind.
     pub fn emit(&mut self, event: SimEvent) {
+        for &idx in &self.routes[event.kind() as usize] {
+            self.listeners[idx].on_event(&event);
+        }
+    }
+
+    /// Notify all listeners that the run is over.
+    pub fn finish(&mut self, ts: u64) {
         for listener in self.listeners.iter_mut() {
-            listener.on_event(&event);
+            listener.on_finish(ts);
+        }
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    struct Counter {
+        interests: Option<&'static [EventKind]>,
+        seen: Arc<Mutex<Vec<EventKind>>>,
+    }
+
+    impl EventListener for Counter {
+        fn on_event(&mut self, event: &SimEvent) {
+            self.seen.lock().unwrap().push(event.kind());
+        }
+
+        fn interests(&self) -> Option<&'static [EventKind]> {
+            self.interests
+        }
+    }
+
+    fn oracle_tick() -> SimEvent {
+        SimEvent::OracleTick {
+            ts: 1,
+            symbol: "ETH-USD".to_string(),
+            price_min: 3_000,
+            price_max: 3_000,
         }
     }
+
+    fn expired() -> SimEvent {
+        SimEvent::OrderExpired {
+            ts: 2,
+            account: 1,
+            order_id: 7,
+            symbol: "ETH-USD".to_string(),
+            side: Side::Buy,
+        }
+    }
+
+    #[test]
+    fn test_emit_routes_by_kind() {
+        let all = Arc::new(Mutex::new(Vec::new()));
+        let ticks = Arc::new(Mutex::new(Vec::new()));
+        let mut bus = EventBus::new();
+        bus.subscribe(Box::new(Counter {
+            interests: None,
+            seen: all.clone(),
+        }));
+        bus.subscribe(Box::new(Counter {
+            interests: Some(&[EventKind::OracleTick, EventKind::OracleTick]),
+            seen: ticks.clone(),
+        }));
+
+        bus.emit(oracle_tick());
+        bus.emit(expired());
+
+        assert_eq!(
+            *all.lock().unwrap(),
+            vec![EventKind::OracleTick, EventKind::OrderExpired]
+        );
+        assert_eq!(*ticks.lock().unwrap(), vec![EventKind::OracleTick]);
+    }
 }
@@ -0,0 +1,141 @@
+// Executor state of the ExchangeAgent carried from one run into the next.
+//
+// At the end of a run the exchange writes what it books locally to
+// `exchange_state.json` in the logs directory: per market the last oracle
+// price, the pool liquidity and shares and the realized pool PnL, the LP shares
+// of agents and the positions and balances of local agents
+// (`vara.local_agents`). A scenario with
+// `warm_start_from` loads such a file into its exchange before the first tick,
+// so a long-horizon funding experiment continues from the positions and pool
+// balances the previous run ended with instead of starting flat. Positions and
+// orders on the contract are not part of the file: they stay on chain between
+// runs of the same contract.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::messages::{AgentId, Side};
+
+/// Name of the state file in the logs directory
+pub const STATE_FILE: &str = "exchange_state.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MarketState {
+    pub symbol: String,
+    /// Last oracle mid (micro-USD)
+    pub last_price: u64,
+    pub pool_shares: f64,
+    /// Realized pool PnL booked into the market's liquidity (micro-USD)
+    pub pool_pnl: i128,
+    /// Pool liquidity: initial liquidity, LP deposits and withdrawals and the pool PnL
+    /// (micro-USD; absent in files of older runs)
+    #[serde(default)]
+    pub liquidity_usd: Option<i128>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LpShareState {
+    pub agent: AgentId,
+    pub symbol: String,
+    pub shares: f64,
+}
+
+/// Capital ledger balance of a local agent (micro-USD).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BalanceState {
+    pub agent: AgentId,
+    pub balance: i64,
+}
+
+/// Position of a local agent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocalPositionState {
+    pub agent: AgentId,
+    pub symbol: String,
+    pub side: Side,
+    pub size_usd: u64,
+    pub size_tokens: f64,
+    /// Micro-USD value of the collateral when it was posted
+    pub collateral: u64,
+    pub collateral_tokens: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeState {
+    /// Sim time the state was written at
+    pub ts: u64,
+    pub markets: Vec<MarketState>,
+    #[serde(default)]
+    pub lp_shares: Vec<LpShareState>,
+    #[serde(default)]
+    pub local_positions: Vec<LocalPositionState>,
+    #[serde(default)]
+    pub balances: Vec<BalanceState>,
+}
+
+impl ExchangeState {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_round_trips_through_a_file() {
+        let state = ExchangeState {
+            ts: 3_600_000_000_000,
+            markets: vec![MarketState {
+                symbol: "ETH-USD".to_string(),
+                last_price: 3_000_000_000,
+                pool_shares: 1_000_000.5,
+                pool_pnl: -12_345_678,
+                liquidity_usd: Some(1_249_987_654_322),
+            }],
+            lp_shares: vec![LpShareState {
+                agent: 7,
+                symbol: "ETH-USD".to_string(),
+                shares: 250.0,
+            }],
+            local_positions: vec![LocalPositionState {
+                agent: 42,
+                symbol: "ETH-USD".to_string(),
+                side: Side::Sell,
+                size_usd: 15_000_000_000,
+                size_tokens: 5.0,
+                collateral: 3_000_000_000,
+                collateral_tokens: 3_000.0,
+            }],
+            balances: vec![BalanceState {
+                agent: 42,
+                balance: 9_250_000_000,
+            }],
+        };
+        let dir = std::env::temp_dir().join(format!("exchange-state-{}", std::process::id()));
+        let path = dir.join(STATE_FILE);
+        state.save(&path).unwrap();
+        assert_eq!(ExchangeState::load(&path).unwrap(), state);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // A file of a run without local agents or LPs
+        let json = r#"{ "ts": 0, "markets": [] }"#;
+        assert_eq!(
+            serde_json::from_str::<ExchangeState>(json).unwrap(),
+            ExchangeState::default()
+        );
+        assert!(ExchangeState::load(dir.join("missing.json")).is_err());
+    }
+}
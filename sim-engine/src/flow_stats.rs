@@ -0,0 +1,242 @@
+// Order flow statistics per market on top of EventBus.
+//
+// Every execution is taker flow against the pool: opening a long and closing
+// or liquidating a short buy, opening a short and closing or liquidating a
+// long sell. Over the last `window_sec` of sim time the tracker sums the buy
+// and sell volume of each market, counts the trades and derives the
+// imbalance, (buy - sell) / (buy + sell) in bps, and the average trade size.
+// The exchange broadcasts them as MarketStats every `broadcast_ms`, and
+// markets.csv carries them next to each market snapshot.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::{EventKind, EventListener, SimEvent};
+use crate::messages::Side;
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+
+/// Rolling window of the statistics and how often the exchange broadcasts them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketStatsConfig {
+    #[serde(default = "default_window_sec")]
+    pub window_sec: u64,
+    /// Interval of MarketStats broadcasts (0 = not broadcast)
+    #[serde(default = "default_broadcast_ms")]
+    pub broadcast_ms: u64,
+}
+
+fn default_window_sec() -> u64 {
+    300
+}
+
+fn default_broadcast_ms() -> u64 {
+    10_000
+}
+
+impl Default for MarketStatsConfig {
+    fn default() -> Self {
+        Self {
+            window_sec: default_window_sec(),
+            broadcast_ms: default_broadcast_ms(),
+        }
+    }
+}
+
+impl MarketStatsConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window_sec == 0 {
+            return Err("window_sec must be > 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Taker flow of a market over the window (volumes in micro-USD).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlowStats {
+    pub buy_volume_usd: u64,
+    pub sell_volume_usd: u64,
+    pub trades: u64,
+}
+
+impl FlowStats {
+    /// (buy - sell) / (buy + sell) in bps, -10000..=10000; 0 without volume
+    pub fn imbalance_bps(&self) -> i64 {
+        let total = self.buy_volume_usd as i128 + self.sell_volume_usd as i128;
+        if total == 0 {
+            return 0;
+        }
+        ((self.buy_volume_usd as i128 - self.sell_volume_usd as i128) * 10_000 / total) as i64
+    }
+
+    pub fn avg_trade_usd(&self) -> u64 {
+        (self.buy_volume_usd + self.sell_volume_usd) / self.trades.max(1)
+    }
+}
+
+/// Execution of a market: (ts, buy, size_usd).
+type Trade = (u64, bool, u64);
+
+#[derive(Default)]
+struct Flows {
+    window_ns: u64,
+    trades: HashMap<String, VecDeque<Trade>>,
+}
+
+/// Shared read handle to the recent flow of every market.
+#[derive(Clone, Default)]
+pub struct FlowStatsBook {
+    inner: Arc<Mutex<Flows>>,
+}
+
+impl FlowStatsBook {
+    /// Flow of `symbol` in the window ending at `now_ns`.
+    pub fn stats(&self, symbol: &str, now_ns: u64) -> FlowStats {
+        let Ok(flows) = self.inner.lock() else {
+            return FlowStats::default();
+        };
+        let start = now_ns.saturating_sub(flows.window_ns);
+        let mut stats = FlowStats::default();
+        for &(_, buy, size_usd) in flows
+            .trades
+            .get(symbol)
+            .into_iter()
+            .flatten()
+            .filter(|(ts, ..)| *ts > start && *ts <= now_ns)
+        {
+            if buy {
+                stats.buy_volume_usd += size_usd;
+            } else {
+                stats.sell_volume_usd += size_usd;
+            }
+            stats.trades += 1;
+        }
+        stats
+    }
+
+    /// Window length in seconds
+    pub fn window_sec(&self) -> u64 {
+        self.inner.lock().map(|f| f.window_ns / NS_PER_SEC).unwrap_or(0)
+    }
+}
+
+/// Flow tracker: subscribe it before the listeners and agents that read its `book()`.
+pub struct FlowStatsTracker {
+    book: FlowStatsBook,
+}
+
+impl FlowStatsTracker {
+    pub fn new(config: MarketStatsConfig) -> Self {
+        let book = FlowStatsBook::default();
+        if let Ok(mut flows) = book.inner.lock() {
+            flows.window_ns = config.window_sec * NS_PER_SEC;
+        }
+        Self { book }
+    }
+
+    /// Handle for the exchange and markets.csv.
+    pub fn book(&self) -> FlowStatsBook {
+        self.book.clone()
+    }
+
+    fn record(&mut self, ts: u64, symbol: &str, buy: bool, size_usd: u64) {
+        let Ok(mut flows) = self.book.inner.lock() else {
+            return;
+        };
+        let window_ns = flows.window_ns;
+        let trades = flows.trades.entry(symbol.to_string()).or_default();
+        while trades.front().is_some_and(|(t, ..)| ts.saturating_sub(*t) >= window_ns) {
+            trades.pop_front();
+        }
+        trades.push_back((ts, buy, size_usd));
+    }
+}
+
+impl EventListener for FlowStatsTracker {
+    fn on_event(&mut self, event: &SimEvent) {
+        if let SimEvent::OrderExecuted {
+            ts,
+            symbol,
+            side,
+            size_usd,
+            order_type,
+            ..
+        } = event
+        {
+            let buy = (order_type == "Increase") == (*side == Side::Buy);
+            self.record(*ts, symbol, buy, *size_usd);
+        }
+    }
+
+    fn interests(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::OrderExecuted])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn executed(ts_sec: u64, symbol: &str, side: Side, order_type: &str, size_usd: u64) -> SimEvent {
+        SimEvent::OrderExecuted {
+            ts: ts_sec * NS_PER_SEC,
+            account: 1,
+            symbol: symbol.to_string(),
+            side,
+            size_usd,
+            collateral: 0,
+            execution_price: 3_000_000_000,
+            leverage: 5,
+            order_type: order_type.to_string(),
+            pnl: 0,
+            fee_usd: 0,
+            funding_fee_usd: 0,
+            decision_price: None,
+        }
+    }
+
+    #[test]
+    fn test_flow_is_split_by_taker_direction_within_the_window() {
+        let mut tracker = FlowStatsTracker::new(MarketStatsConfig {
+            window_sec: 60,
+            broadcast_ms: 0,
+        });
+        let book = tracker.book();
+        for event in [
+            // Falls out of the window
+            executed(0, "ETH-USD", Side::Buy, "Increase", 1_000),
+            // Buys: long open, short close
+            executed(30, "ETH-USD", Side::Buy, "Increase", 300),
+            executed(40, "ETH-USD", Side::Sell, "Decrease", 300),
+            // Sells: short open, long liquidation
+            executed(50, "ETH-USD", Side::Sell, "Increase", 100),
+            executed(60, "ETH-USD", Side::Buy, "Liquidation", 100),
+            executed(60, "BTC-USD", Side::Buy, "Increase", 5_000),
+        ] {
+            tracker.on_event(&event);
+        }
+
+        let stats = book.stats("ETH-USD", 60 * NS_PER_SEC);
+        assert_eq!(
+            (stats.buy_volume_usd, stats.sell_volume_usd, stats.trades),
+            (600, 200, 4)
+        );
+        assert_eq!(stats.imbalance_bps(), 5_000);
+        assert_eq!(stats.avg_trade_usd(), 200);
+        assert_eq!(book.stats("BTC-USD", 60 * NS_PER_SEC).imbalance_bps(), 10_000);
+        assert_eq!(book.stats("SOL-USD", 60 * NS_PER_SEC), FlowStats::default());
+        assert_eq!(book.window_sec(), 60);
+        // Later only the newest trades remain
+        assert_eq!(book.stats("ETH-USD", 115 * NS_PER_SEC).trades, 1);
+        assert!(MarketStatsConfig {
+            window_sec: 0,
+            ..MarketStatsConfig::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,439 @@
+// Technical indicators shared by trading agents.
+//
+// Every indicator is a streaming value over a fixed-size ring buffer: `update`
+// feeds one sample and returns the value once the window is full (None while
+// warming up), `value` returns the latest one. Inputs are plain f64, so the
+// same types work on oracle mid prices, candles or any other series.
+// `RegimeDetector` combines them into trending / ranging / high-vol flags.
+
+use std::collections::VecDeque;
+
+/// Efficiency ratio at or above which the market counts as trending
+const TREND_EFFICIENCY: f64 = 0.3;
+/// Volatility over this multiple of its long-run average counts as high
+const HIGH_VOL_RATIO: f64 = 1.5;
+/// Length of the volatility baseline in detector windows
+const VOL_BASELINE_WINDOWS: usize = 10;
+
+/// Last `capacity` samples with their running sum.
+#[derive(Debug, Clone)]
+pub struct Window {
+    capacity: usize,
+    values: VecDeque<f64>,
+    sum: f64,
+}
+
+impl Window {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            values: VecDeque::with_capacity(capacity + 1),
+            sum: 0.0,
+        }
+    }
+
+    /// Add a sample; once full the oldest one is dropped and returned.
+    pub fn push(&mut self, value: f64) -> Option<f64> {
+        self.values.push_back(value);
+        self.sum += value;
+        if self.values.len() <= self.capacity {
+            return None;
+        }
+        let dropped = self.values.pop_front()?;
+        self.sum -= dropped;
+        Some(dropped)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.values.len() == self.capacity
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    pub fn oldest(&self) -> Option<f64> {
+        self.values.front().copied()
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (!self.values.is_empty()).then(|| self.sum / self.values.len() as f64)
+    }
+
+    /// Sample standard deviation (None below two samples)
+    pub fn std_dev(&self) -> Option<f64> {
+        let n = self.values.len();
+        if n < 2 {
+            return None;
+        }
+        let mean = self.sum / n as f64;
+        let var = self.values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        Some(var.sqrt())
+    }
+}
+
+/// Simple moving average.
+#[derive(Debug, Clone)]
+pub struct Sma {
+    window: Window,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        Self {
+            window: Window::new(period),
+        }
+    }
+
+    pub fn update(&mut self, value: f64) -> Option<f64> {
+        self.window.push(value);
+        self.value()
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.window.is_full().then(|| self.window.mean()).flatten()
+    }
+}
+
+/// Exponential moving average (alpha = 2 / (period + 1)), seeded with the first sample.
+#[derive(Debug, Clone)]
+pub struct Ema {
+    period: usize,
+    alpha: f64,
+    samples: usize,
+    ema: Option<f64>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        let period = period.max(1);
+        Self {
+            period,
+            alpha: 2.0 / (period as f64 + 1.0),
+            samples: 0,
+            ema: None,
+        }
+    }
+
+    pub fn update(&mut self, value: f64) -> Option<f64> {
+        self.samples += 1;
+        self.ema = Some(match self.ema {
+            Some(ema) => self.alpha * value + (1.0 - self.alpha) * ema,
+            None => value,
+        });
+        self.value()
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.ema.filter(|_| self.samples >= self.period)
+    }
+}
+
+/// Relative strength index (0..100) from the average gain and loss of the last `period` changes.
+#[derive(Debug, Clone)]
+pub struct Rsi {
+    prev: Option<f64>,
+    gains: Window,
+    losses: Window,
+}
+
+impl Rsi {
+    pub fn new(period: usize) -> Self {
+        Self {
+            prev: None,
+            gains: Window::new(period),
+            losses: Window::new(period),
+        }
+    }
+
+    pub fn update(&mut self, value: f64) -> Option<f64> {
+        if let Some(prev) = self.prev.replace(value) {
+            let diff = value - prev;
+            self.gains.push(diff.max(0.0));
+            self.losses.push((-diff).max(0.0));
+        }
+        self.value()
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        let avg_gain = self.gains.mean().filter(|_| self.gains.is_full())?;
+        let avg_loss = self.losses.mean()?;
+        if avg_loss < 0.0001 {
+            return Some(100.0);
+        }
+        Some(100.0 - 100.0 / (1.0 + avg_gain / avg_loss))
+    }
+}
+
+/// Average true range over the last `period` candles.
+#[derive(Debug, Clone)]
+pub struct Atr {
+    prev_close: Option<f64>,
+    ranges: Sma,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Self {
+        Self {
+            prev_close: None,
+            ranges: Sma::new(period),
+        }
+    }
+
+    pub fn update(&mut self, high: f64, low: f64, close: f64) -> Option<f64> {
+        let range = match self.prev_close.replace(close) {
+            Some(prev) => (high - low).max((high - prev).abs()).max((low - prev).abs()),
+            None => high - low,
+        };
+        self.ranges.update(range)
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.ranges.value()
+    }
+}
+
+/// Realized volatility: standard deviation of the last `period` log returns (per sample, not annualized).
+#[derive(Debug, Clone)]
+pub struct RealizedVol {
+    prev: Option<f64>,
+    returns: Window,
+}
+
+impl RealizedVol {
+    pub fn new(period: usize) -> Self {
+        Self {
+            prev: None,
+            returns: Window::new(period),
+        }
+    }
+
+    pub fn update(&mut self, price: f64) -> Option<f64> {
+        if let Some(prev) = self.prev.replace(price) {
+            if prev > 0.0 && price > 0.0 {
+                self.returns.push((price / prev).ln());
+            }
+        }
+        self.value()
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.returns.std_dev().filter(|_| self.returns.is_full())
+    }
+}
+
+/// Distance of the latest sample from the window mean, in standard deviations.
+#[derive(Debug, Clone)]
+pub struct ZScore {
+    window: Window,
+    last: f64,
+}
+
+impl ZScore {
+    pub fn new(period: usize) -> Self {
+        Self {
+            window: Window::new(period.max(2)),
+            last: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, value: f64) -> Option<f64> {
+        self.window.push(value);
+        self.last = value;
+        self.value()
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        if !self.window.is_full() {
+            return None;
+        }
+        let mean = self.window.mean()?;
+        let std = self.window.std_dev()?;
+        Some(if std > 0.0 { (self.last - mean) / std } else { 0.0 })
+    }
+}
+
+/// Market regime over the detector window.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Regime {
+    /// Price moved mostly in one direction (efficiency ratio at or above 0.3)
+    pub trending: bool,
+    /// Not trending: the price went back and forth
+    pub ranging: bool,
+    /// Realized volatility above 1.5x its long-run average
+    pub high_vol: bool,
+    /// Net move / sum of absolute moves (0..1)
+    pub efficiency: f64,
+    /// Realized volatility of the window (per sample)
+    pub volatility: f64,
+    /// Z-score of the latest price within the window
+    pub zscore: f64,
+}
+
+/// Classifies a price series into trending / ranging / high-vol regimes.
+#[derive(Debug, Clone)]
+pub struct RegimeDetector {
+    prices: Window,
+    moves: Window,
+    vol: RealizedVol,
+    vol_baseline: Ema,
+    zscore: ZScore,
+    regime: Option<Regime>,
+}
+
+impl RegimeDetector {
+    /// `window`: samples considered for each flag
+    pub fn new(window: usize) -> Self {
+        let window = window.max(2);
+        Self {
+            prices: Window::new(window + 1),
+            moves: Window::new(window),
+            vol: RealizedVol::new(window),
+            vol_baseline: Ema::new(window * VOL_BASELINE_WINDOWS),
+            zscore: ZScore::new(window),
+            regime: None,
+        }
+    }
+
+    pub fn update(&mut self, price: f64) -> Option<Regime> {
+        if let Some(prev) = self.prices.values.back().copied() {
+            self.moves.push((price - prev).abs());
+        }
+        self.prices.push(price);
+        let zscore = self.zscore.update(price);
+        let Some(volatility) = self.vol.update(price) else {
+            return self.regime;
+        };
+        let baseline = self.vol_baseline.update(volatility).unwrap_or(volatility);
+
+        let net_move = (price - self.prices.oldest().unwrap_or(price)).abs();
+        let efficiency = if self.moves.sum() > 0.0 {
+            net_move / self.moves.sum()
+        } else {
+            0.0
+        };
+        let trending = efficiency >= TREND_EFFICIENCY;
+        self.regime = Some(Regime {
+            trending,
+            ranging: !trending,
+            high_vol: baseline > 0.0 && volatility > baseline * HIGH_VOL_RATIO,
+            efficiency,
+            volatility,
+            zscore: zscore.unwrap_or(0.0),
+        });
+        self.regime
+    }
+
+    /// Latest regime (None while warming up)
+    pub fn regime(&self) -> Option<Regime> {
+        self.regime
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed<F: FnMut(f64) -> Option<f64>>(values: &[f64], mut update: F) -> Option<f64> {
+        values.iter().fold(None, |_, v| update(*v))
+    }
+
+    #[test]
+    fn test_window_drops_oldest() {
+        let mut w = Window::new(3);
+        assert_eq!(w.push(1.0), None);
+        w.push(2.0);
+        w.push(3.0);
+        assert!(w.is_full());
+        assert_eq!(w.push(4.0), Some(1.0));
+        assert_eq!(w.sum(), 9.0);
+        assert_eq!(w.oldest(), Some(2.0));
+        assert_eq!(w.mean(), Some(3.0));
+        assert_eq!(w.std_dev(), Some(1.0));
+    }
+
+    #[test]
+    fn test_sma_and_ema() {
+        let mut sma = Sma::new(3);
+        assert_eq!(sma.update(1.0), None);
+        assert_eq!(sma.update(2.0), None);
+        assert_eq!(sma.update(3.0), Some(2.0));
+        assert_eq!(sma.update(7.0), Some(4.0));
+
+        let mut ema = Ema::new(3);
+        assert_eq!(ema.update(10.0), None);
+        assert_eq!(ema.update(20.0), None);
+        // alpha 0.5: 10 -> 15 -> 17.5
+        assert_eq!(ema.update(20.0), Some(17.5));
+    }
+
+    #[test]
+    fn test_rsi_bounds_and_mix() {
+        let mut rsi = Rsi::new(4);
+        assert_eq!(feed(&[1.0, 2.0, 3.0, 4.0, 5.0], |v| rsi.update(v)), Some(100.0));
+
+        let mut rsi = Rsi::new(4);
+        assert_eq!(feed(&[5.0, 4.0, 3.0, 2.0, 1.0], |v| rsi.update(v)), Some(0.0));
+
+        // Gains 2+2, losses 1+1 -> RS 2 -> RSI 66.7
+        let mut rsi = Rsi::new(4);
+        let value = feed(&[10.0, 12.0, 11.0, 13.0, 12.0], |v| rsi.update(v)).unwrap();
+        assert!((value - 200.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_atr_uses_previous_close() {
+        let mut atr = Atr::new(2);
+        assert_eq!(atr.update(11.0, 9.0, 10.0), None);
+        // Gap up: true range is high - previous close = 15 - 10
+        assert_eq!(atr.update(15.0, 14.0, 14.5), Some((2.0 + 5.0) / 2.0));
+        assert_eq!(atr.value(), Some(3.5));
+    }
+
+    #[test]
+    fn test_realized_vol_and_zscore() {
+        let mut vol = RealizedVol::new(3);
+        assert_eq!(feed(&[100.0, 100.0, 100.0, 100.0], |v| vol.update(v)), Some(0.0));
+        let mut vol = RealizedVol::new(2);
+        let value = feed(&[100.0, 110.0, 99.0], |v| vol.update(v)).unwrap();
+        assert!(value > 0.1 && value < 0.2);
+
+        let mut z = ZScore::new(4);
+        assert_eq!(feed(&[1.0, 1.0, 1.0, 1.0], |v| z.update(v)), Some(0.0));
+        let mut z = ZScore::new(3);
+        // mean 2, std 1 -> (3 - 2) / 1
+        assert_eq!(feed(&[1.0, 2.0, 3.0], |v| z.update(v)), Some(1.0));
+    }
+
+    #[test]
+    fn test_regime_trending_vs_ranging() {
+        let mut trend = RegimeDetector::new(10);
+        for i in 0..40 {
+            trend.update(100.0 + i as f64);
+        }
+        let regime = trend.regime().unwrap();
+        assert!(regime.trending && !regime.ranging);
+        assert!((regime.efficiency - 1.0).abs() < 1e-9);
+
+        let mut range = RegimeDetector::new(10);
+        for i in 0..40 {
+            range.update(if i % 2 == 0 { 100.0 } else { 101.0 });
+        }
+        let regime = range.regime().unwrap();
+        assert!(regime.ranging && !regime.trending);
+    }
+
+    #[test]
+    fn test_regime_flags_volatility_spike() {
+        let mut detector = RegimeDetector::new(5);
+        for i in 0..100 {
+            detector.update(100.0 + (i % 2) as f64 * 0.1);
+        }
+        assert!(!detector.regime().unwrap().high_vol);
+        for i in 0..5 {
+            detector.update(if i % 2 == 0 { 90.0 } else { 110.0 });
+        }
+        assert!(detector.regime().unwrap().high_vol);
+    }
+}
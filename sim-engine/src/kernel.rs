@@ -1,11 +1,23 @@
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use serde::{Deserialize, Serialize};
+
 use crate::agents::Agent;
 use crate::events::{EventBus, SimEvent};
 use crate::latency::LatencyModel;
-use crate::messages::{AgentId, Message, MessagePayload, MessageType, SimulatorApi};
+use crate::logging::{self, Verbosity};
+use crate::market_data::MarketDataService;
+use crate::messages::{
+    AdminCommand, AdminResultPayload, AgentId, DirectorCommand, Message, MessagePayload, MessageType, SimulatorApi,
+};
+use crate::metrics::{KernelMetrics, SharedMetrics};
+use crate::replay::{Replay, TraceHeader, TraceWriter};
+use crate::watchdog::{self, TickWatch, WatchdogConfig};
 
 // BinaryHeap wrapper - inverted ordering for min-heap by timestamp
 #[derive(Clone)]
@@ -32,6 +44,103 @@ impl PartialOrd for ScheduledMessage {
     }
 }
 
+/// Inbox of an agent that handles at most a fixed number of messages per sim-second.
+/// Each message keeps the agent busy for `interval_ns`; messages arriving while it is busy
+/// wait here in arrival order. Wakeups are not limited.
+struct Inbox {
+    interval_ns: u64,
+    /// Sim time at which the agent is done with the messages handed to it so far
+    free_at_ns: u64,
+    backlog: VecDeque<Message>,
+    /// Backlog size of the last InboxBacklog event
+    reported: usize,
+    /// Messages that had to wait
+    delayed: u64,
+    max_backlog: usize,
+}
+
+impl Inbox {
+    fn new(msgs_per_sec: f64) -> Self {
+        Self {
+            interval_ns: (1e9 / msgs_per_sec).round() as u64,
+            free_at_ns: 0,
+            backlog: VecDeque::new(),
+            reported: 0,
+            delayed: 0,
+            max_backlog: 0,
+        }
+    }
+
+    /// Book the processing time of a message that arrived at `at_ns`.
+    fn start(&mut self, at_ns: u64) {
+        self.free_at_ns = self.free_at_ns.max(at_ns).saturating_add(self.interval_ns);
+    }
+
+    fn defer(&mut self, msg: Message) {
+        self.backlog.push_back(msg);
+        self.delayed += 1;
+        self.max_backlog = self.max_backlog.max(self.backlog.len());
+    }
+}
+
+/// What the kernel does with a message for an agent id that was never registered
+/// (a wiring bug of the scenario, or an agent that has not joined yet).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case", deny_unknown_fields)]
+pub enum DeadLetterPolicy {
+    /// Drop the message
+    #[default]
+    Drop,
+    /// Try again `after_ticks` later, at most `max_attempts` times, then drop it
+    Retry {
+        #[serde(default = "default_retry_after_ticks")]
+        after_ticks: u32,
+        #[serde(default = "default_retry_attempts")]
+        max_attempts: u32,
+    },
+    /// Stop the run with an error
+    Fail,
+}
+
+fn default_retry_after_ticks() -> u32 {
+    10
+}
+
+fn default_retry_attempts() -> u32 {
+    3
+}
+
+/// Messages for unregistered agent ids. Every one is reported as a DeadLetter event.
+#[derive(Default)]
+struct DeadLetters {
+    policy: DeadLetterPolicy,
+    /// Messages waiting for another attempt: due time, attempts so far
+    retries: Vec<(u64, u32, Message)>,
+    /// Dead letters so far (a retried message counts once)
+    count: u64,
+    dropped: u64,
+}
+
+/// Registered agent. The slot stays in place while its agent is taken out to
+/// handle a message, so delivery never shifts the other agents.
+struct AgentSlot {
+    id: AgentId,
+    /// None while the agent handles a message
+    agent: Option<Box<dyn Agent>>,
+    verbosity: Verbosity,
+    /// Some for agents with a message rate limit
+    inbox: Option<Inbox>,
+}
+
+/// Warm-up phase: until `until_ns` only the exempt agents (oracles, exchange) get
+/// their wakeups, the others only see messages such as oracle ticks.
+struct Warmup {
+    until_ns: u64,
+    exempt: HashSet<AgentId>,
+    /// Wakeups held back: (agent, due time)
+    held: Vec<(AgentId, u64)>,
+}
+
 /// Simulation kernel.
 /// Owns the agents, virtual time, the message queue and the EventBus.
 pub struct Kernel {
@@ -39,12 +148,54 @@ pub struct Kernel {
     tick_ns: u64,
     latency: Box<dyn LatencyModel>,
     queue: BinaryHeap<ScheduledMessage>,
-    agents: Vec<Box<dyn Agent>>,
-    /// O(1) lookup: AgentId -> index in agents vec
+    /// Registration order: broadcasts and on_stop follow it
+    agents: Vec<AgentSlot>,
+    /// O(1) lookup: AgentId -> slot in `agents`
     agent_index: HashMap<AgentId, usize>,
     event_bus: EventBus,
     /// If Some, run in realtime mode with this delay between ticks
     realtime_tick_ms: Option<u64>,
+    /// Agents spawned / removed by other agents, applied after the current message
+    pending_spawns: Vec<Box<dyn Agent>>,
+    pending_removals: Vec<AgentId>,
+    /// Delivered messages are written here (logs/trace.jsonl)
+    trace: Option<TraceWriter>,
+    /// Recorded messages driving the agents that are not live
+    replay: Option<Replay>,
+    /// Messages delivered to agents so far (wakeups included)
+    delivered: u64,
+    /// Kernel output, and the output of agents without their own level
+    verbosity: Verbosity,
+    /// Per-agent output levels, applied when the agent is added
+    agent_verbosity: HashMap<AgentId, Verbosity>,
+    /// Per-agent inbox rates (messages per sim-second), applied when the agent is added
+    inbox_rates: HashMap<AgentId, f64>,
+    /// Members of each agent group, in the order they joined
+    groups: HashMap<String, Vec<AgentId>>,
+    /// Message types whose broadcasts only reach one group
+    broadcast_groups: HashMap<MessageType, String>,
+    /// Delivered oracle prices, read by agents through `SimulatorApi::market_data`
+    market_data: MarketDataService,
+    /// Agents removed during the run: messages still queued for them are dropped quietly
+    departed: HashSet<AgentId>,
+    dead_letters: DeadLetters,
+    /// Why the run was stopped early (dead letter with the `Fail` policy)
+    failure: Option<String>,
+    /// Agents paused from the admin API, with the wakeups held back until they resume
+    paused: HashMap<AgentId, Vec<u64>>,
+    warmup: Option<Warmup>,
+    /// Per-agent (join, leave) times, applied when the agent is added
+    windows: HashMap<AgentId, (u64, Option<u64>)>,
+    /// Agents added before their join time, in the order they were added
+    waiting: Vec<(u64, Box<dyn Agent>)>,
+    /// Joined agents with a leave time
+    leaving: Vec<(u64, AgentId)>,
+    /// Delivery and handler timings, written to `<dir>/metrics.prom` at the end of the run
+    metrics: Option<(SharedMetrics, PathBuf)>,
+    /// Progress shared with the watchdog thread of a realtime run, and whether it aborts
+    watchdog: Option<(TickWatch, bool)>,
+    /// Set from another thread (e.g. the dashboard's quit key) to end the run after the tick
+    stop_requested: Option<Arc<AtomicBool>>,
 }
 
 impl Kernel {
@@ -66,6 +217,28 @@ impl Kernel {
             agent_index: HashMap::new(),
             event_bus: EventBus::new(),
             realtime_tick_ms: None,
+            pending_spawns: Vec::new(),
+            pending_removals: Vec::new(),
+            trace: None,
+            replay: None,
+            delivered: 0,
+            verbosity: Verbosity::default(),
+            agent_verbosity: HashMap::new(),
+            inbox_rates: HashMap::new(),
+            groups: HashMap::new(),
+            broadcast_groups: HashMap::new(),
+            market_data: MarketDataService::default(),
+            departed: HashSet::new(),
+            dead_letters: DeadLetters::default(),
+            failure: None,
+            paused: HashMap::new(),
+            warmup: None,
+            windows: HashMap::new(),
+            waiting: Vec::new(),
+            leaving: Vec::new(),
+            metrics: None,
+            watchdog: None,
+            stop_requested: None,
         }
     }
 
@@ -75,93 +248,682 @@ impl Kernel {
         println!("[Kernel] realtime mode enabled: {}ms per tick", tick_ms);
     }
 
+    /// Kernel output level (Debug: registrations and a banner per tick; Info: start / stop
+    /// lines only), also used for agents without their own level.
+    pub fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.verbosity = verbosity;
+    }
+
+    /// Output level of agent `id` (its `agent_println!` lines). Call before adding the agent.
+    pub fn set_agent_verbosity(&mut self, id: AgentId, verbosity: Verbosity) {
+        self.agent_verbosity.insert(id, verbosity);
+    }
+
+    /// Let agent `id` handle at most `msgs_per_sec` messages per sim-second; the rest queue up
+    /// in its inbox and are handled late. Call before adding the agent.
+    pub fn set_inbox_rate(&mut self, id: AgentId, msgs_per_sec: f64) {
+        if msgs_per_sec > 0.0 && msgs_per_sec.is_finite() {
+            self.inbox_rates.insert(id, msgs_per_sec);
+        }
+    }
+
+    /// Add agent `id` to `groups`, the audiences of `broadcast_to_group`.
+    pub fn set_agent_groups(&mut self, id: AgentId, groups: &[String]) {
+        for group in groups {
+            let members = self.groups.entry(group.clone()).or_default();
+            if !members.contains(&id) {
+                members.push(id);
+            }
+        }
+    }
+
+    /// Send broadcasts of `kind` to the members of `group` only, whoever broadcasts them.
+    pub fn set_broadcast_group(&mut self, kind: MessageType, group: &str) {
+        self.broadcast_groups.insert(kind, group.to_string());
+    }
+
+    /// Members of `group` (empty for an unknown group).
+    pub fn group_members(&self, group: &str) -> &[AgentId] {
+        self.groups.get(group).map_or(&[], Vec::as_slice)
+    }
+
+    /// Messages waiting in the inbox of agent `id` (None without a rate limit).
+    pub fn inbox_backlog(&self, id: AgentId) -> Option<usize> {
+        let idx = *self.agent_index.get(&id)?;
+        self.agents[idx].inbox.as_ref().map(|inbox| inbox.backlog.len())
+    }
+
+    /// Messages delivered to agents so far.
+    pub fn delivered(&self) -> u64 {
+        self.delivered
+    }
+
+    /// Handling of messages for unregistered agent ids (default: drop).
+    pub fn set_dead_letter_policy(&mut self, policy: DeadLetterPolicy) {
+        self.dead_letters.policy = policy;
+    }
+
+    /// Messages for unregistered agent ids so far.
+    pub fn dead_letters(&self) -> u64 {
+        self.dead_letters.count
+    }
+
+    /// Why the run stopped early, if it did.
+    pub fn failure(&self) -> Option<&str> {
+        self.failure.as_deref()
+    }
+
+    /// Flag that ends the run after the current tick once set, through the normal shutdown
+    /// (on_stop, listeners' on_finish). The same flag on every call.
+    pub fn stop_handle(&mut self) -> Arc<AtomicBool> {
+        self.stop_requested
+            .get_or_insert_with(|| Arc::new(AtomicBool::new(false)))
+            .clone()
+    }
+
+    /// Whether the agent is paused from the admin API.
+    pub fn is_paused(&self, id: AgentId) -> bool {
+        self.paused.contains_key(&id)
+    }
+
+    /// Hold back the wakeups of every agent but `exempt` for the first `duration_ns` of the run,
+    /// so traders only start once the oracle ticks built up some history. Call after
+    /// `set_start_time` / `set_replay`.
+    pub fn set_warmup(&mut self, duration_ns: u64, exempt: &[AgentId]) {
+        self.warmup = (duration_ns > 0).then(|| Warmup {
+            until_ns: self.time_ns.saturating_add(duration_ns),
+            exempt: exempt.iter().copied().collect(),
+            held: Vec::new(),
+        });
+    }
+
+    /// Let agent `id` join `join_after_ns` after the current time and leave `leave_after_ns`
+    /// after it (None = stays until the end). Call after `set_start_time` / `set_replay` and
+    /// before adding the agent; until it joins the agent gets no messages.
+    pub fn set_agent_window(&mut self, id: AgentId, join_after_ns: u64, leave_after_ns: Option<u64>) {
+        let join_ns = self.time_ns.saturating_add(join_after_ns);
+        let leave_ns = leave_after_ns.map(|ns| self.time_ns.saturating_add(ns));
+        self.windows.insert(id, (join_ns, leave_ns));
+    }
+
+    /// Agents added but not joined yet.
+    pub fn waiting_agents(&self) -> usize {
+        self.waiting.len()
+    }
+
+    /// Add the waiting agents whose join time came and remove those whose window ended.
+    fn apply_windows(&mut self) {
+        let now = self.time_ns;
+        if self.waiting.iter().any(|(at, _)| *at <= now) {
+            let (mut due, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.waiting)
+                .into_iter()
+                .partition(|(at, _)| *at <= now);
+            self.waiting = waiting;
+            due.sort_by_key(|(at, _)| *at);
+            for (_, agent) in due {
+                self.add_agent(agent);
+            }
+        }
+        let (due, leaving): (Vec<_>, Vec<_>) = std::mem::take(&mut self.leaving)
+            .into_iter()
+            .partition(|(at, _)| *at <= now);
+        self.leaving = leaving;
+        self.pending_removals.extend(due.into_iter().map(|(_, id)| id));
+        self.apply_agent_changes();
+    }
+
+    /// Whether the warm-up phase is still running.
+    pub fn in_warmup(&self) -> bool {
+        self.warmup.is_some()
+    }
+
+    /// End the warm-up once its time is up: the held wakeups are due now.
+    fn finish_warmup(&mut self) {
+        let now = self.time_ns;
+        let Some(warmup) = self.warmup.take_if(|w| now >= w.until_ns) else {
+            return;
+        };
+        let mut held = warmup.held;
+        held.sort_unstable();
+        held.dedup();
+        if self.verbosity >= Verbosity::Info {
+            println!("[Kernel] warm-up over, releasing {} held wakeup(s)", held.len());
+        }
+        for (id, at) in held {
+            self.wakeup(id, at.max(self.time_ns));
+        }
+    }
+
+    /// Start the clock at `time_ns` instead of the wall clock (reproducible runs). Call before adding agents.
+    pub fn set_start_time(&mut self, time_ns: u64) {
+        self.time_ns = time_ns;
+    }
+
+    /// Time every delivery (see `metrics`); the histograms go to `dir`/metrics.prom at the end
+    /// of the run. Returns the live metrics, the same ones on every call.
+    pub fn record_metrics(&mut self, dir: &Path) -> SharedMetrics {
+        self.metrics
+            .get_or_insert_with(|| (KernelMetrics::shared(), dir.to_path_buf()))
+            .0
+            .clone()
+    }
+
+    /// Watch for realtime ticks stuck in an agent's handler (see `watchdog`); does nothing
+    /// outside realtime mode. Call after `set_realtime` and before adding agents.
+    pub fn set_watchdog(&mut self, config: WatchdogConfig) {
+        if let Some(tick_ms) = self.realtime_tick_ms {
+            self.watchdog = Some((TickWatch::new(tick_ms, config), config.abort));
+        }
+    }
+
+    /// Record every delivered message to `dir`/trace.jsonl. Call before adding agents.
+    pub fn record_trace(&mut self, dir: &std::path::Path) {
+        let header = TraceHeader {
+            start_ns: self.time_ns,
+            tick_ns: self.tick_ns,
+        };
+        match TraceWriter::new(dir, header) {
+            Ok(writer) => {
+                self.trace = Some(writer);
+                println!("[Kernel] recording message trace");
+            }
+            Err(e) => eprintln!("[Kernel] failed to open message trace: {}", e),
+        }
+    }
+
+    /// Replay a recorded trace: the clock restarts where the recording started and the
+    /// recorded messages of absent agents are delivered to the ones added. Call before adding agents.
+    pub fn set_replay(&mut self, header: TraceHeader, replay: Replay) {
+        self.time_ns = header.start_ns;
+        self.tick_ns = header.tick_ns;
+        self.replay = Some(replay);
+    }
+
+    /// Next message due at the current time: recorded messages go first on equal times,
+    /// as they were delivered before anything the live agents send now.
+    fn next_due(&mut self) -> Option<Message> {
+        let queued_at = self.queue.peek().map(|sm| sm.0.at);
+        if let Some(replay) = self.replay.as_mut() {
+            if let Some(at) = replay.next_at() {
+                if at <= self.time_ns && queued_at.is_none_or(|q| at <= q) {
+                    return replay.pop();
+                }
+            }
+        }
+        if queued_at? > self.time_ns {
+            return None;
+        }
+        self.queue.pop().map(|sm| sm.0)
+    }
+
     /// Access to the event bus (for SimEngine to subscribe loggers).
     pub fn event_bus_mut(&mut self) -> &mut EventBus {
         &mut self.event_bus
     }
 
-    /// Add a new agent into the simulation.
+    /// Add a new agent into the simulation; one with a later join time (`set_agent_window`)
+    /// waits until then.
     pub fn add_agent(&mut self, mut agent: Box<dyn Agent>) {
         let id = agent.id();
-        println!("[Kernel] registering agent {} (id={})", agent.name(), id);
+        let (join_ns, leave_ns) = self.windows.get(&id).copied().unwrap_or((0, None));
+        if join_ns > self.time_ns {
+            if self.verbosity >= Verbosity::Debug {
+                println!("[Kernel] agent {} (id={}) joins at {} ns", agent.name(), id, join_ns);
+            }
+            self.waiting.push((join_ns, agent));
+            return;
+        }
+        if self.verbosity >= Verbosity::Debug {
+            println!("[Kernel] registering agent {} (id={})", agent.name(), id);
+        }
+        if let Some((watch, _)) = &self.watchdog {
+            watch.register(id, agent.name());
+        }
+        let verbosity = self.agent_verbosity.get(&id).copied().unwrap_or(self.verbosity);
         // Let the agent initialize itself using the simulator API.
+        logging::set_agent_output(verbosity >= Verbosity::Info);
         agent.on_start(self);
+        logging::set_agent_output(true);
         let idx = self.agents.len();
-        self.agents.push(agent);
+        self.agents.push(AgentSlot {
+            id,
+            agent: Some(agent),
+            verbosity,
+            inbox: self.inbox_rates.get(&id).map(|&rate| Inbox::new(rate)),
+        });
         self.agent_index.insert(id, idx);
+        if let Some(leave_ns) = leave_ns {
+            self.leaving.push((leave_ns, id));
+        }
+    }
+
+    /// Apply agent joins/leaves requested during message delivery.
+    fn apply_agent_changes(&mut self) {
+        for agent in std::mem::take(&mut self.pending_spawns) {
+            self.add_agent(agent);
+        }
+        for id in std::mem::take(&mut self.pending_removals) {
+            let Some(idx) = self.agent_index.remove(&id) else {
+                continue;
+            };
+            self.departed.insert(id);
+            self.paused.remove(&id);
+            // Removals are rare: shift the later slots (O(n)) to keep registration order
+            let slot = self.agents.remove(idx);
+            for (i, later) in self.agents.iter().enumerate().skip(idx) {
+                self.agent_index.insert(later.id, i);
+            }
+            let Some(mut agent) = slot.agent else {
+                continue;
+            };
+            logging::set_agent_output(slot.verbosity >= Verbosity::Info);
+            agent.on_stop(self);
+            logging::set_agent_output(true);
+            if self.verbosity >= Verbosity::Debug {
+                println!("[Kernel] removed agent {} (id={})", agent.name(), id);
+            }
+        }
+    }
+
+    /// Hand `msg` on, or queue it in the inbox of its (rate-limited, busy) target.
+    fn admit(&mut self, msg: Message) -> Option<Message> {
+        if msg.msg_type == MessageType::Wakeup {
+            return Some(msg);
+        }
+        let Some(inbox) = self
+            .agent_index
+            .get(&msg.to)
+            .and_then(|&idx| self.agents[idx].inbox.as_mut())
+        else {
+            return Some(msg);
+        };
+        if !inbox.backlog.is_empty() || inbox.free_at_ns > self.time_ns {
+            inbox.defer(msg);
+            return None;
+        }
+        inbox.start(msg.at);
+        Some(msg)
+    }
+
+    /// Deliver the inbox messages whose agents are free again by now. Returns whether any
+    /// was delivered (they may have sent messages due now).
+    fn drain_inboxes(&mut self) -> bool {
+        let waiting: Vec<AgentId> = self
+            .agents
+            .iter()
+            .filter(|s| s.inbox.as_ref().is_some_and(|i| !i.backlog.is_empty()))
+            .map(|s| s.id)
+            .collect();
+        let mut delivered = false;
+        for id in waiting {
+            loop {
+                // Looked up per message: a delivery may remove agents
+                let now = self.time_ns;
+                let Some(inbox) = self
+                    .agent_index
+                    .get(&id)
+                    .and_then(|&idx| self.agents[idx].inbox.as_mut())
+                else {
+                    break;
+                };
+                if inbox.free_at_ns > now {
+                    break;
+                }
+                let Some(msg) = inbox.backlog.pop_front() else {
+                    break;
+                };
+                inbox.start(msg.at);
+                self.deliver(msg);
+                delivered = true;
+            }
+        }
+        delivered
+    }
+
+    /// Emit InboxBacklog for the inboxes whose size changed during the tick.
+    fn report_backlogs(&mut self) {
+        for slot in &mut self.agents {
+            let Some(inbox) = slot.inbox.as_mut() else {
+                continue;
+            };
+            if inbox.backlog.len() != inbox.reported {
+                inbox.reported = inbox.backlog.len();
+                self.event_bus.emit(SimEvent::InboxBacklog {
+                    ts: self.time_ns,
+                    agent: slot.id,
+                    backlog: inbox.reported,
+                    delayed: inbox.delayed,
+                });
+            }
+        }
+    }
+
+    fn deliver(&mut self, msg: Message) {
+        if msg.msg_type == MessageType::Wakeup {
+            if let Some(held) = self.paused.get_mut(&msg.to) {
+                held.push(msg.at);
+                return;
+            }
+            if let Some(warmup) = self.warmup.as_mut().filter(|w| !w.exempt.contains(&msg.to)) {
+                warmup.held.push((msg.to, msg.at));
+                return;
+            }
+        }
+        if let Some(trace) = self.trace.as_mut() {
+            trace.record(&msg);
+        }
+        let target = msg.to;
+
+        // O(1) lookup using agent_index; the agent is taken out of its slot to avoid
+        // aliasing &mut self and &mut agent at the same time.
+        let detached = self.agent_index.get(&target).copied().and_then(|idx| {
+            let slot = &mut self.agents[idx];
+            let agent = slot.agent.take()?;
+            Some((idx, agent, slot.verbosity))
+        });
+
+        if let Some((idx, mut agent, verbosity)) = detached {
+            self.delivered += 1;
+            if matches!(msg.msg_type, MessageType::OracleTick | MessageType::OracleBatchTick) {
+                for tick in msg.payload.oracle_ticks() {
+                    self.market_data.record(tick, msg.at);
+                }
+            }
+
+            {
+                // Use `self` as SimulatorApi while the agent is detached.
+                logging::set_agent_output(verbosity >= Verbosity::Debug);
+                let started = self.metrics.is_some().then(Instant::now);
+                if let Some((watch, _)) = &self.watchdog {
+                    watch.enter(target, msg.msg_type, Instant::now());
+                }
+                match msg.msg_type {
+                    MessageType::Wakeup => agent.on_wakeup(self, msg.at),
+                    MessageType::Admin => self.handle_admin(agent.as_mut(), &msg),
+                    // Timeline parameter changes go straight to the agent's `set_param`
+                    MessageType::Director => match &msg.payload {
+                        MessagePayload::Director(DirectorCommand::SetParam { key, value }) => {
+                            if !agent.set_param(key, value) {
+                                eprintln!("[{}] unsupported param {}={}", agent.name(), key, value);
+                            }
+                        }
+                        _ => agent.on_message(self, &msg),
+                    },
+                    _ => agent.on_message(self, &msg),
+                }
+                if let Some((watch, _)) = &self.watchdog {
+                    watch.leave(Instant::now());
+                }
+                logging::set_agent_output(true);
+                if let (Some((metrics, _)), Some(started)) = (&self.metrics, started) {
+                    let handler_ns = started.elapsed().as_nanos() as u64;
+                    let lag_ns = self.time_ns.saturating_sub(msg.at);
+                    if let Ok(mut metrics) = metrics.lock() {
+                        metrics.record(msg.msg_type, lag_ns, target, agent.name(), handler_ns);
+                    }
+                }
+            }
+
+            // Put the agent back into its slot; joins / leaves only apply after that,
+            // so the slot index is still valid.
+            self.agents[idx].agent = Some(agent);
+            self.apply_agent_changes();
+        } else if msg.msg_type == MessageType::Wakeup || self.departed.contains(&target) {
+            if self.verbosity >= Verbosity::Debug {
+                println!(
+                    "[Kernel] message for departed agent id={} dropped: {:?}",
+                    target, msg.msg_type
+                );
+            }
+        } else if let MessagePayload::Admin(command) = &msg.payload {
+            self.reply_admin(&msg, command, Err(format!("unknown agent id={}", target)));
+        } else {
+            self.dead_letter(msg, 1);
+        }
+    }
+
+    /// Run an admin API command through the hooks of its (detached) agent.
+    fn handle_admin(&mut self, agent: &mut dyn Agent, msg: &Message) {
+        let MessagePayload::Admin(command) = &msg.payload else {
+            return;
+        };
+        let id = msg.to;
+        let result = match command {
+            AdminCommand::Pause if self.paused.contains_key(&id) => Err("already paused".to_string()),
+            AdminCommand::Pause => {
+                self.paused.insert(id, Vec::new());
+                agent.on_pause(self);
+                Ok(())
+            }
+            AdminCommand::Resume => match self.paused.remove(&id) {
+                Some(mut held) => {
+                    agent.on_resume(self);
+                    held.sort_unstable();
+                    held.dedup();
+                    for at in held {
+                        self.wakeup(id, at.max(self.time_ns));
+                    }
+                    Ok(())
+                }
+                None => Err("not paused".to_string()),
+            },
+            AdminCommand::ConfigUpdate(config) => agent.on_config_update(self, config.clone()),
+        };
+        self.reply_admin(msg, command, result);
+    }
+
+    /// Answer the sender of an admin command with AdminResult.
+    fn reply_admin(&mut self, msg: &Message, command: &AdminCommand, result: Result<(), String>) {
+        if self.verbosity >= Verbosity::Info {
+            match &result {
+                Ok(()) => println!("[Kernel] admin {} agent {}", command.name(), msg.to),
+                Err(e) => println!("[Kernel] admin {} agent {} failed: {}", command.name(), msg.to, e),
+            }
+        }
+        self.send(
+            msg.to,
+            msg.from,
+            MessageType::AdminResult,
+            MessagePayload::AdminResult(AdminResultPayload {
+                agent: msg.to,
+                command: command.name().to_string(),
+                error: result.err(),
+            }),
+        );
+    }
+
+    /// Handle attempt `attempt` of a message for an unregistered agent as the policy says.
+    fn dead_letter(&mut self, msg: Message, attempt: u32) {
+        if attempt == 1 {
+            self.dead_letters.count += 1;
+        }
+        let (msg_type, from, to) = (msg.msg_type, msg.from, msg.to);
+        let action = match self.dead_letters.policy {
+            DeadLetterPolicy::Retry {
+                after_ticks,
+                max_attempts,
+            } if attempt <= max_attempts => {
+                let due = self.time_ns.saturating_add(after_ticks as u64 * self.tick_ns);
+                self.dead_letters.retries.push((due, attempt, msg));
+                "retry"
+            }
+            DeadLetterPolicy::Fail => {
+                self.failure
+                    .get_or_insert_with(|| format!("{:?} from agent {} to unknown agent id={}", msg_type, from, to));
+                "fail"
+            }
+            _ => {
+                self.dead_letters.dropped += 1;
+                "drop"
+            }
+        };
+        eprintln!(
+            "[Kernel] dead letter: {:?} from {} to unknown agent id={} (attempt {}) -> {}",
+            msg_type, from, to, attempt, action
+        );
+        self.event_bus.emit(SimEvent::DeadLetter {
+            ts: self.time_ns,
+            from,
+            to,
+            msg_type: format!("{:?}", msg_type),
+            attempt,
+            action: action.to_string(),
+        });
+    }
+
+    /// Try the dead letters due for another attempt: delivered if their agent joined meanwhile.
+    fn retry_dead_letters(&mut self) {
+        if self.dead_letters.retries.is_empty() {
+            return;
+        }
+        let now = self.time_ns;
+        let (due, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.dead_letters.retries)
+            .into_iter()
+            .partition(|(at, _, _)| *at <= now);
+        self.dead_letters.retries = waiting;
+        for (_, attempt, msg) in due {
+            if !self.agent_index.contains_key(&msg.to) {
+                self.dead_letter(msg, attempt + 1);
+            } else if let Some(msg) = self.admit(msg) {
+                self.deliver(msg);
+            }
+        }
+    }
+
+    /// Emit TickOverrun if the tick ran past the watchdog limit.
+    fn report_overrun(&mut self) {
+        let Some((watch, _)) = &self.watchdog else {
+            return;
+        };
+        let limit_ms = watch.limit().as_millis() as u64;
+        let Some(overrun) = watch.end_tick(Instant::now()) else {
+            return;
+        };
+        let (agent, msg_type, handler) = match overrun.slowest {
+            Some((agent, msg_type, took)) => (Some(agent), Some(format!("{:?}", msg_type)), took),
+            None => (None, None, Duration::ZERO),
+        };
+        self.event_bus.emit(SimEvent::TickOverrun {
+            ts: self.time_ns,
+            tick: overrun.tick,
+            elapsed_ms: overrun.elapsed.as_millis() as u64,
+            limit_ms,
+            agent,
+            msg_type,
+            handler_ms: handler.as_millis() as u64,
+        });
+    }
+
+    /// Write the metrics file and print the agents with the most handler time.
+    fn finish_metrics(&self, metrics: &SharedMetrics, dir: &Path) {
+        let Ok(metrics) = metrics.lock() else {
+            return;
+        };
+        let path = dir.join("metrics.prom");
+        if let Err(e) = std::fs::write(&path, metrics.prometheus()) {
+            eprintln!("[Kernel] failed to write {}: {}", path.display(), e);
+        }
+        if self.verbosity >= Verbosity::Info {
+            for (id, times) in metrics.top_handlers(5) {
+                let h = &times.handler;
+                println!(
+                    "[Kernel] handler time of {} (id={}): {:.1}ms total, {} calls, p99 <= {}us, max {}us",
+                    times.name,
+                    id,
+                    h.sum_ns() as f64 / 1e6,
+                    h.count(),
+                    h.quantile_ns(0.99) / 1_000,
+                    h.max_ns() / 1_000
+                );
+            }
+        }
     }
 
     /// Run the simulation for `max_steps` ticks, or until the queue is empty.
     pub fn run(&mut self, max_steps: usize) {
-        println!(
-            "[Kernel] starting simulation with {} agents, tick_ns = {}",
-            self.agents.len(),
-            self.tick_ns
-        );
-        println!("[Kernel] start time: {} ns", self.time_ns);
-        if let Some(ms) = self.realtime_tick_ms {
+        if self.verbosity >= Verbosity::Info {
+            println!(
+                "[Kernel] starting simulation with {} agents, tick_ns = {}",
+                self.agents.len(),
+                self.tick_ns
+            );
+            println!("[Kernel] start time: {} ns", self.time_ns);
+        }
+        if let Some(ms) = self.realtime_tick_ms.filter(|_| self.verbosity >= Verbosity::Info) {
             println!("[Kernel] REALTIME MODE: {}ms between ticks", ms);
         }
+        let watchdog_thread = self
+            .watchdog
+            .as_ref()
+            .map(|(watch, abort)| watchdog::spawn(watch.clone(), *abort));
 
         for step in 0..max_steps {
             let tick_start = Instant::now();
+            if let Some((watch, _)) = &self.watchdog {
+                watch.begin_tick(step as u64 + 1, tick_start);
+            }
 
             // Advance virtual time.
             self.time_ns = self.time_ns.saturating_add(self.tick_ns);
+            self.finish_warmup();
+            self.apply_windows();
 
             // In realtime mode, print less verbose output
-            if self.realtime_tick_ms.is_some() {
-                if step % 10 == 0 {
+            if self.verbosity >= Verbosity::Debug {
+                if self.realtime_tick_ms.is_none() {
+                    println!("\n[Kernel] === TICK {} at t={} ns ===", step + 1, self.time_ns);
+                } else if step % 10 == 0 {
                     println!("[Kernel] TICK {} (realtime)", step + 1);
                 }
-            } else {
-            println!("\n[Kernel] === TICK {} at t={} ns ===", step + 1, self.time_ns);
             }
 
-            // Deliver all messages whose delivery time is <= now.
+            // Deliver all messages whose delivery time is <= now; rate-limited agents take
+            // theirs from the inbox as far as their processing time allows.
+            self.retry_dead_letters();
             loop {
-                let next_at = match self.queue.peek() {
-                    Some(sm) => sm.0.at,
-                    None => break,
-                };
-
-                if next_at > self.time_ns {
+                while let Some(msg) = self.next_due() {
+                    if let Some(msg) = self.admit(msg) {
+                        self.deliver(msg);
+                    }
+                }
+                if !self.drain_inboxes() {
                     break;
                 }
+            }
+            self.report_backlogs();
+            self.report_overrun();
 
-                let sm = self.queue.pop().expect("queue was not empty");
-                let msg = sm.0;
-                let target = msg.to;
-
-                // O(1) lookup using agent_index
-                let idx_opt = self.agent_index.get(&target).copied();
-
-                if let Some(idx) = idx_opt {
-                    // Temporarily move agent out of the vector to avoid
-                    // aliasing &mut self and &mut agent at the same time.
-                    let mut agent = self.agents.remove(idx);
-
-                    {
-                        // Use `self` as SimulatorApi while the agent is detached.
-                        let sim: &mut dyn SimulatorApi = self;
-                        match msg.msg_type {
-                            MessageType::Wakeup => agent.on_wakeup(sim, msg.at),
-                            _ => agent.on_message(sim, &msg),
-                        }
-                    }
+            if let Some(failure) = &self.failure {
+                eprintln!("\n[Kernel] stopping after {} ticks: dead letter {}", step + 1, failure);
+                break;
+            }
 
-                    // Put the agent back in the same position.
-                    self.agents.insert(idx, agent);
-                } else {
-                    println!(
-                        "[Kernel] message scheduled for unknown agent id={} -> dropped: {:?}",
-                        target, msg
-                    );
+            if self
+                .stop_requested
+                .as_ref()
+                .is_some_and(|stop| stop.load(std::sync::atomic::Ordering::Relaxed))
+            {
+                if self.verbosity >= Verbosity::Info {
+                    println!("\n[Kernel] stop requested, stopping after {} ticks", step + 1);
                 }
+                break;
             }
 
-            if self.queue.is_empty() {
-                println!("\n[Kernel] queue is empty, stopping early after {} ticks", step + 1);
+            if self.queue.is_empty()
+                && self.dead_letters.retries.is_empty()
+                && self.paused.values().all(Vec::is_empty)
+                && self.warmup.as_ref().is_none_or(|w| w.held.is_empty())
+                && self.waiting.is_empty()
+                && self.leaving.is_empty()
+                && self.replay.as_ref().is_none_or(|r| r.is_done())
+                && self
+                    .agents
+                    .iter()
+                    .all(|s| s.inbox.as_ref().is_none_or(|i| i.backlog.is_empty()))
+            {
+                if self.verbosity >= Verbosity::Info {
+                    println!("\n[Kernel] queue is empty, stopping early after {} ticks", step + 1);
+                }
                 break;
             }
 
@@ -175,14 +937,61 @@ impl Kernel {
             }
         }
 
+        if let Some((watch, _)) = &self.watchdog {
+            watch.stop();
+        }
+        if let Some(thread) = watchdog_thread {
+            let _ = thread.join();
+        }
+
+        if self.verbosity >= Verbosity::Info {
+            for slot in &self.agents {
+                if let Some(inbox) = slot.inbox.as_ref().filter(|i| i.delayed > 0) {
+                    println!(
+                        "[Kernel] inbox of agent {}: {} messages delayed, max backlog {}, {} unhandled",
+                        slot.id,
+                        inbox.delayed,
+                        inbox.max_backlog,
+                        inbox.backlog.len()
+                    );
+                }
+            }
+        }
+
+        if let Some((metrics, dir)) = &self.metrics {
+            self.finish_metrics(metrics, dir);
+        }
+
+        if self.dead_letters.count > 0 {
+            eprintln!(
+                "[Kernel] {} messages for unknown agents ({} dropped, {} awaiting retry)",
+                self.dead_letters.count,
+                self.dead_letters.dropped,
+                self.dead_letters.retries.len()
+            );
+        }
+
         // Notify agents that we are stopping.
-        for _ in 0..self.agents.len() {
-            let mut agent = self.agents.remove(0);
-            agent.on_stop(self);
-            self.agents.push(agent);
+        for idx in 0..self.agents.len() {
+            if let Some(mut agent) = self.agents[idx].agent.take() {
+                logging::set_agent_output(self.agents[idx].verbosity >= Verbosity::Info);
+                agent.on_stop(self);
+                logging::set_agent_output(true);
+                self.agents[idx].agent = Some(agent);
+            }
+        }
+
+        self.event_bus.finish(self.time_ns);
+        if let Some(trace) = self.trace.as_mut() {
+            trace.finish();
+        }
+        if let Some(replay) = self.replay.as_mut() {
+            replay.finish();
         }
 
-        println!("[Kernel] simulation finished at {} ns", self.time_ns);
+        if self.verbosity >= Verbosity::Info {
+            println!("[Kernel] simulation finished at {} ns", self.time_ns);
+        }
     }
 }
 
@@ -214,6 +1023,7 @@ impl SimulatorApi for Kernel {
                 let (symbol, side, price, qty) = match &msg.payload {
                     MessagePayload::LimitOrder(p) => (Some(p.symbol.clone()), Some(p.side), Some(p.price), Some(p.qty)),
                     MessagePayload::MarketOrder(p) => (Some(p.symbol.clone()), Some(p.side), None, Some(p.qty)),
+                    MessagePayload::ModifyOrder(p) => (None, None, p.trigger_price, p.qty),
                     _ => (None, None, None, None),
                 };
 
@@ -230,8 +1040,8 @@ impl SimulatorApi for Kernel {
                 self.event_bus.emit(ev);
             }
 
-            MessageType::OracleTick => {
-                if let MessagePayload::OracleTick(p) = &msg.payload {
+            MessageType::OracleTick | MessageType::OracleBatchTick => {
+                for p in msg.payload.oracle_ticks() {
                     let ev = SimEvent::OracleTick {
                         ts: self.time_ns,
                         symbol: p.symbol.clone(),
@@ -249,6 +1059,12 @@ impl SimulatorApi for Kernel {
         }
         // --- End of EventBus block ---
 
+        if let Some(replay) = self.replay.as_mut() {
+            if !replay.is_live(to) {
+                replay.on_live_send(msg);
+                return;
+            }
+        }
         self.queue.push(ScheduledMessage(msg));
     }
 
@@ -258,9 +1074,33 @@ impl SimulatorApi for Kernel {
     }
 
     fn broadcast(&mut self, from: AgentId, kind: MessageType, payload: MessagePayload) {
-        // Emit event once for broadcast (not per recipient)
-        if let MessageType::OracleTick = kind {
-            if let MessagePayload::OracleTick(p) = &payload {
+        if let Some(group) = self.broadcast_groups.get(&kind).cloned() {
+            self.broadcast_to_group(from, &group, kind, payload);
+            return;
+        }
+        let recipients: Vec<AgentId> = self
+            .agents
+            .iter()
+            .map(|slot| slot.id)
+            .filter(|id| *id != from)
+            .collect();
+        self.multicast(from, &recipients, kind, payload);
+    }
+
+    fn broadcast_to_group(&mut self, from: AgentId, group: &str, kind: MessageType, payload: MessagePayload) {
+        let recipients: Vec<AgentId> = self
+            .group_members(group)
+            .iter()
+            .copied()
+            .filter(|id| *id != from && self.agent_index.contains_key(id))
+            .collect();
+        self.multicast(from, &recipients, kind, payload);
+    }
+
+    fn multicast(&mut self, from: AgentId, to: &[AgentId], kind: MessageType, payload: MessagePayload) {
+        // Emit event once for the whole fan-out (not per recipient), one per symbol of a batch
+        if matches!(kind, MessageType::OracleTick | MessageType::OracleBatchTick) {
+            for p in payload.oracle_ticks() {
                 let ev = SimEvent::OracleTick {
                     ts: self.time_ns,
                     symbol: p.symbol.clone(),
@@ -271,11 +1111,7 @@ impl SimulatorApi for Kernel {
             }
         }
 
-        for i in 0..self.agents.len() {
-            let id = self.agents[i].id();
-            if id == from {
-                continue;
-            }
+        for &id in to {
             let network = self.latency.delay_ns(from, id);
             let compute = self.latency.compute_ns(id);
             let at = self.time_ns.saturating_add(network).saturating_add(compute);
@@ -294,4 +1130,619 @@ impl SimulatorApi for Kernel {
     fn emit_event(&mut self, event: SimEvent) {
         self.event_bus.emit(event);
     }
+
+    fn spawn_agent(&mut self, agent: Box<dyn Agent>) {
+        self.pending_spawns.push(agent);
+    }
+
+    fn remove_agent(&mut self, agent_id: AgentId) {
+        self.pending_removals.push(agent_id);
+    }
+
+    fn market_data(&self) -> &MarketDataService {
+        &self.market_data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::latency::FixedLatency;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Logs (agent, event) pairs; agent 1 removes agent 2 and broadcasts on its first wakeup.
+    struct Probe {
+        id: AgentId,
+        log: Rc<RefCell<Vec<(AgentId, String)>>>,
+    }
+
+    impl Agent for Probe {
+        fn id(&self) -> AgentId {
+            self.id
+        }
+
+        fn name(&self) -> &str {
+            "Probe"
+        }
+
+        fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+            if self.id == 1 {
+                sim.wakeup(self.id, sim.now_ns() + 1);
+            }
+        }
+
+        fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
+            self.log.borrow_mut().push((self.id, "stop".to_string()));
+        }
+
+        fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, _now_ns: u64) {
+            sim.remove_agent(2);
+            sim.broadcast(self.id, MessageType::MarketData, MessagePayload::Empty);
+        }
+
+        fn on_message(&mut self, _sim: &mut dyn SimulatorApi, msg: &Message) {
+            self.log.borrow_mut().push((self.id, format!("from {}", msg.from)));
+        }
+    }
+
+    #[test]
+    fn test_removed_agent_keeps_others_in_registration_order() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(10, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        for id in 1..=4 {
+            kernel.add_agent(Box::new(Probe { id, log: log.clone() }));
+        }
+        kernel.run(5);
+
+        let log = log.borrow();
+        // Agent 2 stops when removed; the broadcast queued for it is dropped
+        assert_eq!(log[0], (2, "stop".to_string()));
+        let mut received: Vec<AgentId> = log.iter().filter(|(_, e)| e == "from 1").map(|(id, _)| *id).collect();
+        received.sort();
+        assert_eq!(received, vec![3, 4]);
+        // Wakeup + two broadcasts
+        assert_eq!(kernel.delivered(), 3);
+        let stops: Vec<AgentId> = log.iter().filter(|(_, e)| e == "stop").map(|(id, _)| *id).collect();
+        assert_eq!(stops, vec![2, 1, 3, 4]);
+    }
+
+    #[test]
+    fn test_grouped_broadcast_reaches_group_members_only() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(10, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        let traders = ["traders".to_string()];
+        for id in 1..=3 {
+            kernel.set_agent_groups(id, &traders);
+        }
+        kernel.set_agent_groups(4, &["keepers".to_string()]);
+        kernel.set_agent_groups(3, &traders);
+        assert_eq!(kernel.group_members("traders"), &[1, 2, 3]);
+        assert!(kernel.group_members("humans").is_empty());
+        kernel.set_broadcast_group(MessageType::MarketData, "traders");
+        for id in 1..=4 {
+            kernel.add_agent(Box::new(Probe { id, log: log.clone() }));
+        }
+        kernel.run(5);
+
+        // Agent 2 left before the broadcast, agent 4 is not a trader
+        let received: Vec<AgentId> = log
+            .borrow()
+            .iter()
+            .filter(|(_, e)| e == "from 1")
+            .map(|(id, _)| *id)
+            .collect();
+        assert_eq!(received, vec![3]);
+        assert_eq!(kernel.delivered(), 2);
+    }
+
+    /// Agent 1 multicasts to agents 3 and 9 (unknown) on its first wakeup.
+    struct Multicaster(Probe);
+
+    impl Agent for Multicaster {
+        fn id(&self) -> AgentId {
+            self.0.id
+        }
+
+        fn name(&self) -> &str {
+            "Multicaster"
+        }
+
+        fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+            self.0.on_start(sim);
+        }
+
+        fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, _now_ns: u64) {
+            sim.multicast(self.0.id, &[3, 9], MessageType::MarketData, MessagePayload::Empty);
+        }
+
+        fn on_message(&mut self, sim: &mut dyn SimulatorApi, msg: &Message) {
+            self.0.on_message(sim, msg);
+        }
+    }
+
+    #[test]
+    fn test_multicast_reaches_listed_agents_only() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(10, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        for id in 1..=4 {
+            kernel.add_agent(Box::new(Multicaster(Probe { id, log: log.clone() })));
+        }
+        kernel.run(5);
+
+        assert_eq!(*log.borrow(), vec![(3, "from 1".to_string())]);
+        // Wakeup + the message to agent 3; the one to agent 9 is dropped
+        assert_eq!(kernel.delivered(), 2);
+    }
+    /// Records whether `agent_println!` output is enabled in each callback.
+    struct OutputProbe {
+        id: AgentId,
+        log: Rc<RefCell<Vec<(AgentId, &'static str, bool)>>>,
+    }
+
+    impl Agent for OutputProbe {
+        fn id(&self) -> AgentId {
+            self.id
+        }
+
+        fn name(&self) -> &str {
+            "OutputProbe"
+        }
+
+        fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+            self.log.borrow_mut().push((self.id, "start", logging::agent_output()));
+            sim.wakeup(self.id, sim.now_ns() + 1);
+        }
+
+        fn on_stop(&mut self, _sim: &mut dyn SimulatorApi) {
+            self.log.borrow_mut().push((self.id, "stop", logging::agent_output()));
+        }
+
+        fn on_wakeup(&mut self, _sim: &mut dyn SimulatorApi, _now_ns: u64) {
+            self.log.borrow_mut().push((self.id, "wakeup", logging::agent_output()));
+        }
+    }
+
+    #[test]
+    fn test_agent_verbosity_gates_output_per_callback() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(10, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        kernel.set_agent_verbosity(1, Verbosity::Debug);
+        kernel.set_agent_verbosity(2, Verbosity::Info);
+        for id in 1..=3 {
+            kernel.add_agent(Box::new(OutputProbe { id, log: log.clone() }));
+        }
+        kernel.run(5);
+
+        let enabled = |id: AgentId| -> Vec<(&'static str, bool)> {
+            log.borrow().iter().filter(|e| e.0 == id).map(|e| (e.1, e.2)).collect()
+        };
+        assert_eq!(enabled(1), vec![("start", true), ("wakeup", true), ("stop", true)]);
+        assert_eq!(enabled(2), vec![("start", true), ("wakeup", false), ("stop", true)]);
+        // No own level: the kernel's
+        assert_eq!(enabled(3), vec![("start", false), ("wakeup", false), ("stop", false)]);
+        assert!(logging::agent_output());
+    }
+
+    /// Agent 1 sends `burst` messages to agent 2 on its wakeup; agent 2 logs when it gets them.
+    struct Burst {
+        id: AgentId,
+        burst: usize,
+        received: Rc<RefCell<Vec<u64>>>,
+    }
+
+    impl Agent for Burst {
+        fn id(&self) -> AgentId {
+            self.id
+        }
+
+        fn name(&self) -> &str {
+            "Burst"
+        }
+
+        fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+            if self.burst > 0 {
+                sim.wakeup(self.id, sim.now_ns() + 1);
+            }
+        }
+
+        fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, _now_ns: u64) {
+            for _ in 0..self.burst {
+                sim.send(self.id, 2, MessageType::MarketData, MessagePayload::Empty);
+            }
+        }
+
+        fn on_message(&mut self, sim: &mut dyn SimulatorApi, _msg: &Message) {
+            self.received.borrow_mut().push(sim.now_ns());
+        }
+    }
+
+    #[test]
+    fn test_inbox_rate_spreads_a_burst_over_ticks() {
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(10, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        kernel.set_start_time(0);
+        // One message per 100ns: one per tick
+        kernel.set_inbox_rate(2, 1e7);
+        for (id, burst) in [(1, 4), (2, 0)] {
+            kernel.add_agent(Box::new(Burst {
+                id,
+                burst,
+                received: received.clone(),
+            }));
+        }
+        kernel.run(2);
+        assert_eq!(*received.borrow(), vec![200]);
+        assert_eq!(kernel.inbox_backlog(2), Some(3));
+        assert_eq!(kernel.inbox_backlog(1), None);
+
+        kernel.run(10);
+        assert_eq!(*received.borrow(), vec![200, 300, 400, 500]);
+        assert_eq!(kernel.inbox_backlog(2), Some(0));
+    }
+
+    #[test]
+    fn test_metrics_time_deliveries_and_handlers() {
+        let dir = std::env::temp_dir().join(format!("kernel-metrics-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(10, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        kernel.set_start_time(0);
+        kernel.set_inbox_rate(2, 1e7);
+        let metrics = kernel.record_metrics(&dir);
+        for (id, burst) in [(1, 4), (2, 0)] {
+            kernel.add_agent(Box::new(Burst {
+                id,
+                burst,
+                received: received.clone(),
+            }));
+        }
+        kernel.run(10);
+
+        let metrics = metrics.lock().unwrap();
+        // Due at 110, handled at 200..500 as the inbox allows
+        let lag = &metrics.delivery_lag[&MessageType::MarketData];
+        assert_eq!((lag.count(), lag.max_ns()), (4, 390));
+        assert_eq!(metrics.delivery_lag[&MessageType::Wakeup].max_ns(), 99);
+        assert_eq!(metrics.handlers[&2].handler.count(), 4);
+        assert_eq!(metrics.handlers[&1].name, "Burst");
+        let text = std::fs::read_to_string(dir.join("metrics.prom")).unwrap();
+        assert!(text.contains("sim_delivery_lag_seconds_count{msg_type=\"MarketData\"} 4"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Kernel whose agent 1 sends one message to agent 2 (due at t=110), which is not registered.
+    fn kernel_with_dead_letter(policy: DeadLetterPolicy, received: &Rc<RefCell<Vec<u64>>>) -> Kernel {
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(10, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        kernel.set_start_time(0);
+        kernel.set_dead_letter_policy(policy);
+        kernel.add_agent(Box::new(Burst {
+            id: 1,
+            burst: 1,
+            received: received.clone(),
+        }));
+        kernel
+    }
+
+    #[test]
+    fn test_dead_letter_retry_reaches_late_joiner() {
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let policy: DeadLetterPolicy = serde_json::from_str(r#"{"policy": "retry", "after_ticks": 2}"#).unwrap();
+        assert_eq!(
+            policy,
+            DeadLetterPolicy::Retry {
+                after_ticks: 2,
+                max_attempts: 3
+            }
+        );
+        let mut kernel = kernel_with_dead_letter(policy, &received);
+        kernel.run(2);
+        assert_eq!(kernel.dead_letters(), 1);
+        assert!(received.borrow().is_empty());
+
+        kernel.add_agent(Box::new(Burst {
+            id: 2,
+            burst: 0,
+            received: received.clone(),
+        }));
+        kernel.run(10);
+        // First attempt at t=200, retried two ticks later
+        assert_eq!(*received.borrow(), vec![400]);
+        assert_eq!(kernel.dead_letters(), 1);
+        assert_eq!(kernel.dead_letters.dropped, 0);
+    }
+
+    #[test]
+    fn test_dead_letter_dropped_after_retries_or_fails_the_run() {
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let retry = DeadLetterPolicy::Retry {
+            after_ticks: 1,
+            max_attempts: 2,
+        };
+        let mut kernel = kernel_with_dead_letter(retry, &received);
+        kernel.run(10);
+        assert_eq!((kernel.dead_letters(), kernel.dead_letters.dropped), (1, 1));
+        // Attempts at t=200, 300 and 400, then the queue is empty
+        assert_eq!(kernel.now_ns(), 400);
+        assert!(kernel.failure().is_none());
+
+        let mut kernel = kernel_with_dead_letter(DeadLetterPolicy::Fail, &received);
+        kernel.run(10);
+        assert_eq!(kernel.now_ns(), 200);
+        assert_eq!(kernel.failure(), Some("MarketData from agent 1 to unknown agent id=2"));
+    }
+
+    /// Wakes up every `step` ns (a runtime param) and logs wakeups, pause / resume and admin results.
+    struct Ticker {
+        id: AgentId,
+        step: u64,
+        log: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Agent for Ticker {
+        fn id(&self) -> AgentId {
+            self.id
+        }
+
+        fn name(&self) -> &str {
+            "Ticker"
+        }
+
+        fn set_param(&mut self, key: &str, value: &serde_json::Value) -> bool {
+            match (key, value.as_u64()) {
+                ("step", Some(step)) => self.step = step,
+                _ => return false,
+            }
+            true
+        }
+
+        fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+            sim.wakeup(self.id, sim.now_ns() + self.step);
+        }
+
+        fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, now_ns: u64) {
+            self.log.borrow_mut().push(format!("wakeup {}", now_ns));
+            sim.wakeup(self.id, now_ns + self.step);
+        }
+
+        fn on_message(&mut self, _sim: &mut dyn SimulatorApi, msg: &Message) {
+            if let MessagePayload::AdminResult(result) = &msg.payload {
+                let line = format!("{} {}: {:?}", result.command, result.agent, result.error);
+                self.log.borrow_mut().push(line);
+            }
+        }
+
+        fn on_pause(&mut self, _sim: &mut dyn SimulatorApi) {
+            self.log.borrow_mut().push("pause".to_string());
+        }
+
+        fn on_resume(&mut self, _sim: &mut dyn SimulatorApi) {
+            self.log.borrow_mut().push("resume".to_string());
+        }
+    }
+
+    #[test]
+    fn test_admin_pause_holds_wakeups_until_resume() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(10, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        kernel.set_start_time(0);
+        kernel.add_agent(Box::new(Ticker {
+            id: 1,
+            step: 100,
+            log: log.clone(),
+        }));
+        let admin = |kernel: &mut Kernel, to: AgentId, command: AdminCommand| {
+            kernel.send(1, to, MessageType::Admin, MessagePayload::Admin(command));
+        };
+
+        kernel.run(2);
+        admin(&mut kernel, 1, AdminCommand::Pause);
+        kernel.run(3);
+        assert!(kernel.is_paused(1));
+        // The wakeup due at t=300 is held
+        assert_eq!(
+            *log.borrow(),
+            vec!["wakeup 100", "wakeup 200", "pause", "pause 1: None"]
+        );
+
+        log.borrow_mut().clear();
+        admin(
+            &mut kernel,
+            1,
+            AdminCommand::ConfigUpdate(serde_json::json!({"step": 50})),
+        );
+        admin(
+            &mut kernel,
+            1,
+            AdminCommand::ConfigUpdate(serde_json::json!({"speed": 2})),
+        );
+        admin(&mut kernel, 1, AdminCommand::Resume);
+        admin(&mut kernel, 7, AdminCommand::Resume);
+        kernel.run(2);
+        assert!(!kernel.is_paused(1));
+        let log = log.borrow();
+        let wakeups: Vec<&String> = log.iter().filter(|l| l.starts_with("wakeup")).collect();
+        // Held wakeup delivered on resume (t=600), then every 50 ns
+        assert_eq!(wakeups, vec!["wakeup 600", "wakeup 650", "wakeup 700"]);
+        assert!(log.iter().position(|l| l == "resume") < log.iter().position(|l| l == "wakeup 600"));
+        assert!(log.contains(&"config 1: None".to_string()));
+        assert!(log.contains(&"config 1: Some(\"unsupported params: speed\")".to_string()));
+        assert!(log.contains(&"resume 1: None".to_string()));
+        assert!(log.contains(&"resume 7: Some(\"unknown agent id=7\")".to_string()));
+    }
+
+    #[test]
+    fn test_director_set_param_reaches_agent_set_param() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(10, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        kernel.set_start_time(0);
+        kernel.add_agent(Box::new(Ticker {
+            id: 1,
+            step: 100,
+            log: log.clone(),
+        }));
+        let set_param = |kernel: &mut Kernel, key: &str, value: serde_json::Value| {
+            let command = DirectorCommand::SetParam {
+                key: key.to_string(),
+                value,
+            };
+            kernel.send(2, 1, MessageType::Director, MessagePayload::Director(command));
+        };
+
+        kernel.run(1);
+        set_param(&mut kernel, "step", serde_json::json!(50));
+        set_param(&mut kernel, "speed", serde_json::json!(2));
+        kernel.run(4);
+        // Both params arrive at t=110; the wakeup already due at t=200 keeps its time
+        assert_eq!(*log.borrow(), vec!["wakeup 100", "wakeup 200", "wakeup 250"]);
+    }
+
+    #[test]
+    fn test_warmup_holds_wakeups_of_non_exempt_agents() {
+        let oracle_log = Rc::new(RefCell::new(Vec::new()));
+        let trader_log = Rc::new(RefCell::new(Vec::new()));
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(10, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        kernel.set_start_time(0);
+        kernel.set_warmup(250, &[1]);
+        for (id, log) in [(1, &oracle_log), (2, &trader_log)] {
+            kernel.add_agent(Box::new(Ticker {
+                id,
+                step: 100,
+                log: log.clone(),
+            }));
+        }
+
+        kernel.run(2);
+        assert!(kernel.in_warmup());
+        assert_eq!(*oracle_log.borrow(), vec!["wakeup 100", "wakeup 200"]);
+        assert!(trader_log.borrow().is_empty());
+
+        // The wakeup held since t=100 is delivered when the warm-up ends
+        kernel.run(3);
+        assert!(!kernel.in_warmup());
+        assert_eq!(oracle_log.borrow().len(), 5);
+        assert_eq!(*trader_log.borrow(), vec!["wakeup 300", "wakeup 400", "wakeup 500"]);
+    }
+
+    #[test]
+    fn test_agents_join_and_leave_at_their_window() {
+        let oracle_log = Rc::new(RefCell::new(Vec::new()));
+        let trader_log = Rc::new(RefCell::new(Vec::new()));
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(10, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        kernel.set_start_time(0);
+        kernel.set_agent_window(2, 250, Some(450));
+        for (id, log) in [(1, &oracle_log), (2, &trader_log)] {
+            kernel.add_agent(Box::new(Ticker {
+                id,
+                step: 100,
+                log: log.clone(),
+            }));
+        }
+        assert_eq!(kernel.waiting_agents(), 1);
+        assert!(!kernel.agent_index.contains_key(&2));
+
+        // Joins on the first tick past 250, leaves on the first past 450 before its wakeup at 500
+        kernel.run(6);
+        assert_eq!(kernel.waiting_agents(), 0);
+        assert_eq!(*trader_log.borrow(), vec!["wakeup 400"]);
+        assert!(!kernel.agent_index.contains_key(&2));
+        assert_eq!(oracle_log.borrow().len(), 6);
+    }
+
+    #[test]
+    fn test_stop_request_ends_the_run_after_the_tick() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(10, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        kernel.set_start_time(0);
+        kernel.add_agent(Box::new(Ticker {
+            id: 1,
+            step: 100,
+            log: log.clone(),
+        }));
+        let stop = kernel.stop_handle();
+        kernel.run(2);
+        assert_eq!(log.borrow().len(), 2);
+
+        stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        kernel.run(10);
+        assert_eq!(log.borrow().len(), 3);
+    }
+
+    /// Blocks its first wakeup for `block_ms` of wall time.
+    struct Sleeper {
+        block_ms: u64,
+    }
+
+    impl Agent for Sleeper {
+        fn id(&self) -> AgentId {
+            5
+        }
+
+        fn name(&self) -> &str {
+            "Sleeper"
+        }
+
+        fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+            sim.wakeup(5, sim.now_ns() + 1);
+        }
+
+        fn on_wakeup(&mut self, _sim: &mut dyn SimulatorApi, _now_ns: u64) {
+            std::thread::sleep(Duration::from_millis(self.block_ms));
+        }
+    }
+
+    struct Overruns(std::sync::Arc<std::sync::Mutex<Vec<SimEvent>>>);
+
+    impl crate::events::EventListener for Overruns {
+        fn on_event(&mut self, event: &SimEvent) {
+            if matches!(event, SimEvent::TickOverrun { .. }) {
+                self.0.lock().unwrap().push(event.clone());
+            }
+        }
+    }
+
+    #[test]
+    fn test_watchdog_reports_tick_stuck_in_a_handler() {
+        let overruns = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut kernel = Kernel::new(Box::new(FixedLatency::new(10, 0)), 100);
+        kernel.set_verbosity(Verbosity::Quiet);
+        kernel.set_realtime(1);
+        kernel.set_watchdog(WatchdogConfig {
+            factor: 5,
+            abort: false,
+        });
+        kernel.event_bus_mut().subscribe(Box::new(Overruns(overruns.clone())));
+        kernel.add_agent(Box::new(Sleeper { block_ms: 30 }));
+        kernel.run(3);
+
+        let overruns = overruns.lock().unwrap();
+        assert_eq!(overruns.len(), 1);
+        let SimEvent::TickOverrun {
+            tick,
+            elapsed_ms,
+            limit_ms,
+            agent,
+            msg_type,
+            handler_ms,
+            ..
+        } = &overruns[0]
+        else {
+            unreachable!()
+        };
+        assert_eq!((*tick, *limit_ms, *agent), (1, 5, Some(5)));
+        assert_eq!(msg_type.as_deref(), Some("Wakeup"));
+        assert!(*handler_ms >= 30 && *elapsed_ms >= *handler_ms);
+    }
 }
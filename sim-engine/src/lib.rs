@@ -0,0 +1,46 @@
+// Perpetual DEX simulation engine.
+//
+// The `sim-engine` binary drives it through scenario JSON files; the same
+// kernel, agents, price providers and loggers can be used directly, with
+// `simulation::SimulationBuilder` assembling and running a simulation in code.
+
+pub mod activity_schedule;
+pub mod agents;
+pub mod api;
+pub mod bench;
+pub mod blotter;
+pub mod capital;
+pub mod cascades;
+pub mod circuit_breaker;
+pub mod compare;
+pub mod equity;
+pub mod events;
+pub mod exchange_state;
+pub mod flow_stats;
+pub mod indicators;
+pub mod kernel;
+pub mod latency;
+pub mod logging;
+pub mod mark_price;
+pub mod market_data;
+pub mod messages;
+pub mod metrics;
+pub mod oi_caps;
+pub mod order_ids;
+pub mod pending_orders;
+pub mod pnl;
+pub mod price_rules;
+pub mod qty;
+pub mod replay;
+pub mod report;
+pub mod risk_limits;
+pub mod scale;
+pub mod scenarios;
+pub mod sim_engine;
+pub mod simulation;
+pub mod spread_model;
+pub mod trigger_checker;
+pub mod tui;
+pub mod vara;
+pub mod watchdog;
+pub mod webhooks;
@@ -1,10 +1,55 @@
-// Simple CSV loggers on top of EventBus.
+// Simple CSV loggers on top of EventBus, and the console verbosity of agents.
 
+use std::cell::Cell;
 use std::fs::{create_dir_all, OpenOptions};
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use crate::events::{EventListener, SimEvent};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{EventKind, EventListener, SimEvent};
+use crate::flow_stats::FlowStatsBook;
+
+/// Console output level of an agent or the kernel. Errors (`eprintln!`) are always printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verbosity {
+    /// No output
+    Quiet,
+    /// Start / stop lines only
+    Info,
+    /// Everything, including a line per message or tick
+    #[default]
+    Debug,
+}
+
+thread_local! {
+    static AGENT_OUTPUT: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Enable or silence `agent_println!` on this thread; the kernel sets it around each
+/// agent callback from the agent's verbosity.
+pub fn set_agent_output(enabled: bool) {
+    AGENT_OUTPUT.with(|output| output.set(enabled));
+}
+
+pub fn agent_output() -> bool {
+    AGENT_OUTPUT.with(|output| output.get())
+}
+
+/// `println!` for agent code: skipped (arguments not even formatted) when the kernel
+/// silenced the agent handling the current callback.
+#[macro_export]
+macro_rules! agent_println {
+    ($($arg:tt)*) => {
+        if $crate::logging::agent_output() {
+            println!($($arg)*);
+        }
+    };
+}
 
 fn open_csv_with_header(dir: &Path, filename: &str, header: &str) -> std::io::Result<std::fs::File> {
     create_dir_all(dir)?;
@@ -18,15 +63,127 @@ fn open_csv_with_header(dir: &Path, filename: &str, header: &str) -> std::io::Re
     Ok(file)
 }
 
+/// Rotation policy for CSV logs. Zero limits disable the corresponding trigger.
+#[derive(Debug, Clone, Copy)]
+pub struct LogRotation {
+    /// Rotate when the current file grows beyond this size (bytes)
+    pub max_bytes: u64,
+    /// Rotate when the current file is older than this
+    pub max_age: Option<Duration>,
+    /// Number of compressed archives to keep (name.csv.1.gz .. name.csv.N.gz)
+    pub retention: usize,
+}
+
+impl Default for LogRotation {
+    fn default() -> Self {
+        Self {
+            max_bytes: 0,
+            max_age: None,
+            retention: 5,
+        }
+    }
+}
+
+impl LogRotation {
+    fn enabled(&self) -> bool {
+        self.max_bytes > 0 || self.max_age.is_some()
+    }
+}
+
+/// CSV file with header that rotates into gzip archives (orders.csv.1.gz, ...).
+/// Archive 1 is always the most recent one.
+pub struct RotatingCsv {
+    dir: PathBuf,
+    filename: String,
+    header: String,
+    rotation: LogRotation,
+    file: std::fs::File,
+    bytes_written: u64,
+    opened_at: Instant,
+}
+
+impl RotatingCsv {
+    pub fn new(dir: &Path, filename: &str, header: &str, rotation: LogRotation) -> std::io::Result<Self> {
+        let file = open_csv_with_header(dir, filename, header)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            filename: filename.to_string(),
+            header: header.to_string(),
+            rotation,
+            file,
+            bytes_written: header.len() as u64 + 1,
+            opened_at: Instant::now(),
+        })
+    }
+
+    fn archive_path(&self, n: usize) -> PathBuf {
+        self.dir.join(format!("{}.{}.gz", self.filename, n))
+    }
+
+    fn needs_rotation(&self) -> bool {
+        if !self.rotation.enabled() {
+            return false;
+        }
+        let by_size = self.rotation.max_bytes > 0 && self.bytes_written >= self.rotation.max_bytes;
+        let by_age = self
+            .rotation
+            .max_age
+            .map(|age| self.opened_at.elapsed() >= age)
+            .unwrap_or(false);
+        by_size || by_age
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+
+        // Shift archives: N-1 -> N, ..., 1 -> 2; the oldest one falls off.
+        let retention = self.rotation.retention;
+        if retention > 0 {
+            let _ = std::fs::remove_file(self.archive_path(retention));
+            for n in (1..retention).rev() {
+                let from = self.archive_path(n);
+                if from.exists() {
+                    std::fs::rename(&from, self.archive_path(n + 1))?;
+                }
+            }
+
+            let current = self.dir.join(&self.filename);
+            let mut input = std::fs::File::open(&current)?;
+            let output = std::fs::File::create(self.archive_path(1))?;
+            let mut encoder = GzEncoder::new(output, Compression::default());
+            std::io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?;
+        }
+
+        self.file = open_csv_with_header(&self.dir, &self.filename, &self.header)?;
+        self.bytes_written = self.header.len() as u64 + 1;
+        self.opened_at = Instant::now();
+        Ok(())
+    }
+
+    pub fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        if self.needs_rotation() {
+            self.rotate()?;
+        }
+        self.file.write_all(buf)?;
+        self.bytes_written += buf.len() as u64;
+        Ok(())
+    }
+}
+
 /// Order logger: logs/orders.csv
 pub struct CsvOrderLogger {
-    file: std::fs::File,
+    file: RotatingCsv,
 }
 
 impl CsvOrderLogger {
     pub fn new<P: AsRef<Path>>(dir: P) -> std::io::Result<Self> {
+        Self::with_rotation(dir, LogRotation::default())
+    }
+
+    pub fn with_rotation<P: AsRef<Path>>(dir: P, rotation: LogRotation) -> std::io::Result<Self> {
         let header = "ts,from,to,msg_type,symbol,side,price,qty";
-        let file = open_csv_with_header(dir.as_ref(), "orders.csv", header)?;
+        let file = RotatingCsv::new(dir.as_ref(), "orders.csv", header, rotation)?;
         Ok(Self { file })
     }
 }
@@ -66,17 +223,25 @@ impl EventListener for CsvOrderLogger {
             }
         }
     }
+
+    fn interests(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::OrderLog])
+    }
 }
 
 /// Oracle logger: logs/oracle.csv
 pub struct CsvOracleLogger {
-    file: std::fs::File,
+    file: RotatingCsv,
 }
 
 impl CsvOracleLogger {
     pub fn new<P: AsRef<Path>>(dir: P) -> std::io::Result<Self> {
+        Self::with_rotation(dir, LogRotation::default())
+    }
+
+    pub fn with_rotation<P: AsRef<Path>>(dir: P, rotation: LogRotation) -> std::io::Result<Self> {
         let header = "ts,symbol,price_min,price_max,price_mid";
-        let file = open_csv_with_header(dir.as_ref(), "oracle.csv", header)?;
+        let file = RotatingCsv::new(dir.as_ref(), "oracle.csv", header, rotation)?;
         Ok(Self { file })
     }
 }
@@ -97,17 +262,25 @@ impl EventListener for CsvOracleLogger {
             }
         }
     }
+
+    fn interests(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::OracleTick])
+    }
 }
 
 /// Execution logger: logs/executions.csv
 pub struct CsvExecutionLogger {
-    file: std::fs::File,
+    file: RotatingCsv,
 }
 
 impl CsvExecutionLogger {
     pub fn new<P: AsRef<Path>>(dir: P) -> std::io::Result<Self> {
-        let header = "ts,account,symbol,side,size_usd,collateral,execution_price,leverage,order_type,pnl";
-        let file = open_csv_with_header(dir.as_ref(), "executions.csv", header)?;
+        Self::with_rotation(dir, LogRotation::default())
+    }
+
+    pub fn with_rotation<P: AsRef<Path>>(dir: P, rotation: LogRotation) -> std::io::Result<Self> {
+        let header = "ts,account,symbol,side,size_usd,collateral,execution_price,leverage,order_type,pnl,fee_usd,funding_fee_usd,decision_price";
+        let file = RotatingCsv::new(dir.as_ref(), "executions.csv", header, rotation)?;
         Ok(Self { file })
     }
 }
@@ -125,11 +298,15 @@ impl EventListener for CsvExecutionLogger {
             leverage,
             order_type,
             pnl,
+            fee_usd,
+            funding_fee_usd,
+            decision_price,
         } = event
         {
             let side_str = format!("{:?}", side);
+            let decision_price = decision_price.map(|p| p.to_string()).unwrap_or_default();
             let line = format!(
-                "{ts},{account},{symbol},{side},{size_usd},{collateral},{execution_price},{leverage},{order_type},{pnl}\n",
+                "{ts},{account},{symbol},{side},{size_usd},{collateral},{execution_price},{leverage},{order_type},{pnl},{fee_usd},{funding_fee_usd},{decision_price}\n",
                 ts = ts,
                 account = account,
                 symbol = symbol,
@@ -140,6 +317,9 @@ impl EventListener for CsvExecutionLogger {
                 leverage = leverage,
                 order_type = order_type,
                 pnl = pnl,
+                fee_usd = fee_usd,
+                funding_fee_usd = funding_fee_usd,
+                decision_price = decision_price,
             );
 
             if let Err(e) = self.file.write_all(line.as_bytes()) {
@@ -147,17 +327,25 @@ impl EventListener for CsvExecutionLogger {
             }
         }
     }
+
+    fn interests(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::OrderExecuted])
+    }
 }
 
 /// Position snapshot logger: logs/positions.csv
 pub struct CsvPositionLogger {
-    file: std::fs::File,
+    file: RotatingCsv,
 }
 
 impl CsvPositionLogger {
     pub fn new<P: AsRef<Path>>(dir: P) -> std::io::Result<Self> {
+        Self::with_rotation(dir, LogRotation::default())
+    }
+
+    pub fn with_rotation<P: AsRef<Path>>(dir: P, rotation: LogRotation) -> std::io::Result<Self> {
         let header = "ts,account,symbol,side,size_usd,size_tokens,collateral,entry_price,current_price,pnl,liquidation_price,leverage,is_liquidatable,opened_at";
-        let file = open_csv_with_header(dir.as_ref(), "positions.csv", header)?;
+        let file = RotatingCsv::new(dir.as_ref(), "positions.csv", header, rotation)?;
         Ok(Self { file })
     }
 }
@@ -205,18 +393,34 @@ impl EventListener for CsvPositionLogger {
             }
         }
     }
+
+    fn interests(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::PositionSnapshot])
+    }
 }
 
 /// Market state logger: logs/markets.csv
 pub struct CsvMarketLogger {
-    file: std::fs::File,
+    file: RotatingCsv,
+    /// Taker flow columns (0 without a book)
+    flow_stats: Option<FlowStatsBook>,
 }
 
 impl CsvMarketLogger {
     pub fn new<P: AsRef<Path>>(dir: P) -> std::io::Result<Self> {
-        let header = "ts,symbol,oi_long_usd,oi_short_usd,liquidity_usd,funding_rate_bps_hour_fp,borrowing_rate_bps_hour_fp";
-        let file = open_csv_with_header(dir.as_ref(), "markets.csv", header)?;
-        Ok(Self { file })
+        Self::with_rotation(dir, LogRotation::default())
+    }
+
+    pub fn with_rotation<P: AsRef<Path>>(dir: P, rotation: LogRotation) -> std::io::Result<Self> {
+        let header = "ts,symbol,oi_long_usd,oi_short_usd,liquidity_usd,funding_rate_bps_hour_fp,borrowing_rate_bps_hour_fp,index_price,mark_price,utilization_bps,buy_volume_usd,sell_volume_usd,imbalance_bps,trades,avg_trade_usd";
+        let file = RotatingCsv::new(dir.as_ref(), "markets.csv", header, rotation)?;
+        Ok(Self { file, flow_stats: None })
+    }
+
+    /// Fill the flow columns from `book` (subscribe its tracker before this logger).
+    pub fn with_flow_stats(mut self, book: FlowStatsBook) -> Self {
+        self.flow_stats = Some(book);
+        self
     }
 }
 
@@ -225,16 +429,38 @@ impl EventListener for CsvMarketLogger {
         if let SimEvent::MarketSnapshot {
             ts,
             symbol,
+            index_price,
+            mark_price,
             oi_long_usd,
             oi_short_usd,
             liquidity_usd,
+            utilization_bps,
             funding_rate_bps_hour_fp,
             borrowing_rate_bps_hour_fp,
         } = event
         {
+            let flow = self
+                .flow_stats
+                .as_ref()
+                .map(|book| book.stats(symbol, *ts))
+                .unwrap_or_default();
             let line = format!(
-                "{},{},{},{},{},{},{}\n",
-                ts, symbol, oi_long_usd, oi_short_usd, liquidity_usd, funding_rate_bps_hour_fp, borrowing_rate_bps_hour_fp,
+                "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                ts,
+                symbol,
+                oi_long_usd,
+                oi_short_usd,
+                liquidity_usd,
+                funding_rate_bps_hour_fp,
+                borrowing_rate_bps_hour_fp,
+                index_price,
+                mark_price,
+                utilization_bps,
+                flow.buy_volume_usd,
+                flow.sell_volume_usd,
+                flow.imbalance_bps(),
+                flow.trades,
+                flow.avg_trade_usd(),
             );
 
             if let Err(e) = self.file.write_all(line.as_bytes()) {
@@ -242,17 +468,25 @@ impl EventListener for CsvMarketLogger {
             }
         }
     }
+
+    fn interests(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::MarketSnapshot])
+    }
 }
 
 /// Liquidation logger: logs/liquidations.csv
 pub struct CsvLiquidationLogger {
-    file: std::fs::File,
+    file: RotatingCsv,
 }
 
 impl CsvLiquidationLogger {
     pub fn new<P: AsRef<Path>>(dir: P) -> std::io::Result<Self> {
+        Self::with_rotation(dir, LogRotation::default())
+    }
+
+    pub fn with_rotation<P: AsRef<Path>>(dir: P, rotation: LogRotation) -> std::io::Result<Self> {
         let header = "ts,account,symbol,side,size_usd,collateral_lost,pnl,liquidation_price";
-        let file = open_csv_with_header(dir.as_ref(), "liquidations.csv", header)?;
+        let file = RotatingCsv::new(dir.as_ref(), "liquidations.csv", header, rotation)?;
         Ok(Self { file })
     }
 }
@@ -288,4 +522,278 @@ impl EventListener for CsvLiquidationLogger {
             }
         }
     }
+
+    fn interests(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::PositionLiquidated])
+    }
+}
+
+/// On-chain transaction logger: logs/vara_txs.csv
+pub struct CsvChainTxLogger {
+    file: RotatingCsv,
+}
+
+impl CsvChainTxLogger {
+    pub fn new<P: AsRef<Path>>(dir: P) -> std::io::Result<Self> {
+        Self::with_rotation(dir, LogRotation::default())
+    }
+
+    pub fn with_rotation<P: AsRef<Path>>(dir: P, rotation: LogRotation) -> std::io::Result<Self> {
+        let header = "ts,agent,tx_type,success,order_id,latency_ms,tip,fee,reverted,error";
+        let file = RotatingCsv::new(dir.as_ref(), "vara_txs.csv", header, rotation)?;
+        Ok(Self { file })
+    }
+}
+
+impl EventListener for CsvChainTxLogger {
+    fn on_event(&mut self, event: &SimEvent) {
+        if let SimEvent::ChainTx {
+            ts,
+            agent,
+            tx_type,
+            success,
+            order_id,
+            latency_ms,
+            error,
+            tip,
+            fee,
+            reverted,
+        } = event
+        {
+            let order_id = order_id.map(|id| id.to_string()).unwrap_or_default();
+            let fee = fee.map(|fee| fee.to_string()).unwrap_or_default();
+            // Contract errors are free text: keep the CSV columns intact
+            let error = error.as_deref().unwrap_or("").replace('"', "'");
+            let line = format!(
+                "{},{},{},{},{},{},{},{},{},\"{}\"\n",
+                ts, agent, tx_type, success, order_id, latency_ms, tip, fee, reverted, error
+            );
+
+            if let Err(e) = self.file.write_all(line.as_bytes()) {
+                eprintln!("[CsvChainTxLogger] write error: {e}");
+            }
+        }
+    }
+
+    fn interests(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::ChainTx])
+    }
+}
+
+/// Spot venue logger: logs/swaps.csv
+pub struct CsvSwapLogger {
+    file: RotatingCsv,
+}
+
+impl CsvSwapLogger {
+    pub fn new<P: AsRef<Path>>(dir: P) -> std::io::Result<Self> {
+        Self::with_rotation(dir, LogRotation::default())
+    }
+
+    pub fn with_rotation<P: AsRef<Path>>(dir: P, rotation: LogRotation) -> std::io::Result<Self> {
+        let header = "ts,account,symbol,side,qty,price,fee_usd,spot_price,oracle_price,basis_bps";
+        let file = RotatingCsv::new(dir.as_ref(), "swaps.csv", header, rotation)?;
+        Ok(Self { file })
+    }
+}
+
+impl EventListener for CsvSwapLogger {
+    fn on_event(&mut self, event: &SimEvent) {
+        if let SimEvent::SpotSwap {
+            ts,
+            account,
+            symbol,
+            side,
+            qty,
+            price,
+            fee_usd,
+            spot_price,
+            oracle_price,
+        } = event
+        {
+            let basis_bps = if *oracle_price > 0 {
+                (*spot_price as f64 / *oracle_price as f64 - 1.0) * 10_000.0
+            } else {
+                0.0
+            };
+            let line = format!(
+                "{},{},{},{:?},{},{},{},{},{},{:.2}\n",
+                ts, account, symbol, side, qty, price, fee_usd, spot_price, oracle_price, basis_bps
+            );
+
+            if let Err(e) = self.file.write_all(line.as_bytes()) {
+                eprintln!("[CsvSwapLogger] write error: {e}");
+            }
+        }
+    }
+
+    fn interests(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::SpotSwap])
+    }
+}
+
+/// Inbox logger: logs/inbox.csv, backlog of rate-limited agents (InboxBacklog).
+pub struct CsvInboxLogger {
+    file: RotatingCsv,
+}
+
+impl CsvInboxLogger {
+    pub fn new<P: AsRef<Path>>(dir: P) -> std::io::Result<Self> {
+        Self::with_rotation(dir, LogRotation::default())
+    }
+
+    pub fn with_rotation<P: AsRef<Path>>(dir: P, rotation: LogRotation) -> std::io::Result<Self> {
+        let file = RotatingCsv::new(dir.as_ref(), "inbox.csv", "ts,agent,backlog,delayed", rotation)?;
+        Ok(Self { file })
+    }
+}
+
+impl EventListener for CsvInboxLogger {
+    fn on_event(&mut self, event: &SimEvent) {
+        if let SimEvent::InboxBacklog {
+            ts,
+            agent,
+            backlog,
+            delayed,
+        } = event
+        {
+            let line = format!("{},{},{},{}\n", ts, agent, backlog, delayed);
+            if let Err(e) = self.file.write_all(line.as_bytes()) {
+                eprintln!("[CsvInboxLogger] write error: {e}");
+            }
+        }
+    }
+
+    fn interests(&self) -> Option<&'static [EventKind]> {
+        Some(&[EventKind::InboxBacklog])
+    }
+}
+
+/// Risk logger: logs/risk.csv (RiskReport), logs/risk_heatmap.csv (liquidation
+/// clusters per report) and logs/risk_alerts.csv (RiskAlert, RiskLimitBreached
+/// as kind "limit:<name>").
+pub struct CsvRiskLogger {
+    reports: RotatingCsv,
+    heatmap: RotatingCsv,
+    alerts: RotatingCsv,
+}
+
+impl CsvRiskLogger {
+    pub fn new<P: AsRef<Path>>(dir: P) -> std::io::Result<Self> {
+        Self::with_rotation(dir, LogRotation::default())
+    }
+
+    pub fn with_rotation<P: AsRef<Path>>(dir: P, rotation: LogRotation) -> std::io::Result<Self> {
+        let dir = dir.as_ref();
+        let header = "ts,symbol,price,oi_long_usd,oi_short_usd,liquidity_usd,volatility_pct,var_usd,top_account,top_account_pct,at_risk_usd";
+        let reports = RotatingCsv::new(dir, "risk.csv", header, rotation)?;
+        let heatmap_header = "ts,symbol,bucket_price,long_usd,short_usd";
+        let heatmap = RotatingCsv::new(dir, "risk_heatmap.csv", heatmap_header, rotation)?;
+        let alerts_header = "ts,kind,symbol,account,value,message";
+        let alerts = RotatingCsv::new(dir, "risk_alerts.csv", alerts_header, rotation)?;
+        Ok(Self {
+            reports,
+            heatmap,
+            alerts,
+        })
+    }
+
+    fn write_report(&mut self, event: &SimEvent) -> std::io::Result<()> {
+        let SimEvent::RiskReport {
+            ts,
+            symbol,
+            price,
+            oi_long_usd,
+            oi_short_usd,
+            liquidity_usd,
+            volatility_pct,
+            var_usd,
+            top_account,
+            top_account_pct,
+            at_risk_usd,
+            liq_heatmap,
+        } = event
+        else {
+            return Ok(());
+        };
+        let top = top_account.map(|a| a.to_string()).unwrap_or_default();
+        let line = format!(
+            "{},{},{},{},{},{},{:.4},{},{},{:.2},{}\n",
+            ts,
+            symbol,
+            price,
+            oi_long_usd,
+            oi_short_usd,
+            liquidity_usd,
+            volatility_pct,
+            var_usd,
+            top,
+            top_account_pct,
+            at_risk_usd,
+        );
+        self.reports.write_all(line.as_bytes())?;
+        for bucket in liq_heatmap {
+            let line = format!(
+                "{},{},{},{},{}\n",
+                ts, symbol, bucket.price, bucket.long_usd, bucket.short_usd
+            );
+            self.heatmap.write_all(line.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl EventListener for CsvRiskLogger {
+    fn on_event(&mut self, event: &SimEvent) {
+        let result = match event {
+            SimEvent::RiskReport { .. } => self.write_report(event),
+            SimEvent::RiskAlert {
+                ts,
+                kind,
+                symbol,
+                account,
+                value,
+                message,
+            } => {
+                let account = account.map(|a| a.to_string()).unwrap_or_default();
+                // Messages are free text: keep the CSV columns intact
+                let line = format!(
+                    "{},{},{},{},{:.4},\"{}\"\n",
+                    ts,
+                    kind,
+                    symbol,
+                    account,
+                    value,
+                    message.replace('"', "'")
+                );
+                self.alerts.write_all(line.as_bytes())
+            }
+            SimEvent::RiskLimitBreached {
+                ts,
+                account,
+                symbol,
+                limit,
+                value,
+                threshold,
+            } => {
+                let line = format!(
+                    "{},limit:{},{},{},{:.4},\"order rejected: {} {} over {}\"\n",
+                    ts, limit, symbol, account, value, limit, value, threshold
+                );
+                self.alerts.write_all(line.as_bytes())
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            eprintln!("[CsvRiskLogger] write error: {e}");
+        }
+    }
+
+    fn interests(&self) -> Option<&'static [EventKind]> {
+        Some(&[
+            EventKind::RiskReport,
+            EventKind::RiskAlert,
+            EventKind::RiskLimitBreached,
+        ])
+    }
 }
@@ -1,20 +1,10 @@
-pub mod agents;
-pub mod api;
-mod events;
-mod kernel;
-mod latency;
-mod logging;
-mod messages;
-mod pending_orders;
-pub mod scenarios;
-mod sim_engine;
-mod trigger_checker;
-pub mod vara;
-
-use clap::Parser;
-use std::sync::Arc;
-
-use vara::{VaraClient, VaraConfig};
+use clap::{Parser, Subcommand};
+use sim_engine::{compare, scenarios};
+use std::path::PathBuf;
+
+// Counts heap allocations for the `bench` report
+#[global_allocator]
+static GLOBAL: sim_engine::bench::CountingAllocator = sim_engine::bench::CountingAllocator;
 
 #[derive(Parser, Debug)]
 #[command(name = "sim-engine")]
@@ -39,11 +29,276 @@ struct Args {
     /// Skip initial deposits (use when balances already exist on-chain)
     #[arg(long, default_value = "false")]
     skip_deposits: bool,
+
+    /// Show live terminal dashboard (realtime mode only; stdout goes to logs/stdout.log)
+    #[arg(long, default_value = "false")]
+    tui: bool,
+
+    /// Override a scenario field, e.g. --set duration_sec=300 --set smart_traders[0].leverage=20
+    #[arg(long = "set", value_name = "PATH=VALUE", global = true)]
+    set: Vec<String>,
+
+    /// Directory with scenario JSON files (default: $SIM_SCENARIOS_DIR, then src/scenarios)
+    #[arg(long, value_name = "DIR", global = true)]
+    scenarios_dir: Option<PathBuf>,
+
+    /// Monte Carlo: run the scenario N times with seeds seed..seed+N-1 and aggregate the outcomes
+    #[arg(long, value_name = "N")]
+    batch: Option<u32>,
+
+    /// Parallel batch runs (default: number of CPU cores)
+    #[arg(long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Batch parameter sweep, e.g. --grid smart_traders[0].leverage=5,10,20 (cartesian product)
+    #[arg(long, value_name = "PATH=V1,V2,...")]
+    grid: Vec<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Headless throughput benchmark: synthetic agents exchanging messages, no chain or logs
+    Bench {
+        /// Number of agents
+        #[arg(long, default_value = "100")]
+        agents: u32,
+        /// Messages sent per tick by all agents together
+        #[arg(long, default_value = "1000")]
+        messages: u32,
+        /// Ticks to run
+        #[arg(long, default_value = "1000")]
+        ticks: u32,
+        /// OracleTick events emitted per tick by all agents together
+        #[arg(long, default_value = "0")]
+        events: u32,
+        /// Execution-only listeners on the event bus
+        #[arg(long, default_value = "0")]
+        listeners: u32,
+        /// Deliver every event to every listener instead of routing by kind
+        #[arg(long)]
+        broadcast: bool,
+    },
+    /// Compare key metrics of two or more runs (summary.json files or directories of repetitions)
+    Compare {
+        /// First path is the baseline; a directory counts every summary.json inside as one repetition
+        #[arg(required = true, num_args = 2..)]
+        paths: Vec<PathBuf>,
+    },
+    /// Run seeded scenarios and compare executions, final OI and agent PnL with their golden files
+    Golden {
+        /// Scenarios to run (default: every scenario with a golden file)
+        scenarios: Vec<String>,
+        /// Write the outputs as the new golden files
+        #[arg(long)]
+        update: bool,
+        /// Golden file directory (default: <scenarios dir>/golden)
+        #[arg(long, value_name = "DIR")]
+        golden_dir: Option<PathBuf>,
+        /// Absolute tolerance of money values in USD
+        #[arg(long, default_value = "1.0")]
+        abs_usd: f64,
+        /// Relative tolerance of money values
+        #[arg(long, default_value = "0.001")]
+        rel: f64,
+    },
+    /// List available scenarios with duration and agent counts
+    #[command(alias = "list-scenarios")]
+    List,
+    /// Show the resolved configuration of a scenario (after extends / --set)
+    Describe {
+        /// Scenario name (or path to a .json file)
+        scenario: String,
+    },
+    /// Validate scenarios without running them (all scenarios if none given)
+    Validate { scenarios: Vec<String> },
+    /// Deposit, open, query, close and withdraw against the scenario's contract, checking
+    /// balances and the position after each step (report: <logs_dir>/vara_smoke.json)
+    VaraSmoke {
+        /// Scenario with the contract and market to test
+        #[arg(default_value = "vara_smoke")]
+        scenario: String,
+        /// Account used for the round trip
+        #[arg(long, default_value = "20")]
+        agent: u32,
+        /// Deposited and finally withdrawn (USD)
+        #[arg(long, default_value = "1000")]
+        deposit_usd: f64,
+        /// Notional of the test position (USD)
+        #[arg(long, default_value = "100")]
+        size_usd: f64,
+        #[arg(long, default_value = "2")]
+        leverage: u32,
+        /// Fixed index price (USD) instead of a Pyth quote
+        #[arg(long)]
+        price_usd: Option<f64>,
+        /// Seconds to wait for each order transaction
+        #[arg(long, default_value = "120")]
+        timeout_sec: u64,
+        /// Report path
+        #[arg(long, value_name = "FILE")]
+        report: Option<PathBuf>,
+    },
+    /// Upload the contract WASM and run its constructor with the parameters of a deploy
+    /// config, signed by an account of the scenario, e.g. `-s vara_smoke deploy dev_contract.json`
+    Deploy {
+        /// Deploy config: WASM path and constructor parameters
+        config: PathBuf,
+        /// Account that pays for the upload and seeds the liquidity
+        #[arg(long, default_value = "20")]
+        agent: u32,
+        /// Set VARA_CONTRACT_ADDRESS in this env file
+        #[arg(long, value_name = "FILE")]
+        env_file: Option<PathBuf>,
+        /// Set vara.contract in the scenario file
+        #[arg(long)]
+        write_scenario: bool,
+    },
+    /// Re-drive a recorded run (logging.trace) with only the --live agents running, e.g.
+    /// `-s balanced_market replay logs/trace.jsonl --live 42`
+    Replay {
+        /// trace.jsonl of the recorded run
+        trace: PathBuf,
+        /// Agents built from the current code; everyone else replays from the trace
+        #[arg(long, value_delimiter = ',', required = true)]
+        live: Vec<u32>,
+    },
 }
 
 fn main() {
     let args = Args::parse();
 
+    let catalog = scenarios::catalog::Catalog::new(args.scenarios_dir.clone());
+
+    // Subcommands: offline except vara-smoke and deploy, which connect to Vara
+    if let Some(command) = &args.command {
+        let (label, result) = match command {
+            Command::Bench {
+                agents,
+                messages,
+                ticks,
+                events,
+                listeners,
+                broadcast,
+            } => {
+                let opts = sim_engine::bench::BenchOptions {
+                    agents: *agents,
+                    messages_per_tick: *messages,
+                    ticks: *ticks,
+                    events_per_tick: *events,
+                    listeners: *listeners,
+                    broadcast: *broadcast,
+                };
+                ("Bench", sim_engine::bench::run(&opts))
+            }
+            Command::Compare { paths } => ("Compare", compare::run(paths)),
+            Command::Golden {
+                scenarios,
+                update,
+                golden_dir,
+                abs_usd,
+                rel,
+            } => {
+                let opts = scenarios::golden::GoldenOptions {
+                    dir: golden_dir.clone(),
+                    update: *update,
+                    tolerance: scenarios::golden::Tolerance {
+                        abs_usd: *abs_usd,
+                        rel: *rel,
+                    },
+                };
+                ("Golden", scenarios::golden::run(&catalog, scenarios, &args.set, &opts))
+            }
+            Command::List => ("Scenarios", scenarios::catalog::list(&catalog)),
+            Command::Describe { scenario } => {
+                ("Scenarios", scenarios::catalog::describe(&catalog, scenario, &args.set))
+            }
+            Command::Validate { scenarios } => {
+                ("Validate", scenarios::catalog::validate(&catalog, scenarios, &args.set))
+            }
+            Command::VaraSmoke {
+                scenario,
+                agent,
+                deposit_usd,
+                size_usd,
+                leverage,
+                price_usd,
+                timeout_sec,
+                report,
+            } => {
+                let opts = scenarios::vara_smoke::SmokeOptions {
+                    agent_id: *agent,
+                    deposit_usd: *deposit_usd,
+                    size_usd: *size_usd,
+                    leverage: *leverage,
+                    price_usd: *price_usd,
+                    timeout_sec: *timeout_sec,
+                    report: report.clone(),
+                };
+                let result = scenarios::vara_smoke::run(&catalog, scenario, &args.set, &opts).and_then(|report| {
+                    if report.passed {
+                        Ok(())
+                    } else {
+                        Err(format!("failed: {}", report.failures().join("; ")))
+                    }
+                });
+                ("Smoke", result)
+            }
+            Command::Deploy {
+                config,
+                agent,
+                env_file,
+                write_scenario,
+            } => {
+                let opts = scenarios::deploy::DeployOptions {
+                    config: config.clone(),
+                    agent_id: *agent,
+                    env_file: env_file.clone(),
+                    write_scenario: *write_scenario,
+                };
+                let result = scenarios::deploy::run(&catalog, &args.scenario, &args.set, &opts).map(|_| ());
+                ("Deploy", result)
+            }
+            Command::Replay { trace, live } => {
+                let config = scenarios::simple_demo::load_config(&catalog, &args.scenario, &args.set);
+                ("Replay", scenarios::simple_demo::run_replay(config, trace, live))
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("[{}] {}", label, e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(runs) = args.batch {
+        if args.realtime {
+            eprintln!("[Main] --batch runs in fast-forward mode only");
+            std::process::exit(1);
+        }
+        let opts = scenarios::batch::BatchOptions {
+            runs,
+            jobs: args
+                .jobs
+                .unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)),
+            grid: args.grid.clone(),
+            skip_deposits: args.skip_deposits,
+        };
+        if let Err(e) = scenarios::batch::run_batch(&catalog, &args.scenario, &args.set, &opts) {
+            eprintln!("[Batch] {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if !args.grid.is_empty() {
+        eprintln!("[Main] --grid is only used with --batch, ignoring");
+    }
+
+    // Load the scenario before connecting, so config errors fail fast
+    let config = scenarios::simple_demo::load_config(&catalog, &args.scenario, &args.set);
+
     println!("=== PerpDEX on Vara Network ===");
     println!("[Main] Scenario: {}", args.scenario);
     if args.realtime {
@@ -51,62 +306,21 @@ fn main() {
         println!("[Main] API port: {}", args.port);
     } else {
         println!("[Main] Mode: Fast-forward");
-    }
-
-    // Initialize Vara connection (required)
-    let vara_client = match init_vara_client() {
-        Ok(client) => {
-            println!("[Vara] Ready for blockchain operations");
-            Arc::new(client)
-        }
-        Err(e) => {
-            eprintln!("[Vara] FATAL: Failed to initialize: {}", e);
-            eprintln!();
-            eprintln!("Required environment variables:");
-            eprintln!("  VARA_CONTRACT_ADDRESS - deployed VaraPerps contract address");
-            eprintln!("  VARA_WS_ENDPOINT      - WebSocket RPC (default: wss://testnet.vara.network)");
-            eprintln!("  VARA_KEYSTORE_PATH    - path to gring keystore (default: keys/Library/...)");
-            eprintln!("  VARA_PASSPHRASE_PATH  - path to passphrase file (default: keys/.passphrase)");
-            eprintln!("  VARA_PASSPHRASE_FILE  - legacy name for passphrase path");
-            std::process::exit(1);
+        if args.tui {
+            eprintln!("[Main] --tui is only available in realtime mode, ignoring");
         }
-    };
-
-    println!();
+    }
 
+    // The scenario runner connects to Vara (scenario `vara` block, VARA_* env as fallback)
     if args.realtime {
         scenarios::simple_demo::run_realtime_with_blockchain(
-            &args.scenario,
+            config,
             args.tick_ms,
             args.port,
             args.skip_deposits,
-            vara_client,
+            args.tui,
         );
     } else {
-        scenarios::simple_demo::run_scenario_with_blockchain(
-            &args.scenario,
-            args.skip_deposits,
-            vara_client,
-        );
+        scenarios::simple_demo::run_scenario_with_blockchain(config, args.skip_deposits);
     }
 }
-
-/// Initialize VaraClient for blockchain operations
-fn init_vara_client() -> Result<VaraClient, vara::VaraError> {
-    let config = VaraConfig::from_env()?;
-
-    println!("[Vara] Endpoint: {}", config.ws_endpoint);
-    println!("[Vara] Contract: {}", config.contract_address);
-    println!("[Vara] Keystore: {}", config.keystore_path);
-
-    let mut client = VaraClient::new(config)?;
-    client.connect()?;
-
-    // Preload bot keypairs
-    match client.preload_keypairs(200) {
-        Ok(count) => println!("[Vara] Loaded {} bot keypairs", count),
-        Err(e) => eprintln!("[Vara] Warning: Could not preload keypairs: {}", e),
-    }
-
-    Ok(client)
-}
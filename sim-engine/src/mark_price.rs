@@ -0,0 +1,205 @@
+// Mark price of a market, separate from the oracle's index price.
+//
+// Fills happen at the index (oracle mid) plus or minus a fixed fill spread,
+// but liquidation checks and unrealized PnL use the mark: the index plus a
+// funding basis (the current funding rate carried over `funding_horizon_hours`,
+// positive when longs pay) and an EMA of the impact premium, which grows with
+// the OI imbalance relative to pool liquidity. A wick of the index alone moves
+// the mark only as far as the index itself; a crowded side moves it further.
+// The total premium is capped at `max_premium_bps` either way.
+
+use serde::{Deserialize, Serialize};
+
+/// Mark price and fill spread parameters of one market.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarkPriceConfig {
+    /// Hours of funding priced into the mark
+    #[serde(default = "default_funding_horizon_hours")]
+    pub funding_horizon_hours: f64,
+    /// Impact premium when the whole pool backs one side ((long - short) OI = liquidity)
+    #[serde(default)]
+    pub impact_bps: f64,
+    /// Oracle ticks of the impact premium EMA
+    #[serde(default = "default_ema_ticks")]
+    pub ema_ticks: u32,
+    /// Upper bound of |mark - index| in bps of the index
+    #[serde(default = "default_max_premium_bps")]
+    pub max_premium_bps: f64,
+    /// Half-spread around the index that local fills pay, in bps
+    #[serde(default)]
+    pub fill_spread_bps: f64,
+}
+
+fn default_funding_horizon_hours() -> f64 {
+    1.0
+}
+
+fn default_ema_ticks() -> u32 {
+    10
+}
+
+fn default_max_premium_bps() -> f64 {
+    100.0
+}
+
+impl Default for MarkPriceConfig {
+    fn default() -> Self {
+        Self {
+            funding_horizon_hours: default_funding_horizon_hours(),
+            impact_bps: 0.0,
+            ema_ticks: default_ema_ticks(),
+            max_premium_bps: default_max_premium_bps(),
+            fill_spread_bps: 0.0,
+        }
+    }
+}
+
+impl MarkPriceConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let rates = [
+            self.funding_horizon_hours,
+            self.impact_bps,
+            self.max_premium_bps,
+            self.fill_spread_bps,
+        ];
+        if rates.iter().any(|v| !v.is_finite() || *v < 0.0) {
+            return Err("mark_price parameters must be non-negative numbers".to_string());
+        }
+        if self.ema_ticks == 0 {
+            return Err("mark_price.ema_ticks must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Fill price of a taker: index plus the spread when buying, minus when selling.
+    pub fn fill_price(&self, index: u64, buying: bool) -> u64 {
+        let width = (index as f64 * self.fill_spread_bps / 10_000.0).round() as u64;
+        if buying {
+            index.saturating_add(width)
+        } else {
+            index.saturating_sub(width)
+        }
+    }
+}
+
+/// Mark price state of one market, updated on every oracle tick.
+#[derive(Debug, Clone)]
+pub struct MarkPrice {
+    config: MarkPriceConfig,
+    /// EMA of the impact premium (fraction of the index); None before the first tick
+    impact_ema: Option<f64>,
+    last: Option<u64>,
+}
+
+impl MarkPrice {
+    pub fn new(config: MarkPriceConfig) -> Self {
+        Self {
+            config,
+            impact_ema: None,
+            last: None,
+        }
+    }
+
+    pub fn config(&self) -> &MarkPriceConfig {
+        &self.config
+    }
+
+    /// Price the mark from a new index and the current market state, and return it.
+    pub fn update(
+        &mut self,
+        index: u64,
+        funding_rate_bps_hour_fp: i64,
+        oi_long_usd: u64,
+        oi_short_usd: u64,
+        liquidity_usd: u64,
+    ) -> u64 {
+        let imbalance = if liquidity_usd > 0 {
+            ((oi_long_usd as f64 - oi_short_usd as f64) / liquidity_usd as f64).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        };
+        let impact = self.config.impact_bps / 10_000.0 * imbalance;
+        let alpha = 2.0 / (self.config.ema_ticks as f64 + 1.0);
+        let ema = match self.impact_ema {
+            Some(prev) => prev + alpha * (impact - prev),
+            None => impact,
+        };
+        self.impact_ema = Some(ema);
+
+        let funding_basis = funding_rate_bps_hour_fp as f64 / 1e6 / 10_000.0 * self.config.funding_horizon_hours;
+        let cap = self.config.max_premium_bps / 10_000.0;
+        let premium = (funding_basis + ema).clamp(-cap, cap);
+        let mark = (index as f64 * (1.0 + premium)).round().max(0.0) as u64;
+        self.last = Some(mark);
+        mark
+    }
+
+    /// Last computed mark; None before the first tick
+    pub fn value(&self) -> Option<u64> {
+        self.last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INDEX: u64 = 3_000_000_000;
+
+    #[test]
+    fn test_balanced_market_marks_at_index() {
+        let mut mark = MarkPrice::new(MarkPriceConfig {
+            impact_bps: 50.0,
+            ..MarkPriceConfig::default()
+        });
+        assert_eq!(mark.value(), None);
+        assert_eq!(mark.update(INDEX, 0, 1_000, 1_000, 10_000), INDEX);
+        assert_eq!(mark.value(), Some(INDEX));
+    }
+
+    #[test]
+    fn test_funding_and_impact_move_the_mark() {
+        let config = MarkPriceConfig {
+            funding_horizon_hours: 8.0,
+            impact_bps: 100.0,
+            ema_ticks: 1,
+            ..MarkPriceConfig::default()
+        };
+        // 1 bp/hour for 8 hours
+        let mut funding = MarkPrice::new(config);
+        assert_eq!(funding.update(INDEX, 1_000_000, 0, 0, 0), 3_002_400_000);
+        // Longs pay negative funding: shorts crowded, mark below index
+        assert_eq!(funding.update(INDEX, -1_000_000, 0, 0, 0), 2_997_600_000);
+
+        // Half the pool backs longs: 50 bps impact
+        let mut impact = MarkPrice::new(config);
+        assert_eq!(impact.update(INDEX, 0, 6_000, 1_000, 10_000), 3_015_000_000);
+    }
+
+    #[test]
+    fn test_impact_is_smoothed_and_capped() {
+        let mut mark = MarkPrice::new(MarkPriceConfig {
+            impact_bps: 400.0,
+            ema_ticks: 3,
+            ..MarkPriceConfig::default()
+        });
+        assert_eq!(mark.update(INDEX, 0, 0, 0, 10_000), INDEX);
+        // alpha = 0.5: half of the 200 bps impact, then capped at 100 bps
+        assert_eq!(mark.update(INDEX, 0, 5_000, 0, 10_000), 3_030_000_000);
+        assert_eq!(mark.update(INDEX, 0, 5_000, 0, 10_000), 3_030_000_000);
+        assert_eq!(mark.update(INDEX, 0, 0, 0, 10_000), 3_022_500_000);
+    }
+
+    #[test]
+    fn test_fills_pay_the_spread() {
+        let config = MarkPriceConfig {
+            fill_spread_bps: 5.0,
+            ..MarkPriceConfig::default()
+        };
+        assert_eq!(config.fill_price(INDEX, true), 3_001_500_000);
+        assert_eq!(config.fill_price(INDEX, false), 2_998_500_000);
+        assert!(config.validate().is_ok());
+        assert!(MarkPriceConfig { ema_ticks: 0, ..config }.validate().is_err());
+    }
+}
@@ -0,0 +1,237 @@
+// Shared market data: recent oracle prices and candles per symbol.
+//
+// The kernel records every oracle tick once, when the first copy of it is
+// delivered, and agents read it through `SimulatorApi::market_data` instead of
+// each keeping its own price deque. Candles are aligned to multiples of the
+// candle duration, so every agent sees the same bars.
+
+use std::collections::{vec_deque, HashMap, VecDeque};
+
+use crate::messages::{OracleTickPayload, Price};
+
+/// Ticks kept per symbol
+pub const DEFAULT_TICK_CAPACITY: usize = 300;
+pub const DEFAULT_CANDLE_NS: u64 = 5_000_000_000; // 5 sec candles
+/// Closed candles kept per symbol
+pub const DEFAULT_CANDLE_CAPACITY: usize = 256;
+
+/// Oracle price as recorded at delivery time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PricePoint {
+    pub ts: u64,
+    pub price: Price,
+}
+
+impl PricePoint {
+    pub fn mid(&self) -> u64 {
+        (self.price.min + self.price.max) / 2
+    }
+}
+
+/// OHLC of the mid price over `[start_ns, start_ns + candle_ns)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Candle {
+    pub start_ns: u64,
+    pub open: u64,
+    pub high: u64,
+    pub low: u64,
+    pub close: u64,
+}
+
+/// Recent ticks and candles of one symbol.
+#[derive(Debug, Default)]
+pub struct PriceHistory {
+    ticks: VecDeque<PricePoint>,
+    /// Closed candles, oldest first
+    candles: VecDeque<Candle>,
+    open_candle: Option<Candle>,
+    /// Publish time and price of the last recorded tick: the other copies of a broadcast are skipped
+    last_tick: Option<(u64, Price)>,
+}
+
+impl PriceHistory {
+    pub fn latest(&self) -> Option<&PricePoint> {
+        self.ticks.back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ticks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty()
+    }
+
+    /// Ticks, oldest first.
+    pub fn ticks(&self) -> vec_deque::Iter<'_, PricePoint> {
+        self.ticks.iter()
+    }
+
+    /// Mid prices of the last `n` ticks, oldest first (fewer while warming up).
+    pub fn recent_mids(&self, n: usize) -> impl Iterator<Item = u64> + '_ {
+        self.ticks
+            .iter()
+            .skip(self.ticks.len().saturating_sub(n))
+            .map(PricePoint::mid)
+    }
+
+    /// Oldest tick still kept that was recorded at or after `ts`.
+    pub fn first_since(&self, ts: u64) -> Option<&PricePoint> {
+        self.ticks.iter().find(|p| p.ts >= ts)
+    }
+
+    /// Closed candles, oldest first.
+    pub fn candles(&self) -> vec_deque::Iter<'_, Candle> {
+        self.candles.iter()
+    }
+
+    /// Candle still being built from the latest ticks.
+    pub fn open_candle(&self) -> Option<&Candle> {
+        self.open_candle.as_ref()
+    }
+}
+
+/// Price history of every symbol, fed by the kernel and read by agents.
+#[derive(Debug)]
+pub struct MarketDataService {
+    tick_capacity: usize,
+    candle_ns: u64,
+    candle_capacity: usize,
+    symbols: HashMap<String, PriceHistory>,
+}
+
+impl MarketDataService {
+    pub fn new(tick_capacity: usize, candle_ns: u64, candle_capacity: usize) -> Self {
+        Self {
+            tick_capacity: tick_capacity.max(1),
+            candle_ns: candle_ns.max(1),
+            candle_capacity,
+            symbols: HashMap::new(),
+        }
+    }
+
+    pub fn candle_ns(&self) -> u64 {
+        self.candle_ns
+    }
+
+    pub fn history(&self, symbol: &str) -> Option<&PriceHistory> {
+        self.symbols.get(symbol)
+    }
+
+    pub fn latest(&self, symbol: &str) -> Option<&PricePoint> {
+        self.history(symbol)?.latest()
+    }
+
+    /// Mid of the latest tick of `symbol`.
+    pub fn mid(&self, symbol: &str) -> Option<u64> {
+        self.latest(symbol).map(PricePoint::mid)
+    }
+
+    /// Record a delivered oracle tick; repeated copies of the last tick are ignored.
+    pub fn record(&mut self, tick: &OracleTickPayload, ts: u64) {
+        let history = self.symbols.entry(tick.symbol.clone()).or_default();
+        if history.last_tick == Some((tick.publish_time, tick.price)) {
+            return;
+        }
+        history.last_tick = Some((tick.publish_time, tick.price));
+
+        let point = PricePoint { ts, price: tick.price };
+        let mid = point.mid();
+        history.ticks.push_back(point);
+        if history.ticks.len() > self.tick_capacity {
+            history.ticks.pop_front();
+        }
+
+        let start_ns = ts - ts % self.candle_ns;
+        if let Some(candle) = history.open_candle.as_mut().filter(|c| c.start_ns == start_ns) {
+            candle.high = candle.high.max(mid);
+            candle.low = candle.low.min(mid);
+            candle.close = mid;
+            return;
+        }
+        let opened = Candle {
+            start_ns,
+            open: mid,
+            high: mid,
+            low: mid,
+            close: mid,
+        };
+        if let Some(closed) = history.open_candle.replace(opened) {
+            history.candles.push_back(closed);
+            if history.candles.len() > self.candle_capacity {
+                history.candles.pop_front();
+            }
+        }
+    }
+}
+
+impl Default for MarketDataService {
+    fn default() -> Self {
+        Self::new(DEFAULT_TICK_CAPACITY, DEFAULT_CANDLE_NS, DEFAULT_CANDLE_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn tick(symbol: &str, price: u64, publish_time: u64) -> OracleTickPayload {
+        OracleTickPayload {
+            symbol: symbol.to_string(),
+            price: Price { min: price, max: price },
+            publish_time,
+            signature: Arc::from(Vec::new()),
+        }
+    }
+
+    #[test]
+    fn test_records_each_tick_once_and_trims_to_capacity() {
+        let mut md = MarketDataService::new(3, 1_000, 10);
+        for i in 0..5 {
+            let t = tick("ETH-USD", 100 + i, i);
+            md.record(&t, i * 10);
+            // Copy of the same broadcast delivered to another agent
+            md.record(&t, i * 10 + 1);
+        }
+        let history = md.history("ETH-USD").unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.recent_mids(2).collect::<Vec<_>>(), vec![103, 104]);
+        assert_eq!(history.first_since(25).map(|p| p.mid()), Some(103));
+        assert_eq!(md.latest("ETH-USD").map(|p| p.ts), Some(40));
+        assert!(md.latest("BTC-USD").is_none());
+    }
+
+    #[test]
+    fn test_candles_are_aligned_to_the_duration() {
+        let mut md = MarketDataService::new(100, 1_000, 10);
+        for (i, (ts, price)) in [(1_200, 10), (1_500, 14), (1_900, 9), (2_100, 11), (3_050, 12)]
+            .iter()
+            .enumerate()
+        {
+            md.record(&tick("ETH-USD", *price, i as u64), *ts);
+        }
+        let history = md.history("ETH-USD").unwrap();
+        let closed: Vec<Candle> = history.candles().copied().collect();
+        assert_eq!(
+            closed,
+            vec![
+                Candle {
+                    start_ns: 1_000,
+                    open: 10,
+                    high: 14,
+                    low: 9,
+                    close: 9
+                },
+                Candle {
+                    start_ns: 2_000,
+                    open: 11,
+                    high: 11,
+                    low: 11,
+                    close: 11
+                },
+            ]
+        );
+        assert_eq!(history.open_candle().map(|c| c.start_ns), Some(3_000));
+    }
+}
@@ -15,14 +15,20 @@ pub enum MessageType {
     Trade,
     OrderLog,
     OracleTick,
+    /// Prices of every symbol of an oracle for one block, in one message
+    OracleBatchTick,
     OrderAccepted,
     OrderExecuted,
     OrderCancelled,
+    /// Conditional order reached its validity ("order_id:N")
+    OrderExpired,
     OrderRejected,
     LiquidationScan,
     LiquidationExecute,
     PositionLiquidated,
     MarketState,
+    /// Rolling taker flow of a market, broadcast by the exchange
+    MarketStats,
     SubmitOrder,
     ExecuteOrder,
     OrderPending,
@@ -30,15 +36,49 @@ pub enum MessageType {
     // Keeper API
     GetPendingOrders,
     PendingOrdersList,
+    /// Recent fills from the exchange's trade blotter; answered with TradesList
+    GetTrades,
+    TradesList,
     OrderAlreadyExecuted,
     KeeperReward,
+    /// Reserve a triggered order before executing it (off-chain, free); answered with
+    /// OrderClaimed or OrderClaimRejected
+    ClaimOrder,
+    OrderClaimed,
+    /// Another keeper holds the claim, or the order is not triggered
+    OrderClaimRejected,
+    /// Claimed orders executed in one transaction
+    ExecuteOrderBatch,
     PreviewRequest,
     PreviewResponse,
+    // Scenario timeline (DirectorAgent)
+    Director,
+    // Copy trading: follow another agent's fills
+    SubscribeFills,
+    LeaderFill,
+    // Pool liquidity (LP agents)
+    AddLiquidity,
+    RemoveLiquidity,
+    LiquidityChanged,
+    // Risk monitoring: periodic positions dump
+    SubscribeSnapshots,
+    PositionsSnapshot,
+    // Spot venue (SwapExchangeAgent); a rejected swap gets OrderRejected
+    Swap,
+    SwapExecuted,
+    /// Pool price of a spot market after it moved (broadcast)
+    SpotPrice,
+    // Admin API: pause / resume / reconfigure an agent (handled by the kernel,
+    // answered with AdminResult)
+    Admin,
+    AdminResult,
 }
 
+use crate::qty::Qty;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Side {
     #[serde(alias = "long", alias = "Long")]
@@ -54,6 +94,8 @@ pub enum ExecutionType {
     Limit,
     StopLoss,
     TakeProfit,
+    /// Stop whose trigger follows the price at `trailing_distance` (held by the exchange)
+    TrailingStop,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -64,37 +106,43 @@ pub enum OrderType {
 }
 
 /// Price range (bid/ask spread) for perpetual DEX
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Price {
     pub min: u64, // lower bound (bid)
     pub max: u64, // upper bound (ask)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LimitOrderPayload {
     pub symbol: String,
     pub side: Side,
-    pub qty: f64,
+    pub qty: Qty,
     pub price: u64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MarketOrderPayload {
     pub symbol: String,
     pub side: Side,
-    pub qty: f64,      // Number of tokens as float (e.g., 0.5 = 0.5 ETH, 2.0 = 2 ETH)
+    pub qty: Qty,      // Tokens, fixed point (e.g., 0.5 = 0.5 ETH, 2 = 2 ETH)
     pub leverage: u32, // 1-100x, default 5x
+    /// Oracle mid (micro-USD) the sender saw when deciding; fills are compared to it for slippage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision_price: Option<u64>,
 }
 
 /// Close (decrease) an existing position
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CloseOrderPayload {
     pub symbol: String,
     pub side: Side,
+    /// Oracle mid (micro-USD) the sender saw when deciding (see `MarketOrderPayload`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision_price: Option<u64>,
 }
 
 /// Universal order payload for all order types
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderPayload {
     pub symbol: String,
     pub side: Side,
@@ -102,7 +150,7 @@ pub struct OrderPayload {
     pub execution_type: ExecutionType,
     
     // For Increase
-    pub qty: Option<f64>,
+    pub qty: Option<Qty>,
     pub leverage: Option<u32>,
     
     // For Decrease
@@ -111,55 +159,144 @@ pub struct OrderPayload {
     // Conditional execution
     pub trigger_price: Option<u64>,
     pub acceptable_price: Option<u64>,
+
+    // Trailing stop: distance between the best price seen and the trigger (micro-USD)
+    pub trailing_distance: Option<u64>,
     
     // TTL
     pub valid_for_sec: Option<u64>,
+
+    // OCO: executing one order of the group cancels the owner's other orders in it
+    pub oco_group: Option<u64>,
+
+    // Oracle mid at decision time of a market order (see `MarketOrderPayload`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision_price: Option<u64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CancelOrderPayload {
     pub order_id: OrderId,
 }
 
-#[derive(Debug, Clone)]
+/// Amend a resting order; fields left None are unchanged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModifyOrderPayload {
+    pub order_id: OrderId,
+    pub trigger_price: Option<u64>,
+    /// New size of an Increase order (tokens)
+    pub qty: Option<Qty>,
+    /// New size of a Decrease order (micro-USD)
+    pub size_delta_usd: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecuteOrderPayload {
     pub order_id: OrderId,
+    /// Price the keeper fetched just before executing, bundled with the on-chain execution
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_update: Option<PriceUpdatePayload>,
+}
+
+/// Claimed orders a keeper executes together; the on-chain ones go out as one batch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecuteOrderBatchPayload {
+    pub orders: Vec<ExecuteOrderPayload>,
+}
+
+/// Fresh provider price (with VAA) a keeper attaches to an ExecuteOrder in Vara mode.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceUpdatePayload {
+    pub symbol: String,
+    pub price_usd_micro: u64,
+    pub confidence: Option<u64>,
+    /// Unix timestamp (seconds)
+    pub publish_time: u64,
+    pub vaa: Vec<u8>,
+    /// Oldest acceptable `publish_time` when the execution is sent, in seconds (0 = no limit)
+    pub max_age_secs: u64,
 }
 
 /// Oracle price update with signature for on-chain verification.
 /// Includes min/max range computed from confidence interval.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OracleTickPayload {
     pub symbol: String,
-    pub price: Price,       // min/max range (bid/ask)
-    pub publish_time: u64,  // Unix timestamp (seconds)
-    pub signature: Vec<u8>, // VAA signature from oracle provider (e.g., Pyth Network)
+    pub price: Price,         // min/max range (bid/ask)
+    pub publish_time: u64,    // Unix timestamp (seconds)
+    /// Shared by all copies of a broadcast tick instead of cloned per recipient
+    pub signature: Arc<[u8]>, // VAA signature from oracle provider (e.g., Pyth Network)
+}
+
+/// All symbol prices of one oracle wakeup: one queue entry per recipient instead of one per symbol.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OracleBatchTickPayload {
+    pub ticks: Vec<OracleTickPayload>,
 }
 
 /// Market state snapshot for trader logic (OI + liquidity in micro-USD)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MarketStatePayload {
     pub symbol: String,
     pub oi_long_usd: i128,
     pub oi_short_usd: i128,
     pub liquidity_usd: i128,
+    /// Realized pool PnL since start: fees and trader losses minus trader profits
+    pub pool_pnl_usd: i128,
+    /// Estimated funding rate, bps*1e6 per hour (+ = longs pay)
+    #[serde(default)]
+    pub funding_rate_bps_hour_fp: i64,
+    /// Estimated borrowing rate, bps*1e6 per hour
+    #[serde(default)]
+    pub borrowing_rate_bps_hour_fp: u64,
+}
+
+/// Taker flow of a market over the last `window_sec` (volumes in micro-USD)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MarketStatsPayload {
+    pub symbol: String,
+    pub window_sec: u64,
+    pub buy_volume_usd: u64,
+    pub sell_volume_usd: u64,
+    /// (buy - sell) / (buy + sell) in bps
+    pub imbalance_bps: i64,
+    pub trades: u64,
+    pub avg_trade_usd: u64,
+}
+
+/// Add or remove pool liquidity (micro-USD)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiquidityPayload {
+    pub symbol: String,
+    pub amount_usd: i128,
+}
+
+/// Result of an Add/RemoveLiquidity request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiquidityChangedPayload {
+    pub symbol: String,
+    /// + added, - removed (micro-USD)
+    pub delta_usd: i128,
+    /// Current value of the sender's pool share
+    pub lp_value_usd: i128,
+    pub liquidity_usd: i128,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PreviewRequestPayload {
     pub symbol: String,
     pub side: Side,
-    pub qty: f64,
+    pub qty: Qty,
     pub leverage: u32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PreviewResponsePayload {
     pub success: bool,
     pub message: String,
     pub symbol: String,
     pub side: Side,
-    pub qty: f64,
+    pub qty: Qty,
     pub leverage: u32,
     pub size_usd: i128,
     pub collateral: i128,
@@ -176,14 +313,14 @@ pub struct PreviewResponsePayload {
     pub close_fees_usd: u64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LiquidationTaskPayload {
     pub symbol: String,
     pub max_positions: u32,
 }
 
 /// Notification sent to trader when their position is liquidated
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PositionLiquidatedPayload {
     pub symbol: String,
     pub side: Side,
@@ -193,7 +330,7 @@ pub struct PositionLiquidatedPayload {
 }
 
 /// Notification sent to trader when their order is executed
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderExecutedPayload {
     pub symbol: String,
     pub side: Side,
@@ -203,14 +340,14 @@ pub struct OrderExecutedPayload {
     pub size_usd: i128,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OrderExecutionType {
     Increase,
     Decrease,
     Liquidation,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PendingOrderInfo {
     pub order_id: OrderId,
     pub symbol: String,
@@ -219,20 +356,177 @@ pub struct PendingOrderInfo {
     pub execution_type: ExecutionType,
     pub trigger_price: u64,
     pub owner: AgentId,
+    /// Order size in tokens (0 for orders sized in USD); the keeper reward is a share of its notional
+    #[serde(default)]
+    pub qty: Qty,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PendingOrdersListPayload {
     pub orders: Vec<PendingOrderInfo>,
 }
 
-#[derive(Debug, Clone)]
+/// One fill in the exchange's trade blotter. Amounts in micro-USD; `pnl` is realized on
+/// closes and liquidations, 0 on increases.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeRecord {
+    pub ts: u64,
+    pub account: AgentId,
+    pub symbol: String,
+    pub side: Side,
+    pub order_type: OrderExecutionType,
+    pub price: u64,
+    pub size_usd: u64,
+    pub fee_usd: u64,
+    pub pnl: i64,
+}
+
+/// Query of the trade blotter, newest `limit` fills first; unset filters match everything
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetTradesPayload {
+    pub account: Option<AgentId>,
+    pub symbol: Option<String>,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradesListPayload {
+    pub trades: Vec<TradeRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeeperRewardPayload {
     pub order_id: OrderId,
     pub reward_micro_usd: u64,
 }
 
-#[derive(Debug, Clone)]
+/// Subscribe the sender to the executed orders of `leader`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscribeFillsPayload {
+    pub leader: AgentId,
+}
+
+/// Executed order of a followed agent, forwarded to its subscribers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderFillPayload {
+    pub leader: AgentId,
+    pub symbol: String,
+    pub side: Side,
+    pub order_type: OrderExecutionType,
+    pub size_usd: u64,
+    pub execution_price: u64,
+    pub leverage: u32,
+}
+
+/// Open position inside a PositionsSnapshot (micro-USD)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionInfo {
+    pub account: AgentId,
+    pub side: Side,
+    pub size_usd: u64,
+    pub collateral: u64,
+    pub entry_price: u64,
+    /// Contract liquidation price, or the exchange's maintenance-margin estimate (0 = unknown)
+    #[serde(default)]
+    pub liquidation_price: u64,
+}
+
+/// Periodic dump of the open positions of one market, sent to snapshot subscribers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionsSnapshotPayload {
+    pub symbol: String,
+    /// Index price (oracle mid)
+    pub price: u64,
+    /// Mark price liquidations are checked at; equals `price` without a mark model
+    #[serde(default)]
+    pub mark_price: u64,
+    pub oi_long_usd: u64,
+    pub oi_short_usd: u64,
+    pub liquidity_usd: i128,
+    /// Shared by the copies sent to each subscriber
+    pub positions: Arc<[PositionInfo]>,
+}
+
+/// Spot trade on a SwapExchangeAgent: buy (Buy) or sell (Sell) `qty` tokens of `symbol`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapPayload {
+    pub symbol: String,
+    pub side: Side,
+    pub qty: Qty,
+    /// Worst acceptable average price (micro-USD); None = any
+    #[serde(default)]
+    pub limit_price: Option<u64>,
+}
+
+/// Filled swap, sent to the trader (prices in micro-USD)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapExecutedPayload {
+    pub symbol: String,
+    pub side: Side,
+    pub qty: Qty,
+    /// Average price including the fee
+    pub price: u64,
+    pub fee_usd: u64,
+    /// Trader's spot balance of `symbol` after the swap (negative: borrowed)
+    pub holdings: f64,
+    /// Pool price after the swap
+    pub spot_price: u64,
+}
+
+/// Pool price of a spot market next to the oracle price (micro-USD)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpotPricePayload {
+    pub symbol: String,
+    pub spot_price: u64,
+    pub oracle_price: u64,
+}
+
+/// Scripted scenario command sent by the DirectorAgent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DirectorCommand {
+    /// Multiply oracle prices of `symbol` by `factor` (compounds with earlier shocks)
+    PriceShock { symbol: String, factor: f64 },
+    /// Publish `price` (micro-USD) for `symbol` instead of the provider's for `duration_ns`
+    /// (0 ends an override early)
+    PriceOverride {
+        symbol: String,
+        price: u64,
+        duration_ns: u64,
+    },
+    /// Change one runtime parameter of the receiving agent
+    SetParam { key: String, value: serde_json::Value },
+}
+
+/// Admin API command for one agent, run by the kernel through the agent's lifecycle hooks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AdminCommand {
+    /// Hold the agent's wakeups until it is resumed (messages are still delivered)
+    Pause,
+    Resume,
+    /// New runtime parameters (JSON object) for `Agent::on_config_update`
+    ConfigUpdate(serde_json::Value),
+}
+
+impl AdminCommand {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AdminCommand::Pause => "pause",
+            AdminCommand::Resume => "resume",
+            AdminCommand::ConfigUpdate(_) => "config",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminResultPayload {
+    pub agent: AgentId,
+    /// "pause", "resume" or "config"
+    pub command: String,
+    /// None if the command was applied
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MessagePayload {
     Empty,
     Text(String),
@@ -240,21 +534,54 @@ pub enum MessagePayload {
     MarketOrder(MarketOrderPayload),
     CloseOrder(CloseOrderPayload),
     OracleTick(OracleTickPayload),
+    OracleBatchTick(OracleBatchTickPayload),
     LiquidationTask(LiquidationTaskPayload),
     PositionLiquidated(PositionLiquidatedPayload),
     OrderExecuted(OrderExecutedPayload),
     MarketState(MarketStatePayload),
+    MarketStats(MarketStatsPayload),
     PreviewRequest(PreviewRequestPayload),
     PreviewResponse(PreviewResponsePayload),
     Order(OrderPayload),
     CancelOrder(CancelOrderPayload),
+    ModifyOrder(ModifyOrderPayload),
     ExecuteOrder(ExecuteOrderPayload),
+    ExecuteOrderBatch(ExecuteOrderBatchPayload),
     PendingOrdersList(PendingOrdersListPayload),
+    GetTrades(GetTradesPayload),
+    TradesList(TradesListPayload),
     KeeperReward(KeeperRewardPayload),
+    Director(DirectorCommand),
+    SubscribeFills(SubscribeFillsPayload),
+    LeaderFill(LeaderFillPayload),
+    Liquidity(LiquidityPayload),
+    LiquidityChanged(LiquidityChangedPayload),
+    PositionsSnapshot(PositionsSnapshotPayload),
+    Swap(SwapPayload),
+    SwapExecuted(SwapExecutedPayload),
+    SpotPrice(SpotPricePayload),
+    Admin(AdminCommand),
+    AdminResult(AdminResultPayload),
+}
+
+impl MessagePayload {
+    /// Price ticks of an OracleTick or OracleBatchTick payload (empty for anything else).
+    pub fn oracle_ticks(&self) -> &[OracleTickPayload] {
+        match self {
+            MessagePayload::OracleTick(tick) => std::slice::from_ref(tick),
+            MessagePayload::OracleBatchTick(batch) => &batch.ticks,
+            _ => &[],
+        }
+    }
+
+    /// The price tick of `symbol`, if the payload carries one.
+    pub fn oracle_tick(&self, symbol: &str) -> Option<&OracleTickPayload> {
+        self.oracle_ticks().iter().find(|tick| tick.symbol == symbol)
+    }
 }
 
 /// Core message type that flows through the Kernel.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub to: AgentId,
     pub from: AgentId,
@@ -301,6 +628,25 @@ pub trait SimulatorApi {
     /// Broadcast a message from one agent to all others.
     fn broadcast(&mut self, from: AgentId, kind: MessageType, payload: MessagePayload);
 
+    /// Broadcast a message to the other members of agent group `group` (see the scenario's `groups`).
+    fn broadcast_to_group(&mut self, from: AgentId, group: &str, kind: MessageType, payload: MessagePayload);
+
+    /// Send one message to each agent of `to`; like a broadcast, it is logged once.
+    fn multicast(&mut self, from: AgentId, to: &[AgentId], kind: MessageType, payload: MessagePayload) {
+        for &id in to {
+            self.send(from, id, kind, payload.clone());
+        }
+    }
+
     /// Emit a high-level event to the event bus (for logging/analytics).
     fn emit_event(&mut self, event: crate::events::SimEvent);
+
+    /// Add an agent while the simulation is running (registered after the current message).
+    fn spawn_agent(&mut self, agent: Box<dyn crate::agents::Agent>);
+
+    /// Remove an agent (on_stop is called; later messages to it are dropped).
+    fn remove_agent(&mut self, agent_id: AgentId);
+
+    /// Recent oracle prices and candles of every symbol, shared by all agents (read-only).
+    fn market_data(&self) -> &crate::market_data::MarketDataService;
 }
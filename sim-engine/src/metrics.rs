@@ -0,0 +1,224 @@
+// Timing histograms of the kernel.
+//
+// With metrics enabled the kernel records two things for every delivered
+// message: per message type, how long after its scheduled time it was handled
+// (sim time: tick rounding, inbox waits, dead-letter retries), and per agent,
+// how long the agent's handler ran (wall clock). The handler times show which
+// agent eats the tick budget of a realtime run. Both are written to
+// `metrics.prom` at the end of the run and served on the API's `/metrics`, in
+// the Prometheus text format.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+use crate::messages::{AgentId, MessageType};
+
+/// Metrics shared between the kernel and the API server
+pub type SharedMetrics = Arc<Mutex<KernelMetrics>>;
+
+/// Upper bounds of the histogram buckets in ns (1µs to 16s, x4 each); larger values go to +Inf.
+pub const BUCKETS_NS: [u64; 13] = [
+    1_000,
+    4_000,
+    16_000,
+    64_000,
+    256_000,
+    1_000_000,
+    4_000_000,
+    16_000_000,
+    64_000_000,
+    256_000_000,
+    1_000_000_000,
+    4_000_000_000,
+    16_000_000_000,
+];
+
+/// Durations in ns bucketed by `BUCKETS_NS`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Histogram {
+    /// Per bucket (not cumulative), +Inf last
+    counts: [u64; BUCKETS_NS.len() + 1],
+    count: u64,
+    sum_ns: u128,
+    max_ns: u64,
+}
+
+impl Histogram {
+    pub fn record(&mut self, ns: u64) {
+        let bucket = BUCKETS_NS
+            .iter()
+            .position(|&bound| ns <= bound)
+            .unwrap_or(BUCKETS_NS.len());
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum_ns += ns as u128;
+        self.max_ns = self.max_ns.max(ns);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn sum_ns(&self) -> u128 {
+        self.sum_ns
+    }
+
+    pub fn max_ns(&self) -> u64 {
+        self.max_ns
+    }
+
+    pub fn mean_ns(&self) -> u64 {
+        (self.sum_ns / self.count.max(1) as u128) as u64
+    }
+
+    /// Upper bound of the bucket holding quantile `q` (the max for the +Inf bucket); 0 when empty.
+    pub fn quantile_ns(&self, q: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, n) in self.counts.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return BUCKETS_NS.get(i).map_or(self.max_ns, |&bound| bound.min(self.max_ns));
+            }
+        }
+        self.max_ns
+    }
+
+    /// `<name>_bucket`, `_sum` and `_count` lines in seconds, labelled with `labels`.
+    fn write_prometheus(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (i, n) in self.counts.iter().enumerate() {
+            cumulative += n;
+            let le = BUCKETS_NS
+                .get(i)
+                .map_or("+Inf".to_string(), |&bound| seconds(bound as u128));
+            let _ = writeln!(out, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, le, cumulative);
+        }
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, seconds(self.sum_ns));
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+fn seconds(ns: u128) -> String {
+    format!("{}", ns as f64 / 1e9)
+}
+
+/// Handler times of one agent.
+#[derive(Debug, Clone, Default)]
+pub struct AgentTimes {
+    pub name: String,
+    pub handler: Histogram,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct KernelMetrics {
+    /// Sim time from scheduled to actual delivery, per message type
+    pub delivery_lag: HashMap<MessageType, Histogram>,
+    /// Wall time of `on_message` / `on_wakeup`, per agent
+    pub handlers: HashMap<AgentId, AgentTimes>,
+}
+
+impl KernelMetrics {
+    pub fn shared() -> SharedMetrics {
+        Arc::new(Mutex::new(Self::default()))
+    }
+
+    /// Record a message of `kind` handled `lag_ns` late by agent `agent` in `handler_ns`.
+    pub fn record(&mut self, kind: MessageType, lag_ns: u64, agent: AgentId, name: &str, handler_ns: u64) {
+        self.delivery_lag.entry(kind).or_default().record(lag_ns);
+        self.handlers
+            .entry(agent)
+            .or_insert_with(|| AgentTimes {
+                name: name.to_string(),
+                handler: Histogram::default(),
+            })
+            .handler
+            .record(handler_ns);
+    }
+
+    /// Agents by total handler time, largest first.
+    pub fn top_handlers(&self, n: usize) -> Vec<(AgentId, &AgentTimes)> {
+        let mut agents: Vec<(AgentId, &AgentTimes)> = self.handlers.iter().map(|(id, t)| (*id, t)).collect();
+        agents.sort_by(|a, b| b.1.handler.sum_ns().cmp(&a.1.handler.sum_ns()).then(a.0.cmp(&b.0)));
+        agents.truncate(n);
+        agents
+    }
+
+    /// Both histogram families in the Prometheus text format.
+    pub fn prometheus(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP sim_delivery_lag_seconds Sim time between a message's scheduled and actual delivery\n");
+        out.push_str("# TYPE sim_delivery_lag_seconds histogram\n");
+        let mut kinds: Vec<(String, &Histogram)> = self
+            .delivery_lag
+            .iter()
+            .map(|(kind, h)| (format!("{:?}", kind), h))
+            .collect();
+        kinds.sort_by(|a, b| a.0.cmp(&b.0));
+        for (kind, histogram) in kinds {
+            let labels = format!("msg_type=\"{}\"", kind);
+            histogram.write_prometheus(&mut out, "sim_delivery_lag_seconds", &labels);
+        }
+
+        out.push_str("# HELP sim_handler_seconds Wall-clock time of an agent's message and wakeup handlers\n");
+        out.push_str("# TYPE sim_handler_seconds histogram\n");
+        let mut agents: Vec<(&AgentId, &AgentTimes)> = self.handlers.iter().collect();
+        agents.sort_by_key(|(id, _)| **id);
+        for (id, times) in agents {
+            let name = times.name.replace('\\', "\\\\").replace('"', "\\\"");
+            let labels = format!("agent=\"{}\",name=\"{}\"", id, name);
+            times.handler.write_prometheus(&mut out, "sim_handler_seconds", &labels);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets_and_quantiles() {
+        let mut h = Histogram::default();
+        assert_eq!(h.quantile_ns(0.5), 0);
+        for ns in [500, 1_000, 3_000, 50_000, 2_000_000] {
+            h.record(ns);
+        }
+        assert_eq!(h.count(), 5);
+        assert_eq!(h.max_ns(), 2_000_000);
+        assert_eq!(h.mean_ns(), 410_900);
+        // 1µs bucket holds two, 4µs one, 64µs one, 4ms one
+        assert_eq!(h.quantile_ns(0.4), 1_000);
+        assert_eq!(h.quantile_ns(0.6), 4_000);
+        assert_eq!(h.quantile_ns(0.8), 64_000);
+        // Capped at the largest value seen
+        assert_eq!(h.quantile_ns(1.0), 2_000_000);
+
+        h.record(60_000_000_000);
+        assert_eq!(h.quantile_ns(1.0), 60_000_000_000);
+    }
+
+    #[test]
+    fn test_prometheus_text_is_cumulative_per_label() {
+        let mut metrics = KernelMetrics::default();
+        metrics.record(MessageType::OracleTick, 0, 3, "Exchange", 2_000);
+        metrics.record(MessageType::OracleTick, 100_000_000, 3, "Exchange", 20_000_000);
+        metrics.record(MessageType::Wakeup, 0, 7, "Bot \"7\"", 500);
+
+        let text = metrics.prometheus();
+        assert!(text.contains("sim_delivery_lag_seconds_bucket{msg_type=\"OracleTick\",le=\"0.000001\"} 1\n"));
+        assert!(text.contains("sim_delivery_lag_seconds_bucket{msg_type=\"OracleTick\",le=\"+Inf\"} 2\n"));
+        assert!(text.contains("sim_delivery_lag_seconds_sum{msg_type=\"OracleTick\"} 0.1\n"));
+        assert!(text.contains("sim_handler_seconds_bucket{agent=\"3\",name=\"Exchange\",le=\"0.004\"} 1\n"));
+        assert!(text.contains("sim_handler_seconds_bucket{agent=\"3\",name=\"Exchange\",le=\"0.064\"} 2\n"));
+        assert!(text.contains("sim_handler_seconds_count{agent=\"7\",name=\"Bot \\\"7\\\"\"} 1\n"));
+
+        let top = metrics.top_handlers(1);
+        assert_eq!(top.len(), 1);
+        assert_eq!((top[0].0, top[0].1.name.as_str()), (3, "Exchange"));
+    }
+}
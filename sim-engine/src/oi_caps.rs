@@ -0,0 +1,199 @@
+// Open interest caps of a market.
+//
+// The exchange checks every new increase against the market's caps before the
+// risk limits: the OI it would reach, in total and on its side, both in USD and
+// relative to the pool liquidity backing it. An increase past a cap is
+// rejected with an `oi_cap:<cap>,value:<v>,limit:<l>` reason, so a crowded
+// side cannot grow until the pool is drained. Closes and liquidations always
+// pass. Utilization is the total OI over pool liquidity, as in the borrowing
+// rate (without its cap at 1).
+
+use serde::{Deserialize, Serialize};
+
+use crate::messages::Side;
+
+/// Caps of one market; unset fields are not checked. Amounts in micro-USD, ratios in bps of pool liquidity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OiCaps {
+    /// Long plus short OI
+    #[serde(default)]
+    pub max_oi_usd: Option<u64>,
+    /// OI of the order's side
+    #[serde(default)]
+    pub max_side_oi_usd: Option<u64>,
+    /// Long plus short OI over pool liquidity
+    #[serde(default)]
+    pub max_utilization_bps: Option<u64>,
+    /// OI of the order's side over pool liquidity
+    #[serde(default)]
+    pub max_side_utilization_bps: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OiCapKind {
+    TotalOi,
+    SideOi,
+    Utilization,
+    SideUtilization,
+}
+
+impl OiCapKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OiCapKind::TotalOi => "max_oi_usd",
+            OiCapKind::SideOi => "max_side_oi_usd",
+            OiCapKind::Utilization => "max_utilization_bps",
+            OiCapKind::SideUtilization => "max_side_utilization_bps",
+        }
+    }
+}
+
+/// A rejected increase: which cap, the value the order would reach and the cap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OiCapBreach {
+    pub kind: OiCapKind,
+    pub value: u64,
+    pub limit: u64,
+}
+
+impl OiCapBreach {
+    /// Rejection text sent to the agent
+    pub fn reason(&self) -> String {
+        format!(
+            "oi_cap:{},value:{},limit:{}",
+            self.kind.as_str(),
+            self.value,
+            self.limit
+        )
+    }
+}
+
+/// OI over pool liquidity in bps; u64::MAX for open interest without liquidity.
+pub fn utilization_bps(oi_usd: u64, liquidity_usd: u64) -> u64 {
+    if liquidity_usd == 0 {
+        return if oi_usd > 0 { u64::MAX } else { 0 };
+    }
+    (oi_usd as u128 * 10_000 / liquidity_usd as u128).min(u64::MAX as u128) as u64
+}
+
+impl OiCaps {
+    pub fn is_enabled(&self) -> bool {
+        self.max_oi_usd.is_some()
+            || self.max_side_oi_usd.is_some()
+            || self.max_utilization_bps.is_some()
+            || self.max_side_utilization_bps.is_some()
+    }
+
+    /// Check an increase of `size_usd` on `side` against the current OI and pool liquidity.
+    pub fn check(
+        &self,
+        oi_long_usd: u64,
+        oi_short_usd: u64,
+        liquidity_usd: u64,
+        side: Side,
+        size_usd: u64,
+    ) -> Result<(), OiCapBreach> {
+        let side_oi = match side {
+            Side::Buy => oi_long_usd,
+            Side::Sell => oi_short_usd,
+        }
+        .saturating_add(size_usd);
+        let total_oi = oi_long_usd.saturating_add(oi_short_usd).saturating_add(size_usd);
+        let caps = [
+            (OiCapKind::TotalOi, self.max_oi_usd, total_oi),
+            (OiCapKind::SideOi, self.max_side_oi_usd, side_oi),
+            (
+                OiCapKind::Utilization,
+                self.max_utilization_bps,
+                utilization_bps(total_oi, liquidity_usd),
+            ),
+            (
+                OiCapKind::SideUtilization,
+                self.max_side_utilization_bps,
+                utilization_bps(side_oi, liquidity_usd),
+            ),
+        ];
+        for (kind, limit, value) in caps {
+            if let Some(limit) = limit.filter(|limit| value > *limit) {
+                return Err(OiCapBreach { kind, value, limit });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIQUIDITY: u64 = 1_000_000_000_000; // $1M
+
+    #[test]
+    fn test_disabled_caps_accept_any_increase() {
+        let caps = OiCaps::default();
+        assert!(!caps.is_enabled());
+        assert!(caps.check(u64::MAX / 2, 0, 0, Side::Buy, u64::MAX / 2).is_ok());
+    }
+
+    #[test]
+    fn test_usd_caps() {
+        let caps = OiCaps {
+            max_oi_usd: Some(500_000_000_000),
+            max_side_oi_usd: Some(300_000_000_000),
+            ..OiCaps::default()
+        };
+        // 200k long + 200k short: another 100k long reaches both caps exactly
+        assert!(caps
+            .check(200_000_000_000, 200_000_000_000, LIQUIDITY, Side::Buy, 100_000_000_000)
+            .is_ok());
+        let breach = caps
+            .check(200_000_000_000, 250_000_000_000, LIQUIDITY, Side::Buy, 100_000_000_000)
+            .unwrap_err();
+        assert_eq!(breach.kind, OiCapKind::TotalOi);
+        assert_eq!(breach.value, 550_000_000_000);
+        let breach = caps
+            .check(250_000_000_000, 0, LIQUIDITY, Side::Buy, 100_000_000_000)
+            .unwrap_err();
+        assert_eq!(breach.kind, OiCapKind::SideOi);
+        assert_eq!(
+            breach.reason(),
+            "oi_cap:max_side_oi_usd,value:350000000000,limit:300000000000"
+        );
+        // The other side is not crowded
+        assert!(caps
+            .check(250_000_000_000, 0, LIQUIDITY, Side::Sell, 100_000_000_000)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_utilization_caps_follow_liquidity() {
+        let caps = OiCaps {
+            max_utilization_bps: Some(8_000),
+            max_side_utilization_bps: Some(5_000),
+            ..OiCaps::default()
+        };
+        assert!(caps
+            .check(400_000_000_000, 0, LIQUIDITY, Side::Sell, 400_000_000_000)
+            .is_ok());
+        let breach = caps
+            .check(400_000_000_000, 0, LIQUIDITY, Side::Buy, 200_000_000_000)
+            .unwrap_err();
+        assert_eq!((breach.kind, breach.value), (OiCapKind::SideUtilization, 6_000));
+        let breach = caps
+            .check(400_000_000_000, 400_000_000_000, LIQUIDITY, Side::Sell, 50_000_000_000)
+            .unwrap_err();
+        assert_eq!((breach.kind, breach.value), (OiCapKind::Utilization, 8_500));
+        // A drained pool rejects every increase
+        assert!(caps.check(0, 0, 0, Side::Buy, 1).is_err());
+        assert!(caps.check(0, 0, 0, Side::Buy, 0).is_ok());
+    }
+
+    #[test]
+    fn test_utilization_bps() {
+        assert_eq!(utilization_bps(250_000_000_000, LIQUIDITY), 2_500);
+        assert_eq!(utilization_bps(2 * LIQUIDITY, LIQUIDITY), 20_000);
+        assert_eq!(utilization_bps(0, 0), 0);
+        assert_eq!(utilization_bps(1, 0), u64::MAX);
+    }
+}
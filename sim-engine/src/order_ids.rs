@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use crate::messages::OrderId;
+
+/// Two-way table between the ids the exchange hands to agents and the ids the contract
+/// assigns to the same orders. Agents only see local ids; the contract id is known once the
+/// submission's tx result arrives and changes when an amendment resubmits the order.
+#[derive(Debug, Default)]
+pub struct OrderIdMap {
+    to_chain: HashMap<OrderId, OrderId>,
+    to_local: HashMap<OrderId, OrderId>,
+}
+
+impl OrderIdMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map `local` to `chain`, replacing the previous contract id of `local`.
+    pub fn link(&mut self, local: OrderId, chain: OrderId) {
+        if let Some(old) = self.to_chain.insert(local, chain) {
+            self.to_local.remove(&old);
+        }
+        if let Some(old) = self.to_local.insert(chain, local) {
+            if old != local {
+                self.to_chain.remove(&old);
+            }
+        }
+    }
+
+    pub fn chain_id(&self, local: OrderId) -> Option<OrderId> {
+        self.to_chain.get(&local).copied()
+    }
+
+    pub fn local_id(&self, chain: OrderId) -> Option<OrderId> {
+        self.to_local.get(&chain).copied()
+    }
+
+    /// Forget the order by its contract id; returns its local id.
+    pub fn remove_chain(&mut self, chain: OrderId) -> Option<OrderId> {
+        let local = self.to_local.remove(&chain)?;
+        self.to_chain.remove(&local);
+        Some(local)
+    }
+
+    pub fn len(&self) -> usize {
+        self.to_chain.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.to_chain.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translates_both_ways() {
+        let mut ids = OrderIdMap::new();
+        ids.link(1_000, 7);
+        ids.link(1_001, 8);
+        assert_eq!(ids.chain_id(1_000), Some(7));
+        assert_eq!(ids.local_id(8), Some(1_001));
+        assert_eq!(ids.chain_id(1_002), None);
+        assert_eq!(ids.local_id(9), None);
+        assert_eq!(ids.len(), 2);
+    }
+
+    #[test]
+    fn test_relink_drops_the_old_contract_id() {
+        let mut ids = OrderIdMap::new();
+        ids.link(1_000, 7);
+        // Amended: cancelled and resubmitted under a new contract id
+        ids.link(1_000, 12);
+        assert_eq!(ids.chain_id(1_000), Some(12));
+        assert_eq!(ids.local_id(12), Some(1_000));
+        assert_eq!(ids.local_id(7), None);
+        assert_eq!(ids.len(), 1);
+    }
+
+    #[test]
+    fn test_remove_by_contract_id() {
+        let mut ids = OrderIdMap::new();
+        ids.link(1_000, 7);
+        assert_eq!(ids.remove_chain(7), Some(1_000));
+        assert_eq!(ids.remove_chain(7), None);
+        assert_eq!(ids.chain_id(1_000), None);
+        assert!(ids.is_empty());
+    }
+}
@@ -1,5 +1,6 @@
-use std::collections::HashMap;
-use crate::messages::{AgentId, OrderId, OrderPayload};
+use std::collections::{BTreeSet, HashMap};
+use crate::messages::{AgentId, ExecutionType, ModifyOrderPayload, OrderId, OrderPayload, Price};
+use crate::trigger_checker::{self, TriggerDirection};
 
 #[derive(Debug, Clone)]
 pub struct PendingOrder {
@@ -9,10 +10,54 @@ pub struct PendingOrder {
     pub valid_until_ns: u64,
 }
 
+impl PendingOrder {
+    fn trigger_key(&self) -> Option<(TriggerDirection, u64)> {
+        let payload = &self.payload;
+        let direction = trigger_checker::trigger_direction(payload.execution_type, payload.order_type, payload.side)?;
+        Some((direction, payload.trigger_price?))
+    }
+}
+
+/// Orders of one symbol sorted by trigger price, per direction. A price range
+/// `[min, max]` can only trigger falling orders at or above `min` and rising orders
+/// at or below `max`, so lookups cost O(log n + k) instead of a full scan.
+#[derive(Debug, Default)]
+pub struct TriggerIndex {
+    falling: BTreeSet<(u64, OrderId)>,
+    rising: BTreeSet<(u64, OrderId)>,
+}
+
+impl TriggerIndex {
+    pub fn insert(&mut self, direction: TriggerDirection, trigger: u64, order_id: OrderId) {
+        self.side_mut(direction).insert((trigger, order_id));
+    }
+
+    pub fn remove(&mut self, direction: TriggerDirection, trigger: u64, order_id: OrderId) {
+        self.side_mut(direction).remove(&(trigger, order_id));
+    }
+
+    /// Orders the price range may trigger. Whether `min` or `max` decides depends on the
+    /// order type, so callers confirm candidates with `trigger_checker`.
+    pub fn candidates(&self, price: &Price) -> impl Iterator<Item = OrderId> + '_ {
+        let falling = self.falling.range((price.min, OrderId::MIN)..).map(|&(_, id)| id);
+        let rising = self.rising.range(..=(price.max, OrderId::MAX)).map(|&(_, id)| id);
+        falling.chain(rising)
+    }
+
+    fn side_mut(&mut self, direction: TriggerDirection) -> &mut BTreeSet<(u64, OrderId)> {
+        match direction {
+            TriggerDirection::Falling => &mut self.falling,
+            TriggerDirection::Rising => &mut self.rising,
+        }
+    }
+}
+
 pub struct PendingOrderStore {
     orders: HashMap<OrderId, PendingOrder>,
     by_owner: HashMap<AgentId, Vec<OrderId>>,
     by_symbol: HashMap<String, Vec<OrderId>>,
+    by_oco: HashMap<(AgentId, u64), Vec<OrderId>>,
+    by_trigger: HashMap<String, TriggerIndex>,
 }
 
 impl PendingOrderStore {
@@ -21,9 +66,24 @@ impl PendingOrderStore {
             orders: HashMap::new(),
             by_owner: HashMap::new(),
             by_symbol: HashMap::new(),
+            by_oco: HashMap::new(),
+            by_trigger: HashMap::new(),
         }
     }
 
+    pub fn insert(&mut self, order: PendingOrder) {
+        self.by_owner.entry(order.owner).or_default().push(order.id);
+        self.by_symbol.entry(order.payload.symbol.clone()).or_default().push(order.id);
+        if let Some(group) = order.payload.oco_group {
+            self.by_oco.entry((order.owner, group)).or_default().push(order.id);
+        }
+        if let Some((direction, trigger)) = order.trigger_key() {
+            let index = self.by_trigger.entry(order.payload.symbol.clone()).or_default();
+            index.insert(direction, trigger, order.id);
+        }
+        self.orders.insert(order.id, order);
+    }
+
     pub fn remove(&mut self, order_id: OrderId) -> Option<PendingOrder> {
         let order = self.orders.remove(&order_id)?;
         if let Some(ids) = self.by_owner.get_mut(&order.owner) {
@@ -32,6 +92,20 @@ impl PendingOrderStore {
         if let Some(ids) = self.by_symbol.get_mut(&order.payload.symbol) {
             ids.retain(|&id| id != order_id);
         }
+        if let Some(group) = order.payload.oco_group {
+            let key = (order.owner, group);
+            if let Some(ids) = self.by_oco.get_mut(&key) {
+                ids.retain(|&id| id != order_id);
+                if ids.is_empty() {
+                    self.by_oco.remove(&key);
+                }
+            }
+        }
+        if let (Some((direction, trigger)), Some(index)) =
+            (order.trigger_key(), self.by_trigger.get_mut(&order.payload.symbol))
+        {
+            index.remove(direction, trigger, order_id);
+        }
         Some(order)
     }
 
@@ -39,6 +113,29 @@ impl PendingOrderStore {
         self.orders.get(&order_id)
     }
 
+    /// Amend the trigger and size of a resting order in place (id, owner and expiry are kept).
+    pub fn modify(&mut self, change: &ModifyOrderPayload) -> Option<&PendingOrder> {
+        let order = self.orders.get_mut(&change.order_id)?;
+        let index = self.by_trigger.entry(order.payload.symbol.clone()).or_default();
+        if let Some((direction, trigger)) = order.trigger_key() {
+            index.remove(direction, trigger, order.id);
+        }
+        let payload = &mut order.payload;
+        if let Some(trigger) = change.trigger_price {
+            payload.trigger_price = Some(trigger);
+        }
+        if let Some(qty) = change.qty {
+            payload.qty = Some(qty);
+        }
+        if let Some(size) = change.size_delta_usd {
+            payload.size_delta_usd = Some(size);
+        }
+        if let Some((direction, trigger)) = order.trigger_key() {
+            index.insert(direction, trigger, order.id);
+        }
+        Some(order)
+    }
+
     pub fn get_by_symbol(&self, symbol: &str) -> Vec<&PendingOrder> {
         self.by_symbol
             .get(symbol)
@@ -46,6 +143,53 @@ impl PendingOrderStore {
             .unwrap_or_default()
     }
 
+    /// Orders on `symbol` triggered at `price`, via the trigger index.
+    #[allow(dead_code)]
+    pub fn triggered(&self, symbol: &str, price: &Price) -> Vec<&PendingOrder> {
+        let Some(index) = self.by_trigger.get(symbol) else {
+            return Vec::new();
+        };
+        index
+            .candidates(price)
+            .filter_map(|id| self.orders.get(&id))
+            .filter(|order| trigger_checker::is_triggered(order, price))
+            .collect()
+    }
+
+    /// Move the triggers of trailing stops on `symbol` after a new price.
+    /// Returns how many triggers moved.
+    pub fn update_trailing(&mut self, symbol: &str, price: &Price) -> usize {
+        let Some(ids) = self.by_symbol.get(symbol) else {
+            return 0;
+        };
+        let index = self.by_trigger.entry(symbol.to_string()).or_default();
+        let mut moved = 0;
+        for id in ids {
+            let Some(order) = self.orders.get_mut(id) else {
+                continue;
+            };
+            let payload = &mut order.payload;
+            if payload.execution_type != ExecutionType::TrailingStop {
+                continue;
+            }
+            let distance = payload.trailing_distance.unwrap_or(0);
+            let trigger = trigger_checker::trail_trigger(payload.side, distance, payload.trigger_price, price);
+            if payload.trigger_price != Some(trigger) {
+                let direction =
+                    trigger_checker::trigger_direction(payload.execution_type, payload.order_type, payload.side);
+                if let Some(direction) = direction {
+                    if let Some(old) = payload.trigger_price {
+                        index.remove(direction, old, *id);
+                    }
+                    index.insert(direction, trigger, *id);
+                }
+                payload.trigger_price = Some(trigger);
+                moved += 1;
+            }
+        }
+        moved
+    }
+
     pub fn remove_expired(&mut self, now_ns: u64) -> Vec<PendingOrder> {
         let expired_ids: Vec<OrderId> = self.orders
             .iter()
@@ -54,6 +198,17 @@ impl PendingOrderStore {
             .collect();
         expired_ids.iter().filter_map(|&id| self.remove(id)).collect()
     }
+
+    /// Remove the remaining orders of an OCO group.
+    pub fn remove_oco_group(&mut self, owner: AgentId, group: u64) -> Vec<PendingOrder> {
+        let ids = self.by_oco.remove(&(owner, group)).unwrap_or_default();
+        ids.iter().filter_map(|&id| self.remove(id)).collect()
+    }
+
+    pub fn remove_by_owner(&mut self, owner: AgentId) -> Vec<PendingOrder> {
+        let ids = self.by_owner.remove(&owner).unwrap_or_default();
+        ids.iter().filter_map(|&id| self.remove(id)).collect()
+    }
 }
 
 impl Default for PendingOrderStore {
@@ -61,3 +216,90 @@ impl Default for PendingOrderStore {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{OrderType, Side};
+    use crate::qty::Qty;
+
+    /// Limit orders open a position, the others close it
+    fn order(id: OrderId, execution_type: ExecutionType, side: Side, trigger: u64) -> PendingOrder {
+        let order_type = if execution_type == ExecutionType::Limit {
+            OrderType::Increase
+        } else {
+            OrderType::Decrease
+        };
+        PendingOrder {
+            id,
+            owner: 1,
+            payload: OrderPayload {
+                symbol: "ETH-USD".to_string(),
+                side,
+                order_type,
+                execution_type,
+                qty: Some(Qty::from_tokens(1.0)),
+                leverage: Some(5),
+                size_delta_usd: None,
+                trigger_price: Some(trigger),
+                acceptable_price: None,
+                trailing_distance: Some(100),
+                valid_for_sec: None,
+                oco_group: None,
+                decision_price: None,
+            },
+            valid_until_ns: u64::MAX,
+        }
+    }
+
+    fn at(p: u64) -> Price {
+        Price { min: p, max: p }
+    }
+
+    fn triggered_ids(store: &PendingOrderStore, price: Price) -> Vec<OrderId> {
+        let mut ids: Vec<OrderId> = store.triggered("ETH-USD", &price).iter().map(|o| o.id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_triggered_by_price_range() {
+        let mut store = PendingOrderStore::new();
+        // Long entry and stop loss below the price, take profit and short entry above
+        store.insert(order(1, ExecutionType::Limit, Side::Buy, 2900));
+        store.insert(order(2, ExecutionType::StopLoss, Side::Buy, 2800));
+        store.insert(order(3, ExecutionType::TakeProfit, Side::Buy, 3200));
+        store.insert(order(4, ExecutionType::Limit, Side::Sell, 3100));
+
+        assert!(triggered_ids(&store, at(3000)).is_empty());
+        assert_eq!(triggered_ids(&store, at(2900)), vec![1]);
+        assert_eq!(triggered_ids(&store, at(2750)), vec![1, 2]);
+        assert_eq!(triggered_ids(&store, at(3250)), vec![3, 4]);
+        // A wide spread: limit Buy checks max, stop loss checks min
+        assert_eq!(triggered_ids(&store, Price { min: 2790, max: 2950 }), vec![2]);
+        assert!(store.triggered("BTC-USD", &at(3000)).is_empty());
+    }
+
+    #[test]
+    fn test_index_follows_remove_modify_and_trailing() {
+        let mut store = PendingOrderStore::new();
+        store.insert(order(1, ExecutionType::StopLoss, Side::Buy, 2800));
+        store.insert(order(2, ExecutionType::TrailingStop, Side::Buy, 2900));
+
+        store.modify(&ModifyOrderPayload {
+            order_id: 1,
+            trigger_price: Some(2950),
+            qty: None,
+            size_delta_usd: None,
+        });
+        assert_eq!(triggered_ids(&store, at(2940)), vec![1]);
+
+        // The trail moves up to 3100
+        assert_eq!(store.update_trailing("ETH-USD", &at(3200)), 1);
+        assert_eq!(triggered_ids(&store, at(3050)), vec![2]);
+
+        store.remove(1);
+        store.remove(2);
+        assert!(triggered_ids(&store, at(1000)).is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+// Central per-account PnL accounting on top of EventBus.
+//
+// The tracker consumes authoritative execution events (OrderExecuted,
+// PositionLiquidated, PositionSnapshot) and keeps one ledger per account.
+// Agents read it through a cloneable `PnlBook` handle instead of keeping
+// their own bookkeeping. Values are in micro-USD (1e6 = $1).
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::create_dir_all;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::{EventKind, EventListener, SimEvent};
+use crate::messages::{AgentId, Side};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AccountPnl {
+    pub realized_pnl: i64,
+    pub unrealized_pnl: i64,
+    pub fees_paid: u64,
+    /// Funding settled by the trader (+ = paid, - = received)
+    pub funding_paid: i64,
+    pub trades: u64,
+    pub volume_usd: u64,
+    /// Notional of currently open positions
+    pub open_notional_usd: u64,
+}
+
+impl AccountPnl {
+    /// Realized + unrealized PnL after fees and funding.
+    pub fn net_pnl(&self) -> i64 {
+        self.realized_pnl + self.unrealized_pnl - self.fees_paid as i64 - self.funding_paid
+    }
+}
+
+/// Shared read handle to the tracker's ledgers.
+#[derive(Clone, Default)]
+pub struct PnlBook {
+    inner: Arc<Mutex<BTreeMap<AgentId, AccountPnl>>>,
+}
+
+impl PnlBook {
+    /// PnL ledger of one account (None if it never traded).
+    pub fn get(&self, account: AgentId) -> Option<AccountPnl> {
+        self.inner.lock().ok()?.get(&account).cloned()
+    }
+
+    /// All ledgers, ordered by account.
+    pub fn all(&self) -> Vec<(AgentId, AccountPnl)> {
+        match self.inner.lock() {
+            Ok(book) => book.iter().map(|(id, pnl)| (*id, pnl.clone())).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn update<F: FnOnce(&mut AccountPnl)>(&self, account: AgentId, f: F) {
+        if let Ok(mut book) = self.inner.lock() {
+            f(book.entry(account).or_default());
+        }
+    }
+}
+
+/// PnL tracker listener: logs/pnl.csv is written at the end of the run.
+pub struct PnlTracker {
+    dir: PathBuf,
+    book: PnlBook,
+    /// Latest snapshot per (account, side): (snapshot ts, unrealized pnl, size_usd)
+    open: HashMap<(AgentId, Side), (u64, i64, u64)>,
+}
+
+impl PnlTracker {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            book: PnlBook::default(),
+            open: HashMap::new(),
+        }
+    }
+
+    /// Handle for agents / reports to query the ledgers.
+    pub fn book(&self) -> PnlBook {
+        self.book.clone()
+    }
+
+    fn refresh_unrealized(&mut self, account: AgentId) {
+        let (unrealized, notional) = self
+            .open
+            .iter()
+            .filter(|((id, _), _)| *id == account)
+            .fold((0i64, 0u64), |(pnl_acc, size_acc), (_, (_, pnl, size))| {
+                (pnl_acc + pnl, size_acc + size)
+            });
+        self.book.update(account, |p| {
+            p.unrealized_pnl = unrealized;
+            p.open_notional_usd = notional;
+        });
+    }
+
+    fn write_csv(&self) -> std::io::Result<()> {
+        create_dir_all(&self.dir)?;
+        let mut file = std::fs::File::create(self.dir.join("pnl.csv"))?;
+        writeln!(
+            file,
+            "account,trades,volume_usd,realized_pnl,unrealized_pnl,fees_paid,funding_paid,net_pnl"
+        )?;
+        for (account, p) in self.book.all() {
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{},{}",
+                account,
+                p.trades,
+                p.volume_usd,
+                p.realized_pnl,
+                p.unrealized_pnl,
+                p.fees_paid,
+                p.funding_paid,
+                p.net_pnl()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl EventListener for PnlTracker {
+    fn on_event(&mut self, event: &SimEvent) {
+        match event {
+            SimEvent::OrderExecuted {
+                account,
+                size_usd,
+                order_type,
+                pnl,
+                fee_usd,
+                funding_fee_usd,
+                ..
+            } => {
+                let closing = order_type != "Increase";
+                self.book.update(*account, |p| {
+                    p.trades += 1;
+                    p.volume_usd += size_usd;
+                    p.fees_paid += fee_usd;
+                    p.funding_paid += funding_fee_usd;
+                    if closing {
+                        p.realized_pnl += pnl;
+                    }
+                });
+            }
+
+            SimEvent::PositionLiquidated { account, side, pnl, .. } => {
+                self.open.remove(&(*account, *side));
+                self.book.update(*account, |p| p.realized_pnl += pnl);
+                self.refresh_unrealized(*account);
+            }
+
+            SimEvent::PositionSnapshot {
+                ts,
+                account,
+                side,
+                size_usd,
+                unrealized_pnl,
+                ..
+            } => {
+                self.open.insert((*account, *side), (*ts, *unrealized_pnl, *size_usd));
+                self.refresh_unrealized(*account);
+            }
+
+            SimEvent::MarketSnapshot { ts, .. } => {
+                // End of a snapshot batch: positions not reported any more are closed
+                let stale: Vec<(AgentId, Side)> = self
+                    .open
+                    .iter()
+                    .filter(|(_, (snap_ts, _, _))| snap_ts < ts)
+                    .map(|(key, _)| *key)
+                    .collect();
+                for key in stale {
+                    self.open.remove(&key);
+                    self.refresh_unrealized(key.0);
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    fn interests(&self) -> Option<&'static [EventKind]> {
+        Some(&[
+            EventKind::OrderExecuted,
+            EventKind::PositionLiquidated,
+            EventKind::PositionSnapshot,
+            EventKind::MarketSnapshot,
+        ])
+    }
+
+    fn on_finish(&mut self, _ts: u64) {
+        match self.write_csv() {
+            Ok(()) => println!("[PnlTracker] written to {}/pnl.csv", self.dir.display()),
+            Err(e) => eprintln!("[PnlTracker] failed to write pnl.csv: {e}"),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+// Venue price rules of a market.
+//
+// The exchange checks every price an order carries (trigger and acceptable
+// price) at submission and amendment: it has to be a multiple of the market's
+// tick size and lie within the band around the current oracle mid. Orders
+// breaking a rule are rejected with a `price_rule:<rule>,...` reason instead
+// of resting far off-market and distorting the fills and impact figures.
+
+use serde::{Deserialize, Serialize};
+
+/// Tick size and price band of one market; 0 disables a rule. Prices in micro-USD.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PriceRules {
+    /// Order prices must be multiples of this (e.g. 10_000 = $0.01)
+    #[serde(default)]
+    pub tick_size: u64,
+    /// Largest distance of an order price from the oracle mid, in bps
+    #[serde(default)]
+    pub band_bps: u32,
+}
+
+/// Rule an order price breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceViolation {
+    OffTick { price: u64, tick_size: u64 },
+    OutsideBand { price: u64, min: u64, max: u64 },
+}
+
+impl PriceViolation {
+    pub fn rule(&self) -> &'static str {
+        match self {
+            PriceViolation::OffTick { .. } => "tick_size",
+            PriceViolation::OutsideBand { .. } => "price_band",
+        }
+    }
+
+    /// Rejection text sent to the agent
+    pub fn reason(&self) -> String {
+        match self {
+            PriceViolation::OffTick { price, tick_size } => {
+                format!("price_rule:tick_size,price:{},tick_size:{}", price, tick_size)
+            }
+            PriceViolation::OutsideBand { price, min, max } => {
+                format!("price_rule:price_band,price:{},min:{},max:{}", price, min, max)
+            }
+        }
+    }
+}
+
+impl PriceRules {
+    pub fn is_enabled(&self) -> bool {
+        self.tick_size > 0 || self.band_bps > 0
+    }
+
+    /// Check an order price against the rules; the band needs the oracle mid (None = not priced yet, not checked).
+    pub fn check(&self, price: u64, mid: Option<u64>) -> Result<(), PriceViolation> {
+        if self.tick_size > 0 && price % self.tick_size != 0 {
+            return Err(PriceViolation::OffTick {
+                price,
+                tick_size: self.tick_size,
+            });
+        }
+        if let (true, Some(mid)) = (self.band_bps > 0, mid) {
+            let width = (mid as u128 * self.band_bps as u128 / 10_000) as u64;
+            let (min, max) = (mid.saturating_sub(width), mid.saturating_add(width));
+            if price < min || price > max {
+                return Err(PriceViolation::OutsideBand { price, min, max });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_rules_accept_any_price() {
+        let rules = PriceRules::default();
+        assert!(!rules.is_enabled());
+        assert!(rules.check(3_000_123_457, Some(1)).is_ok());
+    }
+
+    #[test]
+    fn test_tick_size() {
+        let rules = PriceRules {
+            tick_size: 10_000,
+            band_bps: 0,
+        };
+        assert!(rules.check(3_000_010_000, None).is_ok());
+        let violation = rules.check(3_000_015_000, None).unwrap_err();
+        assert_eq!(violation.rule(), "tick_size");
+        assert_eq!(
+            violation.reason(),
+            "price_rule:tick_size,price:3000015000,tick_size:10000"
+        );
+    }
+
+    #[test]
+    fn test_band_around_mid() {
+        // 5% around $3000
+        let rules = PriceRules {
+            tick_size: 0,
+            band_bps: 500,
+        };
+        let mid = Some(3_000_000_000);
+        assert!(rules.check(2_850_000_000, mid).is_ok());
+        assert!(rules.check(3_150_000_000, mid).is_ok());
+        assert_eq!(
+            rules.check(3_150_000_001, mid),
+            Err(PriceViolation::OutsideBand {
+                price: 3_150_000_001,
+                min: 2_850_000_000,
+                max: 3_150_000_000,
+            })
+        );
+        assert!(rules.check(1, mid).is_err());
+        // No oracle price yet
+        assert!(rules.check(1, None).is_ok());
+    }
+}
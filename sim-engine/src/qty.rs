@@ -0,0 +1,200 @@
+use std::fmt;
+use std::ops::{Add, AddAssign};
+use std::str::FromStr;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Token amount of an order in fixed point: one token is `Qty::SCALE` atoms, so sub-token
+/// sizes (0.25 ETH, 0.001 BTC) are exact and payloads, logs and the API agree on the amount.
+/// Serialized as decimal tokens (`0.5`); deserialized from a number or a decimal string.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Qty(u64);
+
+impl Qty {
+    /// Atoms per token
+    pub const SCALE: u64 = 1_000_000_000;
+    pub const ZERO: Qty = Qty(0);
+
+    pub const fn from_atoms(atoms: u64) -> Self {
+        Self(atoms)
+    }
+
+    /// Nearest amount to `tokens`; negative and non-finite input gives zero.
+    pub fn from_tokens(tokens: f64) -> Self {
+        if !tokens.is_finite() || tokens <= 0.0 {
+            return Self::ZERO;
+        }
+        // `as` saturates at u64::MAX
+        Self((tokens * Self::SCALE as f64).round() as u64)
+    }
+
+    pub const fn atoms(self) -> u64 {
+        self.0
+    }
+
+    pub fn tokens(self) -> f64 {
+        self.0 as f64 / Self::SCALE as f64
+    }
+
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Value at `price` per token, in the price's unit (micro-USD), rounded down.
+    pub fn notional(self, price: u64) -> u64 {
+        let value = self.0 as u128 * price as u128 / Self::SCALE as u128;
+        value.min(u64::MAX as u128) as u64
+    }
+
+    pub fn saturating_sub(self, other: Qty) -> Qty {
+        Qty(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Add for Qty {
+    type Output = Qty;
+
+    fn add(self, other: Qty) -> Qty {
+        Qty(self.0.saturating_add(other.0))
+    }
+}
+
+impl AddAssign for Qty {
+    fn add_assign(&mut self, other: Qty) {
+        *self = *self + other;
+    }
+}
+
+impl fmt::Display for Qty {
+    /// Decimal tokens without trailing zeros: `2`, `0.5`, `0.000000001`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let whole = self.0 / Self::SCALE;
+        let frac = self.0 % Self::SCALE;
+        if frac == 0 {
+            return write!(f, "{}", whole);
+        }
+        let digits = format!("{:09}", frac);
+        write!(f, "{}.{}", whole, digits.trim_end_matches('0'))
+    }
+}
+
+impl FromStr for Qty {
+    type Err = String;
+
+    /// Exact decimal tokens, at most 9 decimals ("1", "0.25", ".5")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+        let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if (whole.is_empty() && frac.is_empty()) || !is_digits(whole) || !is_digits(frac) {
+            return Err(format!("invalid qty '{}'", s));
+        }
+        if frac.len() > 9 {
+            return Err(format!("qty '{}' has more than 9 decimals", s));
+        }
+        let whole: u64 = if whole.is_empty() {
+            0
+        } else {
+            whole.parse().map_err(|_| format!("qty '{}' is too large", s))?
+        };
+        let frac: u64 = format!("{:0<9}", frac).parse().unwrap_or(0);
+        whole
+            .checked_mul(Self::SCALE)
+            .and_then(|atoms| atoms.checked_add(frac))
+            .map(Qty)
+            .ok_or_else(|| format!("qty '{}' is too large", s))
+    }
+}
+
+impl Serialize for Qty {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.tokens())
+    }
+}
+
+struct QtyVisitor;
+
+impl Visitor<'_> for QtyVisitor {
+    type Value = Qty;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a non-negative token amount (number or decimal string)")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Qty, E> {
+        v.checked_mul(Qty::SCALE)
+            .map(Qty)
+            .ok_or_else(|| E::custom(format!("qty {} is too large", v)))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Qty, E> {
+        let v = u64::try_from(v).map_err(|_| E::custom(format!("qty {} is negative", v)))?;
+        self.visit_u64(v)
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Qty, E> {
+        if !v.is_finite() || v < 0.0 {
+            return Err(E::custom(format!("qty {} is not a non-negative number", v)));
+        }
+        Ok(Qty::from_tokens(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Qty, E> {
+        v.parse().map_err(E::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Qty {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(QtyVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_round_trip() {
+        assert_eq!(Qty::from_tokens(0.5).atoms(), 500_000_000);
+        assert_eq!(Qty::from_tokens(0.1).atoms(), 100_000_000);
+        assert_eq!(Qty::from_tokens(2.0).tokens(), 2.0);
+        assert_eq!(Qty::from_tokens(-1.0), Qty::ZERO);
+        assert_eq!(Qty::from_tokens(f64::NAN), Qty::ZERO);
+    }
+
+    #[test]
+    fn test_display_and_parse() {
+        assert_eq!(Qty::from_atoms(2 * Qty::SCALE).to_string(), "2");
+        assert_eq!(Qty::from_tokens(0.25).to_string(), "0.25");
+        assert_eq!(Qty::from_atoms(1).to_string(), "0.000000001");
+        assert_eq!("0.25".parse::<Qty>(), Ok(Qty::from_atoms(250_000_000)));
+        assert_eq!(".5".parse::<Qty>(), Ok(Qty::from_atoms(500_000_000)));
+        assert_eq!("3".parse::<Qty>(), Ok(Qty::from_atoms(3 * Qty::SCALE)));
+        assert!("0.0000000001".parse::<Qty>().is_err());
+        assert!("-1".parse::<Qty>().is_err());
+        assert!("1e3".parse::<Qty>().is_err());
+        assert!(".".parse::<Qty>().is_err());
+    }
+
+    #[test]
+    fn test_notional_is_exact() {
+        // 0.1 ETH at $3000.000001
+        let qty: Qty = "0.1".parse().unwrap();
+        assert_eq!(qty.notional(3_000_000_001), 300_000_000);
+        assert_eq!(Qty::from_atoms(u64::MAX).notional(u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_serde_accepts_numbers_and_strings() {
+        let qty: Qty = serde_json::from_str("1.5").unwrap();
+        assert_eq!(qty.atoms(), 1_500_000_000);
+        let qty: Qty = serde_json::from_str("2").unwrap();
+        assert_eq!(qty.atoms(), 2_000_000_000);
+        let qty: Qty = serde_json::from_str("\"0.001\"").unwrap();
+        assert_eq!(qty.atoms(), 1_000_000);
+        assert!(serde_json::from_str::<Qty>("-1").is_err());
+        assert_eq!(serde_json::to_string(&Qty::from_tokens(0.5)).unwrap(), "0.5");
+    }
+}
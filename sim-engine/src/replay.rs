@@ -0,0 +1,208 @@
+// Message traces and deterministic replay.
+//
+// With `logging.trace` the kernel writes every delivered message to
+// logs/trace.jsonl: a header line with the start time and tick, then one
+// `Message` per line in delivery order. A replay rebuilds only the agents
+// listed as live and feeds them the recorded messages of everyone else, so a
+// single agent can be swapped for a patched build while the rest of the run
+// replays identically. Messages the live agents send to recorded agents are
+// not delivered; they are compared with what the original agent sent.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::{create_dir_all, File};
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::messages::{AgentId, Message};
+
+pub const TRACE_FILE: &str = "trace.jsonl";
+
+/// Divergences printed in the replay summary
+const MAX_REPORTED_DIVERGENCES: usize = 10;
+
+/// First line of a trace: kernel clock at the time agents were registered.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TraceHeader {
+    pub start_ns: u64,
+    pub tick_ns: u64,
+}
+
+/// Writes delivered messages to logs/trace.jsonl.
+pub struct TraceWriter {
+    out: BufWriter<File>,
+}
+
+impl TraceWriter {
+    pub fn new(dir: &Path, header: TraceHeader) -> std::io::Result<Self> {
+        create_dir_all(dir)?;
+        let mut out = BufWriter::new(File::create(dir.join(TRACE_FILE))?);
+        serde_json::to_writer(&mut out, &header)?;
+        out.write_all(b"\n")?;
+        Ok(Self { out })
+    }
+
+    pub fn record(&mut self, msg: &Message) {
+        if serde_json::to_writer(&mut self.out, msg).is_ok() {
+            let _ = self.out.write_all(b"\n");
+        }
+    }
+
+    pub fn finish(&mut self) {
+        let _ = self.out.flush();
+    }
+}
+
+/// Recorded messages of a trace, consumed by the kernel in delivery order.
+pub struct Replay {
+    lines: Lines<BufReader<File>>,
+    next: Option<Message>,
+    live: HashSet<AgentId>,
+    /// Per (from, to): recorded messages of live agents not yet matched by a live send
+    expected: HashMap<(AgentId, AgentId), VecDeque<Message>>,
+    /// Per (from, to): live sends not yet matched by a recorded message
+    actual: HashMap<(AgentId, AgentId), VecDeque<Message>>,
+    delivered: u64,
+    matched: u64,
+    divergences: Vec<String>,
+    diverged: u64,
+}
+
+impl Replay {
+    pub fn open(path: &Path, live: HashSet<AgentId>) -> Result<(TraceHeader, Self), String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut lines = BufReader::new(file).lines();
+        let header: TraceHeader = match lines.next() {
+            Some(Ok(line)) => serde_json::from_str(&line).map_err(|e| format!("invalid trace header: {}", e))?,
+            Some(Err(e)) => return Err(e.to_string()),
+            None => return Err(format!("{} is empty", path.display())),
+        };
+        let mut replay = Self {
+            lines,
+            next: None,
+            live,
+            expected: HashMap::new(),
+            actual: HashMap::new(),
+            delivered: 0,
+            matched: 0,
+            divergences: Vec::new(),
+            diverged: 0,
+        };
+        replay.advance();
+        Ok((header, replay))
+    }
+
+    pub fn is_live(&self, id: AgentId) -> bool {
+        self.live.contains(&id)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next.is_none()
+    }
+
+    /// Delivery time of the next recorded message for a live agent.
+    pub fn next_at(&self) -> Option<u64> {
+        self.next.as_ref().map(|msg| msg.at)
+    }
+
+    /// Take the next recorded message for a live agent.
+    pub fn pop(&mut self) -> Option<Message> {
+        let msg = self.next.take()?;
+        self.delivered += 1;
+        self.advance();
+        Some(msg)
+    }
+
+    /// A live agent sent a message to a recorded agent: compare it with the original.
+    pub fn on_live_send(&mut self, msg: Message) {
+        let key = (msg.from, msg.to);
+        match self.expected.get_mut(&key).and_then(|q| q.pop_front()) {
+            Some(recorded) => self.compare(&recorded, &msg),
+            None => self.actual.entry(key).or_default().push_back(msg),
+        }
+    }
+
+    /// Read up to the next message a live agent has to receive. Messages between live
+    /// agents (wakeups included) are produced by the live agents themselves.
+    fn advance(&mut self) {
+        while let Some(msg) = self.read_message() {
+            match (self.is_live(msg.from), self.is_live(msg.to)) {
+                (false, true) => {
+                    self.next = Some(msg);
+                    return;
+                }
+                (true, false) => {
+                    let key = (msg.from, msg.to);
+                    match self.actual.get_mut(&key).and_then(|q| q.pop_front()) {
+                        Some(live) => self.compare(&msg, &live),
+                        None => self.expected.entry(key).or_default().push_back(msg),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn read_message(&mut self) -> Option<Message> {
+        for line in self.lines.by_ref() {
+            match line
+                .map_err(|e| e.to_string())
+                .and_then(|l| serde_json::from_str(&l).map_err(|e| e.to_string()))
+            {
+                Ok(msg) => return Some(msg),
+                Err(e) => eprintln!("[Replay] skipping unreadable trace line: {}", e),
+            }
+        }
+        None
+    }
+
+    fn compare(&mut self, recorded: &Message, live: &Message) {
+        let same = recorded.msg_type == live.msg_type
+            && serde_json::to_value(&recorded.payload).ok() == serde_json::to_value(&live.payload).ok();
+        if same {
+            self.matched += 1;
+        } else {
+            self.diverge(format!(
+                "t={} {} -> {}: recorded {:?} {:?}, replayed {:?} {:?}",
+                live.at, live.from, live.to, recorded.msg_type, recorded.payload, live.msg_type, live.payload
+            ));
+        }
+    }
+
+    fn diverge(&mut self, description: String) {
+        self.diverged += 1;
+        if self.divergences.len() < MAX_REPORTED_DIVERGENCES {
+            self.divergences.push(description);
+        }
+    }
+
+    /// Print the summary; unmatched sends on either side count as divergences.
+    pub fn finish(&mut self) {
+        let missing: Vec<Message> = self.expected.drain().flat_map(|(_, q)| q).collect();
+        for msg in missing {
+            self.diverge(format!(
+                "t={} {} -> {}: recorded {:?} not sent",
+                msg.at, msg.from, msg.to, msg.msg_type
+            ));
+        }
+        let extra: Vec<Message> = self.actual.drain().flat_map(|(_, q)| q).collect();
+        for msg in extra {
+            self.diverge(format!(
+                "t={} {} -> {}: extra {:?}",
+                msg.at, msg.from, msg.to, msg.msg_type
+            ));
+        }
+
+        println!(
+            "[Replay] delivered {} recorded messages, {} live sends matched, {} diverged",
+            self.delivered, self.matched, self.diverged
+        );
+        for d in &self.divergences {
+            println!("[Replay]   {}", d);
+        }
+        if self.diverged == 0 {
+            println!("[Replay] live agents behaved exactly as recorded");
+        }
+    }
+}
@@ -0,0 +1,1065 @@
+// End-of-run summary report on top of EventBus.
+//
+// Aggregates the whole event stream and, when the run finishes, writes
+// logs/summary.json (machine-readable) and logs/summary.txt (human-readable).
+// All money values are in micro-USD (1e6 = $1), same as the CSV logs.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cascades::{CascadeBook, CascadeReport};
+use crate::equity::{EquityBook, EquityStats};
+use crate::events::{EventListener, SimEvent};
+use crate::messages::{AgentId, Side};
+use crate::vara::TX_BUDGET_EXHAUSTED;
+
+/// Gap between two oracle ticks above which the feed is considered down.
+const DEFAULT_ORACLE_STALE_NS: u64 = 10_000_000_000; // 10s
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AgentReport {
+    pub account: AgentId,
+    /// Agent type / strategy label (e.g. "market_maker", "hodler")
+    #[serde(default)]
+    pub kind: String,
+    pub trades: u64,
+    pub closed_trades: u64,
+    pub wins: u64,
+    pub win_rate_pct: f64,
+    pub volume_usd: u64,
+    pub realized_pnl: i64,
+    pub unrealized_pnl: i64,
+    pub total_pnl: i64,
+    pub max_drawdown: i64,
+    pub liquidations: u64,
+    #[serde(default)]
+    pub bankruptcies: u64,
+    /// Sampled equity-curve statistics (if an EquityTracker is attached)
+    #[serde(default)]
+    pub equity: Option<EquityStats>,
+    /// Fills of orders carrying a decision price
+    #[serde(default)]
+    pub slippage: Option<SlippageStats>,
+}
+
+/// Fill prices against the oracle mid the agent decided at, in bps (+ = worse for the agent).
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SlippageStats {
+    pub fills: u64,
+    pub mean_bps: f64,
+    pub p50_bps: f64,
+    pub p95_bps: f64,
+    /// Worst fill
+    pub max_bps: f64,
+}
+
+impl SlippageStats {
+    fn from_samples(samples: &[f64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let n = sorted.len();
+        let quantile = |q: f64| sorted[((q * n as f64).ceil() as usize).clamp(1, n) - 1];
+        Some(Self {
+            fills: n as u64,
+            mean_bps: sorted.iter().sum::<f64>() / n as f64,
+            p50_bps: quantile(0.5),
+            p95_bps: quantile(0.95),
+            max_bps: sorted[n - 1],
+        })
+    }
+}
+
+/// Slippage of a fill at `execution_price` against `decision_price`, in bps; + = worse for the agent.
+fn slippage_bps(buying: bool, decision_price: u64, execution_price: u64) -> f64 {
+    let diff = execution_price as f64 - decision_price as f64;
+    let sign = if buying { 1.0 } else { -1.0 };
+    sign * diff * 10_000.0 / decision_price as f64
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct OiPoint {
+    pub ts: u64,
+    pub symbol: String,
+    pub oi_long_usd: u64,
+    pub oi_short_usd: u64,
+    /// bps*1e6 per hour, + = longs pay
+    #[serde(default)]
+    pub funding_rate_bps_hour_fp: i64,
+    /// bps*1e6 per hour
+    #[serde(default)]
+    pub borrowing_rate_bps_hour_fp: u64,
+}
+
+/// Funding and borrowing rates of one market over the market snapshots (bps per hour).
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FeeRateReport {
+    pub symbol: String,
+    pub samples: u64,
+    pub last_funding_bps_hour: f64,
+    pub avg_funding_bps_hour: f64,
+    /// Largest |funding rate| seen, signed
+    pub peak_funding_bps_hour: f64,
+    pub last_borrowing_bps_hour: f64,
+    pub avg_borrowing_bps_hour: f64,
+    pub max_borrowing_bps_hour: f64,
+}
+
+/// On-chain transactions of one type and how they ended.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ChainTxReport {
+    pub tx_type: String,
+    pub total: u64,
+    pub failed: u64,
+    pub failure_rate_pct: f64,
+    pub avg_latency_ms: f64,
+    pub max_latency_ms: u64,
+    /// Sum of the priority tips offered
+    #[serde(default)]
+    pub tips: u128,
+    /// Successes later reverted by a simulated reorg (counted in `total` once)
+    #[serde(default)]
+    pub reverted: u64,
+    /// Sum of the measured native fees (`vara.tx_spend`)
+    #[serde(default)]
+    pub fees: u128,
+}
+
+/// Measured native fees of one agent's transactions (`vara.tx_spend`).
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ChainSpendReport {
+    pub agent: AgentId,
+    /// Transactions with a measured fee
+    pub txs: u64,
+    pub fees: u128,
+    /// Transactions refused because the agent's budget was spent
+    pub refused: u64,
+}
+
+/// Insurance fund balance of a market at one of its market snapshots.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FundPoint {
+    pub ts: u64,
+    pub balance: i64,
+}
+
+/// Insurance fund of one market (the pool's realized PnL) over the run.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct InsuranceFundReport {
+    pub symbol: String,
+    pub balance: i64,
+    pub min_balance: i64,
+    pub max_balance: i64,
+    /// Net flow per reason ("position_fee", "liquidation", "trader_pnl", "cross_margin")
+    pub flows: BTreeMap<String, i64>,
+    /// Balance at every market snapshot
+    pub trajectory: Vec<FundPoint>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct OracleReport {
+    pub symbol: String,
+    pub ticks: u64,
+    pub max_gap_ms: u64,
+    pub uptime_pct: f64,
+}
+
+/// Price provider switch of an oracle agent.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct OracleFailoverReport {
+    pub ts: u64,
+    pub oracle: AgentId,
+    pub from: String,
+    pub to: String,
+    pub reason: String,
+}
+
+/// Halt of a market by its circuit breaker.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerReport {
+    pub ts: u64,
+    pub symbol: String,
+    pub from_price: u64,
+    pub to_price: u64,
+    pub move_bps: u64,
+    pub halted_until: u64,
+}
+
+/// Realtime tick that ran past the watchdog limit.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TickOverrunReport {
+    pub ts: u64,
+    pub tick: u64,
+    pub elapsed_ms: u64,
+    pub limit_ms: u64,
+    /// Agent and message of the longest handler in the tick
+    pub agent: Option<AgentId>,
+    pub msg_type: Option<String>,
+    pub handler_ms: u64,
+}
+
+/// Messages of one type for one unregistered agent id (see `DeadLetterPolicy`).
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DeadLetterReport {
+    pub to: AgentId,
+    pub msg_type: String,
+    pub count: u64,
+    /// Given up on (the rest was delivered after a retry, or failed the run)
+    pub dropped: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub scenario_name: String,
+    pub start_ts: u64,
+    pub end_ts: u64,
+    pub duration_sec: f64,
+    pub total_trades: u64,
+    pub total_volume_usd: u64,
+    pub total_liquidations: u64,
+    #[serde(default)]
+    pub total_bankruptcies: u64,
+    /// Liquidation cascades (if a CascadeTracker is attached)
+    #[serde(default)]
+    pub liquidation_cascades: Vec<CascadeReport>,
+    /// Deepest cascade (0 without cascades)
+    #[serde(default)]
+    pub max_cascade_depth: u32,
+    /// Position fees collected on executions
+    pub fee_revenue_usd: u64,
+    /// Fees charged to traders per kind ("position_fee", "liquidation")
+    #[serde(default)]
+    pub fees_by_kind: BTreeMap<String, u64>,
+    pub agents: Vec<AgentReport>,
+    pub oracles: Vec<OracleReport>,
+    pub oi_timeline: Vec<OiPoint>,
+    #[serde(default)]
+    pub fee_rates: Vec<FeeRateReport>,
+    #[serde(default)]
+    pub chain_txs: Vec<ChainTxReport>,
+    #[serde(default)]
+    pub chain_spend: Vec<ChainSpendReport>,
+    #[serde(default)]
+    pub insurance_funds: Vec<InsuranceFundReport>,
+    #[serde(default)]
+    pub oracle_failovers: Vec<OracleFailoverReport>,
+    #[serde(default)]
+    pub dead_letters: Vec<DeadLetterReport>,
+    #[serde(default)]
+    pub circuit_breakers: Vec<CircuitBreakerReport>,
+    #[serde(default)]
+    pub tick_overruns: Vec<TickOverrunReport>,
+}
+
+#[derive(Default)]
+struct AgentStats {
+    trades: u64,
+    closed_trades: u64,
+    wins: u64,
+    volume_usd: u64,
+    realized_pnl: i64,
+    /// Latest unrealized PnL per side: (snapshot ts, pnl)
+    unrealized: HashMap<Side, (u64, i64)>,
+    peak_equity: i64,
+    max_drawdown: i64,
+    liquidations: u64,
+    bankruptcies: u64,
+    /// Per fill with a decision price (bps)
+    slippage_bps: Vec<f64>,
+}
+
+impl AgentStats {
+    fn unrealized_pnl(&self) -> i64 {
+        self.unrealized.values().map(|(_, pnl)| *pnl).sum()
+    }
+
+    /// Update peak/drawdown from current equity (realized + unrealized).
+    fn mark(&mut self) {
+        let equity = self.realized_pnl + self.unrealized_pnl();
+        if equity > self.peak_equity {
+            self.peak_equity = equity;
+        }
+        let dd = self.peak_equity - equity;
+        if dd > self.max_drawdown {
+            self.max_drawdown = dd;
+        }
+    }
+}
+
+#[derive(Default)]
+struct OracleStats {
+    ticks: u64,
+    first_ts: u64,
+    last_ts: u64,
+    max_gap_ns: u64,
+    /// Time spent in gaps longer than the stale threshold
+    down_ns: u64,
+}
+
+#[derive(Default)]
+struct FundStats {
+    balance: i64,
+    min_balance: i64,
+    max_balance: i64,
+    flows: BTreeMap<String, i64>,
+    trajectory: Vec<FundPoint>,
+}
+
+#[derive(Default)]
+struct ChainTxStats {
+    total: u64,
+    failed: u64,
+    latency_sum_ms: u64,
+    max_latency_ms: u64,
+    tips: u128,
+    reverted: u64,
+    fees: u128,
+}
+
+/// Summary report listener: subscribe it to the EventBus, files are written in `on_finish`.
+pub struct ReportBuilder {
+    dir: PathBuf,
+    scenario_name: String,
+    oracle_stale_ns: u64,
+    start_ts: Option<u64>,
+    agents: BTreeMap<AgentId, AgentStats>,
+    oracles: BTreeMap<String, OracleStats>,
+    chain_txs: BTreeMap<String, ChainTxStats>,
+    chain_spend: BTreeMap<AgentId, ChainSpendReport>,
+    funds: BTreeMap<String, FundStats>,
+    oracle_failovers: Vec<OracleFailoverReport>,
+    /// (to, msg_type) -> (messages, dropped)
+    dead_letters: BTreeMap<(AgentId, String), (u64, u64)>,
+    circuit_breakers: Vec<CircuitBreakerReport>,
+    tick_overruns: Vec<TickOverrunReport>,
+    oi_timeline: Vec<OiPoint>,
+    total_volume_usd: u64,
+    total_fees_usd: u64,
+    fees_by_kind: BTreeMap<String, u64>,
+    equity_book: Option<EquityBook>,
+    cascade_book: Option<CascadeBook>,
+    agent_kinds: HashMap<AgentId, String>,
+}
+
+impl ReportBuilder {
+    pub fn new<P: AsRef<Path>>(dir: P, scenario_name: &str) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            scenario_name: scenario_name.to_string(),
+            oracle_stale_ns: DEFAULT_ORACLE_STALE_NS,
+            start_ts: None,
+            agents: BTreeMap::new(),
+            oracles: BTreeMap::new(),
+            chain_txs: BTreeMap::new(),
+            chain_spend: BTreeMap::new(),
+            funds: BTreeMap::new(),
+            oracle_failovers: Vec::new(),
+            dead_letters: BTreeMap::new(),
+            circuit_breakers: Vec::new(),
+            tick_overruns: Vec::new(),
+            oi_timeline: Vec::new(),
+            total_volume_usd: 0,
+            total_fees_usd: 0,
+            fees_by_kind: BTreeMap::new(),
+            equity_book: None,
+            cascade_book: None,
+            agent_kinds: HashMap::new(),
+        }
+    }
+
+    /// Label accounts with their agent type so runs can be compared per type.
+    pub fn with_agent_kinds(mut self, kinds: HashMap<AgentId, String>) -> Self {
+        self.agent_kinds = kinds;
+        self
+    }
+
+    /// Include equity-curve statistics; the EquityTracker must be subscribed before this report.
+    pub fn with_equity(mut self, book: EquityBook) -> Self {
+        self.equity_book = Some(book);
+        self
+    }
+
+    /// Include liquidation cascades; the CascadeTracker must be subscribed before this report.
+    pub fn with_cascades(mut self, book: CascadeBook) -> Self {
+        self.cascade_book = Some(book);
+        self
+    }
+
+    fn agent(&mut self, account: AgentId) -> &mut AgentStats {
+        self.agents.entry(account).or_default()
+    }
+
+    /// Build the summary for the run ending at `end_ts`.
+    pub fn build(&self, end_ts: u64) -> RunSummary {
+        let start_ts = self.start_ts.unwrap_or(end_ts);
+
+        let agents: Vec<AgentReport> = self
+            .agents
+            .iter()
+            .map(|(account, s)| {
+                let unrealized_pnl = s.unrealized_pnl();
+                AgentReport {
+                    account: *account,
+                    kind: self.agent_kinds.get(account).cloned().unwrap_or_else(|| "unknown".to_string()),
+                    trades: s.trades,
+                    closed_trades: s.closed_trades,
+                    wins: s.wins,
+                    win_rate_pct: if s.closed_trades > 0 {
+                        s.wins as f64 * 100.0 / s.closed_trades as f64
+                    } else {
+                        0.0
+                    },
+                    volume_usd: s.volume_usd,
+                    realized_pnl: s.realized_pnl,
+                    unrealized_pnl,
+                    total_pnl: s.realized_pnl + unrealized_pnl,
+                    max_drawdown: s.max_drawdown,
+                    liquidations: s.liquidations,
+                    bankruptcies: s.bankruptcies,
+                    equity: self.equity_book.as_ref().and_then(|b| b.get(*account)),
+                    slippage: SlippageStats::from_samples(&s.slippage_bps),
+                }
+            })
+            .collect();
+
+        let oracles = self
+            .oracles
+            .iter()
+            .map(|(symbol, o)| {
+                // Downtime also covers the tail after the last tick
+                let tail = end_ts.saturating_sub(o.last_ts);
+                let tail_down = if tail > self.oracle_stale_ns { tail } else { 0 };
+                let span = end_ts.saturating_sub(o.first_ts);
+                let uptime_pct = if span > 0 {
+                    100.0 * (1.0 - (o.down_ns + tail_down) as f64 / span as f64)
+                } else {
+                    100.0
+                };
+                OracleReport {
+                    symbol: symbol.clone(),
+                    ticks: o.ticks,
+                    max_gap_ms: o.max_gap_ns / 1_000_000,
+                    uptime_pct: uptime_pct.max(0.0),
+                }
+            })
+            .collect();
+
+        let chain_txs = self
+            .chain_txs
+            .iter()
+            .map(|(tx_type, t)| ChainTxReport {
+                tx_type: tx_type.clone(),
+                total: t.total,
+                failed: t.failed,
+                failure_rate_pct: t.failed as f64 * 100.0 / t.total as f64,
+                avg_latency_ms: t.latency_sum_ms as f64 / t.total as f64,
+                max_latency_ms: t.max_latency_ms,
+                tips: t.tips,
+                reverted: t.reverted,
+                fees: t.fees,
+            })
+            .collect();
+
+        let insurance_funds = self
+            .funds
+            .iter()
+            .map(|(symbol, f)| InsuranceFundReport {
+                symbol: symbol.clone(),
+                balance: f.balance,
+                min_balance: f.min_balance,
+                max_balance: f.max_balance,
+                flows: f.flows.clone(),
+                trajectory: f.trajectory.clone(),
+            })
+            .collect();
+
+        let liquidation_cascades = self.cascade_book.as_ref().map(CascadeBook::cascades).unwrap_or_default();
+
+        RunSummary {
+            scenario_name: self.scenario_name.clone(),
+            start_ts,
+            end_ts,
+            duration_sec: end_ts.saturating_sub(start_ts) as f64 / 1e9,
+            total_trades: agents.iter().map(|a| a.trades).sum(),
+            total_volume_usd: self.total_volume_usd,
+            total_liquidations: agents.iter().map(|a| a.liquidations).sum(),
+            total_bankruptcies: agents.iter().map(|a| a.bankruptcies).sum(),
+            max_cascade_depth: liquidation_cascades.iter().map(|c| c.depth).max().unwrap_or(0),
+            liquidation_cascades,
+            fee_revenue_usd: self.total_fees_usd,
+            fees_by_kind: self.fees_by_kind.clone(),
+            agents,
+            oracles,
+            oi_timeline: self.oi_timeline.clone(),
+            fee_rates: fee_rates(&self.oi_timeline),
+            chain_txs,
+            chain_spend: self.chain_spend.values().cloned().collect(),
+            insurance_funds,
+            oracle_failovers: self.oracle_failovers.clone(),
+            dead_letters: self
+                .dead_letters
+                .iter()
+                .map(|((to, msg_type), (count, dropped))| DeadLetterReport {
+                    to: *to,
+                    msg_type: msg_type.clone(),
+                    count: *count,
+                    dropped: *dropped,
+                })
+                .collect(),
+            circuit_breakers: self.circuit_breakers.clone(),
+            tick_overruns: self.tick_overruns.clone(),
+        }
+    }
+
+    fn write(&self, summary: &RunSummary) -> std::io::Result<()> {
+        create_dir_all(&self.dir)?;
+        let json = serde_json::to_string_pretty(summary)?;
+        std::fs::write(self.dir.join("summary.json"), json)?;
+        std::fs::write(self.dir.join("summary.txt"), format_text(summary))?;
+        Ok(())
+    }
+}
+
+/// Per-market rate statistics from the snapshot timeline.
+fn fee_rates(timeline: &[OiPoint]) -> Vec<FeeRateReport> {
+    let mut by_symbol: BTreeMap<&str, Vec<&OiPoint>> = BTreeMap::new();
+    for p in timeline {
+        by_symbol.entry(&p.symbol).or_default().push(p);
+    }
+    by_symbol
+        .into_iter()
+        .map(|(symbol, points)| {
+            let funding: Vec<f64> = points.iter().map(|p| p.funding_rate_bps_hour_fp as f64 / 1e6).collect();
+            let borrowing: Vec<f64> = points
+                .iter()
+                .map(|p| p.borrowing_rate_bps_hour_fp as f64 / 1e6)
+                .collect();
+            let n = points.len() as f64;
+            FeeRateReport {
+                symbol: symbol.to_string(),
+                samples: points.len() as u64,
+                last_funding_bps_hour: funding.last().copied().unwrap_or(0.0),
+                avg_funding_bps_hour: funding.iter().sum::<f64>() / n,
+                peak_funding_bps_hour: funding
+                    .iter()
+                    .copied()
+                    .fold(0.0, |a, r| if r.abs() > a.abs() { r } else { a }),
+                last_borrowing_bps_hour: borrowing.last().copied().unwrap_or(0.0),
+                avg_borrowing_bps_hour: borrowing.iter().sum::<f64>() / n,
+                max_borrowing_bps_hour: borrowing.iter().copied().fold(0.0, f64::max),
+            }
+        })
+        .collect()
+}
+
+fn usd(micro: i64) -> String {
+    format!("${:.2}", micro as f64 / 1_000_000.0)
+}
+
+/// Human-readable report.
+pub fn format_text(s: &RunSummary) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("=== Run summary: {} ===\n", s.scenario_name));
+    out.push_str(&format!("Duration:      {:.1}s\n", s.duration_sec));
+    out.push_str(&format!("Trades:        {}\n", s.total_trades));
+    out.push_str(&format!("Volume:        {}\n", usd(s.total_volume_usd as i64)));
+    out.push_str(&format!("Liquidations:  {}\n", s.total_liquidations));
+    if s.total_bankruptcies > 0 {
+        out.push_str(&format!("Bankruptcies:  {}\n", s.total_bankruptcies));
+    }
+    if !s.liquidation_cascades.is_empty() {
+        out.push_str(&format!(
+            "Cascades:      {} (max depth {})\n",
+            s.liquidation_cascades.len(),
+            s.max_cascade_depth
+        ));
+    }
+    out.push_str(&format!("Fee revenue:   {}\n", usd(s.fee_revenue_usd as i64)));
+    for (kind, amount) in &s.fees_by_kind {
+        out.push_str(&format!("  {:<12} {}\n", kind, usd(*amount as i64)));
+    }
+
+    out.push_str("\n--- Agents ---\n");
+    out.push_str(&format!(
+        "{:>6} {:>7} {:>8} {:>14} {:>14} {:>14} {:>12} {:>5}\n",
+        "agent", "trades", "win%", "realized", "unrealized", "total", "max_dd", "liq"
+    ));
+    for a in &s.agents {
+        out.push_str(&format!(
+            "{:>6} {:>7} {:>7.1}% {:>14} {:>14} {:>14} {:>12} {:>5}\n",
+            a.account,
+            a.trades,
+            a.win_rate_pct,
+            usd(a.realized_pnl),
+            usd(a.unrealized_pnl),
+            usd(a.total_pnl),
+            usd(a.max_drawdown),
+            a.liquidations,
+        ));
+    }
+
+    if s.agents.iter().any(|a| a.equity.is_some()) {
+        out.push_str("\n--- Equity ---\n");
+        out.push_str(&format!(
+            "{:>6} {:>14} {:>8} {:>8} {:>14} {:>14} {:>8}\n",
+            "agent", "final", "dd%", "sharpe", "avg_exposure", "max_exposure", "in_mkt%"
+        ));
+        for a in &s.agents {
+            if let Some(e) = &a.equity {
+                out.push_str(&format!(
+                    "{:>6} {:>14} {:>7.2}% {:>8.3} {:>14} {:>14} {:>7.1}%\n",
+                    a.account,
+                    usd(e.final_equity),
+                    e.max_drawdown_pct,
+                    e.sharpe_like,
+                    usd(e.avg_exposure_usd as i64),
+                    usd(e.max_exposure_usd as i64),
+                    e.time_in_market_pct,
+                ));
+            }
+        }
+    }
+
+    if s.agents.iter().any(|a| a.slippage.is_some()) {
+        out.push_str("\n--- Slippage vs decision mid (bps, + = worse) ---\n");
+        out.push_str(&format!(
+            "{:>6} {:<14} {:>7} {:>8} {:>8} {:>8} {:>8}\n",
+            "agent", "kind", "fills", "mean", "p50", "p95", "max"
+        ));
+        for a in &s.agents {
+            if let Some(sl) = &a.slippage {
+                out.push_str(&format!(
+                    "{:>6} {:<14} {:>7} {:>8.2} {:>8.2} {:>8.2} {:>8.2}\n",
+                    a.account, a.kind, sl.fills, sl.mean_bps, sl.p50_bps, sl.p95_bps, sl.max_bps,
+                ));
+            }
+        }
+    }
+
+    out.push_str("\n--- Oracles ---\n");
+    for o in &s.oracles {
+        out.push_str(&format!(
+            "{:<10} ticks={:<6} max_gap={}ms uptime={:.2}%\n",
+            o.symbol, o.ticks, o.max_gap_ms, o.uptime_pct
+        ));
+    }
+    for f in &s.oracle_failovers {
+        out.push_str(&format!(
+            "{:.1}s oracle {} {}: {} -> {}\n",
+            f.ts.saturating_sub(s.start_ts) as f64 / 1e9,
+            f.oracle,
+            f.reason,
+            f.from,
+            f.to
+        ));
+    }
+
+    if !s.liquidation_cascades.is_empty() {
+        out.push_str("\n--- Liquidation cascades ---\n");
+        out.push_str(&format!(
+            "{:>8} {:<10} {:>6} {:>6} {:>14} {:>12} {:>12}\n",
+            "start", "market", "liqs", "depth", "notional", "from", "to"
+        ));
+        for c in &s.liquidation_cascades {
+            out.push_str(&format!(
+                "{:>7.1}s {:<10} {:>6} {:>6} {:>14} {:>12} {:>12}\n",
+                c.start_ts.saturating_sub(s.start_ts) as f64 / 1e9,
+                c.symbol,
+                c.liquidations,
+                c.depth,
+                usd(c.notional_usd as i64),
+                usd(c.first_price as i64),
+                usd(c.last_price as i64),
+            ));
+        }
+    }
+
+    if !s.circuit_breakers.is_empty() {
+        out.push_str("\n--- Circuit breakers ---\n");
+        for c in &s.circuit_breakers {
+            out.push_str(&format!(
+                "{:.1}s {:<10} {} -> {} ({:.2}%) halted for {:.0}s\n",
+                c.ts.saturating_sub(s.start_ts) as f64 / 1e9,
+                c.symbol,
+                usd(c.from_price as i64),
+                usd(c.to_price as i64),
+                c.move_bps as f64 / 100.0,
+                c.halted_until.saturating_sub(c.ts) as f64 / 1e9
+            ));
+        }
+    }
+
+    if let Some(last) = s.oi_timeline.last() {
+        out.push_str("\n--- Open interest ---\n");
+        out.push_str(&format!(
+            "samples={} last: long={} short={}\n",
+            s.oi_timeline.len(),
+            usd(last.oi_long_usd as i64),
+            usd(last.oi_short_usd as i64),
+        ));
+    }
+
+    if !s.fee_rates.is_empty() {
+        out.push_str("\n--- Fee rates (bps/hour) ---\n");
+        out.push_str(&format!(
+            "{:<10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}\n",
+            "market", "funding", "avg", "peak", "borrowing", "avg", "max"
+        ));
+        for r in &s.fee_rates {
+            out.push_str(&format!(
+                "{:<10} {:>10.4} {:>10.4} {:>10.4} {:>10.4} {:>10.4} {:>10.4}\n",
+                r.symbol,
+                r.last_funding_bps_hour,
+                r.avg_funding_bps_hour,
+                r.peak_funding_bps_hour,
+                r.last_borrowing_bps_hour,
+                r.avg_borrowing_bps_hour,
+                r.max_borrowing_bps_hour,
+            ));
+        }
+    }
+
+    if !s.insurance_funds.is_empty() {
+        out.push_str("\n--- Insurance fund ---\n");
+        for f in &s.insurance_funds {
+            out.push_str(&format!(
+                "{:<10} balance={} min={} max={} snapshots={}\n",
+                f.symbol,
+                usd(f.balance),
+                usd(f.min_balance),
+                usd(f.max_balance),
+                f.trajectory.len()
+            ));
+            for (reason, flow) in &f.flows {
+                out.push_str(&format!("{:<10}   {:<14} {:>14}\n", "", reason, usd(*flow)));
+            }
+        }
+    }
+
+    if !s.chain_txs.is_empty() {
+        out.push_str("\n--- Chain transactions ---\n");
+        out.push_str(&format!(
+            "{:<18} {:>7} {:>7} {:>8} {:>10} {:>10} {:>14} {:>8} {:>16}\n",
+            "tx_type", "total", "failed", "fail%", "avg_ms", "max_ms", "tips", "reverted", "fees"
+        ));
+        for t in &s.chain_txs {
+            out.push_str(&format!(
+                "{:<18} {:>7} {:>7} {:>7.1}% {:>10.0} {:>10} {:>14} {:>8} {:>16}\n",
+                t.tx_type,
+                t.total,
+                t.failed,
+                t.failure_rate_pct,
+                t.avg_latency_ms,
+                t.max_latency_ms,
+                t.tips,
+                t.reverted,
+                t.fees,
+            ));
+        }
+    }
+
+    if !s.chain_spend.is_empty() {
+        out.push_str("\n--- Chain fees per agent ---\n");
+        for a in &s.chain_spend {
+            out.push_str(&format!(
+                "agent={:<6} txs={:<7} fees={:<16} refused={}\n",
+                a.agent, a.txs, a.fees, a.refused
+            ));
+        }
+    }
+
+    if !s.dead_letters.is_empty() {
+        out.push_str("\n--- Dead letters (messages for unknown agents) ---\n");
+        for d in &s.dead_letters {
+            out.push_str(&format!(
+                "to={:<6} {:<22} count={:<6} dropped={}\n",
+                d.to, d.msg_type, d.count, d.dropped
+            ));
+        }
+    }
+
+    if !s.tick_overruns.is_empty() {
+        out.push_str("\n--- Tick overruns (realtime watchdog) ---\n");
+        for t in &s.tick_overruns {
+            let slowest = match (t.agent, &t.msg_type) {
+                (Some(agent), Some(msg_type)) => format!("slowest: agent={} {} {}ms", agent, msg_type, t.handler_ms),
+                _ => "outside agent handlers".to_string(),
+            };
+            out.push_str(&format!(
+                "{:.1}s tick={:<8} {}ms (limit {}ms) {}\n",
+                t.ts.saturating_sub(s.start_ts) as f64 / 1e9,
+                t.tick,
+                t.elapsed_ms,
+                t.limit_ms,
+                slowest
+            ));
+        }
+    }
+    out
+}
+
+impl EventListener for ReportBuilder {
+    fn on_event(&mut self, event: &SimEvent) {
+        let ts = event.ts();
+        if self.start_ts.is_none() {
+            self.start_ts = Some(ts);
+        }
+
+        match event {
+            SimEvent::OrderExecuted {
+                account,
+                side,
+                size_usd,
+                execution_price,
+                order_type,
+                pnl,
+                fee_usd,
+                decision_price,
+                ..
+            } => {
+                self.total_volume_usd += size_usd;
+                self.total_fees_usd += fee_usd;
+                let a = self.agent(*account);
+                a.trades += 1;
+                a.volume_usd += size_usd;
+                if let Some(decision_price) = decision_price.filter(|p| *p > 0) {
+                    // Opening a long and closing a short buy
+                    let buying = (order_type == "Increase") == (*side == Side::Buy);
+                    a.slippage_bps
+                        .push(slippage_bps(buying, decision_price, *execution_price));
+                }
+                if order_type != "Increase" {
+                    a.closed_trades += 1;
+                    a.realized_pnl += pnl;
+                    if *pnl > 0 {
+                        a.wins += 1;
+                    }
+                }
+                a.mark();
+            }
+
+            SimEvent::PositionLiquidated { account, side, pnl, .. } => {
+                let a = self.agent(*account);
+                a.liquidations += 1;
+                a.closed_trades += 1;
+                a.realized_pnl += pnl;
+                a.unrealized.remove(side);
+                a.mark();
+            }
+
+            SimEvent::PositionSnapshot {
+                ts,
+                account,
+                side,
+                unrealized_pnl,
+                ..
+            } => {
+                self.agent(*account).unrealized.insert(*side, (*ts, *unrealized_pnl));
+            }
+
+            SimEvent::MarketSnapshot {
+                ts,
+                symbol,
+                oi_long_usd,
+                oi_short_usd,
+                funding_rate_bps_hour_fp,
+                borrowing_rate_bps_hour_fp,
+                ..
+            } => {
+                // MarketSnapshot closes a snapshot batch: positions missing from it are closed
+                for a in self.agents.values_mut() {
+                    a.unrealized.retain(|_, (snap_ts, _)| *snap_ts >= *ts);
+                    a.mark();
+                }
+                if let Some(fund) = self.funds.get_mut(symbol) {
+                    fund.trajectory.push(FundPoint {
+                        ts: *ts,
+                        balance: fund.balance,
+                    });
+                }
+                self.oi_timeline.push(OiPoint {
+                    ts: *ts,
+                    symbol: symbol.clone(),
+                    oi_long_usd: *oi_long_usd,
+                    oi_short_usd: *oi_short_usd,
+                    funding_rate_bps_hour_fp: *funding_rate_bps_hour_fp,
+                    borrowing_rate_bps_hour_fp: *borrowing_rate_bps_hour_fp,
+                });
+            }
+
+            SimEvent::OracleTick { ts, symbol, .. } => {
+                let stale_ns = self.oracle_stale_ns;
+                let o = self.oracles.entry(symbol.clone()).or_default();
+                if o.ticks == 0 {
+                    o.first_ts = *ts;
+                } else {
+                    let gap = ts.saturating_sub(o.last_ts);
+                    o.max_gap_ns = o.max_gap_ns.max(gap);
+                    if gap > stale_ns {
+                        o.down_ns += gap;
+                    }
+                }
+                o.ticks += 1;
+                o.last_ts = *ts;
+            }
+
+            SimEvent::AgentBankrupt { account, .. } => {
+                self.agent(*account).bankruptcies += 1;
+            }
+
+            SimEvent::ChainTx {
+                agent,
+                tx_type,
+                success,
+                latency_ms,
+                error,
+                tip,
+                fee,
+                reverted,
+                ..
+            } => {
+                let t = self.chain_txs.entry(tx_type.clone()).or_default();
+                if *reverted {
+                    t.reverted += 1;
+                    return;
+                }
+                t.total += 1;
+                if !success {
+                    t.failed += 1;
+                }
+                t.latency_sum_ms += latency_ms;
+                t.max_latency_ms = t.max_latency_ms.max(*latency_ms);
+                t.tips += tip;
+                t.fees += fee.unwrap_or(0);
+
+                let refused = error.as_deref().is_some_and(|e| e.starts_with(TX_BUDGET_EXHAUSTED));
+                if fee.is_some() || refused {
+                    let spend = self.chain_spend.entry(*agent).or_insert_with(|| ChainSpendReport {
+                        agent: *agent,
+                        ..ChainSpendReport::default()
+                    });
+                    match fee {
+                        Some(fee) => {
+                            spend.txs += 1;
+                            spend.fees += fee;
+                        }
+                        None => spend.refused += 1,
+                    }
+                }
+            }
+
+            SimEvent::FeeAccrued { kind, amount_usd, .. } => {
+                *self.fees_by_kind.entry(kind.clone()).or_default() += amount_usd;
+            }
+
+            SimEvent::InsuranceFundChanged {
+                symbol,
+                delta,
+                balance,
+                reason,
+                ..
+            } => {
+                let fund = self.funds.entry(symbol.clone()).or_default();
+                fund.balance = *balance;
+                fund.min_balance = fund.min_balance.min(*balance);
+                fund.max_balance = fund.max_balance.max(*balance);
+                *fund.flows.entry(reason.clone()).or_default() += delta;
+            }
+
+            SimEvent::OracleFailover {
+                ts,
+                oracle,
+                from,
+                to,
+                reason,
+                ..
+            } => {
+                self.oracle_failovers.push(OracleFailoverReport {
+                    ts: *ts,
+                    oracle: *oracle,
+                    from: from.clone(),
+                    to: to.clone(),
+                    reason: reason.clone(),
+                });
+            }
+
+            SimEvent::DeadLetter {
+                to,
+                msg_type,
+                attempt,
+                action,
+                ..
+            } => {
+                let (count, dropped) = self.dead_letters.entry((*to, msg_type.clone())).or_default();
+                if *attempt == 1 {
+                    *count += 1;
+                }
+                if action == "drop" {
+                    *dropped += 1;
+                }
+            }
+
+            SimEvent::CircuitBreakerTripped {
+                ts,
+                symbol,
+                from_price,
+                to_price,
+                move_bps,
+                halted_until,
+            } => {
+                self.circuit_breakers.push(CircuitBreakerReport {
+                    ts: *ts,
+                    symbol: symbol.clone(),
+                    from_price: *from_price,
+                    to_price: *to_price,
+                    move_bps: *move_bps,
+                    halted_until: *halted_until,
+                });
+            }
+
+            SimEvent::TickOverrun {
+                ts,
+                tick,
+                elapsed_ms,
+                limit_ms,
+                agent,
+                msg_type,
+                handler_ms,
+            } => {
+                self.tick_overruns.push(TickOverrunReport {
+                    ts: *ts,
+                    tick: *tick,
+                    elapsed_ms: *elapsed_ms,
+                    limit_ms: *limit_ms,
+                    agent: *agent,
+                    msg_type: msg_type.clone(),
+                    handler_ms: *handler_ms,
+                });
+            }
+
+            SimEvent::OrderLog { .. }
+            | SimEvent::RiskReport { .. }
+            | SimEvent::RiskAlert { .. }
+            | SimEvent::RiskLimitBreached { .. }
+            | SimEvent::AgentRecapitalized { .. }
+            | SimEvent::OrderExpired { .. }
+            | SimEvent::SpotSwap { .. }
+            | SimEvent::InboxBacklog { .. }
+            | SimEvent::LocalFillAbandoned { .. } => {}
+        }
+    }
+
+    fn on_finish(&mut self, ts: u64) {
+        let summary = self.build(ts);
+        print!("\n{}", format_text(&summary));
+        match self.write(&summary) {
+            Ok(()) => println!("[Report] written to {}/summary.json", self.dir.display()),
+            Err(e) => eprintln!("[Report] failed to write summary: {e}"),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+// Pre-trade risk limits shared by all traders.
+//
+// The exchange runs every new order (market or conditional Increase) through
+// `RiskGuard::check_order` before it is filled or sent on-chain, so agents do
+// not need their own limit logic. Closes and cancels always pass: they only
+// reduce risk. Fills are fed back (`record_increase` / `record_close`) to track
+// exposure per market side and the realized PnL of the current sim day.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::messages::{AgentId, Side};
+
+const DAY_NS: u64 = 86_400_000_000_000;
+const MINUTE_NS: u64 = 60_000_000_000;
+
+/// Limits of one account; unset fields are not checked. Amounts in micro-USD.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RiskLimits {
+    /// Notional of one position (market + side) after the order
+    #[serde(default)]
+    pub max_position_usd: Option<u64>,
+    #[serde(default)]
+    pub max_leverage: Option<u32>,
+    /// Realized loss (PnL after fees) within one sim day; new orders are blocked until the next day
+    #[serde(default)]
+    pub max_daily_loss_usd: Option<u64>,
+    /// New orders within any 60s of sim time
+    #[serde(default)]
+    pub max_orders_per_min: Option<u32>,
+}
+
+/// Scenario `risk_limits` block: defaults for every trader plus per-agent overrides.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RiskLimitsConfig {
+    #[serde(default)]
+    pub default: Option<RiskLimits>,
+    /// Agent id -> limits (replace the defaults for that agent)
+    #[serde(default)]
+    pub agents: BTreeMap<AgentId, RiskLimits>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitKind {
+    PositionSize,
+    Leverage,
+    DailyLoss,
+    OrderRate,
+}
+
+impl LimitKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LimitKind::PositionSize => "max_position_usd",
+            LimitKind::Leverage => "max_leverage",
+            LimitKind::DailyLoss => "max_daily_loss_usd",
+            LimitKind::OrderRate => "max_orders_per_min",
+        }
+    }
+}
+
+/// A rejected order: which limit, the value the order would reach and the limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breach {
+    pub kind: LimitKind,
+    pub value: f64,
+    pub limit: f64,
+}
+
+#[derive(Debug, Default)]
+struct AccountRisk {
+    exposure: HashMap<(String, Side), u64>,
+    day: u64,
+    day_pnl: i64,
+    /// Sim times of accepted orders within the last minute
+    recent_orders: VecDeque<u64>,
+}
+
+impl AccountRisk {
+    fn roll_day(&mut self, now_ns: u64) {
+        let day = now_ns / DAY_NS;
+        if day != self.day {
+            self.day = day;
+            self.day_pnl = 0;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RiskGuard {
+    default: Option<RiskLimits>,
+    per_agent: HashMap<AgentId, RiskLimits>,
+    accounts: HashMap<AgentId, AccountRisk>,
+}
+
+impl RiskGuard {
+    pub fn new(config: RiskLimitsConfig) -> Self {
+        Self {
+            default: config.default,
+            per_agent: config.agents.into_iter().collect(),
+            accounts: HashMap::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.default.is_some() || !self.per_agent.is_empty()
+    }
+
+    fn limits(&self, account: AgentId) -> Option<&RiskLimits> {
+        self.per_agent.get(&account).or(self.default.as_ref())
+    }
+
+    /// Check a new order adding `size_usd` to (`symbol`, `side`); accepted orders count toward the rate limit.
+    pub fn check_order(
+        &mut self,
+        account: AgentId,
+        now_ns: u64,
+        symbol: &str,
+        side: Side,
+        size_usd: u64,
+        leverage: u32,
+    ) -> Result<(), Breach> {
+        let Some(limits) = self.limits(account).cloned() else {
+            return Ok(());
+        };
+        let state = self.accounts.entry(account).or_default();
+        state.roll_day(now_ns);
+        while state
+            .recent_orders
+            .front()
+            .is_some_and(|ts| now_ns.saturating_sub(*ts) >= MINUTE_NS)
+        {
+            state.recent_orders.pop_front();
+        }
+
+        if let Some(max) = limits.max_leverage {
+            if leverage > max {
+                return Err(Breach {
+                    kind: LimitKind::Leverage,
+                    value: leverage as f64,
+                    limit: max as f64,
+                });
+            }
+        }
+        if let Some(max) = limits.max_position_usd {
+            let current = state.exposure.get(&(symbol.to_string(), side)).copied().unwrap_or(0);
+            if current + size_usd > max {
+                return Err(Breach {
+                    kind: LimitKind::PositionSize,
+                    value: (current + size_usd) as f64,
+                    limit: max as f64,
+                });
+            }
+        }
+        if let Some(max) = limits.max_daily_loss_usd {
+            if state.day_pnl < 0 && state.day_pnl.unsigned_abs() >= max {
+                return Err(Breach {
+                    kind: LimitKind::DailyLoss,
+                    value: -state.day_pnl as f64,
+                    limit: max as f64,
+                });
+            }
+        }
+        if let Some(max) = limits.max_orders_per_min {
+            if state.recent_orders.len() >= max as usize {
+                return Err(Breach {
+                    kind: LimitKind::OrderRate,
+                    value: state.recent_orders.len() as f64 + 1.0,
+                    limit: max as f64,
+                });
+            }
+        }
+        state.recent_orders.push_back(now_ns);
+        Ok(())
+    }
+
+    /// Book an executed Increase.
+    pub fn record_increase(&mut self, account: AgentId, symbol: &str, side: Side, size_usd: u64) {
+        if self.limits(account).is_none() {
+            return;
+        }
+        let state = self.accounts.entry(account).or_default();
+        *state.exposure.entry((symbol.to_string(), side)).or_insert(0) += size_usd;
+    }
+
+    /// Undo a booked Increase whose transaction was reverted.
+    pub fn revert_increase(&mut self, account: AgentId, symbol: &str, side: Side, size_usd: u64) {
+        if let Some(exposure) = self
+            .accounts
+            .get_mut(&account)
+            .and_then(|state| state.exposure.get_mut(&(symbol.to_string(), side)))
+        {
+            *exposure = exposure.saturating_sub(size_usd);
+        }
+    }
+
+    /// Book a close or liquidation of the whole side; `pnl` is the realized PnL net of fees.
+    pub fn record_close(&mut self, account: AgentId, now_ns: u64, symbol: &str, side: Side, pnl: i64) {
+        if self.limits(account).is_none() {
+            return;
+        }
+        let state = self.accounts.entry(account).or_default();
+        state.roll_day(now_ns);
+        state.day_pnl += pnl;
+        state.exposure.remove(&(symbol.to_string(), side));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(limits: RiskLimits) -> RiskGuard {
+        RiskGuard::new(RiskLimitsConfig {
+            default: Some(limits),
+            agents: BTreeMap::new(),
+        })
+    }
+
+    #[test]
+    fn test_no_limits_accepts_everything() {
+        let mut g = RiskGuard::default();
+        assert!(!g.is_enabled());
+        assert!(g.check_order(1, 0, "ETH-USD", Side::Buy, u64::MAX / 2, 100).is_ok());
+    }
+
+    #[test]
+    fn test_position_size_counts_existing_exposure() {
+        let mut g = guard(RiskLimits {
+            max_position_usd: Some(1_000),
+            ..Default::default()
+        });
+        assert!(g.check_order(1, 0, "ETH-USD", Side::Buy, 600, 2).is_ok());
+        g.record_increase(1, "ETH-USD", Side::Buy, 600);
+        let breach = g.check_order(1, 0, "ETH-USD", Side::Buy, 600, 2).unwrap_err();
+        assert_eq!(breach.kind, LimitKind::PositionSize);
+        assert_eq!(breach.value, 1_200.0);
+        // A reverted Increase gives its room back
+        g.revert_increase(1, "ETH-USD", Side::Buy, 600);
+        assert!(g.check_order(1, 0, "ETH-USD", Side::Buy, 1_000, 2).is_ok());
+        g.record_increase(1, "ETH-USD", Side::Buy, 600);
+        // Other side and closed positions have their own room
+        assert!(g.check_order(1, 0, "ETH-USD", Side::Sell, 600, 2).is_ok());
+        g.record_close(1, 0, "ETH-USD", Side::Buy, 0);
+        assert!(g.check_order(1, 0, "ETH-USD", Side::Buy, 900, 2).is_ok());
+    }
+
+    #[test]
+    fn test_daily_loss_resets_next_day() {
+        let mut g = guard(RiskLimits {
+            max_daily_loss_usd: Some(500),
+            ..Default::default()
+        });
+        g.record_close(1, 0, "ETH-USD", Side::Buy, -500);
+        let breach = g.check_order(1, 1, "ETH-USD", Side::Buy, 10, 1).unwrap_err();
+        assert_eq!(breach.kind, LimitKind::DailyLoss);
+        assert!(g.check_order(1, DAY_NS, "ETH-USD", Side::Buy, 10, 1).is_ok());
+    }
+
+    #[test]
+    fn test_order_rate_uses_sliding_minute() {
+        let mut g = guard(RiskLimits {
+            max_orders_per_min: Some(2),
+            max_leverage: Some(10),
+            ..Default::default()
+        });
+        assert!(g.check_order(1, 0, "ETH-USD", Side::Buy, 1, 1).is_ok());
+        assert!(g.check_order(1, 1, "ETH-USD", Side::Buy, 1, 1).is_ok());
+        assert_eq!(
+            g.check_order(1, 2, "ETH-USD", Side::Buy, 1, 1).unwrap_err().kind,
+            LimitKind::OrderRate
+        );
+        // Rejected orders do not count
+        assert_eq!(
+            g.check_order(1, MINUTE_NS, "ETH-USD", Side::Buy, 1, 20)
+                .unwrap_err()
+                .kind,
+            LimitKind::Leverage
+        );
+        assert!(g.check_order(1, MINUTE_NS, "ETH-USD", Side::Buy, 1, 1).is_ok());
+    }
+
+    #[test]
+    fn test_agent_override_replaces_default() {
+        let mut agents = BTreeMap::new();
+        agents.insert(7, RiskLimits::default());
+        let mut g = RiskGuard::new(RiskLimitsConfig {
+            default: Some(RiskLimits {
+                max_leverage: Some(5),
+                ..Default::default()
+            }),
+            agents,
+        });
+        assert!(g.check_order(1, 0, "ETH-USD", Side::Buy, 1, 10).is_err());
+        assert!(g.check_order(7, 0, "ETH-USD", Side::Buy, 1, 10).is_ok());
+    }
+}
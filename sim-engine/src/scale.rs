@@ -0,0 +1,310 @@
+// Fixed-point scales of the simulator and the contract.
+//
+// The simulator counts USD in micro-USD (1e6 = $1) and prices per whole token;
+// the contract counts USD values in 1e30, prices in USD(1e30) per atom of the
+// token and token amounts in atoms (10^decimals atoms per token):
+//
+//   value_1e30     = micro_usd * 10^24
+//   price_per_atom = price_micro_usd * 10^(24 - decimals)
+//
+// Examples:
+//   ETH ($3000, 18 decimals): 3000_000_000 * 10^6 = 3000 * 10^12
+//   BTC ($100000, 8 decimals): 100000_000_000 * 10^16 = 100000 * 10^22
+//
+// Every conversion between the two goes through the types here; `ScaleRegistry`
+// holds the index and collateral decimals of each market. Conversions round
+// down unless named otherwise and saturate at u64::MAX on the micro-USD side.
+// Tokens with more than 24 decimals lose precision in their atom prices.
+
+use std::collections::HashMap;
+
+use primitive_types::U256;
+
+/// Decimal places between USD(1e30) and micro-USD
+pub const USD_1E30_OVER_MICRO: u32 = 24;
+
+/// Amount of USD in micro-USD (1e6 = $1)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MicroUsd(pub u64);
+
+/// Amount of USD in the contract's 1e30 fixed point
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Usd1e30(pub U256);
+
+/// Token amount in atoms, with the decimals of its token
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenAmount {
+    pub atoms: U256,
+    pub decimals: u32,
+}
+
+/// Decimals of one token: converts its prices and amounts between the two scales.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenScale {
+    pub decimals: u32,
+}
+
+/// Index and collateral token scales of one market.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketScale {
+    pub index: TokenScale,
+    pub collateral: TokenScale,
+}
+
+fn saturating_u64(value: U256) -> u64 {
+    if value > U256::from(u64::MAX) {
+        u64::MAX
+    } else {
+        value.low_u64()
+    }
+}
+
+impl MicroUsd {
+    /// Whole dollars, truncated to the micro-dollar
+    pub fn from_usd(usd: f64) -> Self {
+        Self((usd * 1_000_000.0) as u64)
+    }
+
+    pub fn to_usd(self) -> f64 {
+        self.0 as f64 / 1_000_000.0
+    }
+
+    pub fn to_1e30(self) -> Usd1e30 {
+        Usd1e30(U256::from(self.0) * U256::exp10(USD_1E30_OVER_MICRO as usize))
+    }
+}
+
+impl Usd1e30 {
+    pub fn to_micro(self) -> MicroUsd {
+        MicroUsd(saturating_u64(self.0 / U256::exp10(USD_1E30_OVER_MICRO as usize)))
+    }
+
+    /// Average price per whole token of `tokens` bought for this value (0 without tokens)
+    pub fn per_token(self, tokens: TokenAmount) -> MicroUsd {
+        if tokens.atoms.is_zero() {
+            return MicroUsd(0);
+        }
+        let scaled = self.0 * U256::exp10(tokens.decimals as usize) / tokens.atoms;
+        Usd1e30(scaled).to_micro()
+    }
+}
+
+impl TokenAmount {
+    pub fn new(atoms: U256, decimals: u32) -> Self {
+        Self { atoms, decimals }
+    }
+
+    /// Value of the amount at `price` per whole token
+    pub fn value_at(self, price: MicroUsd) -> MicroUsd {
+        MicroUsd(saturating_u64(
+            self.atoms * U256::from(price.0) / U256::exp10(self.decimals as usize),
+        ))
+    }
+
+    /// Whole tokens, for display and float math
+    pub fn to_tokens(self) -> f64 {
+        let whole = U256::exp10(self.decimals as usize);
+        let (int, frac) = self.atoms.div_mod(whole);
+        saturating_u64(int) as f64 + frac.low_u128() as f64 / 10f64.powi(self.decimals as i32)
+    }
+}
+
+impl TokenScale {
+    pub const fn new(decimals: u32) -> Self {
+        Self { decimals }
+    }
+
+    /// micro-USD per whole token -> USD(1e30) per atom, rounding down
+    pub fn atom_price(self, price: MicroUsd) -> U256 {
+        self.scale_price(price, false)
+    }
+
+    /// micro-USD per whole token -> USD(1e30) per atom, rounding up
+    pub fn atom_price_ceil(self, price: MicroUsd) -> U256 {
+        self.scale_price(price, true)
+    }
+
+    /// USD(1e30) per atom -> micro-USD per whole token
+    pub fn micro_price(self, atom_price: U256) -> MicroUsd {
+        let micro = if self.decimals <= USD_1E30_OVER_MICRO {
+            atom_price / U256::exp10((USD_1E30_OVER_MICRO - self.decimals) as usize)
+        } else {
+            atom_price.saturating_mul(U256::exp10((self.decimals - USD_1E30_OVER_MICRO) as usize))
+        };
+        MicroUsd(saturating_u64(micro))
+    }
+
+    pub fn amount(self, atoms: U256) -> TokenAmount {
+        TokenAmount::new(atoms, self.decimals)
+    }
+
+    /// Amount worth `value` at `price` per whole token
+    pub fn amount_for(self, value: MicroUsd, price: MicroUsd) -> TokenAmount {
+        let atoms = U256::from(value.0) * U256::exp10(self.decimals as usize) / U256::from(price.0.max(1));
+        self.amount(atoms)
+    }
+
+    fn scale_price(self, price: MicroUsd, round_up: bool) -> U256 {
+        let micro = U256::from(price.0);
+        if self.decimals <= USD_1E30_OVER_MICRO {
+            return micro * U256::exp10((USD_1E30_OVER_MICRO - self.decimals) as usize);
+        }
+        let divisor = U256::exp10((self.decimals - USD_1E30_OVER_MICRO) as usize);
+        let (quot, rem) = micro.div_mod(divisor);
+        if round_up && !rem.is_zero() {
+            quot + 1
+        } else {
+            quot
+        }
+    }
+}
+
+impl MarketScale {
+    /// 18-decimal index token (ETH) against a 6-decimal stablecoin, for markets nobody registered
+    pub const DEFAULT: MarketScale = MarketScale::new(18, 6);
+
+    pub const fn new(index_decimals: u32, collateral_decimals: u32) -> Self {
+        Self {
+            index: TokenScale::new(index_decimals),
+            collateral: TokenScale::new(collateral_decimals),
+        }
+    }
+}
+
+/// Token scales per market symbol.
+#[derive(Debug, Clone, Default)]
+pub struct ScaleRegistry {
+    markets: HashMap<String, MarketScale>,
+}
+
+impl ScaleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, symbol: &str, scale: MarketScale) {
+        self.markets.insert(symbol.to_string(), scale);
+    }
+
+    /// Scale of `symbol`; `MarketScale::DEFAULT` if it was never registered
+    pub fn get(&self, symbol: &str) -> MarketScale {
+        self.markets.get(symbol).copied().unwrap_or(MarketScale::DEFAULT)
+    }
+
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.markets.contains_key(symbol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DECIMALS: [u32; 7] = [0, 6, 8, 12, 18, 24, 27];
+
+    #[test]
+    fn test_scaling_matches_documented_examples() {
+        // ETH $3000, 18 decimals -> 3000 * 10^12
+        assert_eq!(
+            TokenScale::new(18).atom_price(MicroUsd(3_000_000_000)),
+            U256::from(3000u64) * U256::exp10(12)
+        );
+        // BTC $100000, 8 decimals -> 100000 * 10^22
+        assert_eq!(
+            TokenScale::new(8).atom_price(MicroUsd(100_000_000_000)),
+            U256::from(100_000u64) * U256::exp10(22)
+        );
+        // USDC $1, 6 decimals -> 10^24
+        assert_eq!(TokenScale::new(6).atom_price(MicroUsd(1_000_000)), U256::exp10(24));
+        // $5 -> 5 * 10^30
+        assert_eq!(
+            MicroUsd::from_usd(5.0).to_1e30(),
+            Usd1e30(U256::from(5u64) * U256::exp10(30))
+        );
+    }
+
+    #[test]
+    fn test_atom_price_round_trip() {
+        for decimals in DECIMALS.into_iter().filter(|d| *d <= USD_1E30_OVER_MICRO) {
+            let scale = TokenScale::new(decimals);
+            for micro in [0, 1, 999_999, 3_000_123_456, 100_000_000_000, u64::MAX] {
+                let atom = scale.atom_price(MicroUsd(micro));
+                assert_eq!(atom, scale.atom_price_ceil(MicroUsd(micro)));
+                assert_eq!(
+                    scale.micro_price(atom),
+                    MicroUsd(micro),
+                    "decimals={decimals} micro={micro}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_above_24_decimals_rounds_outward() {
+        let scale = TokenScale::new(27);
+        // 1_234_567 micro / 10^3 -> 1234 down, 1235 up
+        assert_eq!(scale.atom_price(MicroUsd(1_234_567)), U256::from(1234u64));
+        assert_eq!(scale.atom_price_ceil(MicroUsd(1_234_567)), U256::from(1235u64));
+        assert_eq!(scale.atom_price_ceil(MicroUsd(1_234_000)), U256::from(1234u64));
+        assert_eq!(scale.micro_price(U256::from(1234u64)), MicroUsd(1_234_000));
+    }
+
+    #[test]
+    fn test_usd_round_trip() {
+        for micro in [0, 1, 1_000_000, 123_456_789_012, u64::MAX] {
+            assert_eq!(MicroUsd(micro).to_1e30().to_micro(), MicroUsd(micro));
+        }
+        // Sub-micro remainders are cut, values beyond u64 saturate
+        let dust = Usd1e30(MicroUsd(42).to_1e30().0 + U256::exp10(23));
+        assert_eq!(dust.to_micro(), MicroUsd(42));
+        assert_eq!(Usd1e30(U256::MAX).to_micro(), MicroUsd(u64::MAX));
+        assert_eq!(MicroUsd::from_usd(1.5).to_usd(), 1.5);
+    }
+
+    #[test]
+    fn test_token_amount_round_trip() {
+        for decimals in DECIMALS {
+            let scale = TokenScale::new(decimals);
+            for price in [1, 1_000_000, 3_000_000_000] {
+                // Whole-dollar values of whole tokens convert back exactly
+                let value = MicroUsd(price * 7);
+                let amount = scale.amount_for(value, MicroUsd(price));
+                assert_eq!(amount.atoms, U256::from(7u64) * U256::exp10(decimals as usize));
+                assert_eq!(
+                    amount.value_at(MicroUsd(price)),
+                    value,
+                    "decimals={decimals} price={price}"
+                );
+                assert_eq!(amount.to_tokens(), 7.0);
+            }
+        }
+        // Fractions of an atom are cut: $1 of a 0-decimal token at $3
+        assert_eq!(
+            TokenScale::new(0)
+                .amount_for(MicroUsd(1_000_000), MicroUsd(3_000_000))
+                .atoms,
+            U256::zero()
+        );
+        assert_eq!(TokenScale::new(6).amount(U256::from(1_500_000u64)).to_tokens(), 1.5);
+    }
+
+    #[test]
+    fn test_entry_price_from_size_and_tokens() {
+        let scale = TokenScale::new(18);
+        // $6000 for 2 ETH
+        let size = MicroUsd(6_000_000_000).to_1e30();
+        let tokens = scale.amount(U256::from(2u64) * U256::exp10(18));
+        assert_eq!(size.per_token(tokens), MicroUsd(3_000_000_000));
+        assert_eq!(size.per_token(scale.amount(U256::zero())), MicroUsd(0));
+    }
+
+    #[test]
+    fn test_registry_falls_back_to_default() {
+        let mut registry = ScaleRegistry::new();
+        registry.register("BTC-USD", MarketScale::new(8, 6));
+        assert_eq!(registry.get("BTC-USD").index.decimals, 8);
+        assert!(registry.contains("BTC-USD"));
+        assert_eq!(registry.get("ETH-USD"), MarketScale::DEFAULT);
+        assert!(!registry.contains("ETH-USD"));
+    }
+}
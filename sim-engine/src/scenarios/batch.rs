@@ -0,0 +1,312 @@
+// Monte Carlo batch runner: the same scenario N times with different seeds,
+// optionally swept over a parameter grid, on parallel worker threads.
+//
+// Layout under <logs_dir>/batch_<scenario>/:
+//   [p<j>/]seed_<seed>/   logs and summary.json of one run (p<j> = grid point)
+//   batch_summary.json    outcome distributions per grid point
+// A grid point directory is a valid `compare` input (one repetition per seed).
+
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use super::catalog::Catalog;
+use super::simple_demo::{self, SimConfig};
+use crate::compare;
+use crate::report::RunSummary;
+
+/// Batch parameters (`--batch`, `--jobs`, `--grid`).
+pub struct BatchOptions {
+    /// Repetitions per grid point, with seeds `seed, seed + 1, ...`
+    pub runs: u32,
+    /// Worker threads; every run connects its own VaraClient
+    pub jobs: usize,
+    /// Parameter grid entries `PATH=v1,v2,...`; the cartesian product is swept
+    pub grid: Vec<String>,
+    pub skip_deposits: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricStats {
+    pub mean: f64,
+    pub std: f64,
+    pub min: f64,
+    pub p5: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PointReport {
+    pub label: String,
+    /// Grid overrides of this point (empty without a grid)
+    pub overrides: Vec<String>,
+    pub runs: usize,
+    pub failed: usize,
+    pub metrics: BTreeMap<String, MetricStats>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchReport {
+    pub scenario: String,
+    pub base_seed: u64,
+    pub runs_per_point: u32,
+    pub points: Vec<PointReport>,
+}
+
+struct Job {
+    point: usize,
+    seed: u64,
+    dir: PathBuf,
+    config: SimConfig,
+}
+
+/// Expand `PATH=v1,v2` entries into the list of override sets (cartesian product).
+fn parse_grid(grid: &[String]) -> Result<Vec<Vec<String>>, String> {
+    let mut points: Vec<Vec<String>> = vec![vec![]];
+    for entry in grid {
+        let (path, values) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("--grid {}: expected PATH=V1,V2,...", entry))?;
+        let values: Vec<&str> = values.split(',').map(str::trim).filter(|v| !v.is_empty()).collect();
+        if values.is_empty() {
+            return Err(format!("--grid {}: no values", entry));
+        }
+        points = points
+            .into_iter()
+            .flat_map(|point| {
+                values.iter().map(move |v| {
+                    let mut point = point.clone();
+                    point.push(format!("{}={}", path.trim(), v));
+                    point
+                })
+            })
+            .collect();
+    }
+    Ok(points)
+}
+
+/// Nearest-rank quantile of sorted samples.
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn stats(xs: &[f64]) -> MetricStats {
+    let (mean, var) = compare::mean_var(xs);
+    let mut sorted = xs.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    MetricStats {
+        mean,
+        std: var.sqrt(),
+        min: sorted.first().copied().unwrap_or(0.0),
+        p5: quantile(&sorted, 0.05),
+        p50: quantile(&sorted, 0.50),
+        p95: quantile(&sorted, 0.95),
+        max: sorted.last().copied().unwrap_or(0.0),
+    }
+}
+
+fn aggregate(label: String, overrides: Vec<String>, runs: &[RunSummary], failed: usize) -> PointReport {
+    let per_run: Vec<_> = runs.iter().map(compare::metrics).collect();
+    let mut names: Vec<&String> = per_run.iter().flat_map(|m| m.keys()).collect();
+    names.sort();
+    names.dedup();
+    let metrics = names
+        .into_iter()
+        .map(|name| {
+            let xs: Vec<f64> = per_run.iter().map(|m| m.get(name).copied().unwrap_or(0.0)).collect();
+            (name.clone(), stats(&xs))
+        })
+        .collect();
+    PointReport {
+        label,
+        overrides,
+        runs: runs.len(),
+        failed,
+        metrics,
+    }
+}
+
+fn read_summary(dir: &Path) -> Result<RunSummary, String> {
+    let path = dir.join("summary.json");
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn print_report(report: &BatchReport) {
+    println!();
+    println!(
+        "=== Batch: {} ({} run(s) per point, seeds {}..{}) ===",
+        report.scenario,
+        report.runs_per_point,
+        report.base_seed,
+        report.base_seed.wrapping_add(report.runs_per_point as u64 - 1)
+    );
+    for point in &report.points {
+        println!();
+        println!("--- {} ({} ok, {} failed) ---", point.label, point.runs, point.failed);
+        println!(
+            "{:<32} {:>12} {:>12} {:>12} {:>12} {:>12}",
+            "metric", "mean", "std", "p5", "p50", "p95"
+        );
+        for (name, s) in &point.metrics {
+            println!(
+                "{:<32} {:>12.2} {:>12.2} {:>12.2} {:>12.2} {:>12.2}",
+                name, s.mean, s.std, s.p5, s.p50, s.p95
+            );
+        }
+    }
+}
+
+/// Run `scenario` `opts.runs` times per grid point and aggregate the outcomes.
+/// Every run connects its own VaraClient (parallel runs can't share a tx result stream).
+/// Per-run logs and `batch_summary.json` are written below the scenario's logs_dir.
+pub fn run_batch(
+    catalog: &Catalog,
+    scenario: &str,
+    sets: &[String],
+    opts: &BatchOptions,
+) -> Result<BatchReport, String> {
+    if opts.runs == 0 {
+        return Err("--batch needs at least one run".to_string());
+    }
+    let path = catalog
+        .find(scenario)
+        .ok_or_else(|| format!("scenario '{}' not found in {}", scenario, catalog.dir().display()))?;
+    let path = path.to_string_lossy().into_owned();
+    let base = SimConfig::from_file(&path, sets)?;
+    let points = parse_grid(&opts.grid)?;
+    let batch_dir = Path::new(base.logs_dir()).join(format!("batch_{}", base.scenario_name()));
+
+    // Resolve every run config up front so a bad grid value fails before anything runs
+    let mut queue = VecDeque::new();
+    for (j, point) in points.iter().enumerate() {
+        let point_dir = if opts.grid.is_empty() {
+            batch_dir.clone()
+        } else {
+            batch_dir.join(format!("p{}", j))
+        };
+        for i in 0..opts.runs {
+            let seed = base.seed().wrapping_add(i as u64);
+            let dir = point_dir.join(format!("seed_{}", seed));
+            let mut run_sets = sets.to_vec();
+            run_sets.extend(point.iter().cloned());
+            run_sets.push(format!("seed={}", seed));
+            let logs_dir = serde_json::Value::from(dir.display().to_string());
+            run_sets.push(format!("logs_dir={}", logs_dir));
+            let config = SimConfig::from_file(&path, &run_sets)?;
+            queue.push_back(Job {
+                point: j,
+                seed,
+                dir,
+                config,
+            });
+        }
+    }
+
+    let total = queue.len();
+    let jobs = opts.jobs.clamp(1, total);
+    println!(
+        "[Batch] {}: {} grid point(s) x {} run(s) = {} runs on {} worker(s) -> {}",
+        base.scenario_name(),
+        points.len(),
+        opts.runs,
+        total,
+        jobs,
+        batch_dir.display()
+    );
+
+    let queue = Mutex::new(queue);
+    let results: Mutex<Vec<(usize, Result<RunSummary, String>)>> = Mutex::new(Vec::with_capacity(total));
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let Some(job) = queue.lock().unwrap().pop_front() else {
+                    break;
+                };
+                println!("[Batch] start p{} seed={}", job.point, job.seed);
+                let outcome = simple_demo::connect_vara(&job.config).and_then(|client| {
+                    simple_demo::run_with_config(job.config, opts.skip_deposits, client)?;
+                    read_summary(&job.dir)
+                });
+                if let Err(e) = &outcome {
+                    eprintln!("[Batch] p{} seed={} failed: {}", job.point, job.seed, e);
+                }
+                results.lock().unwrap().push((job.point, outcome));
+            });
+        }
+    });
+
+    let mut by_point: Vec<(Vec<RunSummary>, usize)> = points.iter().map(|_| (Vec::new(), 0)).collect();
+    for (point, outcome) in results.into_inner().unwrap() {
+        match outcome {
+            Ok(summary) => by_point[point].0.push(summary),
+            Err(_) => by_point[point].1 += 1,
+        }
+    }
+
+    let report = BatchReport {
+        scenario: base.scenario_name().to_string(),
+        base_seed: base.seed(),
+        runs_per_point: opts.runs,
+        points: points
+            .into_iter()
+            .zip(by_point)
+            .enumerate()
+            .map(|(j, (overrides, (runs, failed)))| {
+                let label = if overrides.is_empty() {
+                    "all runs".to_string()
+                } else {
+                    format!("p{}: {}", j, overrides.join(" "))
+                };
+                aggregate(label, overrides, &runs, failed)
+            })
+            .collect(),
+    };
+
+    print_report(&report);
+    let out = batch_dir.join("batch_summary.json");
+    let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&batch_dir).map_err(|e| format!("{}: {}", batch_dir.display(), e))?;
+    std::fs::write(&out, json).map_err(|e| format!("{}: {}", out.display(), e))?;
+    println!("[Batch] written to {}", out.display());
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_grid_cartesian_product() {
+        let grid = vec![
+            "duration_sec=60,120".to_string(),
+            "smart_traders[0].leverage=5,10,20".to_string(),
+        ];
+        let points = parse_grid(&grid).unwrap();
+        assert_eq!(points.len(), 6);
+        assert_eq!(points[0], vec!["duration_sec=60", "smart_traders[0].leverage=5"]);
+        assert_eq!(points[5], vec!["duration_sec=120", "smart_traders[0].leverage=20"]);
+        assert_eq!(parse_grid(&[]).unwrap(), vec![Vec::<String>::new()]);
+        assert!(parse_grid(&["duration_sec".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_stats_quantiles() {
+        let xs: Vec<f64> = (1..=100).map(|x| x as f64).collect();
+        let s = stats(&xs);
+        assert_eq!(s.min, 1.0);
+        assert_eq!(s.max, 100.0);
+        assert_eq!(s.p5, 5.0);
+        assert_eq!(s.p50, 50.0);
+        assert_eq!(s.p95, 95.0);
+        assert!((s.mean - 50.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,221 @@
+// Scenario discovery: where scenario JSON files live, and the offline
+// `list` / `describe` / `validate` subcommands on top of it.
+//
+// The directory is --scenarios-dir, else $SIM_SCENARIOS_DIR, else the first of
+// the usual locations relative to the working directory, else the scenarios
+// directory of the source tree this binary was built from.
+
+use std::path::{Path, PathBuf};
+
+use super::simple_demo::SimConfig;
+
+const RELATIVE_DIRS: [&str; 3] = ["sim-engine/src/scenarios", "src/scenarios", "scenarios"];
+
+pub struct Catalog {
+    dir: PathBuf,
+}
+
+impl Catalog {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        let env_dir = std::env::var("SIM_SCENARIOS_DIR").ok().map(PathBuf::from);
+        // An empty base keeps the relative locations relative to the working directory
+        Self {
+            dir: resolve_dir(dir, env_dir, Path::new("")),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Scenario file for `name`: a path to a .json file, or `<dir>/<name>.json`.
+    pub fn find(&self, name: &str) -> Option<PathBuf> {
+        let as_path = Path::new(name);
+        if name.ends_with(".json") && as_path.is_file() {
+            return Some(as_path.to_path_buf());
+        }
+        let file = format!("{}.json", name.trim_end_matches(".json"));
+        [self.dir.join(&file), PathBuf::from(&file)]
+            .into_iter()
+            .find(|p| p.is_file())
+    }
+
+    /// All scenarios in the directory: (name, path), sorted by name.
+    pub fn scenarios(&self) -> Result<Vec<(String, PathBuf)>, String> {
+        let entries = std::fs::read_dir(&self.dir).map_err(|e| format!("{}: {}", self.dir.display(), e))?;
+        let mut out: Vec<(String, PathBuf)> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|ext| ext == "json").unwrap_or(false))
+            .filter_map(|p| Some((p.file_stem()?.to_string_lossy().into_owned(), p)))
+            .collect();
+        out.sort();
+        Ok(out)
+    }
+
+    fn load(&self, name: &str, sets: &[String]) -> Result<SimConfig, String> {
+        let path = self
+            .find(name)
+            .ok_or_else(|| format!("scenario '{}' not found in {}", name, self.dir.display()))?;
+        SimConfig::from_file(&path.to_string_lossy(), sets)
+    }
+}
+
+/// The scenarios directory: `flag`, else `env`, else the first usual location under `base`,
+/// else the source tree's.
+fn resolve_dir(flag: Option<PathBuf>, env: Option<PathBuf>, base: &Path) -> PathBuf {
+    flag.or(env)
+        .or_else(|| RELATIVE_DIRS.iter().map(|dir| base.join(dir)).find(|p| p.is_dir()))
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("src/scenarios"))
+}
+
+/// `list`: one line per scenario with duration and agent count.
+pub fn list(catalog: &Catalog) -> Result<(), String> {
+    let scenarios = catalog.scenarios()?;
+    println!("Scenarios in {}:", catalog.dir().display());
+    println!("{:<28} {:>10} {:>8}  {}", "name", "duration", "agents", "markets");
+    for (name, path) in &scenarios {
+        match SimConfig::from_file(&path.to_string_lossy(), &[]) {
+            Ok(config) => {
+                let info = config.summary();
+                println!(
+                    "{:<28} {:>9}s {:>8}  {}",
+                    name,
+                    info.duration_sec,
+                    info.agents.values().sum::<usize>(),
+                    info.markets.join(", ")
+                );
+            }
+            Err(e) => {
+                let first = e.lines().next().unwrap_or_default();
+                println!("{:<28} INVALID: {}", name, first);
+            }
+        }
+    }
+    println!("{} scenario(s)", scenarios.len());
+    Ok(())
+}
+
+/// `describe <scenario>`: resolved configuration overview (after extends / --set).
+pub fn describe(catalog: &Catalog, name: &str, sets: &[String]) -> Result<(), String> {
+    let config = catalog.load(name, sets)?;
+    let info = config.summary();
+    println!("Scenario:   {}", info.name);
+    if let Some(path) = catalog.find(name) {
+        println!("File:       {}", path.display());
+    }
+    println!("Duration:   {}s", info.duration_sec);
+    println!("Logs:       {}", info.logs_dir);
+    println!("Seed:       {}", info.seed);
+    println!("Markets:    {}", info.markets.join(", "));
+    println!("Agents:     {}", info.agents.values().sum::<usize>());
+    for (agent_type, n) in &info.agents {
+        println!("  {:<20} {}", agent_type, n);
+    }
+    if info.populations > 0 {
+        println!("Populations: {}", info.populations);
+    }
+    if info.timeline_events > 0 {
+        println!("Timeline:   {} event(s)", info.timeline_events);
+    }
+    Ok(())
+}
+
+/// `validate [scenario...]`: check configs without running them (all when none given).
+pub fn validate(catalog: &Catalog, names: &[String], sets: &[String]) -> Result<(), String> {
+    let names: Vec<String> = if names.is_empty() {
+        catalog.scenarios()?.into_iter().map(|(name, _)| name).collect()
+    } else {
+        names.to_vec()
+    };
+
+    let mut invalid = 0;
+    for name in &names {
+        match catalog.load(name, sets) {
+            Ok(_) => println!("[Validate] {}: OK", name),
+            Err(e) => {
+                invalid += 1;
+                println!("[Validate] {}: {}", name, e);
+            }
+        }
+    }
+    if invalid > 0 {
+        return Err(format!("{} of {} scenario(s) invalid", invalid, names.len()));
+    }
+    println!("[Validate] {} scenario(s) OK", names.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fresh temp dir holding a valid scenario, a broken one and a non-scenario file
+    fn scenarios_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("catalog-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let sources = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/scenarios");
+        std::fs::copy(sources.join("multi_agent.json"), dir.join("good.json")).unwrap();
+        std::fs::write(dir.join("broken.json"), "{ \"duration_sec\": ").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a scenario").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_dir_resolution_order() {
+        let base = std::env::temp_dir().join(format!("catalog-resolve-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(&base).unwrap();
+        let flag = PathBuf::from("/flag");
+        let env = PathBuf::from("/env");
+        assert_eq!(resolve_dir(Some(flag.clone()), Some(env.clone()), &base), flag);
+        assert_eq!(resolve_dir(None, Some(env.clone()), &base), env);
+
+        // Nothing under the base: the source tree's scenarios
+        let sources = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/scenarios");
+        assert_eq!(resolve_dir(None, None, &base), sources);
+
+        // The first existing usual location wins
+        std::fs::create_dir_all(base.join("scenarios")).unwrap();
+        assert_eq!(resolve_dir(None, None, &base), base.join("scenarios"));
+        std::fs::create_dir_all(base.join("src/scenarios")).unwrap();
+        assert_eq!(resolve_dir(None, None, &base), base.join("src/scenarios"));
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_find_and_list_json_files() {
+        let dir = scenarios_dir("find");
+        let catalog = Catalog::new(Some(dir.clone()));
+        let names: Vec<String> = catalog.scenarios().unwrap().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["broken", "good"]);
+
+        assert_eq!(catalog.find("good"), Some(dir.join("good.json")));
+        assert_eq!(catalog.find("good.json"), Some(dir.join("good.json")));
+        let path = dir.join("broken.json").display().to_string();
+        assert_eq!(catalog.find(&path), Some(PathBuf::from(&path)));
+        assert_eq!(catalog.find("notes"), None);
+        assert!(Catalog::new(Some(dir.join("missing"))).scenarios().is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_validate_and_describe() {
+        let dir = scenarios_dir("validate");
+        let catalog = Catalog::new(Some(dir.clone()));
+
+        assert_eq!(validate(&catalog, &["good".to_string()], &[]), Ok(()));
+        assert_eq!(
+            validate(&catalog, &[], &[]),
+            Err("1 of 2 scenario(s) invalid".to_string())
+        );
+        // Overrides are validated as well
+        assert!(validate(&catalog, &["good".to_string()], &["duration_sec=0".to_string()]).is_err());
+
+        assert_eq!(describe(&catalog, "good", &[]), Ok(()));
+        let missing = describe(&catalog, "missing", &[]).unwrap_err();
+        assert!(missing.starts_with("scenario 'missing' not found"), "{}", missing);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,445 @@
+// Contract (re)deployment.
+//
+// `deploy <config.json>` uploads the VaraPerps WASM named in a deploy config
+// and runs the `Create` constructor with the config's parameters, signed by
+// one of the scenario's accounts. The new address is printed and can be
+// written back into an env file (VARA_CONTRACT_ADDRESS) and the scenario's
+// `vara.contract`. With `seed_liquidity_usd` the deployer then deposits that
+// much collateral and adds it as pool liquidity, so a fresh test contract is
+// ready for scenarios. Money values of the config are in USD, factors in bps
+// or as fractions; the contract's fixed-point factors are given verbatim as
+// decimal strings.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use primitive_types::U256;
+use serde::Deserialize;
+
+use super::catalog::Catalog;
+use super::simple_demo::{self, SimConfig};
+use crate::scale::{MicroUsd, TokenScale};
+use crate::vara::oracle::PEGGED_COLLATERAL_MICRO;
+use crate::vara::{
+    ActorId, BorrowingConfig, FundingConfig, ImpactRebalanceConfig, InitConfig, LiquidationFeeCfg, OracleConfig,
+    OracleMode, RiskCfg,
+};
+
+/// Fixed-point scale of `RiskCfg::min_collateral_factor_fp`
+const FACTOR_SCALE_DECIMALS: usize = 18;
+
+/// `deploy` options.
+#[derive(Debug, Clone, Default)]
+pub struct DeployOptions {
+    /// Deploy config (JSON)
+    pub config: PathBuf,
+    /// Account that uploads the contract and seeds the liquidity
+    pub agent_id: u32,
+    /// Env file whose VARA_CONTRACT_ADDRESS is set to the new contract
+    pub env_file: Option<PathBuf>,
+    /// Write the new contract into the scenario's `vara.contract`
+    pub write_scenario: bool,
+}
+
+/// Deploy config: the WASM and the constructor parameters.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeployConfig {
+    /// Optimized contract WASM, relative to the config file
+    pub wasm: PathBuf,
+    /// Collateral token program (hex); default: none (virtual balances)
+    #[serde(default)]
+    pub collateral_token: Option<String>,
+    /// Program salt (hex); default: current time, so every deploy gets a new address
+    #[serde(default)]
+    pub salt: Option<String>,
+    /// Default: the block gas limit
+    #[serde(default)]
+    pub gas_limit: Option<u64>,
+    pub funding: FundingJson,
+    pub borrowing: BorrowingJson,
+    pub impact: ImpactJson,
+    pub oracle: OracleJson,
+    pub risk: RiskJson,
+    pub liquidation: LiquidationJson,
+    /// Deposited and added as pool liquidity by the deployer after the deploy
+    #[serde(default)]
+    pub seed_liquidity_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FundingJson {
+    pub daily_rate_bps: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BorrowingJson {
+    pub base_rate_per_day_bps: u64,
+    /// Added at 100% utilization
+    pub slope_per_day_bps: u64,
+}
+
+/// Impact curve; factors are the contract's fixed-point values as decimal strings.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImpactJson {
+    pub exponent: u32,
+    pub same_side_positive_factor_fp: String,
+    pub same_side_negative_factor_fp: String,
+    pub crossover_positive_factor_fp: String,
+    pub crossover_negative_factor_fp: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OracleJson {
+    /// Accept raw prices without signatures (dev contracts)
+    #[serde(default = "default_dev_prices")]
+    pub dev_prices: bool,
+    #[serde(default = "default_max_age_secs")]
+    pub max_age_secs: u64,
+    /// ed25519 public key of the price signer (hex), signed mode only
+    #[serde(default)]
+    pub signer_pubkey: Option<String>,
+}
+
+fn default_dev_prices() -> bool {
+    true
+}
+
+fn default_max_age_secs() -> u64 {
+    60
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RiskJson {
+    /// Positions left below this size are closed as dust
+    pub min_position_size_usd: f64,
+    pub min_collateral_usd: f64,
+    /// Maintenance margin as a fraction of the position size (0.02 = 50x max leverage)
+    pub maintenance_margin: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LiquidationJson {
+    pub close_position_fee_bps: u32,
+    pub liquidation_fee_bps: u32,
+}
+
+impl DeployConfig {
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut config: DeployConfig = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if config.wasm.is_relative() {
+            if let Some(dir) = path.parent() {
+                config.wasm = dir.join(&config.wasm);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Constructor arguments: the contract config and the collateral token.
+    pub fn init_args(&self) -> Result<(InitConfig, ActorId), String> {
+        let risk = &self.risk;
+        if !(0.0..1.0).contains(&risk.maintenance_margin) || risk.maintenance_margin == 0.0 {
+            return Err("risk.maintenance_margin must be in (0, 1)".to_string());
+        }
+        if risk.min_position_size_usd < 0.0 || risk.min_collateral_usd < 0.0 {
+            return Err("risk: minimum sizes must be >= 0".to_string());
+        }
+        let factor_scale = U256::exp10(FACTOR_SCALE_DECIMALS);
+        let min_collateral_factor_fp = U256::from((risk.maintenance_margin * 1e18).round() as u128);
+
+        let signer_pubkey = match &self.oracle.signer_pubkey {
+            Some(key) => parse_bytes32("oracle.signer_pubkey", key)?,
+            None if !self.oracle.dev_prices => return Err("oracle.signer_pubkey is required for signed prices".into()),
+            None => [0u8; 32],
+        };
+        let collateral_token = match &self.collateral_token {
+            Some(token) => parse_bytes32("collateral_token", token)?,
+            None => [0u8; 32],
+        };
+
+        let init = InitConfig {
+            funding_cfg: FundingConfig {
+                daily_rate_bps: self.funding.daily_rate_bps,
+            },
+            borrowing_cfg: BorrowingConfig {
+                base_rate_per_day_bps: self.borrowing.base_rate_per_day_bps,
+                slope_per_day_bps: self.borrowing.slope_per_day_bps,
+            },
+            impact_cfg: ImpactRebalanceConfig {
+                impact_exponent: self.impact.exponent,
+                same_side_positive_factor_fp: parse_fp(
+                    "impact.same_side_positive_factor_fp",
+                    &self.impact.same_side_positive_factor_fp,
+                )?,
+                same_side_negative_factor_fp: parse_fp(
+                    "impact.same_side_negative_factor_fp",
+                    &self.impact.same_side_negative_factor_fp,
+                )?,
+                crossover_positive_factor_fp: parse_fp(
+                    "impact.crossover_positive_factor_fp",
+                    &self.impact.crossover_positive_factor_fp,
+                )?,
+                crossover_negative_factor_fp: parse_fp(
+                    "impact.crossover_negative_factor_fp",
+                    &self.impact.crossover_negative_factor_fp,
+                )?,
+            },
+            oracle_cfg: OracleConfig {
+                mode: if self.oracle.dev_prices {
+                    OracleMode::DevProvided
+                } else {
+                    OracleMode::Signed
+                },
+                max_age_secs: self.oracle.max_age_secs,
+                signer_pubkey,
+            },
+            risk_cfg: RiskCfg {
+                min_position_size_usd: MicroUsd::from_usd(risk.min_position_size_usd).to_1e30().0,
+                min_collateral_usd: MicroUsd::from_usd(risk.min_collateral_usd).to_1e30().0,
+                min_collateral_factor_fp,
+                factor_scale,
+            },
+            liq_cfg: LiquidationFeeCfg {
+                close_position_fee_bps: self.liquidation.close_position_fee_bps,
+                liquidation_fee_bps: self.liquidation.liquidation_fee_bps,
+            },
+        };
+        Ok((init, collateral_token.into()))
+    }
+
+    fn salt(&self) -> Result<Vec<u8>, String> {
+        match &self.salt {
+            Some(salt) => hex::decode(salt.trim_start_matches("0x")).map_err(|e| format!("salt: {}", e)),
+            None => {
+                let nanos = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos();
+                Ok(nanos.to_le_bytes().to_vec())
+            }
+        }
+    }
+}
+
+fn parse_fp(field: &str, value: &str) -> Result<U256, String> {
+    U256::from_dec_str(value.trim()).map_err(|e| format!("{}: '{}' is not a decimal integer: {:?}", field, value, e))
+}
+
+fn parse_bytes32(field: &str, value: &str) -> Result<[u8; 32], String> {
+    let bytes = hex::decode(value.trim_start_matches("0x")).map_err(|e| format!("{}: {}", field, e))?;
+    bytes
+        .try_into()
+        .map_err(|b: Vec<u8>| format!("{}: expected 32 bytes, got {}", field, b.len()))
+}
+
+/// Deploy with the accounts and connection of `scenario`; returns the new contract address.
+pub fn run(catalog: &Catalog, scenario: &str, sets: &[String], opts: &DeployOptions) -> Result<String, String> {
+    let path = catalog
+        .find(scenario)
+        .ok_or_else(|| format!("scenario '{}' not found in {}", scenario, catalog.dir().display()))?;
+    let config = SimConfig::from_file(&path.to_string_lossy(), sets)?;
+    let deploy = DeployConfig::from_file(&opts.config)?;
+    let (init, collateral_token) = deploy.init_args()?;
+    let wasm = std::fs::read(&deploy.wasm).map_err(|e| format!("{}: {}", deploy.wasm.display(), e))?;
+
+    let mut client = simple_demo::connect_vara_for_deploy(&config)?;
+    let contract_id = client
+        .deploy_contract(
+            opts.agent_id,
+            &wasm,
+            init,
+            collateral_token,
+            &deploy.salt()?,
+            deploy.gas_limit,
+        )
+        .map_err(|e| e.to_string())?;
+    let address = format!("0x{}", hex::encode(contract_id));
+    println!("[Deploy] VaraPerps deployed at {}", address);
+
+    if let Some(env_file) = &opts.env_file {
+        let text = std::fs::read_to_string(env_file).unwrap_or_default();
+        std::fs::write(env_file, set_env_contract(&text, &address))
+            .map_err(|e| format!("{}: {}", env_file.display(), e))?;
+        println!("[Deploy] VARA_CONTRACT_ADDRESS written to {}", env_file.display());
+    }
+    if opts.write_scenario {
+        let text = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let text = set_scenario_contract(&text, &address)?;
+        std::fs::write(&path, text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        println!("[Deploy] vara.contract written to {}", path.display());
+    }
+
+    if let Some(usd) = deploy.seed_liquidity_usd.filter(|usd| *usd > 0.0) {
+        client.set_contract(contract_id).map_err(|e| e.to_string())?;
+        let collateral_decimals = config.market_decimals().first().map_or(6, |(_, _, decimals)| *decimals);
+        let amount = TokenScale::new(collateral_decimals)
+            .amount_for(MicroUsd::from_usd(usd), MicroUsd(PEGGED_COLLATERAL_MICRO))
+            .atoms;
+        client.deposit(opts.agent_id, amount).map_err(|e| e.to_string())?;
+        client.add_liquidity(opts.agent_id, amount).map_err(|e| e.to_string())?;
+        println!(
+            "[Deploy] seeded ${:.2} of pool liquidity from agent {}",
+            usd, opts.agent_id
+        );
+    }
+    Ok(address)
+}
+
+/// `text` of an env file with VARA_CONTRACT_ADDRESS set to `address` (appended if missing).
+fn set_env_contract(text: &str, address: &str) -> String {
+    let mut found = false;
+    let mut lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            let (export, rest) = match line.strip_prefix("export ") {
+                Some(rest) => ("export ", rest),
+                None => ("", line),
+            };
+            if rest.trim_start().starts_with("VARA_CONTRACT_ADDRESS=") {
+                found = true;
+                format!("{}VARA_CONTRACT_ADDRESS={}", export, address)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    if !found {
+        lines.push(format!("VARA_CONTRACT_ADDRESS={}", address));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Scenario JSON `text` with `vara.contract` set to `address`. Edited in place to keep
+/// the file's layout; the result is checked to parse and carry the new address.
+fn set_scenario_contract(text: &str, address: &str) -> Result<String, String> {
+    let value = format!("\"{}\"", address);
+    let edited = if let Some(key) = find_key(text, "contract") {
+        // Replace the string after `"contract":`
+        let start = key + text[key..].find(':').ok_or("malformed \"contract\" entry")? + 1;
+        let open = start + text[start..].find('"').ok_or("vara.contract is not a string")?;
+        let close = open + 1 + text[open + 1..].find('"').ok_or("unterminated vara.contract")?;
+        format!("{}{}{}", &text[..open], value, &text[close + 1..])
+    } else if let Some(key) = find_key(text, "vara") {
+        let brace = key + text[key..].find('{').ok_or("vara is not an object")?;
+        let empty = text[brace + 1..].trim_start().starts_with('}');
+        let entry = format!("\"contract\": {}{}", value, if empty { "" } else { "," });
+        format!("{} {}{}", &text[..=brace], entry, &text[brace + 1..])
+    } else {
+        let brace = text.find('{').ok_or("scenario is not a JSON object")?;
+        format!(
+            "{}\n  \"vara\": {{ \"contract\": {} }},{}",
+            &text[..=brace],
+            value,
+            &text[brace + 1..]
+        )
+    };
+
+    let doc: serde_json::Value =
+        serde_json::from_str(&edited).map_err(|e| format!("could not set vara.contract: {}", e))?;
+    if doc.pointer("/vara/contract").and_then(|v| v.as_str()) != Some(address) {
+        return Err("could not set vara.contract: set it by hand".to_string());
+    }
+    Ok(edited)
+}
+
+/// Byte offset of the only `"name"` key in `text`; None if it is missing or not unique.
+fn find_key(text: &str, name: &str) -> Option<usize> {
+    let needle = format!("\"{}\"", name);
+    let mut matches = text
+        .match_indices(&needle)
+        .map(|(i, _)| i)
+        .filter(|i| text[i + needle.len()..].trim_start().starts_with(':'));
+    let first = matches.next()?;
+    matches.next().is_none().then_some(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "0x0101010101010101010101010101010101010101010101010101010101010101";
+
+    #[test]
+    fn test_env_file_gets_the_new_address() {
+        let text = "export VARA_WS_ENDPOINT=wss://testnet.vara.network\nexport VARA_CONTRACT_ADDRESS=0xold\n";
+        let out = set_env_contract(text, ADDRESS);
+        assert_eq!(
+            out,
+            format!("export VARA_WS_ENDPOINT=wss://testnet.vara.network\nexport VARA_CONTRACT_ADDRESS={ADDRESS}\n")
+        );
+        assert_eq!(
+            set_env_contract("", ADDRESS),
+            format!("VARA_CONTRACT_ADDRESS={ADDRESS}\n")
+        );
+    }
+
+    #[test]
+    fn test_scenario_contract_is_replaced_or_added() {
+        let replaced = set_scenario_contract(
+            "{\n  \"vara\": {\n    \"contract\": \"0xold\",\n    \"keys\": {}\n  }\n}",
+            ADDRESS,
+        )
+        .unwrap();
+        assert_eq!(
+            replaced,
+            format!("{{\n  \"vara\": {{\n    \"contract\": \"{ADDRESS}\",\n    \"keys\": {{}}\n  }}\n}}")
+        );
+
+        for text in [
+            "{ \"vara\": { \"keys\": {} }, \"duration_sec\": 60 }",
+            "{ \"vara\": {}, \"duration_sec\": 60 }",
+            "{\n  \"duration_sec\": 60\n}",
+        ] {
+            let out = set_scenario_contract(text, ADDRESS).unwrap();
+            let doc: serde_json::Value = serde_json::from_str(&out).unwrap();
+            assert_eq!(doc["vara"]["contract"], ADDRESS, "{text}");
+            assert_eq!(doc["duration_sec"], 60);
+        }
+    }
+
+    #[test]
+    fn test_init_args_scale_the_risk_config() {
+        let config: DeployConfig = serde_json::from_str(
+            r#"{
+                "wasm": "vara_perps.opt.wasm",
+                "funding": { "daily_rate_bps": 10 },
+                "borrowing": { "base_rate_per_day_bps": 5, "slope_per_day_bps": 50 },
+                "impact": {
+                    "exponent": 2,
+                    "same_side_positive_factor_fp": "500000000000000",
+                    "same_side_negative_factor_fp": "1000000000000000",
+                    "crossover_positive_factor_fp": "500000000000000",
+                    "crossover_negative_factor_fp": "1000000000000000"
+                },
+                "oracle": {},
+                "risk": { "min_position_size_usd": 1.0, "min_collateral_usd": 5.0, "maintenance_margin": 0.02 },
+                "liquidation": { "close_position_fee_bps": 10, "liquidation_fee_bps": 50 }
+            }"#,
+        )
+        .unwrap();
+        let (init, _) = config.init_args().unwrap();
+        assert_eq!(init.risk_cfg.min_collateral_usd, U256::from(5u64) * U256::exp10(30));
+        assert_eq!(
+            init.risk_cfg.min_collateral_factor_fp,
+            U256::from(2u64) * U256::exp10(16)
+        );
+        assert_eq!(init.impact_cfg.same_side_negative_factor_fp, U256::exp10(15));
+
+        let signed = DeployConfig {
+            oracle: OracleJson {
+                dev_prices: false,
+                max_age_secs: 60,
+                signer_pubkey: None,
+            },
+            ..config
+        };
+        assert!(signed.init_args().is_err());
+    }
+}
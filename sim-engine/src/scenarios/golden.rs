@@ -0,0 +1,317 @@
+// Golden-run regression harness.
+//
+// `golden` runs small seeded scenarios and compares their key outputs with the
+// golden files in <scenarios dir>/golden/<scenario>.json: the execution totals
+// of executions.csv, the final open interest per market and the PnL of every
+// agent. Counts must match exactly, money within the tolerances. Any
+// difference fails the command (exit code 1), so CI catches engine changes
+// that silently alter economics; `--update` rewrites the golden files after an
+// intended change. Runs are offline: every trader of a golden scenario must be
+// in `vara.local_agents` and prices come from a synthetic or playback oracle.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::catalog::Catalog;
+use super::simple_demo::{self, SimConfig};
+use crate::messages::AgentId;
+use crate::report::RunSummary;
+
+/// `golden` options.
+pub struct GoldenOptions {
+    /// Directory of the golden files (default: <scenarios dir>/golden)
+    pub dir: Option<PathBuf>,
+    /// Write the outputs as the new golden files instead of comparing
+    pub update: bool,
+    pub tolerance: Tolerance,
+}
+
+/// Allowed difference of money values: the larger of `abs_usd` and `rel` x golden value.
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+    pub abs_usd: f64,
+    pub rel: f64,
+}
+
+impl Tolerance {
+    fn allows(&self, golden: i64, actual: i64) -> bool {
+        let limit = (self.abs_usd * 1e6).max(self.rel * golden.unsigned_abs() as f64);
+        golden.abs_diff(actual) as f64 <= limit
+    }
+}
+
+/// Key outputs of one run (money in micro-USD).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GoldenRun {
+    pub scenario: String,
+    pub seed: u64,
+    pub executions: u64,
+    pub volume_usd: i64,
+    pub fees_usd: i64,
+    pub realized_pnl_usd: i64,
+    pub liquidations: u64,
+    /// Last (long, short) open interest per market
+    pub final_oi: BTreeMap<String, (u64, u64)>,
+    /// Total PnL per agent
+    pub agent_pnl: BTreeMap<AgentId, i64>,
+}
+
+impl GoldenRun {
+    /// Collect the outputs of a finished run from its logs directory.
+    pub fn from_logs(dir: &Path, scenario: &str, seed: u64) -> Result<Self, String> {
+        let path = dir.join("summary.json");
+        let content = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let summary: RunSummary = serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let path = dir.join("executions.csv");
+        let content = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let mut run = Self {
+            scenario: scenario.to_string(),
+            seed,
+            liquidations: summary.total_liquidations,
+            ..Default::default()
+        };
+        run.add_executions(&content)?;
+        for point in &summary.oi_timeline {
+            run.final_oi
+                .insert(point.symbol.clone(), (point.oi_long_usd, point.oi_short_usd));
+        }
+        for agent in &summary.agents {
+            run.agent_pnl.insert(agent.account, agent.total_pnl);
+        }
+        Ok(run)
+    }
+
+    /// Totals of executions.csv (size_usd, pnl and fee_usd columns).
+    fn add_executions(&mut self, csv: &str) -> Result<(), String> {
+        for (i, line) in csv.lines().enumerate().skip(1) {
+            if line.is_empty() {
+                continue;
+            }
+            let cols: Vec<&str> = line.split(',').collect();
+            let field = |idx: usize| -> Result<i64, String> {
+                let value = cols
+                    .get(idx)
+                    .ok_or_else(|| format!("executions.csv:{}: too few columns", i + 1))?;
+                value
+                    .parse::<i64>()
+                    .map_err(|e| format!("executions.csv:{}: {}: {}", i + 1, value, e))
+            };
+            self.executions += 1;
+            self.volume_usd += field(4)?;
+            self.realized_pnl_usd += field(9)?;
+            self.fees_usd += field(10)?;
+        }
+        Ok(())
+    }
+
+    /// Differences to the golden run; empty when the run matches.
+    pub fn diff(&self, golden: &GoldenRun, tol: &Tolerance) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut exact = |name: &str, golden: u64, actual: u64| {
+            if golden != actual {
+                out.push(format!("{}: {} -> {}", name, golden, actual));
+            }
+        };
+        exact("executions", golden.executions, self.executions);
+        exact("liquidations", golden.liquidations, self.liquidations);
+
+        let mut money = |name: String, golden: i64, actual: i64| {
+            if !tol.allows(golden, actual) {
+                out.push(format!(
+                    "{}: ${:.2} -> ${:.2}",
+                    name,
+                    golden as f64 / 1e6,
+                    actual as f64 / 1e6
+                ));
+            }
+        };
+        money("volume".to_string(), golden.volume_usd, self.volume_usd);
+        money("fees".to_string(), golden.fees_usd, self.fees_usd);
+        money(
+            "realized_pnl".to_string(),
+            golden.realized_pnl_usd,
+            self.realized_pnl_usd,
+        );
+
+        let symbols: BTreeSet<&String> = golden.final_oi.keys().chain(self.final_oi.keys()).collect();
+        for symbol in symbols {
+            let (g_long, g_short) = golden.final_oi.get(symbol).copied().unwrap_or_default();
+            let (a_long, a_short) = self.final_oi.get(symbol).copied().unwrap_or_default();
+            money(format!("final_oi_long[{}]", symbol), g_long as i64, a_long as i64);
+            money(format!("final_oi_short[{}]", symbol), g_short as i64, a_short as i64);
+        }
+
+        let accounts: BTreeSet<&AgentId> = golden.agent_pnl.keys().chain(self.agent_pnl.keys()).collect();
+        for account in accounts {
+            let g = golden.agent_pnl.get(account).copied().unwrap_or(0);
+            let a = self.agent_pnl.get(account).copied().unwrap_or(0);
+            money(format!("pnl[{}]", account), g, a);
+        }
+        out
+    }
+}
+
+/// Scenarios with a golden file, sorted by name.
+fn golden_scenarios(dir: &Path) -> Result<Vec<String>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let mut names: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|p| Some(p.file_stem()?.to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Run one scenario offline into `<logs_dir>/golden_<scenario>` and collect its outputs.
+fn run_scenario(catalog: &Catalog, scenario: &str, sets: &[String]) -> Result<GoldenRun, String> {
+    let path = catalog
+        .find(scenario)
+        .ok_or_else(|| format!("scenario '{}' not found in {}", scenario, catalog.dir().display()))?;
+    let path = path.to_string_lossy().into_owned();
+    let base = SimConfig::from_file(&path, sets)?;
+    let dir = Path::new(base.logs_dir()).join(format!("golden_{}", base.scenario_name()));
+    let mut run_sets = sets.to_vec();
+    let logs_dir = serde_json::Value::from(dir.display().to_string());
+    run_sets.push(format!("logs_dir={}", logs_dir));
+    let config = SimConfig::from_file(&path, &run_sets)?;
+    let seed = config.seed();
+
+    let client = simple_demo::offline_vara(&config)?;
+    simple_demo::run_with_config(config, true, client)?;
+    GoldenRun::from_logs(&dir, scenario, seed)
+}
+
+/// Run `scenarios` (default: every scenario with a golden file) and compare, or with
+/// `update` store the results as the new golden files.
+pub fn run(catalog: &Catalog, scenarios: &[String], sets: &[String], opts: &GoldenOptions) -> Result<(), String> {
+    let dir = opts.dir.clone().unwrap_or_else(|| catalog.dir().join("golden"));
+    let scenarios = if scenarios.is_empty() {
+        golden_scenarios(&dir)?
+    } else {
+        scenarios.to_vec()
+    };
+    if scenarios.is_empty() {
+        return Err(format!("no golden files in {}", dir.display()));
+    }
+
+    let mut failed = Vec::new();
+    for scenario in &scenarios {
+        let file = dir.join(format!("{}.json", scenario));
+        let actual = run_scenario(catalog, scenario, sets)?;
+
+        if opts.update {
+            let json = serde_json::to_string_pretty(&actual).map_err(|e| e.to_string())?;
+            std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+            std::fs::write(&file, json + "\n").map_err(|e| format!("{}: {}", file.display(), e))?;
+            println!("[Golden] {}: written to {}", scenario, file.display());
+            continue;
+        }
+
+        let content = std::fs::read_to_string(&file)
+            .map_err(|e| format!("{}: {} (create it with --update)", file.display(), e))?;
+        let golden: GoldenRun = serde_json::from_str(&content).map_err(|e| format!("{}: {}", file.display(), e))?;
+        let diffs = actual.diff(&golden, &opts.tolerance);
+        if diffs.is_empty() {
+            println!("[Golden] {}: ok", scenario);
+        } else {
+            println!("[Golden] {}: {} difference(s)", scenario, diffs.len());
+            for d in &diffs {
+                println!("[Golden]   {}", d);
+            }
+            failed.push(scenario.clone());
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("outputs changed for {}", failed.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOL: Tolerance = Tolerance {
+        abs_usd: 1.0,
+        rel: 0.01,
+    };
+
+    fn golden() -> GoldenRun {
+        GoldenRun {
+            scenario: "small".to_string(),
+            executions: 2,
+            volume_usd: 1_000_000_000,
+            fees_usd: 1_000_000,
+            final_oi: BTreeMap::from([("ETH-USD".to_string(), (500_000_000, 0))]),
+            agent_pnl: BTreeMap::from([(10, 50_000_000), (11, -2_000_000)]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_executions_totals() {
+        let csv = "ts,account,symbol,side,size_usd,collateral,execution_price,leverage,order_type,pnl,fee_usd,funding_fee_usd\n\
+                   1,10,ETH-USD,Buy,500000000,100000000,3000000000,5,Increase,0,500000,0\n\
+                   2,10,ETH-USD,Buy,500000000,-100000000,3100000000,5,Decrease,16000000,500000,-10\n";
+        let mut run = GoldenRun::default();
+        run.add_executions(csv).unwrap();
+        assert_eq!(run.executions, 2);
+        assert_eq!(run.volume_usd, 1_000_000_000);
+        assert_eq!(run.realized_pnl_usd, 16_000_000);
+        assert_eq!(run.fees_usd, 1_000_000);
+        assert!(GoldenRun::default().add_executions("header\n1,2,3\n").is_err());
+    }
+
+    #[test]
+    fn test_diff_within_tolerance() {
+        let mut run = golden();
+        assert!(run.diff(&golden(), &TOL).is_empty());
+        // $0.50 off: below the $1 floor; 0.5% of the volume: below 1%
+        run.fees_usd += 500_000;
+        run.volume_usd += 5_000_000;
+        assert!(run.diff(&golden(), &TOL).is_empty());
+    }
+
+    #[test]
+    fn test_diff_reports_changes() {
+        let mut run = golden();
+        run.executions = 3;
+        run.agent_pnl.insert(11, -5_000_000);
+        run.agent_pnl.insert(12, 9_000_000);
+        run.final_oi.insert("ETH-USD".to_string(), (500_000_000, 20_000_000));
+        let diffs = run.diff(&golden(), &TOL);
+        assert_eq!(diffs.len(), 4);
+        assert_eq!(diffs[0], "executions: 2 -> 3");
+        assert!(diffs.iter().any(|d| d.starts_with("final_oi_short[ETH-USD]")));
+        assert!(diffs.iter().any(|d| d.starts_with("pnl[11]")));
+        assert!(diffs.iter().any(|d| d.starts_with("pnl[12]")));
+    }
+
+    #[test]
+    fn test_golden_files_are_reproduced() {
+        let catalog = Catalog::new(Some(Path::new(env!("CARGO_MANIFEST_DIR")).join("src/scenarios")));
+        let logs = std::env::temp_dir().join(format!("golden-{}", std::process::id()));
+        let sets = vec![format!(
+            "logs_dir={}",
+            serde_json::Value::from(logs.display().to_string())
+        )];
+        let opts = GoldenOptions {
+            dir: None,
+            update: false,
+            tolerance: Tolerance {
+                abs_usd: 1.0,
+                rel: 0.001,
+            },
+        };
+        let result = run(&catalog, &[], &sets, &opts);
+        let _ = std::fs::remove_dir_all(&logs);
+        assert_eq!(result, Ok(()));
+    }
+}
@@ -1,4 +1,11 @@
 // src/scenarios/mod.rs
 // Scenario modules.
 
+pub mod batch;
+pub mod catalog;
+pub mod deploy;
+pub mod golden;
+mod overrides;
+pub(crate) mod population;
 pub mod simple_demo;
+pub mod vara_smoke;
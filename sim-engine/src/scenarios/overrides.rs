@@ -0,0 +1,172 @@
+// Scenario inheritance (`"extends": "base_scenario"`) and CLI overrides
+// (`--set smart_traders[0].leverage=20`) on the raw JSON document.
+//
+// A scenario that extends another one is deep-merged onto its base: objects
+// merge key by key, everything else (arrays included) replaces the base value.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+/// Read a scenario document and resolve its `extends` chain.
+/// Returns the merged document and whether any base was involved.
+pub fn load_with_extends(path: &Path) -> Result<(Value, bool), String> {
+    let mut chain = Vec::new();
+    let doc = load_chain(path, &mut chain)?;
+    Ok((doc, chain.len() > 1))
+}
+
+fn load_chain(path: &Path, chain: &mut Vec<PathBuf>) -> Result<Value, String> {
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if chain.contains(&canonical) {
+        return Err(format!("{}: circular extends", path.display()));
+    }
+    chain.push(canonical);
+
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut doc: Value = serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let base = match doc.as_object_mut().and_then(|obj| obj.remove("extends")) {
+        None => return Ok(doc),
+        Some(Value::String(base)) => base,
+        Some(other) => return Err(format!("{}: extends: expected a scenario name, got {}", path.display(), other)),
+    };
+
+    let base_path = base_path(path, &base);
+    let mut merged = load_chain(&base_path, chain)?;
+    merge(&mut merged, doc);
+    Ok(merged)
+}
+
+/// Base scenarios are looked up next to the extending file.
+fn base_path(path: &Path, base: &str) -> PathBuf {
+    let file = if base.ends_with(".json") {
+        base.to_string()
+    } else {
+        format!("{}.json", base)
+    };
+    path.parent().map(|dir| dir.join(&file)).unwrap_or_else(|| PathBuf::from(file))
+}
+
+/// Deep-merge `patch` onto `base`: objects merge recursively, other values replace.
+pub fn merge(base: &mut Value, patch: Value) {
+    match (base, patch) {
+        (Value::Object(base), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, patch) => *base = patch,
+    }
+}
+
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+fn parse_path(path: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        let (key, mut rest) = match part.find('[') {
+            Some(pos) => (&part[..pos], &part[pos..]),
+            None => (part, ""),
+        };
+        if key.is_empty() && segments.is_empty() {
+            return Err(format!("'{}': path must start with a field name", path));
+        }
+        if !key.is_empty() {
+            segments.push(Segment::Key(key.to_string()));
+        }
+        while !rest.is_empty() {
+            let end = rest.find(']').ok_or_else(|| format!("'{}': missing ']'", path))?;
+            let index = rest[1..end]
+                .parse::<usize>()
+                .map_err(|_| format!("'{}': invalid index '{}'", path, &rest[1..end]))?;
+            segments.push(Segment::Index(index));
+            rest = &rest[end + 1..];
+            if !rest.is_empty() && !rest.starts_with('[') {
+                return Err(format!("'{}': unexpected '{}'", path, rest));
+            }
+        }
+    }
+    Ok(segments)
+}
+
+/// Apply one `path=value` override. The value is parsed as JSON, falling back to a
+/// plain string (`--set logs_dir=logs/run1`). Missing object keys are created;
+/// array indices must exist (or equal the length to append).
+pub fn apply_set(doc: &mut Value, expr: &str) -> Result<(), String> {
+    let (path, raw) = expr
+        .split_once('=')
+        .ok_or_else(|| format!("--set {}: expected PATH=VALUE", expr))?;
+    let value = serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()));
+    let segments = parse_path(path.trim()).map_err(|e| format!("--set {}", e))?;
+
+    let mut target = doc;
+    for segment in &segments {
+        target = match segment {
+            Segment::Key(key) => {
+                if target.is_null() {
+                    *target = Value::Object(Default::default());
+                }
+                let obj = target
+                    .as_object_mut()
+                    .ok_or_else(|| format!("--set {}: '{}' is not inside an object", path, key))?;
+                obj.entry(key.clone()).or_insert(Value::Null)
+            }
+            Segment::Index(index) => {
+                let arr = target
+                    .as_array_mut()
+                    .ok_or_else(|| format!("--set {}: [{}] is not inside an array", path, index))?;
+                if *index == arr.len() {
+                    arr.push(Value::Null);
+                }
+                let len = arr.len();
+                arr.get_mut(*index)
+                    .ok_or_else(|| format!("--set {}: index {} out of range (len {})", path, index, len))?
+            }
+        };
+    }
+    *target = value;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_objects_and_replace_arrays() {
+        let mut base = json!({"duration_sec": 60, "exchange": {"id": 1, "name": "Ex"}, "keepers": [{"id": 1}]});
+        merge(&mut base, json!({"exchange": {"name": "Ex2"}, "keepers": []}));
+        assert_eq!(base, json!({"duration_sec": 60, "exchange": {"id": 1, "name": "Ex2"}, "keepers": []}));
+    }
+
+    #[test]
+    fn test_apply_set() {
+        let mut doc = json!({"duration_sec": 60, "smart_traders": [{"leverage": 5}]});
+        apply_set(&mut doc, "duration_sec=300").unwrap();
+        apply_set(&mut doc, "smart_traders[0].leverage=20").unwrap();
+        apply_set(&mut doc, "logs_dir=logs/run1").unwrap();
+        apply_set(&mut doc, "logging.oracle_log=false").unwrap();
+        assert_eq!(doc["duration_sec"], 300);
+        assert_eq!(doc["smart_traders"][0]["leverage"], 20);
+        assert_eq!(doc["logs_dir"], "logs/run1");
+        assert_eq!(doc["logging"]["oracle_log"], false);
+    }
+
+    #[test]
+    fn test_apply_set_errors() {
+        let mut doc = json!({"smart_traders": [{"leverage": 5}]});
+        assert!(apply_set(&mut doc, "smart_traders[3].leverage=1").is_err());
+        assert!(apply_set(&mut doc, "smart_traders.leverage=1").is_err());
+        assert!(apply_set(&mut doc, "duration_sec").is_err());
+    }
+}
@@ -0,0 +1,264 @@
+// Population generator: expands one `populations` entry into `count` agent
+// parameter sets with seeded sampling, so cohorts of hundreds of traders
+// don't have to be written out by hand.
+//
+// Any parameter of the template may be a distribution instead of a value:
+//   {"min": 10, "max": 50}                               uniform (integer if both bounds are)
+//   {"dist": "uniform", "min": 0.1, "max": 2.0}
+//   {"dist": "normal", "mean": 5, "std": 2, "min": 1, "int": true}
+//   {"dist": "lognormal", "mu": 0.0, "sigma": 0.5, "max": 10}
+//   {"dist": "choice", "values": ["long", "short"], "weights": [3, 1]}
+// Same seed + same template = same population.
+
+use serde_json::{Map, Number, Value};
+
+/// splitmix64: small, seedable and good enough for parameter sampling.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Standard normal (Box-Muller)
+    pub fn normal(&mut self) -> f64 {
+        let u1 = self.next_f64().max(f64::MIN_POSITIVE);
+        let u2 = self.next_f64();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+enum Dist {
+    Uniform { min: f64, max: f64 },
+    Normal { mean: f64, std: f64 },
+    LogNormal { mu: f64, sigma: f64 },
+    Choice { values: Vec<Value>, weights: Vec<f64> },
+}
+
+struct Sampler {
+    dist: Dist,
+    min: Option<f64>,
+    max: Option<f64>,
+    int: bool,
+}
+
+fn num(obj: &Map<String, Value>, key: &str, field: &str) -> Result<Option<f64>, String> {
+    match obj.get(key) {
+        None => Ok(None),
+        Some(v) => v
+            .as_f64()
+            .map(Some)
+            .ok_or_else(|| format!("{}.{}: expected a number", field, key)),
+    }
+}
+
+fn required(obj: &Map<String, Value>, key: &str, field: &str) -> Result<f64, String> {
+    num(obj, key, field)?.ok_or_else(|| format!("{}: missing '{}'", field, key))
+}
+
+/// Distribution described by `obj`, or None if it is a plain object value.
+fn parse_sampler(field: &str, obj: &Map<String, Value>) -> Result<Option<Sampler>, String> {
+    let dist = match obj.get("dist") {
+        Some(Value::String(d)) => d.as_str(),
+        Some(_) => return Err(format!("{}.dist: expected a string", field)),
+        None if obj.contains_key("min") && obj.contains_key("max") && obj.len() == 2 => "uniform",
+        None => return Ok(None),
+    };
+
+    let min = num(obj, "min", field)?;
+    let max = num(obj, "max", field)?;
+    let bounds_int = [obj.get("min"), obj.get("max")]
+        .iter()
+        .all(|v| v.map(|v| v.is_i64() || v.is_u64()).unwrap_or(false));
+    let int = match obj.get("int") {
+        Some(v) => v.as_bool().ok_or_else(|| format!("{}.int: expected a bool", field))?,
+        None => dist == "uniform" && bounds_int,
+    };
+
+    let dist = match dist {
+        "uniform" => {
+            let (min, max) = (required(obj, "min", field)?, required(obj, "max", field)?);
+            if max < min {
+                return Err(format!("{}: max < min", field));
+            }
+            Dist::Uniform { min, max }
+        }
+        "normal" => Dist::Normal {
+            mean: required(obj, "mean", field)?,
+            std: required(obj, "std", field)?,
+        },
+        "lognormal" => Dist::LogNormal {
+            mu: required(obj, "mu", field)?,
+            sigma: required(obj, "sigma", field)?,
+        },
+        "choice" => {
+            let values = obj
+                .get("values")
+                .and_then(|v| v.as_array())
+                .filter(|v| !v.is_empty())
+                .cloned()
+                .ok_or_else(|| format!("{}: 'choice' needs a non-empty 'values' array", field))?;
+            let weights = match obj.get("weights") {
+                None => vec![1.0; values.len()],
+                Some(w) => {
+                    let w: Vec<f64> = w
+                        .as_array()
+                        .map(|a| a.iter().filter_map(|x| x.as_f64()).collect())
+                        .unwrap_or_default();
+                    if w.len() != values.len() || w.iter().any(|x| *x < 0.0) || w.iter().sum::<f64>() <= 0.0 {
+                        return Err(format!("{}.weights: one non-negative weight per value", field));
+                    }
+                    w
+                }
+            };
+            Dist::Choice { values, weights }
+        }
+        other => return Err(format!("{}.dist: unknown distribution '{}'", field, other)),
+    };
+
+    Ok(Some(Sampler { dist, min, max, int }))
+}
+
+impl Sampler {
+    fn sample(&self, rng: &mut Rng) -> Value {
+        let mut x = match &self.dist {
+            Dist::Uniform { min, max } => {
+                if self.int {
+                    // Inclusive integer range
+                    let span = (max.floor() - min.ceil()) as u64 + 1;
+                    min.ceil() + (rng.next_u64() % span) as f64
+                } else {
+                    min + (max - min) * rng.next_f64()
+                }
+            }
+            Dist::Normal { mean, std } => mean + std * rng.normal(),
+            Dist::LogNormal { mu, sigma } => (mu + sigma * rng.normal()).exp(),
+            Dist::Choice { values, weights } => {
+                let total: f64 = weights.iter().sum();
+                let mut pick = rng.next_f64() * total;
+                for (value, w) in values.iter().zip(weights) {
+                    if pick < *w {
+                        return value.clone();
+                    }
+                    pick -= w;
+                }
+                return values[values.len() - 1].clone();
+            }
+        };
+
+        if let Some(min) = self.min {
+            x = x.max(min);
+        }
+        if let Some(max) = self.max {
+            x = x.min(max);
+        }
+        if self.int {
+            let r = x.round();
+            if r >= 0.0 {
+                Value::Number(Number::from(r as u64))
+            } else {
+                Value::Number(Number::from(r as i64))
+            }
+        } else {
+            Number::from_f64(x).map(Value::Number).unwrap_or(Value::Null)
+        }
+    }
+}
+
+/// Expand a template into `count` parameter sets with ids `id_start..` and
+/// names `<name_prefix>_<id>`.
+pub fn expand(
+    template: &Map<String, Value>,
+    count: u32,
+    id_start: u32,
+    name_prefix: &str,
+    seed: u64,
+) -> Result<Vec<Map<String, Value>>, String> {
+    let mut samplers = Vec::new();
+    for (key, value) in template {
+        if key == "id" || key == "name" {
+            return Err(format!("{}: set by the generator (use id_start / name_prefix)", key));
+        }
+        let sampler = match value {
+            Value::Object(obj) => parse_sampler(key, obj)?,
+            _ => None,
+        };
+        samplers.push((key, sampler));
+    }
+
+    let mut rng = Rng::new(seed);
+    let mut out = Vec::with_capacity(count as usize);
+    for i in 0..count {
+        let id = id_start
+            .checked_add(i)
+            .ok_or_else(|| "id_start + count overflows".to_string())?;
+        let mut params = Map::new();
+        params.insert("id".to_string(), Value::from(id));
+        params.insert("name".to_string(), Value::from(format!("{}_{}", name_prefix, id)));
+        for (key, sampler) in &samplers {
+            let value = match sampler {
+                Some(s) => s.sample(&mut rng),
+                None => template[key.as_str()].clone(),
+            };
+            params.insert(key.to_string(), value);
+        }
+        out.push(params);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn template(v: Value) -> Map<String, Value> {
+        v.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_expand_is_seeded() {
+        let t = template(json!({
+            "strategy": "hodler",
+            "leverage": {"min": 10, "max": 50},
+            "qty_min": {"dist": "lognormal", "mu": 0.0, "sigma": 0.5, "max": 5.0},
+            "side": {"dist": "choice", "values": ["long", "short"]}
+        }));
+        let a = expand(&t, 100, 1000, "Risky", 7).unwrap();
+        let b = expand(&t, 100, 1000, "Risky", 7).unwrap();
+        let c = expand(&t, 100, 1000, "Risky", 8).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        assert_eq!(a.len(), 100);
+        assert_eq!(a[0]["id"], 1000);
+        assert_eq!(a[99]["name"], "Risky_1099");
+        for p in &a {
+            let lev = p["leverage"].as_u64().unwrap();
+            assert!((10..=50).contains(&lev));
+            let qty = p["qty_min"].as_f64().unwrap();
+            assert!(qty > 0.0 && qty <= 5.0);
+            assert_eq!(p["strategy"], "hodler");
+        }
+    }
+
+    #[test]
+    fn test_expand_rejects_bad_distributions() {
+        assert!(expand(&template(json!({"x": {"dist": "pareto"}})), 1, 1, "a", 0).is_err());
+        assert!(expand(&template(json!({"x": {"min": 5, "max": 1}})), 1, 1, "a", 0).is_err());
+        assert!(expand(&template(json!({"id": 3})), 1, 1, "a", 0).is_err());
+    }
+}
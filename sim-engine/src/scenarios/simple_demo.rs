@@ -1,27 +1,77 @@
+use crate::activity_schedule::ActivitySchedule;
 use crate::agents::{
-    exchange_agent::{ExchangeAgent, MarketConfig},
+    Agent,
+    arbitrage_agent::{ArbitrageAgent, ArbitrageConfig},
+    balance_monitor_agent::{BalanceMonitorAgent, BalanceMonitorConfig, TopUpConfig, TopUpSource},
+    bridge_strategy::{BridgeAgent, BridgeConfig, BridgeStrategy, TimeoutPolicy},
+    copy_trader_agent::{CopyTraderAgent, CopyTraderConfig},
+    director_agent::{DirectorAgent, TimelineAction, TimelineEvent},
+    exchange_agent::{CollateralPrice, ExchangeAgent, FeeRates, LocalFallbackConfig, MarketConfig},
+    funding_arb_agent::{FundingArbAgent, FundingArbConfig},
+    hedger_agent::{HedgeLeg, HedgerAgent, HedgerConfig},
     human_agent::HumanAgent,
     keeper_agent::{KeeperAgent, KeeperConfig},
     limit_trader_agent::{LimitStrategy, LimitTraderAgent, LimitTraderConfig, OrderMode},
     liquidation_agent::LiquidationAgent,
+    lp_agent::{LpAgent, LpConfig},
     market_maker_agent::{MarketMakerAgent, MarketMakerConfig},
-    oracle_agent::OracleAgent,
+    noise_trader_agent::{NoiseTraderAgent, NoiseTraderConfig},
+    oracle_agent::{DeviationTrigger, FailoverConfig, OracleAgent},
+    portfolio_agent::{ExposureLimits, PortfolioAgent},
+    quoting_mm_agent::{QuotingMarketMakerAgent, QuotingMmConfig},
+    remote_strategy::{RemoteAgent, RemoteConfig, RemoteStrategy},
+    risk_monitor_agent::{RiskMonitorAgent, RiskMonitorConfig},
+    scripted_strategy::{ScriptedAgent, ScriptedConfig, ScriptedStrategy},
+    sma_cross_strategy::{SmaCrossConfig, SmaCrossStrategy},
     smart_trader_agent::{SmartTraderAgent, SmartTraderConfig, TradingStrategy},
+    strategy_agent::StrategyAgent,
+    swap_exchange_agent::{SwapExchangeAgent, SwapExchangeConfig, SwapPoolConfig},
+    whale_agent::{WhaleAgent, WhaleConfig},
+};
+use crate::api::{
+    ApiCommand, ApiLimits, ApiResponse, BinanceProvider, CachedPriceProvider, CommandGuard, CorsConfig,
+    PlaybackProvider, PriceProvider, PythProvider, RecordingProvider, RemoteHub, SessionHub, SyntheticConfig,
+    SyntheticProvider,
 };
-use crate::api::{CachedPriceProvider, PythProvider};
 use crate::events::{EventListener, SimEvent};
-use crate::logging::{CsvExecutionLogger, CsvLiquidationLogger, CsvMarketLogger, CsvOracleLogger, CsvPositionLogger};
-use crate::messages::Side;
+use crate::logging::{
+    CsvChainTxLogger, CsvExecutionLogger, CsvInboxLogger, CsvLiquidationLogger, CsvMarketLogger, CsvOracleLogger,
+    CsvOrderLogger, CsvPositionLogger, CsvRiskLogger, CsvSwapLogger, LogRotation, Verbosity,
+};
+use crate::messages::{MessageType, Side};
+use crate::blotter::TradeBlotter;
+use crate::capital::{CapitalConfig, CapitalLedger};
+use crate::cascades::{CascadeConfig, CascadeTracker};
+use crate::circuit_breaker::CircuitBreakerConfig;
+use crate::equity::EquityTracker;
+use crate::exchange_state::{ExchangeState, STATE_FILE};
+use crate::flow_stats::{FlowStatsBook, FlowStatsTracker, MarketStatsConfig};
+use crate::kernel::DeadLetterPolicy;
+use crate::mark_price::MarkPriceConfig;
+use crate::oi_caps::OiCaps;
+use crate::pnl::{PnlBook, PnlTracker};
+use crate::price_rules::PriceRules;
+use crate::replay::Replay;
+use crate::report::ReportBuilder;
+use crate::risk_limits::{RiskGuard, RiskLimitsConfig};
+use crate::scale::{MicroUsd, TokenScale};
 use crate::sim_engine::SimEngine;
-use crate::vara::VaraClient;
-use crate::vara::keystore::normalize_agent_id;
+use crate::spread_model::SpreadModel;
+use crate::vara::keystore::agent_key_name;
+use crate::vara::oracle::PEGGED_COLLATERAL_MICRO;
+use crate::vara::{ReorgConfig, TxSpendConfig, TxTips, VaraClient, VaraConfig};
+use crate::watchdog::WatchdogConfig;
+use crate::webhooks::{WebhookConfig, WebhookSink};
+use super::catalog::Catalog;
+use super::{overrides, population};
+use crossbeam_channel::{Receiver, Sender};
 use primitive_types::U256;
 
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::fs::{self, File};
-use std::io::{BufWriter, Write};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
 use std::sync::Arc;
 
 struct ClosureListener<F: FnMut(&SimEvent)> {
@@ -35,6 +85,7 @@ impl<F: FnMut(&SimEvent)> EventListener for ClosureListener<F> {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct LiquidityConfig {
     collateral_amount: i128,
     index_amount: i128,
@@ -49,10 +100,12 @@ struct AddressEntry {
 
 struct AddressBook {
     entries: HashMap<String, String>,
+    /// Explicit agent_id -> key name mapping (scenario `vara.keys`)
+    agent_keys: HashMap<u32, String>,
 }
 
 impl AddressBook {
-    fn load() -> Self {
+    fn load(agent_keys: HashMap<u32, String>) -> Self {
         // Try to find addresses.json in multiple locations
         let path = std::env::var("VARA_ADDRESS_BOOK").ok().or_else(|| {
             let candidates = [
@@ -89,13 +142,11 @@ impl AddressBook {
             }
         };
 
-        Self { entries }
+        Self { entries, agent_keys }
     }
 
     fn address_for_agent(&self, id: u32) -> Option<String> {
-        let normalized = normalize_agent_id(id);
-        let name = format!("bot_{:03}", normalized);
-        self.entries.get(&name).cloned()
+        self.entries.get(&agent_key_name(id, &self.agent_keys)).cloned()
     }
 
     fn write_funding_list(&self, path: &str) {
@@ -127,6 +178,7 @@ impl AddressBook {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct MarketJsonConfig {
     id: u32,
     symbol: String,
@@ -137,6 +189,27 @@ struct MarketJsonConfig {
     #[serde(default = "default_collateral_decimals")]
     collateral_decimals: u32,
     initial_liquidity: LiquidityConfig,
+    /// Position open/close fee in bps (must match contract close_position_fee_bps)
+    #[serde(default = "default_position_fee_bps")]
+    position_fee_bps: u32,
+    /// Funding/borrowing parameters of the contract's InitConfig, used to estimate the rates
+    #[serde(default)]
+    fee_rates: FeeRates,
+    /// Oracle feed of a non-stable collateral token; absent = pegged at $1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    collateral_price: Option<CollateralPrice>,
+    /// Tick size and band around the oracle price for order prices; absent = any price
+    #[serde(default)]
+    price_rules: PriceRules,
+    /// Mark price for liquidations and uPnL, and the fill spread; absent = both at the index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mark_price: Option<MarkPriceConfig>,
+    /// Halt of new increases after an extreme oracle move; absent = never halted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    circuit_breaker: Option<CircuitBreakerConfig>,
+    /// Caps on total and per-side OI for new increases; absent = uncapped
+    #[serde(default)]
+    oi_caps: OiCaps,
 }
 
 fn default_index_decimals() -> u32 {
@@ -147,14 +220,39 @@ fn default_collateral_decimals() -> u32 {
     6 // USDT default
 }
 
+fn default_position_fee_bps() -> u32 {
+    10 // 0.10%
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct ExchangeConfig {
     id: u32,
     name: String,
     markets: Vec<MarketJsonConfig>,
+    /// Interval for position/market snapshots (positions.csv, markets.csv); 0 disables
+    #[serde(default = "default_snapshot_interval")]
+    snapshot_interval_ms: u64,
+    /// Recent fills kept in memory for GetTrades / GET /trades
+    #[serde(default = "default_trade_history")]
+    trade_history: usize,
+    #[serde(flatten)]
+    common: AgentCommonJsonConfig,
+    /// Agent groups of the exchange (see `broadcast_groups`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<String>,
+}
+
+fn default_snapshot_interval() -> u64 {
+    15000 // every 5 blocks
+}
+
+fn default_trade_history() -> usize {
+    TradeBlotter::DEFAULT_CAPACITY
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct OracleConfig {
     id: u32,
     name: String,
@@ -163,20 +261,63 @@ struct OracleConfig {
     cache_duration_ms: u64,
     #[serde(default = "default_wake_interval")]
     wake_interval_ms: u64,
+    /// Synthetic min/max band from simulated volatility and utilization; default: provider confidence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spread: Option<SpreadModel>,
+    /// Archive every fetched price (with VAA) to this JSONL file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    record: Option<String>,
+    /// Replay prices from a `record` file instead of fetching them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    playback: Option<String>,
+    /// Providers taking over, in order, while `provider` keeps failing ("Pyth", "Binance")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    fallback_providers: Vec<String>,
+    /// Consecutive blocks with a failed symbol before switching provider
+    #[serde(default = "default_failover_after")]
+    failover_after: u32,
+    /// Blocks on a fallback between probes of the primary
+    #[serde(default = "default_recovery_blocks")]
+    recovery_blocks: u64,
+    /// Publish a symbol only after its mid moved this far (bps) or `max_age_ms` passed; default: every block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deviation_bps: Option<f64>,
+    /// Heartbeat of the deviation mode
+    #[serde(default = "default_max_age_ms")]
+    max_age_ms: u64,
+    /// Random-walk prices of the `Synthetic` provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    synthetic: Option<SyntheticJsonConfig>,
+    /// Agents receiving the ticks besides the exchange; default: every agent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    subscribers: Option<Vec<u32>>,
+    #[serde(flatten)]
+    common: AgentCommonJsonConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct SmartTraderJsonConfig {
     id: u32,
     name: String,
+    /// Traded market (single-symbol traders; empty when `symbols` is used)
+    #[serde(default)]
     symbol: String,
+    /// Portfolio legs: one account trading several markets
+    #[serde(default)]
+    symbols: Vec<PortfolioLegJsonConfig>,
+    /// Portfolio exposure limits over all legs (micro-USD notional)
+    #[serde(default)]
+    max_gross_usd: Option<i128>,
+    #[serde(default)]
+    max_net_usd: Option<i128>,
     strategy: String,
     #[serde(default = "default_side")]
     side: String,
     #[serde(default = "default_leverage")]
     leverage: u32,
     #[serde(default = "default_qty")]
-    qty: u64,
+    qty: f64,
     #[serde(default)]
     qty_min: Option<f64>,
     #[serde(default)]
@@ -207,8 +348,38 @@ struct SmartTraderJsonConfig {
     exit_deviation_pct: Option<f64>,
     #[serde(default)]
     balance: Option<i128>,
+    #[serde(flatten)]
+    common: AgentCommonJsonConfig,
+}
+
+/// One market of a multi-symbol trader; unset fields fall back to the trader's own.
+/// The trader's `balance` is split evenly over legs without their own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PortfolioLegJsonConfig {
+    symbol: String,
     #[serde(default)]
-    start_delay_ms: Option<u64>,
+    qty: Option<f64>,
+    #[serde(default)]
+    leverage: Option<u32>,
+    #[serde(default)]
+    balance: Option<i128>,
+}
+
+/// `symbol` xor `symbols`, without duplicate legs.
+fn check_legs(symbol: &str, legs: &[PortfolioLegJsonConfig]) -> Result<(), String> {
+    match (symbol.is_empty(), legs.is_empty()) {
+        (true, true) => return Err("either symbol or symbols is required".to_string()),
+        (false, false) => return Err("symbol and symbols are mutually exclusive".to_string()),
+        _ => {}
+    }
+    let mut seen = HashSet::new();
+    for leg in legs {
+        if !seen.insert(leg.symbol.as_str()) {
+            return Err(format!("symbols: duplicate symbol '{}'", leg.symbol));
+        }
+    }
+    Ok(())
 }
 
 fn default_side() -> String {
@@ -219,8 +390,8 @@ fn default_leverage() -> u32 {
     5
 }
 
-fn default_qty() -> u64 {
-    1
+fn default_qty() -> f64 {
+    1.0
 }
 
 fn default_hold_duration() -> u64 {
@@ -240,11 +411,14 @@ fn default_smart_wake_interval() -> u64 {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct LiquidationAgentConfig {
     id: u32,
     name: String,
     #[serde(default = "default_liquidation_wake_interval")]
     wake_interval_ms: u64,
+    #[serde(flatten)]
+    common: AgentCommonJsonConfig,
 }
 
 fn default_liquidation_wake_interval() -> u64 {
@@ -252,6 +426,7 @@ fn default_liquidation_wake_interval() -> u64 {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct MarketMakerJsonConfig {
     id: u32,
     name: String,
@@ -268,13 +443,23 @@ struct MarketMakerJsonConfig {
     wake_interval_ms: u64,
     #[serde(default = "default_mm_balance")]
     balance: i128,
+    #[serde(flatten)]
+    common: AgentCommonJsonConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct LimitTraderJsonConfig {
     id: u32,
     name: String,
+    #[serde(default)]
     symbol: String,
+    #[serde(default)]
+    symbols: Vec<PortfolioLegJsonConfig>,
+    #[serde(default)]
+    max_gross_usd: Option<i128>,
+    #[serde(default)]
+    max_net_usd: Option<i128>,
     strategy: String,
     #[serde(default = "default_leverage")]
     leverage: u32,
@@ -292,6 +477,9 @@ struct LimitTraderJsonConfig {
     stop_loss_pct: Option<f64>,
     #[serde(default)]
     take_profit_pct: Option<f64>,
+    /// Trailing stop distance in % (replaces the fixed stop loss)
+    #[serde(default)]
+    trailing_stop_pct: Option<f64>,
     #[serde(default)]
     trend_lookback: Option<u32>,
     #[serde(default)]
@@ -317,6 +505,8 @@ struct LimitTraderJsonConfig {
     take_atr_mult: Option<f64>,
     #[serde(default)]
     order_mode: Option<String>,
+    #[serde(flatten)]
+    common: AgentCommonJsonConfig,
 }
 
 fn default_limit_wake_interval() -> u64 {
@@ -324,244 +514,1741 @@ fn default_limit_wake_interval() -> u64 {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct KeeperJsonConfig {
     id: u32,
     name: String,
     #[serde(default = "default_keeper_wake_interval")]
     wake_interval_ms: u64,
+    /// Simulated gas per execution attempt (micro-USD); 0 executes every triggered order
+    #[serde(default)]
+    gas_cost_usd: u64,
+    /// Required reward / gas ratio for an execution
+    #[serde(default = "default_keeper_min_profit_ratio")]
+    min_profit_ratio: f64,
+    /// Fetch the latest Pyth price (VAA) right before each execution and bundle it (Vara mode)
+    #[serde(default)]
+    jit_price: bool,
+    /// Oldest bundled price the exchange still sends, in seconds (0 = no limit)
+    #[serde(default = "default_keeper_max_price_age")]
+    max_price_age_secs: u64,
+    /// Executions sent in one transaction when several orders trigger together (1 = unbatched)
+    #[serde(default = "default_keeper_max_batch")]
+    max_batch: usize,
+    /// Simulated gas of each execution after the first in a batch; default: half of `gas_cost_usd`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    batched_order_gas_usd: Option<u64>,
+    #[serde(flatten)]
+    common: AgentCommonJsonConfig,
+    /// Messages handled per sim-second; the rest wait in its inbox (default: unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    inbox_rate: Option<f64>,
 }
 
 fn default_keeper_wake_interval() -> u64 {
     200
 }
 
-fn default_mm_target_oi() -> i128 {
-    150_000_000_000 // $150k per side
-}
-fn default_mm_max_imbalance() -> f64 {
-    30.0
-}
-fn default_mm_order_size() -> f64 {
-    3.0
-}
-fn default_mm_leverage() -> u32 {
-    2
+fn default_keeper_min_profit_ratio() -> f64 {
+    1.0
 }
-fn default_mm_wake_interval() -> u64 {
-    500
+
+fn default_keeper_max_price_age() -> u64 {
+    10
 }
-fn default_mm_balance() -> i128 {
-    1_000_000_000_000 // $1M
+
+fn default_keeper_max_batch() -> usize {
+    1
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SimConfig {
-    scenario_name: String,
-    duration_sec: u64,
-    logs_dir: String,
-    exchange: ExchangeConfig,
-    oracles: Vec<OracleConfig>,
-    #[serde(default)]
-    smart_traders: Vec<SmartTraderJsonConfig>,
-    #[serde(default)]
-    limit_traders: Vec<LimitTraderJsonConfig>,
+#[serde(deny_unknown_fields)]
+struct HumanJsonConfig {
+    #[serde(default = "default_human_id")]
+    id: u32,
+    #[serde(default = "default_human_name")]
+    name: String,
+    /// On-chain address (default: VARA_HUMAN_ADDRESS, then address book)
     #[serde(default)]
-    liquidation_agent: Option<LiquidationAgentConfig>,
+    address: Option<String>,
+    /// Command polling interval (default: realtime tick)
     #[serde(default)]
-    market_maker: Option<MarketMakerJsonConfig>,
+    wake_interval_ms: Option<u64>,
+    /// Per-client HumanAgents for WebSocket clients on `/session/<token>`
     #[serde(default)]
-    keepers: Vec<KeeperJsonConfig>,
+    sessions: Option<HumanSessionsJsonConfig>,
 }
 
-fn default_wake_interval() -> u64 {
-    3000
+/// Each session trades through its own agent id: `first_id`, `first_id + 1`, ...
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HumanSessionsJsonConfig {
+    first_id: u32,
+    /// Concurrent sessions (agent ids reserved)
+    max_sessions: u32,
+    /// Accepted tokens (default: any)
+    #[serde(default)]
+    tokens: Vec<String>,
 }
 
-impl SimConfig {
-    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let content = std::fs::read_to_string(path)?;
-        Ok(serde_json::from_str(&content)?)
+impl HumanSessionsJsonConfig {
+    fn ids(&self) -> std::ops::Range<u32> {
+        self.first_id..self.first_id.saturating_add(self.max_sessions)
     }
 }
 
-impl Default for SimConfig {
+fn default_human_id() -> u32 {
+    100
+}
+
+fn default_human_name() -> String {
+    "HumanTrader".to_string()
+}
+
+impl Default for HumanJsonConfig {
     fn default() -> Self {
         Self {
-            scenario_name: "simple_demo".to_string(),
-            duration_sec: 10,
-            logs_dir: "logs".to_string(),
-            exchange: ExchangeConfig {
-                id: 1,
-                name: "PerpExchange".to_string(),
-                markets: vec![MarketJsonConfig {
-                    id: 0,
-                    symbol: "ETH-USD".to_string(),
-                    index_token: "ETH".to_string(),
-                    index_decimals: 18,
-                    collateral_token: "USDT".to_string(),
-                    collateral_decimals: 6,
-                    initial_liquidity: LiquidityConfig {
-                        collateral_amount: 1_000_000_000_000,
-                        index_amount: 500_000_000_000,
-                        liquidity_usd: 2_000_000_000_000,
-                    },
-                }],
-            },
-            oracles: vec![OracleConfig {
-                id: 2,
-                name: "PythOracle".to_string(),
-                symbols: vec!["ETH-USD".to_string(), "USDT-USD".to_string()],
-                provider: "Pyth".to_string(),
-                cache_duration_ms: 10000,
-                wake_interval_ms: 3000,
-            }],
-            smart_traders: vec![],
-            limit_traders: vec![],
-            liquidation_agent: None,
-            market_maker: None,
-            keepers: vec![],
+            id: default_human_id(),
+            name: default_human_name(),
+            address: None,
+            wake_interval_ms: None,
+            sessions: None,
         }
     }
 }
 
-/// Helper function to parse strategy from JSON config
-fn parse_strategy(smart_cfg: &SmartTraderJsonConfig) -> TradingStrategy {
-    let side = match smart_cfg.side.to_lowercase().as_str() {
-        "short" | "sell" => Side::Sell,
-        _ => Side::Buy,
-    };
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ArbitrageJsonConfig {
+    id: u32,
+    name: String,
+    symbol: String,
+    /// Reference price provider: "binance" or "pyth"
+    #[serde(default = "default_arb_reference")]
+    reference: String,
+    #[serde(default = "default_arb_qty")]
+    qty: f64,
+    #[serde(default = "default_leverage")]
+    leverage: u32,
+    #[serde(default = "default_arb_entry_bps")]
+    entry_basis_bps: f64,
+    #[serde(default = "default_arb_exit_bps")]
+    exit_basis_bps: f64,
+    #[serde(default = "default_arb_impact_factor")]
+    impact_factor: f64,
+    #[serde(default = "default_arb_max_hold")]
+    max_hold_sec: u64,
+    #[serde(default = "default_smart_wake_interval")]
+    wake_interval_ms: u64,
+    #[serde(default)]
+    balance: Option<i128>,
+}
 
-    match smart_cfg.strategy.to_lowercase().as_str() {
-        "hodler" => TradingStrategy::Hodler {
-            side,
-            hold_duration_sec: smart_cfg.hold_duration_sec,
-            leverage: smart_cfg.leverage,
-            take_profit_pct: smart_cfg.take_profit_pct,
-            stop_loss_pct: smart_cfg.stop_loss_pct,
-        },
-        "institutional" | "inst" => TradingStrategy::Institutional {
-            side,
-            leverage: smart_cfg.leverage,
-            take_profit_pct: smart_cfg.take_profit_pct.unwrap_or(3.0),
-            stop_loss_pct: smart_cfg.stop_loss_pct.unwrap_or(8.0),
-            min_hold_sec: smart_cfg.min_hold_sec.unwrap_or(60),
-            max_hold_sec: smart_cfg.max_hold_sec.unwrap_or(600),
-            reentry_delay_sec: smart_cfg.reentry_delay_sec.unwrap_or(30),
-        },
-        "trend_follower" | "trend" => TradingStrategy::TrendFollower {
-            lookback_sec: smart_cfg.lookback_sec,
-            threshold_pct: smart_cfg.threshold_pct,
-            leverage: smart_cfg.leverage,
-            take_profit_pct: smart_cfg.take_profit_pct,
-            stop_loss_pct: smart_cfg.stop_loss_pct,
-        },
-        "mean_reversion" | "meanrev" | "mr" => TradingStrategy::MeanReversion {
-            lookback_periods: smart_cfg.lookback_periods.unwrap_or(20),
-            entry_deviation_pct: smart_cfg.entry_deviation_pct.unwrap_or(1.0),
-            exit_deviation_pct: smart_cfg.exit_deviation_pct.unwrap_or(0.2),
-            leverage: smart_cfg.leverage,
-            max_hold_sec: smart_cfg.max_hold_sec.unwrap_or(300),
-        },
-        "arbitrageur" | "arb" => TradingStrategy::Arbitrageur {
-            min_imbalance_pct: smart_cfg.entry_deviation_pct.unwrap_or(5.0), // reuse field
-            leverage: smart_cfg.leverage,
-            hold_duration_sec: smart_cfg.hold_duration_sec,
-            take_profit_pct: smart_cfg.take_profit_pct,
-            stop_loss_pct: smart_cfg.stop_loss_pct,
-        },
-        "funding_harvester" | "funding" | "fh" => TradingStrategy::FundingHarvester {
-            min_imbalance_pct: smart_cfg.entry_deviation_pct.unwrap_or(3.0),
-            leverage: smart_cfg.leverage,
-            min_hold_sec: smart_cfg.min_hold_sec.unwrap_or(60),
-            max_hold_sec: smart_cfg.max_hold_sec.unwrap_or(600),
-            exit_imbalance_pct: smart_cfg.exit_deviation_pct.unwrap_or(5.0),
-            stop_loss_pct: smart_cfg.stop_loss_pct.unwrap_or(10.0),
-        },
-        _ => {
-            eprintln!("[Scenario] Unknown strategy: {}, using Hodler", smart_cfg.strategy);
-            TradingStrategy::Hodler {
-                side,
-                hold_duration_sec: smart_cfg.hold_duration_sec,
-                leverage: smart_cfg.leverage,
-                take_profit_pct: None,
-                stop_loss_pct: None,
-            }
-        }
-    }
+fn default_arb_reference() -> String {
+    "binance".to_string()
 }
 
-/// Helper function to create SmartTraderAgent from JSON config
-fn create_smart_trader(smart_cfg: &SmartTraderJsonConfig, exchange_id: u32) -> SmartTraderAgent {
-    let strategy = parse_strategy(smart_cfg);
+fn default_arb_qty() -> f64 {
+    1.0
+}
 
-    let qty_min = smart_cfg.qty_min.unwrap_or(smart_cfg.qty as f64);
-    let qty_max = smart_cfg.qty_max.unwrap_or(smart_cfg.qty as f64);
+fn default_arb_entry_bps() -> f64 {
+    30.0
+}
 
-    let smart_config = SmartTraderConfig {
-        name: smart_cfg.name.clone(),
-        exchange_id,
-        symbol: smart_cfg.symbol.clone(),
-        address: None,
-        strategy,
-        qty_min,
-        qty_max,
-        wake_interval_ms: smart_cfg.wake_interval_ms,
-        balance: smart_cfg.balance,
-        start_delay_ms: smart_cfg.start_delay_ms,
-    };
+fn default_arb_exit_bps() -> f64 {
+    5.0
+}
 
-    SmartTraderAgent::new(smart_cfg.id, smart_config)
+fn default_arb_impact_factor() -> f64 {
+    1.0
 }
 
-fn parse_limit_strategy(cfg: &LimitTraderJsonConfig) -> LimitStrategy {
-    match cfg.strategy.to_lowercase().as_str() {
-        "mean_reversion" | "meanrev" => LimitStrategy::MeanReversion {
-            entry_offset_pct: cfg.entry_offset_pct.unwrap_or(1.5),
-            stop_loss_pct: cfg.stop_loss_pct.unwrap_or(3.0),
-            take_profit_pct: cfg.take_profit_pct.unwrap_or(2.0),
-            leverage: cfg.leverage,
-            trend_lookback: cfg.trend_lookback.unwrap_or(10),
-        },
-        "breakout" => {
-            let direction = match cfg.direction.as_deref() {
-                Some("sell") | Some("short") => Side::Sell,
-                _ => Side::Buy,
-            };
-            LimitStrategy::Breakout {
-                breakout_offset_pct: cfg.breakout_offset_pct.unwrap_or(1.0),
-                stop_loss_pct: cfg.stop_loss_pct.unwrap_or(2.0),
-                take_profit_pct: cfg.take_profit_pct.unwrap_or(3.0),
-                leverage: cfg.leverage,
-                direction,
-            }
-        }
-        "grid" => LimitStrategy::Grid {
-            levels: 5,
-            spacing_pct: 1.0,
-            qty_per_level: cfg.qty,
-            leverage: cfg.leverage,
-            take_profit_pct: cfg.take_profit_pct.unwrap_or(1.5),
-        },
-        "smart" => {
-            let order_mode = match cfg.order_mode.as_deref() {
-                Some("active") => OrderMode::Active,
-                _ => OrderMode::Passive,
-            };
-            LimitStrategy::Smart {
-                sma_fast: cfg.sma_fast.unwrap_or(10),
-                sma_slow: cfg.sma_slow.unwrap_or(30),
-                rsi_period: cfg.rsi_period.unwrap_or(14),
-                rsi_low: cfg.rsi_low.unwrap_or(35.0),
-                rsi_high: cfg.rsi_high.unwrap_or(65.0),
-                atr_period: cfg.atr_period.unwrap_or(14),
-                entry_atr_mult: cfg.entry_atr_mult.unwrap_or(0.5),
-                stop_atr_mult: cfg.stop_atr_mult.unwrap_or(1.2),
-                take_atr_mult: cfg.take_atr_mult.unwrap_or(1.8),
-                leverage: cfg.leverage,
-                order_mode,
-            }
-        }
-        _ => LimitStrategy::MeanReversion {
+fn default_arb_max_hold() -> u64 {
+    600
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NoiseTraderJsonConfig {
+    id: u32,
+    name: String,
+    symbol: String,
+    /// Poisson arrival rate
+    #[serde(default = "default_noise_orders_per_min")]
+    orders_per_min: f64,
+    #[serde(default = "default_noise_buy_prob")]
+    buy_prob: f64,
+    #[serde(default = "default_noise_close_prob")]
+    close_prob: f64,
+    /// Lognormal order size in tokens: exp(mu + sigma * N(0,1))
+    #[serde(default)]
+    size_mu: f64,
+    #[serde(default = "default_noise_size_sigma")]
+    size_sigma: f64,
+    #[serde(default = "default_noise_qty_max")]
+    qty_max: f64,
+    /// Leverage choices, sampled by `leverage_weights` (uniform if omitted)
+    #[serde(default = "default_noise_leverage_choices")]
+    leverage_choices: Vec<u32>,
+    #[serde(default)]
+    leverage_weights: Vec<f64>,
+    /// Own seed (default: scenario seed)
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(default)]
+    balance: Option<i128>,
+}
+
+fn default_noise_orders_per_min() -> f64 {
+    6.0
+}
+
+fn default_noise_buy_prob() -> f64 {
+    0.5
+}
+
+fn default_noise_close_prob() -> f64 {
+    0.4
+}
+
+fn default_noise_size_sigma() -> f64 {
+    0.8
+}
+
+fn default_noise_qty_max() -> f64 {
+    20.0
+}
+
+fn default_noise_leverage_choices() -> Vec<u32> {
+    vec![1, 2, 3, 5, 10]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WhaleJsonConfig {
+    id: u32,
+    name: String,
+    symbol: String,
+    /// "long" or "short"
+    #[serde(default = "default_side")]
+    side: String,
+    #[serde(default = "default_leverage")]
+    leverage: u32,
+    total_qty: f64,
+    #[serde(default)]
+    accumulate_start_sec: u64,
+    accumulate_sec: u64,
+    #[serde(default = "default_whale_chunks")]
+    accumulate_chunks: u32,
+    dump_at_sec: u64,
+    #[serde(default)]
+    dump_qty: f64,
+    #[serde(default)]
+    dump_sec: u64,
+    #[serde(default = "default_whale_chunks")]
+    dump_chunks: u32,
+    /// Optional oracle shock in percent at `shock_at_sec`
+    #[serde(default)]
+    shock_pct: Option<f64>,
+    #[serde(default)]
+    shock_at_sec: u64,
+    #[serde(default)]
+    shock_duration_sec: Option<u64>,
+    #[serde(default)]
+    balance: Option<i128>,
+}
+
+fn default_whale_chunks() -> u32 {
+    10
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CopyTraderJsonConfig {
+    id: u32,
+    name: String,
+    /// Agent whose fills are mirrored
+    leader_id: u32,
+    #[serde(default = "default_copy_ratio")]
+    ratio: f64,
+    #[serde(default = "default_copy_delay_ms")]
+    delay_ms: u64,
+    #[serde(default = "default_copy_max_leverage")]
+    max_leverage: u32,
+    #[serde(default)]
+    balance: Option<i128>,
+}
+
+fn default_copy_ratio() -> f64 {
+    0.5
+}
+
+fn default_copy_delay_ms() -> u64 {
+    500
+}
+
+fn default_copy_max_leverage() -> u32 {
+    10
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QuotingMmJsonConfig {
+    id: u32,
+    name: String,
+    symbol: String,
+    #[serde(default = "default_mm_order_size")]
+    qty: f64,
+    #[serde(default = "default_mm_leverage")]
+    leverage: u32,
+    #[serde(default = "default_qmm_spread_bps")]
+    spread_bps: f64,
+    #[serde(default = "default_qmm_requote_bps")]
+    requote_bps: f64,
+    #[serde(default = "default_qmm_inventory_skew_bps")]
+    inventory_skew_bps: f64,
+    #[serde(default = "default_qmm_max_inventory")]
+    max_inventory_usd: i128,
+    #[serde(default = "default_qmm_quote_ttl")]
+    quote_ttl_sec: u64,
+    #[serde(default)]
+    balance: Option<i128>,
+}
+
+fn default_qmm_spread_bps() -> f64 {
+    20.0
+}
+
+fn default_qmm_requote_bps() -> f64 {
+    5.0
+}
+
+fn default_qmm_inventory_skew_bps() -> f64 {
+    10.0
+}
+
+fn default_qmm_max_inventory() -> i128 {
+    50_000_000_000 // $50k net
+}
+
+fn default_qmm_quote_ttl() -> u64 {
+    60
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LpJsonConfig {
+    id: u32,
+    name: String,
+    symbol: String,
+    /// Deposited into the pool on start (micro-USD)
+    initial_usd: i128,
+    #[serde(default = "default_lp_step")]
+    step_usd: i128,
+    /// Cap on the net deposit (default: initial_usd)
+    #[serde(default)]
+    max_deposit_usd: Option<i128>,
+    #[serde(default = "default_lp_add_above_util")]
+    add_above_util: f64,
+    #[serde(default = "default_lp_remove_below_util")]
+    remove_below_util: f64,
+    #[serde(default = "default_lp_panic_loss_pct")]
+    panic_loss_pct: f64,
+    #[serde(default = "default_lp_cooldown_ms")]
+    cooldown_ms: u64,
+}
+
+fn default_lp_step() -> i128 {
+    50_000_000_000 // $50k
+}
+
+fn default_lp_add_above_util() -> f64 {
+    0.8
+}
+
+fn default_lp_remove_below_util() -> f64 {
+    0.2
+}
+
+fn default_lp_panic_loss_pct() -> f64 {
+    5.0
+}
+
+fn default_lp_cooldown_ms() -> u64 {
+    30_000
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FundingArbJsonConfig {
+    id: u32,
+    name: String,
+    symbol: String,
+    /// Correlated market for the delta hedge (requires a local agent)
+    #[serde(default)]
+    hedge_symbol: Option<String>,
+    /// Hedge with spot of `symbol` on this swap_exchange agent instead
+    #[serde(default)]
+    hedge_venue: Option<u32>,
+    #[serde(default = "default_funding_arb_hedge_ratio")]
+    hedge_ratio: f64,
+    #[serde(default = "default_arb_qty")]
+    qty: f64,
+    #[serde(default = "default_leverage")]
+    leverage: u32,
+    /// Funding rate at full OI imbalance, as in the contract's FundingConfig
+    #[serde(default = "default_funding_arb_daily_rate")]
+    daily_rate_bps: f64,
+    #[serde(default = "default_funding_arb_entry")]
+    entry_rate_bps_hour: f64,
+    #[serde(default = "default_funding_arb_exit")]
+    exit_rate_bps_hour: f64,
+    #[serde(default = "default_funding_arb_max_hold")]
+    max_hold_sec: u64,
+    #[serde(default)]
+    balance: Option<i128>,
+}
+
+fn default_funding_arb_hedge_ratio() -> f64 {
+    1.0
+}
+
+fn default_funding_arb_daily_rate() -> f64 {
+    100.0
+}
+
+fn default_funding_arb_entry() -> f64 {
+    1.0
+}
+
+fn default_funding_arb_exit() -> f64 {
+    0.25
+}
+
+fn default_funding_arb_max_hold() -> u64 {
+    4 * 3600
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HedgeLegJsonConfig {
+    symbol: String,
+    #[serde(default = "default_hedge_factor")]
+    weight: f64,
+    #[serde(default = "default_hedge_factor")]
+    beta: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HedgerJsonConfig {
+    id: u32,
+    name: String,
+    spot_symbol: String,
+    /// Virtual spot holding in tokens (negative = short)
+    spot_qty: f64,
+    hedges: Vec<HedgeLegJsonConfig>,
+    #[serde(default)]
+    target_delta_usd: f64,
+    #[serde(default = "default_hedger_band")]
+    band_pct: f64,
+    #[serde(default = "default_leverage")]
+    leverage: u32,
+    #[serde(default = "default_hedger_min_interval")]
+    min_interval_sec: u64,
+    #[serde(default)]
+    balance: Option<i128>,
+}
+
+fn default_hedge_factor() -> f64 {
+    1.0
+}
+
+fn default_hedger_band() -> f64 {
+    5.0
+}
+
+fn default_hedger_min_interval() -> u64 {
+    30
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SwapPoolJsonConfig {
+    symbol: String,
+    /// USD on each side of the pool at the first oracle price (micro-USD)
+    #[serde(default = "default_swap_liquidity")]
+    liquidity_usd: i128,
+}
+
+/// Constant-product spot venue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SwapExchangeJsonConfig {
+    id: u32,
+    name: String,
+    symbols: Vec<SwapPoolJsonConfig>,
+    #[serde(default = "default_swap_fee_bps")]
+    fee_bps: f64,
+    #[serde(default = "default_swap_arb_pull")]
+    arb_pull: f64,
+}
+
+fn default_swap_liquidity() -> i128 {
+    1_000_000_000_000 // $1M
+}
+
+fn default_swap_fee_bps() -> f64 {
+    30.0
+}
+
+fn default_swap_arb_pull() -> f64 {
+    0.2
+}
+
+/// Moving-average crossover running on the generic StrategyAgent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SmaCrossJsonConfig {
+    id: u32,
+    name: String,
+    symbol: String,
+    /// Window lengths in oracle ticks
+    #[serde(default = "default_sma_fast")]
+    fast: usize,
+    #[serde(default = "default_sma_slow")]
+    slow: usize,
+    #[serde(default = "default_arb_qty")]
+    qty: f64,
+    #[serde(default = "default_leverage")]
+    leverage: u32,
+    /// Only open in a trending regime
+    #[serde(default)]
+    trend_filter: bool,
+    #[serde(default)]
+    balance: Option<i128>,
+}
+
+fn default_sma_fast() -> usize {
+    5
+}
+
+fn default_sma_slow() -> usize {
+    20
+}
+
+/// Rhai strategy script; `params` becomes the script's `this`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScriptedJsonConfig {
+    id: u32,
+    name: String,
+    /// Inline source (exclusive with `script_file`)
+    #[serde(default)]
+    script: Option<String>,
+    #[serde(default)]
+    script_file: Option<String>,
+    #[serde(default)]
+    params: serde_json::Value,
+    #[serde(default)]
+    timer_interval_ms: Option<u64>,
+    #[serde(default = "default_script_max_operations")]
+    max_operations: u64,
+    #[serde(default)]
+    balance: Option<i128>,
+}
+
+fn default_script_max_operations() -> u64 {
+    100_000
+}
+
+/// Strategy in an external process, reached over localhost TCP
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BridgeJsonConfig {
+    id: u32,
+    name: String,
+    /// host:port the external process listens on
+    address: String,
+    #[serde(default = "default_bridge_connect_timeout")]
+    connect_timeout_ms: u64,
+    #[serde(default = "default_bridge_timeout")]
+    timeout_ms: u64,
+    #[serde(default = "default_bridge_on_timeout")]
+    on_timeout: TimeoutPolicy,
+    #[serde(default = "default_bridge_max_timeouts")]
+    max_timeouts: u32,
+    #[serde(default)]
+    timer_interval_ms: Option<u64>,
+    #[serde(default)]
+    balance: Option<i128>,
+}
+
+fn default_bridge_connect_timeout() -> u64 {
+    10_000
+}
+
+fn default_bridge_timeout() -> u64 {
+    200
+}
+
+fn default_bridge_on_timeout() -> TimeoutPolicy {
+    TimeoutPolicy::Skip
+}
+
+fn default_bridge_max_timeouts() -> u32 {
+    10
+}
+
+/// Strategy in an external process attached over the WebSocket API (realtime only)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RemoteJsonConfig {
+    id: u32,
+    /// Also the session path: ws://host:<api_port + 1>/remote/<name>
+    name: String,
+    #[serde(default = "default_bridge_timeout")]
+    timeout_ms: u64,
+    #[serde(default)]
+    timer_interval_ms: Option<u64>,
+    #[serde(default)]
+    balance: Option<i128>,
+}
+
+impl ScriptedJsonConfig {
+    fn strategy_config(&self) -> Result<ScriptedConfig, String> {
+        let source = match (&self.script, &self.script_file) {
+            (Some(source), None) => source.clone(),
+            (None, Some(path)) => fs::read_to_string(path).map_err(|e| format!("script_file {}: {}", path, e))?,
+            _ => return Err("set exactly one of script / script_file".to_string()),
+        };
+        Ok(ScriptedConfig {
+            name: self.name.clone(),
+            source,
+            params: self.params.clone(),
+            timer_interval_ms: self.timer_interval_ms.filter(|ms| *ms > 0),
+            max_operations: self.max_operations,
+        })
+    }
+}
+
+/// Risk monitor: reports on every exchange snapshot (`exchange.snapshot_interval_ms`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RiskMonitorJsonConfig {
+    id: u32,
+    name: String,
+    #[serde(default = "default_risk_var_horizon")]
+    var_horizon_sec: u64,
+    /// One-sided z-score (2.326 = 99%)
+    #[serde(default = "default_risk_var_z")]
+    var_z: f64,
+    #[serde(default = "default_risk_vol_window")]
+    vol_window: usize,
+    #[serde(default = "default_risk_maintenance_margin")]
+    maintenance_margin: f64,
+    #[serde(default = "default_risk_bucket_bps")]
+    bucket_bps: f64,
+    #[serde(default = "default_risk_heatmap_range")]
+    heatmap_range_pct: f64,
+    #[serde(default = "default_risk_warn_distance")]
+    warn_distance_pct: f64,
+    #[serde(default = "default_risk_var_alert")]
+    var_alert_pct: f64,
+    #[serde(default = "default_risk_concentration_alert")]
+    concentration_alert_pct: f64,
+    #[serde(default = "default_risk_cluster_alert")]
+    cluster_alert_pct: f64,
+}
+
+fn default_risk_var_horizon() -> u64 {
+    3600
+}
+
+fn default_risk_var_z() -> f64 {
+    2.326
+}
+
+fn default_risk_vol_window() -> usize {
+    300
+}
+
+fn default_risk_maintenance_margin() -> f64 {
+    0.01
+}
+
+fn default_risk_bucket_bps() -> f64 {
+    50.0
+}
+
+fn default_risk_heatmap_range() -> f64 {
+    20.0
+}
+
+fn default_risk_warn_distance() -> f64 {
+    2.0
+}
+
+fn default_risk_var_alert() -> f64 {
+    10.0
+}
+
+fn default_risk_concentration_alert() -> f64 {
+    25.0
+}
+
+fn default_risk_cluster_alert() -> f64 {
+    20.0
+}
+
+fn default_mm_target_oi() -> i128 {
+    150_000_000_000 // $150k per side
+}
+fn default_mm_max_imbalance() -> f64 {
+    30.0
+}
+fn default_mm_order_size() -> f64 {
+    3.0
+}
+fn default_mm_leverage() -> u32 {
+    2
+}
+fn default_mm_wake_interval() -> u64 {
+    500
+}
+fn default_mm_balance() -> i128 {
+    1_000_000_000_000 // $1M
+}
+
+/// On-chain setup of a scenario. Connection fields fall back to the VARA_* environment.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VaraJsonConfig {
+    #[serde(default)]
+    endpoint: Option<String>,
+    #[serde(default)]
+    contract: Option<String>,
+    #[serde(default)]
+    keystore: Option<String>,
+    #[serde(default)]
+    passphrase: Option<String>,
+    #[serde(default)]
+    block_time_ms: Option<u64>,
+    #[serde(default)]
+    gas_limit: Option<u64>,
+    /// Priority tip per tx type (replaces the VARA_TIP_* environment)
+    #[serde(default)]
+    tips: Option<TxTips>,
+    /// Simulated reorgs: successful txs reported as reverted a few blocks later
+    #[serde(default)]
+    reorg: Option<ReorgConfig>,
+    /// Fill market/close orders locally when their tx fails transiently, syncing the chain later
+    #[serde(default)]
+    local_fallback: Option<LocalFallbackConfig>,
+    /// Measure the native fee of every transaction, with optional per-agent budgets
+    #[serde(default)]
+    tx_spend: Option<TxSpendConfig>,
+    /// Agent id -> key name in seeds.json (default: bot_NNN by normalized id)
+    #[serde(default)]
+    keys: BTreeMap<u32, String>,
+    /// Deposit for traders without an explicit `balance` (micro-USD, default $1M)
+    #[serde(default)]
+    default_deposit: Option<i128>,
+    /// Per-agent deposit overrides (micro-USD)
+    #[serde(default)]
+    deposits: BTreeMap<u32, i128>,
+    /// Balances already exist on-chain (same as --skip-deposits)
+    #[serde(default)]
+    skip_deposits: bool,
+    /// Agents filled locally by the exchange: no key, no deposit, market orders only
+    #[serde(default)]
+    local_agents: Vec<u32>,
+    /// Local agents whose positions share collateral (cross margin); the others are isolated
+    #[serde(default)]
+    cross_margin: Vec<u32>,
+    /// Native gas balance watch of the on-chain accounts
+    #[serde(default)]
+    balance_monitor: Option<BalanceMonitorJsonConfig>,
+}
+
+/// `vara.balance_monitor`: warns about (and optionally refills) bot accounts low on gas.
+/// Amounts in the chain's smallest unit (1 VARA = 10^12).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BalanceMonitorJsonConfig {
+    id: u32,
+    #[serde(default = "default_balance_monitor_name")]
+    name: String,
+    /// Watched agents (default: every on-chain agent of the scenario)
+    #[serde(default)]
+    accounts: Vec<u32>,
+    #[serde(default = "default_balance_interval_sec")]
+    interval_sec: u64,
+    #[serde(default = "default_min_gas_balance")]
+    min_balance: u128,
+    /// Refill low accounts by a transfer from this agent's account
+    #[serde(default)]
+    funder: Option<u32>,
+    /// ... or by a request to this testnet faucet
+    #[serde(default)]
+    faucet_url: Option<String>,
+    #[serde(default)]
+    top_up_amount: u128,
+    #[serde(default = "default_top_up_cooldown_sec")]
+    cooldown_sec: u64,
+}
+
+fn default_balance_monitor_name() -> String {
+    "GasMonitor".to_string()
+}
+fn default_balance_interval_sec() -> u64 {
+    60
+}
+fn default_min_gas_balance() -> u128 {
+    10_000_000_000_000 // 10 VARA
+}
+fn default_top_up_cooldown_sec() -> u64 {
+    600
+}
+
+impl BalanceMonitorJsonConfig {
+    fn check(&self) -> Result<(), String> {
+        if self.interval_sec == 0 {
+            return Err("interval_sec must be > 0".to_string());
+        }
+        match (self.funder, &self.faucet_url) {
+            (Some(_), Some(_)) => Err("funder and faucet_url are exclusive".to_string()),
+            (Some(_), None) if self.top_up_amount == 0 => Err("a funder needs top_up_amount > 0".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    fn top_up(&self) -> Option<TopUpConfig> {
+        let source = match (self.funder, &self.faucet_url) {
+            (Some(funder), _) => TopUpSource::Funder(funder),
+            (None, Some(url)) => TopUpSource::Faucet(url.clone()),
+            (None, None) => return None,
+        };
+        Some(TopUpConfig {
+            source,
+            amount: self.top_up_amount,
+            cooldown_ms: self.cooldown_sec * 1000,
+        })
+    }
+}
+
+impl VaraJsonConfig {
+    fn agent_keys(&self) -> HashMap<u32, String> {
+        self.keys.iter().map(|(id, name)| (*id, name.clone())).collect()
+    }
+
+    /// Client config: scenario values first, environment for the rest.
+    fn client_config(&self) -> Result<VaraConfig, String> {
+        let mut config = VaraConfig::from_env_with_contract(self.contract.clone()).map_err(|e| e.to_string())?;
+        if let Some(endpoint) = &self.endpoint {
+            config.ws_endpoint = endpoint.clone();
+        }
+        if let Some(keystore) = &self.keystore {
+            config.keystore_path = keystore.clone();
+        }
+        if let Some(passphrase) = &self.passphrase {
+            config.passphrase_path = passphrase.clone();
+        }
+        if let Some(block_time_ms) = self.block_time_ms {
+            config.block_time_ms = block_time_ms;
+        }
+        if let Some(gas_limit) = self.gas_limit {
+            config.gas_limit = gas_limit;
+        }
+        if let Some(tips) = self.tips {
+            config.tips = tips;
+        }
+        config.reorg = self.reorg;
+        config.tx_spend = self.tx_spend.clone();
+        config.agent_keys = self.agent_keys();
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SimConfig {
+    scenario_name: String,
+    duration_sec: u64,
+    logs_dir: String,
+    exchange: ExchangeConfig,
+    #[serde(default)]
+    oracles: Vec<OracleConfig>,
+    #[serde(default)]
+    smart_traders: Vec<SmartTraderJsonConfig>,
+    #[serde(default)]
+    limit_traders: Vec<LimitTraderJsonConfig>,
+    #[serde(default)]
+    liquidation_agent: Option<LiquidationAgentConfig>,
+    #[serde(default)]
+    market_maker: Option<MarketMakerJsonConfig>,
+    #[serde(default)]
+    keepers: Vec<KeeperJsonConfig>,
+    /// Unified agent list: `{"type": "<agent type>", ...}`, started after the per-type sections
+    #[serde(default)]
+    agents: Vec<AgentSpec>,
+    /// Generated agent cohorts, started after `agents`
+    #[serde(default)]
+    populations: Vec<PopulationConfig>,
+    /// Base seed for generated populations
+    #[serde(default)]
+    seed: u64,
+    /// Scripted events (price shocks, agents joining/leaving, parameter changes)
+    #[serde(default)]
+    timeline: Vec<TimelineJsonEntry>,
+    /// Equity curve sampling interval in sim-seconds (equity.csv)
+    #[serde(default = "default_equity_sample_sec")]
+    equity_sample_sec: u64,
+    /// Oracle-only phase at the start of the run and fixed starting prices
+    #[serde(default)]
+    warmup: WarmupJsonConfig,
+    /// `exchange_state.json` of an earlier run: its local positions and pool balances are restored
+    #[serde(default)]
+    warm_start_from: Option<String>,
+    /// How close in time and price liquidations must be to form a cascade (summary report)
+    #[serde(default)]
+    cascades: CascadeConfig,
+    /// Rolling taker flow per market: markets.csv columns and MarketStats broadcasts
+    #[serde(default)]
+    market_stats: MarketStatsConfig,
+    /// POST selected events (liquidations, circuit breaker trips, ...) to external services
+    #[serde(default)]
+    webhooks: Vec<WebhookConfig>,
+    #[serde(default)]
+    logging: LoggingJsonConfig,
+    /// Chain connection, key mapping, deposits and local (off-chain) agents
+    #[serde(default)]
+    vara: VaraJsonConfig,
+    /// Pre-trade limits checked by the exchange for every trader
+    #[serde(default)]
+    risk_limits: Option<RiskLimitsConfig>,
+    /// Trader balances, bankruptcy threshold and recapitalization
+    #[serde(default)]
+    capital: CapitalConfig,
+    /// What happens to messages for unknown agent ids (drop / retry / fail)
+    #[serde(default)]
+    dead_letters: DeadLetterPolicy,
+    /// Realtime runs: report (or abort on) ticks stuck in an agent's handler
+    #[serde(default)]
+    watchdog: Option<WatchdogConfig>,
+    /// Message type -> agent group: broadcasts of that type only reach the group's members
+    /// (agents join groups with their `groups` parameter)
+    #[serde(default)]
+    broadcast_groups: BTreeMap<String, String>,
+    /// Time-of-day / weekend volatility and volume cycle (synthetic prices, noise traders)
+    #[serde(default)]
+    activity_schedule: ActivitySchedule,
+    /// HTTP/WS API of realtime runs
+    #[serde(default)]
+    api: ApiJsonConfig,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiJsonConfig {
+    /// Per-client command rate and order bounds, checked before commands reach the HumanAgent
+    #[serde(default)]
+    limits: ApiLimits,
+    /// Browser origins allowed to call the HTTP API (default: none)
+    #[serde(default)]
+    cors: Option<CorsConfig>,
+}
+
+/// Scenario overview: agent counts per type include generated populations.
+pub struct ScenarioSummary {
+    pub name: String,
+    pub duration_sec: u64,
+    pub logs_dir: String,
+    pub seed: u64,
+    pub markets: Vec<String>,
+    pub agents: BTreeMap<String, usize>,
+    pub populations: usize,
+    pub timeline_events: usize,
+}
+
+/// CSV log options: rotation (size/time) with gzip archives, and high-volume log switches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LoggingJsonConfig {
+    /// Rotate a CSV once it exceeds this size (0 = no size limit)
+    #[serde(default)]
+    max_file_mb: u64,
+    /// Rotate a CSV once it is older than this (0 = no time limit)
+    #[serde(default)]
+    max_file_age_min: u64,
+    /// Number of .gz archives kept per CSV
+    #[serde(default = "default_log_retention")]
+    retention: usize,
+    /// Write oracle ticks to oracle.csv (high volume)
+    #[serde(default = "default_true")]
+    oracle_log: bool,
+    /// Record every delivered message to trace.jsonl (input of `replay`)
+    #[serde(default)]
+    trace: bool,
+    /// Time deliveries and agent handlers into metrics.prom (always on in realtime mode, served on /metrics)
+    #[serde(default)]
+    metrics: bool,
+    /// Console output of the kernel (Debug: a banner per tick) and of agents without their own `verbosity`
+    #[serde(default)]
+    verbosity: Verbosity,
+}
+
+fn default_log_retention() -> usize {
+    5
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for LoggingJsonConfig {
+    fn default() -> Self {
+        Self {
+            max_file_mb: 0,
+            max_file_age_min: 0,
+            retention: default_log_retention(),
+            oracle_log: true,
+            trace: false,
+            metrics: false,
+            verbosity: Verbosity::default(),
+        }
+    }
+}
+
+impl LoggingJsonConfig {
+    fn rotation(&self) -> LogRotation {
+        LogRotation {
+            max_bytes: self.max_file_mb * 1024 * 1024,
+            max_age: (self.max_file_age_min > 0)
+                .then(|| std::time::Duration::from_secs(self.max_file_age_min * 60)),
+            retention: self.retention,
+        }
+    }
+}
+
+fn default_equity_sample_sec() -> u64 {
+    10
+}
+
+fn default_wake_interval() -> u64 {
+    3000
+}
+
+fn default_failover_after() -> u32 {
+    FailoverConfig::default().failures_to_switch
+}
+
+fn default_recovery_blocks() -> u64 {
+    FailoverConfig::default().recovery_interval_blocks
+}
+
+fn default_max_age_ms() -> u64 {
+    10_000 // the summary counts longer oracle gaps as downtime
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WarmupJsonConfig {
+    /// Sim-seconds at the start in which only the oracles and the exchange run: the other
+    /// agents receive the ticks, their wakeups wait until the warm-up ends
+    #[serde(default)]
+    duration_sec: u64,
+    /// First oracle price per symbol (USD); the provider's moves carry on from there
+    #[serde(default)]
+    start_prices: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SyntheticJsonConfig {
+    /// Start price per symbol (USD)
+    start_prices: BTreeMap<String, f64>,
+    /// Standard deviation of daily log returns (%), scaled by `activity_schedule`
+    #[serde(default = "default_daily_vol_pct")]
+    daily_vol_pct: f64,
+    /// Default: the scenario seed
+    #[serde(default)]
+    seed: Option<u64>,
+}
+
+fn default_daily_vol_pct() -> f64 {
+    3.0
+}
+
+impl SimConfig {
+    /// Load and validate a scenario file, resolving `extends` and applying `--set`
+    /// overrides. Errors carry the field path (and line/column for standalone files).
+    pub fn from_file(path: &str, sets: &[String]) -> Result<Self, String> {
+        let (mut doc, extended) = overrides::load_with_extends(std::path::Path::new(path))?;
+        for expr in sets {
+            overrides::apply_set(&mut doc, expr)?;
+        }
+
+        let config: SimConfig = if !extended && sets.is_empty() {
+            // Re-read the text so schema errors keep line/column
+            let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            let de = &mut serde_json::Deserializer::from_str(&content);
+            serde_path_to_error::deserialize(de).map_err(|e| format!("{}: {}: {}", path, e.path(), e.inner()))?
+        } else {
+            serde_path_to_error::deserialize(doc).map_err(|e| format!("{}: {}: {}", path, e.path(), e.inner()))?
+        };
+        config
+            .validate()
+            .map_err(|errors| format!("{}: {} error(s):\n  - {}", path, errors.len(), errors.join("\n  - ")))?;
+        Ok(config)
+    }
+
+    pub fn scenario_name(&self) -> &str {
+        &self.scenario_name
+    }
+
+    pub fn logs_dir(&self) -> &str {
+        &self.logs_dir
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// (symbol, index_decimals, collateral_decimals) of every market
+    pub fn market_decimals(&self) -> Vec<(String, u32, u32)> {
+        self.exchange
+            .markets
+            .iter()
+            .map(|m| (m.symbol.clone(), m.index_decimals, m.collateral_decimals))
+            .collect()
+    }
+
+    /// Overview for `list` / `describe`.
+    pub fn summary(&self) -> ScenarioSummary {
+        let mut agents = BTreeMap::new();
+        for spec in self.agent_specs() {
+            *agents.entry(spec.agent_type).or_insert(0) += 1;
+        }
+        ScenarioSummary {
+            name: self.scenario_name.clone(),
+            duration_sec: self.duration_sec,
+            logs_dir: self.logs_dir.clone(),
+            seed: self.seed,
+            markets: self.exchange.markets.iter().map(|m| m.symbol.clone()).collect(),
+            agents,
+            populations: self.populations.len(),
+            timeline_events: self.timeline.len(),
+        }
+    }
+
+    /// Semantic checks on top of the serde schema: agent parameters, duplicate ids,
+    /// market references and oracle coverage. Returns every problem found.
+    fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        let registry = AgentRegistry::builtin();
+
+        if self.duration_sec == 0 {
+            errors.push("duration_sec: must be > 0".to_string());
+        }
+        if self.exchange.markets.is_empty() {
+            errors.push("exchange.markets: at least one market is required".to_string());
+        }
+        if self.exchange.common.start_delay_ms.is_some() || self.exchange.common.active_window.is_some() {
+            errors.push("exchange: runs for the whole scenario (no start_delay_ms / active_window)".to_string());
+        }
+
+        let mut market_ids = HashSet::new();
+        let markets: HashSet<&str> = self.exchange.markets.iter().map(|m| m.symbol.as_str()).collect();
+        for (i, m) in self.exchange.markets.iter().enumerate() {
+            if !market_ids.insert(m.id) {
+                errors.push(format!("exchange.markets[{}]: duplicate market id {}", i, m.id));
+            }
+        }
+
+        for (i, p) in self.populations.iter().enumerate() {
+            if let Err(e) = p.expand(i, self.seed) {
+                errors.push(format!("populations[{}] ({}): {}", i, p.agent_type, e));
+            }
+        }
+
+        let mut ids: HashMap<u32, String> = HashMap::new();
+        ids.insert(self.exchange.id, "exchange".to_string());
+        if !self.timeline.is_empty() {
+            ids.insert(DIRECTOR_ID, "director".to_string());
+        }
+        let mut oracle_symbols: HashSet<String> = HashSet::new();
+
+        for (i, spec) in self.agent_specs().iter().enumerate() {
+            let label = format!("agents[{}] ({})", i, spec.agent_type);
+            if let Err(e) = registry.check(spec) {
+                errors.push(format!("{}: {}", label, e));
+                continue;
+            }
+
+            check_spec_refs(&label, spec, &markets, &mut ids, &mut errors);
+            self.check_portfolio_is_local(&label, spec, &mut errors);
+            if let Some(sessions) = (spec.agent_type == "human")
+                .then(|| spec.parse::<HumanJsonConfig>().ok()?.sessions)
+                .flatten()
+            {
+                for id in sessions.ids() {
+                    if let Some(prev) = ids.insert(id, "human session".to_string()) {
+                        errors.push(format!("{}: session id {} is already used by {}", label, id, prev));
+                    }
+                }
+            }
+            if spec.agent_type == "oracle" {
+                if let Some(symbols) = spec.params.get("symbols").and_then(|v| v.as_array()) {
+                    oracle_symbols.extend(symbols.iter().filter_map(|s| s.as_str()).map(String::from));
+                }
+            }
+        }
+
+        for (symbol, usd) in &self.warmup.start_prices {
+            if !oracle_symbols.contains(symbol) {
+                errors.push(format!("warmup.start_prices: no oracle feeds '{}'", symbol));
+            }
+            if !usd.is_finite() || *usd <= 0.0 {
+                errors.push(format!("warmup.start_prices: price of '{}' must be > 0", symbol));
+            }
+        }
+        if self.warmup.duration_sec > 0 && self.warmup.duration_sec >= self.duration_sec {
+            errors.push("warmup.duration_sec: must be shorter than duration_sec".to_string());
+        }
+        if let Some(Err(e)) = self.warm_start_from.as_ref().map(ExchangeState::load) {
+            errors.push(format!("warm_start_from: {}", e));
+        }
+
+        for m in &self.exchange.markets {
+            if !oracle_symbols.contains(&m.symbol) {
+                errors.push(format!("exchange.markets: '{}' has no oracle price feed", m.symbol));
+            }
+            if let Some(cp) = &m.collateral_price {
+                if !oracle_symbols.contains(&cp.feed) {
+                    errors.push(format!(
+                        "exchange.markets: collateral of '{}' is priced by '{}', which no oracle feeds",
+                        m.symbol, cp.feed
+                    ));
+                }
+                if cp.initial_usd <= 0.0 {
+                    errors.push(format!(
+                        "exchange.markets: collateral_price.initial_usd of '{}' must be > 0",
+                        m.symbol
+                    ));
+                }
+            }
+            if let Some(Err(e)) = m.mark_price.as_ref().map(MarkPriceConfig::validate) {
+                errors.push(format!("exchange.markets: '{}': {}", m.symbol, e));
+            }
+            if let Some(Err(e)) = m.circuit_breaker.as_ref().map(CircuitBreakerConfig::validate) {
+                errors.push(format!("exchange.markets: '{}': {}", m.symbol, e));
+            }
+        }
+
+        // Joining agents first, so later entries may refer to them
+        for (i, entry) in self.timeline.iter().enumerate() {
+            if let TimelineJsonEntry::SpawnAgent { agent, .. } = entry {
+                let label = format!("timeline[{}] ({})", i, agent.agent_type);
+                if agent.agent_type == "human" {
+                    errors.push(format!("{}: the HumanAgent cannot join via the timeline", label));
+                } else if let Err(e) = registry.check(agent) {
+                    errors.push(format!("{}: {}", label, e));
+                } else {
+                    check_spec_refs(&label, agent, &markets, &mut ids, &mut errors);
+                    self.check_portfolio_is_local(&label, agent, &mut errors);
+                }
+            }
+        }
+        for (i, entry) in self.timeline.iter().enumerate() {
+            let label = format!("timeline[{}]", i);
+            match entry {
+                TimelineJsonEntry::PriceShock { symbol, pct, .. } => {
+                    // Collateral feeds too: depeg and collateral-crash scenarios
+                    let collateral_feed = self
+                        .exchange
+                        .markets
+                        .iter()
+                        .any(|m| m.collateral_price.as_ref().is_some_and(|cp| cp.feed == *symbol));
+                    if !markets.contains(symbol.as_str()) && !collateral_feed {
+                        errors.push(format!(
+                            "{}: symbol '{}' is not an exchange market or collateral feed",
+                            label, symbol
+                        ));
+                    }
+                    if *pct <= -100.0 {
+                        errors.push(format!("{}: pct must be > -100", label));
+                    }
+                }
+                TimelineJsonEntry::RemoveAgent { id, .. } | TimelineJsonEntry::SetParam { id, .. } => {
+                    if *id == self.exchange.id || *id == DIRECTOR_ID {
+                        errors.push(format!("{}: agent id {} cannot be targeted", label, id));
+                    } else if !ids.contains_key(id) {
+                        errors.push(format!("{}: unknown agent id {}", label, id));
+                    }
+                }
+                TimelineJsonEntry::SpawnAgent { .. } => {}
+            }
+        }
+
+        let leaders = self
+            .agent_specs()
+            .into_iter()
+            .chain(timeline_specs(self))
+            .filter(|spec| spec.agent_type == "copy_trader")
+            .filter_map(|spec| spec.params.get("leader_id").and_then(|v| v.as_u64()));
+        for leader in leaders {
+            if !ids.contains_key(&(leader as u32)) {
+                errors.push(format!("copy_trader: unknown leader_id {}", leader));
+            }
+        }
+        let subscribers = self
+            .agent_specs()
+            .into_iter()
+            .filter(|spec| spec.agent_type == "oracle")
+            .filter_map(|spec| spec.parse::<OracleConfig>().ok())
+            .flat_map(|cfg| cfg.subscribers.unwrap_or_default());
+        for subscriber in subscribers {
+            if !ids.contains_key(&subscriber) {
+                errors.push(format!("oracle: unknown subscriber {}", subscriber));
+            }
+        }
+        let venues = self
+            .agent_specs()
+            .into_iter()
+            .chain(timeline_specs(self))
+            .filter(|spec| spec.agent_type == "funding_arb")
+            .filter_map(|spec| spec.params.get("hedge_venue").and_then(|v| v.as_u64()));
+        for venue in venues {
+            if !ids.get(&(venue as u32)).is_some_and(|agent| agent.starts_with("swap_exchange ")) {
+                errors.push(format!("funding_arb: hedge_venue {} is not a swap_exchange agent", venue));
+            }
+        }
+        let recapitalization = self.capital.recapitalize.as_ref();
+        if recapitalization.is_some_and(|r| r.amount_usd == Some(0)) {
+            errors.push("capital.recapitalize.amount_usd: must be > 0".to_string());
+        }
+        for id in self.risk_limits.iter().flat_map(|limits| limits.agents.keys()) {
+            if !ids.contains_key(id) {
+                errors.push(format!("risk_limits.agents: unknown agent id {}", id));
+            }
+        }
+
+        let vara_refs = self
+            .vara
+            .keys
+            .keys()
+            .map(|id| ("keys", *id))
+            .chain(self.vara.deposits.keys().map(|id| ("deposits", *id)))
+            .chain(self.vara.local_agents.iter().map(|id| ("local_agents", *id)))
+            .chain(self.vara.cross_margin.iter().map(|id| ("cross_margin", *id)))
+            .chain(
+                self.vara
+                    .tx_spend
+                    .iter()
+                    .flat_map(|spend| spend.budgets.keys())
+                    .map(|id| ("tx_spend.budgets", *id)),
+            );
+        for (field, id) in vara_refs {
+            if id == self.exchange.id || id == DIRECTOR_ID || !ids.contains_key(&id) {
+                errors.push(format!("vara.{}: unknown agent id {}", field, id));
+            }
+        }
+        // The contract margins every position on its own
+        for id in &self.vara.cross_margin {
+            if !self.vara.local_agents.contains(id) {
+                errors.push(format!("vara.cross_margin: agent {} must be in vara.local_agents", id));
+            }
+        }
+        for (id, amount) in &self.vara.deposits {
+            if *amount < 0 {
+                errors.push(format!("vara.deposits: negative deposit for agent {}", id));
+            }
+        }
+        if self.vara.default_deposit.is_some_and(|d| d < 0) {
+            errors.push("vara.default_deposit: must be >= 0".to_string());
+        }
+        if let Some(reorg) = &self.vara.reorg {
+            if !(0.0..=1.0).contains(&reorg.probability) {
+                errors.push("vara.reorg.probability: must be in [0, 1]".to_string());
+            }
+            if reorg.depth_blocks == 0 {
+                errors.push("vara.reorg.depth_blocks: must be > 0".to_string());
+            }
+        }
+        if self.vara.local_fallback.is_some_and(|f| f.retry_after_ms == 0) {
+            errors.push("vara.local_fallback.retry_after_ms: must be > 0".to_string());
+        }
+        if let Err(e) = self.activity_schedule.validate() {
+            errors.push(format!("activity_schedule.{}", e));
+        }
+        if let Err(e) = self.api.limits.validate() {
+            errors.push(format!("api.limits.{}", e));
+        }
+        for symbol in self.api.limits.symbols.iter().filter(|s| !markets.contains(s.as_str())) {
+            errors.push(format!("api.limits.symbols: unknown market '{}'", symbol));
+        }
+        if let Some(Err(e)) = self.api.cors.as_ref().map(CorsConfig::validate) {
+            errors.push(format!("api.cors.{}", e));
+        }
+        if let Err(e) = self.cascades.validate() {
+            errors.push(format!("cascades.{}", e));
+        }
+        if let Err(e) = self.market_stats.validate() {
+            errors.push(format!("market_stats.{}", e));
+        }
+        if let Some(Err(e)) = self.watchdog.as_ref().map(WatchdogConfig::validate) {
+            errors.push(format!("watchdog.{}", e));
+        }
+        for (i, webhook) in self.webhooks.iter().enumerate() {
+            if let Err(e) = webhook.validate() {
+                errors.push(format!("webhooks[{}].{}", i, e));
+            }
+        }
+        if let DeadLetterPolicy::Retry {
+            after_ticks,
+            max_attempts,
+        } = self.dead_letters
+        {
+            if after_ticks == 0 {
+                errors.push("dead_letters.after_ticks: must be > 0".to_string());
+            }
+            if max_attempts == 0 {
+                errors.push("dead_letters.max_attempts: must be > 0".to_string());
+            }
+        }
+        let groups: HashSet<String> = self
+            .agent_specs()
+            .into_iter()
+            .chain(timeline_specs(self))
+            .filter_map(|spec| spec.groups().ok())
+            .flatten()
+            .chain(self.exchange.groups.iter().cloned())
+            .collect();
+        for (kind, group) in &self.broadcast_groups {
+            if let Err(e) = broadcast_kind(kind) {
+                errors.push(format!("broadcast_groups.{}: {}", kind, e));
+            }
+            if !groups.contains(group) {
+                errors.push(format!("broadcast_groups.{}: no agent is in group '{}'", kind, group));
+            }
+        }
+        if let Some(monitor) = &self.vara.balance_monitor {
+            if let Some(owner) = ids.get(&monitor.id) {
+                errors.push(format!(
+                    "vara.balance_monitor: id {} already used by {}",
+                    monitor.id, owner
+                ));
+            }
+            if let Err(e) = monitor.check() {
+                errors.push(format!("vara.balance_monitor: {}", e));
+            }
+            for id in monitor.accounts.iter().chain(&monitor.funder) {
+                if !ids.contains_key(id) || self.vara.local_agents.contains(id) {
+                    errors.push(format!("vara.balance_monitor: {} is not an on-chain agent", id));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The contract keeps one position per account and side, so a trader with
+    /// several markets (or a hedge leg) can only run as a local agent.
+    fn check_portfolio_is_local(&self, label: &str, spec: &AgentSpec, errors: &mut Vec<String>) {
+        let legs = ["symbols", "hedges"]
+            .iter()
+            .filter_map(|key| spec.params.get(*key).and_then(|v| v.as_array()))
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0);
+        let hedged = spec.params.get("hedge_symbol").is_some_and(|v| !v.is_null());
+        if (legs > 1 || hedged) && spec.id().is_some_and(|id| !self.vara.local_agents.contains(&id)) {
+            errors.push(format!(
+                "{}: traders with several symbols must be listed in vara.local_agents",
+                label
+            ));
+        }
+    }
+
+    /// All agents in start order: per-type sections first (oracles, market maker
+    /// before other traders for seed liquidity, traders, keepers, liquidator),
+    /// then the `agents` array, then generated populations.
+    fn agent_specs(&self) -> Vec<AgentSpec> {
+        let mut specs = Vec::new();
+        specs.extend(self.oracles.iter().map(|c| AgentSpec::from_config("oracle", c)));
+        if let Some(c) = &self.market_maker {
+            specs.push(AgentSpec::from_config("market_maker", c));
+        }
+        specs.extend(self.smart_traders.iter().map(|c| AgentSpec::from_config("smart_trader", c)));
+        specs.extend(self.limit_traders.iter().map(|c| AgentSpec::from_config("limit_trader", c)));
+        specs.extend(self.keepers.iter().map(|c| AgentSpec::from_config("keeper", c)));
+        if let Some(c) = &self.liquidation_agent {
+            specs.push(AgentSpec::from_config("liquidation", c));
+        }
+        specs.extend(self.agents.iter().cloned());
+        for (i, p) in self.populations.iter().enumerate() {
+            match p.expand(i, self.seed) {
+                Ok(generated) => specs.extend(generated),
+                Err(e) => eprintln!("[Scenario] populations[{}]: {}", i, e),
+            }
+        }
+        specs
+    }
+}
+
+/// Id uniqueness and market reference checks shared by `agents` and timeline joins.
+fn check_spec_refs(
+    label: &str,
+    spec: &AgentSpec,
+    markets: &HashSet<&str>,
+    ids: &mut HashMap<u32, String>,
+    errors: &mut Vec<String>,
+) {
+    if let Some(id) = spec.id() {
+        let name = spec.params.get("name").and_then(|v| v.as_str()).unwrap_or("?");
+        if let Some(prev) = ids.insert(id, format!("{} '{}'", spec.agent_type, name)) {
+            errors.push(format!("{}: duplicate agent id {} (already used by {})", label, id, prev));
+        }
+    }
+    let legs = spec.params.get("symbols").and_then(|v| v.as_array());
+    let symbols = spec
+        .params
+        .get("symbol")
+        .into_iter()
+        .chain(legs.into_iter().flatten().filter_map(|leg| leg.get("symbol")))
+        .filter_map(|v| v.as_str())
+        .filter(|s| !s.is_empty());
+    for symbol in symbols {
+        if !markets.contains(symbol) {
+            errors.push(format!("{}: symbol '{}' is not an exchange market", label, symbol));
+        }
+    }
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            scenario_name: "simple_demo".to_string(),
+            duration_sec: 10,
+            logs_dir: "logs".to_string(),
+            exchange: ExchangeConfig {
+                id: 1,
+                name: "PerpExchange".to_string(),
+                markets: vec![MarketJsonConfig {
+                    id: 0,
+                    symbol: "ETH-USD".to_string(),
+                    index_token: "ETH".to_string(),
+                    index_decimals: 18,
+                    collateral_token: "USDT".to_string(),
+                    collateral_decimals: 6,
+                    initial_liquidity: LiquidityConfig {
+                        collateral_amount: 1_000_000_000_000,
+                        index_amount: 500_000_000_000,
+                        liquidity_usd: 2_000_000_000_000,
+                    },
+                    position_fee_bps: default_position_fee_bps(),
+                    fee_rates: FeeRates::default(),
+                    collateral_price: None,
+                    price_rules: PriceRules::default(),
+                    mark_price: None,
+                    circuit_breaker: None,
+                    oi_caps: OiCaps::default(),
+                }],
+                snapshot_interval_ms: default_snapshot_interval(),
+                trade_history: default_trade_history(),
+                common: AgentCommonJsonConfig::default(),
+                groups: vec![],
+            },
+            oracles: vec![OracleConfig {
+                id: 2,
+                name: "PythOracle".to_string(),
+                symbols: vec!["ETH-USD".to_string(), "USDT-USD".to_string()],
+                provider: "Pyth".to_string(),
+                cache_duration_ms: 10000,
+                wake_interval_ms: 3000,
+                spread: None,
+                record: None,
+                playback: None,
+                fallback_providers: vec![],
+                failover_after: default_failover_after(),
+                recovery_blocks: default_recovery_blocks(),
+                deviation_bps: None,
+                max_age_ms: default_max_age_ms(),
+                synthetic: None,
+                common: AgentCommonJsonConfig::default(),
+                subscribers: None,
+            }],
+            smart_traders: vec![],
+            limit_traders: vec![],
+            liquidation_agent: None,
+            market_maker: None,
+            keepers: vec![],
+            agents: vec![],
+            populations: vec![],
+            seed: 0,
+            timeline: vec![],
+            equity_sample_sec: default_equity_sample_sec(),
+            warmup: WarmupJsonConfig::default(),
+            warm_start_from: None,
+            cascades: CascadeConfig::default(),
+            market_stats: MarketStatsConfig::default(),
+            webhooks: vec![],
+            logging: LoggingJsonConfig::default(),
+            vara: VaraJsonConfig::default(),
+            risk_limits: None,
+            capital: CapitalConfig::default(),
+            dead_letters: DeadLetterPolicy::default(),
+            watchdog: None,
+            broadcast_groups: BTreeMap::new(),
+            activity_schedule: ActivitySchedule::default(),
+            api: ApiJsonConfig::default(),
+        }
+    }
+}
+
+/// Helper function to parse strategy from JSON config
+fn parse_strategy(smart_cfg: &SmartTraderJsonConfig) -> TradingStrategy {
+    let side = match smart_cfg.side.to_lowercase().as_str() {
+        "short" | "sell" => Side::Sell,
+        _ => Side::Buy,
+    };
+
+    match smart_cfg.strategy.to_lowercase().as_str() {
+        "hodler" => TradingStrategy::Hodler {
+            side,
+            hold_duration_sec: smart_cfg.hold_duration_sec,
+            leverage: smart_cfg.leverage,
+            take_profit_pct: smart_cfg.take_profit_pct,
+            stop_loss_pct: smart_cfg.stop_loss_pct,
+        },
+        "institutional" | "inst" => TradingStrategy::Institutional {
+            side,
+            leverage: smart_cfg.leverage,
+            take_profit_pct: smart_cfg.take_profit_pct.unwrap_or(3.0),
+            stop_loss_pct: smart_cfg.stop_loss_pct.unwrap_or(8.0),
+            min_hold_sec: smart_cfg.min_hold_sec.unwrap_or(60),
+            max_hold_sec: smart_cfg.max_hold_sec.unwrap_or(600),
+            reentry_delay_sec: smart_cfg.reentry_delay_sec.unwrap_or(30),
+        },
+        "trend_follower" | "trend" => TradingStrategy::TrendFollower {
+            lookback_sec: smart_cfg.lookback_sec,
+            threshold_pct: smart_cfg.threshold_pct,
+            leverage: smart_cfg.leverage,
+            take_profit_pct: smart_cfg.take_profit_pct,
+            stop_loss_pct: smart_cfg.stop_loss_pct,
+        },
+        "mean_reversion" | "meanrev" | "mr" => TradingStrategy::MeanReversion {
+            lookback_periods: smart_cfg.lookback_periods.unwrap_or(20),
+            entry_deviation_pct: smart_cfg.entry_deviation_pct.unwrap_or(1.0),
+            exit_deviation_pct: smart_cfg.exit_deviation_pct.unwrap_or(0.2),
+            leverage: smart_cfg.leverage,
+            max_hold_sec: smart_cfg.max_hold_sec.unwrap_or(300),
+        },
+        "arbitrageur" | "arb" => TradingStrategy::Arbitrageur {
+            min_imbalance_pct: smart_cfg.entry_deviation_pct.unwrap_or(5.0), // reuse field
+            leverage: smart_cfg.leverage,
+            hold_duration_sec: smart_cfg.hold_duration_sec,
+            take_profit_pct: smart_cfg.take_profit_pct,
+            stop_loss_pct: smart_cfg.stop_loss_pct,
+        },
+        "funding_harvester" | "funding" | "fh" => TradingStrategy::FundingHarvester {
+            min_imbalance_pct: smart_cfg.entry_deviation_pct.unwrap_or(3.0),
+            leverage: smart_cfg.leverage,
+            min_hold_sec: smart_cfg.min_hold_sec.unwrap_or(60),
+            max_hold_sec: smart_cfg.max_hold_sec.unwrap_or(600),
+            exit_imbalance_pct: smart_cfg.exit_deviation_pct.unwrap_or(5.0),
+            stop_loss_pct: smart_cfg.stop_loss_pct.unwrap_or(10.0),
+        },
+        _ => {
+            eprintln!("[Scenario] Unknown strategy: {}, using Hodler", smart_cfg.strategy);
+            TradingStrategy::Hodler {
+                side,
+                hold_duration_sec: smart_cfg.hold_duration_sec,
+                leverage: smart_cfg.leverage,
+                take_profit_pct: None,
+                stop_loss_pct: None,
+            }
+        }
+    }
+}
+
+/// Helper function to create SmartTraderAgent from JSON config
+fn create_smart_trader(smart_cfg: &SmartTraderJsonConfig, exchange_id: u32) -> SmartTraderAgent {
+    let strategy = parse_strategy(smart_cfg);
+
+    let qty_min = smart_cfg.qty_min.unwrap_or(smart_cfg.qty);
+    let qty_max = smart_cfg.qty_max.unwrap_or(smart_cfg.qty);
+
+    let smart_config = SmartTraderConfig {
+        name: smart_cfg.name.clone(),
+        exchange_id,
+        symbol: smart_cfg.symbol.clone(),
+        address: None,
+        strategy,
+        qty_min,
+        qty_max,
+        wake_interval_ms: smart_cfg.wake_interval_ms,
+        balance: smart_cfg.balance,
+    };
+
+    SmartTraderAgent::new(smart_cfg.id, smart_config)
+}
+
+fn parse_limit_strategy(cfg: &LimitTraderJsonConfig) -> LimitStrategy {
+    match cfg.strategy.to_lowercase().as_str() {
+        "mean_reversion" | "meanrev" => LimitStrategy::MeanReversion {
+            entry_offset_pct: cfg.entry_offset_pct.unwrap_or(1.5),
+            stop_loss_pct: cfg.stop_loss_pct.unwrap_or(3.0),
+            take_profit_pct: cfg.take_profit_pct.unwrap_or(2.0),
+            leverage: cfg.leverage,
+            trend_lookback: cfg.trend_lookback.unwrap_or(10),
+        },
+        "breakout" => {
+            let direction = match cfg.direction.as_deref() {
+                Some("sell") | Some("short") => Side::Sell,
+                _ => Side::Buy,
+            };
+            LimitStrategy::Breakout {
+                breakout_offset_pct: cfg.breakout_offset_pct.unwrap_or(1.0),
+                stop_loss_pct: cfg.stop_loss_pct.unwrap_or(2.0),
+                take_profit_pct: cfg.take_profit_pct.unwrap_or(3.0),
+                leverage: cfg.leverage,
+                direction,
+            }
+        }
+        "grid" => LimitStrategy::Grid {
+            levels: 5,
+            spacing_pct: 1.0,
+            qty_per_level: cfg.qty,
+            leverage: cfg.leverage,
+            take_profit_pct: cfg.take_profit_pct.unwrap_or(1.5),
+        },
+        "smart" => {
+            let order_mode = match cfg.order_mode.as_deref() {
+                Some("active") => OrderMode::Active,
+                _ => OrderMode::Passive,
+            };
+            LimitStrategy::Smart {
+                sma_fast: cfg.sma_fast.unwrap_or(10),
+                sma_slow: cfg.sma_slow.unwrap_or(30),
+                rsi_period: cfg.rsi_period.unwrap_or(14),
+                rsi_low: cfg.rsi_low.unwrap_or(35.0),
+                rsi_high: cfg.rsi_high.unwrap_or(65.0),
+                atr_period: cfg.atr_period.unwrap_or(14),
+                entry_atr_mult: cfg.entry_atr_mult.unwrap_or(0.5),
+                stop_atr_mult: cfg.stop_atr_mult.unwrap_or(1.2),
+                take_atr_mult: cfg.take_atr_mult.unwrap_or(1.8),
+                leverage: cfg.leverage,
+                order_mode,
+            }
+        }
+        _ => LimitStrategy::MeanReversion {
             entry_offset_pct: 1.5,
             stop_loss_pct: 3.0,
             take_profit_pct: 2.0,
@@ -571,66 +2258,1329 @@ fn parse_limit_strategy(cfg: &LimitTraderJsonConfig) -> LimitStrategy {
     }
 }
 
-fn create_limit_trader(cfg: &LimitTraderJsonConfig, exchange_id: u32) -> LimitTraderAgent {
-    let strategy = parse_limit_strategy(cfg);
+fn create_limit_trader(cfg: &LimitTraderJsonConfig, exchange_id: u32) -> LimitTraderAgent {
+    let strategy = parse_limit_strategy(cfg);
+
+    let config = LimitTraderConfig {
+        name: cfg.name.clone(),
+        exchange_id,
+        symbol: cfg.symbol.clone(),
+        address: None,
+        strategy,
+        qty: cfg.qty,
+        wake_interval_ms: cfg.wake_interval_ms,
+        balance: cfg.balance,
+        trailing_stop_pct: cfg.trailing_stop_pct,
+    };
+
+    LimitTraderAgent::new(cfg.id, config)
+}
+
+const DEFAULT_DEPOSIT_MICRO_USD: i128 = 1_000_000_000_000; // $1M
+
+/// Settings every agent section accepts; applied by the kernel through `AgentSpec`, not by the agent.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct AgentCommonJsonConfig {
+    /// Console output of this agent (quiet / info / debug); default `logging.verbosity`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    verbosity: Option<Verbosity>,
+    /// Join this late (see `AgentSpec::window`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start_delay_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    active_window: Option<ActiveWindowConfig>,
+}
+
+/// Part of the run an agent takes part in (`active_window`), in seconds after the start.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ActiveWindowConfig {
+    #[serde(default)]
+    start_sec: u64,
+    /// None = until the end of the run
+    #[serde(default)]
+    end_sec: Option<u64>,
+}
+
+/// Entry of the unified `agents` array: `{"type": "smart_trader", "id": 20, ...}`.
+/// All other fields are the parameters of that agent type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AgentSpec {
+    #[serde(rename = "type")]
+    agent_type: String,
+    #[serde(flatten)]
+    params: serde_json::Map<String, serde_json::Value>,
+}
+
+impl AgentSpec {
+    fn from_config<T: Serialize>(agent_type: &str, cfg: &T) -> Self {
+        let params = match serde_json::to_value(cfg) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        Self {
+            agent_type: agent_type.to_string(),
+            params,
+        }
+    }
+
+    fn id(&self) -> Option<u32> {
+        self.params.get("id").and_then(|v| v.as_u64()).map(|id| id as u32)
+    }
+
+    /// Console output level (`verbosity`, accepted by every agent type); None = scenario default.
+    fn verbosity(&self) -> Result<Option<Verbosity>, String> {
+        self.params
+            .get("verbosity")
+            .map(|v| serde_json::from_value(v.clone()).map_err(|e| format!("verbosity: {}", e)))
+            .transpose()
+    }
+
+    /// Inbox rate limit (`inbox_rate`, messages per sim-second, accepted by every agent type);
+    /// None = unlimited.
+    fn inbox_rate(&self) -> Result<Option<f64>, String> {
+        let Some(value) = self.params.get("inbox_rate") else {
+            return Ok(None);
+        };
+        match value.as_f64() {
+            Some(rate) if rate > 0.0 => Ok(Some(rate)),
+            _ => Err(format!("inbox_rate: must be a number > 0, got {}", value)),
+        }
+    }
+
+    /// Agent groups (`groups`, names for `broadcast_groups`, accepted by every agent type).
+    fn groups(&self) -> Result<Vec<String>, String> {
+        self.params
+            .get("groups")
+            .map(|v| serde_json::from_value(v.clone()).map_err(|e| format!("groups: {}", e)))
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+
+    /// Join and leave time in ns after the start (`active_window.start_sec` plus `start_delay_ms`,
+    /// and `active_window.end_sec`, accepted by every agent type); None = the whole run.
+    fn window(&self) -> Result<Option<(u64, Option<u64>)>, String> {
+        let delay_ms = match self.params.get("start_delay_ms").filter(|v| !v.is_null()) {
+            None => 0.0,
+            Some(value) => value
+                .as_f64()
+                .filter(|ms| *ms >= 0.0)
+                .ok_or_else(|| format!("start_delay_ms: must be a number >= 0, got {}", value))?,
+        };
+        let window: ActiveWindowConfig = self
+            .params
+            .get("active_window")
+            .filter(|v| !v.is_null())
+            .map(|v| serde_json::from_value(v.clone()).map_err(|e| format!("active_window: {}", e)))
+            .transpose()?
+            .unwrap_or_default();
+        let join_ns = window.start_sec * 1_000_000_000 + (delay_ms * 1_000_000.0) as u64;
+        let leave_ns = window.end_sec.map(|sec| sec * 1_000_000_000);
+        if leave_ns.is_some_and(|leave_ns| leave_ns <= join_ns) {
+            return Err("active_window: end_sec must be after the agent joins".to_string());
+        }
+        Ok((join_ns > 0 || leave_ns.is_some()).then_some((join_ns, leave_ns)))
+    }
+
+    /// Deserialize the parameters into the config struct of this agent type.
+    fn parse<T: DeserializeOwned>(&self) -> Result<T, String> {
+        let mut params = self.params.clone();
+        // Applied by the kernel, not by the agent (see `verbosity`, `inbox_rate`, `groups`, `window`)
+        params.remove("verbosity");
+        params.remove("inbox_rate");
+        params.remove("groups");
+        params.remove("start_delay_ms");
+        params.remove("active_window");
+        serde_path_to_error::deserialize(serde_json::Value::Object(params)).map_err(|e| {
+            if e.path().iter().next().is_some() {
+                format!("{}: {}", e.path(), e.inner())
+            } else {
+                e.inner().to_string()
+            }
+        })
+    }
+}
+
+/// Cohort of `count` agents of one type generated from a parameter template.
+/// Template values may be distributions (see `population.rs`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PopulationConfig {
+    #[serde(rename = "type")]
+    agent_type: String,
+    count: u32,
+    id_start: u32,
+    /// Agent names are `<name_prefix>_<id>` (default: the agent type)
+    #[serde(default)]
+    name_prefix: Option<String>,
+    /// Sampling seed (default: scenario seed + population index)
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(flatten)]
+    params: serde_json::Map<String, serde_json::Value>,
+}
+
+impl PopulationConfig {
+    fn expand(&self, index: usize, scenario_seed: u64) -> Result<Vec<AgentSpec>, String> {
+        let prefix = self.name_prefix.as_deref().unwrap_or(&self.agent_type);
+        let seed = self.seed.unwrap_or(scenario_seed.wrapping_add(index as u64));
+        let generated = population::expand(&self.params, self.count, self.id_start, prefix, seed)?;
+        Ok(generated
+            .into_iter()
+            .map(|params| AgentSpec {
+                agent_type: self.agent_type.clone(),
+                params,
+            })
+            .collect())
+    }
+}
+
+/// Reserved agent id of the DirectorAgent (only present with a non-empty timeline)
+const DIRECTOR_ID: u32 = 99;
+
+/// Entry of the scenario `timeline`: `{"at_sec": 120, "action": "price_shock", ...}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+enum TimelineJsonEntry {
+    /// Move oracle prices of `symbol` by `pct` percent, for `duration_sec` or until the end
+    PriceShock {
+        at_sec: u64,
+        symbol: String,
+        pct: f64,
+        #[serde(default)]
+        duration_sec: Option<u64>,
+    },
+    /// Start a new agent: `"agent": {"type": "smart_trader", "id": 40, ...}`
+    SpawnAgent { at_sec: u64, agent: AgentSpec },
+    /// Stop an agent (its open positions stay on the exchange)
+    RemoveAgent { at_sec: u64, id: u32 },
+    /// Change a runtime parameter of an agent (e.g. `wake_interval_ms`, `leverage`)
+    SetParam {
+        at_sec: u64,
+        id: u32,
+        key: String,
+        value: serde_json::Value,
+    },
+}
+
+impl TimelineJsonEntry {
+    fn joining_spec(&self) -> Option<&AgentSpec> {
+        match self {
+            TimelineJsonEntry::SpawnAgent { agent, .. } => Some(agent),
+            _ => None,
+        }
+    }
+}
+
+/// Agent built from a spec, with what the scenario runner needs to know about it.
+struct AgentBuild {
+    agent: Box<dyn Agent>,
+    /// Initial deposit (micro-USD) for trader accounts; None = no collateral account
+    balance: Option<i128>,
+    /// Agent type label used to aggregate reports
+    kind: String,
+}
+
+/// Realtime-only channels of the HumanAgent (HTTP/WS API) and remote agents (WS).
+struct HumanChannels {
+    cmd_rx: Receiver<ApiCommand>,
+    response_tx: Sender<ApiResponse>,
+    tick_ms: u64,
+    remotes: RemoteHub,
+    sessions: SessionHub,
+}
+
+/// Shared inputs of agent factories.
+struct BuildContext<'a> {
+    exchange_id: u32,
+    /// Scenario seed for agents with pseudo-random behaviour
+    seed: u64,
+    /// Oracles that accept price shocks from scripted agents
+    oracle_ids: Vec<u32>,
+    /// Deposit of traders without an explicit `balance` (micro-USD)
+    default_deposit: i128,
+    /// Agents filled locally by the exchange (may have no key / address)
+    local_agents: HashSet<u32>,
+    address_book: &'a AddressBook,
+    pnl_book: PnlBook,
+    human: Option<HumanChannels>,
+    schedule: &'a ActivitySchedule,
+    /// First oracle price per symbol (USD, `warmup.start_prices`)
+    start_prices: &'a BTreeMap<String, f64>,
+}
+
+impl BuildContext<'_> {
+    fn address(&self, id: u32, agent_type: &str) -> Result<String, String> {
+        match self.address_book.address_for_agent(id) {
+            Some(address) => Ok(address),
+            None if self.local_agents.contains(&id) => Ok(format!("local_{}", id)),
+            None => Err(format!("{}: missing address for id={}", agent_type, id)),
+        }
+    }
+}
+
+/// Builds an agent from its spec. `Ok(None)` = agent not available in this mode.
+type AgentFactory = fn(&AgentSpec, &BuildContext) -> Result<Option<AgentBuild>, String>;
 
-    let config = LimitTraderConfig {
+/// Checks the parameters of a spec without building the agent.
+type AgentCheck = fn(&AgentSpec) -> Result<(), String>;
+
+fn check_params<T: DeserializeOwned>(spec: &AgentSpec) -> Result<(), String> {
+    spec.parse::<T>().map(|_| ())
+}
+
+fn check_oracle(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: OracleConfig = spec.parse()?;
+    if let Some(spread) = cfg.spread {
+        spread.validate()?;
+    }
+    if let Some(name) = cfg
+        .fallback_providers
+        .iter()
+        .find(|p| !REFERENCE_PROVIDERS.contains(&p.to_lowercase().as_str()))
+    {
+        return Err(format!(
+            "fallback_providers: unknown provider '{}' ({})",
+            name,
+            REFERENCE_PROVIDERS.join(", ")
+        ));
+    }
+    if cfg.failover_after == 0 || cfg.recovery_blocks == 0 {
+        return Err("failover_after and recovery_blocks must be at least 1".to_string());
+    }
+    if cfg.deviation_bps.is_some_and(|bps| !bps.is_finite() || bps <= 0.0) {
+        return Err("deviation_bps must be positive".to_string());
+    }
+    if cfg.deviation_bps.is_some() && cfg.max_age_ms < cfg.wake_interval_ms {
+        return Err("max_age_ms must be at least wake_interval_ms".to_string());
+    }
+    if cfg.playback.is_some() && !cfg.fallback_providers.is_empty() {
+        return Err("a playback oracle has no fallback_providers".to_string());
+    }
+    match (&cfg.synthetic, cfg.provider == "Synthetic") {
+        (None, true) => return Err("the Synthetic provider needs a `synthetic` section".to_string()),
+        (Some(_), false) => return Err("`synthetic` is only used with provider Synthetic".to_string()),
+        (Some(_), true) if cfg.record.is_some() || cfg.playback.is_some() => {
+            return Err("synthetic prices are reproducible from the seed: no record / playback".to_string());
+        }
+        (Some(synthetic), true) => {
+            if let Some(symbol) = cfg.symbols.iter().find(|s| !synthetic.start_prices.contains_key(*s)) {
+                return Err(format!("synthetic.start_prices: no price for {}", symbol));
+            }
+            if synthetic.start_prices.values().any(|p| !p.is_finite() || *p <= 0.0) {
+                return Err("synthetic.start_prices must be positive".to_string());
+            }
+            if !synthetic.daily_vol_pct.is_finite() || synthetic.daily_vol_pct < 0.0 {
+                return Err("synthetic.daily_vol_pct must be >= 0".to_string());
+            }
+        }
+        (None, false) => {}
+    }
+    match (&cfg.record, &cfg.playback) {
+        (Some(_), Some(_)) => Err("set at most one of record / playback".to_string()),
+        (None, Some(path)) if !Path::new(path).is_file() => Err(format!("playback: no recording at {}", path)),
+        _ => Ok(()),
+    }
+}
+
+fn check_arbitrage(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: ArbitrageJsonConfig = spec.parse()?;
+    if !REFERENCE_PROVIDERS.contains(&cfg.reference.to_lowercase().as_str()) {
+        return Err(format!(
+            "reference: unknown provider '{}' ({})",
+            cfg.reference,
+            REFERENCE_PROVIDERS.join(", ")
+        ));
+    }
+    if cfg.exit_basis_bps >= cfg.entry_basis_bps {
+        return Err("exit_basis_bps must be below entry_basis_bps".to_string());
+    }
+    Ok(())
+}
+
+fn check_noise_trader(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: NoiseTraderJsonConfig = spec.parse()?;
+    if cfg.orders_per_min <= 0.0 {
+        return Err("orders_per_min must be positive".to_string());
+    }
+    for (field, p) in [("buy_prob", cfg.buy_prob), ("close_prob", cfg.close_prob)] {
+        if !(0.0..=1.0).contains(&p) {
+            return Err(format!("{} must be within [0, 1]", field));
+        }
+    }
+    if cfg.leverage_choices.is_empty() || cfg.leverage_choices.contains(&0) {
+        return Err("leverage_choices must be non-empty and >= 1".to_string());
+    }
+    if !cfg.leverage_weights.is_empty() {
+        if cfg.leverage_weights.len() != cfg.leverage_choices.len() {
+            return Err("leverage_weights must match leverage_choices".to_string());
+        }
+        if cfg.leverage_weights.iter().any(|w| *w < 0.0) || cfg.leverage_weights.iter().sum::<f64>() <= 0.0 {
+            return Err("leverage_weights must be non-negative with a positive sum".to_string());
+        }
+    }
+    Ok(())
+}
+
+fn check_whale(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: WhaleJsonConfig = spec.parse()?;
+    if cfg.total_qty <= 0.0 || cfg.dump_qty < 0.0 {
+        return Err("total_qty must be positive and dump_qty non-negative".to_string());
+    }
+    if cfg.accumulate_chunks == 0 || cfg.dump_chunks == 0 {
+        return Err("accumulate_chunks and dump_chunks must be at least 1".to_string());
+    }
+    if cfg.dump_at_sec < cfg.accumulate_start_sec + cfg.accumulate_sec {
+        return Err("dump_at_sec must be after the accumulation phase".to_string());
+    }
+    if cfg.shock_pct.is_some_and(|pct| pct <= -100.0) {
+        return Err("shock_pct must be above -100".to_string());
+    }
+    Ok(())
+}
+
+fn check_copy_trader(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: CopyTraderJsonConfig = spec.parse()?;
+    if cfg.ratio <= 0.0 {
+        return Err("ratio must be positive".to_string());
+    }
+    if cfg.leader_id == cfg.id {
+        return Err("leader_id must be another agent".to_string());
+    }
+    Ok(())
+}
+
+fn check_quoting_mm(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: QuotingMmJsonConfig = spec.parse()?;
+    if cfg.qty <= 0.0 || cfg.spread_bps <= 0.0 {
+        return Err("qty and spread_bps must be positive".to_string());
+    }
+    if cfg.max_inventory_usd <= 0 || cfg.quote_ttl_sec == 0 {
+        return Err("max_inventory_usd and quote_ttl_sec must be positive".to_string());
+    }
+    Ok(())
+}
+
+fn check_human(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: HumanJsonConfig = spec.parse()?;
+    let Some(sessions) = &cfg.sessions else {
+        return Ok(());
+    };
+    if sessions.max_sessions == 0 || sessions.first_id.checked_add(sessions.max_sessions).is_none() {
+        return Err("sessions: max_sessions must be positive and the ids must fit in u32".to_string());
+    }
+    if sessions.ids().contains(&cfg.id) {
+        return Err(format!("sessions: ids overlap the HumanAgent's own id {}", cfg.id));
+    }
+    if sessions.tokens.iter().any(|t| t.is_empty() || t.contains('/')) {
+        return Err("sessions: tokens must be non-empty and without '/'".to_string());
+    }
+    Ok(())
+}
+
+fn check_lp(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: LpJsonConfig = spec.parse()?;
+    if cfg.initial_usd < 0 || cfg.step_usd <= 0 {
+        return Err("initial_usd must be >= 0 and step_usd positive".to_string());
+    }
+    if cfg.max_deposit_usd.is_some_and(|max| max < cfg.initial_usd) {
+        return Err("max_deposit_usd must be at least initial_usd".to_string());
+    }
+    if cfg.remove_below_util >= cfg.add_above_util {
+        return Err("remove_below_util must be below add_above_util".to_string());
+    }
+    Ok(())
+}
+
+fn check_funding_arb(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: FundingArbJsonConfig = spec.parse()?;
+    if cfg.exit_rate_bps_hour >= cfg.entry_rate_bps_hour {
+        return Err("exit_rate_bps_hour must be below entry_rate_bps_hour".to_string());
+    }
+    if cfg.hedge_symbol.as_deref() == Some(cfg.symbol.as_str()) {
+        return Err("hedge_symbol must differ from symbol".to_string());
+    }
+    if cfg.hedge_symbol.is_some() && cfg.hedge_venue.is_some() {
+        return Err("hedge_symbol and hedge_venue are exclusive".to_string());
+    }
+    if cfg.hedge_ratio < 0.0 {
+        return Err("hedge_ratio must be >= 0".to_string());
+    }
+    Ok(())
+}
+
+fn check_swap_exchange(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: SwapExchangeJsonConfig = spec.parse()?;
+    if cfg.symbols.is_empty() {
+        return Err("symbols must not be empty".to_string());
+    }
+    let mut seen = HashSet::new();
+    for pool in &cfg.symbols {
+        if !seen.insert(pool.symbol.as_str()) {
+            return Err(format!("symbols: duplicate symbol '{}'", pool.symbol));
+        }
+        if pool.liquidity_usd <= 0 {
+            return Err(format!("symbols: {} needs a positive liquidity_usd", pool.symbol));
+        }
+    }
+    if !(0.0..10_000.0).contains(&cfg.fee_bps) {
+        return Err("fee_bps must be in [0, 10000)".to_string());
+    }
+    if !(0.0..=1.0).contains(&cfg.arb_pull) {
+        return Err("arb_pull must be in [0, 1]".to_string());
+    }
+    Ok(())
+}
+
+fn check_hedger(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: HedgerJsonConfig = spec.parse()?;
+    if cfg.hedges.is_empty() {
+        return Err("hedges must not be empty".to_string());
+    }
+    let mut seen = HashSet::new();
+    for leg in &cfg.hedges {
+        if !seen.insert(leg.symbol.as_str()) {
+            return Err(format!("hedges: duplicate symbol '{}'", leg.symbol));
+        }
+        if leg.weight < 0.0 || leg.beta == 0.0 {
+            return Err(format!("hedges: {} needs weight >= 0 and a non-zero beta", leg.symbol));
+        }
+    }
+    if cfg.band_pct <= 0.0 {
+        return Err("band_pct must be positive".to_string());
+    }
+    Ok(())
+}
+
+fn check_sma_cross(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: SmaCrossJsonConfig = spec.parse()?;
+    if cfg.fast == 0 || cfg.fast >= cfg.slow {
+        return Err("sma windows need 0 < fast < slow".to_string());
+    }
+    if cfg.qty <= 0.0 {
+        return Err("qty must be positive".to_string());
+    }
+    Ok(())
+}
+
+/// Compiles the script so syntax errors surface at load time.
+fn check_scripted(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: ScriptedJsonConfig = spec.parse()?;
+    ScriptedStrategy::new(cfg.strategy_config()?).map(|_| ())
+}
+
+fn check_bridge(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: BridgeJsonConfig = spec.parse()?;
+    if cfg.timeout_ms == 0 {
+        return Err("timeout_ms must be positive".to_string());
+    }
+    if !cfg.address.contains(':') {
+        return Err(format!("address '{}' must be host:port", cfg.address));
+    }
+    Ok(())
+}
+
+fn check_remote(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: RemoteJsonConfig = spec.parse()?;
+    if cfg.timeout_ms == 0 {
+        return Err("timeout_ms must be positive".to_string());
+    }
+    if cfg.name.is_empty() || cfg.name.contains(['/', '?', '#', ' ']) {
+        return Err(format!("name '{}' must be usable as a URL path segment", cfg.name));
+    }
+    Ok(())
+}
+
+fn check_risk_monitor(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: RiskMonitorJsonConfig = spec.parse()?;
+    if cfg.var_horizon_sec == 0 || cfg.vol_window < 2 {
+        return Err("var_horizon_sec must be positive and vol_window at least 2".to_string());
+    }
+    if cfg.bucket_bps <= 0.0 || cfg.heatmap_range_pct <= 0.0 {
+        return Err("bucket_bps and heatmap_range_pct must be positive".to_string());
+    }
+    if !(0.0..1.0).contains(&cfg.maintenance_margin) {
+        return Err("maintenance_margin must be in [0, 1)".to_string());
+    }
+    Ok(())
+}
+
+fn check_keeper(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: KeeperJsonConfig = spec.parse()?;
+    if cfg.max_batch == 0 {
+        return Err("max_batch must be at least 1".to_string());
+    }
+    Ok(())
+}
+
+fn check_smart_trader(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: SmartTraderJsonConfig = spec.parse()?;
+    check_legs(&cfg.symbol, &cfg.symbols)
+}
+
+fn check_limit_trader(spec: &AgentSpec) -> Result<(), String> {
+    let cfg: LimitTraderJsonConfig = spec.parse()?;
+    check_legs(&cfg.symbol, &cfg.symbols)
+}
+
+/// Agent types available in scenario JSON: `"type"` -> (factory, parameter check).
+struct AgentRegistry {
+    factories: HashMap<&'static str, (AgentFactory, AgentCheck)>,
+}
+
+impl AgentRegistry {
+    fn builtin() -> Self {
+        let mut registry = Self {
+            factories: HashMap::new(),
+        };
+        registry.register("oracle", build_oracle, check_oracle);
+        registry.register("market_maker", build_market_maker, check_params::<MarketMakerJsonConfig>);
+        registry.register("quoting_mm", build_quoting_mm, check_quoting_mm);
+        registry.register("smart_trader", build_smart_trader, check_smart_trader);
+        registry.register("limit_trader", build_limit_trader, check_limit_trader);
+        registry.register("arbitrage", build_arbitrage, check_arbitrage);
+        registry.register("funding_arb", build_funding_arb, check_funding_arb);
+        registry.register("hedger", build_hedger, check_hedger);
+        registry.register("swap_exchange", build_swap_exchange, check_swap_exchange);
+        registry.register("sma_cross", build_sma_cross, check_sma_cross);
+        registry.register("scripted", build_scripted, check_scripted);
+        registry.register("bridge", build_bridge, check_bridge);
+        registry.register("remote", build_remote, check_remote);
+        registry.register("noise_trader", build_noise_trader, check_noise_trader);
+        registry.register("whale", build_whale, check_whale);
+        registry.register("copy_trader", build_copy_trader, check_copy_trader);
+        registry.register("lp", build_lp, check_lp);
+        registry.register("risk_monitor", build_risk_monitor, check_risk_monitor);
+        registry.register("keeper", build_keeper, check_keeper);
+        registry.register("liquidation", build_liquidation, check_params::<LiquidationAgentConfig>);
+        registry.register("human", build_human, check_human);
+        registry
+    }
+
+    fn register(&mut self, agent_type: &'static str, factory: AgentFactory, check: AgentCheck) {
+        self.factories.insert(agent_type, (factory, check));
+    }
+
+    fn lookup(&self, spec: &AgentSpec) -> Result<&(AgentFactory, AgentCheck), String> {
+        self.factories.get(spec.agent_type.as_str()).ok_or_else(|| {
+            let mut known: Vec<_> = self.factories.keys().copied().collect();
+            known.sort();
+            format!("unknown agent type '{}' (known: {})", spec.agent_type, known.join(", "))
+        })
+    }
+
+    fn build(&self, spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+        let (factory, _) = self.lookup(spec)?;
+        factory(spec, ctx)
+    }
+
+    fn check(&self, spec: &AgentSpec) -> Result<(), String> {
+        let (_, check) = self.lookup(spec)?;
+        spec.verbosity()?;
+        spec.inbox_rate()?;
+        spec.groups()?;
+        spec.window()?;
+        check(spec)
+    }
+}
+
+fn build_oracle(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: OracleConfig = spec.parse()?;
+    if cfg.provider != "Pyth" && cfg.provider != "Synthetic" {
+        eprintln!("[Scenario] Unknown provider: {}, using Pyth", cfg.provider);
+    }
+    let live = || CachedPriceProvider::new(PythProvider::new(), cfg.cache_duration_ms / 1000);
+    let provider: Box<dyn PriceProvider> = match (&cfg.record, &cfg.playback) {
+        _ if cfg.synthetic.is_some() => Box::new(synthetic_provider(&cfg, ctx)),
+        (_, Some(path)) => {
+            let playback = PlaybackProvider::open(Path::new(path)).map_err(|e| format!("playback: {}", e))?;
+            println!(
+                "[Scenario] Oracle {} replays {} fetches from {}",
+                cfg.name,
+                playback.len(),
+                path
+            );
+            Box::new(playback)
+        }
+        (Some(path), None) => {
+            let recording =
+                RecordingProvider::new(live(), Path::new(path)).map_err(|e| format!("record {}: {}", path, e))?;
+            println!("[Scenario] Oracle {} records prices to {}", cfg.name, path);
+            Box::new(recording)
+        }
+        (None, None) => Box::new(live()),
+    };
+    let start_prices: HashMap<String, u64> = cfg
+        .symbols
+        .iter()
+        .filter_map(|s| Some((s.clone(), MicroUsd::from_usd(*ctx.start_prices.get(s)?).0)))
+        .collect();
+    let mut agent = OracleAgent::new(
+        cfg.id,
+        cfg.name,
+        cfg.symbols,
+        ctx.exchange_id,
+        cfg.wake_interval_ms * 1_000_000,
+        provider,
+    );
+    if let Some(spread) = cfg.spread {
+        agent.set_spread_model(spread);
+    }
+    if let Some(subscribers) = &cfg.subscribers {
+        agent.set_subscribers(subscribers);
+    }
+    if !start_prices.is_empty() {
+        agent.set_start_prices(start_prices);
+    }
+    if let Some(deviation_bps) = cfg.deviation_bps {
+        agent.set_deviation_trigger(DeviationTrigger {
+            deviation_bps,
+            max_age_ns: cfg.max_age_ms * 1_000_000,
+        });
+    }
+    if !cfg.fallback_providers.is_empty() {
+        let fallbacks = cfg.fallback_providers.iter().map(|p| reference_provider(p)).collect();
+        agent.set_fallback_providers(
+            fallbacks,
+            FailoverConfig {
+                failures_to_switch: cfg.failover_after,
+                recovery_interval_blocks: cfg.recovery_blocks,
+            },
+        );
+    }
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: None,
+        kind: "oracle".to_string(),
+    }))
+}
+
+/// Random-walk prices starting now, one step per oracle block.
+fn synthetic_provider(cfg: &OracleConfig, ctx: &BuildContext) -> SyntheticProvider {
+    let synthetic = cfg.synthetic.as_ref().expect("checked by the caller");
+    let start_ns = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+    println!(
+        "[Scenario] Oracle {} uses synthetic prices ({}% daily vol{})",
+        cfg.name,
+        synthetic.daily_vol_pct,
+        if ctx.schedule.is_empty() { "" } else { ", scheduled" }
+    );
+    SyntheticProvider::new(SyntheticConfig {
+        start_prices: synthetic
+            .start_prices
+            .iter()
+            .map(|(symbol, usd)| (symbol.clone(), MicroUsd::from_usd(*usd).0))
+            .collect(),
+        daily_vol_pct: synthetic.daily_vol_pct,
+        seed: synthetic.seed.unwrap_or(ctx.seed) ^ cfg.id as u64,
+        start_ns,
+        step_ns: cfg.wake_interval_ms * 1_000_000,
+        schedule: ctx.schedule.clone(),
+    })
+}
+
+fn build_market_maker(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: MarketMakerJsonConfig = spec.parse()?;
+    let mm_config = MarketMakerConfig {
         name: cfg.name.clone(),
-        exchange_id,
+        exchange_id: ctx.exchange_id,
         symbol: cfg.symbol.clone(),
-        address: None,
-        strategy,
-        qty: cfg.qty,
+        address: Some(ctx.address(cfg.id, &spec.agent_type)?),
+        target_oi_per_side: cfg.target_oi_per_side,
+        max_imbalance_pct: cfg.max_imbalance_pct,
+        order_size_tokens: cfg.order_size_tokens,
+        leverage: cfg.leverage,
         wake_interval_ms: cfg.wake_interval_ms,
         balance: cfg.balance,
     };
+    Ok(Some(AgentBuild {
+        agent: Box::new(MarketMakerAgent::new(cfg.id, mm_config)),
+        balance: Some(cfg.balance),
+        kind: "market_maker".to_string(),
+    }))
+}
 
-    LimitTraderAgent::new(cfg.id, config)
+fn build_quoting_mm(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: QuotingMmJsonConfig = spec.parse()?;
+    let qmm_config = QuotingMmConfig {
+        name: cfg.name.clone(),
+        exchange_id: ctx.exchange_id,
+        symbol: cfg.symbol.clone(),
+        qty: cfg.qty,
+        leverage: cfg.leverage,
+        spread_bps: cfg.spread_bps,
+        requote_bps: cfg.requote_bps,
+        inventory_skew_bps: cfg.inventory_skew_bps,
+        max_inventory_usd: cfg.max_inventory_usd,
+        quote_ttl_sec: cfg.quote_ttl_sec,
+    };
+    let mut agent = QuotingMarketMakerAgent::new(cfg.id, qmm_config);
+    agent.set_address(ctx.address(cfg.id, &spec.agent_type)?);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(cfg.balance.unwrap_or(ctx.default_deposit)),
+        kind: "quoting_mm".to_string(),
+    }))
+}
+
+/// Balance of every leg: its own, else an even share of the trader's, else of the default deposit.
+fn leg_balances(legs: &[PortfolioLegJsonConfig], balance: Option<i128>, default_deposit: i128) -> Vec<i128> {
+    let share = balance.unwrap_or(default_deposit) / legs.len().max(1) as i128;
+    legs.iter().map(|leg| leg.balance.unwrap_or(share)).collect()
+}
+
+/// Multi-symbol trader: one SmartTrader leg per `symbols` entry under a PortfolioAgent.
+fn build_smart_portfolio(cfg: &SmartTraderJsonConfig, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let address = ctx.address(cfg.id, "smart_trader")?;
+    let balances = leg_balances(&cfg.symbols, cfg.balance, ctx.default_deposit);
+    let mut legs: Vec<(String, Box<dyn Agent>)> = Vec::new();
+    for (i, (leg, balance)) in cfg.symbols.iter().zip(&balances).enumerate() {
+        let mut leg_cfg = cfg.clone();
+        leg_cfg.name = format!("{}/{}", cfg.name, leg.symbol);
+        leg_cfg.symbol = leg.symbol.clone();
+        leg_cfg.symbols = Vec::new();
+        if let Some(qty) = leg.qty {
+            leg_cfg.qty_min = Some(qty);
+            leg_cfg.qty_max = Some(qty);
+        }
+        leg_cfg.leverage = leg.leverage.unwrap_or(cfg.leverage);
+        leg_cfg.balance = Some(*balance);
+        let mut agent = create_smart_trader(&leg_cfg, ctx.exchange_id);
+        agent.set_address(address.clone());
+        agent.set_seed(ctx.seed.wrapping_add(i as u64));
+        legs.push((leg.symbol.clone(), Box::new(agent)));
+    }
+    let limits = ExposureLimits {
+        max_gross_usd: cfg.max_gross_usd,
+        max_net_usd: cfg.max_net_usd,
+    };
+    let agent = PortfolioAgent::new(cfg.id, cfg.name.clone(), ctx.exchange_id, legs, limits);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(balances.iter().sum()),
+        kind: format!("portfolio_{}", cfg.strategy.to_lowercase()),
+    }))
+}
+
+fn build_limit_portfolio(cfg: &LimitTraderJsonConfig, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let address = ctx.address(cfg.id, "limit_trader")?;
+    let balances = leg_balances(&cfg.symbols, cfg.balance, ctx.default_deposit);
+    let mut legs: Vec<(String, Box<dyn Agent>)> = Vec::new();
+    for (leg, balance) in cfg.symbols.iter().zip(&balances) {
+        let mut leg_cfg = cfg.clone();
+        leg_cfg.name = format!("{}/{}", cfg.name, leg.symbol);
+        leg_cfg.symbol = leg.symbol.clone();
+        leg_cfg.symbols = Vec::new();
+        leg_cfg.qty = leg.qty.unwrap_or(cfg.qty);
+        leg_cfg.leverage = leg.leverage.unwrap_or(cfg.leverage);
+        leg_cfg.balance = Some(*balance);
+        let mut agent = create_limit_trader(&leg_cfg, ctx.exchange_id);
+        agent.set_address(address.clone());
+        legs.push((leg.symbol.clone(), Box::new(agent)));
+    }
+    let limits = ExposureLimits {
+        max_gross_usd: cfg.max_gross_usd,
+        max_net_usd: cfg.max_net_usd,
+    };
+    let agent = PortfolioAgent::new(cfg.id, cfg.name.clone(), ctx.exchange_id, legs, limits);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(balances.iter().sum()),
+        kind: format!("portfolio_limit_{}", cfg.strategy.to_lowercase()),
+    }))
+}
+
+fn build_smart_trader(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: SmartTraderJsonConfig = spec.parse()?;
+    if !cfg.symbols.is_empty() {
+        return build_smart_portfolio(&cfg, ctx);
+    }
+    let mut agent = create_smart_trader(&cfg, ctx.exchange_id);
+    agent.set_address(ctx.address(cfg.id, &spec.agent_type)?);
+    agent.set_seed(ctx.seed);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(cfg.balance.unwrap_or(ctx.default_deposit)),
+        kind: cfg.strategy.to_lowercase(),
+    }))
+}
+
+fn build_limit_trader(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: LimitTraderJsonConfig = spec.parse()?;
+    if !cfg.symbols.is_empty() {
+        return build_limit_portfolio(&cfg, ctx);
+    }
+    let mut agent = create_limit_trader(&cfg, ctx.exchange_id);
+    agent.set_address(ctx.address(cfg.id, &spec.agent_type)?);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(cfg.balance.unwrap_or(ctx.default_deposit)),
+        kind: format!("limit_{}", cfg.strategy.to_lowercase()),
+    }))
+}
+
+const REFERENCE_PROVIDERS: [&str; 2] = ["binance", "pyth"];
+
+fn reference_provider(name: &str) -> Box<dyn PriceProvider> {
+    match name.to_lowercase().as_str() {
+        "pyth" => Box::new(PythProvider::new()),
+        _ => Box::new(BinanceProvider::new()),
+    }
+}
+
+fn build_arbitrage(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: ArbitrageJsonConfig = spec.parse()?;
+    let arb_config = ArbitrageConfig {
+        name: cfg.name.clone(),
+        exchange_id: ctx.exchange_id,
+        symbol: cfg.symbol.clone(),
+        qty: cfg.qty,
+        leverage: cfg.leverage,
+        entry_basis_bps: cfg.entry_basis_bps,
+        exit_basis_bps: cfg.exit_basis_bps,
+        impact_factor: cfg.impact_factor,
+        max_hold_sec: cfg.max_hold_sec,
+        wake_interval_ms: cfg.wake_interval_ms,
+    };
+    let mut agent = ArbitrageAgent::new(cfg.id, arb_config, reference_provider(&cfg.reference));
+    agent.set_address(ctx.address(cfg.id, &spec.agent_type)?);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(cfg.balance.unwrap_or(ctx.default_deposit)),
+        kind: "arbitrage".to_string(),
+    }))
+}
+
+fn build_noise_trader(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: NoiseTraderJsonConfig = spec.parse()?;
+    let leverage_weights = if cfg.leverage_weights.is_empty() {
+        vec![1.0; cfg.leverage_choices.len()]
+    } else {
+        cfg.leverage_weights
+    };
+    let noise_config = NoiseTraderConfig {
+        name: cfg.name.clone(),
+        exchange_id: ctx.exchange_id,
+        symbol: cfg.symbol.clone(),
+        orders_per_min: cfg.orders_per_min,
+        buy_prob: cfg.buy_prob,
+        close_prob: cfg.close_prob,
+        size_mu: cfg.size_mu,
+        size_sigma: cfg.size_sigma,
+        qty_max: cfg.qty_max,
+        leverage_choices: cfg.leverage_choices,
+        leverage_weights,
+        seed: cfg.seed.unwrap_or(ctx.seed),
+        schedule: ctx.schedule.clone(),
+    };
+    let mut agent = NoiseTraderAgent::new(cfg.id, noise_config);
+    agent.set_address(ctx.address(cfg.id, &spec.agent_type)?);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(cfg.balance.unwrap_or(ctx.default_deposit)),
+        kind: "noise_trader".to_string(),
+    }))
+}
+
+fn build_whale(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: WhaleJsonConfig = spec.parse()?;
+    let side = match cfg.side.to_lowercase().as_str() {
+        "short" | "sell" => Side::Sell,
+        _ => Side::Buy,
+    };
+    let whale_config = WhaleConfig {
+        name: cfg.name.clone(),
+        exchange_id: ctx.exchange_id,
+        symbol: cfg.symbol.clone(),
+        side,
+        leverage: cfg.leverage,
+        total_qty: cfg.total_qty,
+        accumulate_start_sec: cfg.accumulate_start_sec,
+        accumulate_sec: cfg.accumulate_sec,
+        accumulate_chunks: cfg.accumulate_chunks,
+        dump_at_sec: cfg.dump_at_sec,
+        dump_qty: cfg.dump_qty,
+        dump_sec: cfg.dump_sec,
+        dump_chunks: cfg.dump_chunks,
+        shock_pct: cfg.shock_pct,
+        shock_at_sec: cfg.shock_at_sec,
+        shock_duration_sec: cfg.shock_duration_sec,
+        oracle_ids: ctx.oracle_ids.clone(),
+    };
+    let mut agent = WhaleAgent::new(cfg.id, whale_config);
+    agent.set_address(ctx.address(cfg.id, &spec.agent_type)?);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(cfg.balance.unwrap_or(ctx.default_deposit)),
+        kind: "whale".to_string(),
+    }))
+}
+
+fn build_copy_trader(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: CopyTraderJsonConfig = spec.parse()?;
+    let copy_config = CopyTraderConfig {
+        name: cfg.name.clone(),
+        exchange_id: ctx.exchange_id,
+        leader_id: cfg.leader_id,
+        ratio: cfg.ratio,
+        delay_ms: cfg.delay_ms,
+        max_leverage: cfg.max_leverage,
+    };
+    let mut agent = CopyTraderAgent::new(cfg.id, copy_config);
+    agent.set_address(ctx.address(cfg.id, &spec.agent_type)?);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(cfg.balance.unwrap_or(ctx.default_deposit)),
+        kind: "copy_trader".to_string(),
+    }))
+}
+
+/// On-chain LPs get their maximum deposit as collateral to fund the pool from.
+fn build_lp(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: LpJsonConfig = spec.parse()?;
+    let max_deposit_usd = cfg.max_deposit_usd.unwrap_or(cfg.initial_usd);
+    let lp_config = LpConfig {
+        name: cfg.name.clone(),
+        exchange_id: ctx.exchange_id,
+        symbol: cfg.symbol.clone(),
+        initial_usd: cfg.initial_usd,
+        step_usd: cfg.step_usd,
+        max_deposit_usd,
+        add_above_util: cfg.add_above_util,
+        remove_below_util: cfg.remove_below_util,
+        panic_loss_pct: cfg.panic_loss_pct,
+        cooldown_ms: cfg.cooldown_ms,
+    };
+    let mut agent = LpAgent::new(cfg.id, lp_config);
+    agent.set_address(ctx.address(cfg.id, &spec.agent_type)?);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(max_deposit_usd),
+        kind: "lp".to_string(),
+    }))
+}
+
+fn build_funding_arb(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: FundingArbJsonConfig = spec.parse()?;
+    let farb_config = FundingArbConfig {
+        name: cfg.name.clone(),
+        exchange_id: ctx.exchange_id,
+        symbol: cfg.symbol.clone(),
+        hedge_symbol: cfg.hedge_symbol.clone(),
+        hedge_venue: cfg.hedge_venue,
+        hedge_ratio: cfg.hedge_ratio,
+        qty: cfg.qty,
+        leverage: cfg.leverage,
+        daily_rate_bps: cfg.daily_rate_bps,
+        entry_rate_bps_hour: cfg.entry_rate_bps_hour,
+        exit_rate_bps_hour: cfg.exit_rate_bps_hour,
+        max_hold_sec: cfg.max_hold_sec,
+    };
+    let mut agent = FundingArbAgent::new(cfg.id, farb_config);
+    agent.set_address(ctx.address(cfg.id, &spec.agent_type)?);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(cfg.balance.unwrap_or(ctx.default_deposit)),
+        kind: "funding_arb".to_string(),
+    }))
+}
+
+fn build_swap_exchange(spec: &AgentSpec, _ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: SwapExchangeJsonConfig = spec.parse()?;
+    let swap_config = SwapExchangeConfig {
+        name: cfg.name,
+        pools: cfg
+            .symbols
+            .into_iter()
+            .map(|pool| SwapPoolConfig {
+                symbol: pool.symbol,
+                liquidity_usd: pool.liquidity_usd,
+            })
+            .collect(),
+        fee_bps: cfg.fee_bps,
+        arb_pull: cfg.arb_pull,
+    };
+    Ok(Some(AgentBuild {
+        agent: Box::new(SwapExchangeAgent::new(cfg.id, swap_config)),
+        balance: None,
+        kind: "swap_exchange".to_string(),
+    }))
+}
+
+fn build_hedger(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: HedgerJsonConfig = spec.parse()?;
+    let hedger_config = HedgerConfig {
+        name: cfg.name.clone(),
+        exchange_id: ctx.exchange_id,
+        spot_symbol: cfg.spot_symbol.clone(),
+        spot_qty: cfg.spot_qty,
+        hedges: cfg
+            .hedges
+            .iter()
+            .map(|leg| HedgeLeg {
+                symbol: leg.symbol.clone(),
+                weight: leg.weight,
+                beta: leg.beta,
+            })
+            .collect(),
+        target_delta_usd: cfg.target_delta_usd,
+        band_pct: cfg.band_pct,
+        leverage: cfg.leverage,
+        min_interval_sec: cfg.min_interval_sec,
+    };
+    let mut agent = HedgerAgent::new(cfg.id, hedger_config);
+    agent.set_address(ctx.address(cfg.id, &spec.agent_type)?);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(cfg.balance.unwrap_or(ctx.default_deposit)),
+        kind: "hedger".to_string(),
+    }))
+}
+
+fn build_sma_cross(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: SmaCrossJsonConfig = spec.parse()?;
+    let strategy = SmaCrossStrategy::new(SmaCrossConfig {
+        symbol: cfg.symbol.clone(),
+        fast: cfg.fast,
+        slow: cfg.slow,
+        qty: cfg.qty,
+        leverage: cfg.leverage,
+        trend_filter: cfg.trend_filter,
+    });
+    let mut agent = StrategyAgent::new(cfg.id, cfg.name.clone(), ctx.exchange_id, strategy);
+    agent.set_address(ctx.address(cfg.id, &spec.agent_type)?);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(cfg.balance.unwrap_or(ctx.default_deposit)),
+        kind: "sma_cross".to_string(),
+    }))
+}
+
+fn build_scripted(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: ScriptedJsonConfig = spec.parse()?;
+    let strategy = ScriptedStrategy::new(cfg.strategy_config()?)?;
+    let mut agent = ScriptedAgent::new(cfg.id, cfg.name.clone(), ctx.exchange_id, strategy);
+    agent.set_address(ctx.address(cfg.id, &spec.agent_type)?);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(cfg.balance.unwrap_or(ctx.default_deposit)),
+        kind: "scripted".to_string(),
+    }))
+}
+
+fn build_bridge(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: BridgeJsonConfig = spec.parse()?;
+    let strategy = BridgeStrategy::new(BridgeConfig {
+        name: cfg.name.clone(),
+        address: cfg.address.clone(),
+        connect_timeout_ms: cfg.connect_timeout_ms,
+        timeout_ms: cfg.timeout_ms,
+        on_timeout: cfg.on_timeout,
+        max_timeouts: cfg.max_timeouts,
+        timer_interval_ms: cfg.timer_interval_ms.filter(|ms| *ms > 0),
+    });
+    let mut agent = BridgeAgent::new(cfg.id, cfg.name.clone(), ctx.exchange_id, strategy);
+    agent.set_address(ctx.address(cfg.id, &spec.agent_type)?);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(cfg.balance.unwrap_or(ctx.default_deposit)),
+        kind: "bridge".to_string(),
+    }))
+}
+
+/// Remote agents wait for a WS client, so they only exist in realtime mode.
+fn build_remote(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: RemoteJsonConfig = spec.parse()?;
+    let Some(channels) = &ctx.human else {
+        println!("[Scenario] Skipping RemoteAgent {} (realtime mode only)", cfg.name);
+        return Ok(None);
+    };
+    let strategy = RemoteStrategy::new(
+        RemoteConfig {
+            name: cfg.name.clone(),
+            timeout_ms: cfg.timeout_ms,
+            timer_interval_ms: cfg.timer_interval_ms.filter(|ms| *ms > 0),
+        },
+        channels.remotes.register(&cfg.name),
+    );
+    let mut agent = RemoteAgent::new(cfg.id, cfg.name.clone(), ctx.exchange_id, strategy);
+    agent.set_address(ctx.address(cfg.id, &spec.agent_type)?);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: Some(cfg.balance.unwrap_or(ctx.default_deposit)),
+        kind: "remote".to_string(),
+    }))
+}
+
+/// The risk monitor only observes: no account, no deposit.
+fn build_risk_monitor(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: RiskMonitorJsonConfig = spec.parse()?;
+    let risk_config = RiskMonitorConfig {
+        name: cfg.name,
+        exchange_id: ctx.exchange_id,
+        var_horizon_sec: cfg.var_horizon_sec,
+        var_z: cfg.var_z,
+        vol_window: cfg.vol_window,
+        maintenance_margin: cfg.maintenance_margin,
+        bucket_bps: cfg.bucket_bps,
+        heatmap_range_pct: cfg.heatmap_range_pct,
+        warn_distance_pct: cfg.warn_distance_pct,
+        var_alert_pct: cfg.var_alert_pct,
+        concentration_alert_pct: cfg.concentration_alert_pct,
+        cluster_alert_pct: cfg.cluster_alert_pct,
+    };
+    Ok(Some(AgentBuild {
+        agent: Box::new(RiskMonitorAgent::new(cfg.id, risk_config)),
+        balance: None,
+        kind: "risk_monitor".to_string(),
+    }))
+}
+
+/// Keepers have no blockchain access - they send messages to Exchange, which signs for them.
+fn build_keeper(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: KeeperJsonConfig = spec.parse()?;
+    let keeper_config = KeeperConfig {
+        name: cfg.name,
+        exchange_id: ctx.exchange_id,
+        address: None,
+        wake_interval_ms: cfg.wake_interval_ms,
+        gas_cost_usd: cfg.gas_cost_usd,
+        min_profit_ratio: cfg.min_profit_ratio,
+        max_price_age_secs: cfg.max_price_age_secs,
+        max_batch: cfg.max_batch,
+        batched_order_gas_usd: cfg.batched_order_gas_usd.unwrap_or(cfg.gas_cost_usd / 2),
+    };
+    let mut keeper = KeeperAgent::new(cfg.id, keeper_config);
+    if cfg.jit_price {
+        // Uncached: the point is the newest VAA at execution time
+        keeper.set_price_source(Box::new(PythProvider::new()));
+    }
+    Ok(Some(AgentBuild {
+        agent: Box::new(keeper),
+        balance: None,
+        kind: "keeper".to_string(),
+    }))
+}
+
+/// Liquidation agent has no blockchain access - it sends LiquidationScan to Exchange.
+fn build_liquidation(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: LiquidationAgentConfig = spec.parse()?;
+    let agent = LiquidationAgent::new(cfg.id, cfg.name, ctx.exchange_id, cfg.wake_interval_ms * 1_000_000);
+    Ok(Some(AgentBuild {
+        agent: Box::new(agent),
+        balance: None,
+        kind: "liquidation".to_string(),
+    }))
+}
+
+/// HumanAgent is driven by the HTTP/WS API and only exists in realtime mode.
+fn build_human(spec: &AgentSpec, ctx: &BuildContext) -> Result<Option<AgentBuild>, String> {
+    let cfg: HumanJsonConfig = spec.parse()?;
+    let Some(channels) = &ctx.human else {
+        println!("[Scenario] Skipping HumanAgent {} (realtime mode only)", cfg.name);
+        return Ok(None);
+    };
+    let address = cfg
+        .address
+        .or_else(|| std::env::var("VARA_HUMAN_ADDRESS").ok())
+        .or_else(|| ctx.address_book.address_for_agent(cfg.id));
+    let mut human = HumanAgent::new(
+        cfg.id,
+        cfg.name,
+        ctx.exchange_id,
+        address,
+        channels.cmd_rx.clone(),
+        channels.response_tx.clone(),
+        cfg.wake_interval_ms.unwrap_or(channels.tick_ms),
+    );
+    human.set_pnl_book(ctx.pnl_book.clone());
+    human.set_oracle_ids(ctx.oracle_ids.clone());
+    if let Some(sessions) = &cfg.sessions {
+        // Session agents join later; their orders need a key or a local account now
+        for id in sessions.ids() {
+            ctx.address(id, "human session")?;
+        }
+        human.set_sessions(channels.sessions.clone());
+    }
+    Ok(Some(AgentBuild {
+        agent: Box::new(human),
+        balance: None,
+        kind: "human".to_string(),
+    }))
 }
 
-const DEFAULT_DEPOSIT_MICRO_USD: i128 = 1_000_000_000_000; // $1M
+/// Build the given agent specs through the registry. Exits on configuration errors.
+fn build_agents(specs: &[AgentSpec], ctx: &BuildContext) -> Vec<AgentBuild> {
+    let registry = AgentRegistry::builtin();
+    let mut roster = Vec::with_capacity(specs.len());
+    for (i, spec) in specs.iter().enumerate() {
+        match registry.build(spec, ctx) {
+            Ok(Some(built)) => {
+                println!(
+                    "[Scenario] Added {} ({}): {}",
+                    spec.agent_type,
+                    built.kind,
+                    built.agent.name()
+                );
+                roster.push(built);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("[Scenario] agents[{}] ({}): {}", i, spec.agent_type, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    roster
+}
 
-fn balance_to_collateral_tokens(balance_micro_usd: i128, collateral_decimals: u32) -> U256 {
+/// Balance in micro-USD to collateral atoms at `collateral_price` (micro-USD per token).
+fn balance_to_collateral_tokens(balance_micro_usd: i128, collateral_decimals: u32, collateral_price: u64) -> U256 {
     if balance_micro_usd <= 0 {
         return U256::zero();
     }
-    let mut amount = U256::from(balance_micro_usd as u128);
-    if collateral_decimals > 6 {
-        let exp = (collateral_decimals - 6) as usize;
-        amount *= U256::exp10(exp);
-    } else if collateral_decimals < 6 {
-        let exp = (6 - collateral_decimals) as usize;
-        let divisor = U256::exp10(exp);
-        if !divisor.is_zero() {
-            amount /= divisor;
+    TokenScale::new(collateral_decimals)
+        .amount_for(MicroUsd(balance_micro_usd as u64), MicroUsd(collateral_price))
+        .atoms
+}
+
+/// Deposit initial balances unless disabled (--skip-deposits or `vara.skip_deposits`).
+/// Local agents have no on-chain account and are skipped.
+fn bootstrap_deposits(config: &SimConfig, roster: &mut [AgentBuild], vara_client: &VaraClient, skip_deposits: bool) {
+    for built in roster.iter_mut() {
+        if let Some(amount) = config.vara.deposits.get(&built.agent.id()) {
+            built.balance = Some(*amount);
         }
     }
-    amount
+    if skip_deposits || config.vara.skip_deposits {
+        println!("[Scenario] Skipping initial deposits (flag enabled)");
+    } else {
+        deposit_initial_balances(config, roster, vara_client);
+    }
 }
 
-fn deposit_initial_balances(config: &SimConfig, vara_client: &VaraClient) {
+fn deposit_initial_balances(config: &SimConfig, roster: &[AgentBuild], vara_client: &VaraClient) {
     let collateral_decimals = config
         .exchange
         .markets
         .first()
         .map(|m| m.collateral_decimals)
         .unwrap_or(6);
+    // Non-stable collateral is deposited at its configured starting price
+    let collateral_price = config
+        .exchange
+        .markets
+        .first()
+        .and_then(|m| m.collateral_price.as_ref())
+        .map_or(PEGGED_COLLATERAL_MICRO, |cp| (cp.initial_usd * 1_000_000.0) as u64);
 
-    let mut deposits: Vec<(u32, i128)> = Vec::new();
-
-    if let Some(mm_cfg) = &config.market_maker {
-        deposits.push((mm_cfg.id, mm_cfg.balance));
-    }
-
-    for smart_cfg in &config.smart_traders {
-        let balance = smart_cfg.balance.unwrap_or(DEFAULT_DEPOSIT_MICRO_USD);
-        deposits.push((smart_cfg.id, balance));
-    }
-
-    for limit_cfg in &config.limit_traders {
-        let balance = limit_cfg.balance.unwrap_or(DEFAULT_DEPOSIT_MICRO_USD);
-        deposits.push((limit_cfg.id, balance));
-    }
+    let deposits: Vec<(u32, i128)> = roster
+        .iter()
+        .filter(|b| !config.vara.local_agents.contains(&b.agent.id()))
+        .filter_map(|b| b.balance.map(|balance| (b.agent.id(), balance)))
+        .collect();
 
     if deposits.is_empty() {
         println!("[Scenario] No deposits to make");
@@ -647,7 +3597,7 @@ fn deposit_initial_balances(config: &SimConfig, vara_client: &VaraClient) {
     let batch: Vec<(u32, primitive_types::U256)> = deposits
         .iter()
         .map(|(agent_id, balance_micro)| {
-            (*agent_id, balance_to_collateral_tokens(*balance_micro, collateral_decimals))
+            (*agent_id, balance_to_collateral_tokens(*balance_micro, collateral_decimals, collateral_price))
         })
         .filter(|(_, amount)| !amount.is_zero())
         .collect();
@@ -667,14 +3617,224 @@ fn deposit_initial_balances(config: &SimConfig, vara_client: &VaraClient) {
     }
 }
 
-/// Register all CSV event loggers on the engine.
-fn register_csv_loggers(engine: &mut SimEngine, logs_dir: &str) {
+/// Ids of the oracle agents in the roster.
+/// WebSocket trading sessions of the scenario's HumanAgent (none without `sessions`).
+fn session_hub(specs: &[AgentSpec]) -> SessionHub {
+    let sessions = specs
+        .iter()
+        .filter(|s| s.agent_type == "human")
+        .find_map(|s| s.parse::<HumanJsonConfig>().ok()?.sessions);
+    match sessions {
+        Some(sessions) => SessionHub::new(sessions.ids(), sessions.tokens),
+        None => SessionHub::default(),
+    }
+}
+
+fn oracle_ids(specs: &[AgentSpec]) -> Vec<u32> {
+    specs
+        .iter()
+        .filter(|s| s.agent_type == "oracle")
+        .filter_map(|s| s.id())
+        .collect()
+}
+
+/// Agents joining via the timeline are built with the roster, so their addresses,
+/// deposits and report entries are set up front.
+fn timeline_specs(config: &SimConfig) -> Vec<AgentSpec> {
+    config.timeline.iter().filter_map(|e| e.joining_spec()).cloned().collect()
+}
+
+/// Add the roster to the kernel. Timeline joiners are handed to the DirectorAgent
+/// instead, which is added last when the scenario has a timeline.
+fn start_agents(engine: &mut SimEngine, config: &SimConfig, roster: Vec<AgentBuild>) {
+    let joining_ids: HashSet<u32> = timeline_specs(config).iter().filter_map(|s| s.id()).collect();
+    let mut oracle_ids = Vec::new();
+    let mut joining: HashMap<u32, Box<dyn Agent>> = HashMap::new();
+    for built in roster {
+        let id = built.agent.id();
+        if joining_ids.contains(&id) {
+            joining.insert(id, built.agent);
+            continue;
+        }
+        if built.kind == "oracle" {
+            oracle_ids.push(id);
+        }
+        engine.kernel.add_agent(built.agent);
+    }
+
+    if config.timeline.is_empty() {
+        return;
+    }
+    let events: Vec<TimelineEvent> = config
+        .timeline
+        .iter()
+        .filter_map(|entry| {
+            let (at_sec, action) = match entry.clone() {
+                TimelineJsonEntry::PriceShock {
+                    at_sec,
+                    symbol,
+                    pct,
+                    duration_sec,
+                } => (
+                    at_sec,
+                    TimelineAction::PriceShock {
+                        symbol,
+                        pct,
+                        duration_sec,
+                    },
+                ),
+                TimelineJsonEntry::SpawnAgent { at_sec, agent } => {
+                    let agent = agent.id().and_then(|id| joining.remove(&id))?;
+                    (at_sec, TimelineAction::SpawnAgent(agent))
+                }
+                TimelineJsonEntry::RemoveAgent { at_sec, id } => (at_sec, TimelineAction::RemoveAgent(id)),
+                TimelineJsonEntry::SetParam { at_sec, id, key, value } => (
+                    at_sec,
+                    TimelineAction::SetParam {
+                        agent_id: id,
+                        key,
+                        value,
+                    },
+                ),
+            };
+            Some(TimelineEvent { at_sec, action })
+        })
+        .collect();
+    println!("[Scenario] Timeline: {} event(s)", events.len());
+    let director = DirectorAgent::new(DIRECTOR_ID, "Director".to_string(), oracle_ids, events);
+    engine.kernel.add_agent(Box::new(director));
+}
+
+/// Message type named `name` in `broadcast_groups` (e.g. "MarketState").
+fn broadcast_kind(name: &str) -> Result<MessageType, String> {
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .map_err(|_| format!("unknown message type '{}'", name))
+}
+
+/// Kernel output level, the agents' own `verbosity`, `inbox_rate`, `groups` and join / leave
+/// times, the grouped broadcasts, the warm-up and the realtime watchdog (call before adding agents).
+fn apply_kernel_settings<'a>(
+    engine: &mut SimEngine,
+    config: &SimConfig,
+    specs: impl IntoIterator<Item = &'a AgentSpec>,
+) {
+    engine.kernel.set_verbosity(config.logging.verbosity);
+    engine.kernel.set_dead_letter_policy(config.dead_letters);
+    if let Some(watchdog) = config.watchdog {
+        engine.kernel.set_watchdog(watchdog);
+    }
+    if let Some(verbosity) = config.exchange.common.verbosity {
+        engine.kernel.set_agent_verbosity(config.exchange.id, verbosity);
+    }
+    engine
+        .kernel
+        .set_agent_groups(config.exchange.id, &config.exchange.groups);
+    for (kind, group) in &config.broadcast_groups {
+        if let Ok(kind) = broadcast_kind(kind) {
+            engine.kernel.set_broadcast_group(kind, group);
+        }
+    }
+    let mut exempt = vec![config.exchange.id, DIRECTOR_ID];
+    for spec in specs {
+        let Some(id) = spec.id() else {
+            continue;
+        };
+        if spec.agent_type == "oracle" {
+            exempt.push(id);
+        }
+        if let Ok(Some(verbosity)) = spec.verbosity() {
+            engine.kernel.set_agent_verbosity(id, verbosity);
+        }
+        if let Ok(Some(rate)) = spec.inbox_rate() {
+            engine.kernel.set_inbox_rate(id, rate);
+        }
+        if let Ok(groups) = spec.groups() {
+            engine.kernel.set_agent_groups(id, &groups);
+        }
+        if let Ok(Some((join_ns, leave_ns))) = spec.window() {
+            engine.kernel.set_agent_window(id, join_ns, leave_ns);
+        }
+    }
+    engine
+        .kernel
+        .set_warmup(config.warmup.duration_sec * 1_000_000_000, &exempt);
+}
+
+/// Register all CSV event loggers on the engine, with the flow tracker feeding markets.csv.
+fn register_csv_loggers(engine: &mut SimEngine, config: &SimConfig) -> FlowStatsBook {
+    let logs_dir = &config.logs_dir;
+    let rotation = config.logging.rotation();
     let _ = fs::create_dir_all(logs_dir);
-    if let Ok(l) = CsvOracleLogger::new(logs_dir) { engine.kernel.event_bus_mut().subscribe(Box::new(l)); }
-    if let Ok(l) = CsvExecutionLogger::new(logs_dir) { engine.kernel.event_bus_mut().subscribe(Box::new(l)); }
-    if let Ok(l) = CsvPositionLogger::new(logs_dir) { engine.kernel.event_bus_mut().subscribe(Box::new(l)); }
-    if let Ok(l) = CsvMarketLogger::new(logs_dir) { engine.kernel.event_bus_mut().subscribe(Box::new(l)); }
-    if let Ok(l) = CsvLiquidationLogger::new(logs_dir) { engine.kernel.event_bus_mut().subscribe(Box::new(l)); }
+    let flow_tracker = FlowStatsTracker::new(config.market_stats);
+    let flow_stats = flow_tracker.book();
+    let bus = engine.kernel.event_bus_mut();
+    bus.subscribe(Box::new(flow_tracker));
+    if let Ok(l) = CsvOrderLogger::with_rotation(logs_dir, rotation) { bus.subscribe(Box::new(l)); }
+    if config.logging.oracle_log {
+        if let Ok(l) = CsvOracleLogger::with_rotation(logs_dir, rotation) { bus.subscribe(Box::new(l)); }
+    } else {
+        println!("[Scenario] oracle.csv disabled by config");
+    }
+    if let Ok(l) = CsvExecutionLogger::with_rotation(logs_dir, rotation) { bus.subscribe(Box::new(l)); }
+    if let Ok(l) = CsvPositionLogger::with_rotation(logs_dir, rotation) { bus.subscribe(Box::new(l)); }
+    if let Ok(l) = CsvMarketLogger::with_rotation(logs_dir, rotation) {
+        bus.subscribe(Box::new(l.with_flow_stats(flow_stats.clone())));
+    }
+    if let Ok(l) = CsvLiquidationLogger::with_rotation(logs_dir, rotation) { bus.subscribe(Box::new(l)); }
+    if let Ok(l) = CsvChainTxLogger::with_rotation(logs_dir, rotation) { bus.subscribe(Box::new(l)); }
+    if config.agent_specs().iter().any(|spec| spec.agent_type == "swap_exchange") {
+        if let Ok(l) = CsvSwapLogger::with_rotation(logs_dir, rotation) { bus.subscribe(Box::new(l)); }
+    }
+    if config.agent_specs().iter().any(|spec| spec.params.contains_key("inbox_rate")) {
+        if let Ok(l) = CsvInboxLogger::with_rotation(logs_dir, rotation) { bus.subscribe(Box::new(l)); }
+    }
+    if config.risk_limits.is_some() || config.agent_specs().iter().any(|spec| spec.agent_type == "risk_monitor") {
+        if let Ok(l) = CsvRiskLogger::with_rotation(logs_dir, rotation) { bus.subscribe(Box::new(l)); }
+    }
+    if config.logging.trace {
+        engine.kernel.record_trace(Path::new(logs_dir));
+    }
+    if config.logging.metrics {
+        engine.kernel.record_metrics(Path::new(logs_dir));
+    }
+    flow_stats
+}
+
+/// Initial (deposited) balance per trader, micro-USD.
+fn initial_balances(roster: &[AgentBuild]) -> HashMap<u32, i64> {
+    roster
+        .iter()
+        .filter_map(|b| b.balance.map(|balance| (b.agent.id(), balance as i64)))
+        .collect()
+}
+
+/// Agent type label per account (used to aggregate reports per agent type).
+fn agent_kinds(roster: &[AgentBuild]) -> HashMap<u32, String> {
+    roster.iter().map(|b| (b.agent.id(), b.kind.clone())).collect()
+}
+
+/// Register PnL tracker, equity sampler, cascade detector, end-of-run report and webhooks (in this order).
+fn register_analytics(engine: &mut SimEngine, config: &SimConfig, pnl_tracker: PnlTracker, roster: &[AgentBuild]) {
+    let equity_tracker = EquityTracker::new(
+        &config.logs_dir,
+        config.equity_sample_sec,
+        initial_balances(roster),
+        pnl_tracker.book(),
+    );
+    let cascade_tracker = CascadeTracker::new(config.cascades);
+    let report = ReportBuilder::new(&config.logs_dir, &config.scenario_name)
+        .with_equity(equity_tracker.book())
+        .with_cascades(cascade_tracker.book())
+        .with_agent_kinds(agent_kinds(roster));
+
+    let bus = engine.kernel.event_bus_mut();
+    bus.subscribe(Box::new(pnl_tracker));
+    bus.subscribe(Box::new(equity_tracker));
+    bus.subscribe(Box::new(cascade_tracker));
+    bus.subscribe(Box::new(report));
+    for webhook in &config.webhooks {
+        bus.subscribe(Box::new(WebhookSink::new(webhook.clone(), &config.scenario_name)));
+    }
 }
 
 /// Convert JSON market configs to ExchangeAgent MarketConfig.
@@ -691,248 +3851,339 @@ fn convert_markets(json_markets: &[MarketJsonConfig]) -> Vec<MarketConfig> {
             liquidity_usd: m.initial_liquidity.liquidity_usd,
             index_decimals: m.index_decimals,
             collateral_decimals: m.collateral_decimals,
+            position_fee_bps: m.position_fee_bps,
+            fee_rates: m.fee_rates,
+            collateral_price: m.collateral_price.clone(),
+            price_rules: m.price_rules,
+            mark_price: m.mark_price,
+            circuit_breaker: m.circuit_breaker,
+            oi_caps: m.oi_caps,
         })
         .collect()
 }
 
-/// Register the orders.csv event listener on the engine.
-fn register_orders_csv(engine: &mut SimEngine, logs_dir: &str) {
-    let orders_path = format!("{}/orders.csv", logs_dir);
-    let file = match File::create(&orders_path) {
-        Ok(f) => f,
-        Err(e) => {
-            eprintln!("[Scenario] Failed to create {}: {}", orders_path, e);
-            return;
+/// ExchangeAgent wired to the client's tx result stream, with the scenario's local agents.
+fn build_exchange(
+    config: &SimConfig,
+    roster: &[AgentBuild],
+    vara_client: Arc<VaraClient>,
+    flow_stats: FlowStatsBook,
+) -> ExchangeAgent {
+    let tx_result_rx = vara_client.take_tx_result_receiver();
+    if tx_result_rx.is_none() {
+        eprintln!("[Scenario] tx result receiver already taken, on-chain fills won't be confirmed");
+    }
+    let mut exchange = ExchangeAgent::new(
+        config.exchange.id,
+        config.exchange.name.clone(),
+        convert_markets(&config.exchange.markets),
+        vara_client,
+        tx_result_rx,
+        config.exchange.snapshot_interval_ms,
+    );
+    exchange.set_local_agents(config.vara.local_agents.iter().copied().collect());
+    exchange.set_cross_margin(config.vara.cross_margin.iter().copied().collect());
+    exchange.set_trade_history(config.exchange.trade_history);
+    exchange.set_market_stats(flow_stats, config.market_stats.broadcast_ms);
+    if let Some(fallback) = config.vara.local_fallback {
+        exchange.set_local_fallback(fallback);
+    }
+    if let Some(limits) = &config.risk_limits {
+        exchange.set_risk_guard(RiskGuard::new(limits.clone()));
+    }
+    let deposits = initial_balances(roster)
+        .into_iter()
+        .map(|(id, balance)| (id, balance.max(0) as u64))
+        .collect();
+    exchange.set_capital(CapitalLedger::new(config.capital.clone(), deposits));
+    exchange.set_state_file(Path::new(&config.logs_dir).join(STATE_FILE));
+    if let Some(path) = &config.warm_start_from {
+        match ExchangeState::load(path) {
+            Ok(state) => exchange.restore_state(state),
+            Err(e) => eprintln!("[Scenario] warm start skipped: {}", e),
         }
+    }
+    exchange
+}
+
+/// Gas balance monitor of the scenario's on-chain accounts, if `vara.balance_monitor` is set.
+fn build_balance_monitor(
+    config: &SimConfig,
+    roster: &[AgentBuild],
+    address_book: &AddressBook,
+    vara_client: &Arc<VaraClient>,
+) -> Option<BalanceMonitorAgent> {
+    let cfg = config.vara.balance_monitor.as_ref()?;
+    let accounts = if cfg.accounts.is_empty() {
+        roster
+            .iter()
+            .map(|b| b.agent.id())
+            .filter(|id| !config.vara.local_agents.contains(id) && address_book.address_for_agent(*id).is_some())
+            .collect()
+    } else {
+        cfg.accounts.clone()
     };
-    let writer = RefCell::new(BufWriter::new(file));
-    if let Err(e) = writeln!(writer.borrow_mut(), "ts,from,to,msg_type,symbol,side,price,qty,reason") {
-        eprintln!("[Scenario] Failed to write CSV header: {}", e);
-        return;
+    let monitor_config = BalanceMonitorConfig {
+        name: cfg.name.clone(),
+        accounts,
+        interval_ms: cfg.interval_sec * 1000,
+        min_balance: cfg.min_balance,
+        top_up: cfg.top_up(),
+    };
+    Some(BalanceMonitorAgent::new(cfg.id, monitor_config, vara_client.clone()))
+}
+
+/// Connect the VaraClient described by the scenario's `vara` block (VARA_* env as fallback).
+pub(super) fn connect_vara(config: &SimConfig) -> Result<Arc<VaraClient>, String> {
+    connect_client(config.vara.client_config()?).map(Arc::new)
+}
+
+/// Connect for a `deploy`: no contract needed yet, the client is pointed at the new one later.
+pub(super) fn connect_vara_for_deploy(config: &SimConfig) -> Result<VaraClient, String> {
+    let mut vara = config.vara.clone();
+    vara.contract.get_or_insert_with(|| format!("0x{}", "00".repeat(32)));
+    connect_client(vara.client_config()?)
+}
+
+/// Client of a run without chain connection (`golden`): every agent besides the exchange and
+/// the oracles must be in `vara.local_agents`, so nothing is ever sent to the contract.
+pub(super) fn offline_vara(config: &SimConfig) -> Result<Arc<VaraClient>, String> {
+    let specs: Vec<AgentSpec> = config.agent_specs().into_iter().chain(timeline_specs(config)).collect();
+    let oracles = oracle_ids(&specs);
+    let on_chain: Vec<u32> = specs
+        .iter()
+        .filter_map(|s| s.id())
+        .filter(|id| !oracles.contains(id) && !config.vara.local_agents.contains(id))
+        .collect();
+    if !on_chain.is_empty() {
+        return Err(format!(
+            "offline run: agents {:?} are not in vara.local_agents",
+            on_chain
+        ));
     }
+    if config.vara.balance_monitor.is_some() {
+        return Err("offline run: vara.balance_monitor needs the chain".to_string());
+    }
+    let mut vara = config.vara.clone();
+    vara.contract.get_or_insert_with(|| format!("0x{}", "00".repeat(32)));
+    let client = VaraClient::new(vara.client_config()?).map_err(|e| e.to_string())?;
+    Ok(Arc::new(client))
+}
 
-    let listener = move |ev: &SimEvent| {
-        if let SimEvent::OrderLog { ts, from, to, msg_type, symbol, side, price, qty } = ev {
-            let symbol_str = symbol.as_deref().unwrap_or("");
-            let side_str = side.map(|s| format!("{:?}", s)).unwrap_or_default();
-            let price_str = price.map(|p| p.to_string()).unwrap_or_default();
-            let qty_str = qty.map(|q| q.to_string()).unwrap_or_default();
-            if let Err(e) = writeln!(
-                writer.borrow_mut(),
-                "{ts},{from},{to},{:?},{symbol},{side},{price},{qty}",
-                msg_type,
-                symbol = symbol_str,
-                side = side_str,
-                price = price_str,
-                qty = qty_str,
-            ) {
-                eprintln!("[Scenario] failed to write to orders.csv: {e}");
-            }
+fn connect_client(vara_config: VaraConfig) -> Result<VaraClient, String> {
+    println!("[Vara] Endpoint: {}", vara_config.ws_endpoint);
+    println!("[Vara] Contract: {}", vara_config.contract_address);
+    println!("[Vara] Keystore: {}", vara_config.keystore_path);
+    if !vara_config.agent_keys.is_empty() {
+        println!("[Vara] {} agent(s) with explicit keys", vara_config.agent_keys.len());
+    }
+
+    let mut client = VaraClient::new(vara_config).map_err(|e| e.to_string())?;
+    client.connect().map_err(|e| e.to_string())?;
+
+    // Preload bot keypairs
+    match client.preload_keypairs(200) {
+        Ok(count) => println!("[Vara] Loaded {} bot keypairs", count),
+        Err(e) => eprintln!("[Vara] Warning: Could not preload keypairs: {}", e),
+    }
+    Ok(client)
+}
+
+/// Blockchain connection is required: exit with setup help when it fails.
+fn connect_vara_or_exit(config: &SimConfig) -> Arc<VaraClient> {
+    match connect_vara(config) {
+        Ok(client) => {
+            println!("[Vara] Ready for blockchain operations");
+            println!();
+            client
         }
-    };
-    engine.kernel.event_bus_mut().subscribe(Box::new(ClosureListener { closure: listener }));
+        Err(e) => {
+            eprintln!("[Vara] FATAL: Failed to initialize: {}", e);
+            eprintln!();
+            eprintln!("Set these in the scenario's \"vara\" block or as environment variables:");
+            eprintln!("  contract   / VARA_CONTRACT_ADDRESS - deployed VaraPerps contract address");
+            eprintln!("  endpoint   / VARA_WS_ENDPOINT      - WebSocket RPC (default: wss://testnet.vara.network)");
+            eprintln!("  keystore   / VARA_KEYSTORE_PATH    - path to gring keystore (default: keys/Library/...)");
+            eprintln!("  passphrase / VARA_PASSPHRASE_PATH  - path to passphrase file (default: keys/.passphrase)");
+            eprintln!("               VARA_PASSPHRASE_FILE  - legacy name for passphrase path");
+            std::process::exit(1);
+        }
+    }
 }
 
-/// Run a simulation with given configuration
-fn run_with_config(config: SimConfig, skip_deposits: bool, vara_client: Arc<VaraClient>) {
+/// Run a simulation with given configuration. Fails when the kernel stopped the run
+/// (a dead letter under the `fail` policy).
+pub(super) fn run_with_config(
+    config: SimConfig,
+    skip_deposits: bool,
+    vara_client: Arc<VaraClient>,
+) -> Result<(), String> {
+    let specs = config.agent_specs();
     println!("[Scenario] Loading scenario: {}", config.scenario_name);
     println!("[Scenario] Duration: {}s", config.duration_sec);
     println!("[Scenario] Markets: {}", config.exchange.markets.len());
-    println!("[Scenario] Oracles: {}", config.oracles.len());
-    println!("[Scenario] SmartTraders: {}", config.smart_traders.len());
+    println!("[Scenario] Agents: {}", specs.len());
     println!("[Scenario] Blockchain: Vara Network");
 
     let max_ticks = (config.duration_sec * 1000 / 100) as usize;
 
     let mut engine = SimEngine::with_default_latency();
-    let address_book = AddressBook::load();
+    let address_book = AddressBook::load(config.vara.agent_keys());
     address_book.write_funding_list("keys/funding_addresses.txt");
-    if skip_deposits {
-        println!("[Scenario] Skipping initial deposits (flag enabled)");
-    } else {
-        deposit_initial_balances(&config, &vara_client);
-    }
 
-    register_csv_loggers(&mut engine, &config.logs_dir);
-    register_orders_csv(&mut engine, &config.logs_dir);
-
-    let markets = convert_markets(&config.exchange.markets);
-    let tx_result_rx = vara_client.take_tx_result_receiver();
-    let exchange = ExchangeAgent::new(
-        config.exchange.id,
-        config.exchange.name.clone(),
-        markets,
-        vara_client.clone(),
-        tx_result_rx,
-        Some(&config.logs_dir),
-    );
-    engine.kernel.add_agent(Box::new(exchange));
-
-    for oracle_cfg in &config.oracles {
-        let cache_duration_sec = oracle_cfg.cache_duration_ms / 1000;
+    let pnl_tracker = PnlTracker::new(&config.logs_dir);
+    let ctx = BuildContext {
+        exchange_id: config.exchange.id,
+        seed: config.seed,
+        oracle_ids: oracle_ids(&specs),
+        default_deposit: config.vara.default_deposit.unwrap_or(DEFAULT_DEPOSIT_MICRO_USD),
+        local_agents: config.vara.local_agents.iter().copied().collect(),
+        address_book: &address_book,
+        pnl_book: pnl_tracker.book(),
+        human: None,
+        schedule: &config.activity_schedule,
+        start_prices: &config.warmup.start_prices,
+    };
+    let mut roster = build_agents(&specs, &ctx);
+    roster.extend(build_agents(&timeline_specs(&config), &ctx));
 
-        let provider: Box<dyn crate::api::PriceProvider> = match oracle_cfg.provider.as_str() {
-            "Pyth" => {
-                let pyth = PythProvider::new();
-                Box::new(CachedPriceProvider::new(pyth, cache_duration_sec))
-            }
-            _ => {
-                eprintln!("[Scenario] Unknown provider: {}, using Pyth", oracle_cfg.provider);
-                let pyth = PythProvider::new();
-                Box::new(CachedPriceProvider::new(pyth, cache_duration_sec))
-            }
-        };
+    bootstrap_deposits(&config, &mut roster, &vara_client, skip_deposits);
 
-        let wake_interval_ns = oracle_cfg.wake_interval_ms * 1_000_000;
+    let flow_stats = register_csv_loggers(&mut engine, &config);
+    register_analytics(&mut engine, &config, pnl_tracker, &roster);
 
-        engine.kernel.add_agent(Box::new(OracleAgent::new(
-            oracle_cfg.id,
-            oracle_cfg.name.clone(),
-            oracle_cfg.symbols.clone(),
-            config.exchange.id,
-            wake_interval_ns,
-            provider,
-        )));
+    apply_kernel_settings(&mut engine, &config, specs.iter().chain(&timeline_specs(&config)));
+    if let Some(monitor) = build_balance_monitor(&config, &roster, &address_book, &vara_client) {
+        engine.kernel.add_agent(Box::new(monitor));
     }
+    let exchange = build_exchange(&config, &roster, vara_client, flow_stats);
+    engine.kernel.add_agent(Box::new(exchange));
 
-    // Add Market Maker if configured (MUST be added BEFORE other traders for seed liquidity)
-    if let Some(mm_cfg) = &config.market_maker {
-        let mm_address = address_book.address_for_agent(mm_cfg.id).unwrap_or_else(|| {
-            eprintln!("[Scenario] Missing address for MarketMaker id={}", mm_cfg.id);
-            std::process::exit(1);
-        });
-        let mm_config = MarketMakerConfig {
-            name: mm_cfg.name.clone(),
-            exchange_id: config.exchange.id,
-            symbol: mm_cfg.symbol.clone(),
-            address: Some(mm_address),
-            target_oi_per_side: mm_cfg.target_oi_per_side,
-            max_imbalance_pct: mm_cfg.max_imbalance_pct,
-            order_size_tokens: mm_cfg.order_size_tokens,
-            leverage: mm_cfg.leverage,
-            wake_interval_ms: mm_cfg.wake_interval_ms,
-            balance: mm_cfg.balance,
-        };
-        engine
-            .kernel
-            .add_agent(Box::new(MarketMakerAgent::new(mm_cfg.id, mm_config)));
-        println!("[Scenario] Added MarketMaker: {}", mm_cfg.name);
+    start_agents(&mut engine, &config, roster);
+
+    println!("[Scenario] starting {}", config.scenario_name);
+    engine.run(max_ticks);
+    if let Some(failure) = engine.kernel.failure() {
+        return Err(format!("{} failed: {}", config.scenario_name, failure));
     }
+    println!("[Scenario] finished {}", config.scenario_name);
+    Ok(())
+}
 
-    // Create smart traders
-    for smart_cfg in &config.smart_traders {
-        let address = address_book.address_for_agent(smart_cfg.id).unwrap_or_else(|| {
-            eprintln!("[Scenario] Missing address for SmartTrader id={}", smart_cfg.id);
-            std::process::exit(1);
-        });
-        let mut agent = create_smart_trader(smart_cfg, config.exchange.id);
-        agent.set_address(address);
-        engine.kernel.add_agent(Box::new(agent));
+/// Replay a recorded trace of the scenario: only the `live` agents are built (from the
+/// current code and config); everyone else, the exchange included, is replayed from the
+/// trace. No chain connection is needed. Logs of the live agents go to `<logs_dir>/replay`.
+pub fn run_replay(mut config: SimConfig, trace: &Path, live: &[u32]) -> Result<(), String> {
+    if live.contains(&config.exchange.id) {
+        return Err(format!(
+            "the exchange (id={}) needs the chain and can only be replayed",
+            config.exchange.id
+        ));
     }
+    let live_ids: HashSet<u32> = live.iter().copied().collect();
+    let (header, replay) = Replay::open(trace, live_ids.clone())?;
 
-    // Create limit traders
-    for limit_cfg in &config.limit_traders {
-        let address = address_book.address_for_agent(limit_cfg.id).unwrap_or_else(|| {
-            eprintln!("[Scenario] Missing address for LimitTrader id={}", limit_cfg.id);
-            std::process::exit(1);
-        });
-        let mut agent = create_limit_trader(limit_cfg, config.exchange.id);
-        agent.set_address(address);
-        engine.kernel.add_agent(Box::new(agent));
-        println!("[Scenario] Added LimitTrader: {}", limit_cfg.name);
-    }
-
-    // Add keepers (no blockchain access - they send messages to Exchange)
-    for keeper_cfg in &config.keepers {
-        let keeper_config = KeeperConfig {
-            name: keeper_cfg.name.clone(),
-            exchange_id: config.exchange.id,
-            address: None, // Keepers don't need address - Exchange signs for them
-            wake_interval_ms: keeper_cfg.wake_interval_ms,
-        };
-        let agent = KeeperAgent::new(keeper_cfg.id, keeper_config);
-        engine.kernel.add_agent(Box::new(agent));
-        println!("[Scenario] Added Keeper: {}", keeper_cfg.name);
+    let specs: Vec<AgentSpec> = config
+        .agent_specs()
+        .into_iter()
+        .chain(timeline_specs(&config))
+        .collect();
+    let address_book = AddressBook::load(config.vara.agent_keys());
+    let pnl_tracker = PnlTracker::new(&config.logs_dir);
+    let ctx = BuildContext {
+        exchange_id: config.exchange.id,
+        seed: config.seed,
+        oracle_ids: oracle_ids(&specs),
+        default_deposit: config.vara.default_deposit.unwrap_or(DEFAULT_DEPOSIT_MICRO_USD),
+        // Live agents trade against the recorded exchange: no key needed
+        local_agents: live_ids.clone(),
+        address_book: &address_book,
+        pnl_book: pnl_tracker.book(),
+        human: None,
+        schedule: &config.activity_schedule,
+        start_prices: &config.warmup.start_prices,
+    };
+    let live_specs: Vec<AgentSpec> = specs
+        .into_iter()
+        .filter(|s| s.id().is_some_and(|id| live_ids.contains(&id)))
+        .collect();
+    let roster = build_agents(&live_specs, &ctx);
+    let missing: Vec<u32> = live
+        .iter()
+        .copied()
+        .filter(|id| !roster.iter().any(|b| b.agent.id() == *id))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("no agent with id {:?} in {}", missing, config.scenario_name));
     }
 
-    // Add liquidation agent if configured (no blockchain access - sends LiquidationScan to Exchange)
-    if let Some(liq_cfg) = &config.liquidation_agent {
-        let wake_interval_ns = liq_cfg.wake_interval_ms * 1_000_000;
-        let agent = LiquidationAgent::new(liq_cfg.id, liq_cfg.name.clone(), config.exchange.id, wake_interval_ns);
-        engine.kernel.add_agent(Box::new(agent));
-        println!("[Scenario] Added Liquidation: {}", liq_cfg.name);
+    config.logs_dir = format!("{}/replay", config.logs_dir);
+    config.logging.trace = false;
+    let mut engine = SimEngine::with_default_latency();
+    engine.kernel.set_replay(header, replay);
+    register_csv_loggers(&mut engine, &config);
+    apply_kernel_settings(&mut engine, &config, &live_specs);
+    for built in roster {
+        engine.kernel.add_agent(built.agent);
     }
 
-    println!("[Scenario] starting {}", config.scenario_name);
+    println!(
+        "[Replay] {} from {} (live {:?})",
+        config.scenario_name,
+        trace.display(),
+        live
+    );
+    let max_ticks = (config.duration_sec * 1_000_000_000 / header.tick_ns.max(1)) as usize;
     engine.run(max_ticks);
-    println!("[Scenario] finished {}", config.scenario_name);
-}
-
-fn find_config_file(scenario_name: &str) -> Option<String> {
-    // Try multiple possible locations
-    let candidates = [
-        format!("sim-engine/src/scenarios/{}.json", scenario_name),
-        format!("src/scenarios/{}.json", scenario_name),
-        format!("scenarios/{}.json", scenario_name),
-        format!("{}.json", scenario_name),
-    ];
-
-    for path in &candidates {
-        if std::path::Path::new(path).exists() {
-            return Some(path.clone());
-        }
+    match engine.kernel.failure() {
+        Some(failure) => Err(format!("replay failed: {}", failure)),
+        None => Ok(()),
     }
-    None
 }
 
-/// Run scenario with blockchain
-pub fn run_scenario_with_blockchain(
-    scenario_name: &str,
-    skip_deposits: bool,
-    vara_client: Arc<VaraClient>,
-) {
-    let config = match find_config_file(scenario_name) {
+/// Find, parse and validate a scenario. Exits on any error; only the built-in
+/// `simple_demo` falls back to the default configuration when no file exists.
+pub fn load_config(catalog: &Catalog, scenario_name: &str, overrides: &[String]) -> SimConfig {
+    match catalog.find(scenario_name) {
         Some(path) => {
-            println!("[Scenario] Found config: {}", path);
-            SimConfig::from_file(&path).unwrap_or_else(|e| {
-                eprintln!("[Scenario] Failed to parse {}: {}", path, e);
-                eprintln!("[Scenario] Using default configuration");
-                SimConfig::default()
+            println!("[Scenario] Found config: {}", path.display());
+            for expr in overrides {
+                println!("[Scenario] Override: {}", expr);
+            }
+            SimConfig::from_file(&path.to_string_lossy(), overrides).unwrap_or_else(|e| {
+                eprintln!("[Scenario] Invalid scenario {}", e);
+                std::process::exit(1);
             })
         }
-        None => {
+        None if scenario_name == "simple_demo" && overrides.is_empty() => {
             eprintln!("[Scenario] Config file not found for: {}", scenario_name);
             eprintln!("[Scenario] Using default configuration");
             SimConfig::default()
         }
-    };
+        None => {
+            eprintln!(
+                "[Scenario] Config file not found for: {} (scenarios dir: {})",
+                scenario_name,
+                catalog.dir().display()
+            );
+            std::process::exit(1);
+        }
+    }
+}
 
-    run_with_config(config, skip_deposits, vara_client);
+/// Run scenario with blockchain
+pub fn run_scenario_with_blockchain(config: SimConfig, skip_deposits: bool) {
+    let vara_client = connect_vara_or_exit(&config);
+    if let Err(e) = run_with_config(config, skip_deposits, vara_client) {
+        eprintln!("[Scenario] {}", e);
+        std::process::exit(1);
+    }
 }
 
 /// Run simulation in realtime mode with blockchain
-pub fn run_realtime_with_blockchain(
-    scenario_name: &str,
-    tick_ms: u64,
-    api_port: u16,
-    skip_deposits: bool,
-    vara_client: Arc<VaraClient>,
-) {
-    let config = match find_config_file(scenario_name) {
-        Some(path) => {
-            println!("[Scenario] Found config: {}", path);
-            SimConfig::from_file(&path).unwrap_or_else(|e| {
-                eprintln!("[Scenario] Failed to parse {}: {}", path, e);
-                SimConfig::default()
-            })
-        }
-        None => {
-            eprintln!("[Scenario] Config file not found for: {}", scenario_name);
-            SimConfig::default()
-        }
-    };
-
-    run_realtime_with_config(config, tick_ms, api_port, skip_deposits, vara_client);
+pub fn run_realtime_with_blockchain(config: SimConfig, tick_ms: u64, api_port: u16, skip_deposits: bool, tui: bool) {
+    let vara_client = connect_vara_or_exit(&config);
+    run_realtime_with_config(config, tick_ms, api_port, skip_deposits, tui, vara_client);
 }
 
 /// Internal realtime runner with config
@@ -941,6 +4192,7 @@ fn run_realtime_with_config(
     tick_ms: u64,
     api_port: u16,
     skip_deposits: bool,
+    tui: bool,
     vara_client: Arc<VaraClient>,
 ) {
     println!("[Scenario] Loading: {} (REALTIME)", config.scenario_name);
@@ -949,30 +4201,73 @@ fn run_realtime_with_config(
     let max_ticks = usize::MAX; // Run indefinitely
 
     let mut engine = SimEngine::with_realtime(tick_ms);
-    let address_book = AddressBook::load();
+    let address_book = AddressBook::load(config.vara.agent_keys());
     address_book.write_funding_list("keys/funding_addresses.txt");
-    if skip_deposits {
-        println!("[Scenario] Skipping initial deposits (flag enabled)");
-    } else {
-        deposit_initial_balances(&config, &vara_client);
-    }
 
-    register_csv_loggers(&mut engine, &config.logs_dir);
-
-    // Start API server (HTTP)
+    // Channels between API servers and HumanAgent
     let (response_tx, response_rx) = crossbeam_channel::unbounded();
     let (response_tx_ws, response_rx_ws) = crossbeam_channel::unbounded();
-
     // Use a shared channel for commands from both HTTP and WS
     let (cmd_tx, cmd_rx) = crossbeam_channel::unbounded();
+    let (human_response_tx, human_response_rx) = crossbeam_channel::unbounded::<ApiResponse>();
+    let remotes = RemoteHub::new();
+
+    // HumanAgent (id=100, reserved) is always present in realtime unless the scenario declares one
+    let mut specs = config.agent_specs();
+    if !specs.iter().any(|s| s.agent_type == "human") {
+        specs.push(AgentSpec::from_config("human", &HumanJsonConfig::default()));
+    }
+    let sessions = session_hub(&specs);
+
+    let pnl_tracker = PnlTracker::new(&config.logs_dir);
+    let ctx = BuildContext {
+        exchange_id: config.exchange.id,
+        seed: config.seed,
+        oracle_ids: oracle_ids(&specs),
+        default_deposit: config.vara.default_deposit.unwrap_or(DEFAULT_DEPOSIT_MICRO_USD),
+        local_agents: config.vara.local_agents.iter().copied().collect(),
+        address_book: &address_book,
+        pnl_book: pnl_tracker.book(),
+        human: Some(HumanChannels {
+            cmd_rx,
+            response_tx: human_response_tx,
+            tick_ms,
+            remotes: remotes.clone(),
+            sessions: sessions.clone(),
+        }),
+        schedule: &config.activity_schedule,
+        start_prices: &config.warmup.start_prices,
+    };
+    let mut roster = build_agents(&specs, &ctx);
+    roster.extend(build_agents(&timeline_specs(&config), &ctx));
+
+    bootstrap_deposits(&config, &mut roster, &vara_client, skip_deposits);
+
+    let flow_stats = register_csv_loggers(&mut engine, &config);
+    register_analytics(&mut engine, &config, pnl_tracker, &roster);
+
+    // Commands from both servers share one guard; symbols default to the exchange's markets
+    let mut limits = config.api.limits.clone();
+    if limits.symbols.is_empty() {
+        limits.symbols = config.exchange.markets.iter().map(|m| m.symbol.clone()).collect();
+    }
+    let guard = Arc::new(CommandGuard::new(limits));
+    let metrics = engine.kernel.record_metrics(Path::new(&config.logs_dir));
 
     // Start HTTP API
-    let _api_server = crate::api::ApiServer::start_with_channel(api_port, response_rx, cmd_tx.clone());
+    let _api_server = crate::api::ApiServer::start_with_channel(
+        api_port,
+        response_rx,
+        cmd_tx.clone(),
+        guard.clone(),
+        config.api.cors.clone(),
+        Some(metrics),
+    );
 
     // Start WebSocket API (on port + 1)
     let ws_port = api_port + 1;
     let (event_tx, event_rx) = crossbeam_channel::unbounded();
-    let _ws_server = crate::api::WsServer::start(ws_port, cmd_tx, event_rx, response_rx_ws);
+    let _ws_server = crate::api::WsServer::start(ws_port, cmd_tx, event_rx, response_rx_ws, remotes, sessions, guard);
 
     // Subscribe WS to all events
     {
@@ -986,146 +4281,72 @@ fn run_realtime_with_config(
             .subscribe(Box::new(ClosureListener { closure: listener }));
     }
 
-    let (human_response_tx, human_response_rx) = crossbeam_channel::unbounded::<crate::api::ApiResponse>();
-
-    // Response forwarder thread
+    // Response forwarder thread: answers to HTTP requests go to the HTTP server, the rest to WS clients
     std::thread::spawn(move || {
         while let Ok(resp) = human_response_rx.recv() {
-            let _ = response_tx.send(resp.clone());
-            let _ = response_tx_ws.send(resp);
+            let http = resp
+                .request_id
+                .as_deref()
+                .is_some_and(|id| id.starts_with(crate::api::HTTP_REQUEST_ID_PREFIX));
+            let _ = if http { response_tx.send(resp) } else { response_tx_ws.send(resp) };
         }
     });
 
-    let markets = convert_markets(&config.exchange.markets);
-    let tx_result_rx = vara_client.take_tx_result_receiver();
-    let exchange = ExchangeAgent::new(
-        config.exchange.id,
-        config.exchange.name.clone(),
-        markets,
-        vara_client.clone(),
-        tx_result_rx,
-        Some(&config.logs_dir),
-    );
-    engine.kernel.add_agent(Box::new(exchange));
-
-    // Add oracles
-    for oracle_cfg in &config.oracles {
-        let provider: Box<dyn crate::api::PriceProvider> = {
-            let pyth = PythProvider::new();
-            Box::new(CachedPriceProvider::new(pyth, oracle_cfg.cache_duration_ms / 1000))
-        };
-
-        engine.kernel.add_agent(Box::new(OracleAgent::new(
-            oracle_cfg.id,
-            oracle_cfg.name.clone(),
-            oracle_cfg.symbols.clone(),
-            config.exchange.id,
-            oracle_cfg.wake_interval_ms * 1_000_000,
-            provider,
-        )));
-    }
-
-    // Add Market Maker if configured (MUST be added BEFORE other traders for seed liquidity)
-    if let Some(mm_cfg) = &config.market_maker {
-        let mm_address = address_book.address_for_agent(mm_cfg.id).unwrap_or_else(|| {
-            eprintln!("[Scenario] Missing address for MarketMaker id={}", mm_cfg.id);
-            std::process::exit(1);
-        });
-        let mm_config = MarketMakerConfig {
-            name: mm_cfg.name.clone(),
-            exchange_id: config.exchange.id,
-            symbol: mm_cfg.symbol.clone(),
-            address: Some(mm_address),
-            target_oi_per_side: mm_cfg.target_oi_per_side,
-            max_imbalance_pct: mm_cfg.max_imbalance_pct,
-            order_size_tokens: mm_cfg.order_size_tokens,
-            leverage: mm_cfg.leverage,
-            wake_interval_ms: mm_cfg.wake_interval_ms,
-            balance: mm_cfg.balance,
-        };
-        engine
-            .kernel
-            .add_agent(Box::new(MarketMakerAgent::new(mm_cfg.id, mm_config)));
-        println!("[Scenario] Added MarketMaker: {}", mm_cfg.name);
-    }
-
-    // Add smart traders
-    for smart_cfg in &config.smart_traders {
-        let address = address_book.address_for_agent(smart_cfg.id).unwrap_or_else(|| {
-            eprintln!("[Scenario] Missing address for SmartTrader id={}", smart_cfg.id);
-            std::process::exit(1);
-        });
-        let mut agent = create_smart_trader(smart_cfg, config.exchange.id);
-        agent.set_address(address);
-        engine.kernel.add_agent(Box::new(agent));
-    }
-
-    // Add limit traders
-    for limit_cfg in &config.limit_traders {
-        let address = address_book.address_for_agent(limit_cfg.id).unwrap_or_else(|| {
-            eprintln!("[Scenario] Missing address for LimitTrader id={}", limit_cfg.id);
-            std::process::exit(1);
-        });
-        let mut agent = create_limit_trader(limit_cfg, config.exchange.id);
-        agent.set_address(address);
-        engine.kernel.add_agent(Box::new(agent));
-    }
-
-    // Add keepers (no blockchain access - they send messages to Exchange)
-    for keeper_cfg in &config.keepers {
-        let keeper_config = KeeperConfig {
-            name: keeper_cfg.name.clone(),
-            exchange_id: config.exchange.id,
-            address: None, // Keepers don't need address - Exchange signs for them
-            wake_interval_ms: keeper_cfg.wake_interval_ms,
-        };
-        let agent = KeeperAgent::new(keeper_cfg.id, keeper_config);
-        engine.kernel.add_agent(Box::new(agent));
+    apply_kernel_settings(&mut engine, &config, specs.iter().chain(&timeline_specs(&config)));
+    if let Some(monitor) = build_balance_monitor(&config, &roster, &address_book, &vara_client) {
+        engine.kernel.add_agent(Box::new(monitor));
     }
+    let exchange = build_exchange(&config, &roster, vara_client, flow_stats);
+    engine.kernel.add_agent(Box::new(exchange));
 
-    // Add liquidation agent if configured (no blockchain access - sends LiquidationScan to Exchange)
-    if let Some(liq_cfg) = &config.liquidation_agent {
-        let wake_interval_ns = liq_cfg.wake_interval_ms * 1_000_000;
-        let agent = LiquidationAgent::new(liq_cfg.id, liq_cfg.name.clone(), config.exchange.id, wake_interval_ns);
-        engine.kernel.add_agent(Box::new(agent));
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for built in &roster {
+        *counts.entry(built.kind.as_str()).or_insert(0) += 1;
     }
-
-    // Add HumanAgent (id=100, reserved). Default to bot_100 address if not provided.
-    let human_address = std::env::var("VARA_HUMAN_ADDRESS")
-        .ok()
-        .or_else(|| address_book.address_for_agent(100));
-    engine.kernel.add_agent(Box::new(HumanAgent::new(
-        100,
-        "HumanTrader".to_string(),
-        config.exchange.id,
-        human_address,
-        cmd_rx,
-        human_response_tx,
-        tick_ms,
-    )));
+    let summary: Vec<String> = counts.iter().map(|(kind, n)| format!("{} {}", n, kind)).collect();
 
     println!();
     println!("=== REALTIME MODE ===");
-    println!(
-        "Agents: {} smart + {} limit + {} keepers + HumanAgent{}{}",
-        config.smart_traders.len(),
-        config.limit_traders.len(),
-        config.keepers.len(),
-        if config.market_maker.is_some() { " + MM" } else { "" },
-        if config.liquidation_agent.is_some() {
-            " + Liquidator"
-        } else {
-            ""
-        }
-    );
+    println!("Agents: {}", summary.join(" + "));
     println!();
     println!("=== API Endpoints ===");
     println!("  POST http://localhost:{}/order", api_port);
+    println!("  GET  http://localhost:{}/openapi.json  (OpenAPI spec)", api_port);
+    println!("  GET  http://localhost:{}/metrics  (kernel timings)", api_port);
     println!("  WS   ws://localhost:{}", ws_port);
     println!("       {{\"action\":\"open\", \"symbol\":\"ETH-USD\", ...}}");
+    println!("  WS   ws://localhost:{}/remote/<name>  (remote agents)", ws_port);
+    println!("  WS   ws://localhost:{}/session/<token>  (own HumanAgent per client)", ws_port);
     println!();
     println!("Press Ctrl+C to stop");
     println!();
 
+    start_agents(&mut engine, &config, roster);
+
+    let mut dashboard_thread = None;
+    if tui {
+        let dashboard = crate::tui::new_dashboard(&config.scenario_name);
+        engine
+            .kernel
+            .event_bus_mut()
+            .subscribe(Box::new(crate::tui::TuiListener::new(dashboard.clone())));
+        let log_path = std::path::Path::new(&config.logs_dir).join("stdout.log");
+        println!("[Scenario] TUI enabled, engine output -> {}", log_path.display());
+        match crate::tui::redirect_output(&log_path) {
+            Ok(()) => match crate::tui::spawn(dashboard, engine.kernel.stop_handle()) {
+                Ok(handle) => dashboard_thread = Some(handle),
+                Err(e) => eprintln!("[Scenario] Failed to start TUI: {}", e),
+            },
+            Err(e) => eprintln!("[Scenario] Failed to redirect output, TUI disabled: {}", e),
+        }
+    }
+
     engine.run(max_ticks);
+    if let Some(handle) = dashboard_thread {
+        handle.join();
+    }
+    if let Some(failure) = engine.kernel.failure() {
+        eprintln!("[Scenario] {} failed: {}", config.scenario_name, failure);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,359 @@
+// End-to-end smoke test of the chain path.
+//
+// `vara-smoke` connects to the contract of a scenario (vara_smoke.json by
+// default) and walks one account through deposit -> open -> query -> close ->
+// withdraw, checking the on-chain balance and position after every step. The
+// first failing step stops the run. The outcome is printed and written as
+// JSON (<logs_dir>/vara_smoke.json) so CI can pick up the pass/fail result.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crossbeam_channel::Receiver;
+use primitive_types::U256;
+use serde::Serialize;
+
+use super::catalog::Catalog;
+use super::simple_demo::{self, SimConfig};
+use crate::api::{PriceProvider, PythProvider, SignedPriceData};
+use crate::scale::{MicroUsd, TokenScale};
+use crate::vara::oracle::PEGGED_COLLATERAL_MICRO;
+use crate::vara::{
+    ActorId, ExecutionType, OracleInput, OracleInputBuilder, Order, OrderType, PositionKey, Side, TxResult, VaraClient,
+};
+
+/// `vara-smoke` options.
+#[derive(Debug, Clone)]
+pub struct SmokeOptions {
+    /// Account used for the round trip (key from the scenario's `vara.keys`, else bot_NNN)
+    pub agent_id: u32,
+    /// Deposited and finally withdrawn (USD)
+    pub deposit_usd: f64,
+    /// Notional of the test position (USD)
+    pub size_usd: f64,
+    pub leverage: u32,
+    /// Fixed index price instead of a Pyth quote (USD), e.g. for dev contracts
+    pub price_usd: Option<f64>,
+    /// How long to wait for an order transaction
+    pub timeout_sec: u64,
+    /// Report path (default: <logs_dir>/vara_smoke.json)
+    pub report: Option<PathBuf>,
+}
+
+impl Default for SmokeOptions {
+    fn default() -> Self {
+        Self {
+            agent_id: 20,
+            deposit_usd: 1_000.0,
+            size_usd: 100.0,
+            leverage: 2,
+            price_usd: None,
+            timeout_sec: 120,
+            report: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SmokeStep {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SmokeReport {
+    pub scenario: String,
+    pub contract: String,
+    pub agent_id: u32,
+    pub symbol: String,
+    pub passed: bool,
+    pub steps: Vec<SmokeStep>,
+}
+
+impl SmokeReport {
+    /// "step: detail" of every failed step
+    pub fn failures(&self) -> Vec<String> {
+        self.steps
+            .iter()
+            .filter(|s| !s.passed)
+            .map(|s| format!("{}: {}", s.name, s.detail))
+            .collect()
+    }
+}
+
+struct Smoke<'a> {
+    client: &'a VaraClient,
+    tx_results: Receiver<TxResult>,
+    opts: &'a SmokeOptions,
+    symbol: String,
+    index_decimals: u32,
+    collateral_decimals: u32,
+    account: ActorId,
+    report: SmokeReport,
+}
+
+impl Smoke<'_> {
+    /// Run one step and record it; skipped once a step has failed.
+    fn step(&mut self, name: &str, f: impl FnOnce(&mut Self) -> Result<String, String>) {
+        if !self.report.passed {
+            return;
+        }
+        let started = Instant::now();
+        let result = f(self);
+        let passed = result.is_ok();
+        let detail = result.unwrap_or_else(|e| e);
+        println!("[Smoke] {} {}: {}", if passed { "PASS" } else { "FAIL" }, name, detail);
+        self.report.steps.push(SmokeStep {
+            name: name.to_string(),
+            passed,
+            detail,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        });
+        self.report.passed &= passed;
+    }
+
+    fn balance(&self) -> Result<U256, String> {
+        self.client.get_balance(self.account).map_err(|e| e.to_string())
+    }
+
+    fn position_key(&self) -> PositionKey {
+        PositionKey {
+            account: self.account,
+            side: Side::Long,
+        }
+    }
+
+    /// Collateral pegged at $1
+    fn usd_to_collateral(&self, usd: f64) -> U256 {
+        TokenScale::new(self.collateral_decimals)
+            .amount_for(MicroUsd::from_usd(usd), MicroUsd(PEGGED_COLLATERAL_MICRO))
+            .atoms
+    }
+
+    fn oracle_input(&self) -> Result<OracleInput, String> {
+        let now_secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let price = match self.opts.price_usd {
+            Some(usd) => SignedPriceData {
+                symbol: self.symbol.clone(),
+                price_usd_micro: (usd * 1_000_000.0) as u64,
+                confidence: None,
+                ema_price: None,
+                publish_time: now_secs,
+                signature: Vec::new(),
+                provider_name: "fixed".to_string(),
+            },
+            None => PythProvider::new()
+                .fetch_signed_price(&self.symbol)
+                .map_err(|e| format!("Pyth price for {}: {}", self.symbol, e))?,
+        };
+        OracleInputBuilder::new(self.index_decimals, self.collateral_decimals)
+            .max_age_secs(60)
+            .build(&price, None, now_secs)
+            .map_err(|e| e.to_string())
+    }
+
+    fn market_order(&self, order_type: OrderType, collateral: U256, size_usd: U256) -> Order {
+        let now_sec = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Order {
+            account: self.account,
+            side: Side::Long,
+            order_type,
+            execution_type: ExecutionType::Market,
+            collateral_delta_tokens: collateral,
+            size_delta_usd: size_usd,
+            trigger_price: None,
+            acceptable_price: None,
+            withdraw_collateral_amount: U256::zero(),
+            target_leverage_x: self.opts.leverage.max(1),
+            created_at: now_sec,
+            valid_from: now_sec,
+            valid_until: now_sec + 3600,
+        }
+    }
+
+    /// Submit + execute `order` and wait for the transaction result.
+    fn execute(&self, order: Order) -> Result<Option<u64>, String> {
+        let oracle_input = self.oracle_input()?;
+        self.client
+            .submit_and_execute_order_async(self.opts.agent_id, order, oracle_input)
+            .map_err(|e| e.to_string())?;
+        let deadline = Instant::now() + Duration::from_secs(self.opts.timeout_sec);
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let result = self
+                .tx_results
+                .recv_timeout(left)
+                .map_err(|_| format!("no transaction result within {}s", self.opts.timeout_sec))?;
+            if result.agent_id != self.opts.agent_id {
+                continue;
+            }
+            return if result.success {
+                Ok(result.order_id)
+            } else {
+                Err(result.error.unwrap_or(result.detail))
+            };
+        }
+    }
+}
+
+/// Run the round trip against the contract of `scenario`. Err only when the run
+/// can't start (invalid scenario, no connection); failed steps are in the report.
+pub fn run(catalog: &Catalog, scenario: &str, sets: &[String], opts: &SmokeOptions) -> Result<SmokeReport, String> {
+    let path = catalog
+        .find(scenario)
+        .ok_or_else(|| format!("scenario '{}' not found in {}", scenario, catalog.dir().display()))?;
+    let config = SimConfig::from_file(&path.to_string_lossy(), sets)?;
+    let (symbol, index_decimals, collateral_decimals) = config
+        .market_decimals()
+        .into_iter()
+        .next()
+        .ok_or("scenario has no markets")?;
+
+    let client = simple_demo::connect_vara(&config)?;
+    let tx_results = client
+        .take_tx_result_receiver()
+        .ok_or("transaction results already taken")?;
+    let account = client.get_actor_id(opts.agent_id).map_err(|e| e.to_string())?;
+
+    let mut smoke = Smoke {
+        client: &client,
+        tx_results,
+        opts,
+        symbol: symbol.clone(),
+        index_decimals,
+        collateral_decimals,
+        account,
+        report: SmokeReport {
+            scenario: config.scenario_name().to_string(),
+            contract: client.contract_address().to_string(),
+            agent_id: opts.agent_id,
+            symbol,
+            passed: true,
+            steps: Vec::new(),
+        },
+    };
+
+    let deposit = smoke.usd_to_collateral(opts.deposit_usd);
+    let collateral = smoke.usd_to_collateral(opts.size_usd / opts.leverage.max(1) as f64);
+    let size_usd = MicroUsd::from_usd(opts.size_usd).to_1e30().0;
+    let mut balance = U256::zero();
+
+    smoke.step("balance", |s| {
+        balance = s.balance()?;
+        if s.client
+            .get_position(&s.position_key())
+            .map_err(|e| e.to_string())?
+            .is_some_and(|p| !p.size_usd.is_zero())
+        {
+            return Err("account already has an open long position".to_string());
+        }
+        Ok(format!("balance={}", balance))
+    });
+
+    smoke.step("deposit", |s| {
+        s.client.deposit(opts.agent_id, deposit).map_err(|e| e.to_string())?;
+        let after = s.balance()?;
+        if after != balance + deposit {
+            return Err(format!("balance {} after depositing {} to {}", after, deposit, balance));
+        }
+        balance = after;
+        Ok(format!("deposited {}, balance={}", deposit, after))
+    });
+
+    smoke.step("open", |s| {
+        let order_id = s.execute(s.market_order(OrderType::Increase, collateral, size_usd))?;
+        let after = s.balance()?;
+        if after + collateral != balance {
+            return Err(format!(
+                "balance {} after opening with collateral {} from {}",
+                after, collateral, balance
+            ));
+        }
+        balance = after;
+        Ok(format!(
+            "order #{} executed, balance={}",
+            order_id.unwrap_or_default(),
+            after
+        ))
+    });
+
+    smoke.step("query", |s| {
+        let position = s
+            .client
+            .get_position(&s.position_key())
+            .map_err(|e| e.to_string())?
+            .ok_or("no position after open")?;
+        if position.size_usd != size_usd {
+            return Err(format!("position size {} != {}", position.size_usd, size_usd));
+        }
+        if position.collateral_amount.is_zero() || position.collateral_amount > collateral {
+            return Err(format!(
+                "position collateral {} (deposited {})",
+                position.collateral_amount, collateral
+            ));
+        }
+        Ok(format!(
+            "size={} collateral={}",
+            position.size_usd, position.collateral_amount
+        ))
+    });
+
+    smoke.step("close", |s| {
+        let order_id = s.execute(s.market_order(OrderType::Decrease, U256::zero(), size_usd))?;
+        if let Some(p) = s.client.get_position(&s.position_key()).map_err(|e| e.to_string())? {
+            if !p.size_usd.is_zero() {
+                return Err(format!("position still open: size={}", p.size_usd));
+            }
+        }
+        let after = s.balance()?;
+        if after <= balance {
+            return Err(format!("no collateral returned: balance {} -> {}", balance, after));
+        }
+        balance = after;
+        Ok(format!(
+            "order #{} executed, balance={}",
+            order_id.unwrap_or_default(),
+            after
+        ))
+    });
+
+    smoke.step("withdraw", |s| {
+        let amount = deposit.min(balance);
+        s.client.withdraw(opts.agent_id, amount).map_err(|e| e.to_string())?;
+        let after = s.balance()?;
+        if after + amount != balance {
+            return Err(format!(
+                "balance {} after withdrawing {} from {}",
+                after, amount, balance
+            ));
+        }
+        Ok(format!("withdrew {}, balance={}", amount, after))
+    });
+
+    let report = smoke.report;
+    let path = opts
+        .report
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(config.logs_dir()).join("vara_smoke.json"));
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("{}: {}", path.display(), e))?;
+    println!(
+        "[Smoke] {} ({}/{} steps passed), report: {}",
+        if report.passed { "PASSED" } else { "FAILED" },
+        report.steps.iter().filter(|s| s.passed).count(),
+        report.steps.len(),
+        path.display()
+    );
+    Ok(report)
+}
@@ -68,17 +68,18 @@ impl SimEngine {
         Self { kernel }
     }
 
+    /// Engine without built-in loggers: scenarios register their own (see `register_csv_loggers`).
     pub fn with_default_latency() -> Self {
         let latency: Box<dyn LatencyModel> = Box::new(FixedLatency::new(1_000_000, 500_000));
         let tick_ns = 100_000_000; // 100ms tick
-        Self::new(latency, tick_ns, Some(Path::new("logs")))
+        Self::new(latency, tick_ns, None)
     }
 
     /// Create engine for realtime mode with specified tick interval
     pub fn with_realtime(tick_ms: u64) -> Self {
         let latency: Box<dyn LatencyModel> = Box::new(FixedLatency::new(1_000_000, 500_000));
         let tick_ns = tick_ms * 1_000_000;
-        let mut engine = Self::new(latency, tick_ns, Some(Path::new("logs")));
+        let mut engine = Self::new(latency, tick_ns, None);
         engine.kernel.set_realtime(tick_ms);
         engine
     }
@@ -0,0 +1,316 @@
+// Programmatic simulation setup.
+//
+// `SimulationBuilder` assembles a kernel from agents, price providers (each
+// polled by an OracleAgent), a latency model and event listeners, runs it in
+// fast-forward mode and returns a `SimulationResult`: the run summary (same
+// report as summary.json) and, on request, every emitted event. It reads no
+// scenario file and opens no chain connection; agents that need Vara (e.g. an
+// ExchangeAgent) are built by the caller with their own VaraClient.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::agents::oracle_agent::OracleAgent;
+use crate::agents::Agent;
+use crate::api::PriceProvider;
+use crate::events::{EventListener, SimEvent};
+use crate::latency::{FixedLatency, LatencyModel};
+use crate::logging::Verbosity;
+use crate::messages::{AgentId, SimulatorApi};
+use crate::report::{ReportBuilder, RunSummary};
+use crate::sim_engine::SimEngine;
+
+const DEFAULT_TICK_NS: u64 = 100_000_000; // 100ms
+const DEFAULT_DURATION_SEC: u64 = 60;
+
+/// Outcome of `SimulationBuilder::run`.
+#[derive(Debug, Clone)]
+pub struct SimulationResult {
+    /// Kernel clock when the run started / finished (ns)
+    pub start_ns: u64,
+    pub end_ns: u64,
+    /// Messages delivered to agents (wakeups included)
+    pub messages: u64,
+    pub summary: RunSummary,
+    /// Every emitted event in order (only with `record_events`)
+    pub events: Vec<SimEvent>,
+}
+
+pub struct SimulationBuilder {
+    name: String,
+    tick_ns: u64,
+    duration_sec: u64,
+    start_ns: Option<u64>,
+    latency: Box<dyn LatencyModel>,
+    agents: Vec<Box<dyn Agent>>,
+    agent_kinds: HashMap<AgentId, String>,
+    listeners: Vec<Box<dyn EventListener>>,
+    logs_dir: Option<PathBuf>,
+    record_events: bool,
+    verbosity: Verbosity,
+}
+
+impl SimulationBuilder {
+    /// 60s run with a 100ms tick and the default fixed latency (1ms network, 0.5ms compute).
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            tick_ns: DEFAULT_TICK_NS,
+            duration_sec: DEFAULT_DURATION_SEC,
+            start_ns: None,
+            latency: Box::new(FixedLatency::new(1_000_000, 500_000)),
+            agents: Vec::new(),
+            agent_kinds: HashMap::new(),
+            listeners: Vec::new(),
+            logs_dir: None,
+            record_events: false,
+            verbosity: Verbosity::default(),
+        }
+    }
+
+    pub fn tick_ms(mut self, tick_ms: u64) -> Self {
+        self.tick_ns = tick_ms.max(1) * 1_000_000;
+        self
+    }
+
+    /// Sim time to run; the run also ends early once no message is pending.
+    pub fn duration_sec(mut self, duration_sec: u64) -> Self {
+        self.duration_sec = duration_sec;
+        self
+    }
+
+    /// Start the clock at a fixed time instead of now (reproducible timestamps).
+    pub fn start_ns(mut self, start_ns: u64) -> Self {
+        self.start_ns = Some(start_ns);
+        self
+    }
+
+    pub fn latency(mut self, latency: Box<dyn LatencyModel>) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Add an agent; agents are registered in the order they were added.
+    pub fn agent(mut self, agent: Box<dyn Agent>) -> Self {
+        self.agents.push(agent);
+        self
+    }
+
+    /// Label an account in the summary (e.g. "market_maker"; default "unknown").
+    pub fn agent_kind(mut self, account: AgentId, kind: &str) -> Self {
+        self.agent_kinds.insert(account, kind.to_string());
+        self
+    }
+
+    /// Add an OracleAgent `id` that polls `provider` every `wake_interval_ms` and sends the
+    /// prices of `symbols` to `exchange_id`.
+    pub fn oracle(
+        self,
+        id: AgentId,
+        exchange_id: AgentId,
+        symbols: Vec<String>,
+        wake_interval_ms: u64,
+        provider: Box<dyn PriceProvider>,
+    ) -> Self {
+        let oracle = OracleAgent::new(
+            id,
+            "Oracle".to_string(),
+            symbols,
+            exchange_id,
+            wake_interval_ms * 1_000_000,
+            provider,
+        );
+        self.agent(Box::new(oracle)).agent_kind(id, "oracle")
+    }
+
+    /// Subscribe a listener to the event bus.
+    pub fn listener(mut self, listener: Box<dyn EventListener>) -> Self {
+        self.listeners.push(listener);
+        self
+    }
+
+    /// Write the order / oracle / execution / position / market CSV logs and the summary to `dir`.
+    pub fn logs_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.logs_dir = Some(dir.into());
+        self
+    }
+
+    /// Keep every emitted event in `SimulationResult::events`.
+    pub fn record_events(mut self) -> Self {
+        self.record_events = true;
+        self
+    }
+
+    /// Console output of the kernel and the agents (default Debug: every line).
+    pub fn verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Suppress the kernel's and the agents' console output.
+    pub fn quiet(self) -> Self {
+        self.verbosity(Verbosity::Quiet)
+    }
+
+    pub fn run(self) -> SimulationResult {
+        let mut engine = SimEngine::new(self.latency, self.tick_ns, self.logs_dir.as_deref());
+        let kernel = &mut engine.kernel;
+        if let Some(start_ns) = self.start_ns {
+            kernel.set_start_time(start_ns);
+        }
+        kernel.set_verbosity(self.verbosity);
+
+        let bus = kernel.event_bus_mut();
+        for listener in self.listeners {
+            bus.subscribe(listener);
+        }
+        let events = Arc::new(Mutex::new(Vec::new()));
+        if self.record_events {
+            bus.subscribe(Box::new(EventRecorder { events: events.clone() }));
+        }
+        let summary = Arc::new(Mutex::new(None));
+        let report_dir = self.logs_dir.clone().unwrap_or_default();
+        bus.subscribe(Box::new(SummaryCollector {
+            report: ReportBuilder::new(report_dir, &self.name).with_agent_kinds(self.agent_kinds),
+            write: self.logs_dir.is_some(),
+            summary: summary.clone(),
+        }));
+
+        for agent in self.agents {
+            kernel.add_agent(agent);
+        }
+
+        let start_ns = kernel.now_ns();
+        let max_ticks = (self.duration_sec * 1_000_000_000 / self.tick_ns) as usize;
+        engine.run(max_ticks);
+        let end_ns = engine.kernel.now_ns();
+        let messages = engine.kernel.delivered();
+
+        let summary = summary.lock().ok().and_then(|mut s| s.take()).unwrap_or_default();
+        let events = events.lock().map(|mut e| std::mem::take(&mut *e)).unwrap_or_default();
+        SimulationResult {
+            start_ns,
+            end_ns,
+            messages,
+            summary,
+            events,
+        }
+    }
+}
+
+/// Builds the run summary for the result (and writes it when a logs dir is set).
+struct SummaryCollector {
+    report: ReportBuilder,
+    write: bool,
+    summary: Arc<Mutex<Option<RunSummary>>>,
+}
+
+impl EventListener for SummaryCollector {
+    fn on_event(&mut self, event: &SimEvent) {
+        self.report.on_event(event);
+    }
+
+    fn on_finish(&mut self, ts: u64) {
+        if self.write {
+            self.report.on_finish(ts);
+        }
+        if let Ok(mut summary) = self.summary.lock() {
+            *summary = Some(self.report.build(ts));
+        }
+    }
+}
+
+struct EventRecorder {
+    events: Arc<Mutex<Vec<SimEvent>>>,
+}
+
+impl EventListener for EventRecorder {
+    fn on_event(&mut self, event: &SimEvent) {
+        if let Ok(mut events) = self.events.lock() {
+            events.push(event.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::Side;
+
+    /// Emits one execution per wakeup, 100ms apart, until `left` runs out.
+    struct Trader {
+        id: AgentId,
+        left: u32,
+    }
+
+    impl Agent for Trader {
+        fn id(&self) -> AgentId {
+            self.id
+        }
+
+        fn name(&self) -> &str {
+            "Trader"
+        }
+
+        fn on_start(&mut self, sim: &mut dyn SimulatorApi) {
+            sim.wakeup(self.id, sim.now_ns() + DEFAULT_TICK_NS);
+        }
+
+        fn on_wakeup(&mut self, sim: &mut dyn SimulatorApi, _now_ns: u64) {
+            sim.emit_event(SimEvent::OrderExecuted {
+                ts: sim.now_ns(),
+                account: self.id,
+                symbol: "ETH-USD".to_string(),
+                side: Side::Buy,
+                size_usd: 1_000_000_000,
+                collateral: 100_000_000,
+                execution_price: 3_000_000_000,
+                leverage: 10,
+                order_type: "Increase".to_string(),
+                pnl: 0,
+                fee_usd: 500_000,
+                funding_fee_usd: 0,
+                decision_price: None,
+            });
+            self.left -= 1;
+            if self.left > 0 {
+                sim.wakeup(self.id, sim.now_ns() + DEFAULT_TICK_NS);
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_returns_summary_and_events() {
+        let result = SimulationBuilder::new("unit")
+            .start_ns(1_000_000_000)
+            .agent(Box::new(Trader { id: 7, left: 3 }))
+            .agent_kind(7, "trader")
+            .record_events()
+            .run();
+
+        assert_eq!(result.start_ns, 1_000_000_000);
+        // Stops once the last wakeup is delivered, long before the 60s duration
+        assert_eq!(result.end_ns, 1_000_000_000 + 3 * DEFAULT_TICK_NS);
+        assert_eq!(result.messages, 3);
+        assert_eq!(result.events.len(), 3);
+        assert_eq!(result.summary.scenario_name, "unit");
+        assert_eq!(result.summary.total_trades, 3);
+        assert_eq!(result.summary.fee_revenue_usd, 1_500_000);
+        assert_eq!(result.summary.agents[0].kind, "trader");
+    }
+
+    #[test]
+    fn test_duration_caps_the_run() {
+        let result = SimulationBuilder::new("capped")
+            .start_ns(0)
+            .tick_ms(1_000)
+            .duration_sec(2)
+            .agent(Box::new(Trader { id: 1, left: 100 }))
+            .quiet()
+            .run();
+        assert_eq!(result.end_ns, 2_000_000_000);
+        assert!(result.events.is_empty());
+        assert_eq!(result.summary.total_trades, 2);
+    }
+}
@@ -0,0 +1,186 @@
+// Synthetic spread of the oracle's min/max band.
+//
+// By default the oracle passes the provider's confidence interval straight
+// through as the min/max price, so execution costs ignore what happens in the
+// simulated market. With a spread model the band is derived from simulated
+// stress instead: a base half-spread widened by the realized volatility of the
+// oracle's own recent mids and by pool utilization (larger OI side / liquidity,
+// from the exchange's MarketState), capped at `max_bps`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::indicators::RealizedVol;
+use crate::messages::MarketStatePayload;
+
+/// Half-spread as a function of market stress, all in bps of the mid.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpreadModel {
+    /// Half-spread of a calm, empty market
+    #[serde(default = "default_base_bps")]
+    pub base_bps: f64,
+    /// Oracle ticks in the realized volatility window
+    #[serde(default = "default_vol_window")]
+    pub vol_window: usize,
+    /// Half-spread added per bp of per-tick realized volatility
+    #[serde(default = "default_vol_factor")]
+    pub vol_factor: f64,
+    /// Half-spread added at 100% utilization (linear below)
+    #[serde(default)]
+    pub utilization_bps: f64,
+    /// Upper bound of the half-spread
+    #[serde(default = "default_max_bps")]
+    pub max_bps: f64,
+}
+
+fn default_base_bps() -> f64 {
+    2.0
+}
+
+fn default_vol_window() -> usize {
+    30
+}
+
+fn default_vol_factor() -> f64 {
+    1.0
+}
+
+fn default_max_bps() -> f64 {
+    200.0
+}
+
+impl Default for SpreadModel {
+    fn default() -> Self {
+        Self {
+            base_bps: default_base_bps(),
+            vol_window: default_vol_window(),
+            vol_factor: default_vol_factor(),
+            utilization_bps: 0.0,
+            max_bps: default_max_bps(),
+        }
+    }
+}
+
+impl SpreadModel {
+    pub fn validate(&self) -> Result<(), String> {
+        let rates = [self.base_bps, self.vol_factor, self.utilization_bps, self.max_bps];
+        if rates.iter().any(|v| !v.is_finite() || *v < 0.0) {
+            return Err("spread parameters must be non-negative numbers".to_string());
+        }
+        if self.vol_window < 2 {
+            return Err("spread.vol_window must be at least 2".to_string());
+        }
+        Ok(())
+    }
+
+    /// Half-spread in bps for a per-tick volatility (None while warming up) and a utilization in [0, 1].
+    pub fn half_spread_bps(&self, vol: Option<f64>, utilization: f64) -> f64 {
+        let vol_bps = vol.unwrap_or(0.0) * 10_000.0;
+        let bps = self.base_bps + self.vol_factor * vol_bps + self.utilization_bps * utilization.clamp(0.0, 1.0);
+        bps.min(self.max_bps)
+    }
+}
+
+/// Stress inputs of one symbol, fed by the oracle's ticks and the exchange's MarketState.
+#[derive(Debug, Clone)]
+pub struct SpreadTracker {
+    model: SpreadModel,
+    vol: RealizedVol,
+    utilization: f64,
+}
+
+impl SpreadTracker {
+    pub fn new(model: SpreadModel) -> Self {
+        Self {
+            model,
+            vol: RealizedVol::new(model.vol_window),
+            utilization: 0.0,
+        }
+    }
+
+    pub fn on_market_state(&mut self, state: &MarketStatePayload) {
+        self.utilization = if state.liquidity_usd > 0 {
+            (state.oi_long_usd.max(state.oi_short_usd) as f64 / state.liquidity_usd as f64).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+    }
+
+    /// Record a new mid and return the (min, max) band around it.
+    pub fn band(&mut self, mid: u64) -> (u64, u64) {
+        let vol = self.vol.update(mid as f64);
+        let half_bps = self.model.half_spread_bps(vol, self.utilization);
+        let width = (mid as f64 * half_bps / 10_000.0).round() as u64;
+        (mid.saturating_sub(width), mid.saturating_add(width))
+    }
+
+    pub fn half_spread_bps(&self) -> f64 {
+        self.model.half_spread_bps(self.vol.value(), self.utilization)
+    }
+
+    pub fn utilization(&self) -> f64 {
+        self.utilization
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(oi_long: i128, oi_short: i128, liquidity: i128) -> MarketStatePayload {
+        MarketStatePayload {
+            symbol: "ETH-USD".to_string(),
+            oi_long_usd: oi_long,
+            oi_short_usd: oi_short,
+            liquidity_usd: liquidity,
+            pool_pnl_usd: 0,
+            funding_rate_bps_hour_fp: 0,
+            borrowing_rate_bps_hour_fp: 0,
+        }
+    }
+
+    #[test]
+    fn test_calm_market_uses_base_spread() {
+        let mut tracker = SpreadTracker::new(SpreadModel::default());
+        // 2 bps of $3000
+        assert_eq!(tracker.band(3_000_000_000), (2_999_400_000, 3_000_600_000));
+        for _ in 0..40 {
+            tracker.band(3_000_000_000);
+        }
+        assert_eq!(tracker.band(3_000_000_000), (2_999_400_000, 3_000_600_000));
+    }
+
+    #[test]
+    fn test_volatility_and_utilization_widen_the_band() {
+        let model = SpreadModel {
+            vol_window: 4,
+            utilization_bps: 50.0,
+            ..SpreadModel::default()
+        };
+        let mut tracker = SpreadTracker::new(model);
+        tracker.on_market_state(&state(800, 200, 1_000));
+        assert_eq!(tracker.utilization(), 0.8);
+        // 2 + 0.8 * 50 while the volatility window fills
+        assert!((tracker.half_spread_bps() - 42.0).abs() < 1e-9);
+        for i in 0..10 {
+            let mid = if i % 2 == 0 { 3_000_000_000 } else { 3_030_000_000 };
+            tracker.band(mid);
+        }
+        assert!(tracker.half_spread_bps() > 42.0);
+        tracker.on_market_state(&state(0, 0, 0));
+        assert_eq!(tracker.utilization(), 0.0);
+    }
+
+    #[test]
+    fn test_spread_is_capped() {
+        let model = SpreadModel {
+            utilization_bps: 1_000.0,
+            max_bps: 100.0,
+            ..SpreadModel::default()
+        };
+        assert_eq!(model.half_spread_bps(Some(0.5), 1.0), 100.0);
+        assert!(model.validate().is_ok());
+        let bad = SpreadModel { vol_window: 1, ..model };
+        assert!(bad.validate().is_err());
+    }
+}
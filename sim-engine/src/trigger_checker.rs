@@ -47,6 +47,10 @@ fn check_trigger_condition(
         (ExecutionType::StopLoss, OrderType::Decrease, Side::Buy) => price.min <= trigger,
         (ExecutionType::StopLoss, OrderType::Decrease, Side::Sell) => price.max >= trigger,
 
+        // TRAILING STOP (Decrease only) — triggers like a stop loss at the current trail
+        (ExecutionType::TrailingStop, OrderType::Decrease, Side::Buy) => price.min <= trigger,
+        (ExecutionType::TrailingStop, OrderType::Decrease, Side::Sell) => price.max >= trigger,
+
         // TAKE PROFIT (Decrease only)
         (ExecutionType::TakeProfit, OrderType::Decrease, Side::Buy) => price.min >= trigger,
         (ExecutionType::TakeProfit, OrderType::Decrease, Side::Sell) => price.max <= trigger,
@@ -59,6 +63,50 @@ fn check_trigger_condition(
     }
 }
 
+/// Which way the price has to move to reach an order's trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerDirection {
+    /// Triggered once the price falls to the trigger or below
+    Falling,
+    /// Triggered once the price rises to the trigger or above
+    Rising,
+}
+
+/// Direction of a conditional order; None for combinations that never trigger.
+/// Mirrors `check_trigger_condition`.
+pub fn trigger_direction(exec_type: ExecutionType, order_type: OrderType, side: Side) -> Option<TriggerDirection> {
+    match (exec_type, order_type, side) {
+        (ExecutionType::Limit, OrderType::Increase, Side::Buy)
+        | (ExecutionType::Limit, OrderType::Decrease, Side::Sell)
+        | (ExecutionType::StopLoss, OrderType::Decrease, Side::Buy)
+        | (ExecutionType::TrailingStop, OrderType::Decrease, Side::Buy)
+        | (ExecutionType::TakeProfit, OrderType::Decrease, Side::Sell) => Some(TriggerDirection::Falling),
+
+        (ExecutionType::Limit, OrderType::Increase, Side::Sell)
+        | (ExecutionType::Limit, OrderType::Decrease, Side::Buy)
+        | (ExecutionType::StopLoss, OrderType::Decrease, Side::Sell)
+        | (ExecutionType::TrailingStop, OrderType::Decrease, Side::Sell)
+        | (ExecutionType::TakeProfit, OrderType::Decrease, Side::Buy) => Some(TriggerDirection::Rising),
+
+        _ => None,
+    }
+}
+
+/// Trailing stop trigger after a new price: `distance` below the price for longs (only
+/// moves up), above it for shorts (only moves down). `current` is the previous trigger.
+pub fn trail_trigger(side: Side, distance: u64, current: Option<u64>, price: &Price) -> u64 {
+    match side {
+        Side::Buy => {
+            let candidate = price.min.saturating_sub(distance);
+            current.map_or(candidate, |t| t.max(candidate))
+        }
+        Side::Sell => {
+            let candidate = price.max.saturating_add(distance);
+            current.map_or(candidate, |t| t.min(candidate))
+        }
+    }
+}
+
 #[allow(dead_code)]
 pub fn passes_slippage_check(order: &PendingOrder, execution_price: u64) -> bool {
     match order.payload.acceptable_price {
@@ -69,3 +117,69 @@ pub fn passes_slippage_check(order: &PendingOrder, execution_price: u64) -> bool
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(p: u64) -> Price {
+        Price { min: p, max: p }
+    }
+
+    #[test]
+    fn test_trail_trigger_ratchets() {
+        // Long: follows the price up, never down
+        let t = trail_trigger(Side::Buy, 100, None, &at(3000));
+        assert_eq!(t, 2900);
+        let t = trail_trigger(Side::Buy, 100, Some(t), &at(3200));
+        assert_eq!(t, 3100);
+        let t = trail_trigger(Side::Buy, 100, Some(t), &at(3150));
+        assert_eq!(t, 3100);
+
+        // Short: follows the price down, never up
+        let t = trail_trigger(Side::Sell, 100, None, &at(3000));
+        assert_eq!(t, 3100);
+        let t = trail_trigger(Side::Sell, 100, Some(t), &at(2800));
+        assert_eq!(t, 2900);
+        let t = trail_trigger(Side::Sell, 100, Some(t), &at(2850));
+        assert_eq!(t, 2900);
+    }
+
+    #[test]
+    fn test_trailing_stop_triggers_like_stop_loss() {
+        let check = |side, trigger, price| {
+            check_trigger_condition(ExecutionType::TrailingStop, OrderType::Decrease, side, trigger, &at(price))
+        };
+        assert!(check(Side::Buy, 3100, 3099));
+        assert!(!check(Side::Buy, 3100, 3101));
+        assert!(check(Side::Sell, 2900, 2901));
+        assert!(!check(Side::Sell, 2900, 2899));
+    }
+
+    #[test]
+    fn test_trigger_direction_matches_condition() {
+        let exec_types = [
+            ExecutionType::Market,
+            ExecutionType::Limit,
+            ExecutionType::StopLoss,
+            ExecutionType::TakeProfit,
+            ExecutionType::TrailingStop,
+        ];
+        for exec_type in exec_types {
+            for order_type in [OrderType::Increase, OrderType::Decrease] {
+                for side in [Side::Buy, Side::Sell] {
+                    let check = |price| check_trigger_condition(exec_type, order_type, side, 3000, &at(price));
+                    let expected = match (check(2999), check(3001)) {
+                        (true, false) => Some(TriggerDirection::Falling),
+                        (false, true) => Some(TriggerDirection::Rising),
+                        _ => None,
+                    };
+                    assert_eq!(trigger_direction(exec_type, order_type, side), expected);
+                    if expected.is_some() {
+                        assert!(check(3000));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,450 @@
+// Live terminal dashboard for realtime runs (--tui).
+//
+// A `TuiListener` on the EventBus keeps a compact dashboard state; a separate
+// render thread draws it with ratatui every 250ms. While the dashboard owns the
+// terminal, stdout/stderr of the engine are redirected to logs/stdout.log.
+// 'q' or Ctrl+C asks the kernel to stop, so the run ends through its normal
+// shutdown and writes its reports; dropping the `TuiHandle` afterwards ends the
+// render thread and gives the terminal back.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table};
+use ratatui::{Frame, Terminal};
+
+use crate::events::{EventListener, SimEvent};
+use crate::messages::{AgentId, Side};
+
+const MAX_EXECUTIONS: usize = 15;
+const MAX_POSITIONS: usize = 15;
+const REFRESH_MS: u64 = 250;
+
+#[derive(Clone)]
+struct PositionRow {
+    ts: u64,
+    symbol: String,
+    size_usd: u64,
+    entry_price: u64,
+    unrealized_pnl: i64,
+    leverage: u32,
+}
+
+#[derive(Clone)]
+struct ExecutionRow {
+    account: AgentId,
+    symbol: String,
+    side: Side,
+    order_type: String,
+    size_usd: u64,
+    price: u64,
+    pnl: i64,
+}
+
+#[derive(Default)]
+pub struct DashboardState {
+    scenario_name: String,
+    last_ts: u64,
+    /// symbol -> (price_min, price_max)
+    prices: BTreeMap<String, (u64, u64)>,
+    /// symbol -> (oi_long, oi_short, liquidity)
+    markets: BTreeMap<String, (u64, u64, u64)>,
+    positions: HashMap<(AgentId, Side), PositionRow>,
+    executions: VecDeque<ExecutionRow>,
+    confirmed_txs: u64,
+    last_confirmed_ts: u64,
+    liquidations: u64,
+}
+
+/// Shared dashboard state (listener writes, render thread reads).
+pub type SharedDashboard = Arc<Mutex<DashboardState>>;
+
+pub fn new_dashboard(scenario_name: &str) -> SharedDashboard {
+    Arc::new(Mutex::new(DashboardState {
+        scenario_name: scenario_name.to_string(),
+        ..Default::default()
+    }))
+}
+
+pub struct TuiListener {
+    state: SharedDashboard,
+}
+
+impl TuiListener {
+    pub fn new(state: SharedDashboard) -> Self {
+        Self { state }
+    }
+}
+
+impl EventListener for TuiListener {
+    fn on_event(&mut self, event: &SimEvent) {
+        let mut st = match self.state.lock() {
+            Ok(st) => st,
+            Err(_) => return,
+        };
+        st.last_ts = event.ts();
+
+        match event {
+            SimEvent::OracleTick {
+                symbol,
+                price_min,
+                price_max,
+                ..
+            } => {
+                st.prices.insert(symbol.clone(), (*price_min, *price_max));
+            }
+
+            SimEvent::MarketSnapshot {
+                ts,
+                symbol,
+                oi_long_usd,
+                oi_short_usd,
+                liquidity_usd,
+                ..
+            } => {
+                st.markets
+                    .insert(symbol.clone(), (*oi_long_usd, *oi_short_usd, *liquidity_usd));
+                // Positions missing from the latest snapshot batch are closed
+                st.positions.retain(|_, p| p.ts >= *ts);
+            }
+
+            SimEvent::PositionSnapshot {
+                ts,
+                account,
+                symbol,
+                side,
+                size_usd,
+                entry_price,
+                unrealized_pnl,
+                leverage_actual,
+                ..
+            } => {
+                st.positions.insert(
+                    (*account, *side),
+                    PositionRow {
+                        ts: *ts,
+                        symbol: symbol.clone(),
+                        size_usd: *size_usd,
+                        entry_price: *entry_price,
+                        unrealized_pnl: *unrealized_pnl,
+                        leverage: *leverage_actual,
+                    },
+                );
+            }
+
+            SimEvent::OrderExecuted {
+                ts,
+                account,
+                symbol,
+                side,
+                size_usd,
+                execution_price,
+                order_type,
+                pnl,
+                ..
+            } => {
+                st.confirmed_txs += 1;
+                st.last_confirmed_ts = *ts;
+                st.executions.push_front(ExecutionRow {
+                    account: *account,
+                    symbol: symbol.clone(),
+                    side: *side,
+                    order_type: order_type.clone(),
+                    size_usd: *size_usd,
+                    price: *execution_price,
+                    pnl: *pnl,
+                });
+                st.executions.truncate(MAX_EXECUTIONS);
+            }
+
+            SimEvent::PositionLiquidated { account, side, .. } => {
+                st.liquidations += 1;
+                st.positions.remove(&(*account, *side));
+            }
+
+            SimEvent::OrderLog { .. }
+            | SimEvent::RiskReport { .. }
+            | SimEvent::RiskAlert { .. }
+            | SimEvent::RiskLimitBreached { .. }
+            | SimEvent::AgentBankrupt { .. }
+            | SimEvent::AgentRecapitalized { .. }
+            | SimEvent::OrderExpired { .. }
+            | SimEvent::ChainTx { .. }
+            | SimEvent::SpotSwap { .. }
+            | SimEvent::FeeAccrued { .. }
+            | SimEvent::InsuranceFundChanged { .. }
+            | SimEvent::InboxBacklog { .. }
+            | SimEvent::OracleFailover { .. }
+            | SimEvent::DeadLetter { .. }
+            | SimEvent::CircuitBreakerTripped { .. }
+            | SimEvent::TickOverrun { .. }
+            | SimEvent::LocalFillAbandoned { .. } => {}
+        }
+    }
+}
+
+fn usd(micro: i64) -> String {
+    format!("${:.2}", micro as f64 / 1_000_000.0)
+}
+
+fn side_str(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "LONG",
+        Side::Sell => "SHORT",
+    }
+}
+
+fn render(f: &mut Frame, st: &DashboardState) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(8),
+            Constraint::Min(8),
+            Constraint::Min(8),
+        ])
+        .split(f.area());
+
+    let header_style = Style::default().add_modifier(Modifier::BOLD);
+
+    // Title / Vara status
+    let title = Paragraph::new(format!(
+        "{}  |  sim t={}s  |  confirmed txs: {}  last confirm: {}s ago  |  liquidations: {}  |  q: quit",
+        st.scenario_name,
+        st.last_ts / 1_000_000_000,
+        st.confirmed_txs,
+        if st.last_confirmed_ts > 0 {
+            (st.last_ts.saturating_sub(st.last_confirmed_ts) / 1_000_000_000).to_string()
+        } else {
+            "-".to_string()
+        },
+        st.liquidations,
+    ))
+    .block(Block::default().title("perp-sim").borders(Borders::ALL));
+    f.render_widget(title, rows[0]);
+
+    // Prices and markets side by side
+    let top = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(rows[1]);
+
+    let price_rows = st.prices.iter().map(|(symbol, (min, max))| {
+        Row::new(vec![
+            symbol.clone(),
+            usd(((min + max) / 2) as i64),
+            usd(max.saturating_sub(*min) as i64),
+        ])
+    });
+    let prices = Table::new(
+        price_rows,
+        [Constraint::Length(10), Constraint::Length(14), Constraint::Length(10)],
+    )
+    .header(Row::new(vec!["symbol", "mid", "spread"]).style(header_style))
+    .block(Block::default().title("Prices").borders(Borders::ALL));
+    f.render_widget(prices, top[0]);
+
+    let market_rows = st.markets.iter().map(|(symbol, (long, short, liq))| {
+        Row::new(vec![
+            symbol.clone(),
+            usd(*long as i64),
+            usd(*short as i64),
+            usd(*long as i64 - *short as i64),
+            usd(*liq as i64),
+        ])
+    });
+    let markets = Table::new(
+        market_rows,
+        [
+            Constraint::Length(10),
+            Constraint::Length(14),
+            Constraint::Length(14),
+            Constraint::Length(14),
+            Constraint::Length(16),
+        ],
+    )
+    .header(Row::new(vec!["symbol", "OI long", "OI short", "imbalance", "liquidity"]).style(header_style))
+    .block(Block::default().title("Open interest").borders(Borders::ALL));
+    f.render_widget(markets, top[1]);
+
+    // Top positions by size
+    let mut positions: Vec<_> = st.positions.iter().collect();
+    positions.sort_by(|a, b| b.1.size_usd.cmp(&a.1.size_usd));
+    let position_rows = positions.into_iter().take(MAX_POSITIONS).map(|((account, side), p)| {
+        let pnl_style = if p.unrealized_pnl >= 0 {
+            Style::default().fg(Color::Green)
+        } else {
+            Style::default().fg(Color::Red)
+        };
+        Row::new(vec![
+            account.to_string(),
+            p.symbol.clone(),
+            side_str(*side).to_string(),
+            usd(p.size_usd as i64),
+            usd(p.entry_price as i64),
+            format!("{}x", p.leverage),
+            usd(p.unrealized_pnl),
+        ])
+        .style(pnl_style)
+    });
+    let positions = Table::new(
+        position_rows,
+        [
+            Constraint::Length(6),
+            Constraint::Length(10),
+            Constraint::Length(6),
+            Constraint::Length(14),
+            Constraint::Length(12),
+            Constraint::Length(5),
+            Constraint::Length(12),
+        ],
+    )
+    .header(Row::new(vec!["agent", "symbol", "side", "size", "entry", "lev", "uPnL"]).style(header_style))
+    .block(Block::default().title("Top positions").borders(Borders::ALL));
+    f.render_widget(positions, rows[2]);
+
+    // Recent executions
+    let exec_rows = st.executions.iter().map(|e| {
+        Row::new(vec![
+            e.account.to_string(),
+            e.symbol.clone(),
+            side_str(e.side).to_string(),
+            e.order_type.clone(),
+            usd(e.size_usd as i64),
+            usd(e.price as i64),
+            usd(e.pnl),
+        ])
+    });
+    let executions = Table::new(
+        exec_rows,
+        [
+            Constraint::Length(6),
+            Constraint::Length(10),
+            Constraint::Length(6),
+            Constraint::Length(10),
+            Constraint::Length(14),
+            Constraint::Length(12),
+            Constraint::Length(12),
+        ],
+    )
+    .header(Row::new(vec!["agent", "symbol", "side", "type", "size", "price", "pnl"]).style(header_style))
+    .block(Block::default().title("Recent executions").borders(Borders::ALL));
+    f.render_widget(executions, rows[3]);
+}
+
+/// Redirect the process stdout/stderr into `path` so log lines don't tear the dashboard.
+#[cfg(unix)]
+pub fn redirect_output(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = std::fs::File::create(path)?;
+    let fd = file.as_raw_fd();
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+    // SAFETY: fd is a valid open descriptor; dup2 only replaces fds 1 and 2.
+    unsafe {
+        if libc::dup2(fd, libc::STDOUT_FILENO) < 0 || libc::dup2(fd, libc::STDERR_FILENO) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn redirect_output(_path: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "output redirection is only supported on unix",
+    ))
+}
+
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    if let Ok(mut tty) = open_tty() {
+        let _ = execute!(tty, LeaveAlternateScreen);
+    }
+}
+
+fn open_tty() -> std::io::Result<std::fs::File> {
+    std::fs::OpenOptions::new().write(true).open("/dev/tty")
+}
+
+/// Render thread of the dashboard; dropping it (or `join`) ends the thread and restores the terminal.
+pub struct TuiHandle {
+    done: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TuiHandle {
+    pub fn join(mut self) {
+        self.finish();
+    }
+
+    fn finish(&mut self) {
+        self.done.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for TuiHandle {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// Start the render thread. Draws directly to /dev/tty; 'q' or Ctrl+C sets `stop`
+/// (the kernel's `stop_handle`).
+pub fn spawn(state: SharedDashboard, stop: Arc<AtomicBool>) -> std::io::Result<TuiHandle> {
+    let mut tty = open_tty()?;
+    enable_raw_mode()?;
+    execute!(tty, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(tty))?;
+    let done = Arc::new(AtomicBool::new(false));
+    let finished = done.clone();
+
+    let thread = std::thread::spawn(move || {
+        while !finished.load(Ordering::Relaxed) {
+            {
+                let st = match state.lock() {
+                    Ok(st) => st,
+                    Err(_) => break,
+                };
+                if let Err(e) = terminal.draw(|f| render(f, &st)) {
+                    eprintln!("[Tui] draw failed: {}", e);
+                    break;
+                }
+            }
+
+            if let Ok(true) = event::poll(Duration::from_millis(REFRESH_MS)) {
+                if let Ok(Event::Key(key)) = event::read() {
+                    let quit = key.code == KeyCode::Char('q')
+                        || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL));
+                    if quit {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+        restore_terminal();
+    });
+    Ok(TuiHandle {
+        done,
+        thread: Some(thread),
+    })
+}
@@ -1,5 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use gclient::{GearApi, WSAddress};
 use primitive_types::U256;
@@ -30,57 +32,63 @@ macro_rules! query {
 
 /// Macro to run a fire-and-forget transaction on the bounded blocking thread pool.
 /// Handles the common shell: inner clone, agent lock, spawn_blocking, runtime build,
-/// keypair load, error reporting. The body receives (keypair, api, contract_id, gas_limit, tx_sender).
+/// keypair load, error reporting, the agent's tx budget and fee measurement. The body receives
+/// (keypair, api, contract_id, gas_limit, tip, reporter); the tip of the tx type comes from
+/// `VaraConfig::tips`.
 ///
 /// Usage:
 /// ```ignore
-/// fire_and_forget!(self, agent_id, TxType::SubmitOrder, |kp, api, cid, gas, tx| {
-///     // ... async code using kp, api, cid, gas; send result via tx ...
+/// fire_and_forget!(self, agent_id, TxType::SubmitOrder, |kp, api, cid, gas, tip, tx| {
+///     // ... async code using kp, api, cid, gas, tip; send result via tx ...
 /// });
 /// ```
 macro_rules! fire_and_forget {
-    ($self:expr, $agent_id:expr, $tx_type:expr, $( $captures:ident ),* , |$kp:ident, $api:ident, $cid:ident, $gas:ident, $tx:ident| $body:expr) => {{
+    ($self:expr, $agent_id:expr, $tx_type:expr, $( $captures:ident ),* , |$kp:ident, $api:ident, $cid:ident, $gas:ident, $tip:ident, $tx:ident| $body:expr) => {{
         let inner = $self.inner_ref()?.clone();
         let lock = $self.agent_lock($agent_id);
-        let tx_sender = $self.tx_result_tx.clone();
+        let $tip = $self.config.tips.for_tx(&$tx_type);
+        let reporter = $self.reporter($agent_id, $tx_type, $tip);
         let agent_id = $agent_id;
+        let spent = $self.spent.clone();
         $( let $captures = $captures; )*
 
-        $self.runtime.handle().spawn_blocking(move || {
-            let _guard = lock.lock().unwrap();
-            let rt = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
-                Ok(rt) => rt,
-                Err(e) => {
-                    eprintln!("[Vara] {}: runtime error: {}", stringify!($tx_type), e);
-                    let _ = tx_sender.send(TxResult {
-                        agent_id, tx_type: $tx_type, success: false,
-                        order_id: None, error: Some(e.to_string()),
-                        detail: "runtime build error".into(),
-                    });
-                    return;
-                }
-            };
-            rt.block_on(async move {
-                let ($kp, $api, $cid, $gas) = {
-                    let guard = inner.read().await;
-                    let kp = match guard.keystore.load_keypair_for_agent(agent_id) {
-                        Ok(kp) => kp.clone(),
-                        Err(e) => {
-                            eprintln!("[Vara] {}: keypair error: {}", stringify!($tx_type), e);
-                            let _ = tx_sender.send(TxResult {
-                                agent_id, tx_type: $tx_type, success: false,
-                                order_id: None, error: Some(e.to_string()),
-                                detail: "keypair error".into(),
-                            });
-                            return;
-                        }
-                    };
-                    (kp, guard.api.clone(), guard.contract_id, guard.gas_limits)
+        if let Some(reason) = $self.budget_exhausted(agent_id) {
+            reporter.send(false, None, Some(reason), "over budget".into());
+            reporter.release(None);
+        } else {
+            $self.runtime.handle().spawn_blocking(move || {
+                let _guard = lock.lock().unwrap();
+                let rt = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                    Ok(rt) => rt,
+                    Err(e) => {
+                        eprintln!("[Vara] {}: runtime error: {}", stringify!($tx_type), e);
+                        reporter.send(false, None, Some(e.to_string()), "runtime build error".into());
+                        reporter.release(None);
+                        return;
+                    }
                 };
-                let $tx = tx_sender;
-                $body
+                rt.block_on(async move {
+                    let ($kp, $api, $cid, $gas) = {
+                        let guard = inner.read().await;
+                        let kp = match guard.keystore.load_keypair_for_agent(agent_id) {
+                            Ok(kp) => kp.clone(),
+                            Err(e) => {
+                                eprintln!("[Vara] {}: keypair error: {}", stringify!($tx_type), e);
+                                reporter.send(false, None, Some(e.to_string()), "keypair error".into());
+                                reporter.release(None);
+                                return;
+                            }
+                        };
+                        (kp, guard.api.clone(), guard.contract_id, guard.gas_limits)
+                    };
+                    let probe = FeeProbe::start(reporter.is_held(), &$api, &$kp.address).await;
+                    let $tx = &reporter;
+                    async { $body }.await;
+                    let fee = probe.finish(agent_id, &spent).await;
+                    reporter.release(fee);
+                });
             });
-        });
+        }
     }};
 }
 
@@ -89,9 +97,13 @@ use super::generated::VaraPerpsProgram;
 use super::generated::vara_perps::VaraPerps as _VaraPerpsServiceTrait; // trait must be in scope for service methods
 use super::keystore::{KeystoreError, KeystoreManager};
 use super::types::{
-    ActorId, LiquidationPreview, OracleInput, Order, OrderId, Position, PositionKey,
+    ActorId, InitConfig, LiquidationPreview, OracleInput, Order, OrderId, Position, PositionKey,
     Side as VaraSide,
 };
+use crate::scale::Usd1e30;
+use crate::scenarios::population::Rng;
+use parity_scale_codec::Encode;
+use serde::{Deserialize, Serialize};
 
 // ========== Transaction Result Feedback ==========
 
@@ -102,6 +114,8 @@ pub enum TxType {
     ExecuteOrder,
     CancelOrder,
     SubmitAndExecute,
+    UpdateOrder,
+    Deposit,
 }
 
 impl std::fmt::Display for TxType {
@@ -111,6 +125,54 @@ impl std::fmt::Display for TxType {
             Self::ExecuteOrder => write!(f, "ExecuteOrder"),
             Self::CancelOrder => write!(f, "CancelOrder"),
             Self::SubmitAndExecute => write!(f, "SubmitAndExecute"),
+            Self::UpdateOrder => write!(f, "UpdateOrder"),
+            Self::Deposit => write!(f, "Deposit"),
+        }
+    }
+}
+
+/// Priority tip per transaction type, in the chain's smallest unit (0 = no tip).
+/// Lets keepers outbid other actors for block space during congestion experiments.
+/// Reported with every TxResult; the pinned sails/gclient clients do not expose the
+/// extrinsic tip, so it is not signed into the transaction yet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TxTips {
+    pub submit_order: u128,
+    pub execute_order: u128,
+    pub cancel_order: u128,
+    pub submit_and_execute: u128,
+    pub update_order: u128,
+    pub deposit: u128,
+}
+
+impl TxTips {
+    /// From VARA_TIP_SUBMIT_ORDER, VARA_TIP_EXECUTE_ORDER, ... (unset = 0)
+    pub fn from_env() -> Self {
+        let tip = |name: &str| {
+            std::env::var(format!("VARA_TIP_{}", name))
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0)
+        };
+        Self {
+            submit_order: tip("SUBMIT_ORDER"),
+            execute_order: tip("EXECUTE_ORDER"),
+            cancel_order: tip("CANCEL_ORDER"),
+            submit_and_execute: tip("SUBMIT_AND_EXECUTE"),
+            update_order: tip("UPDATE_ORDER"),
+            deposit: tip("DEPOSIT"),
+        }
+    }
+
+    pub fn for_tx(&self, tx_type: &TxType) -> u128 {
+        match tx_type {
+            TxType::SubmitOrder => self.submit_order,
+            TxType::ExecuteOrder => self.execute_order,
+            TxType::CancelOrder => self.cancel_order,
+            TxType::SubmitAndExecute => self.submit_and_execute,
+            TxType::UpdateOrder => self.update_order,
+            TxType::Deposit => self.deposit,
         }
     }
 }
@@ -131,6 +193,155 @@ pub struct TxResult {
     pub error: Option<String>,
     /// Human-readable detail for logging
     pub detail: String,
+    /// Wall-clock time from queueing the transaction to its result
+    pub latency_ms: u64,
+    /// Priority tip offered with the transaction
+    pub tip: u128,
+    /// Native fee the transaction cost its account (with `VaraConfig::tx_spend`)
+    pub fee: Option<u128>,
+    /// Client-wide id of the transaction; a revert carries the id of the result it undoes
+    pub tx_id: u64,
+    /// Follow-up of an earlier successful result: a reorg dropped the transaction
+    pub reverted: bool,
+}
+
+/// Simulated chain reorgs (testnet fault injection): a successful fire-and-forget
+/// transaction is, with `probability`, reported a second time `depth_blocks` blocks
+/// later as reverted. Only the result is injected; the transaction stays on chain.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReorgConfig {
+    /// Chance that a successful transaction is reverted (0..1)
+    pub probability: f64,
+    /// Blocks between the success and the revert
+    pub depth_blocks: u32,
+    /// Seed of the revert draws (same seed, same reverted tx ids)
+    pub seed: u64,
+}
+
+impl Default for ReorgConfig {
+    fn default() -> Self {
+        Self {
+            probability: 0.0,
+            depth_blocks: 3,
+            seed: 0,
+        }
+    }
+}
+
+/// Error of a transaction refused because its agent spent its budget
+pub const TX_BUDGET_EXHAUSTED: &str = "tx budget exhausted";
+
+/// Fee tracking of fire-and-forget transactions (keeper economics on testnet). The fee of a
+/// transaction is the drop of its account's free balance across it, which the per-account tx
+/// lock keeps to that transaction (a top-up arriving meanwhile hides the fee). Once an agent's
+/// fees reach its budget, its new transactions fail with `TX_BUDGET_EXHAUSTED` unsent.
+/// Amounts in the chain's smallest unit.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TxSpendConfig {
+    /// Budget of every agent without its own (None = unlimited)
+    pub default_budget: Option<u128>,
+    /// Budgets per agent id
+    pub budgets: BTreeMap<u32, u128>,
+}
+
+impl TxSpendConfig {
+    pub fn budget(&self, agent_id: u32) -> Option<u128> {
+        self.budgets.get(&agent_id).copied().or(self.default_budget)
+    }
+}
+
+/// Free balance of an account before its transaction, for the fee measured after it.
+struct FeeProbe {
+    before: Option<(GearApi, String, u128)>,
+}
+
+impl FeeProbe {
+    /// Read the balance if the fee is wanted (`active`); a failed read skips the measurement.
+    async fn start(active: bool, api: &GearApi, address: &str) -> Self {
+        if !active {
+            return Self { before: None };
+        }
+        let before = api.free_balance(address).await.ok();
+        Self {
+            before: before.map(|b| (api.clone(), address.to_string(), b)),
+        }
+    }
+
+    /// Fee of the transaction, added to the agent's total in `spent`.
+    async fn finish(self, agent_id: u32, spent: &Mutex<HashMap<u32, u128>>) -> Option<u128> {
+        let (api, address, before) = self.before?;
+        let after = api.free_balance(address.as_str()).await.ok()?;
+        let fee = before.saturating_sub(after);
+        *spent.lock().unwrap().entry(agent_id).or_default() += fee;
+        Some(fee)
+    }
+}
+
+/// Reports the result of one fire-and-forget transaction, timed from its creation.
+struct TxReporter {
+    sender: crossbeam_channel::Sender<TxResult>,
+    agent_id: u32,
+    tx_type: TxType,
+    tip: u128,
+    tx_id: u64,
+    /// Simulated reorg: depth in blocks and the delay of the revert after a success
+    revert: Option<(u32, Duration)>,
+    /// With fee tracking the result waits here for the fee (`release`)
+    held: Option<Mutex<Option<TxResult>>>,
+    started: std::time::Instant,
+}
+
+impl TxReporter {
+    fn send(&self, success: bool, order_id: Option<u64>, error: Option<String>, detail: String) {
+        let result = TxResult {
+            agent_id: self.agent_id,
+            tx_type: self.tx_type.clone(),
+            success,
+            order_id,
+            error,
+            detail,
+            latency_ms: self.started.elapsed().as_millis() as u64,
+            tip: self.tip,
+            fee: None,
+            tx_id: self.tx_id,
+            reverted: false,
+        };
+        match &self.held {
+            Some(held) => *held.lock().unwrap() = Some(result),
+            None => self.dispatch(result),
+        }
+    }
+
+    fn is_held(&self) -> bool {
+        self.held.is_some()
+    }
+
+    /// Send the held result with the fee measured for it.
+    fn release(&self, fee: Option<u128>) {
+        if let Some(result) = self.held.as_ref().and_then(|held| held.lock().unwrap().take()) {
+            self.dispatch(TxResult { fee, ..result });
+        }
+    }
+
+    fn dispatch(&self, result: TxResult) {
+        if let (true, Some((depth, delay))) = (result.success, self.revert) {
+            let sender = self.sender.clone();
+            let reverted = TxResult {
+                success: false,
+                error: Some(format!("reverted by a {}-block reorg", depth)),
+                fee: None,
+                reverted: true,
+                ..result.clone()
+            };
+            std::thread::spawn(move || {
+                std::thread::sleep(delay);
+                let _ = sender.send(reverted);
+            });
+        }
+        let _ = self.sender.send(result);
+    }
 }
 
 /// Error type for Vara client operations
@@ -192,13 +403,30 @@ pub struct VaraConfig {
     pub block_time_ms: u64,
     /// Gas limit for transactions
     pub gas_limit: u64,
+    /// Explicit agent_id -> key name mapping (default: bot_NNN by normalized id)
+    pub agent_keys: HashMap<u32, String>,
+    /// Priority tip per fire-and-forget transaction type
+    pub tips: TxTips,
+    /// Simulated reorgs reverting successful transactions (off by default)
+    pub reorg: Option<ReorgConfig>,
+    /// Fee tracking and per-agent budgets (off by default)
+    pub tx_spend: Option<TxSpendConfig>,
 }
 
 impl VaraConfig {
     /// Load configuration from environment variables
     pub fn from_env() -> Result<Self, VaraError> {
-        let contract_address = std::env::var("VARA_CONTRACT_ADDRESS")
-            .map_err(|_| VaraError::Config("VARA_CONTRACT_ADDRESS not set".to_string()))?;
+        Self::from_env_with_contract(None)
+    }
+
+    /// Load configuration from environment variables; `contract_address` (e.g. from a
+    /// scenario file) takes precedence over VARA_CONTRACT_ADDRESS
+    pub fn from_env_with_contract(contract_address: Option<String>) -> Result<Self, VaraError> {
+        let contract_address = match contract_address {
+            Some(address) => address,
+            None => std::env::var("VARA_CONTRACT_ADDRESS")
+                .map_err(|_| VaraError::Config("VARA_CONTRACT_ADDRESS not set".to_string()))?,
+        };
 
         let ws_endpoint =
             std::env::var("VARA_WS_ENDPOINT").unwrap_or_else(|_| "wss://testnet.vara.network".to_string());
@@ -242,6 +470,10 @@ impl VaraConfig {
             passphrase_path,
             block_time_ms,
             gas_limit,
+            agent_keys: HashMap::new(),
+            tips: TxTips::from_env(),
+            reorg: None,
+            tx_spend: None,
         })
     }
 
@@ -259,6 +491,10 @@ impl VaraConfig {
             passphrase_path: passphrase_path.into(),
             block_time_ms: 3000,
             gas_limit: 100_000_000_000,
+            agent_keys: HashMap::new(),
+            tips: TxTips::default(),
+            reorg: None,
+            tx_spend: None,
         }
     }
 }
@@ -315,11 +551,15 @@ pub struct VaraClient {
     /// Connection status
     connected: bool,
     /// Per-agent mutexes to serialize txs from the same account (prevents nonce collisions)
-    agent_locks: Arc<Mutex<HashMap<u32, Arc<Mutex<()>>>>>,
+    agent_locks: Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>,
     /// Channel sender for reporting fire-and-forget transaction results
     tx_result_tx: crossbeam_channel::Sender<TxResult>,
     /// Channel receiver (taken once by ExchangeAgent)
     tx_result_rx: Mutex<Option<crossbeam_channel::Receiver<TxResult>>>,
+    /// Id of the next fire-and-forget transaction
+    next_tx_id: AtomicU64,
+    /// Measured fees per agent (with `VaraConfig::tx_spend`)
+    spent: Arc<Mutex<HashMap<u32, u128>>>,
 }
 
 impl VaraClient {
@@ -342,6 +582,8 @@ impl VaraClient {
             agent_locks: Arc::new(Mutex::new(HashMap::new())),
             tx_result_tx,
             tx_result_rx: Mutex::new(Some(tx_result_rx)),
+            next_tx_id: AtomicU64::new(0),
+            spent: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
@@ -357,13 +599,48 @@ impl VaraClient {
         Self::new(config)
     }
 
+    /// Reporter of a new fire-and-forget transaction; draws whether a simulated reorg reverts it.
+    fn reporter(&self, agent_id: u32, tx_type: TxType, tip: u128) -> TxReporter {
+        let tx_id = self.next_tx_id.fetch_add(1, Ordering::Relaxed);
+        let revert = self
+            .config
+            .reorg
+            .filter(|reorg| Rng::new(reorg.seed.wrapping_add(tx_id)).next_f64() < reorg.probability)
+            .map(|reorg| {
+                let delay_ms = reorg.depth_blocks as u64 * self.config.block_time_ms;
+                (reorg.depth_blocks, Duration::from_millis(delay_ms))
+            });
+        TxReporter {
+            sender: self.tx_result_tx.clone(),
+            agent_id,
+            tx_type,
+            tip,
+            tx_id,
+            revert,
+            held: self.config.tx_spend.as_ref().map(|_| Mutex::new(None)),
+            started: std::time::Instant::now(),
+        }
+    }
+
+    /// Fees measured so far per agent (empty without `tx_spend`).
+    pub fn spent_fees(&self) -> HashMap<u32, u128> {
+        self.spent.lock().unwrap().clone()
+    }
+
+    /// Refusal of a new transaction of `agent_id` whose fees reached its budget.
+    fn budget_exhausted(&self, agent_id: u32) -> Option<String> {
+        let budget = self.config.tx_spend.as_ref()?.budget(agent_id)?;
+        let spent = self.spent.lock().unwrap().get(&agent_id).copied().unwrap_or(0);
+        (spent >= budget).then(|| format!("{}: spent {} of {}", TX_BUDGET_EXHAUSTED, spent, budget))
+    }
+
     /// Get or create a per-agent mutex to serialize txs from the same keypair.
-    /// Normalizes agent_id so that different IDs mapping to the same keypair
+    /// Keyed by key name so that different IDs mapping to the same keypair
     /// share a lock (prevents nonce collisions).
     fn agent_lock(&self, agent_id: u32) -> Arc<Mutex<()>> {
-        let normalized = super::keystore::normalize_agent_id(agent_id);
+        let name = super::keystore::agent_key_name(agent_id, &self.config.agent_keys);
         let mut map = self.agent_locks.lock().unwrap();
-        map.entry(normalized).or_insert_with(|| Arc::new(Mutex::new(()))).clone()
+        map.entry(name).or_insert_with(|| Arc::new(Mutex::new(()))).clone()
     }
 
     /// Connect to the Vara network
@@ -388,7 +665,8 @@ impl VaraClient {
             println!("[Vara] Contract: {}", config.contract_address);
 
             // Initialize keystore from seeds.json
-            let keystore = KeystoreManager::new(&config.keystore_path, &config.passphrase_path)?;
+            let mut keystore = KeystoreManager::new(&config.keystore_path, &config.passphrase_path)?;
+            keystore.set_agent_keys(config.agent_keys.clone());
             println!("[Vara] Keystore: {} (seeds.json)", config.keystore_path);
 
             Ok::<_, VaraError>(VaraClientInner {
@@ -420,6 +698,25 @@ impl VaraClient {
         self.config.block_time_ms
     }
 
+    /// Time after which a successful transaction can no longer be reverted by a simulated
+    /// reorg; None without reorg simulation
+    pub fn reorg_window_ms(&self) -> Option<u64> {
+        self.config
+            .reorg
+            .filter(|reorg| reorg.probability > 0.0)
+            .map(|reorg| reorg.depth_blocks as u64 * self.config.block_time_ms)
+    }
+
+    /// Point the client at another contract, e.g. one just deployed
+    pub fn set_contract(&mut self, contract_id: [u8; 32]) -> Result<(), VaraError> {
+        let inner = self.inner_ref()?.clone();
+        self.runtime.block_on(async {
+            inner.write().await.contract_id = contract_id;
+        });
+        self.config.contract_address = format!("0x{}", hex::encode(contract_id));
+        Ok(())
+    }
+
     /// Get a reference to the inner state, or return "Not connected" error.
     fn inner_ref(&self) -> Result<&Arc<RwLock<VaraClientInner>>, VaraError> {
         self.inner.as_ref().ok_or_else(|| VaraError::Connection("Not connected".into()))
@@ -487,6 +784,28 @@ impl VaraClient {
         })
     }
 
+    /// Deposit collateral (non-blocking, result via channel).
+    pub fn deposit_async(&self, agent_id: u32, amount: U256) -> Result<H256, VaraError> {
+        fire_and_forget!(self, agent_id, TxType::Deposit, amount, |kp, api, cid, gas, tip, tx| {
+            let detail = format!("{} from {}", amount, kp.address);
+            println!("[Vara] Deposit {} tip={}", detail, tip);
+            let env = GclientEnv::new(api).with_suri(kp.suri());
+            let actor = Actor::<VaraPerpsProgram, GclientEnv>::new(env, cid.into());
+            let mut service = actor.vara_perps();
+            match service.deposit(amount).with_gas_limit(gas.deposit).await {
+                Ok(_) => {
+                    println!("[Vara] Deposit {} OK", detail);
+                    tx.send(true, None, None, detail);
+                }
+                Err(e) => {
+                    eprintln!("[Vara] Deposit {} FAILED: {}", detail, e);
+                    tx.send(false, None, Some(format!("Deposit rejected: {}", e)), detail);
+                }
+            }
+        });
+        Ok([0u8; 32])
+    }
+
     /// Deposit collateral for many agents in parallel, in batches.
     /// Each agent signs its own transaction, so there are no nonce conflicts.
     /// Sends BATCH_SIZE transactions concurrently, waits, then next batch.
@@ -586,6 +905,44 @@ impl VaraClient {
         })
     }
 
+    /// Upload the contract WASM and run its `Create` constructor, signed by `agent_id`
+    /// (blocking). `gas_limit` defaults to the block gas limit. Returns the id of the new
+    /// program once it answers a query: a failed constructor leaves no usable program.
+    pub fn deploy_contract(
+        &self,
+        agent_id: u32,
+        wasm: &[u8],
+        init: InitConfig,
+        collateral_token: ActorId,
+        salt: &[u8],
+        gas_limit: Option<u64>,
+    ) -> Result<[u8; 32], VaraError> {
+        let (keypair, api, _, _) = self.read_agent_context(agent_id)?;
+        println!("[Vara] Deploy {} bytes of WASM from {}", wasm.len(), keypair.address);
+
+        // Sails constructor call: route name, then the arguments, SCALE encoded
+        let mut payload = "Create".to_string().encode();
+        (init, collateral_token).encode_to(&mut payload);
+
+        self.runtime.block_on(async {
+            let api = api.with(keypair.suri())?;
+            let gas_limit = match gas_limit {
+                Some(gas_limit) => gas_limit,
+                None => api.block_gas_limit()?,
+            };
+            let (_, program_id, _) = api.upload_program_bytes(wasm, salt, payload, gas_limit, 0).await?;
+            let program_id = program_id.into_bytes();
+            println!("[Vara] Uploaded program 0x{}", hex::encode(program_id));
+
+            let env = GclientEnv::new(api);
+            let actor = Actor::<VaraPerpsProgram, GclientEnv>::new(env, program_id.into());
+            actor.vara_perps().get_all_positions().query().await.map_err(|e| {
+                VaraError::Transaction(format!("contract 0x{} did not initialize: {}", hex::encode(program_id), e))
+            })?;
+            Ok(program_id)
+        })
+    }
+
     /// Withdraw collateral
     pub fn withdraw(&self, agent_id: u32, amount: U256) -> Result<H256, VaraError> {
         let (keypair, api, contract_id, gas_limits) = self.read_agent_context(agent_id)?;
@@ -627,20 +984,20 @@ impl VaraClient {
     /// Actual result (OrderId or error) is sent via `tx_result_tx` channel.
     pub fn submit_order(&self, agent_id: u32, order: &Order) -> Result<OrderId, VaraError> {
         let order = order.clone();
-        fire_and_forget!(self, agent_id, TxType::SubmitOrder, order, |kp, api, cid, gas, tx| {
+        fire_and_forget!(self, agent_id, TxType::SubmitOrder, order, |kp, api, cid, gas, tip, tx| {
             let detail = format!("{:?} {:?} size={} from {}", order.order_type, order.side, order.size_delta_usd, kp.address);
-            println!("[Vara] SubmitOrder {}", detail);
+            println!("[Vara] SubmitOrder {} tip={}", detail, tip);
             let env = GclientEnv::new(api).with_suri(kp.suri());
             let actor = Actor::<VaraPerpsProgram, GclientEnv>::new(env, cid.into());
             let mut service = actor.vara_perps();
             match service.submit_order(order).with_gas_limit(gas.submit_order).await {
                 Ok(oid) => {
                     println!("[Vara] SubmitOrder OK -> OrderId #{}", oid.0);
-                    let _ = tx.send(TxResult { agent_id, tx_type: TxType::SubmitOrder, success: true, order_id: Some(oid.0), error: None, detail });
+                    tx.send(true, Some(oid.0), None, detail);
                 }
                 Err(e) => {
                     eprintln!("[Vara] SubmitOrder FAILED: {}", e);
-                    let _ = tx.send(TxResult { agent_id, tx_type: TxType::SubmitOrder, success: false, order_id: None, error: Some(e.to_string()), detail });
+                    tx.send(false, None, Some(e.to_string()), detail);
                 }
             }
         });
@@ -657,9 +1014,9 @@ impl VaraClient {
         order: Order,
         oracle_input: OracleInput,
     ) -> Result<(), VaraError> {
-        fire_and_forget!(self, agent_id, TxType::SubmitAndExecute, order, oracle_input, |kp, api, cid, gas, tx| {
+        fire_and_forget!(self, agent_id, TxType::SubmitAndExecute, order, oracle_input, |kp, api, cid, gas, tip, tx| {
             let detail = format!("{:?} {:?} size={} from {}", order.order_type, order.side, order.size_delta_usd, kp.address);
-            println!("[Vara] SubmitOrder+Execute {}", detail);
+            println!("[Vara] SubmitOrder+Execute {} tip={}", detail, tip);
 
             // 1) SubmitOrder — await reply to get OrderId
             let env = GclientEnv::new(api.clone()).with_suri(kp.suri());
@@ -669,7 +1026,7 @@ impl VaraClient {
                 Ok(id) => id,
                 Err(e) => {
                     eprintln!("[Vara] SubmitOrder FAILED: {}", e);
-                    let _ = tx.send(TxResult { agent_id, tx_type: TxType::SubmitAndExecute, success: false, order_id: None, error: Some(e.to_string()), detail });
+                    tx.send(false, None, Some(e.to_string()), detail);
                     return;
                 }
             };
@@ -683,11 +1040,11 @@ impl VaraClient {
             match service2.execute_order(order_id, oracle_input).with_gas_limit(gas.execute_order).await {
                 Ok(_) => {
                     println!("[Vara] ExecuteOrder #{} OK", oid);
-                    let _ = tx.send(TxResult { agent_id, tx_type: TxType::SubmitAndExecute, success: true, order_id: Some(oid), error: None, detail });
+                    tx.send(true, Some(oid), None, detail);
                 }
                 Err(e) => {
                     eprintln!("[Vara] ExecuteOrder #{} FAILED: {}", oid, e);
-                    let _ = tx.send(TxResult { agent_id, tx_type: TxType::SubmitAndExecute, success: false, order_id: Some(oid), error: Some(e.to_string()), detail });
+                    tx.send(false, Some(oid), Some(e.to_string()), detail);
                 }
             }
         });
@@ -696,21 +1053,52 @@ impl VaraClient {
 
     /// Cancel an order (non-blocking, result via channel)
     pub fn cancel_order(&self, agent_id: u32, order_id: OrderId) -> Result<H256, VaraError> {
-        fire_and_forget!(self, agent_id, TxType::CancelOrder, order_id, |kp, api, cid, gas, tx| {
+        fire_and_forget!(self, agent_id, TxType::CancelOrder, order_id, |kp, api, cid, gas, tip, tx| {
             let oid = order_id.0;
             let detail = format!("#{} from {}", oid, kp.address);
-            println!("[Vara] CancelOrder {}", detail);
+            println!("[Vara] CancelOrder {} tip={}", detail, tip);
             let env = GclientEnv::new(api).with_suri(kp.suri());
             let actor = Actor::<VaraPerpsProgram, GclientEnv>::new(env, cid.into());
             let mut service = actor.vara_perps();
             match service.cancel_order(order_id).with_gas_limit(gas.cancel_order).await {
                 Ok(_) => {
                     println!("[Vara] CancelOrder #{} OK", oid);
-                    let _ = tx.send(TxResult { agent_id, tx_type: TxType::CancelOrder, success: true, order_id: Some(oid), error: None, detail });
+                    tx.send(true, Some(oid), None, detail);
                 }
                 Err(e) => {
                     eprintln!("[Vara] CancelOrder #{} FAILED: {}", oid, e);
-                    let _ = tx.send(TxResult { agent_id, tx_type: TxType::CancelOrder, success: false, order_id: Some(oid), error: Some(e.to_string()), detail });
+                    tx.send(false, Some(oid), Some(e.to_string()), detail);
+                }
+            }
+        });
+        Ok([0u8; 32])
+    }
+
+    /// Replace a pending order with an amended copy (non-blocking, result via channel).
+    /// The contract has no amendment call: CancelOrder, then SubmitOrder; the result
+    /// carries the id of the new order.
+    pub fn update_order(&self, agent_id: u32, order_id: OrderId, order: &Order) -> Result<H256, VaraError> {
+        let order = order.clone();
+        fire_and_forget!(self, agent_id, TxType::UpdateOrder, order_id, order, |kp, api, cid, gas, tip, tx| {
+            let oid = order_id.0;
+            let detail = format!("#{} {:?} {:?} size={} from {}", oid, order.order_type, order.side, order.size_delta_usd, kp.address);
+            println!("[Vara] UpdateOrder {} tip={}", detail, tip);
+            let env = GclientEnv::new(api).with_suri(kp.suri());
+            let actor = Actor::<VaraPerpsProgram, GclientEnv>::new(env, cid.into());
+            let mut service = actor.vara_perps();
+            if let Err(e) = service.cancel_order(order_id).with_gas_limit(gas.cancel_order).await {
+                eprintln!("[Vara] UpdateOrder #{} cancel FAILED: {}", oid, e);
+                tx.send(false, Some(oid), Some(e.to_string()), detail);
+                return;
+            }
+            match service.submit_order(order).with_gas_limit(gas.submit_order).await {
+                Ok(new_id) => {
+                    println!("[Vara] UpdateOrder #{} OK -> OrderId #{}", oid, new_id.0);
+                    tx.send(true, Some(new_id.0), None, detail);
+                }
+                Err(e) => {
+                    eprintln!("[Vara] UpdateOrder #{} resubmit FAILED: {}", oid, e);
+                    tx.send(false, Some(oid), Some(e.to_string()), detail);
                 }
             }
         });
@@ -720,27 +1108,119 @@ impl VaraClient {
     /// Execute a pending order — keeper action (non-blocking, result via channel)
     pub fn execute_order(&self, agent_id: u32, order_id: OrderId, oracle_input: &OracleInput) -> Result<H256, VaraError> {
         let oracle_input = oracle_input.clone();
-        fire_and_forget!(self, agent_id, TxType::ExecuteOrder, order_id, oracle_input, |kp, api, cid, gas, tx| {
+        fire_and_forget!(self, agent_id, TxType::ExecuteOrder, order_id, oracle_input, |kp, api, cid, gas, tip, tx| {
             let oid = order_id.0;
             let detail = format!("#{} by keeper {}", oid, kp.address);
-            println!("[Vara] ExecuteOrder {}", detail);
+            println!("[Vara] ExecuteOrder {} tip={}", detail, tip);
             let env = GclientEnv::new(api).with_suri(kp.suri());
             let actor = Actor::<VaraPerpsProgram, GclientEnv>::new(env, cid.into());
             let mut service = actor.vara_perps();
             match service.execute_order(order_id, oracle_input).with_gas_limit(gas.execute_order).await {
                 Ok(_) => {
                     println!("[Vara] ExecuteOrder #{} OK", oid);
-                    let _ = tx.send(TxResult { agent_id, tx_type: TxType::ExecuteOrder, success: true, order_id: Some(oid), error: None, detail });
+                    tx.send(true, Some(oid), None, detail);
                 }
                 Err(e) => {
                     eprintln!("[Vara] ExecuteOrder #{} FAILED: {}", oid, e);
-                    let _ = tx.send(TxResult { agent_id, tx_type: TxType::ExecuteOrder, success: false, order_id: Some(oid), error: Some(e.to_string()), detail });
+                    tx.send(false, Some(oid), Some(e.to_string()), detail);
                 }
             }
         });
         Ok([0u8; 32])
     }
 
+    /// Execute several pending orders for one keeper (non-blocking, results via channel).
+    /// The contract has no batch entrypoint: the ExecuteOrder calls go out back to back from
+    /// one job, under one account lock and connection, instead of a queued job per order.
+    /// Every order gets its own ExecuteOrder result; a measured fee is split between them.
+    pub fn execute_orders_batch(&self, agent_id: u32, orders: Vec<(OrderId, OracleInput)>) -> Result<H256, VaraError> {
+        let inner = self.inner_ref()?.clone();
+        let lock = self.agent_lock(agent_id);
+        let tip = self.config.tips.for_tx(&TxType::ExecuteOrder);
+        let calls: Vec<(OrderId, OracleInput, TxReporter)> = orders
+            .into_iter()
+            .map(|(order_id, oracle_input)| {
+                (
+                    order_id,
+                    oracle_input,
+                    self.reporter(agent_id, TxType::ExecuteOrder, tip),
+                )
+            })
+            .collect();
+        let fail_all = |calls: &[(OrderId, OracleInput, TxReporter)], error: String, detail: &str| {
+            for (order_id, _, tx) in calls {
+                tx.send(false, Some(order_id.0), Some(error.clone()), detail.to_string());
+                tx.release(None);
+            }
+        };
+        if let Some(reason) = self.budget_exhausted(agent_id) {
+            fail_all(&calls, reason, "over budget");
+            return Ok([0u8; 32]);
+        }
+        let spent = self.spent.clone();
+
+        self.runtime.handle().spawn_blocking(move || {
+            let _guard = lock.lock().unwrap();
+            let rt = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(rt) => rt,
+                Err(e) => {
+                    eprintln!("[Vara] ExecuteOrder batch: runtime error: {}", e);
+                    fail_all(&calls, e.to_string(), "runtime build error");
+                    return;
+                }
+            };
+            rt.block_on(async move {
+                let (kp, api, cid, gas) = {
+                    let guard = inner.read().await;
+                    let kp = match guard.keystore.load_keypair_for_agent(agent_id) {
+                        Ok(kp) => kp.clone(),
+                        Err(e) => {
+                            eprintln!("[Vara] ExecuteOrder batch: keypair error: {}", e);
+                            fail_all(&calls, e.to_string(), "keypair error");
+                            return;
+                        }
+                    };
+                    (kp, guard.api.clone(), guard.contract_id, guard.gas_limits)
+                };
+                println!(
+                    "[Vara] ExecuteOrder batch of {} by keeper {} tip={}",
+                    calls.len(),
+                    kp.address,
+                    tip
+                );
+                let held = calls.first().is_some_and(|(_, _, tx)| tx.is_held());
+                let probe = FeeProbe::start(held, &api, &kp.address).await;
+                let env = GclientEnv::new(api).with_suri(kp.suri());
+                let actor = Actor::<VaraPerpsProgram, GclientEnv>::new(env, cid.into());
+                let mut service = actor.vara_perps();
+                for (order_id, oracle_input, tx) in &calls {
+                    let oid = order_id.0;
+                    let detail = format!("#{} by keeper {} (batch)", oid, kp.address);
+                    match service
+                        .execute_order(OrderId(oid), oracle_input.clone())
+                        .with_gas_limit(gas.execute_order)
+                        .await
+                    {
+                        Ok(_) => {
+                            println!("[Vara] ExecuteOrder #{} OK", oid);
+                            tx.send(true, Some(oid), None, detail);
+                        }
+                        Err(e) => {
+                            eprintln!("[Vara] ExecuteOrder #{} FAILED: {}", oid, e);
+                            tx.send(false, Some(oid), Some(e.to_string()), detail);
+                        }
+                    }
+                }
+                let fee = probe.finish(agent_id, &spent).await;
+                let share = fee.map(|fee| fee / calls.len() as u128);
+                for (_, _, tx) in &calls {
+                    tx.release(share);
+                }
+            });
+        });
+        Ok([0u8; 32])
+    }
+
     // ========== Contract Queries ==========
 
     /// Shorthand for query errors.
@@ -831,14 +1311,13 @@ impl VaraClient {
                 let s = actor.vara_perps();
                 match s.get_all_positions().query().await {
                     Ok(positions) => {
-                        let divisor = U256::exp10(24);
                         let mut oi_long: i128 = 0;
                         let mut oi_short: i128 = 0;
                         for p in positions.iter() {
                             if p.size_usd.is_zero() {
                                 continue;
                             }
-                            let size_micro = (U256::from(p.size_usd) / divisor).low_u64() as i128;
+                            let size_micro = Usd1e30(U256::from(p.size_usd)).to_micro().0 as i128;
                             match p.key.side {
                                 VaraSide::Long => oi_long += size_micro,
                                 VaraSide::Short => oi_short += size_micro,
@@ -854,6 +1333,197 @@ impl VaraClient {
         });
     }
 
+    /// Non-blocking: fetch all positions on the blocking pool and send them via the
+    /// provided channel, each with its `CalculateLiquidationPrice` result when an oracle
+    /// input is given (None if that query fails). Used for periodic position/market snapshots.
+    /// A failed fetch sends its error, so the caller can start the next one.
+    pub fn fetch_positions_async(
+        &self,
+        oracle_input: Option<OracleInput>,
+        sender: crossbeam_channel::Sender<Result<Vec<(Position, Option<U256>)>, String>>,
+    ) {
+        let inner = match self.inner_ref() {
+            Ok(i) => i.clone(),
+            Err(e) => {
+                let _ = sender.send(Err(e.to_string()));
+                return;
+            }
+        };
+
+        self.runtime.handle().spawn_blocking(move || {
+            let rt = match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(rt) => rt,
+                Err(e) => {
+                    let _ = sender.send(Err(format!("runtime error: {}", e)));
+                    return;
+                }
+            };
+            rt.block_on(async move {
+                let guard = inner.read().await;
+                let env = GclientEnv::new(guard.api.clone());
+                let actor =
+                    Actor::<VaraPerpsProgram, GclientEnv>::new(env, guard.contract_id.into());
+                let s = actor.vara_perps();
+                match s.get_all_positions().query().await {
+                    Ok(positions) => {
+                        let mut out = Vec::with_capacity(positions.len());
+                        for p in positions {
+                            let liquidation_price = match &oracle_input {
+                                Some(oi) if !p.size_usd.is_zero() => s
+                                    .calculate_liquidation_price(p.key.clone(), oi.clone())
+                                    .query()
+                                    .await
+                                    .ok(),
+                                _ => None,
+                            };
+                            out.push((p, liquidation_price));
+                        }
+                        let _ = sender.send(Ok(out));
+                    }
+                    Err(e) => {
+                        let _ = sender.send(Err(format!("query failed: {}", e)));
+                    }
+                }
+            });
+        });
+    }
+
+    /// Non-blocking: query the free native (VARA) balance of each agent's account on the
+    /// blocking pool and send (agent_id, balance) per agent via the provided channel.
+    pub fn fetch_native_balances_async(
+        &self,
+        agent_ids: Vec<u32>,
+        sender: crossbeam_channel::Sender<(u32, Result<u128, String>)>,
+    ) {
+        let inner = match self.inner_ref() {
+            Ok(i) => i.clone(),
+            Err(e) => {
+                eprintln!("[Vara] fetch_native_balances_async: {}", e);
+                return;
+            }
+        };
+
+        self.runtime.handle().spawn_blocking(move || {
+            let rt = match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(rt) => rt,
+                Err(e) => {
+                    eprintln!("[Vara] fetch_native_balances_async: runtime error: {}", e);
+                    return;
+                }
+            };
+            rt.block_on(async move {
+                let guard = inner.read().await;
+                for agent_id in agent_ids {
+                    let result = match guard.keystore.load_keypair_for_agent(agent_id) {
+                        Ok(kp) => guard.api.free_balance(kp.address.as_str()).await.map_err(|e| e.to_string()),
+                        Err(e) => Err(e.to_string()),
+                    };
+                    let _ = sender.send((agent_id, result));
+                }
+            });
+        });
+    }
+
+    /// Non-blocking: check which of `order_ids` still rest on the contract, all in one job on
+    /// the blocking pool, and send (order id, present) per order via the provided channel as
+    /// one batch. A failed query reports its error for that order.
+    pub fn fetch_orders_async(
+        &self,
+        order_ids: Vec<u64>,
+        sender: crossbeam_channel::Sender<Vec<(u64, Result<bool, String>)>>,
+    ) {
+        let inner = match self.inner_ref() {
+            Ok(i) => i.clone(),
+            Err(e) => {
+                let _ = sender.send(order_ids.into_iter().map(|id| (id, Err(e.to_string()))).collect());
+                return;
+            }
+        };
+
+        self.runtime.handle().spawn_blocking(move || {
+            let rt = match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(rt) => rt,
+                Err(e) => {
+                    let error = format!("runtime error: {}", e);
+                    let _ = sender.send(order_ids.into_iter().map(|id| (id, Err(error.clone()))).collect());
+                    return;
+                }
+            };
+            rt.block_on(async move {
+                let guard = inner.read().await;
+                let env = GclientEnv::new(guard.api.clone());
+                let actor =
+                    Actor::<VaraPerpsProgram, GclientEnv>::new(env, guard.contract_id.into());
+                let s = actor.vara_perps();
+                let mut out = Vec::with_capacity(order_ids.len());
+                for id in order_ids {
+                    let present = s
+                        .get_order(OrderId(id))
+                        .query()
+                        .await
+                        .map(|order| order.is_some())
+                        .map_err(|e| e.to_string());
+                    out.push((id, present));
+                }
+                let _ = sender.send(out);
+            });
+        });
+    }
+
+    /// Non-blocking: transfer `amount` native tokens from the account of agent `from` to
+    /// the account of agent `to` (keep-alive: never reaps the sender), serialized with the
+    /// other txs of `from`. The result is sent as (to, result) via the provided channel.
+    pub fn transfer_native_async(
+        &self,
+        from: u32,
+        to: u32,
+        amount: u128,
+        sender: crossbeam_channel::Sender<(u32, Result<(), String>)>,
+    ) {
+        let inner = match self.inner_ref() {
+            Ok(i) => i.clone(),
+            Err(e) => {
+                let _ = sender.send((to, Err(e.to_string())));
+                return;
+            }
+        };
+        let lock = self.agent_lock(from);
+
+        self.runtime.handle().spawn_blocking(move || {
+            let _guard = lock.lock().unwrap();
+            let rt = match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(rt) => rt,
+                Err(e) => {
+                    let _ = sender.send((to, Err(format!("runtime error: {}", e))));
+                    return;
+                }
+            };
+            let result = rt.block_on(async move {
+                let (api, destination) = {
+                    let guard = inner.read().await;
+                    let suri = guard.keystore.load_keypair_for_agent(from)?.suri().to_string();
+                    let address = guard.keystore.load_keypair_for_agent(to)?.address.clone();
+                    (guard.api.clone().with(suri)?, actor_id_from_address(&address)?)
+                };
+                api.transfer_keep_alive(destination, amount).await?;
+                Ok::<_, VaraError>(())
+            });
+            let _ = sender.send((to, result.map_err(|e| e.to_string())));
+        });
+    }
+
     // ========== Utility Methods ==========
 
     /// Get address string for an agent
@@ -861,12 +1531,7 @@ impl VaraClient {
         let inner = self.inner_ref()?;
         self.runtime.block_on(async {
             let guard = inner.read().await;
-            let normalized = super::keystore::normalize_agent_id(agent_id);
-            let name = format!("bot_{:03}", normalized);
-            let keypair = guard
-                .keystore
-                .get_keypair(&name)
-                .ok_or_else(|| VaraError::Keystore(KeystoreError::NotFound(name)))?;
+            let keypair = guard.keystore.load_keypair_for_agent(agent_id)?;
             Ok(keypair.address.clone())
         })
     }
@@ -0,0 +1,118 @@
+// Mapping between sim time and the chain's Unix clock.
+//
+// Contract orders carry `created_at` / `valid_from` / `valid_until` Unix
+// timestamps checked against block time, while the sim clock may start at a
+// fixed time or run in fast-forward. Sending `now_ns / 1e9` as-is makes orders
+// of a run started at 0 expire on arrival and those of a fast-forward run
+// valid only in the future. The exchange therefore anchors the sim clock to
+// the chain clock on its first order and converts every timestamp through the
+// anchor: "now" is the chain's now, and an interval of sim time becomes an
+// interval of chain time divided by the speed of the run (sim seconds per
+// chain second, measured since the anchor and never below 1), plus a margin
+// for a run that slows down later. The exchange still cancels orders at their
+// sim-time expiry; the chain's `valid_until` only has to outlive it.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+
+/// Chain seconds of the anchor before the measured speed is trusted
+const MIN_SPEED_SAMPLE_SEC: u64 = 10;
+
+/// Extra chain seconds on every converted validity window
+pub const VALIDITY_MARGIN_SEC: u64 = 60;
+
+/// Current Unix time, which block timestamps follow.
+pub fn chain_now_sec() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Converts sim instants into chain timestamps and back.
+#[derive(Debug, Clone, Default)]
+pub struct ChainClock {
+    /// (sim ns, chain seconds) of the first conversion
+    anchor: Option<(u64, u64)>,
+}
+
+impl ChainClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sim seconds per chain second since the anchor; 1 until enough chain time passed.
+    pub fn speed(&mut self, now_ns: u64, chain_now: u64) -> f64 {
+        let (anchor_ns, anchor_sec) = *self.anchor.get_or_insert((now_ns, chain_now));
+        let chain_elapsed = chain_now.saturating_sub(anchor_sec);
+        if chain_elapsed < MIN_SPEED_SAMPLE_SEC {
+            return 1.0;
+        }
+        let sim_elapsed = now_ns.saturating_sub(anchor_ns) as f64 / NS_PER_SEC as f64;
+        (sim_elapsed / chain_elapsed as f64).max(1.0)
+    }
+
+    /// Chain timestamp by which sim time `until_ns` has passed, with the validity margin.
+    pub fn chain_time(&mut self, now_ns: u64, chain_now: u64, until_ns: u64) -> u64 {
+        let speed = self.speed(now_ns, chain_now);
+        let sim_sec = until_ns.saturating_sub(now_ns) as f64 / NS_PER_SEC as f64;
+        chain_now + (sim_sec / speed).ceil() as u64 + VALIDITY_MARGIN_SEC
+    }
+
+    /// Sim time at which chain timestamp `chain_sec` is reached at the current speed.
+    pub fn sim_time(&mut self, now_ns: u64, chain_now: u64, chain_sec: u64) -> u64 {
+        let speed = self.speed(now_ns, chain_now);
+        let chain_left = chain_sec.saturating_sub(chain_now) as f64;
+        now_ns.saturating_add((chain_left * speed * NS_PER_SEC as f64) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAIN_START: u64 = 1_700_000_000;
+
+    #[test]
+    fn test_fixed_start_time_maps_onto_chain_now() {
+        // A reproducible run starts its clock at 0
+        let mut clock = ChainClock::new();
+        let valid_until = clock.chain_time(0, CHAIN_START, 3600 * NS_PER_SEC);
+        assert_eq!(valid_until, CHAIN_START + 3600 + VALIDITY_MARGIN_SEC);
+        // An order sent 5 chain seconds later still gets its full window
+        let valid_until = clock.chain_time(5 * NS_PER_SEC, CHAIN_START + 5, 65 * NS_PER_SEC);
+        assert_eq!(valid_until, CHAIN_START + 5 + 60 + VALIDITY_MARGIN_SEC);
+    }
+
+    #[test]
+    fn test_fast_forward_shrinks_chain_windows() {
+        let mut clock = ChainClock::new();
+        let start_ns = CHAIN_START * NS_PER_SEC;
+        clock.chain_time(start_ns, CHAIN_START, start_ns);
+        // 100 sim seconds per chain second: a day of sim time lasts 864 chain seconds
+        let now_ns = start_ns + 6_000 * NS_PER_SEC;
+        let chain_now = CHAIN_START + 60;
+        assert_eq!(clock.speed(now_ns, chain_now), 100.0);
+        let day = 86_400 * NS_PER_SEC;
+        assert_eq!(
+            clock.chain_time(now_ns, chain_now, now_ns + day),
+            chain_now + 864 + VALIDITY_MARGIN_SEC
+        );
+        // And back: 864 chain seconds are a day of sim time
+        assert_eq!(clock.sim_time(now_ns, chain_now, chain_now + 864), now_ns + day);
+        assert_eq!(clock.sim_time(now_ns, chain_now, chain_now - 1), now_ns);
+    }
+
+    #[test]
+    fn test_slow_runs_keep_realtime_windows() {
+        let mut clock = ChainClock::new();
+        clock.chain_time(0, CHAIN_START, 0);
+        // Half speed: windows are not stretched beyond realtime
+        assert_eq!(clock.speed(30 * NS_PER_SEC, CHAIN_START + 60), 1.0);
+        assert_eq!(
+            clock.chain_time(30 * NS_PER_SEC, CHAIN_START + 60, 90 * NS_PER_SEC),
+            CHAIN_START + 120 + VALIDITY_MARGIN_SEC
+        );
+    }
+}
@@ -83,6 +83,8 @@ pub struct KeystoreManager {
     keypairs: HashMap<String, KeyPair>,
     /// Address to name mapping for reverse lookup
     address_to_name: HashMap<String, String>,
+    /// Explicit agent_id -> key name mapping (scenario `vara.keys`)
+    agent_keys: HashMap<u32, String>,
 }
 
 impl KeystoreManager {
@@ -139,6 +141,7 @@ impl KeystoreManager {
         Ok(Self {
             keypairs,
             address_to_name,
+            agent_keys: HashMap::new(),
         })
     }
 
@@ -169,11 +172,15 @@ impl KeystoreManager {
 
     /// Load keypair for a bot by agent ID
     ///
-    /// Maps agent_id to bot name: id=1 -> "bot_001", id=3000 -> "bot_001" (normalized)
+    /// Maps agent_id to bot name: id=1 -> "bot_001", id=3000 -> "bot_001" (normalized),
+    /// unless the agent has an explicit key (see `set_agent_keys`)
     pub fn load_keypair_for_agent(&self, agent_id: u32) -> Result<&KeyPair, KeystoreError> {
-        let normalized = normalize_agent_id(agent_id);
-        let name = format!("bot_{:03}", normalized);
-        self.load_keypair(&name)
+        self.load_keypair(&agent_key_name(agent_id, &self.agent_keys))
+    }
+
+    /// Use explicit key names for these agents instead of the normalized bot_NNN
+    pub fn set_agent_keys(&mut self, agent_keys: HashMap<u32, String>) {
+        self.agent_keys = agent_keys;
     }
 
     /// Get a previously loaded keypair by name
@@ -221,6 +228,15 @@ pub fn normalize_agent_id(agent_id: u32) -> u32 {
     if agent_id == 0 { 1 } else { ((agent_id - 1) % 200) + 1 }
 }
 
+/// Key name for an agent: the explicit mapping if present, else "bot_NNN" (normalized).
+/// Used by the keystore, the client's per-account locks and the scenario AddressBook.
+pub fn agent_key_name(agent_id: u32, agent_keys: &HashMap<u32, String>) -> String {
+    agent_keys
+        .get(&agent_id)
+        .cloned()
+        .unwrap_or_else(|| format!("bot_{:03}", normalize_agent_id(agent_id)))
+}
+
 /// Find seeds.json relative to a keystore path
 fn find_seeds_json(base_path: &Path) -> Result<PathBuf, KeystoreError> {
     // Try: base_path itself, base_path/seeds.json, parent/seeds.json, etc.
@@ -267,4 +283,13 @@ mod tests {
         assert_eq!(format!("bot_{:03}", normalize_agent_id(20)), "bot_020");
         assert_eq!(format!("bot_{:03}", normalize_agent_id(200)), "bot_200");
     }
+
+    #[test]
+    fn test_agent_key_name_mapping() {
+        let mut keys = HashMap::new();
+        keys.insert(10, "whale".to_string());
+        assert_eq!(agent_key_name(10, &keys), "whale");
+        assert_eq!(agent_key_name(11, &keys), "bot_011");
+        assert_eq!(agent_key_name(210, &HashMap::new()), "bot_010");
+    }
 }
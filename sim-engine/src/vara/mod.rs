@@ -1,12 +1,17 @@
 pub mod client;
+pub mod clock;
 pub mod keystore;
+pub mod oracle;
 pub mod types;
 
 pub mod generated {
     include!(concat!(env!("OUT_DIR"), "/vara_perps_client.rs"));
 }
 
-pub use client::{TxResult, TxType, VaraClient, VaraConfig, VaraError};
+pub use client::{
+    ReorgConfig, TxResult, TxSpendConfig, TxTips, TxType, VaraClient, VaraConfig, VaraError, TX_BUDGET_EXHAUSTED,
+};
 pub use keystore::KeystoreManager;
+pub use oracle::{OracleInputBuilder, OracleInputError};
 pub use generated::*;
 pub use types::{ActorId, u256_from_sails, u256_to_sails};
@@ -0,0 +1,365 @@
+//! Building the contract's `OracleInput` from provider prices.
+//!
+//! Providers quote prices in micro-USD (1e6 = $1) per whole token; perp-futures
+//! expects USD(1e30) per 1 atom of token (see `crate::scale` for the conversion).
+//!
+//! Min/max bounds are widened by the provider's confidence interval on request.
+//! Tokens with more than 24 decimals lose precision; there the `min` bound is
+//! rounded down and the `max` bound up, so the contract never sees a tighter
+//! spread than the provider published.
+
+use crate::api::provider::SignedPriceData;
+use crate::scale::{MicroUsd, TokenScale};
+use crate::vara::types::{ActorId, OracleInput, OraclePrices, SignedOraclePrices};
+
+/// Domain tag of the signed oracle envelope (see `SignedOraclePrices` in the IDL)
+pub const SIGNED_ORACLE_DOMAIN: [u8; 8] = *b"PERPSOR1";
+
+/// Publish times this far ahead of the local clock are tolerated (clock skew)
+pub const MAX_FUTURE_SKEW_SECS: u64 = 5;
+
+/// Collateral price in micro-USD when no collateral feed is given (USDC/USDT peg)
+pub const PEGGED_COLLATERAL_MICRO: u64 = 1_000_000;
+
+/// Why a price could not be turned into an `OracleInput`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OracleInputError {
+    /// Price (or its lower confidence bound) is zero
+    ZeroPrice(String),
+    /// Price is older than `max_age_secs`
+    Stale {
+        symbol: String,
+        age_secs: u64,
+        max_age_secs: u64,
+    },
+    /// Publish time is ahead of the local clock by more than the allowed skew
+    FromFuture { symbol: String, ahead_secs: u64 },
+    /// Signed envelope requested but the provider signature is not 64 bytes
+    InvalidSignature { symbol: String, len: usize },
+}
+
+impl std::fmt::Display for OracleInputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ZeroPrice(s) => write!(f, "Zero price: {}", s),
+            Self::Stale {
+                symbol,
+                age_secs,
+                max_age_secs,
+            } => {
+                write!(
+                    f,
+                    "Stale price: {} is {}s old (max {}s)",
+                    symbol, age_secs, max_age_secs
+                )
+            }
+            Self::FromFuture { symbol, ahead_secs } => {
+                write!(f, "Price from the future: {} is {}s ahead", symbol, ahead_secs)
+            }
+            Self::InvalidSignature { symbol, len } => {
+                write!(f, "Invalid signature: {} has {} bytes, expected 64", symbol, len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OracleInputError {}
+
+#[derive(Debug, Clone)]
+struct SignedEnvelope {
+    program_id: ActorId,
+    market_id: u32,
+}
+
+/// Builds `OracleInput`/`OraclePrices` for one market from `SignedPriceData`.
+///
+/// ```ignore
+/// let input = OracleInputBuilder::new(18, 6)
+///     .max_age_secs(60)
+///     .with_confidence(true)
+///     .build(&eth_price, None, now_secs)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct OracleInputBuilder {
+    index: TokenScale,
+    collateral: TokenScale,
+    max_age_secs: u64,
+    with_confidence: bool,
+    signed: Option<SignedEnvelope>,
+}
+
+impl OracleInputBuilder {
+    pub fn new(index_decimals: u32, collateral_decimals: u32) -> Self {
+        Self {
+            index: TokenScale::new(index_decimals),
+            collateral: TokenScale::new(collateral_decimals),
+            max_age_secs: 0,
+            with_confidence: false,
+            signed: None,
+        }
+    }
+
+    /// Reject prices older than this (0 = no staleness check)
+    pub fn max_age_secs(mut self, secs: u64) -> Self {
+        self.max_age_secs = secs;
+        self
+    }
+
+    /// Widen min/max by the provider's confidence interval
+    pub fn with_confidence(mut self, enabled: bool) -> Self {
+        self.with_confidence = enabled;
+        self
+    }
+
+    /// Produce `OracleInput::Signed` for this program/market instead of `DevPrices`
+    pub fn signed(mut self, program_id: ActorId, market_id: u32) -> Self {
+        self.signed = Some(SignedEnvelope { program_id, market_id });
+        self
+    }
+
+    /// Scale already-validated micro-USD bounds into `OraclePrices`
+    pub fn prices(
+        &self,
+        index_min_micro: u64,
+        index_max_micro: u64,
+        collateral_min_micro: u64,
+        collateral_max_micro: u64,
+    ) -> OraclePrices {
+        OraclePrices {
+            index_price_min: self.index.atom_price(MicroUsd(index_min_micro)),
+            index_price_max: self.index.atom_price_ceil(MicroUsd(index_max_micro)),
+            collateral_price_min: self.collateral.atom_price(MicroUsd(collateral_min_micro)),
+            collateral_price_max: self.collateral.atom_price_ceil(MicroUsd(collateral_max_micro)),
+        }
+    }
+
+    /// Validate `index` (and `collateral`, pegged at $1 if `None`) and build the input.
+    /// `now_secs` is the wall-clock time the publish times are checked against.
+    pub fn build(
+        &self,
+        index: &SignedPriceData,
+        collateral: Option<&SignedPriceData>,
+        now_secs: u64,
+    ) -> Result<OracleInput, OracleInputError> {
+        let (index_min, index_max) = self.bounds(index, now_secs)?;
+        let (collateral_min, collateral_max) = match collateral {
+            Some(c) => self.bounds(c, now_secs)?,
+            None => (PEGGED_COLLATERAL_MICRO, PEGGED_COLLATERAL_MICRO),
+        };
+        let prices = self.prices(index_min, index_max, collateral_min, collateral_max);
+
+        let Some(envelope) = &self.signed else {
+            return Ok(OracleInput::DevPrices(prices));
+        };
+        let Ok(signature) = <[u8; 64]>::try_from(index.signature.as_slice()) else {
+            return Err(OracleInputError::InvalidSignature {
+                symbol: index.symbol.clone(),
+                len: index.signature.len(),
+            });
+        };
+        Ok(OracleInput::Signed(SignedOraclePrices {
+            domain: SIGNED_ORACLE_DOMAIN,
+            program_id: envelope.program_id,
+            market_id: envelope.market_id,
+            prices,
+            signature,
+        }))
+    }
+
+    /// Staleness checks and the (min, max) micro-USD bounds of one price
+    pub fn bounds(&self, data: &SignedPriceData, now_secs: u64) -> Result<(u64, u64), OracleInputError> {
+        if data.publish_time > now_secs + MAX_FUTURE_SKEW_SECS {
+            return Err(OracleInputError::FromFuture {
+                symbol: data.symbol.clone(),
+                ahead_secs: data.publish_time - now_secs,
+            });
+        }
+        let age_secs = now_secs.saturating_sub(data.publish_time);
+        if self.max_age_secs > 0 && age_secs > self.max_age_secs {
+            return Err(OracleInputError::Stale {
+                symbol: data.symbol.clone(),
+                age_secs,
+                max_age_secs: self.max_age_secs,
+            });
+        }
+
+        let conf = if self.with_confidence {
+            data.confidence.unwrap_or(0)
+        } else {
+            0
+        };
+        let min = data.price_usd_micro.saturating_sub(conf);
+        let max = data.price_usd_micro.saturating_add(conf);
+        if min == 0 {
+            return Err(OracleInputError::ZeroPrice(data.symbol.clone()));
+        }
+        Ok((min, max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitive_types::U256;
+
+    fn price(symbol: &str, micro: u64, confidence: Option<u64>, publish_time: u64) -> SignedPriceData {
+        SignedPriceData {
+            symbol: symbol.to_string(),
+            price_usd_micro: micro,
+            confidence,
+            ema_price: None,
+            publish_time,
+            signature: Vec::new(),
+            provider_name: "test".to_string(),
+        }
+    }
+
+    fn dev_prices(input: OracleInput) -> OraclePrices {
+        match input {
+            OracleInput::DevPrices(p) => p,
+            OracleInput::Signed(_) => panic!("expected DevPrices"),
+        }
+    }
+
+    /// USD(1e30) per atom back to micro-USD per whole token
+    fn atom_price_to_micro(price: U256, decimals: u32) -> u64 {
+        TokenScale::new(decimals).micro_price(price).0
+    }
+
+    #[test]
+    fn test_pegged_collateral_scaling() {
+        // USDC $1, 6 decimals -> 10^24
+        let p = OracleInputBuilder::new(18, 6).prices(1, 1, PEGGED_COLLATERAL_MICRO, PEGGED_COLLATERAL_MICRO);
+        assert_eq!(p.collateral_price_min, U256::exp10(24));
+        assert_eq!(atom_price_to_micro(p.collateral_price_max, 6), PEGGED_COLLATERAL_MICRO);
+    }
+
+    #[test]
+    fn test_above_24_decimals_rounds_outward() {
+        let builder = OracleInputBuilder::new(27, 6);
+        // 1_234_567 micro / 10^3 = 1234.567 atoms-price -> min 1234, max 1235
+        let p = builder.prices(1_234_567, 1_234_567, PEGGED_COLLATERAL_MICRO, PEGGED_COLLATERAL_MICRO);
+        assert_eq!(p.index_price_min, U256::from(1234u64));
+        assert_eq!(p.index_price_max, U256::from(1235u64));
+        // Exact multiples don't move
+        let p = builder.prices(2_000_000, 2_000_000, PEGGED_COLLATERAL_MICRO, PEGGED_COLLATERAL_MICRO);
+        assert_eq!(p.index_price_min, p.index_price_max);
+    }
+
+    #[test]
+    fn test_confidence_widens_bounds() {
+        let eth = price("ETH-USD", 3_000_000_000, Some(1_500_000), 100);
+
+        let p = dev_prices(OracleInputBuilder::new(18, 6).build(&eth, None, 100).unwrap());
+        assert_eq!(p.index_price_min, p.index_price_max);
+
+        let p = dev_prices(
+            OracleInputBuilder::new(18, 6)
+                .with_confidence(true)
+                .build(&eth, None, 100)
+                .unwrap(),
+        );
+        assert_eq!(atom_price_to_micro(p.index_price_min, 18), 2_998_500_000);
+        assert_eq!(atom_price_to_micro(p.index_price_max, 18), 3_001_500_000);
+        assert_eq!(p.collateral_price_min, U256::exp10(24));
+        assert_eq!(p.collateral_price_max, U256::exp10(24));
+    }
+
+    #[test]
+    fn test_collateral_feed_is_scaled_and_checked() {
+        let eth = price("ETH-USD", 3_000_000_000, None, 100);
+        let usdt = price("USDT-USD", 999_800, Some(300), 100);
+        let builder = OracleInputBuilder::new(18, 6).with_confidence(true);
+
+        let p = dev_prices(builder.build(&eth, Some(&usdt), 100).unwrap());
+        assert_eq!(atom_price_to_micro(p.collateral_price_min, 6), 999_500);
+        assert_eq!(atom_price_to_micro(p.collateral_price_max, 6), 1_000_100);
+
+        let stale_usdt = price("USDT-USD", 999_800, None, 10);
+        let err = builder
+            .max_age_secs(30)
+            .build(&eth, Some(&stale_usdt), 100)
+            .unwrap_err();
+        assert!(matches!(err, OracleInputError::Stale { ref symbol, age_secs: 90, .. } if symbol == "USDT-USD"));
+    }
+
+    #[test]
+    fn test_staleness_checks() {
+        let builder = OracleInputBuilder::new(18, 6).max_age_secs(60);
+
+        assert!(builder
+            .build(&price("ETH-USD", 3_000_000_000, None, 40), None, 100)
+            .is_ok());
+        assert_eq!(
+            builder
+                .build(&price("ETH-USD", 3_000_000_000, None, 39), None, 100)
+                .unwrap_err(),
+            OracleInputError::Stale {
+                symbol: "ETH-USD".to_string(),
+                age_secs: 61,
+                max_age_secs: 60
+            }
+        );
+
+        // Small clock skew is tolerated, anything beyond is rejected
+        assert!(builder
+            .build(&price("ETH-USD", 3_000_000_000, None, 105), None, 100)
+            .is_ok());
+        assert_eq!(
+            builder
+                .build(&price("ETH-USD", 3_000_000_000, None, 106), None, 100)
+                .unwrap_err(),
+            OracleInputError::FromFuture {
+                symbol: "ETH-USD".to_string(),
+                ahead_secs: 6
+            }
+        );
+
+        // max_age_secs = 0 disables the age check
+        let unchecked = OracleInputBuilder::new(18, 6);
+        assert!(unchecked
+            .build(&price("ETH-USD", 3_000_000_000, None, 0), None, 100_000)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_zero_price_rejected() {
+        let builder = OracleInputBuilder::new(18, 6).with_confidence(true);
+        let err = builder.build(&price("ETH-USD", 0, None, 100), None, 100).unwrap_err();
+        assert_eq!(err, OracleInputError::ZeroPrice("ETH-USD".to_string()));
+        // Confidence wider than the price leaves no lower bound
+        let err = builder
+            .build(&price("ETH-USD", 1_000, Some(1_000), 100), None, 100)
+            .unwrap_err();
+        assert_eq!(err, OracleInputError::ZeroPrice("ETH-USD".to_string()));
+    }
+
+    #[test]
+    fn test_signed_envelope() {
+        let program_id = ActorId::from([7u8; 32]);
+        let builder = OracleInputBuilder::new(8, 6).signed(program_id, 3);
+
+        let mut btc = price("BTC-USD", 100_000_000_000, None, 100);
+        btc.signature = vec![0xab; 64];
+        match builder.build(&btc, None, 100).unwrap() {
+            OracleInput::Signed(s) => {
+                assert_eq!(s.domain, SIGNED_ORACLE_DOMAIN);
+                assert_eq!(s.program_id, program_id);
+                assert_eq!(s.market_id, 3);
+                assert_eq!(s.signature, [0xab; 64]);
+                assert_eq!(atom_price_to_micro(s.prices.index_price_min, 8), 100_000_000_000);
+            }
+            OracleInput::DevPrices(_) => panic!("expected Signed"),
+        }
+
+        // A Pyth VAA is not an ed25519 signature the contract can check
+        btc.signature = vec![0u8; 1024];
+        assert_eq!(
+            builder.build(&btc, None, 100).unwrap_err(),
+            OracleInputError::InvalidSignature {
+                symbol: "BTC-USD".to_string(),
+                len: 1024
+            }
+        );
+    }
+}
@@ -0,0 +1,249 @@
+// Watchdog of realtime ticks.
+//
+// A realtime tick has to finish within its interval; an agent blocked inside a
+// handler (a network call without a timeout, a lock) stalls the whole run
+// without a trace. With a watchdog the kernel shares which tick it is on and
+// which agent is handling which message, and a thread polls that every tenth
+// of the tick interval. Once a tick has run `factor` times its interval the
+// thread reports it, naming the agent it is stuck in; with `abort` it ends the
+// process with those diagnostics. When an overrunning tick does finish, the
+// kernel emits a `TickOverrun` event with the handler that took longest in it.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::messages::{AgentId, MessageType};
+
+/// Exit code of a run aborted by the watchdog
+pub const ABORT_EXIT_CODE: i32 = 3;
+
+/// When a tick counts as stuck and what happens then.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchdogConfig {
+    /// A tick is stuck after this many tick intervals
+    #[serde(default = "default_factor")]
+    pub factor: u32,
+    /// End the run when a tick is stuck (otherwise only report it)
+    #[serde(default)]
+    pub abort: bool,
+}
+
+fn default_factor() -> u32 {
+    10
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            factor: default_factor(),
+            abort: false,
+        }
+    }
+}
+
+impl WatchdogConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.factor < 2 {
+            return Err("factor must be >= 2".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Handler of the current tick: (agent, message type, start).
+type Handler = (AgentId, MessageType, Instant);
+
+/// Tick that ran past the limit, reported when it finished.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overrun {
+    pub tick: u64,
+    pub elapsed: Duration,
+    /// Handler that took longest in the tick: (agent, message type, duration)
+    pub slowest: Option<(AgentId, MessageType, Duration)>,
+}
+
+#[derive(Default)]
+struct Progress {
+    tick: u64,
+    tick_start: Option<Instant>,
+    handler: Option<Handler>,
+    slowest: Option<(AgentId, MessageType, Duration)>,
+    /// The current tick was reported as stuck
+    reported: bool,
+    stopped: bool,
+    names: HashMap<AgentId, String>,
+}
+
+/// Progress of the kernel, shared with the watchdog thread.
+#[derive(Clone)]
+pub struct TickWatch {
+    progress: Arc<Mutex<Progress>>,
+    limit: Duration,
+    /// Polling interval of the thread (a tenth of the tick)
+    poll: Duration,
+}
+
+impl TickWatch {
+    pub fn new(tick_ms: u64, config: WatchdogConfig) -> Self {
+        Self {
+            progress: Arc::new(Mutex::new(Progress::default())),
+            limit: Duration::from_millis(tick_ms) * config.factor,
+            poll: Duration::from_millis(tick_ms / 10).max(Duration::from_millis(1)),
+        }
+    }
+
+    pub fn limit(&self) -> Duration {
+        self.limit
+    }
+
+    /// Name of an agent in the diagnostics.
+    pub fn register(&self, agent: AgentId, name: &str) {
+        if let Ok(mut progress) = self.progress.lock() {
+            progress.names.insert(agent, name.to_string());
+        }
+    }
+
+    pub fn begin_tick(&self, tick: u64, now: Instant) {
+        if let Ok(mut progress) = self.progress.lock() {
+            progress.tick = tick;
+            progress.tick_start = Some(now);
+            progress.slowest = None;
+            progress.reported = false;
+        }
+    }
+
+    pub fn enter(&self, agent: AgentId, msg_type: MessageType, now: Instant) {
+        if let Ok(mut progress) = self.progress.lock() {
+            progress.handler = Some((agent, msg_type, now));
+        }
+    }
+
+    pub fn leave(&self, now: Instant) {
+        let Ok(mut progress) = self.progress.lock() else {
+            return;
+        };
+        if let Some((agent, msg_type, started)) = progress.handler.take() {
+            let took = now.saturating_duration_since(started);
+            if progress.slowest.is_none_or(|(_, _, slowest)| took > slowest) {
+                progress.slowest = Some((agent, msg_type, took));
+            }
+        }
+    }
+
+    /// Close the tick; the overrun if it took longer than the limit.
+    pub fn end_tick(&self, now: Instant) -> Option<Overrun> {
+        let mut progress = self.progress.lock().ok()?;
+        let elapsed = now.saturating_duration_since(progress.tick_start.take()?);
+        (elapsed > self.limit).then(|| Overrun {
+            tick: progress.tick,
+            elapsed,
+            slowest: progress.slowest,
+        })
+    }
+
+    /// Description of the current tick if it has been running past the limit at `now`;
+    /// once per tick.
+    pub fn check(&self, now: Instant) -> Option<String> {
+        let mut progress = self.progress.lock().ok()?;
+        let elapsed = now.saturating_duration_since(progress.tick_start?);
+        if progress.reported || elapsed <= self.limit {
+            return None;
+        }
+        progress.reported = true;
+        let stuck_in = match progress.handler {
+            Some((agent, msg_type, started)) => format!(
+                "in {} (id={}) handling {:?} for {}ms",
+                progress.names.get(&agent).map_or("?", String::as_str),
+                agent,
+                msg_type,
+                now.saturating_duration_since(started).as_millis()
+            ),
+            None => "outside agent handlers".to_string(),
+        };
+        Some(format!(
+            "tick {} running for {}ms (limit {}ms), {}",
+            progress.tick,
+            elapsed.as_millis(),
+            self.limit.as_millis(),
+            stuck_in
+        ))
+    }
+
+    /// End the watchdog thread.
+    pub fn stop(&self) {
+        if let Ok(mut progress) = self.progress.lock() {
+            progress.stopped = true;
+        }
+    }
+
+    fn stopped(&self) -> bool {
+        self.progress.lock().map(|p| p.stopped).unwrap_or(true)
+    }
+}
+
+/// Poll `watch` until it is stopped; a stuck tick is reported on stderr, and ends the
+/// process with `ABORT_EXIT_CODE` when `abort` is set.
+pub fn spawn(watch: TickWatch, abort: bool) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        while !watch.stopped() {
+            std::thread::sleep(watch.poll);
+            let Some(report) = watch.check(Instant::now()) else {
+                continue;
+            };
+            eprintln!("[Watchdog] {}", report);
+            if abort {
+                eprintln!("[Watchdog] aborting the run");
+                std::process::exit(ABORT_EXIT_CODE);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stuck_tick_is_reported_once_with_its_agent() {
+        let config = WatchdogConfig {
+            factor: 4,
+            abort: false,
+        };
+        let watch = TickWatch::new(100, config);
+        assert_eq!(watch.limit(), Duration::from_millis(400));
+        watch.register(7, "Slow");
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        watch.begin_tick(1, start);
+        watch.enter(3, MessageType::OracleTick, at(0));
+        watch.leave(at(50));
+        watch.enter(7, MessageType::Wakeup, at(50));
+        assert_eq!(watch.check(at(400)), None);
+        assert_eq!(
+            watch.check(at(450)).as_deref(),
+            Some("tick 1 running for 450ms (limit 400ms), in Slow (id=7) handling Wakeup for 400ms")
+        );
+        assert_eq!(watch.check(at(500)), None);
+        watch.leave(at(600));
+
+        let overrun = watch.end_tick(at(610)).unwrap();
+        assert_eq!(overrun.tick, 1);
+        assert_eq!(overrun.elapsed, Duration::from_millis(610));
+        assert_eq!(
+            overrun.slowest,
+            Some((7, MessageType::Wakeup, Duration::from_millis(550)))
+        );
+
+        // A tick within the limit
+        watch.begin_tick(2, at(610));
+        assert_eq!(watch.end_tick(at(700)), None);
+        assert_eq!(watch.check(at(2_000)), None);
+        assert!(WatchdogConfig { factor: 1, abort: true }.validate().is_err());
+    }
+}
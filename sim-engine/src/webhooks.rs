@@ -0,0 +1,292 @@
+// Webhook sinks on top of EventBus.
+//
+// A webhook POSTs the events of the selected types (`events`, empty = all) to
+// `url`, so an unattended testnet run can alert a chat channel on
+// liquidations or circuit breaker trips. Events go to a background thread,
+// which sends them in batches of up to `batch_size`, or after `flush_ms` of
+// wall time once the first event of a batch is waiting; the rest is sent when
+// the run finishes. The body is `{"scenario", "events": [SimEvent...]}`, or
+// with `"format": "slack"` a `{"text"}` message with one line per event, which
+// Slack incoming webhooks (and most chat relays) accept as is. Failed POSTs
+// are logged and their batch is dropped; so are events beyond `max_queue`
+// while the endpoint is slow, so a dead endpoint never stalls the run.
+
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{RecvTimeoutError, Sender, TrySendError};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::events::{EventKind, EventListener, SimEvent};
+
+/// Body layout of the POSTs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// `{"scenario": ..., "events": [...]}`
+    #[default]
+    Json,
+    /// `{"text": ...}`, one line per event
+    Slack,
+}
+
+/// Where the events go, which ones and how they are batched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Event types to send, e.g. "PositionLiquidated" (empty = all)
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Largest number of events per POST
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Longest wait for a batch to fill (wall clock)
+    #[serde(default = "default_flush_ms")]
+    pub flush_ms: u64,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Events waiting to be sent; newer ones are dropped beyond it
+    #[serde(default = "default_max_queue")]
+    pub max_queue: usize,
+}
+
+fn default_batch_size() -> usize {
+    20
+}
+
+fn default_flush_ms() -> u64 {
+    5_000
+}
+
+fn default_timeout_ms() -> u64 {
+    5_000
+}
+
+fn default_max_queue() -> usize {
+    10_000
+}
+
+impl WebhookConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            return Err(format!("url: '{}' is not an http(s) URL", self.url));
+        }
+        if self.batch_size == 0 {
+            return Err("batch_size must be > 0".to_string());
+        }
+        if self.max_queue == 0 {
+            return Err("max_queue must be > 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Sends the body of a batch; the HTTP POST outside tests.
+type Transport = Box<dyn FnMut(&Value) -> Result<(), String> + Send>;
+
+/// Event listener feeding one webhook.
+pub struct WebhookSink {
+    url: String,
+    events: Vec<EventKind>,
+    tx: Option<Sender<SimEvent>>,
+    worker: Option<JoinHandle<(u64, u64)>>,
+    dropped: u64,
+}
+
+impl WebhookSink {
+    pub fn new(config: WebhookConfig, scenario: &str) -> Self {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .expect("Failed to create HTTP client");
+        let url = config.url.clone();
+        let transport: Transport = Box::new(move |body| {
+            let response = client.post(&url).json(body).send().map_err(|e| e.to_string())?;
+            if response.status().is_success() {
+                Ok(())
+            } else {
+                Err(format!("answered {}", response.status()))
+            }
+        });
+        Self::with_transport(config, scenario, transport)
+    }
+
+    fn with_transport(config: WebhookConfig, scenario: &str, mut transport: Transport) -> Self {
+        let (tx, rx) = crossbeam_channel::bounded::<SimEvent>(config.max_queue);
+        let scenario = scenario.to_string();
+        let (url, format, batch_size) = (config.url.clone(), config.format, config.batch_size);
+        let flush = Duration::from_millis(config.flush_ms);
+        let worker = std::thread::spawn(move || {
+            let (mut posts, mut failed) = (0, 0);
+            let mut batch = Vec::new();
+            let mut deadline = None;
+            loop {
+                let received = match deadline {
+                    Some(deadline) => rx.recv_deadline(deadline),
+                    None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                let done = match received {
+                    Ok(event) => {
+                        deadline.get_or_insert_with(|| Instant::now() + flush);
+                        batch.push(event);
+                        false
+                    }
+                    Err(RecvTimeoutError::Timeout) => false,
+                    Err(RecvTimeoutError::Disconnected) => true,
+                };
+                if !batch.is_empty()
+                    && (done || batch.len() >= batch_size || deadline.is_some_and(|d| Instant::now() >= d))
+                {
+                    posts += 1;
+                    if let Err(e) = transport(&body(format, &scenario, &batch)) {
+                        failed += 1;
+                        eprintln!("[Webhook] {}: {} events not delivered: {}", url, batch.len(), e);
+                    }
+                    batch.clear();
+                    deadline = None;
+                }
+                if done {
+                    return (posts, failed);
+                }
+            }
+        });
+        Self {
+            url: config.url,
+            events: config.events,
+            tx: Some(tx),
+            worker: Some(worker),
+            dropped: 0,
+        }
+    }
+}
+
+/// Body of a POST with `events`.
+fn body(format: WebhookFormat, scenario: &str, events: &[SimEvent]) -> Value {
+    match format {
+        WebhookFormat::Json => json!({ "scenario": scenario, "events": events }),
+        WebhookFormat::Slack => {
+            let lines: Vec<String> = events
+                .iter()
+                .map(|event| {
+                    let mut fields = serde_json::to_value(event).unwrap_or_default();
+                    let kind = fields
+                        .as_object_mut()
+                        .and_then(|f| f.remove("event_type"))
+                        .and_then(|k| k.as_str().map(str::to_string))
+                        .unwrap_or_default();
+                    format!("[{}] {} {}", scenario, kind, fields)
+                })
+                .collect();
+            json!({ "text": lines.join("\n") })
+        }
+    }
+}
+
+impl EventListener for WebhookSink {
+    fn on_event(&mut self, event: &SimEvent) {
+        if !self.events.is_empty() && !self.events.contains(&event.kind()) {
+            return;
+        }
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        if let Err(TrySendError::Full(_)) = tx.try_send(event.clone()) {
+            self.dropped += 1;
+        }
+    }
+
+    fn on_finish(&mut self, _ts: u64) {
+        // Closing the channel sends the last batch and ends the worker
+        self.tx = None;
+        let Some(Ok((posts, failed))) = self.worker.take().map(JoinHandle::join) else {
+            return;
+        };
+        println!(
+            "[Webhook] {}: {} posts ({} failed), {} events dropped on a full queue",
+            self.url, posts, failed, self.dropped
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::Side;
+    use std::sync::{Arc, Mutex};
+
+    fn liquidated(ts: u64) -> SimEvent {
+        SimEvent::PositionLiquidated {
+            ts,
+            account: 1,
+            symbol: "ETH-USD".to_string(),
+            side: Side::Buy,
+            size_usd: 10,
+            collateral_lost: 0,
+            pnl: 0,
+            liquidation_price: 3_000_000_000,
+        }
+    }
+
+    fn config(format: WebhookFormat) -> WebhookConfig {
+        serde_json::from_value(json!({
+            "url": "http://localhost:9/hook",
+            "events": ["PositionLiquidated"],
+            "format": format,
+            "batch_size": 2,
+            "flush_ms": 60_000,
+        }))
+        .unwrap()
+    }
+
+    fn capture(sink_config: WebhookConfig) -> (WebhookSink, Arc<Mutex<Vec<Value>>>) {
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+        let posted = bodies.clone();
+        let transport: Transport = Box::new(move |body| {
+            posted.lock().unwrap().push(body.clone());
+            Ok(())
+        });
+        (WebhookSink::with_transport(sink_config, "demo", transport), bodies)
+    }
+
+    #[test]
+    fn test_selected_events_are_posted_in_batches() {
+        let (mut sink, bodies) = capture(config(WebhookFormat::Json));
+        for ts in 1..=3 {
+            sink.on_event(&liquidated(ts));
+            sink.on_event(&SimEvent::InboxBacklog {
+                ts,
+                agent: 2,
+                backlog: 1,
+                delayed: 1,
+            });
+        }
+        sink.on_finish(3);
+
+        let bodies = bodies.lock().unwrap();
+        // A full batch, then the rest at the end of the run
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[0]["scenario"], "demo");
+        assert_eq!(bodies[0]["events"].as_array().unwrap().len(), 2);
+        assert_eq!(bodies[0]["events"][0]["event_type"], "PositionLiquidated");
+        assert_eq!(bodies[1]["events"][0]["ts"], 3);
+    }
+
+    #[test]
+    fn test_slack_format_has_one_line_per_event() {
+        let (mut sink, bodies) = capture(config(WebhookFormat::Slack));
+        sink.on_event(&liquidated(1));
+        sink.on_finish(1);
+
+        let text = bodies.lock().unwrap()[0]["text"].as_str().unwrap().to_string();
+        assert!(text.starts_with("[demo] PositionLiquidated {"));
+        assert!(text.contains("\"liquidation_price\":3000000000"));
+        assert!(serde_json::from_value::<WebhookConfig>(json!({ "url": "http://x", "events": ["Nope"] })).is_err());
+        let mut bad = config(WebhookFormat::Json);
+        bad.url = "localhost:9".to_string();
+        assert!(bad.validate().is_err());
+    }
+}
@@ -0,0 +1,18 @@
+//! Chain round trip against a deployed contract (deposit, open, query, close, withdraw).
+//!
+//! Ignored by default: it needs the contract of `vara_smoke.json` (or VARA_CONTRACT_ADDRESS),
+//! a reachable node and a funded key for agent 20.
+//!
+//!   cargo test --test vara_smoke -- --ignored --nocapture
+
+use sim_engine::scenarios::catalog::Catalog;
+use sim_engine::scenarios::vara_smoke::{self, SmokeOptions};
+
+#[test]
+#[ignore = "needs a deployed contract and funded testnet keys"]
+fn vara_smoke_round_trip() {
+    let catalog = Catalog::new(None);
+    let report = vara_smoke::run(&catalog, "vara_smoke", &[], &SmokeOptions::default()).expect("smoke run");
+    assert!(report.passed, "{:?}", report.failures());
+    assert_eq!(report.steps.len(), 6);
+}